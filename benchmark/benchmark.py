@@ -31,6 +31,21 @@ def benchmark_multiply():
     return y
 
 
+def benchmark_add():
+    y = 0
+    for _x in range(REPEAT_100M):
+        y = y + 1
+    return y
+
+
+def benchmark_add_big():
+    # Past the 32-bit range, so every addition is on big integers.
+    y = 2147483648
+    for _x in range(REPEAT_100M):
+        y = y + 1
+    return y
+
+
 def benchmark_call_native_len():
     y = 0
     xs = []
@@ -91,4 +106,16 @@ def benchmark_call_def_8name():
     return y
 
 
-print(benchmark_call_def_1name())
+def benchmark_call_method():
+    y = 0
+    s = "abc"
+    xs = []
+    for x in range(REPEAT_100M):
+        if s.startswith("a"):
+            y = y + 1
+        xs.append(x)
+        xs.pop()
+    return y
+
+
+print(benchmark_call_method())
@@ -16,7 +16,6 @@
  */
 
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
@@ -37,7 +36,9 @@ use serde_json::Map;
 use serde_json::Value;
 use starlark::codemap::FileSpan;
 use starlark::codemap::FileSpanRef;
+use starlark::codemap::ResolvedSpan;
 use starlark::environment::Module;
+use starlark::eval::BreakpointFilter;
 use starlark::eval::Evaluator;
 use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
@@ -54,9 +55,12 @@ struct Backend {
 
     // These breakpoints must all match statements as per before_stmt.
     // Those values for which we abort the execution.
-    breakpoints: Arc<Mutex<HashMap<String, HashSet<FileSpan>>>>,
+    // Spans are resolved, because code maps of different parses of a file never compare equal.
+    breakpoints: Arc<Mutex<HashMap<String, HashMap<ResolvedSpan, BreakpointFilter>>>>,
     // Set while we are doing evaluate calls (>= 1 means disable)
     disable_breakpoints: Arc<AtomicUsize>,
+    // Set by the step requests, cleared when we stop again.
+    step: Arc<Mutex<Option<Step>>>,
 
     sender: Sender<Box<dyn Fn(FileSpanRef, &mut Evaluator) -> Next + Send>>,
     receiver: Arc<Mutex<Receiver<Box<dyn Fn(FileSpanRef, &mut Evaluator) -> Next + Send>>>>,
@@ -67,6 +71,35 @@ enum Next {
     RemainPaused,
 }
 
+#[derive(Debug, Clone, Copy, Dupe)]
+enum StepKind {
+    In,
+    Over,
+    Out,
+}
+
+/// A step in progress, started at call stack depth `depth`.
+#[derive(Debug, Clone, Copy, Dupe)]
+struct Step {
+    kind: StepKind,
+    depth: usize,
+}
+
+impl Step {
+    /// Should the step stop at a statement executed at call stack depth `depth`.
+    fn stop_at(self, depth: usize) -> bool {
+        match self.kind {
+            StepKind::In => true,
+            StepKind::Over => depth <= self.depth,
+            StepKind::Out => depth < self.depth,
+        }
+    }
+}
+
+fn call_stack_depth(eval: &Evaluator) -> usize {
+    eval.call_stack().into_frames().len()
+}
+
 impl Backend {
     fn inject<T: 'static + Send>(
         &self,
@@ -87,6 +120,15 @@ impl Backend {
         self.inject(Box::new(|_, _| (Next::Continue, ())))
     }
 
+    fn inject_step(&self, kind: StepKind) {
+        let step = self.step.dupe();
+        self.inject(Box::new(move |_, eval| {
+            let depth = call_stack_depth(eval);
+            *step.lock().unwrap() = Some(Step { kind, depth });
+            (Next::Continue, ())
+        }))
+    }
+
     fn with_ctx<T: 'static + Send>(
         &self,
         f: Box<dyn Fn(FileSpanRef, &mut Evaluator) -> T + Send>,
@@ -102,6 +144,7 @@ impl Backend {
         let path = PathBuf::from(path);
         let breakpoints = self.breakpoints.dupe();
         let disable_breakpoints = self.disable_breakpoints.dupe();
+        let step = self.step.dupe();
         let receiver = self.receiver.dupe();
 
         let go = move || -> anyhow::Result<String> {
@@ -112,17 +155,45 @@ impl Backend {
             let mut eval = Evaluator::new(&module);
             let fun = |span_loc: FileSpanRef, eval: &mut Evaluator| {
                 let stop = if disable_breakpoints.load(Ordering::SeqCst) > 0 {
-                    false
+                    None
                 } else {
-                    let breaks = breakpoints.lock().unwrap();
-                    breaks
-                        .get(span_loc.filename())
-                        .map(|set| set.contains(&span_loc.to_file_span()))
-                        .unwrap_or_default()
+                    let mut step = step.lock().unwrap();
+                    let mut breaks = breakpoints.lock().unwrap();
+                    if step.map_or(false, |s| s.stop_at(call_stack_depth(eval))) {
+                        *step = None;
+                        Some("step")
+                    } else if let Some(filter) = breaks
+                        .get_mut(span_loc.filename())
+                        .and_then(|x| x.get_mut(&span_loc.resolve_span()))
+                    {
+                        // Evaluating the condition runs statements, which must not stop.
+                        disable_breakpoints.fetch_add(1, Ordering::SeqCst);
+                        let stop = filter.should_stop(eval);
+                        disable_breakpoints.fetch_sub(1, Ordering::SeqCst);
+                        let stop = match stop {
+                            Ok(stop) => stop,
+                            Err(e) => {
+                                client.event_output(output_event(format!(
+                                    "Breakpoint condition failed: {:#}\n",
+                                    e
+                                )));
+                                true
+                            }
+                        };
+                        if stop {
+                            // A breakpoint ends any step in progress.
+                            *step = None;
+                            Some("breakpoint")
+                        } else {
+                            None
+                        }
+                    } else {
+                        None
+                    }
                 };
-                if stop {
+                if let Some(reason) = stop {
                     client.event_stopped(StoppedEventBody {
-                        reason: "breakpoint".to_owned(),
+                        reason: reason.to_owned(),
                         thread_id: Some(0),
                         description: Some("Hello".to_owned()),
                         all_threads_stopped: Some(true),
@@ -153,15 +224,7 @@ impl Backend {
                 Err(e) => format!("{:#}", e),
                 Ok(v) => v.to_owned(),
             };
-            client2.event_output(OutputEventBody {
-                output,
-                category: None,
-                column: None,
-                data: None,
-                line: None,
-                source: None,
-                variables_reference: None,
-            });
+            client2.event_output(output_event(output));
             client2.event_exited(ExitedEventBody {
                 exit_code: if res.is_ok() { 0 } else { 1 },
             });
@@ -170,14 +233,26 @@ impl Backend {
     }
 }
 
-fn breakpoint(verified: bool) -> Breakpoint {
+fn output_event(output: String) -> OutputEventBody {
+    OutputEventBody {
+        output,
+        category: None,
+        column: None,
+        data: None,
+        line: None,
+        source: None,
+        variables_reference: None,
+    }
+}
+
+fn breakpoint(verified: bool, message: Option<String>) -> Breakpoint {
     Breakpoint {
         column: None,
         end_column: None,
         end_line: None,
         id: None,
         line: None,
-        message: None,
+        message,
         source: None,
         verified,
     }
@@ -188,6 +263,8 @@ impl DebugServer for Backend {
         self.client.event_initialized(None);
         Ok(Some(Capabilities {
             supports_configuration_done_request: Some(true),
+            supports_conditional_breakpoints: Some(true),
+            supports_hit_conditional_breakpoints: Some(true),
             supports_evaluate_for_hovers: Some(true),
             supports_set_variable: Some(true),
             supports_step_in_targets_request: Some(true),
@@ -212,23 +289,35 @@ impl DebugServer for Backend {
                 Err(_) => {
                     self.breakpoints.lock().unwrap().remove(&source);
                     Ok(SetBreakpointsResponseBody {
-                        breakpoints: vec![breakpoint(false); breakpoints.len()],
+                        breakpoints: vec![breakpoint(false, None); breakpoints.len()],
                     })
                 }
                 Ok(ast) => {
-                    let poss: HashMap<usize, FileSpan> = ast
+                    let poss: HashMap<usize, ResolvedSpan> = ast
                         .stmt_locations()
                         .iter()
-                        .map(|span| (span.resolve_span().begin_line, span.dupe()))
+                        .map(|span| span.resolve_span())
+                        .map(|span| (span.begin_line, span))
                         .collect();
-                    let list = breakpoints.map(|x| poss.get(&(x.line as usize - 1)));
-                    self.breakpoints
-                        .lock()
-                        .unwrap()
-                        .insert(source, list.iter().filter_map(|x| x.duped()).collect());
-                    Ok(SetBreakpointsResponseBody {
-                        breakpoints: list.map(|x| breakpoint(x.is_some())),
-                    })
+                    let list = breakpoints.into_map(|x| {
+                        let span = poss.get(&(x.line as usize - 1)).duped();
+                        let filter = BreakpointFilter::new(x.condition, x.hit_condition.as_deref());
+                        (span, filter)
+                    });
+                    let mut set = HashMap::new();
+                    let mut res = Vec::with_capacity(list.len());
+                    for (span, filter) in list {
+                        res.push(match (span, filter) {
+                            (None, _) => breakpoint(false, None),
+                            (Some(_), Err(e)) => breakpoint(false, Some(format!("{:#}", e))),
+                            (Some(span), Ok(filter)) => {
+                                set.insert(span, filter);
+                                breakpoint(true, None)
+                            }
+                        });
+                    }
+                    self.breakpoints.lock().unwrap().insert(source, set);
+                    Ok(SetBreakpointsResponseBody { breakpoints: res })
                 }
             }
         }
@@ -356,19 +445,59 @@ impl DebugServer for Backend {
         }))
     }
 
+    fn set_variable(&self, x: SetVariableArguments) -> anyhow::Result<SetVariableResponseBody> {
+        let disable_breakpoints = self.disable_breakpoints.dupe();
+        self.with_ctx(Box::new(move |_, eval| {
+            disable_breakpoints.fetch_add(1, Ordering::SeqCst);
+            let res = eval
+                .eval_watch(&x.value)
+                .and_then(|v| eval.set_local_variable(&x.name, v).map(|_| v));
+            disable_breakpoints.fetch_sub(1, Ordering::SeqCst);
+            let value = res?;
+            Ok(SetVariableResponseBody {
+                value: value.to_string(),
+                type_: Some(value.get_type().to_owned()),
+                indexed_variables: None,
+                named_variables: None,
+                variables_reference: None,
+            })
+        }))
+    }
+
     fn continue_(&self, _: ContinueArguments) -> anyhow::Result<ContinueResponseBody> {
         self.inject_continue();
         Ok(ContinueResponseBody::default())
     }
 
+    fn next(&self, _: NextArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::Over);
+        Ok(())
+    }
+
+    fn step_in(&self, _: StepInArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::In);
+        Ok(())
+    }
+
+    fn step_out(&self, _: StepOutArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::Out);
+        Ok(())
+    }
+
     fn evaluate(&self, x: EvaluateArguments) -> anyhow::Result<EvaluateResponseBody> {
         let disable_breakpoints = self.disable_breakpoints.dupe();
         self.with_ctx(Box::new(move |_, eval| {
             // We don't want to trigger breakpoints during an evaluate,
             // not least because we currently don't allow reenterant evaluate
             disable_breakpoints.fetch_add(1, Ordering::SeqCst);
-            let ast = AstModule::parse("interactive", x.expression.clone(), &Dialect::Extended);
-            let s = match ast.and_then(|ast| eval.eval_statements(ast)) {
+            // Watches are evaluated each time we stop, so must not assign variables.
+            let res = if x.context.as_deref() == Some("watch") {
+                eval.eval_watch(&x.expression)
+            } else {
+                AstModule::parse("interactive", x.expression.clone(), &Dialect::Extended)
+                    .and_then(|ast| eval.eval_statements(ast))
+            };
+            let s = match res {
                 Err(e) => format!("{:#}", e),
                 Ok(v) => v.to_string(),
             };
@@ -391,6 +520,7 @@ pub(crate) fn server() {
         client,
         breakpoints: Default::default(),
         disable_breakpoints: Default::default(),
+        step: Default::default(),
         file: Default::default(),
         sender,
         receiver: Arc::new(Mutex::new(receiver)),
@@ -15,37 +15,61 @@
  * limitations under the License.
  */
 
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::env;
 use std::fs;
 use std::io;
-use std::iter;
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
 
+use dupe::Dupe;
 use gazebo::prelude::*;
-use itertools::Either;
 use lsp_types::Diagnostic;
+use lsp_types::Range;
 use lsp_types::Url;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
 use starlark::docs::get_registered_starlark_docs;
 use starlark::docs::render_docs_as_code;
 use starlark::docs::Doc;
 use starlark::docs::DocItem;
 use starlark::environment::FrozenModule;
 use starlark::environment::Globals;
+use starlark::environment::LibraryExtension;
 use starlark::environment::Module;
 use starlark::errors::EvalMessage;
 use starlark::eval::Evaluator;
+use starlark::lsp::build_system::BuildSystem;
+use starlark::lsp::build_system::BuildSystemRegistry;
+use starlark::lsp::build_system::Label;
+use starlark::lsp::build_system::TargetQueryCache;
+use starlark::lsp::build_system::TargetQuerySnapshot;
 use starlark::lsp::server::LspContext;
 use starlark::lsp::server::LspEvalResult;
 use starlark::lsp::server::LspUrl;
 use starlark::lsp::server::StringLiteralResult;
 use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
+use starlark::typing::Approximation;
+use starlark::typing::Interface;
+use starlark::typing::InterfaceCache;
+use starlark::typing::OracleStandard;
+use starlark::typing::TypeMap;
+use starlark::typing::TypecheckProfile;
+use starlark::typing::TypingOracle;
+use starlark::PrintHandler;
 
 #[derive(Debug)]
 pub(crate) enum ContextMode {
     Check,
+    Typecheck,
     Run,
 }
 
@@ -67,15 +91,32 @@ pub(crate) struct Context {
     pub(crate) module: Option<Module>,
     pub(crate) builtin_docs: HashMap<LspUrl, String>,
     pub(crate) builtin_symbols: HashMap<String, LspUrl>,
+    pub(crate) build_system: Option<Arc<dyn BuildSystem>>,
+    /// The targets of the packages of `build_system`.
+    pub(crate) target_cache: Option<TargetQueryCache>,
+    /// When set, how long typechecking each file took, including the loaded ones.
+    pub(crate) typecheck_profile: Option<Mutex<Vec<(String, TypecheckProfile)>>>,
+    /// The interfaces of loaded modules, keyed by the digest of their content and loads,
+    /// so unchanged modules aren't typechecked again by later checks.
+    interface_cache: Mutex<InterfaceCache>,
+    /// Where the language server keeps its [`LspState`] between runs.
+    pub(crate) lsp_state_file: Option<PathBuf>,
 }
 
-/// The outcome of evaluating (checking, parsing or running) given starlark code.
-pub(crate) struct EvalResult<T: Iterator<Item = EvalMessage>> {
-    /// The diagnostic and error messages from evaluating a given piece of starlark code.
-    pub messages: T,
-    /// If the code is only parsed, not run, and there were no errors, this will contain
-    /// the parsed module. Otherwise, it will be `None`
-    pub ast: Option<AstModule>,
+/// The modules loaded while typechecking a file.
+#[derive(Default)]
+struct LoadState {
+    /// The modules being loaded, innermost last, to detect cycles.
+    loading: Vec<PathBuf>,
+    /// The interface of every module loaded so far, so that a module loaded through
+    /// several paths of the load graph is only typechecked once.
+    interfaces: HashMap<PathBuf, Option<Interface>>,
+}
+
+/// The caches of a [`Context`] worth keeping between runs of the language server.
+#[derive(Default, Serialize, Deserialize)]
+struct LspState {
+    targets: Option<TargetQuerySnapshot>,
 }
 
 /// Errors when [`LspContext::resolve_load()`] cannot resolve a given path.
@@ -88,6 +129,18 @@ enum ResolveLoadError {
     /// The scheme provided was not correct or supported.
     #[error("Url `{}` was expected to be of type `{}`", .1, .0)]
     WrongScheme(String, LspUrl),
+    /// A label was used, but the workspace does not belong to a known build system.
+    #[error("Label `{}` provided, but no build system was found for the workspace", .0)]
+    NoBuildSystem(String),
+    /// The repository in a label is not known to the build system.
+    #[error("Unknown repository `{}` in label `{}`", .1, .0)]
+    UnknownRepository(String, String),
+    /// The package of a label is not a directory with a build file.
+    #[error("No such package `{}` in label `{}`, `{}` has no build file", .1, .0, .2.display())]
+    NoSuchPackage(String, String, PathBuf),
+    /// The target of a label is in a subpackage of the package the label names.
+    #[error("Label `{}` crosses into the package at `{}`", .0, .1.display())]
+    CrossesPackageBoundary(String, PathBuf),
 }
 
 impl Context {
@@ -98,14 +151,7 @@ impl Context {
         module: bool,
     ) -> anyhow::Result<Self> {
         let globals = globals();
-        let prelude = prelude.try_map(|x| {
-            let env = Module::new();
-
-            let mut eval = Evaluator::new(&env);
-            let module = AstModule::parse_file(x, &dialect())?;
-            eval.eval_module(module, &globals)?;
-            env.freeze()
-        })?;
+        let prelude = prelude.try_map(|x| Self::prelude_module(x, &globals))?;
 
         let module = if module {
             Some(Self::new_module(&prelude))
@@ -123,7 +169,6 @@ impl Context {
             .into_iter()
             .map(|(u, ds)| (u, render_docs_as_code(&ds)))
             .collect();
-
         Ok(Self {
             mode,
             print_non_none,
@@ -131,9 +176,89 @@ impl Context {
             module,
             builtin_docs,
             builtin_symbols,
+            build_system: None,
+            target_cache: None,
+            typecheck_profile: None,
+            interface_cache: Mutex::new(InterfaceCache::new()),
+            lsp_state_file: None,
         })
     }
 
+    /// Find the build system of the workspace in the current directory, so labels can be
+    /// resolved. Only the language server needs this, and finding it may be slow.
+    pub(crate) fn resolve_build_system(
+        &mut self,
+        build_systems: &BuildSystemRegistry,
+    ) -> anyhow::Result<()> {
+        let build_system: Option<Arc<dyn BuildSystem>> =
+            build_systems.resolve(&env::current_dir()?).map(Arc::from);
+        self.target_cache = build_system
+            .as_ref()
+            .map(|x| TargetQueryCache::new(x.dupe()));
+        self.build_system = build_system;
+        Ok(())
+    }
+
+    /// Evaluate a prelude file, or reuse its serialized module cached from a previous run
+    /// with the same contents, globals and dialect. Only preludes exporting data can be
+    /// serialized, so those exporting anything else, such as functions (as most `.bzl`
+    /// files do), are evaluated every time. So are preludes which print, as a cached
+    /// prelude is not run again.
+    fn prelude_module(path: &Path, globals: &Globals) -> anyhow::Result<FrozenModule> {
+        let source = fs::read_to_string(path)?;
+        let cache = Self::prelude_cache_file(path, &source, globals);
+        if let Some(module) = cache
+            .as_ref()
+            .and_then(|cache| fs::read(cache).ok())
+            .and_then(|data| FrozenModule::deserialize(&data).ok())
+        {
+            return Ok(module);
+        }
+
+        let env = Module::new();
+        let mut eval = Evaluator::new(&env);
+        let print_handler = PreludePrintHandler::default();
+        eval.set_print_handler(&print_handler);
+        let module = AstModule::parse(&path.to_string_lossy(), source, &dialect())?;
+        eval.eval_module(module, globals)?;
+        drop(eval);
+        let module = env.freeze()?;
+        let cache = cache.filter(|_| !print_handler.printed.get());
+        if let (Some(cache), Ok(data)) = (cache, module.serialize()) {
+            // The cache is only an optimisation, so failing to write it is fine.
+            let _ = write_atomically(&cache, &data);
+        }
+        Ok(module)
+    }
+
+    /// Where the serialized prelude is cached, in the cache directory of the current user.
+    /// The name is a hash of everything that affects the result of evaluating it: the
+    /// prelude, the dialect, the globals it can see, and the binary implementing them.
+    fn prelude_cache_file(path: &Path, source: &str, globals: &Globals) -> Option<PathBuf> {
+        let mut hasher = Sha256::new();
+        let mut add = |x: &[u8]| {
+            hasher.update((x.len() as u64).to_le_bytes());
+            hasher.update(x);
+        };
+        add(env!("CARGO_PKG_VERSION").as_bytes());
+        let exe = env::current_exe().ok()?;
+        let modified = fs::metadata(&exe).ok()?.modified().ok()?;
+        add(exe.to_string_lossy().as_bytes());
+        add(format!("{:?}", modified).as_bytes());
+        add(path.to_string_lossy().as_bytes());
+        add(source.as_bytes());
+        add(format!("{:?}", dialect()).as_bytes());
+        add(globals.describe().as_bytes());
+        let hash = hasher.finalize();
+        let name: String = hash.iter().map(|x| format!("{:02x}", x)).collect();
+        Some(
+            dirs::cache_dir()?
+                .join("starlark")
+                .join("prelude")
+                .join(name),
+        )
+    }
+
     fn url_for_doc(doc: &Doc) -> LspUrl {
         let url = match &doc.item {
             DocItem::Module(_) => Url::parse("starlark:/native/builtins.bzl").unwrap(),
@@ -153,75 +278,88 @@ impl Context {
         module
     }
 
-    fn go(&self, file: &str, ast: AstModule) -> EvalResult<impl Iterator<Item = EvalMessage>> {
-        let mut warnings = Either::Left(iter::empty());
-        let mut errors = Either::Left(iter::empty());
-        let final_ast = match self.mode {
+    /// Evaluate (check, typecheck or run) `ast`, passing each diagnostic and error message
+    /// to `on_message` as soon as it is known, so long runs give feedback as they go.
+    ///
+    /// If the code is only parsed, not run, this returns the parsed module.
+    fn go(
+        &self,
+        file: &str,
+        ast: AstModule,
+        on_message: &mut dyn FnMut(EvalMessage),
+    ) -> Option<AstModule> {
+        match self.mode {
             ContextMode::Check => {
-                warnings = Either::Right(self.check(&ast));
+                self.check(&ast, on_message);
                 Some(ast)
             }
+            ContextMode::Typecheck => {
+                self.typecheck(file, ast, on_message);
+                None
+            }
             ContextMode::Run => {
-                errors = Either::Right(self.run(file, ast).messages);
+                self.run(file, ast, on_message);
                 None
             }
-        };
-        EvalResult {
-            messages: warnings.chain(errors),
-            ast: final_ast,
         }
     }
 
-    // Convert an anyhow over iterator of EvalMessage, into an iterator of EvalMessage
-    fn err(
+    /// Like [`go`](Context::go), but for code which may not parse.
+    fn parse_and_go(
+        &self,
         file: &str,
-        result: anyhow::Result<EvalResult<impl Iterator<Item = EvalMessage>>>,
-    ) -> EvalResult<impl Iterator<Item = EvalMessage>> {
-        match result {
-            Err(e) => EvalResult {
-                messages: Either::Left(iter::once(EvalMessage::from_anyhow(Path::new(file), &e))),
-                ast: None,
-            },
-            Ok(res) => EvalResult {
-                messages: Either::Right(res.messages),
-                ast: res.ast,
-            },
+        content: String,
+        on_message: &mut dyn FnMut(EvalMessage),
+    ) -> Option<AstModule> {
+        match AstModule::parse(file, content, &dialect()) {
+            Ok(ast) => self.go(file, ast, on_message),
+            Err(e) => {
+                on_message(EvalMessage::from_anyhow(Path::new(file), &e));
+                None
+            }
         }
     }
 
     pub(crate) fn expression(
         &self,
         content: String,
-    ) -> EvalResult<impl Iterator<Item = EvalMessage>> {
-        let file = "expression";
-        Self::err(
-            file,
-            AstModule::parse(file, content, &dialect()).map(|module| self.go(file, module)),
-        )
+        on_message: &mut dyn FnMut(EvalMessage),
+    ) -> Option<AstModule> {
+        self.parse_and_go("expression", content, on_message)
     }
 
-    pub(crate) fn file(&self, file: &Path) -> EvalResult<impl Iterator<Item = EvalMessage>> {
-        let filename = &file.to_string_lossy();
-        Self::err(
-            filename,
-            fs::read_to_string(file)
-                .map(|content| self.file_with_contents(filename, content))
-                .map_err(|e| e.into()),
-        )
+    /// Evaluate `file`, or the program on stdin if `file` is `-`.
+    pub(crate) fn file(
+        &self,
+        file: &Path,
+        on_message: &mut dyn FnMut(EvalMessage),
+    ) -> Option<AstModule> {
+        let (filename, content) = if file == Path::new("-") {
+            let mut content = String::new();
+            let res = io::stdin().read_to_string(&mut content).map(|_| content);
+            ("<stdin>".into(), res)
+        } else {
+            (file.to_string_lossy(), fs::read_to_string(file))
+        };
+        match content {
+            Ok(content) => self.file_with_contents(&filename, content, on_message),
+            Err(e) => {
+                on_message(EvalMessage::from_anyhow(Path::new(&*filename), &e.into()));
+                None
+            }
+        }
     }
 
     pub(crate) fn file_with_contents(
         &self,
         filename: &str,
         content: String,
-    ) -> EvalResult<impl Iterator<Item = EvalMessage>> {
-        Self::err(
-            filename,
-            AstModule::parse(filename, content, &dialect()).map(|module| self.go(filename, module)),
-        )
+        on_message: &mut dyn FnMut(EvalMessage),
+    ) -> Option<AstModule> {
+        self.parse_and_go(filename, content, on_message)
     }
 
-    fn run(&self, file: &str, ast: AstModule) -> EvalResult<impl Iterator<Item = EvalMessage>> {
+    fn run(&self, file: &str, ast: AstModule, on_message: &mut dyn FnMut(EvalMessage)) {
         let new_module;
         let module = match self.module.as_ref() {
             Some(module) => module,
@@ -233,21 +371,212 @@ impl Context {
         let mut eval = Evaluator::new(module);
         eval.enable_terminal_breakpoint_console();
         let globals = globals();
-        Self::err(
-            file,
-            eval.eval_module(ast, &globals).map(|v| {
+        match eval.eval_module(ast, &globals) {
+            Ok(v) => {
                 if self.print_non_none && !v.is_none() {
                     println!("{}", v);
                 }
-                EvalResult {
-                    messages: iter::empty(),
-                    ast: None,
-                }
-            }),
-        )
+            }
+            Err(e) => on_message(EvalMessage::from_anyhow(Path::new(file), &e)),
+        }
+    }
+
+    /// Resolve a label like `@repo//pkg/path:file.bzl` to a file path.
+    fn resolve_label(&self, label: &str) -> anyhow::Result<PathBuf> {
+        let build_system = self
+            .build_system
+            .as_ref()
+            .ok_or_else(|| ResolveLoadError::NoBuildSystem(label.to_owned()))?;
+        let parsed = Label::parse(label)?;
+        let repository = parsed.repository.as_deref().unwrap_or("");
+        let root = build_system.repository_path(repository).ok_or_else(|| {
+            ResolveLoadError::UnknownRepository(label.to_owned(), repository.to_owned())
+        })?;
+        let package = parsed.package.unwrap_or_default();
+        let package_dir = root.join(&package);
+        // Only a directory with a build file is a package.
+        if build_system.package_directory(&package_dir).as_ref() != Some(&package_dir) {
+            return Err(
+                ResolveLoadError::NoSuchPackage(label.to_owned(), package, package_dir).into(),
+            );
+        }
+        Self::resolve_in_package(&**build_system, label, &package_dir, &parsed.target)
     }
 
-    fn check(&self, module: &AstModule) -> impl Iterator<Item = EvalMessage> {
+    /// Resolve the `target` of `label` to a file in the package at `package_dir`,
+    /// which must not belong to one of its subpackages.
+    fn resolve_in_package(
+        build_system: &dyn BuildSystem,
+        label: &str,
+        package_dir: &Path,
+        target: &str,
+    ) -> anyhow::Result<PathBuf> {
+        let path = package_dir.join(target);
+        match build_system.package_directory(&path) {
+            Some(dir) if dir != package_dir => {
+                Err(ResolveLoadError::CrossesPackageBoundary(label.to_owned(), dir).into())
+            }
+            _ => Ok(path),
+        }
+    }
+
+    /// If `path`, as resolved from a label, names a target in the main repository rather
+    /// than a file, find where the target is defined in the package's build file.
+    ///
+    /// Only consults the targets already known, so the LSP never waits for the build system.
+    fn resolve_target(&self, path: &Path) -> Option<StringLiteralResult> {
+        let cache = self.target_cache.as_ref()?;
+        let build_system = cache.build_system();
+        let package = build_system.package_for_path(path)?;
+        let target = path
+            .strip_prefix(&package.directory)
+            .ok()?
+            .to_str()?
+            .to_owned();
+        let targets = cache.get_nonblocking("", &package.name)?;
+        if !targets.contains(&target) {
+            return None;
+        }
+        let build_file = build_system
+            .build_file_names()
+            .iter()
+            .map(|name| package.directory.join(name))
+            .find(|x| x.is_file())?;
+        Some(StringLiteralResult {
+            url: Url::from_file_path(build_file).ok()?.try_into().ok()?,
+            location_finder: Some(Box::new(move |ast, _url| {
+                Ok(ast.find_function_call_with_name(&target).map(Range::from))
+            })),
+        })
+    }
+
+    /// Typecheck `ast` against the builtins, using the types of the modules it loads.
+    fn typecheck(&self, file: &str, ast: AstModule, on_message: &mut dyn FnMut(EvalMessage)) {
+        // Symbols from the prelude are in scope without being loaded, and shadow the builtins.
+        let mut oracle: Vec<Box<dyn TypingOracle>> = self
+            .prelude
+            .iter()
+            .map(|x| Box::new(Interface::from_module(x)) as Box<dyn TypingOracle>)
+            .collect();
+        oracle.push(Box::new(OracleStandard::new(LibraryExtension::all())));
+        // Loads are resolved relative to the file, which must be absolute to become a URL.
+        let path = fs::canonicalize(file)
+            .or_else(|_| env::current_dir().map(|dir| dir.join(file)))
+            .unwrap_or_else(|_| PathBuf::from(file));
+        let mut state = LoadState {
+            loading: vec![path.clone()],
+            ..LoadState::default()
+        };
+        let loads = self.typecheck_loads(&oracle, &ast, &path, &mut state);
+        let (errors, typemap, ..) = self.typecheck_module(&oracle, file, ast, &loads);
+        for e in errors {
+            on_message(EvalMessage::from_anyhow(Path::new(file), &e));
+        }
+        for lint in typemap
+            .unreachable_branches()
+            .iter()
+            .chain(typemap.unreachable_code())
+            .chain(typemap.non_exhaustive())
+        {
+            on_message(EvalMessage::from(lint.clone()));
+        }
+    }
+
+    /// The interfaces of the modules loaded by `ast`, typechecking them in turn.
+    /// Modules that can't be resolved, read or parsed, or that are already being
+    /// loaded by a cycle, are left out, so their symbols are untyped.
+    fn typecheck_loads(
+        &self,
+        oracle: &dyn TypingOracle,
+        ast: &AstModule,
+        file: &Path,
+        state: &mut LoadState,
+    ) -> HashMap<String, Interface> {
+        let mut res = HashMap::new();
+        for load in ast.loads() {
+            if let Some(interface) = self.load_interface(oracle, load.module_id, file, state) {
+                res.insert(load.module_id.to_owned(), interface);
+            }
+        }
+        res
+    }
+
+    fn load_interface(
+        &self,
+        oracle: &dyn TypingOracle,
+        module_id: &str,
+        current_file: &Path,
+        state: &mut LoadState,
+    ) -> Option<Interface> {
+        let path = match self
+            .resolve_load(module_id, &LspUrl::File(current_file.to_owned()))
+            .ok()?
+        {
+            LspUrl::File(path) => fs::canonicalize(&path).unwrap_or(path),
+            _ => return None,
+        };
+        if let Some(interface) = state.interfaces.get(&path) {
+            return interface.dupe();
+        }
+        if state.loading.contains(&path) {
+            return None;
+        }
+        state.loading.push(path.clone());
+        let interface = self.typecheck_interface(oracle, &path, state);
+        state.loading.pop();
+        state.interfaces.insert(path, interface.dupe());
+        interface
+    }
+
+    /// The interface of the module at `path`, typechecking it unless the interface
+    /// cache has it for the same content and loads.
+    fn typecheck_interface(
+        &self,
+        oracle: &dyn TypingOracle,
+        path: &Path,
+        state: &mut LoadState,
+    ) -> Option<Interface> {
+        // An interface file next to the module describes it instead, for modules that
+        // can't be typechecked themselves.
+        let stub = path.with_extension("star-interface");
+        if let Ok(content) = fs::read_to_string(&stub) {
+            return Interface::parse_stub(&stub.to_string_lossy(), content).ok();
+        }
+        let filename = path.to_string_lossy();
+        let content = fs::read_to_string(path).ok()?;
+        let ast = AstModule::parse(&filename, content.clone(), &dialect()).ok()?;
+        let loads = self.typecheck_loads(oracle, &ast, path, state);
+        let digest = InterfaceCache::digest(&content, &loads);
+        if let Some(interface) = self.interface_cache.lock().unwrap().get(&filename, digest) {
+            return Some(interface.dupe());
+        }
+        let (_, _, interface, _) = self.typecheck_module(oracle, &filename, ast, &loads);
+        self.interface_cache
+            .lock()
+            .unwrap()
+            .insert(&filename, digest, interface.dupe());
+        Some(interface)
+    }
+
+    /// Typecheck `ast`, recording how long it took if profiling is on.
+    fn typecheck_module(
+        &self,
+        oracle: &dyn TypingOracle,
+        file: &str,
+        ast: AstModule,
+        loads: &HashMap<String, Interface>,
+    ) -> (Vec<anyhow::Error>, TypeMap, Interface, Vec<Approximation>) {
+        match &self.typecheck_profile {
+            None => ast.typecheck(oracle, loads),
+            Some(profiles) => {
+                let (res, profile) = ast.typecheck_profile(oracle, loads);
+                profiles.lock().unwrap().push((file.to_owned(), profile));
+                res
+            }
+        }
+    }
+
+    fn check(&self, module: &AstModule, on_message: &mut dyn FnMut(EvalMessage)) {
         let globals = if self.prelude.is_empty() {
             None
         } else {
@@ -265,44 +594,81 @@ impl Context {
             Some(globals)
         };
 
-        module
-            .lint(globals.as_ref())
-            .into_iter()
-            .map(EvalMessage::from)
+        for lint in module.lint(globals.as_ref()) {
+            on_message(EvalMessage::from(lint));
+        }
     }
 }
 
 impl LspContext for Context {
     fn parse_file_with_contents(&self, uri: &LspUrl, content: String) -> LspEvalResult {
+        let mut diagnostics = Vec::new();
+        let ast =
+            self.parse_file_with_contents_streaming(uri, content, &mut |x| diagnostics.push(x));
+        LspEvalResult { diagnostics, ast }
+    }
+
+    fn parse_file_with_contents_streaming(
+        &self,
+        uri: &LspUrl,
+        content: String,
+        on_diagnostic: &mut dyn FnMut(Diagnostic),
+    ) -> Option<AstModule> {
         match uri {
             LspUrl::File(uri) => {
-                let EvalResult { messages, ast } =
-                    self.file_with_contents(&uri.to_string_lossy(), content);
-                LspEvalResult {
-                    diagnostics: messages.map(Diagnostic::from).collect(),
-                    ast,
-                }
+                self.file_with_contents(&uri.to_string_lossy(), content, &mut |x| {
+                    on_diagnostic(Diagnostic::from(x))
+                })
             }
-            _ => LspEvalResult::default(),
+            _ => None,
         }
     }
 
     fn resolve_load(&self, path: &str, current_file: &LspUrl) -> anyhow::Result<LspUrl> {
-        let path = PathBuf::from(path);
-        match current_file {
-            LspUrl::File(current_file_path) => {
-                let current_file_dir = current_file_path.parent();
-                let absolute_path = match (current_file_dir, path.is_absolute()) {
-                    (_, true) => Ok(path),
-                    (Some(current_file_dir), false) => Ok(current_file_dir.join(&path)),
-                    (None, false) => Err(ResolveLoadError::MissingCurrentFilePath(path)),
-                }?;
-                Ok(Url::from_file_path(absolute_path).unwrap().try_into()?)
-            }
-            _ => Err(
-                ResolveLoadError::WrongScheme("file://".to_owned(), current_file.clone()).into(),
-            ),
+        if path.starts_with('@') || path.starts_with("//") {
+            let path = self.resolve_label(path)?;
+            return Ok(Url::from_file_path(path).unwrap().try_into()?);
         }
+        let current_file_path = match current_file {
+            LspUrl::File(path) => path,
+            _ => {
+                return Err(ResolveLoadError::WrongScheme(
+                    "file://".to_owned(),
+                    current_file.clone(),
+                )
+                .into());
+            }
+        };
+        let path = if path.starts_with(':') {
+            // A file in the same package as the current file, which may start above
+            // the directory of the current file.
+            let label = Label::parse(path)?;
+            let package = self
+                .build_system
+                .as_ref()
+                .and_then(|x| Some((x, x.package_for_path(current_file_path)?)));
+            match package {
+                Some((build_system, package)) => {
+                    let path = Self::resolve_in_package(
+                        &**build_system,
+                        path,
+                        &package.directory,
+                        &label.target,
+                    )?;
+                    return Ok(Url::from_file_path(path).unwrap().try_into()?);
+                }
+                // Without a build system, the package is the directory of the current file.
+                None => PathBuf::from(label.target),
+            }
+        } else {
+            PathBuf::from(path)
+        };
+        let absolute_path = match (current_file_path.parent(), path.is_absolute()) {
+            (_, true) => Ok(path),
+            (Some(current_file_dir), false) => Ok(current_file_dir.join(&path)),
+            (None, false) => Err(ResolveLoadError::MissingCurrentFilePath(path)),
+        }?;
+        Ok(Url::from_file_path(absolute_path).unwrap().try_into()?)
     }
 
     fn resolve_string_literal(
@@ -310,12 +676,18 @@ impl LspContext for Context {
         literal: &str,
         current_file: &LspUrl,
     ) -> anyhow::Result<Option<StringLiteralResult>> {
-        self.resolve_load(literal, current_file).map(|url| {
-            Some(StringLiteralResult {
-                url,
-                location_finder: None,
-            })
-        })
+        let url = self.resolve_load(literal, current_file)?;
+        if let LspUrl::File(path) = &url {
+            if !path.exists() {
+                if let Some(result) = self.resolve_target(path) {
+                    return Ok(Some(result));
+                }
+            }
+        }
+        Ok(Some(StringLiteralResult {
+            url,
+            location_finder: None,
+        }))
     }
 
     fn get_load_contents(&self, uri: &LspUrl) -> anyhow::Result<Option<String>> {
@@ -340,6 +712,57 @@ impl LspContext for Context {
     ) -> anyhow::Result<Option<LspUrl>> {
         Ok(self.builtin_symbols.get(symbol).cloned())
     }
+
+    fn state_file(&self) -> Option<PathBuf> {
+        self.lsp_state_file.clone()
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        let state = LspState {
+            targets: self.target_cache.as_ref().map(|x| x.snapshot()),
+        };
+        serde_json::to_value(state).ok()
+    }
+
+    fn restore_state(&self, state: serde_json::Value) -> anyhow::Result<()> {
+        let state: LspState = serde_json::from_value(state)?;
+        if let (Some(cache), Some(targets)) = (&self.target_cache, state.targets) {
+            cache.restore(targets);
+        }
+        Ok(())
+    }
+}
+
+/// Prints to stderr like the default handler, remembering whether anything was printed,
+/// as the output of a cached prelude would be lost.
+#[derive(Default)]
+struct PreludePrintHandler {
+    printed: Cell<bool>,
+}
+
+impl PrintHandler for PreludePrintHandler {
+    fn println(&self, text: &str) -> anyhow::Result<()> {
+        self.printed.set(true);
+        eprintln!("{}", text);
+        Ok(())
+    }
+}
+
+/// Write `data` to `path` through a temporary file, so concurrent readers never see
+/// a partially written file.
+fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap();
+    fs::create_dir_all(dir)?;
+    let temp = dir.join(format!(
+        ".{}.{}.tmp",
+        path.file_name().unwrap().to_string_lossy(),
+        std::process::id()
+    ));
+    fs::write(&temp, data)?;
+    fs::rename(&temp, path).map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        e
+    })
 }
 
 pub(crate) fn globals() -> Globals {
@@ -349,3 +772,169 @@ pub(crate) fn globals() -> Globals {
 pub(crate) fn dialect() -> Dialect {
     Dialect::Extended
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use starlark::lsp::build_system::BuildSystem;
+    use starlark::lsp::server::LspContext;
+    use starlark::lsp::server::LspUrl;
+    use starlark::syntax::AstModule;
+
+    use crate::eval::dialect;
+    use crate::eval::Context;
+    use crate::eval::ContextMode;
+
+    #[derive(Debug)]
+    struct TestBuildSystem(PathBuf);
+
+    impl BuildSystem for TestBuildSystem {
+        fn workspace_root(&self) -> &Path {
+            &self.0
+        }
+
+        fn workspace_name(&self) -> Option<&str> {
+            None
+        }
+
+        fn repository_path(&self, _repository_name: &str) -> Option<PathBuf> {
+            Some(self.0.clone())
+        }
+    }
+
+    /// A workspace in a fresh temporary directory, with the given files.
+    fn workspace(name: &str, files: &[(&str, &str)]) -> (PathBuf, Context) {
+        let root = std::env::temp_dir().join(format!("starlark-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let mut ctx = Context::new(ContextMode::Check, false, &[], false).unwrap();
+        ctx.build_system = Some(Arc::new(TestBuildSystem(root.clone())));
+        (root, ctx)
+    }
+
+    #[test]
+    fn test_resolve_label() {
+        let (root, ctx) = workspace(
+            "resolve-label",
+            &[
+                ("BUILD", ""),
+                ("pkg/BUILD", ""),
+                ("pkg/defs.bzl", ""),
+                ("pkg/sub/BUILD", ""),
+                ("nobuild/defs.bzl", ""),
+            ],
+        );
+        assert_eq!(
+            root.join("pkg/defs.bzl"),
+            ctx.resolve_label("//pkg:defs.bzl").unwrap()
+        );
+        assert_eq!(
+            root.join("top.bzl"),
+            ctx.resolve_label("//:top.bzl").unwrap()
+        );
+        let err = ctx.resolve_label("//pkg:sub/x.bzl").unwrap_err();
+        assert!(
+            err.to_string().contains("crosses into the package"),
+            "{}",
+            err
+        );
+        // A directory without a build file is not a package, even inside one
+        let err = ctx.resolve_label("//nobuild:defs.bzl").unwrap_err();
+        assert!(
+            err.to_string().contains("No such package `nobuild`"),
+            "{}",
+            err
+        );
+        let err = ctx.resolve_label("//missing:defs.bzl").unwrap_err();
+        assert!(err.to_string().contains("No such package"), "{}", err);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_load_relative_label() {
+        let (root, ctx) = workspace(
+            "relative-label",
+            &[
+                ("pkg/BUILD", ""),
+                ("pkg/dir/defs.bzl", ""),
+                ("pkg/sub/BUILD", ""),
+            ],
+        );
+        let current = LspUrl::File(root.join("pkg/dir/defs.bzl"));
+        let resolve = |path| match ctx.resolve_load(path, &current) {
+            Ok(LspUrl::File(path)) => Ok(path),
+            Ok(url) => panic!("Unexpected url {}", url),
+            Err(e) => Err(e.to_string()),
+        };
+        // Relative to the package, not the directory of the file.
+        assert_eq!(Ok(root.join("pkg/other.bzl")), resolve(":other.bzl"));
+        assert_eq!(Ok(root.join("pkg/dir/other.bzl")), resolve("other.bzl"));
+        let err = resolve(":sub/x.bzl").unwrap_err();
+        assert!(err.contains("crosses into the package"), "{}", err);
+        let err = resolve(":").unwrap_err();
+        assert!(err.contains("Missing target name"), "{}", err);
+        let err = resolve(":a:b.bzl").unwrap_err();
+        assert!(err.contains("Missing target name"), "{}", err);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_typecheck_diamond_load() {
+        let (root, mut ctx) = workspace(
+            "diamond-load",
+            &[
+                (
+                    "main.bzl",
+                    "load('a.bzl', 'a')\nload('b.bzl', 'b')\nx = a() + b()\n",
+                ),
+                ("a.bzl", "load('c.bzl', 'c')\ndef a() -> int: return c()\n"),
+                ("b.bzl", "load('c.bzl', 'c')\ndef b() -> int: return c()\n"),
+                ("c.bzl", "def c() -> int: return 1\n"),
+            ],
+        );
+        ctx.typecheck_profile = Some(Mutex::new(Vec::new()));
+        let main = root.join("main.bzl");
+        let main = main.to_str().unwrap();
+        let checked = |ctx: &Context| {
+            let mut checked: Vec<String> = ctx
+                .typecheck_profile
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .drain(..)
+                .map(|(file, _)| {
+                    Path::new(&file)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            checked.sort();
+            checked
+        };
+        let typecheck = |ctx: &Context| {
+            let ast = AstModule::parse_file(Path::new(main), &dialect()).unwrap();
+            let mut errors = Vec::new();
+            ctx.typecheck(main, ast, &mut |x| errors.push(x.to_string()));
+            assert!(errors.is_empty(), "{:?}", errors);
+        };
+        // `c.bzl` is loaded through both `a.bzl` and `b.bzl`, but only typechecked once.
+        typecheck(&ctx);
+        assert_eq!(vec!["a.bzl", "b.bzl", "c.bzl", "main.bzl"], checked(&ctx));
+        // Unchanged modules come from the interface cache on the next check.
+        typecheck(&ctx);
+        assert_eq!(vec!["main.bzl"], checked(&ctx));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
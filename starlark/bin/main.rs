@@ -22,19 +22,25 @@
 // Disagree these are good hints
 #![allow(clippy::type_complexity)]
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::ffi::OsStr;
 use std::fmt;
 use std::fmt::Display;
+use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 
+use anyhow::Context as _;
 use clap::Parser;
 use clap::ValueEnum;
 use dupe::Dupe;
 use eval::Context;
 use itertools::Either;
 use itertools::Itertools;
+use serde::Serialize;
 use starlark::docs::get_registered_starlark_docs;
 use starlark::docs::render_docs_as_code;
 use starlark::docs::Doc;
@@ -42,18 +48,28 @@ use starlark::docs::Identifier;
 use starlark::docs::MarkdownFlavor;
 use starlark::docs::RenderMarkdown;
 use starlark::environment::Globals;
+use starlark::environment::LibraryExtension;
 use starlark::errors::EvalMessage;
 use starlark::errors::EvalSeverity;
 use starlark::lsp;
+use starlark::lsp::build_system::BuildSystemConfig;
+use starlark::lsp::build_system::BuildSystemRegistry;
 use starlark::read_line::ReadLine;
-use walkdir::WalkDir;
+use starlark::syntax::AstModule;
+use starlark::syntax::ModuleMetrics;
+use starlark::typing::Interface;
+use starlark::typing::OracleStandard;
+use starlark::typing::TypeCoverage;
+use starlark::typing::TypecheckProfile;
 
+use crate::eval::dialect;
 use crate::eval::ContextMode;
 use crate::types::LintMessage;
 
 mod dap;
 mod eval;
 mod types;
+mod walk;
 
 #[derive(Debug, Parser)]
 #[command(name = "starlark", about = "Evaluate Starlark code")]
@@ -64,8 +80,12 @@ struct Args {
         conflicts_with_all = &[
             "dap",
             "check",
+            "typecheck",
             "json",
             "docs",
+            "metrics",
+            "type_coverage",
+            "api_diff",
             "evaluate",
             "files",
         ],
@@ -79,8 +99,12 @@ struct Args {
         conflicts_with_all = &[
             "lsp",
             "check",
+            "typecheck",
             "json",
             "docs",
+            "metrics",
+            "type_coverage",
+            "api_diff",
             "extension",
             "prelude",
             "evaluate",
@@ -96,6 +120,20 @@ struct Args {
     )]
     check: bool,
 
+    #[arg(
+        long = "typecheck",
+        help = "Typecheck the code, including the types of loaded modules.",
+        conflicts_with_all = &["lsp", "dap", "check"],
+    )]
+    typecheck: bool,
+
+    #[arg(
+        long = "typecheck-profile",
+        help = "Report how long typechecking each file and function took, slowest first.",
+        requires = "typecheck"
+    )]
+    typecheck_profile: bool,
+
     #[arg(
         long = "json",
         help = "Show output as JSON lines.",
@@ -110,15 +148,59 @@ struct Args {
     )]
     docs: Option<ArgsDoc>,
 
+    #[arg(
+        long = "metrics",
+        help = "Print size and complexity statistics for each file, instead of evaluating them.",
+        conflicts_with_all = &["lsp", "dap", "check", "typecheck", "json", "docs", "evaluate"],
+    )]
+    metrics: Option<ArgsMetrics>,
+
+    #[arg(
+        long = "type-coverage",
+        id = "type_coverage",
+        help = "Print how much of each file is covered by type annotations and inferred types, followed by the total with an empty path, instead of evaluating them.",
+        conflicts_with_all = &["lsp", "dap", "check", "typecheck", "json", "docs", "metrics", "evaluate"],
+    )]
+    type_coverage: Option<ArgsMetrics>,
+
+    #[arg(
+        long = "api-diff",
+        id = "api_diff",
+        value_names = ["OLD", "NEW"],
+        help = "Report changes to the exported symbols between two versions of a file or directory which might break the modules loading them.",
+        conflicts_with_all = &["lsp", "dap", "check", "typecheck", "json", "docs", "metrics", "type_coverage", "evaluate", "files"],
+        num_args = 2,
+    )]
+    api_diff: Option<Vec<PathBuf>>,
+
     #[arg(
         long = "extension",
         help = "File extension when searching directories."
     )]
     extension: Option<String>,
 
-    #[arg(long = "prelude", help = "Files to load in advance.", num_args = 1..)]
+    #[arg(
+        long = "prelude",
+        help = "Files to load in advance. Those only defining data, without printing, are cached between runs.",
+        num_args = 1..
+    )]
     prelude: Vec<PathBuf>,
 
+    #[arg(
+        long = "build-system-config",
+        value_name = "FILE",
+        help = "JSON file describing additional build systems, used by the language server to resolve labels in `load`."
+    )]
+    build_system_config: Option<PathBuf>,
+
+    #[arg(
+        long = "lsp-state-file",
+        value_name = "FILE",
+        help = "File to keep the LSP server's caches in between runs, so restarts are faster.",
+        requires = "lsp"
+    )]
+    lsp_state_file: Option<PathBuf>,
+
     #[arg(
         long = "expression",
         short = 'e',
@@ -133,7 +215,7 @@ struct Args {
     #[arg(
         id = "files",
         value_name = "FILE",
-        help = "Files to evaluate.",
+        help = "Files to evaluate, or `-` to read the program from stdin.",
         conflicts_with_all = &["lsp", "dap"],
     )]
     files: Vec<PathBuf>,
@@ -146,21 +228,239 @@ enum ArgsDoc {
     Code,
 }
 
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum ArgsMetrics {
+    Json,
+    Csv,
+}
+
+#[derive(Serialize)]
+struct FileMetrics<'a> {
+    path: &'a str,
+    #[serde(flatten)]
+    metrics: ModuleMetrics,
+}
+
+fn csv_field(x: &str) -> String {
+    if x.contains([',', '"', '\n']) {
+        format!("\"{}\"", x.replace('"', "\"\""))
+    } else {
+        x.to_owned()
+    }
+}
+
+/// Print the metrics of each file, returning the number of files that failed to parse.
+fn metrics(format: ArgsMetrics, files: impl Iterator<Item = PathBuf>) -> usize {
+    if format == ArgsMetrics::Csv {
+        println!("path,lines,functions,cyclomatic_complexity,max_nesting,loads,exported_symbols");
+    }
+    let mut errors = 0;
+    for file in files {
+        let metrics = match AstModule::parse_file(&file, &dialect()) {
+            Ok(module) => module.metrics(),
+            Err(e) => {
+                errors += 1;
+                eprintln!("{:#}", e);
+                continue;
+            }
+        };
+        let path = file.to_string_lossy();
+        match format {
+            ArgsMetrics::Json => println!(
+                "{}",
+                serde_json::to_string(&FileMetrics {
+                    path: &path,
+                    metrics
+                })
+                .unwrap()
+            ),
+            ArgsMetrics::Csv => println!(
+                "{},{},{},{},{},{},{}",
+                csv_field(&path),
+                metrics.lines,
+                metrics.functions,
+                metrics.cyclomatic_complexity,
+                metrics.max_nesting,
+                metrics.loads,
+                metrics.exported_symbols
+            ),
+        }
+    }
+    errors
+}
+
+#[derive(Serialize)]
+struct FileTypeCoverage<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    #[serde(flatten)]
+    coverage: &'a TypeCoverage,
+    parameter_ratio: f64,
+    return_ratio: f64,
+    any_ratio: f64,
+}
+
+/// Print the type coverage of each file and the total, returning the number of files
+/// that failed to parse. Loaded modules are not followed, so their symbols are `Any`.
+fn type_coverage(format: ArgsMetrics, files: impl Iterator<Item = PathBuf>) -> usize {
+    fn print(format: ArgsMetrics, path: Option<&str>, coverage: &TypeCoverage) {
+        match format {
+            ArgsMetrics::Json => println!(
+                "{}",
+                serde_json::to_string(&FileTypeCoverage {
+                    path,
+                    coverage,
+                    parameter_ratio: coverage.parameter_ratio(),
+                    return_ratio: coverage.return_ratio(),
+                    any_ratio: coverage.any_ratio(),
+                })
+                .unwrap()
+            ),
+            ArgsMetrics::Csv => println!(
+                "{},{},{},{},{},{},{},{},{:.3},{:.3},{:.3}",
+                csv_field(path.unwrap_or_default()),
+                coverage.functions,
+                coverage.parameters,
+                coverage.annotated_parameters,
+                coverage.annotated_returns,
+                coverage.bindings,
+                coverage.any_bindings,
+                coverage.approximations,
+                coverage.parameter_ratio(),
+                coverage.return_ratio(),
+                coverage.any_ratio()
+            ),
+        }
+    }
+
+    if format == ArgsMetrics::Csv {
+        println!(
+            "path,functions,parameters,annotated_parameters,annotated_returns,bindings,any_bindings,approximations,parameter_ratio,return_ratio,any_ratio"
+        );
+    }
+    let oracle = OracleStandard::new(LibraryExtension::all());
+    let mut total = TypeCoverage::default();
+    let mut errors = 0;
+    for file in files {
+        let coverage = match AstModule::parse_file(&file, &dialect()) {
+            Ok(module) => module.type_coverage(&oracle, &HashMap::new()),
+            Err(e) => {
+                errors += 1;
+                eprintln!("{:#}", e);
+                continue;
+            }
+        };
+        print(format, Some(&file.to_string_lossy()), &coverage);
+        total += &coverage;
+    }
+    print(format, None, &total);
+    errors
+}
+
+/// The interface a file exports to the modules that load it.
+fn interface(file: &Path) -> anyhow::Result<Interface> {
+    let ast = AstModule::parse_file(file, &dialect())?;
+    let oracle = OracleStandard::new(LibraryExtension::all());
+    Ok(ast.typecheck(&oracle, &HashMap::new()).2)
+}
+
+/// Print the changes between two versions of a file, or of the .<extension> files in a
+/// directory, which might break the modules loading them. Returns the number of changes.
+fn api_diff(extension: &str, old: &Path, new: &Path) -> anyhow::Result<usize> {
+    // Files by their path relative to the root, so the two versions can be matched up
+    let files = |root: &Path| -> BTreeMap<PathBuf, PathBuf> {
+        if root.is_dir() {
+            walk::walk_files(root, extension)
+                .map(|x| (x.strip_prefix(root).unwrap().to_owned(), x))
+                .collect()
+        } else {
+            BTreeMap::from([(PathBuf::new(), root.to_owned())])
+        }
+    };
+    let new_files = files(new);
+    let mut changes = 0;
+    for (name, old_file) in files(old) {
+        let display = |file: &Path| {
+            if name.as_os_str().is_empty() {
+                file.display().to_string()
+            } else {
+                name.display().to_string()
+            }
+        };
+        match new_files.get(&name) {
+            None => {
+                changes += 1;
+                println!("{}: file was removed", display(&old_file));
+            }
+            Some(new_file) => {
+                for change in interface(&old_file)?.breaking_changes(&interface(new_file)?) {
+                    changes += 1;
+                    println!("{}: {}", display(new_file), change);
+                }
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Print the slowest files and functions to typecheck to stderr. Files which are
+/// loaded several times are typechecked each time, so their times are added up.
+fn print_typecheck_profile(profiles: &[(String, TypecheckProfile)]) {
+    const TOP: usize = 20;
+    let ms = |x: Duration| x.as_secs_f64() * 1000.0;
+
+    let mut files: BTreeMap<&str, TypecheckProfile> = BTreeMap::new();
+    let mut functions: BTreeMap<(&str, usize, &str), Duration> = BTreeMap::new();
+    for (file, profile) in profiles {
+        let x = files.entry(file).or_default();
+        x.scope += profile.scope;
+        x.bindings += profile.bindings;
+        x.solve += profile.solve;
+        x.check += profile.check;
+        for f in &profile.functions {
+            let line = f.span.resolve_span().begin_line + 1;
+            *functions.entry((file, line, &f.name)).or_default() += f.time;
+        }
+    }
+    let total: Duration = files.values().map(|x| x.total()).sum();
+    eprintln!("Typechecked {} files in {:.1}ms", files.len(), ms(total));
+
+    eprintln!("Slowest files (total, scope, bindings, solve, check):");
+    for (file, x) in files
+        .iter()
+        .sorted_by_key(|(_, x)| std::cmp::Reverse(x.total()))
+        .take(TOP)
+    {
+        eprintln!(
+            "  {:>8.1}ms {:>8.1}ms {:>8.1}ms {:>8.1}ms {:>8.1}ms  {}",
+            ms(x.total()),
+            ms(x.scope),
+            ms(x.bindings),
+            ms(x.solve),
+            ms(x.check),
+            file
+        );
+    }
+
+    eprintln!("Slowest functions to solve:");
+    for ((file, line, name), time) in functions
+        .iter()
+        .sorted_by_key(|(_, x)| std::cmp::Reverse(**x))
+        .take(TOP)
+    {
+        eprintln!("  {:>8.1}ms  {}:{} {}", ms(*time), file, line, name);
+    }
+}
+
 // Treat directories as things to recursively walk for .<extension> files,
-// and everything else as normal files.
+// and everything else as normal files. Ignored files and build outputs are skipped.
 fn expand_dirs(extension: &str, xs: Vec<PathBuf>) -> impl Iterator<Item = PathBuf> {
     let extension = Arc::new(extension.to_owned());
     xs.into_iter().flat_map(move |x| {
         // Have to keep cloning extension so we keep ownership
         let extension = extension.dupe();
         if x.is_dir() {
-            Either::Left(
-                WalkDir::new(x)
-                    .into_iter()
-                    .filter_map(|e| e.ok())
-                    .filter(move |e| e.path().extension() == Some(OsStr::new(extension.as_str())))
-                    .map(|e| e.into_path()),
-            )
+            Either::Left(walk::walk_files(&x, &extension))
         } else {
             Either::Right(Box::new(vec![x].into_iter()))
         }
@@ -200,20 +500,19 @@ impl Stats {
     }
 }
 
-fn drain(xs: impl Iterator<Item = EvalMessage>, json: bool, stats: &mut Stats) {
-    for x in xs {
-        stats.increment(x.severity);
-        if json {
-            println!("{}", serde_json::to_string(&LintMessage::new(x)).unwrap());
-        } else if let Some(error) = x.full_error_with_span {
-            let mut error = error.to_owned();
-            if !error.is_empty() && !error.ends_with('\n') {
-                error.push('\n');
-            }
-            print!("{}", error);
-        } else {
-            println!("{}", x);
+/// Print a message as soon as it is reported, so long runs show progress.
+fn print_message(x: EvalMessage, json: bool, stats: &mut Stats) {
+    stats.increment(x.severity);
+    if json {
+        println!("{}", serde_json::to_string(&LintMessage::new(x)).unwrap());
+    } else if let Some(error) = x.full_error_with_span {
+        let mut error = error.to_owned();
+        if !error.is_empty() && !error.ends_with('\n') {
+            error.push('\n');
         }
+        print!("{}", error);
+    } else {
+        println!("{}", x);
     }
 }
 
@@ -223,7 +522,7 @@ fn interactive(ctx: &Context) -> anyhow::Result<()> {
         match rl.read_line("$> ")? {
             Some(line) => {
                 let mut stats = Stats::default();
-                drain(ctx.expression(line).messages, false, &mut stats);
+                ctx.expression(line, &mut |x| print_message(x, false, &mut stats));
             }
             // User pressed EOF - disconnected terminal, or similar
             None => return Ok(()),
@@ -231,6 +530,19 @@ fn interactive(ctx: &Context) -> anyhow::Result<()> {
     }
 }
 
+/// The built-in build systems, plus those described in the given configuration file.
+fn build_systems(config: Option<&Path>) -> anyhow::Result<BuildSystemRegistry> {
+    let mut registry = BuildSystemRegistry::builtin();
+    if let Some(config) = config {
+        let configs: Vec<BuildSystemConfig> = serde_json::from_str(&fs::read_to_string(config)?)
+            .with_context(|| format!("Invalid build system config `{}`", config.display()))?;
+        for config in configs {
+            registry.register_config(config);
+        }
+    }
+    Ok(registry)
+}
+
 fn main() -> anyhow::Result<()> {
     gazebo::terminate_on_panic();
 
@@ -248,6 +560,8 @@ fn main() -> anyhow::Result<()> {
         let mut ctx = Context::new(
             if args.check {
                 ContextMode::Check
+            } else if args.typecheck {
+                ContextMode::Typecheck
             } else {
                 ContextMode::Run
             },
@@ -255,9 +569,29 @@ fn main() -> anyhow::Result<()> {
             &expand_dirs(ext, args.prelude).collect::<Vec<_>>(),
             is_interactive,
         )?;
+        if args.typecheck_profile {
+            ctx.typecheck_profile = Some(Mutex::new(Vec::new()));
+        }
 
-        if args.lsp {
+        if let Some(format) = args.metrics {
+            let errors = metrics(format, expand_dirs(ext, args.files));
+            if errors > 0 {
+                return Err(anyhow::anyhow!("Failed to parse {} files", errors));
+            }
+        } else if let Some(format) = args.type_coverage {
+            let errors = type_coverage(format, expand_dirs(ext, args.files));
+            if errors > 0 {
+                return Err(anyhow::anyhow!("Failed to parse {} files", errors));
+            }
+        } else if let Some(paths) = args.api_diff {
+            let changes = api_diff(ext, &paths[0], &paths[1])?;
+            if changes > 0 {
+                return Err(anyhow::anyhow!("Found {} breaking changes", changes));
+            }
+        } else if args.lsp {
             ctx.mode = ContextMode::Check;
+            ctx.lsp_state_file = args.lsp_state_file;
+            ctx.resolve_build_system(&build_systems(args.build_system_config.as_deref())?)?;
             lsp::server::stdio_server(ctx)?;
         } else if let Some(docs) = args.docs {
             let mut builtin = get_registered_starlark_docs();
@@ -290,12 +624,16 @@ fn main() -> anyhow::Result<()> {
             let mut stats = Stats::default();
             for e in args.evaluate.clone() {
                 stats.increment_file();
-                drain(ctx.expression(e).messages, args.json, &mut stats);
+                ctx.expression(e, &mut |x| print_message(x, args.json, &mut stats));
             }
 
             for file in expand_dirs(ext, args.files.clone()) {
                 stats.increment_file();
-                drain(ctx.file(&file).messages, args.json, &mut stats);
+                ctx.file(&file, &mut |x| print_message(x, args.json, &mut stats));
+            }
+
+            if let Some(profiles) = &ctx.typecheck_profile {
+                print_typecheck_profile(&profiles.lock().unwrap());
             }
 
             if !args.json {
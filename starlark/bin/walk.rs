@@ -0,0 +1,65 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Filesystem enumeration that skips the same things a user's tools would skip:
+//! entries matched by `.gitignore` / `.ignore` / `.bazelignore` files, and the
+//! output directories of build systems (`bazel-out/`, `buck-out/`, ...).
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use ignore::WalkBuilder;
+
+/// Ignore files honored in addition to the git ignore files.
+const CUSTOM_IGNORE_FILES: &[&str] = &[".bazelignore"];
+
+/// Whether a directory with this name holds build system outputs rather than sources.
+///
+/// Buck creates a `buck-out` directory. Bazel creates `bazel-bin`, `bazel-out`,
+/// `bazel-testlogs` and `bazel-<workspace>`, all of them symlinks, so a `bazel-*`
+/// entry only counts when it is a symlink and ordinary sources like `bazel-rules/`
+/// are still walked.
+pub(crate) fn is_build_output_dir(name: &str, is_symlink: bool) -> bool {
+    name == "buck-out" || (is_symlink && name.starts_with("bazel-"))
+}
+
+/// Recursively list all files below `dir` with the given extension, skipping
+/// ignored entries and build output directories.
+pub(crate) fn walk_files(dir: &Path, extension: &str) -> impl Iterator<Item = PathBuf> {
+    let extension = extension.to_owned();
+    let mut builder = WalkBuilder::new(dir);
+    builder.hidden(false).require_git(false).filter_entry(|e| {
+        // Bazel's convenience links are symlinks to directories, so check those too.
+        let is_symlink = e.path_is_symlink();
+        let is_dir = e.file_type().map_or(false, |t| t.is_dir()) || is_symlink;
+        !(is_dir
+            && e.depth() > 0
+            && e
+                .file_name()
+                .to_str()
+                .map_or(false, |name| is_build_output_dir(name, is_symlink)))
+    });
+    for name in CUSTOM_IGNORE_FILES {
+        builder.add_custom_ignore_filename(name);
+    }
+    builder
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map_or(false, |t| t.is_file()))
+        .filter(move |e| e.path().extension().and_then(|e| e.to_str()) == Some(&extension))
+        .map(|e| e.into_path())
+}
@@ -17,7 +17,6 @@
 
 use std::collections::HashMap;
 
-use crate::codemap::Pos;
 use crate::codemap::Span;
 use crate::syntax::ast::AssignIdentP;
 use crate::syntax::ast::AstAssign;
@@ -60,6 +59,7 @@ pub(crate) enum Bind {
 #[derive(Debug, Clone)]
 pub(crate) struct GetDotted {
     pub(crate) variable: AstString,
+    #[cfg_attr(not(feature = "lsp"), allow(dead_code))] // Only used by the LSP
     pub(crate) attributes: Vec<AstString>,
 }
 
@@ -70,23 +70,6 @@ impl GetDotted {
             attributes,
         }
     }
-
-    /// Determines if a position is contained in this dot access.
-    ///
-    /// The returned value is the index of the segment where `pos` was found, and
-    /// the span that contained it. If the Pos is inside the variable the index is None,
-    /// otherwise the index inside the attributes.
-    pub(crate) fn contains(&self, pos: Pos) -> Option<(Option<usize>, Span)> {
-        if self.variable.span.contains(pos) {
-            return Some((None, self.variable.span));
-        }
-        for (i, s) in self.attributes.iter().enumerate() {
-            if s.span.contains(pos) {
-                return Some((Some(i), s.span));
-            }
-        }
-        None
-    }
 }
 
 #[derive(Debug)]
@@ -284,6 +267,8 @@ fn stmt(x: &AstStmt, res: &mut Vec<Bind>) {
             expr(rhs, res);
             expr_lvalue(lhs, res);
         }
+        // Nothing is assigned, the name is bound by later assignments
+        Stmt::Declare(_, ty) => expr(ty, res),
         Stmt::AssignModify(lhs, _, rhs) => {
             // For a += b, we:
             // 1. Evaluate all variables and expressions in a.
@@ -329,8 +314,6 @@ mod test {
     use gazebo::prelude::*;
 
     use super::*;
-    use crate::codemap::Pos;
-    use crate::codemap::Span;
     use crate::syntax::AstModule;
     use crate::syntax::Dialect;
 
@@ -365,36 +348,4 @@ mod test {
 
         Ok(())
     }
-
-    #[test]
-    fn dotted_contains_is_correct() -> anyhow::Result<()> {
-        let contents = "x1.y1.z1\nx2.y2.z2";
-        let module = AstModule::parse("foo.star", contents.to_owned(), &Dialect::Extended)?;
-        let scope = scope(&module);
-
-        let get = scope
-            .inner
-            .iter()
-            .find_map(|b| match b {
-                Bind::GetDotted(get) => Some((*get).clone()),
-                _ => None,
-            })
-            .unwrap();
-
-        let x1_pos = Pos::new(1);
-        let y1_pos = Pos::new(4);
-        let z1_pos = Pos::new(7);
-        let x2_pos = Pos::new(10);
-
-        let x1_expected = Some((None, Span::new(Pos::new(0), Pos::new(2))));
-        let y1_expected = Some((Some(0), Span::new(Pos::new(3), Pos::new(5))));
-        let z1_expected = Some((Some(1), Span::new(Pos::new(6), Pos::new(8))));
-
-        assert_eq!(x1_expected, get.contains(x1_pos));
-        assert_eq!(y1_expected, get.contains(y1_pos));
-        assert_eq!(z1_expected, get.contains(z1_pos));
-        assert!(get.contains(x2_pos).is_none());
-
-        Ok(())
-    }
 }
@@ -0,0 +1,749 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Computing the constant arguments of top-level calls without running the module.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+
+use num_traits::ToPrimitive;
+
+use crate::codemap::FileSpan;
+use crate::codemap::Span;
+use crate::syntax::ast::ArgumentP;
+use crate::syntax::ast::AssignOp;
+use crate::syntax::ast::AssignP;
+use crate::syntax::ast::AstAssign;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::BinOp;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::Stmt;
+use crate::syntax::lexer::TokenInt;
+use crate::syntax::uniplate::Visit;
+use crate::syntax::AstModule;
+
+/// A value computed from the source of a module without running it,
+/// see [`AstModule::top_level_calls`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    /// `None`.
+    None,
+    /// `True` or `False`.
+    Bool(bool),
+    /// An integer literal, or arithmetic on them.
+    Int(i64),
+    /// A float literal.
+    Float(f64),
+    /// A string literal, or concatenation of them.
+    String(String),
+    /// A list, where every element is constant.
+    List(Vec<ConstExpr>),
+    /// A tuple, where every element is constant.
+    Tuple(Vec<ConstExpr>),
+    /// A dictionary, where every key and value is constant.
+    Dict(Vec<(ConstExpr, ConstExpr)>),
+    /// A call to a global function where every argument is constant, e.g. `glob(["*.c"])`
+    /// or `select({...})`. The call is not evaluated, so the caller can interpret it.
+    Call(String, Vec<(Option<String>, ConstExpr)>),
+}
+
+/// A [`ConstValue`] with the location of the expression it was computed from.
+/// The parts of a value built from a variable (e.g. `SRCS + ["b.c"]`) keep the
+/// location where the variable was defined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstExpr {
+    /// Where the expression is.
+    pub span: FileSpan,
+    /// The value of the expression.
+    pub value: ConstValue,
+}
+
+/// An argument of a [`TopLevelCall`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstArgument {
+    /// The name of the argument, or [`None`] if it is positional.
+    pub name: Option<String>,
+    /// Where the argument is.
+    pub span: FileSpan,
+    /// The value of the argument, or [`None`] if it can't be computed without running the module.
+    pub value: Option<ConstExpr>,
+}
+
+/// A call to a global function in a top-level statement, such as a rule in a build file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopLevelCall {
+    /// The name of the function called.
+    pub name: String,
+    /// Where the call is.
+    pub span: FileSpan,
+    /// The positional and named arguments, in order. `*args` and `**kwargs` are left out.
+    pub args: Vec<ConstArgument>,
+}
+
+impl ConstValue {
+    /// The underlying string, if this is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ConstValue::String(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Number of elements and string bytes in the value, to limit its size.
+    fn size(&self) -> usize {
+        match self {
+            ConstValue::None | ConstValue::Bool(_) | ConstValue::Int(_) | ConstValue::Float(_) => 1,
+            ConstValue::String(x) => 1 + x.len(),
+            ConstValue::List(xs) | ConstValue::Tuple(xs) => {
+                1 + xs.iter().map(|x| x.value.size()).sum::<usize>()
+            }
+            ConstValue::Dict(xs) => {
+                1 + xs
+                    .iter()
+                    .map(|(k, v)| k.value.size() + v.value.size())
+                    .sum::<usize>()
+            }
+            ConstValue::Call(_, args) => 1 + args.iter().map(|(_, x)| x.value.size()).sum::<usize>(),
+        }
+    }
+
+    /// Whether the value is, or contains, a list or a dict, which may be modified.
+    fn is_mutable(&self) -> bool {
+        match self {
+            ConstValue::List(_) | ConstValue::Dict(_) => true,
+            ConstValue::Tuple(xs) => xs.iter().any(|x| x.value.is_mutable()),
+            _ => false,
+        }
+    }
+}
+
+impl TopLevelCall {
+    /// The arguments passed by name, in order.
+    pub fn named_arguments(&self) -> impl Iterator<Item = (&str, &ConstArgument)> {
+        self.args
+            .iter()
+            .filter_map(|x| Some((x.name.as_deref()?, x)))
+    }
+
+    /// The argument passed with the given name, if any.
+    pub fn named_argument(&self, name: &str) -> Option<&ConstArgument> {
+        self.named_arguments()
+            .find(|(x, _)| *x == name)
+            .map(|(_, x)| x)
+    }
+
+    /// The `name` argument, if it is a constant string. For a rule call in a build file,
+    /// this is the name of the target it defines.
+    pub fn name_attribute(&self) -> Option<&str> {
+        self.named_argument("name")?.value.as_ref()?.value.as_str()
+    }
+}
+
+impl Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn comma_separated<T>(
+            f: &mut fmt::Formatter<'_>,
+            xs: &[T],
+            mut g: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+        ) -> fmt::Result {
+            for (i, x) in xs.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(", ")?;
+                }
+                g(f, x)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            ConstValue::None => f.write_str("None"),
+            ConstValue::Bool(true) => f.write_str("True"),
+            ConstValue::Bool(false) => f.write_str("False"),
+            ConstValue::Int(x) => write!(f, "{}", x),
+            ConstValue::Float(x) => write!(f, "{:?}", x),
+            ConstValue::String(x) => write!(f, "{:?}", x),
+            ConstValue::List(xs) => {
+                f.write_str("[")?;
+                comma_separated(f, xs, |f, x| write!(f, "{}", x.value))?;
+                f.write_str("]")
+            }
+            ConstValue::Tuple(xs) => {
+                f.write_str("(")?;
+                comma_separated(f, xs, |f, x| write!(f, "{}", x.value))?;
+                if xs.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            ConstValue::Dict(xs) => {
+                f.write_str("{")?;
+                comma_separated(f, xs, |f, (k, v)| write!(f, "{}: {}", k.value, v.value))?;
+                f.write_str("}")
+            }
+            ConstValue::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                comma_separated(f, args, |f, (name, x)| match name {
+                    None => write!(f, "{}", x.value),
+                    Some(name) => write!(f, "{} = {}", name, x.value),
+                })?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Values larger than this, counting elements and string bytes, are not computed,
+/// as e.g. repeating `x = x + x` grows them exponentially.
+const MAX_SIZE: usize = 1 << 16;
+
+/// The values of the top-level variables known so far.
+struct ConstEnv<'a> {
+    module: &'a AstModule,
+    vars: HashMap<String, ConstExpr>,
+    /// For each variable, the other variables which may share a list or dict with it,
+    /// e.g. after `B = A`. Mutating one of them mutates the others too.
+    aliases: HashMap<String, HashSet<String>>,
+    /// Functions defined in the module, which may mutate any variable when called.
+    defs: HashSet<String>,
+}
+
+impl<'a> ConstEnv<'a> {
+    fn expr(&self, span: Span, value: ConstValue) -> ConstExpr {
+        ConstExpr {
+            span: self.module.file_span(span),
+            value,
+        }
+    }
+
+    fn eval(&self, x: &AstExpr) -> Option<ConstExpr> {
+        let value = match &x.node {
+            Expr::Literal(AstLiteral::Int(i)) => ConstValue::Int(match &i.node {
+                TokenInt::I32(i) => *i as i64,
+                TokenInt::BigInt(i) => i.to_i64()?,
+            }),
+            Expr::Literal(AstLiteral::Float(f)) => ConstValue::Float(f.node),
+            Expr::Literal(AstLiteral::String(s)) => ConstValue::String(s.node.clone()),
+            Expr::Identifier(name, _) => match self.vars.get(name.node.as_str()) {
+                // Keep the location of the definition, since that is where to edit it.
+                Some(x) => return Some(x.clone()),
+                None => match name.node.as_str() {
+                    "None" => ConstValue::None,
+                    "True" => ConstValue::Bool(true),
+                    "False" => ConstValue::Bool(false),
+                    _ => return None,
+                },
+            },
+            Expr::List(xs) => ConstValue::List(self.eval_all(xs)?),
+            Expr::Tuple(xs) => ConstValue::Tuple(self.eval_all(xs)?),
+            Expr::Dict(xs) => {
+                let mut size = 0;
+                ConstValue::Dict(
+                    xs.iter()
+                        .map(|(k, v)| {
+                            let (k, v) = (self.eval(k)?, self.eval(v)?);
+                            size += k.value.size() + v.value.size();
+                            (size <= MAX_SIZE).then_some((k, v))
+                        })
+                        .collect::<Option<_>>()?,
+                )
+            }
+            Expr::Minus(x) => match self.eval(x)?.value {
+                ConstValue::Int(i) => ConstValue::Int(i.checked_neg()?),
+                ConstValue::Float(f) => ConstValue::Float(-f),
+                _ => return None,
+            },
+            Expr::Op(a, BinOp::Add, b) => add(self.eval(a)?.value, self.eval(b)?.value)?,
+            Expr::Call(f, args) => match &f.node {
+                Expr::Identifier(name, _) => {
+                    let mut size = 0;
+                    ConstValue::Call(
+                        name.node.clone(),
+                        args.iter()
+                            .map(|x| {
+                                let (name, x) = match &x.node {
+                                    ArgumentP::Positional(x) => (None, x),
+                                    ArgumentP::Named(name, x) => (Some(name.node.clone()), x),
+                                    ArgumentP::Args(_) | ArgumentP::KwArgs(_) => return None,
+                                };
+                                let x = self.eval(x)?;
+                                size += x.value.size();
+                                (size <= MAX_SIZE).then_some((name, x))
+                            })
+                            .collect::<Option<_>>()?,
+                    )
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(self.expr(x.span, value))
+    }
+
+    fn eval_all(&self, xs: &[AstExpr]) -> Option<Vec<ConstExpr>> {
+        let mut size = 0;
+        xs.iter()
+            .map(|x| {
+                let x = self.eval(x)?;
+                size += x.value.size();
+                (size <= MAX_SIZE).then_some(x)
+            })
+            .collect()
+    }
+
+    /// Update the variables for a top-level statement.
+    fn stmt(&mut self, x: &AstStmt) {
+        if let Stmt::Def(def) = &x.node {
+            self.defs.insert(def.name.0.clone());
+        }
+        self.forget_mutated_stmt(x);
+        match &x.node {
+            Stmt::Assign(lhs, rhs) => match &lhs.node {
+                AssignP::Identifier(name) => {
+                    match self.eval(&rhs.1) {
+                        Some(v) => {
+                            self.vars.insert(name.0.clone(), v);
+                        }
+                        None => {
+                            self.vars.remove(&name.0);
+                        }
+                    }
+                    self.alias(&name.0, &rhs.1);
+                }
+                _ => self.forget(x),
+            },
+            Stmt::AssignModify(lhs, AssignOp::Add, rhs) => match &lhs.node {
+                AssignP::Identifier(name) => {
+                    let value = self
+                        .vars
+                        .get(&name.0)
+                        .cloned()
+                        .zip(self.eval(rhs))
+                        .and_then(|(a, b)| add(a.value, b.value));
+                    // `+=` extends a list in place, changing its aliases too.
+                    self.forget_mutated_var(&name.0);
+                    if let Some(value) = value {
+                        let v = self.expr(x.span, value);
+                        self.vars.insert(name.0.clone(), v);
+                    }
+                }
+                _ => self.forget(x),
+            },
+            Stmt::Expression(_) => {}
+            _ => self.forget(x),
+        }
+    }
+
+    /// Forget the variables which `x` assigns, anywhere within it.
+    fn forget(&mut self, x: &AstStmt) {
+        match &x.node {
+            Stmt::Assign(lhs, _) | Stmt::AssignModify(lhs, _, _) | Stmt::For(lhs, _) => lhs
+                .visit_lvalue(|name| {
+                    self.vars.remove(&name.0);
+                }),
+            Stmt::Def(def) => {
+                self.vars.remove(&def.name.0);
+            }
+            Stmt::Load(load) => {
+                for (name, _) in &load.args {
+                    self.vars.remove(&name.0);
+                }
+            }
+            _ => {}
+        }
+        match &x.node {
+            Stmt::Assign(lhs, rhs) => lhs.visit_lvalue(|name| self.alias(&name.0, &rhs.1)),
+            Stmt::For(lhs, rhs) => lhs.visit_lvalue(|name| self.alias(&name.0, &rhs.0)),
+            _ => {}
+        }
+        x.visit_stmt(|x| self.forget(x));
+    }
+
+    /// Record that `name` was assigned `value`, which may share lists or dicts
+    /// with the variables it mentions.
+    fn alias(&mut self, name: &str, value: &AstExpr) {
+        fn mentioned(x: &AstExpr, res: &mut Vec<String>) {
+            match &x.node {
+                Expr::Identifier(name, _) => res.push(name.node.clone()),
+                _ => x.visit_expr(|x| mentioned(x, res)),
+            }
+        }
+
+        let mut mentions = Vec::new();
+        mentioned(value, &mut mentions);
+        let mut group = HashSet::new();
+        for x in mentions {
+            if let Some(xs) = self.aliases.get(&x) {
+                group.extend(xs.iter().cloned());
+            }
+            group.insert(x);
+        }
+        // The old value of `name` is no longer shared through it.
+        if let Some(old) = self.aliases.remove(name) {
+            for x in old {
+                if let Some(xs) = self.aliases.get_mut(&x) {
+                    xs.remove(name);
+                }
+            }
+        }
+        group.remove(name);
+        for x in &group {
+            self.aliases
+                .entry(x.clone())
+                .or_default()
+                .insert(name.to_owned());
+        }
+        if !group.is_empty() {
+            self.aliases.insert(name.to_owned(), group);
+        }
+    }
+
+    /// Forget the values mutated by running `x`, e.g. by `SRCS.append("c.c")`.
+    /// The bodies of functions are not run when they are defined.
+    fn forget_mutated_stmt(&mut self, x: &AstStmt) {
+        fn assigned_objects<'b>(x: &'b AstAssign, res: &mut Vec<&'b str>) {
+            let object = match &x.node {
+                AssignP::Tuple(xs) => return xs.iter().for_each(|x| assigned_objects(x, res)),
+                AssignP::ArrayIndirection(object_index) => &object_index.0,
+                AssignP::Dot(object, _) => object,
+                AssignP::Identifier(_) => return,
+            };
+            if let Expr::Identifier(name, _) = &object.node {
+                res.push(&name.node);
+            }
+        }
+
+        match &x.node {
+            Stmt::Def(_) => return,
+            // Assigning to `A[0]` or `A.x` modifies `A`.
+            Stmt::Assign(lhs, _) | Stmt::AssignModify(lhs, _, _) | Stmt::For(lhs, _) => {
+                let mut objects = Vec::new();
+                assigned_objects(lhs, &mut objects);
+                for name in objects {
+                    self.forget_mutated_var(name);
+                }
+            }
+            _ => {}
+        }
+        x.visit_children(|x| match x {
+            Visit::Stmt(x) => self.forget_mutated_stmt(x),
+            Visit::Expr(x) => self.forget_mutated(x),
+        })
+    }
+
+    fn forget_mutated(&mut self, x: &AstExpr) {
+        match &x.node {
+            // Lists may be modified by calling their methods.
+            Expr::Dot(object, _) => match &object.node {
+                Expr::Identifier(name, _) => self.forget_mutated_var(&name.node),
+                _ => self.forget_mutated(object),
+            },
+            Expr::Call(f, _) => {
+                if let Expr::Identifier(name, _) = &f.node {
+                    if self.defs.contains(name.node.as_str()) {
+                        // We don't know which globals the function modifies.
+                        let mutable: Vec<String> = self
+                            .vars
+                            .iter()
+                            .filter(|(_, v)| v.value.is_mutable())
+                            .map(|(k, _)| k.clone())
+                            .collect();
+                        for name in mutable {
+                            self.forget_mutated_var(&name);
+                        }
+                    }
+                }
+                x.visit_expr(|x| self.forget_mutated(x))
+            }
+            _ => x.visit_expr(|x| self.forget_mutated(x)),
+        }
+    }
+
+    /// Forget the variable `name`, which is mutated, along with its aliases.
+    fn forget_mutated_var(&mut self, name: &str) {
+        self.vars.remove(name);
+        if let Some(xs) = self.aliases.get(name) {
+            for x in xs {
+                self.vars.remove(x);
+            }
+        }
+    }
+}
+
+/// `a + b` for the types that support it.
+fn add(a: ConstValue, b: ConstValue) -> Option<ConstValue> {
+    if a.size() + b.size() > MAX_SIZE {
+        return None;
+    }
+    Some(match (a, b) {
+        (ConstValue::Int(a), ConstValue::Int(b)) => ConstValue::Int(a.checked_add(b)?),
+        (ConstValue::Float(a), ConstValue::Float(b)) => ConstValue::Float(a + b),
+        (ConstValue::String(a), ConstValue::String(b)) => ConstValue::String(a + &b),
+        (ConstValue::List(mut a), ConstValue::List(b)) => {
+            a.extend(b);
+            ConstValue::List(a)
+        }
+        (ConstValue::Tuple(mut a), ConstValue::Tuple(b)) => {
+            a.extend(b);
+            ConstValue::Tuple(a)
+        }
+        _ => return None,
+    })
+}
+
+impl AstModule {
+    /// The calls to global functions made by top-level statements, such as the rules
+    /// in a build file, in order. Use [`TopLevelCall::name_attribute`] to find the target
+    /// a rule call defines. The arguments are computed without running the module,
+    /// where they only use literals, `+`, calls (which are not evaluated, see
+    /// [`ConstValue::Call`]) and top-level variables defined that way.
+    ///
+    /// Variables which may have been modified, by their methods or by calling
+    /// a function defined in the module, are not computed. Assumes the functions
+    /// loaded by the module do not modify the arguments passed to them.
+    pub fn top_level_calls(&self) -> Vec<TopLevelCall> {
+        let mut env = ConstEnv {
+            module: self,
+            vars: HashMap::new(),
+            aliases: HashMap::new(),
+            defs: HashSet::new(),
+        };
+        let mut res = Vec::new();
+        for x in self.top_level_statements() {
+            if let Stmt::Expression(e) = &x.node {
+                if let Expr::Call(f, args) = &e.node {
+                    if let Expr::Identifier(name, _) = &f.node {
+                        res.push(TopLevelCall {
+                            name: name.node.clone(),
+                            span: self.file_span(e.span),
+                            args: args
+                                .iter()
+                                .filter_map(|arg| {
+                                    let (name, value) = match &arg.node {
+                                        ArgumentP::Positional(x) => (None, x),
+                                        ArgumentP::Named(name, x) => (Some(name.node.clone()), x),
+                                        ArgumentP::Args(_) | ArgumentP::KwArgs(_) => return None,
+                                    };
+                                    Some(ConstArgument {
+                                        name,
+                                        span: self.file_span(arg.span),
+                                        value: env.eval(value),
+                                    })
+                                })
+                                .collect(),
+                        });
+                    }
+                }
+            }
+            env.stmt(x);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn calls(code: &str) -> Vec<TopLevelCall> {
+        AstModule::parse("BUILD", code.to_owned(), &Dialect::Extended)
+            .unwrap()
+            .top_level_calls()
+    }
+
+    fn show(call: &TopLevelCall) -> String {
+        let args = call.args.iter().map(|x| {
+            let value = match &x.value {
+                None => "?".to_owned(),
+                Some(x) => x.value.to_string(),
+            };
+            match &x.name {
+                None => value,
+                Some(name) => format!("{} = {}", name, value),
+            }
+        });
+        format!("{}({})", call.name, args.collect::<Vec<_>>().join(", "))
+    }
+
+    #[test]
+    fn test_top_level_calls() {
+        let res = calls(
+            r#"
+load("rules.bzl", "cc_library")
+COMMON = ["common.c"]
+FLAGS = ("-O2",)
+cc_library(
+    name = "lib",
+    srcs = COMMON + ["lib.c"] + glob(["*.c"]),
+    deps = [":base"],
+    copts = FLAGS + ("-g",),
+    visibility = None,
+)
+cc_library(name = "all", srcs = glob(["*.c"], exclude = ["test.c"]), size = -1 + 3)
+def f():
+    pass
+f()
+"#,
+        );
+        assert_eq!(
+            res.iter().map(show).collect::<Vec<_>>(),
+            &[
+                r#"cc_library(name = "lib", srcs = ?, deps = [":base"], copts = ("-O2", "-g"), visibility = None)"#,
+                r#"cc_library(name = "all", srcs = glob(["*.c"], exclude = ["test.c"]), size = 2)"#,
+                "f()",
+            ]
+        );
+        assert_eq!(res[0].span.to_string(), "BUILD:5:1-11:2");
+        assert_eq!(res[0].args[0].span.to_string(), "BUILD:6:5-17");
+        // The element keeps the location of its definition
+        match &res[0].args[3].value.as_ref().unwrap().value {
+            ConstValue::Tuple(xs) => {
+                assert_eq!(xs[0].span.to_string(), "BUILD:4:10-15");
+                assert_eq!(xs[1].span.to_string(), "BUILD:9:22-26");
+            }
+            x => panic!("Expected a tuple, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_top_level_calls_build_file() {
+        let res = calls(
+            r#"
+load(":defs.bzl", "java_library")
+package(default_visibility = ["//visibility:public"])
+java_library(name = "core", srcs = ["Core.java"], deps = [":util"])
+java_library(name = "util" + "s")
+java_library(name = NAME)
+"#,
+        );
+        assert_eq!(
+            res.iter().map(|x| x.name_attribute()).collect::<Vec<_>>(),
+            &[None, Some("core"), Some("utils"), None]
+        );
+        let core = &res[1];
+        assert_eq!(
+            core.named_arguments().map(|(x, _)| x).collect::<Vec<_>>(),
+            &["name", "srcs", "deps"]
+        );
+        let name = core.named_argument("name").unwrap().value.as_ref().unwrap();
+        assert_eq!(name.span.to_string(), "BUILD:4:21-27");
+        assert!(core.named_argument("visibility").is_none());
+        match &core
+            .named_argument("deps")
+            .unwrap()
+            .value
+            .as_ref()
+            .unwrap()
+            .value
+        {
+            ConstValue::List(xs) => {
+                assert_eq!(xs[0].value.as_str(), Some(":util"));
+                assert_eq!(xs[0].span.to_string(), "BUILD:4:59-66");
+            }
+            x => panic!("Expected a list, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_top_level_calls_variables() {
+        let res = calls(
+            r#"
+SRCS = ["a.c"]
+SRCS += ["b.c"]
+rule(srcs = SRCS)
+SRCS.append("c.c")
+rule(srcs = SRCS)
+NAME = "x"
+def NAME(): pass
+rule(name = NAME)
+X = 1
+for X in []: pass
+rule(x = X, y = {"k": [1, 2.5, True]}, *args, **kwargs)
+"#,
+        );
+        assert_eq!(
+            res.iter().map(show).collect::<Vec<_>>(),
+            &[
+                r#"rule(srcs = ["a.c", "b.c"])"#,
+                "rule(srcs = ?)",
+                "rule(name = ?)",
+                r#"rule(x = ?, y = {"k": [1, 2.5, True]})"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_top_level_calls_mutation() {
+        let res = calls(
+            r#"
+A = [1]
+B = A
+B.append(2)
+rule(a = A)
+C = [1]
+D = [C, 3]
+C += [2]
+rule(c = C, d = D)
+E = {"k": 1}
+E["k"] = 2
+rule(e = E)
+SRCS = ["a.c"]
+NAME = "x"
+def f():
+    SRCS.append("b.c")
+rule(srcs = SRCS)
+f()
+rule(srcs = SRCS, name = NAME)
+G = [1]
+H = G
+H = [2]
+H.append(3)
+rule(g = G)
+"#,
+        );
+        assert_eq!(
+            res.iter().map(show).collect::<Vec<_>>(),
+            &[
+                "rule(a = ?)",
+                "rule(c = [1, 2], d = ?)",
+                "rule(e = ?)",
+                r#"rule(srcs = ["a.c"])"#,
+                "f()",
+                r#"rule(srcs = ?, name = "x")"#,
+                "rule(g = [1])",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_top_level_calls_size_limit() {
+        let code = format!("X = 'x'\n{}rule(x = X)\n", "X = X + X\n".repeat(100));
+        assert_eq!(
+            calls(&code).iter().map(show).collect::<Vec<_>>(),
+            &["rule(x = ?)"]
+        );
+        let code = format!("X = [1]\n{}rule(x = X)\n", "X = [X, X]\n".repeat(100));
+        assert_eq!(
+            calls(&code).iter().map(show).collect::<Vec<_>>(),
+            &["rule(x = ?)"]
+        );
+    }
+}
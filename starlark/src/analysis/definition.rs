@@ -21,8 +21,8 @@ use crate::analysis::bind::scope;
 use crate::analysis::bind::Assigner;
 use crate::analysis::bind::Bind;
 use crate::analysis::bind::Scope;
+use crate::analysis::span_index::SpanIndex;
 use crate::codemap::CodeMap;
-use crate::codemap::Pos;
 use crate::codemap::ResolvedSpan;
 use crate::codemap::Span;
 use crate::codemap::Spanned;
@@ -30,7 +30,6 @@ use crate::syntax::ast::ArgumentP;
 use crate::syntax::ast::AssignP;
 use crate::syntax::ast::AstLiteral;
 use crate::syntax::ast::AstNoPayload;
-use crate::syntax::ast::AstString;
 use crate::syntax::ast::Expr;
 use crate::syntax::ast::ExprP;
 use crate::syntax::ast::Stmt;
@@ -101,64 +100,6 @@ pub(crate) struct DottedDefinition {
     pub(crate) segments: Vec<String>,
 }
 
-/// An inner structure that only holds references and unresolved spans. Used while
-/// finding locations for identifiers. Resolved to a fuller, owned structure
-/// ([`IdentifierDefinition`]) after the entire AST is visited.
-///
-/// This references temporary variables, so cannot be returned to external users directly.
-#[derive(Debug, Clone, Eq, PartialEq)]
-enum TempIdentifierDefinition<'a> {
-    /// The location of the definition of the symbol at the current line/column
-    Location { source: Span, destination: Span },
-    LoadedLocation {
-        source: Span,
-        destination: Span,
-        path: &'a str,
-        name: &'a str,
-    },
-    /// The definition was not found in the current scope but the name of an identifier
-    /// was found at the given position. This should be checked in outer scopes
-    /// to attempt to find the definition.
-    Name { source: Span, name: &'a str },
-    /// None of the accesses matched the position that was provided.
-    NotFound,
-}
-
-/// Reference version of [`DottedDefinitionLocation`]. Analogous to [`IdentifierDefinition`].
-#[derive(Debug, Clone, Eq, PartialEq)]
-struct TempDottedDefinition<'a> {
-    /// The location of the member access that was requested.
-    source: Span,
-    /// The [`IdentifierDefinition`] for the left most component of the expression.
-    ///
-    /// For example, this would be the location of `x` if `source` enclosed `y` in `x.y.z`.
-    root_definition_location: TempIdentifierDefinition<'a>,
-    /// The variable you are pointing at, e.g. `x.y.z` would be `x`.
-    variable: &'a AstString,
-    /// All of the attributes up to the one that `source` includes.
-    ///
-    /// For example. if the `y` in `x.y.z` is the source, this would contain `y`.
-    attributes: &'a [AstString],
-}
-
-#[derive(Debug, Clone, Eq, PartialEq)]
-enum TempDefinition<'a> {
-    Identifier(TempIdentifierDefinition<'a>),
-    Dotted(TempDottedDefinition<'a>),
-}
-
-impl<'a> From<TempIdentifierDefinition<'a>> for TempDefinition<'a> {
-    fn from(def: TempIdentifierDefinition<'a>) -> Self {
-        Self::Identifier(def)
-    }
-}
-
-impl<'a> From<TempDottedDefinition<'a>> for TempDefinition<'a> {
-    fn from(def: TempDottedDefinition<'a>) -> Self {
-        Self::Dotted(def)
-    }
-}
-
 /// The container for both definition locations of a standalone identifier, and
 /// for ones that access members (via '.' syntax)
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -209,11 +150,15 @@ impl Definition {
 /// lists of symbols, etc.
 pub(crate) struct LspModule {
     pub(crate) ast: AstModule,
+    /// The definition of every identifier, string literal and `load` component, indexed
+    /// by their span so looking up a position doesn't have to walk the AST.
+    definitions: SpanIndex<Definition>,
 }
 
 impl LspModule {
     pub(crate) fn new(ast: AstModule) -> Self {
-        Self { ast }
+        let definitions = SpanIndex::new(Self::collect_definitions(&ast));
+        Self { ast, definitions }
     }
 
     /// Attempts to find the location where a symbol is defined in the module.
@@ -223,12 +168,6 @@ impl LspModule {
     /// This method also handles scoping properly (i.e. an access of "foo" in a function
     /// will return location of the parameter "foo", even if there is a global called "foo").
     pub(crate) fn find_definition(&self, line: u32, col: u32) -> Definition {
-        // TODO(nmj): This should probably just store references to all of the AST nodes
-        //            when the LSPModule object is created, and then we can do a much faster
-        //            lookup, especially in cases where a file has not been changed, so the
-        //            LSPModule doesn't need to reparse anything.
-
-        let scope = scope(&self.ast);
         let line_span = match self.ast.codemap.line_span_opt(line as usize) {
             None => {
                 // The document got edited to add new lines, just bail out
@@ -237,182 +176,139 @@ impl LspModule {
             Some(line_span) => line_span,
         };
         let current_pos = std::cmp::min(line_span.begin() + col, line_span.end());
+        self.definitions
+            .find(current_pos)
+            .cloned()
+            .unwrap_or(Definition::Identifier(IdentifierDefinition::NotFound))
+    }
 
-        // Finalize the results after recursing down from and back up to the the top level scope.
-        match Self::find_definition_in_scope(&scope, current_pos) {
-            TempDefinition::Identifier(def) => self
-                .get_definition_location(def, &scope, current_pos)
-                .into(),
-            TempDefinition::Dotted(def) => DottedDefinition {
-                source: self.ast.codemap.resolve_span(def.source),
-                root_definition_location: self.get_definition_location(
-                    def.root_definition_location,
-                    &scope,
-                    current_pos,
-                ),
-                segments: iter::once(def.variable)
-                    .chain(def.attributes)
-                    .map(|s| s.node.to_owned())
-                    .collect(),
-            }
-            .into(),
-        }
+    /// Compute the definition of every position in the module that has one, which are
+    /// the accesses of variables, string literals and the components of `load` statements.
+    fn collect_definitions(ast: &AstModule) -> Vec<(Span, Definition)> {
+        let mut res = Vec::new();
+        Self::collect_scope_definitions(&ast.codemap, &mut vec![&scope(ast)], &mut res);
+        Self::collect_ast_definitions(&ast.codemap, Visit::Stmt(&ast.statement), &mut res);
+        res
     }
 
-    /// Look at the given scope and child scopes to try to find where the identifier
-    /// accessed at Pos is defined.
-    fn find_definition_in_scope<'a>(scope: &'a Scope, pos: Pos) -> TempDefinition<'a> {
-        /// Look for a name in the given scope, with a given source, and return the right
-        /// type of `TempIdentifierDefinition` based on whether / how the variable is bound.
-        fn resolve_get_in_scope<'a>(
-            scope: &'a Scope,
-            name: &'a str,
+    /// Resolve the variable accesses in the innermost of `scopes`, and its inner scopes.
+    fn collect_scope_definitions<'a>(
+        codemap: &CodeMap,
+        scopes: &mut Vec<&'a Scope>,
+        res: &mut Vec<(Span, Definition)>,
+    ) {
+        /// Look for a name in the given scopes, innermost first, and return the right
+        /// type of `IdentifierDefinition` based on whether / how the variable is bound.
+        fn resolve_get(
+            codemap: &CodeMap,
+            scopes: &[&Scope],
+            name: &str,
             source: Span,
-        ) -> TempIdentifierDefinition<'a> {
-            match scope.bound.get(name) {
+        ) -> IdentifierDefinition {
+            let source_resolved = codemap.resolve_span(source);
+            match scopes.iter().rev().find_map(|scope| scope.bound.get(name)) {
                 Some((Assigner::Load { path, name }, span)) => {
-                    TempIdentifierDefinition::LoadedLocation {
-                        source,
-                        destination: *span,
-                        path,
-                        name: name.as_str(),
+                    IdentifierDefinition::LoadedLocation {
+                        source: source_resolved,
+                        destination: codemap.resolve_span(*span),
+                        path: path.node.clone(),
+                        name: name.node.clone(),
                     }
                 }
-                Some((_, span)) => TempIdentifierDefinition::Location {
-                    source,
-                    destination: *span,
+                Some((_, span)) => IdentifierDefinition::Location {
+                    source: source_resolved,
+                    destination: codemap.resolve_span(*span),
+                },
+                // The symbol is not bound in any scope, so should be resolved as a global.
+                None => IdentifierDefinition::Unresolved {
+                    source: source_resolved,
+                    name: name.to_owned(),
                 },
-                // We know the symbol name, but it might only be available in
-                // an outer scope.
-                None => TempIdentifierDefinition::Name { source, name },
             }
         }
 
+        let scope = *scopes.last().unwrap();
         for bind in &scope.inner {
             match bind {
-                Bind::Get(g) if g.span.contains(pos) => {
-                    return resolve_get_in_scope(scope, g.node.as_str(), g.span).into();
+                Bind::Get(g) => {
+                    res.push((g.span, resolve_get(codemap, scopes, &g.node, g.span).into()))
                 }
                 Bind::Scope(inner_scope) => {
-                    match Self::find_definition_in_scope(inner_scope, pos) {
-                        TempDefinition::Identifier(TempIdentifierDefinition::Name {
-                            source,
-                            name,
-                        }) => {
-                            return resolve_get_in_scope(scope, name, source).into();
-                        }
-                        TempDefinition::Identifier(TempIdentifierDefinition::NotFound) => {}
-                        TempDefinition::Dotted(TempDottedDefinition {
-                            source,
-                            root_definition_location:
-                                TempIdentifierDefinition::Name {
-                                    source: root_source,
-                                    name: root_name,
-                                },
-                            variable,
-                            attributes,
-                        }) => {
-                            return TempDefinition::Dotted(TempDottedDefinition {
-                                source,
-                                root_definition_location: resolve_get_in_scope(
-                                    scope,
-                                    root_name,
-                                    root_source,
-                                ),
-                                variable,
-                                attributes,
-                            });
-                        }
-                        x @ TempDefinition::Identifier(TempIdentifierDefinition::Location {
-                            ..
-                        })
-                        | x @ TempDefinition::Identifier(
-                            TempIdentifierDefinition::LoadedLocation { .. },
-                        )
-                        | x @ TempDefinition::Dotted(_) => {
-                            return x;
-                        }
-                    }
+                    scopes.push(inner_scope);
+                    Self::collect_scope_definitions(codemap, scopes, res);
+                    scopes.pop();
                 }
                 Bind::GetDotted(dotted) => {
-                    if let Some((idx, source)) = dotted.contains(pos) {
-                        let root_identifier = &dotted.variable;
-                        let root_definition_location = resolve_get_in_scope(
-                            scope,
-                            root_identifier.node.as_str(),
-                            root_identifier.span,
-                        );
-                        // If someone clicks on the "root" identifier, just treat it as a "get"
-                        match idx {
-                            None => return root_definition_location.into(),
-                            Some(idx) => {
-                                return TempDottedDefinition {
-                                    source,
-                                    root_definition_location,
-                                    variable: &dotted.variable,
-                                    attributes: &dotted.attributes[0..idx + 1],
-                                }
-                                .into();
-                            }
-                        }
+                    let root_definition_location = resolve_get(
+                        codemap,
+                        scopes,
+                        dotted.variable.node.as_str(),
+                        dotted.variable.span,
+                    );
+                    // If someone clicks on the "root" identifier, just treat it as a "get"
+                    res.push((
+                        dotted.variable.span,
+                        root_definition_location.clone().into(),
+                    ));
+                    for (idx, attribute) in dotted.attributes.iter().enumerate() {
+                        let definition = DottedDefinition {
+                            source: codemap.resolve_span(attribute.span),
+                            root_definition_location: root_definition_location.clone(),
+                            segments: iter::once(&dotted.variable)
+                                .chain(&dotted.attributes[0..idx + 1])
+                                .map(|s| s.node.to_owned())
+                                .collect(),
+                        };
+                        res.push((attribute.span, definition.into()));
                     }
                 }
-                // For everything else, just ignore it. Note that the `Get` is ignored
-                // because we already checked the pos above.
-                Bind::Set(_, _) | Bind::Flow | Bind::Get(_) => {}
+                Bind::Set(_, _) | Bind::Flow => {}
             }
         }
-        TempIdentifierDefinition::NotFound.into()
     }
 
-    /// Converts a `TempIdentifierDefinition` to an `IdentifierDefinition`, resolving spans
-    /// against the current AST, owning strings, etc.
-    fn get_definition_location(
-        &self,
-        definition: TempIdentifierDefinition,
-        scope: &Scope,
-        current_pos: Pos,
-    ) -> IdentifierDefinition {
-        match definition {
-            TempIdentifierDefinition::Location {
-                source,
-                destination,
-            } => IdentifierDefinition::Location {
-                source: self.ast.codemap.resolve_span(source),
-                destination: self.ast.codemap.resolve_span(destination),
-            },
-            TempIdentifierDefinition::Name { source, name } => match scope.bound.get(name) {
-                None => IdentifierDefinition::Unresolved {
-                    source: self.ast.codemap.resolve_span(source),
-                    name: name.to_owned(),
-                },
-                Some((Assigner::Load { path, name }, span)) => {
-                    IdentifierDefinition::LoadedLocation {
-                        source: self.ast.codemap.resolve_span(source),
-                        destination: self.ast.codemap.resolve_span(*span),
-                        path: path.node.clone(),
-                        name: name.node.clone(),
+    /// Find the string literals and `load` components in the AST, which are not exposed
+    /// as accesses by bind.
+    fn collect_ast_definitions(
+        codemap: &CodeMap,
+        node: Visit<AstNoPayload>,
+        res: &mut Vec<(Span, Definition)>,
+    ) {
+        match node {
+            Visit::Expr(Spanned {
+                node: Expr::Literal(AstLiteral::String(s)),
+                ..
+            }) => {
+                let definition = IdentifierDefinition::StringLiteral {
+                    source: codemap.resolve_span(s.span),
+                    literal: s.node.to_owned(),
+                };
+                res.push((s.span, definition.into()));
+            }
+            Visit::Stmt(Spanned {
+                node: Stmt::Load(load),
+                ..
+            }) => {
+                let definition = IdentifierDefinition::LoadPath {
+                    source: codemap.resolve_span(load.module.span),
+                    path: load.module.node.to_owned(),
+                };
+                res.push((load.module.span, definition.into()));
+                for (assign, name) in &load.args {
+                    let definition: Definition = IdentifierDefinition::LoadedLocation {
+                        source: codemap.resolve_span(name.span),
+                        destination: codemap.resolve_span(name.span),
+                        path: load.module.node.to_owned(),
+                        name: name.node.to_owned(),
+                    }
+                    .into();
+                    if assign.span != name.span {
+                        res.push((assign.span, definition.clone()));
                     }
+                    res.push((name.span, definition));
                 }
-                Some((_, span)) => IdentifierDefinition::Location {
-                    source: self.ast.codemap.resolve_span(source),
-                    destination: self.ast.codemap.resolve_span(*span),
-                },
-            },
-            // If we could not find the symbol, see if the current position is within
-            // a load statement (these are not exposed as Get/Set in bind).
-            TempIdentifierDefinition::NotFound => self.find_definition_from_ast(current_pos),
-            TempIdentifierDefinition::LoadedLocation {
-                source,
-                destination,
-                path,
-                name,
-            } => IdentifierDefinition::LoadedLocation {
-                source: self.ast.codemap.resolve_span(source),
-                destination: self.ast.codemap.resolve_span(destination),
-                path: path.to_owned(),
-                name: name.to_owned(),
-            },
+            }
+            v => v.visit_children(|node| Self::collect_ast_definitions(codemap, node, res)),
         }
     }
 
@@ -512,64 +408,6 @@ impl LspModule {
                 (None, None) => None,
             })
     }
-
-    fn find_definition_from_ast(&self, pos: Pos) -> IdentifierDefinition {
-        fn visit_node(
-            codemap: &CodeMap,
-            pos: Pos,
-            ret: &mut Option<IdentifierDefinition>,
-            node: Visit<AstNoPayload>,
-        ) {
-            if ret.is_some() {
-                return;
-            }
-            match node {
-                Visit::Expr(Spanned {
-                    node: Expr::Literal(AstLiteral::String(s)),
-                    ..
-                }) if s.span.contains(pos) => {
-                    *ret = Some(IdentifierDefinition::StringLiteral {
-                        source: codemap.resolve_span(s.span),
-                        literal: s.node.to_owned(),
-                    });
-                }
-                Visit::Stmt(Spanned {
-                    node: Stmt::Load(load),
-                    ..
-                }) => {
-                    *ret = if load.module.span.contains(pos) {
-                        Some(IdentifierDefinition::LoadPath {
-                            source: codemap.resolve_span(load.module.span),
-                            path: load.module.node.to_owned(),
-                        })
-                    } else {
-                        load.args.iter().find_map(|(assign, name)| {
-                            if assign.span.contains(pos) || name.span.contains(pos) {
-                                Some(IdentifierDefinition::LoadedLocation {
-                                    source: codemap.resolve_span(name.span),
-                                    destination: codemap.resolve_span(name.span),
-                                    path: load.module.node.to_owned(),
-                                    name: name.node.to_owned(),
-                                })
-                            } else {
-                                None
-                            }
-                        })
-                    }
-                }
-                v => v.visit_children(|node| visit_node(codemap, pos, ret, node)),
-            }
-        }
-
-        let mut ret = None;
-        visit_node(
-            &self.ast.codemap,
-            pos,
-            &mut ret,
-            Visit::Stmt(&self.ast.statement),
-        );
-        ret.unwrap_or(IdentifierDefinition::NotFound)
-    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,155 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::analysis::bind;
+use crate::analysis::bind::Bind;
+use crate::analysis::bind::Scope;
+use crate::codemap::FileSpan;
+use crate::codemap::Span;
+use crate::syntax::AstLoadedSymbol;
+use crate::syntax::AstModule;
+
+impl AstModule {
+    /// The `load` arguments importing `symbol` from `module`.
+    fn loads_of_symbol<'a>(
+        &'a self,
+        module: &'a str,
+        symbol: &'a str,
+    ) -> impl Iterator<Item = AstLoadedSymbol<'a>> + 'a {
+        self.loads()
+            .into_iter()
+            .filter(move |x| x.module_id == module)
+            .flat_map(|x| x.loaded)
+            .filter(move |x| x.symbol == symbol)
+    }
+
+    /// Find every reference in this module to `symbol` exported by `module`, where `module`
+    /// is the path as written in the `load` statement. That includes the symbol name in the
+    /// `load`, any local alias it is bound to, and all uses of that local name that are not
+    /// shadowed by an inner binding.
+    pub fn find_loaded_symbol_references(&self, module: &str, symbol: &str) -> Vec<FileSpan> {
+        let scope = bind::scope(self);
+        let mut res = Vec::new();
+        for x in self.loads_of_symbol(module, symbol) {
+            res.push(x.symbol_span.span);
+            if x.local_span != x.symbol_span {
+                res.push(x.local_span.span);
+            }
+            uses(&scope, x.local, &mut res);
+        }
+        res.sort_by_key(|span| span.begin());
+        res.dedup();
+        res.into_iter().map(|span| self.file_span(span)).collect()
+    }
+
+    /// The edits required in this module when `symbol` exported by `module` is renamed to
+    /// `new_name`, as the span to replace and its replacement. Where the symbol is loaded
+    /// under an alias only the `load` itself changes, otherwise the uses are renamed too.
+    pub fn rename_loaded_symbol(
+        &self,
+        module: &str,
+        symbol: &str,
+        new_name: &str,
+    ) -> Vec<(FileSpan, String)> {
+        let scope = bind::scope(self);
+        let mut res = Vec::new();
+        for x in self.loads_of_symbol(module, symbol) {
+            res.push((x.symbol_span.span, format!("\"{}\"", new_name)));
+            if x.local_span == x.symbol_span {
+                let mut spans = Vec::new();
+                uses(&scope, x.local, &mut spans);
+                res.extend(spans.into_iter().map(|span| (span, new_name.to_owned())));
+            }
+        }
+        res.sort_by_key(|(span, _)| span.begin());
+        res.dedup();
+        res.into_iter()
+            .map(|(span, text)| (self.file_span(span), text))
+            .collect()
+    }
+}
+
+/// The places `name` is read in `scope`, including inner scopes that don't rebind it.
+fn uses(scope: &Scope, name: &str, res: &mut Vec<Span>) {
+    for x in &scope.inner {
+        match x {
+            Bind::Get(x) if x.node == name => res.push(x.span),
+            Bind::GetDotted(x) if x.variable.node == name => res.push(x.variable.span),
+            Bind::Scope(inner) if !inner.bound.contains_key(name) => uses(inner, name, res),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gazebo::prelude::*;
+
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[test]
+    fn test_find_loaded_symbol_references() {
+        let modu = module(
+            r#"
+load("x.bzl", "original", alias = "original")
+load("y.bzl", other = "original")
+def f(alias):
+    return alias
+def g():
+    return alias.field + original()
+"#,
+        );
+        assert_eq!(
+            modu.find_loaded_symbol_references("x.bzl", "original")
+                .map(|x| x.to_string()),
+            &[
+                "X:2:15-25",
+                "X:2:27-32",
+                "X:2:35-45",
+                "X:7:12-17",
+                "X:7:26-34",
+            ]
+        );
+        assert!(modu
+            .find_loaded_symbol_references("x.bzl", "missing")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rename_loaded_symbol() {
+        let modu = module(
+            r#"
+load("x.bzl", "original", alias = "original")
+original(alias)
+"#,
+        );
+        assert_eq!(
+            modu.rename_loaded_symbol("x.bzl", "original", "renamed")
+                .map(|(span, text)| format!("{} {}", span, text)),
+            &[
+                "X:2:15-25 \"renamed\"",
+                "X:2:35-45 \"renamed\"",
+                "X:3:1-9 renamed"
+            ]
+        );
+    }
+}
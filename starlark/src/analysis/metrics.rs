@@ -0,0 +1,169 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cmp;
+
+use serde::Serialize;
+
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::BinOp;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::Stmt;
+use crate::syntax::uniplate::Visit;
+use crate::syntax::AstModule;
+
+/// Statistics about the size and complexity of a module, as produced by
+/// [`AstModule::metrics`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModuleMetrics {
+    /// Number of lines in the file.
+    pub lines: usize,
+    /// Number of `def` statements, including nested ones.
+    pub functions: usize,
+    /// The highest cyclomatic complexity of any function, or of the top-level statements.
+    pub cyclomatic_complexity: usize,
+    /// The deepest nesting of `def`, `if` and `for` blocks, with `elif` not adding a level.
+    pub max_nesting: usize,
+    /// Number of `load` statements.
+    pub loads: usize,
+    /// Number of symbols exported, as per [`AstModule::exported_symbols`].
+    pub exported_symbols: usize,
+}
+
+#[derive(Default)]
+struct Walker {
+    functions: usize,
+    cyclomatic_complexity: usize,
+    max_nesting: usize,
+}
+
+impl Walker {
+    /// Walk `x`, found at `depth` blocks deep, adding its decision points to `complexity`.
+    fn stmt(&mut self, x: &AstStmt, depth: usize, complexity: &mut usize) {
+        match &**x {
+            Stmt::Def(_) => {
+                self.functions += 1;
+                self.max_nesting = cmp::max(self.max_nesting, depth + 1);
+                let mut inner = 1;
+                x.visit_children(|x| match x {
+                    Visit::Stmt(x) => self.stmt(x, depth + 1, &mut inner),
+                    // Default values are evaluated in the enclosing scope
+                    Visit::Expr(x) => expr(x, complexity),
+                });
+                self.cyclomatic_complexity = cmp::max(self.cyclomatic_complexity, inner);
+            }
+            Stmt::IfElse(cond, then_else)
+                if matches!(&then_else.1.node, Stmt::If(..) | Stmt::IfElse(..)) =>
+            {
+                // An `elif`, which we don't treat as nested inside the `if`
+                *complexity += 1;
+                self.max_nesting = cmp::max(self.max_nesting, depth + 1);
+                expr(cond, complexity);
+                self.stmt(&then_else.0, depth + 1, complexity);
+                self.stmt(&then_else.1, depth, complexity);
+            }
+            Stmt::If(..) | Stmt::IfElse(..) | Stmt::For(..) => {
+                *complexity += 1;
+                self.max_nesting = cmp::max(self.max_nesting, depth + 1);
+                x.visit_children(|x| match x {
+                    Visit::Stmt(x) => self.stmt(x, depth + 1, complexity),
+                    Visit::Expr(x) => expr(x, complexity),
+                });
+            }
+            _ => x.visit_children(|x| match x {
+                Visit::Stmt(x) => self.stmt(x, depth, complexity),
+                Visit::Expr(x) => expr(x, complexity),
+            }),
+        }
+    }
+}
+
+/// Add the decision points within an expression to `complexity`.
+fn expr(x: &AstExpr, complexity: &mut usize) {
+    match &**x {
+        Expr::Op(_, BinOp::And | BinOp::Or, _) | Expr::If(_) => *complexity += 1,
+        Expr::ListComprehension(_, _, clauses) | Expr::DictComprehension(_, _, clauses) => {
+            *complexity += 1 + clauses.len()
+        }
+        _ => {}
+    }
+    x.visit_expr(|x| expr(x, complexity));
+}
+
+impl AstModule {
+    /// Compute statistics about the size and complexity of this module, useful for
+    /// tracking how a codebase evolves over time.
+    pub fn metrics(&self) -> ModuleMetrics {
+        let mut walker = Walker::default();
+        let mut top_level = 1;
+        walker.stmt(&self.statement, 0, &mut top_level);
+        ModuleMetrics {
+            lines: self.codemap.source().lines().count(),
+            functions: walker.functions,
+            cyclomatic_complexity: cmp::max(walker.cyclomatic_complexity, top_level),
+            max_nesting: walker.max_nesting,
+            loads: self.loads().len(),
+            exported_symbols: self.exported_symbols().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[test]
+    fn test_metrics() {
+        let modu = module(
+            r#"
+load("a.bzl", "a")
+load("b.bzl", "b", "c")
+def f(x):
+    for y in x:
+        if y and a:
+            pass
+        elif y:
+            pass
+        elif b:
+            pass
+    return [z for z in x if z]
+def _g():
+    def h():
+        pass
+X = 1 if a else 2
+"#,
+        );
+        assert_eq!(
+            modu.metrics(),
+            ModuleMetrics {
+                lines: 16,
+                functions: 3,
+                cyclomatic_complexity: 8,
+                max_nesting: 3,
+                loads: 2,
+                exported_symbols: 2,
+            }
+        );
+        assert_eq!(module("").metrics().cyclomatic_complexity, 1);
+    }
+}
@@ -17,6 +17,11 @@
 
 use std::collections::HashSet;
 
+pub use constant::ConstArgument;
+pub use constant::ConstExpr;
+pub use constant::ConstValue;
+pub use constant::TopLevelCall;
+pub use metrics::ModuleMetrics;
 pub use types::EvalMessage;
 pub use types::EvalSeverity;
 pub use types::Lint;
@@ -25,15 +30,22 @@ use crate::analysis::types::LintT;
 use crate::syntax::AstModule;
 
 mod bind;
+mod constant;
+#[cfg(feature = "lsp")]
 pub(crate) mod definition;
 mod dubious;
 mod exported;
 mod find_call_name;
 mod flow;
 mod incompatible;
+mod loaded_symbols;
+mod metrics;
+mod mutation;
 mod names;
 mod performance;
-mod types;
+#[cfg(feature = "lsp")]
+mod span_index;
+pub(crate) mod types;
 mod underscore;
 
 impl AstModule {
@@ -48,6 +60,7 @@ impl AstModule {
         res.extend(names::lint(self, globals).into_iter().map(LintT::erase));
         res.extend(underscore::lint(self).into_iter().map(LintT::erase));
         res.extend(performance::lint(self).into_iter().map(LintT::erase));
+        res.extend(mutation::lint(self).into_iter().map(LintT::erase));
         res
     }
 }
@@ -0,0 +1,402 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Mutation of values which will be frozen by the time the mutation runs.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use gazebo::variants::VariantName;
+use thiserror::Error;
+
+use crate::analysis::types::LintT;
+use crate::analysis::types::LintWarning;
+use crate::codemap::CodeMap;
+use crate::codemap::Span;
+use crate::syntax::ast::Assign;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstNoPayload;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::DefP;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::Parameter;
+use crate::syntax::ast::Stmt;
+use crate::syntax::uniplate::Visit;
+use crate::syntax::AstModule;
+
+#[derive(Error, Debug, VariantName)]
+pub(crate) enum MutationWarning {
+    #[error(
+        "`{0}` in function `{1}` mutates module-level `{2}`, which fails once the module is frozen. Create the value inside `{1}` and return it, or pass `{2}` as an argument."
+    )]
+    ModuleLevelMutation(String, String, String),
+
+    #[error(
+        "Function `{0}` stores state in module-level dict `{1}`, which fails once the module is frozen, so it cannot be used as a cache. Compute the entries at module level, or pass the dict to `{0}` as an argument."
+    )]
+    ModuleLevelState(String, String),
+
+    #[error(
+        "`{0}` mutates the default value of parameter `{1}`, which is shared by all calls to `{2}` and fails once the module is frozen. Default to `None` and create a new value inside `{2}`."
+    )]
+    MutatedDefault(String, String, String),
+}
+
+impl LintWarning for MutationWarning {
+    fn is_serious(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mutable {
+    List,
+    Dict,
+}
+
+/// Methods of `list` and `dict` which mutate the receiver.
+const MUTATING_METHODS: &[&str] = &[
+    "append",
+    "clear",
+    "extend",
+    "insert",
+    "pop",
+    "popitem",
+    "remove",
+    "setdefault",
+    "update",
+];
+
+/// Whether the expression certainly creates a new mutable value.
+fn mutable_value(x: &AstExpr) -> Option<Mutable> {
+    match &**x {
+        Expr::List(_) | Expr::ListComprehension(..) => Some(Mutable::List),
+        Expr::Dict(_) | Expr::DictComprehension(..) => Some(Mutable::Dict),
+        Expr::Call(f, _) => match &***f {
+            Expr::Identifier(f, _) if f.node == "list" => Some(Mutable::List),
+            Expr::Identifier(f, _) if f.node == "dict" => Some(Mutable::Dict),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn top_level_stmts(module: &AstModule) -> Vec<&AstStmt> {
+    match &*module.statement {
+        Stmt::Statements(xs) => xs.iter().collect(),
+        _ => vec![&module.statement],
+    }
+}
+
+/// Module-level variables assigned a mutable value.
+fn mutable_globals(module: &AstModule) -> HashMap<&str, Mutable> {
+    let mut res = HashMap::new();
+    for x in top_level_stmts(module) {
+        if let Stmt::Assign(lhs, ty_rhs) = &**x {
+            if let (Assign::Identifier(name), Some(kind)) = (&**lhs, mutable_value(&ty_rhs.1)) {
+                res.insert(name.0.as_str(), kind);
+            }
+        }
+    }
+    res
+}
+
+/// Whether `name` is only ever called directly by module-level statements, so it
+/// runs while the module is evaluated, before anything is frozen. Public functions
+/// must be called that way at least once, as otherwise they are presumably there to
+/// be loaded by other modules, which can only call them once the module is frozen.
+fn only_called_during_evaluation(module: &AstModule, name: &str) -> bool {
+    #[derive(Default)]
+    struct Uses {
+        called_at_top_level: bool,
+        used_elsewhere: bool,
+    }
+
+    fn expr(x: &AstExpr, name: &str, top: bool, res: &mut Uses) {
+        match &**x {
+            Expr::Call(f, args)
+                if top && matches!(&***f, Expr::Identifier(f, _) if f.node == name) =>
+            {
+                res.called_at_top_level = true;
+                args.iter().for_each(|x| expr(x.expr(), name, top, res));
+                return;
+            }
+            Expr::Identifier(x, _) if x.node == name => res.used_elsewhere = true,
+            Expr::Lambda(_) => {
+                x.visit_expr(|x| expr(x, name, false, res));
+                return;
+            }
+            _ => {}
+        }
+        x.visit_expr(|x| expr(x, name, top, res));
+    }
+
+    fn stmt(x: &AstStmt, name: &str, top: bool, res: &mut Uses) {
+        let top = top && !matches!(&**x, Stmt::Def(_));
+        x.visit_children(|x| match x {
+            Visit::Stmt(x) => stmt(x, name, top, res),
+            Visit::Expr(x) => expr(x, name, top, res),
+        });
+    }
+
+    let mut uses = Uses::default();
+    stmt(&module.statement, name, true, &mut uses);
+    !uses.used_elsewhere && (uses.called_at_top_level || name.starts_with('_'))
+}
+
+fn parameter_name(x: &Parameter) -> Option<&str> {
+    match x {
+        Parameter::Normal(x, _)
+        | Parameter::WithDefaultValue(x, _, _)
+        | Parameter::Args(x, _)
+        | Parameter::KwArgs(x, _) => Some(&x.0),
+        Parameter::NoArgs => None,
+    }
+}
+
+/// Names bound anywhere inside a function body, including in nested functions.
+fn assigned_names<'a>(x: &'a AstStmt, res: &mut HashSet<&'a str>) {
+    match &**x {
+        Stmt::Assign(lhs, _) | Stmt::AssignModify(lhs, _, _) | Stmt::For(lhs, _) => lhs
+            .visit_lvalue(|x| {
+                res.insert(&x.0);
+            }),
+        Stmt::Def(def) => {
+            res.insert(&def.name.0);
+            res.extend(def.params.iter().filter_map(|x| parameter_name(x)));
+        }
+        _ => {}
+    }
+    x.visit_stmt(|x| assigned_names(x, res));
+}
+
+/// A mutation of the variable `name`, by item assignment or by calling a mutating method.
+struct Mutation<'a> {
+    name: &'a str,
+    /// Stores an entry, as opposed to e.g. appending or removing.
+    stores: bool,
+    span: Span,
+    code: String,
+}
+
+fn mutations<'a>(x: &'a AstStmt, res: &mut Vec<Mutation<'a>>) {
+    fn expr<'a>(x: &'a AstExpr, res: &mut Vec<Mutation<'a>>) {
+        if let Expr::Call(f, _) = &**x {
+            if let Expr::Dot(object, method) = &***f {
+                if let Expr::Identifier(name, _) = &***object {
+                    if MUTATING_METHODS.contains(&method.node.as_str()) {
+                        res.push(Mutation {
+                            name: &name.node,
+                            stores: method.node == "setdefault" || method.node == "update",
+                            span: x.span,
+                            code: x.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        x.visit_expr(|x| expr(x, res));
+    }
+
+    match &**x {
+        Stmt::Assign(lhs, _) | Stmt::AssignModify(lhs, _, _) => {
+            if let Assign::ArrayIndirection(object_index) = &**lhs {
+                if let Expr::Identifier(name, _) = &*object_index.0 {
+                    res.push(Mutation {
+                        name: &name.node,
+                        stores: true,
+                        span: x.span,
+                        code: x.to_string().trim().to_owned(),
+                    });
+                }
+            }
+        }
+        _ => {}
+    }
+    x.visit_children(|x| match x {
+        Visit::Stmt(x) => mutations(x, res),
+        Visit::Expr(x) => expr(x, res),
+    });
+}
+
+fn check_def(
+    codemap: &CodeMap,
+    def: &DefP<AstNoPayload>,
+    globals: &HashMap<&str, Mutable>,
+    check_globals: bool,
+    res: &mut Vec<LintT<MutationWarning>>,
+) {
+    let mut locals = HashSet::new();
+    assigned_names(&def.body, &mut locals);
+    let mut mutable_defaults = HashSet::new();
+    for param in &def.params {
+        if let Parameter::WithDefaultValue(name, _, default) = &**param {
+            if mutable_value(default).is_some() && !locals.contains(name.0.as_str()) {
+                mutable_defaults.insert(name.0.as_str());
+            }
+        }
+    }
+    locals.extend(def.params.iter().filter_map(|x| parameter_name(x)));
+
+    let function = &def.name.0;
+    let mut stateful = HashSet::new();
+    let mut found = Vec::new();
+    mutations(&def.body, &mut found);
+    for m in found {
+        if mutable_defaults.contains(m.name) {
+            res.push(LintT::new(
+                codemap,
+                m.span,
+                MutationWarning::MutatedDefault(m.code, m.name.to_owned(), function.clone()),
+            ));
+        } else if !check_globals || locals.contains(m.name) {
+            continue;
+        } else if let Some(kind) = globals.get(m.name) {
+            if *kind == Mutable::Dict && m.stores {
+                // One warning per dict is enough to explain the problem.
+                if stateful.insert(m.name) {
+                    res.push(LintT::new(
+                        codemap,
+                        m.span,
+                        MutationWarning::ModuleLevelState(function.clone(), m.name.to_owned()),
+                    ));
+                }
+            } else {
+                res.push(LintT::new(
+                    codemap,
+                    m.span,
+                    MutationWarning::ModuleLevelMutation(
+                        m.code,
+                        function.clone(),
+                        m.name.to_owned(),
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+pub(crate) fn lint(module: &AstModule) -> Vec<LintT<MutationWarning>> {
+    let mut res = Vec::new();
+    let globals = mutable_globals(module);
+    for x in top_level_stmts(module) {
+        if let Stmt::Def(def) = &**x {
+            let check_globals = !only_called_during_evaluation(module, &def.name.0);
+            check_def(&module.codemap, def, &globals, check_globals, &mut res);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use gazebo::prelude::*;
+
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("bad.bzl", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[test]
+    fn test_lint_module_level_mutation() {
+        let m = module(
+            r#"
+targets = []
+config = {"x": 1}
+names = list()
+def add(t):
+    targets.append(t)
+    names.extend([t])
+    config.pop("x")
+def local(targets):
+    targets.append(1)
+    names = []
+    names.append(2)
+def _register(t):
+    targets.append(t)
+_register(1)
+_register(2)
+def _callback(t):
+    targets.append(t)
+rule(impl = _callback)
+def reads():
+    return targets[0] + config["x"]
+def register_all(ts):
+    targets.extend(ts)
+register_all([3, 4])
+"#,
+        );
+        assert_eq!(
+            lint(&m).map(|x| x.to_string()),
+            &[
+                "bad.bzl:6:5-22: `targets.append(t)` in function `add` mutates module-level `targets`, which fails once the module is frozen. Create the value inside `add` and return it, or pass `targets` as an argument.",
+                "bad.bzl:7:5-22: `names.extend([t])` in function `add` mutates module-level `names`, which fails once the module is frozen. Create the value inside `add` and return it, or pass `names` as an argument.",
+                "bad.bzl:8:5-20: `config.pop(\"x\")` in function `add` mutates module-level `config`, which fails once the module is frozen. Create the value inside `add` and return it, or pass `config` as an argument.",
+                "bad.bzl:18:5-22: `targets.append(t)` in function `_callback` mutates module-level `targets`, which fails once the module is frozen. Create the value inside `_callback` and return it, or pass `targets` as an argument.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_module_level_state() {
+        let m = module(
+            r#"
+_cache = {}
+def get(k):
+    if k not in _cache:
+        _cache[k] = compute(k)
+    _cache.setdefault(k, 1)
+    return _cache[k]
+def count(k):
+    _counts = {}
+    _counts[k] = 1
+"#,
+        );
+        assert_eq!(
+            lint(&m).map(|x| x.to_string()),
+            &[
+                "bad.bzl:5:9-31: Function `get` stores state in module-level dict `_cache`, which fails once the module is frozen, so it cannot be used as a cache. Compute the entries at module level, or pass the dict to `get` as an argument.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_mutated_default() {
+        let m = module(
+            r#"
+def f(x, xs = [], d = {}, ys = []):
+    xs.append(x)
+    d[x] = 1
+    ys = list(ys)
+    ys.append(x)
+    return xs
+def g(xs = None):
+    xs = xs or []
+    xs.append(1)
+"#,
+        );
+        assert_eq!(
+            lint(&m).map(|x| x.to_string()),
+            &[
+                "bad.bzl:3:5-17: `xs.append(x)` mutates the default value of parameter `xs`, which is shared by all calls to `f` and fails once the module is frozen. Default to `None` and create a new value inside `f`.",
+                "bad.bzl:4:5-13: `d[x] = 1` mutates the default value of parameter `d`, which is shared by all calls to `f` and fails once the module is frozen. Default to `None` and create a new value inside `f`.",
+            ]
+        );
+    }
+}
@@ -22,8 +22,13 @@ use crate::analysis::types::LintT;
 use crate::analysis::types::LintWarning;
 use crate::codemap::CodeMap;
 use crate::syntax::ast::Argument;
+use crate::syntax::ast::Assign;
+use crate::syntax::ast::AssignOp;
 use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::BinOp;
 use crate::syntax::ast::Expr;
+use crate::syntax::ast::Stmt;
 use crate::syntax::AstModule;
 
 #[derive(Error, Debug, VariantName)]
@@ -38,6 +43,14 @@ pub(crate) enum Performance {
 
     #[error("`{0}` allocates a new {1} for the results. Prefer using a for-loop.")]
     InefficientBoolCheck(String, String),
+
+    #[error("`{0}` copies the whole list on every iteration of the loop. Prefer `{1}`.")]
+    ListConcatInLoop(String, String),
+
+    #[error(
+        "`{0}` allocates a temporary list on every iteration of the nested loop. Prefer `{1}`."
+    )]
+    ListAddAssignInNestedLoop(String, String),
 }
 
 impl LintWarning for Performance {
@@ -113,9 +126,77 @@ fn check_call_expr(module: &AstModule, res: &mut Vec<LintT<Performance>>) {
         .visit_expr(|x| check(&module.codemap, x, res));
 }
 
+/// The in-place equivalent of adding `items` to the list `name`, if `items` is a
+/// list literal or comprehension, so adding it is certainly a list operation.
+fn in_place_list_add(name: &str, items: &AstExpr) -> Option<String> {
+    match &**items {
+        Expr::List(xs) if xs.len() == 1 => Some(format!("{}.append({})", name, xs[0].node)),
+        Expr::List(_) | Expr::ListComprehension(..) => {
+            Some(format!("{}.extend({})", name, items.node))
+        }
+        _ => None,
+    }
+}
+
+fn check_list_concat(module: &AstModule, res: &mut Vec<LintT<Performance>>) {
+    fn check(codemap: &CodeMap, x: &AstStmt, loop_depth: usize, res: &mut Vec<LintT<Performance>>) {
+        match &**x {
+            Stmt::For(_, over_body) => {
+                check(codemap, &over_body.1, loop_depth + 1, res);
+                return;
+            }
+            // A function body runs once per call, not once per iteration of a loop around
+            // its definition.
+            Stmt::Def(def) => {
+                check(codemap, &def.body, 0, res);
+                return;
+            }
+            // `xs = xs + [y]` copies `xs` each time, so the loop is quadratic.
+            Stmt::Assign(lhs, ty_rhs) if loop_depth > 0 => {
+                if let (Assign::Identifier(name), Expr::Op(l, BinOp::Add, r)) =
+                    (&**lhs, &ty_rhs.1.node)
+                {
+                    if matches!(&***l, Expr::Identifier(l, _) if l.node == name.node.0) {
+                        if let Some(suggestion) = in_place_list_add(&name.node.0, r) {
+                            res.push(LintT::new(
+                                codemap,
+                                x.span,
+                                Performance::ListConcatInLoop(
+                                    x.to_string().trim().to_owned(),
+                                    suggestion,
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+            // `xs += [y]` extends `xs` in place, but still builds `[y]` first, which adds
+            // up in the innermost loop.
+            Stmt::AssignModify(lhs, AssignOp::Add, rhs) if loop_depth > 1 => {
+                if let Assign::Identifier(name) = &**lhs {
+                    if let Some(suggestion) = in_place_list_add(&name.node.0, rhs) {
+                        res.push(LintT::new(
+                            codemap,
+                            x.span,
+                            Performance::ListAddAssignInNestedLoop(
+                                x.to_string().trim().to_owned(),
+                                suggestion,
+                            ),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+        x.visit_stmt(|x| check(codemap, x, loop_depth, res));
+    }
+    check(&module.codemap, &module.statement, 0, res);
+}
+
 pub(crate) fn lint(module: &AstModule) -> Vec<LintT<Performance>> {
     let mut res = Vec::new();
     check_call_expr(module, &mut res);
+    check_list_concat(module, &mut res);
     res
 }
 
@@ -179,4 +260,44 @@ def foo(items):
             ]
         );
     }
+
+    #[test]
+    fn test_lint_list_concat_in_loop() {
+        let mut res = Vec::new();
+        check_list_concat(
+            &module(
+                r#"
+xs = []
+xs = xs + [1]
+def foo(items, groups):
+    ys = []
+    zs = []
+    for item in items:
+        ys = ys + [item]
+        zs = zs + [x for x in item]
+        total = total + item
+        ys += [item]
+        def bar(ws):
+            ws = ws + [1]
+            return ws
+    for group in groups:
+        for item in group:
+            ys += [item]
+            zs += [item, item]
+            ys += item
+    return ys + zs
+"#,
+            ),
+            &mut res,
+        );
+        assert_eq!(
+            res.map(|x| x.to_string()),
+            &[
+                "bad.bzl:8:9-25: `ys = (ys + [item])` copies the whole list on every iteration of the loop. Prefer `ys.append(item)`.",
+                "bad.bzl:9:9-36: `zs = (zs + [x for x in item])` copies the whole list on every iteration of the loop. Prefer `zs.extend([x for x in item])`.",
+                "bad.bzl:17:13-25: `ys += [item]` allocates a temporary list on every iteration of the nested loop. Prefer `ys.append(item)`.",
+                "bad.bzl:18:13-31: `zs += [item, item]` allocates a temporary list on every iteration of the nested loop. Prefer `zs.extend([item, item])`.",
+            ]
+        );
+    }
 }
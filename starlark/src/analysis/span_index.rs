@@ -0,0 +1,80 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::codemap::Pos;
+use crate::codemap::Span;
+
+/// Values attached to spans of a module, which can be looked up by position in
+/// `O(log n)`, rather than walking the AST for each query.
+///
+/// The spans are expected not to overlap, as is the case for identifiers and literals,
+/// so a sorted array is all that is needed. As [`Span::contains`] includes the end
+/// position, adjacent spans can both contain a position, in which case the first wins.
+#[derive(Debug)]
+pub(crate) struct SpanIndex<T> {
+    entries: Vec<(Span, T)>,
+}
+
+impl<T> SpanIndex<T> {
+    pub(crate) fn new(mut entries: Vec<(Span, T)>) -> Self {
+        entries.sort_by_key(|(span, _)| span.begin());
+        Self { entries }
+    }
+
+    /// The value whose span contains `pos`, if any.
+    pub(crate) fn find(&self, pos: Pos) -> Option<&T> {
+        // The first entry beginning after `pos`, so the one before is the only candidate,
+        // unless it ends right where the one before that ends.
+        let i = self
+            .entries
+            .partition_point(|(span, _)| span.begin() <= pos);
+        self.entries[..i]
+            .iter()
+            .rev()
+            .take(2)
+            .filter(|(span, _)| span.contains(pos))
+            .last()
+            .map(|(_, x)| x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(begin: u32, end: u32) -> Span {
+        Span::new(Pos::new(begin), Pos::new(end))
+    }
+
+    #[test]
+    fn test_find() {
+        let index = SpanIndex::new(vec![
+            (span(5, 8), "b"),
+            (span(0, 3), "a"),
+            (span(8, 9), "c"),
+        ]);
+        let find = |pos| index.find(Pos::new(pos)).copied();
+        assert_eq!(find(0), Some("a"));
+        assert_eq!(find(3), Some("a"));
+        assert_eq!(find(4), None);
+        assert_eq!(find(6), Some("b"));
+        assert_eq!(find(8), Some("b"));
+        assert_eq!(find(9), Some("c"));
+        assert_eq!(find(10), None);
+        assert_eq!(SpanIndex::<()>::new(Vec::new()).find(Pos::new(0)), None);
+    }
+}
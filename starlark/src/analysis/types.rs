@@ -21,10 +21,6 @@ use std::path::Path;
 
 use dupe::Dupe;
 use gazebo::variants::VariantName;
-use lsp_types::Diagnostic;
-use lsp_types::DiagnosticSeverity;
-use lsp_types::NumberOrString;
-use lsp_types::Range;
 use serde::Serialize;
 
 use crate::codemap::CodeMap;
@@ -32,6 +28,7 @@ use crate::codemap::FileSpan;
 use crate::codemap::ResolvedSpan;
 use crate::codemap::Span;
 use crate::errors::Diagnostic as StarlarkDiagnostic;
+use crate::syntax::dialect::ExtendedDialectHint;
 
 pub(crate) trait LintWarning: Display + VariantName {
     fn is_serious(&self) -> bool;
@@ -46,7 +43,7 @@ pub(crate) struct LintT<T> {
 }
 
 /// A lint produced by [`AstModule::lint`](crate::syntax::AstModule::lint).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Lint {
     /// Which code location does this lint refer to.
     pub location: FileSpan,
@@ -119,8 +116,11 @@ impl Display for EvalSeverity {
     }
 }
 
-impl From<EvalSeverity> for DiagnosticSeverity {
+#[cfg(feature = "lsp")]
+impl From<EvalSeverity> for lsp_types::DiagnosticSeverity {
     fn from(s: EvalSeverity) -> Self {
+        use lsp_types::DiagnosticSeverity;
+
         match s {
             EvalSeverity::Error => DiagnosticSeverity::ERROR,
             EvalSeverity::Warning => DiagnosticSeverity::WARNING,
@@ -147,6 +147,9 @@ pub struct EvalMessage {
     pub full_error_with_span: Option<String>,
     /// The text referred to by `.span`
     pub original: Option<String>,
+    /// The error is from parsing code which would be valid with
+    /// [`Dialect::Extended`](crate::syntax::Dialect::Extended), so switching to it might help.
+    pub extended_dialect: bool,
 }
 
 impl Display for EvalMessage {
@@ -180,6 +183,7 @@ impl EvalMessage {
                     description: format!("{:#}", message),
                     full_error_with_span: Some(d.to_string()),
                     original: Some(original),
+                    extended_dialect: message.is::<ExtendedDialectHint>(),
                 }
             }
             _ => Self {
@@ -190,6 +194,7 @@ impl EvalMessage {
                 description: format!("{:#}", x),
                 full_error_with_span: None,
                 original: None,
+                extended_dialect: false,
             },
         }
     }
@@ -210,20 +215,22 @@ impl From<Lint> for EvalMessage {
             description: x.problem,
             full_error_with_span: None,
             original: Some(x.original),
+            extended_dialect: false,
         }
     }
 }
 
-impl From<EvalMessage> for Diagnostic {
+#[cfg(feature = "lsp")]
+impl From<EvalMessage> for lsp_types::Diagnostic {
     fn from(x: EvalMessage) -> Self {
         let range = match x.span {
             Some(s) => s.into(),
-            _ => Range::default(),
+            _ => lsp_types::Range::default(),
         };
-        Diagnostic::new(
+        lsp_types::Diagnostic::new(
             range,
             Some(x.severity.into()),
-            Some(NumberOrString::String(x.name)),
+            Some(lsp_types::NumberOrString::String(x.name)),
             None,
             x.description,
             None,
@@ -105,6 +105,7 @@ impl Span {
     }
 
     /// Determines whether a `pos` is within this span.
+    #[cfg_attr(not(feature = "lsp"), allow(dead_code))] // Only used by the LSP
     pub fn contains(self, pos: Pos) -> bool {
         self.begin <= pos && pos <= self.end
     }
@@ -180,6 +181,8 @@ struct CodeMapData {
     source: String,
     /// Byte positions of line beginnings.
     lines: Vec<Pos>,
+    /// The line the source starts at in the file, when it is only a part of the file.
+    first_line: usize,
 }
 
 /// "Codemap" for `.rs` files.
@@ -241,6 +244,12 @@ impl Hash for CodeMap {
 impl CodeMap {
     /// Creates an new `CodeMap`.
     pub(crate) fn new(filename: String, source: String) -> CodeMap {
+        Self::new_at_line(filename, source, 0)
+    }
+
+    /// Creates a new `CodeMap` for part of a file, whose `source` starts at the 0-indexed
+    /// `first_line`. Spans resolve to lines within the whole file.
+    pub(crate) fn new_at_line(filename: String, source: String, first_line: usize) -> CodeMap {
         let mut lines = vec![Pos(0)];
         lines.extend(source.match_indices('\n').map(|(p, _)| Pos(p as u32 + 1)));
 
@@ -248,6 +257,7 @@ impl CodeMap {
             filename,
             source,
             lines,
+            first_line,
         })))
     }
 
@@ -370,8 +380,12 @@ impl CodeMap {
     }
 
     pub(crate) fn resolve_span(&self, span: Span) -> ResolvedSpan {
-        let begin = self.find_line_col(span.begin);
-        let end = self.find_line_col(span.end);
+        let mut begin = self.find_line_col(span.begin);
+        let mut end = self.find_line_col(span.end);
+        if let CodeMapImpl::Real(data) = &self.0 {
+            begin.line += data.first_line;
+            end.line += data.first_line;
+        }
         ResolvedSpan::from_span(begin, end)
     }
 
@@ -532,6 +546,7 @@ impl Display for ResolvedSpan {
     }
 }
 
+#[cfg(feature = "lsp")]
 impl From<ResolvedSpan> for lsp_types::Range {
     fn from(span: ResolvedSpan) -> Self {
         lsp_types::Range::new(
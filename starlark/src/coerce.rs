@@ -135,6 +135,9 @@ unsafe impl CoerceKey<str> for str {}
 unsafe impl Coerce<()> for () {}
 unsafe impl CoerceKey<()> for () {}
 
+unsafe impl Coerce<usize> for usize {}
+unsafe impl CoerceKey<usize> for usize {}
+
 unsafe impl<FromK, FromV, ToK, ToV> Coerce<SmallMap<ToK, ToV>> for SmallMap<FromK, FromV>
 where
     FromK: CoerceKey<ToK>,
@@ -19,9 +19,117 @@ use dupe::Dupe;
 
 use crate::codemap::CodeMap;
 use crate::codemap::FileSpan;
+use crate::debug::DebugMutationPolicy;
+use crate::eval::Evaluator;
 use crate::syntax::ast::AstStmt;
 use crate::syntax::ast::Stmt;
 use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+#[derive(Debug, thiserror::Error)]
+enum BreakpointFilterError {
+    #[error(
+        "Invalid hit condition `{0}`, expected a number, optionally preceded by `==`, `>`, `>=`, `<`, `<=` or `%`"
+    )]
+    InvalidHitCondition(String),
+}
+
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+enum HitCondition {
+    Eq(u64),
+    Gt(u64),
+    Ge(u64),
+    Lt(u64),
+    Le(u64),
+    Multiple(u64),
+}
+
+impl HitCondition {
+    fn parse(x: &str) -> anyhow::Result<HitCondition> {
+        let x = x.trim();
+        let (op, n): (fn(u64) -> HitCondition, &str) = if let Some(n) = x.strip_prefix("==") {
+            (HitCondition::Eq, n)
+        } else if let Some(n) = x.strip_prefix(">=") {
+            (HitCondition::Ge, n)
+        } else if let Some(n) = x.strip_prefix('>') {
+            (HitCondition::Gt, n)
+        } else if let Some(n) = x.strip_prefix("<=") {
+            (HitCondition::Le, n)
+        } else if let Some(n) = x.strip_prefix('<') {
+            (HitCondition::Lt, n)
+        } else if let Some(n) = x.strip_prefix('%') {
+            (HitCondition::Multiple, n)
+        } else {
+            (HitCondition::Eq, x)
+        };
+        match n.trim().parse().ok().map(op) {
+            None | Some(HitCondition::Multiple(0)) => {
+                Err(BreakpointFilterError::InvalidHitCondition(x.to_owned()).into())
+            }
+            Some(h) => Ok(h),
+        }
+    }
+
+    fn matches(self, hits: u64) -> bool {
+        match self {
+            HitCondition::Eq(n) => hits == n,
+            HitCondition::Gt(n) => hits > n,
+            HitCondition::Ge(n) => hits >= n,
+            HitCondition::Lt(n) => hits < n,
+            HitCondition::Le(n) => hits <= n,
+            HitCondition::Multiple(n) => hits % n == 0,
+        }
+    }
+}
+
+/// Decides whether execution stops at a breakpoint, for conditional breakpoints
+/// and breakpoints with a hit count. Used by the `breakpoint()` function and debuggers.
+#[derive(Debug, Clone)]
+pub struct BreakpointFilter {
+    condition: Option<String>,
+    hit_condition: Option<HitCondition>,
+    /// Number of times the breakpoint was reached with the condition true.
+    hits: u64,
+}
+
+impl BreakpointFilter {
+    /// A filter stopping when the Starlark expression `condition` is true, and the number
+    /// of times it was true so far satisfies `hit_condition`: a number `n` (or `== n`) to
+    /// stop the `n`th time only, `> n`, `>= n`, `< n` or `<= n`, or `% n` to stop every
+    /// `n`th time. Either can be omitted. Fails if either does not parse.
+    pub fn new(
+        condition: Option<String>,
+        hit_condition: Option<&str>,
+    ) -> anyhow::Result<BreakpointFilter> {
+        if let Some(condition) = &condition {
+            Self::parse_condition(condition)?;
+        }
+        Ok(BreakpointFilter {
+            condition,
+            hit_condition: hit_condition.map(HitCondition::parse).transpose()?,
+            hits: 0,
+        })
+    }
+
+    fn parse_condition(condition: &str) -> anyhow::Result<AstModule> {
+        AstModule::parse("condition", condition.to_owned(), &Dialect::Extended)
+    }
+
+    /// Called each time the breakpoint is reached. Evaluates the condition in the frame
+    /// `eval` is paused in, and counts the hit if it is true. The condition must not assign
+    /// variables. Like [`Evaluator::eval_statements`], this disables garbage collection.
+    pub fn should_stop(&mut self, eval: &mut Evaluator) -> anyhow::Result<bool> {
+        if let Some(condition) = &self.condition {
+            let condition = Self::parse_condition(condition)?;
+            let res = eval.eval_statements_with_policy(condition, DebugMutationPolicy::Deny)?;
+            if !res.to_bool() {
+                return Ok(false);
+            }
+        }
+        self.hits += 1;
+        Ok(self.hit_condition.map_or(true, |h| h.matches(self.hits)))
+    }
+}
 
 fn go(x: &AstStmt, codemap: &CodeMap, res: &mut Vec<FileSpan>) {
     match &**x {
@@ -48,6 +156,7 @@ mod tests {
     use gazebo::prelude::*;
 
     use crate::assert;
+    use crate::debug::breakpoint::HitCondition;
 
     #[test]
     fn test_locations() {
@@ -61,4 +170,22 @@ mod tests {
         assert_eq!(&get("foo"), "1:1-4");
         assert_eq!(&get("foo\ndef x():\n   pass"), "1:1-4 2:1-3:8 3:4-8");
     }
+
+    #[test]
+    fn test_hit_condition() {
+        let hits = |x: &str| {
+            let h = HitCondition::parse(x).unwrap();
+            (1..=6).filter(|n| h.matches(*n)).collect::<Vec<_>>()
+        };
+        assert_eq!(vec![3], hits("3"));
+        assert_eq!(vec![3], hits(" == 3"));
+        assert_eq!(vec![4, 5, 6], hits(">3"));
+        assert_eq!(vec![3, 4, 5, 6], hits(">= 3"));
+        assert_eq!(vec![1, 2], hits("<3"));
+        assert_eq!(vec![1, 2, 3], hits("<=3"));
+        assert_eq!(vec![2, 4, 6], hits("%2"));
+        assert!(HitCondition::parse("%0").is_err());
+        assert!(HitCondition::parse("x").is_err());
+        assert!(HitCondition::parse("").is_err());
+    }
 }
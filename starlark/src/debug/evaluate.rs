@@ -17,13 +17,77 @@
 
 use std::mem;
 
+use dupe::Dupe;
+
+use crate::collections::Hashed;
 use crate::collections::SmallMap;
 use crate::debug::inspect::to_scope_names_by_local_slot_id;
 use crate::eval::runtime::slots::LocalSlotIdCapturedOrNot;
 use crate::eval::Evaluator;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::Stmt;
 use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::values::layout::value_captured::value_captured_get;
+use crate::values::layout::value_captured::FrozenValueCaptured;
+use crate::values::layout::value_captured::ValueCaptured;
 use crate::values::FrozenStringValue;
 use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(Debug, thiserror::Error)]
+enum DebugEvaluateError {
+    #[error("Assigning variables is not allowed here")]
+    MutationDenied,
+    #[error("Variable `{0}` not found in the current scope")]
+    VariableNotFound(String),
+    #[error("Variable `{0}` is captured from a frozen function and cannot be assigned")]
+    VariableFrozen(String),
+}
+
+/// What [`Evaluator::eval_statements_with_policy`] does with variables assigned
+/// by the evaluated code.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Default)]
+pub enum DebugMutationPolicy {
+    /// Assignments to local and module variables are kept, and are visible when
+    /// execution resumes.
+    #[default]
+    Allow,
+    /// Variables may be assigned, but are restored afterwards. Values mutated in place,
+    /// e.g. with `list.append`, stay mutated.
+    Discard,
+    /// Code which assigns variables (including with `for`, `def` and `load`) is rejected
+    /// without being evaluated.
+    Deny,
+}
+
+/// Whether `x` binds a variable in the scope it is evaluated in.
+fn assigns_variables(x: &AstStmt) -> bool {
+    match &**x {
+        Stmt::Assign(..)
+        | Stmt::Declare(..)
+        | Stmt::AssignModify(..)
+        | Stmt::For(..)
+        | Stmt::Def(..)
+        | Stmt::Load(..) => true,
+        _ => {
+            let mut res = false;
+            x.visit_stmt(|x| res = res || assigns_variables(x));
+            res
+        }
+    }
+}
+
+/// The value of a local variable, looking through the cell of captured variables.
+pub(crate) fn local_value(x: Value) -> Option<Value> {
+    if x.downcast_ref::<ValueCaptured>().is_some()
+        || x.downcast_ref::<FrozenValueCaptured>().is_some()
+    {
+        value_captured_get(x)
+    } else {
+        Some(x)
+    }
+}
 
 impl<'v, 'a> Evaluator<'v, 'a> {
     /// Evaluate statements in the existing context. This function is designed for debugging,
@@ -33,6 +97,23 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     /// nested definitions etc. It would be a bad idea to rely on the results of continued execution
     /// after evaluating stuff randomly.
     pub fn eval_statements(&mut self, statements: AstModule) -> anyhow::Result<Value<'v>> {
+        self.eval_statements_with_policy(statements, DebugMutationPolicy::Allow)
+    }
+
+    /// Like [`eval_statements`](Evaluator::eval_statements), but with control over
+    /// whether the statements may assign variables of the paused frame.
+    ///
+    /// Local variables of the innermost function, including those captured by or from
+    /// nested functions, are visible as if they were module variables.
+    pub fn eval_statements_with_policy(
+        &mut self,
+        statements: AstModule,
+        policy: DebugMutationPolicy,
+    ) -> anyhow::Result<Value<'v>> {
+        if policy == DebugMutationPolicy::Deny && assigns_variables(&statements.statement) {
+            return Err(DebugEvaluateError::MutationDenied.into());
+        }
+
         // We are doing a lot of funky stuff here. It's amazing anything works, so let's not push our luck with GC.
         self.disable_gc();
 
@@ -74,6 +155,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
                 if let Some(value) = self
                     .current_frame
                     .get_slot_slow(LocalSlotIdCapturedOrNot(slot as u32))
+                    .and_then(local_value)
                 {
                     self.module_env.set(name, value)
                 }
@@ -88,23 +170,90 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         // Now put the Module back how it was before we started, as best we can
         // and move things into locals if that makes sense
         if let Some(names) = &locals {
-            for (slot, name) in names.iter().enumerate() {
-                if let Some(value) = self.module_env.get(name) {
-                    self.current_frame
-                        .set_slot_slow(LocalSlotIdCapturedOrNot(slot as u32), value)
+            if policy == DebugMutationPolicy::Allow {
+                for (slot, name) in names.iter().enumerate() {
+                    if let Some(value) = self.module_env.get(name) {
+                        self.set_local_for_debugger(slot as u32, value);
+                    }
                 }
             }
+        }
+        if locals.is_some() || policy != DebugMutationPolicy::Allow {
             for (name, slot) in self.module_env.names().all_names() {
                 match original_module.get(&name) {
                     None => self.module_env.names().hide_name(&name),
-                    Some(Some(value)) => self.module_env.slots().set_slot(slot, *value),
-                    _ => {} // No way to unassign a previously assigned value yet
+                    Some(value) => {
+                        self.module_env.slots().get_slots_mut()[slot.0 as usize] = *value
+                    }
                 }
             }
         }
 
         res
     }
+
+    /// Evaluate a watch expression in the existing context, e.g. each time execution
+    /// stops at a breakpoint. Unlike [`eval_statements`](Evaluator::eval_statements),
+    /// code which assigns variables is rejected.
+    pub fn eval_watch(&mut self, expression: &str) -> anyhow::Result<Value<'v>> {
+        let ast = AstModule::parse("watch", expression.to_owned(), &Dialect::Extended)?;
+        self.eval_statements_with_policy(ast, DebugMutationPolicy::Deny)
+    }
+
+    /// Assign an existing variable in scope, as listed by
+    /// [`local_variables`](Evaluator::local_variables): a local variable of the innermost
+    /// function, or a module variable at the top-level. This function is designed for debugging,
+    /// not production use.
+    pub fn set_local_variable(&mut self, name: &str, value: Value<'v>) -> anyhow::Result<()> {
+        let locals = self
+            .call_stack
+            .to_function_values()
+            .into_iter()
+            .rev()
+            .find_map(to_scope_names_by_local_slot_id);
+        match locals {
+            Some(names) => {
+                let slot = match names.iter().position(|x| x.as_str() == name) {
+                    Some(slot) => slot as u32,
+                    None => {
+                        return Err(DebugEvaluateError::VariableNotFound(name.to_owned()).into());
+                    }
+                };
+                if let Some(x) = self
+                    .current_frame
+                    .get_slot_slow(LocalSlotIdCapturedOrNot(slot))
+                {
+                    if x.downcast_ref::<FrozenValueCaptured>().is_some() {
+                        return Err(DebugEvaluateError::VariableFrozen(name.to_owned()).into());
+                    }
+                }
+                self.set_local_for_debugger(slot, value);
+            }
+            None => {
+                if self
+                    .module_env
+                    .get_any_visibility(Hashed::new(name))
+                    .is_none()
+                {
+                    return Err(DebugEvaluateError::VariableNotFound(name.to_owned()).into());
+                }
+                self.module_env.set(name, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_local_for_debugger(&mut self, slot: u32, value: Value<'v>) {
+        let slot = LocalSlotIdCapturedOrNot(slot);
+        match self.current_frame.get_slot_slow(slot) {
+            Some(x) if x.downcast_ref::<FrozenValueCaptured>().is_some() => {}
+            Some(x) => match x.downcast_ref::<ValueCaptured>() {
+                Some(captured) => captured.set(value),
+                None => self.current_frame.set_slot_slow(slot, value),
+            },
+            None => self.current_frame.set_slot_slow(slot, value),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -115,7 +264,7 @@ mod tests {
     use crate as starlark;
     use crate::assert;
     use crate::environment::GlobalsBuilder;
-    use crate::syntax::Dialect;
+    use crate::values::none::NoneType;
 
     #[starlark_module]
     fn debugger(builder: &mut GlobalsBuilder) {
@@ -126,6 +275,37 @@ mod tests {
             let ast = AstModule::parse("interactive", code, &Dialect::Extended)?;
             eval.eval_statements(ast)
         }
+
+        fn debug_evaluate_with<'v>(
+            code: String,
+            policy: &str,
+            eval: &mut Evaluator<'v, '_>,
+        ) -> anyhow::Result<Value<'v>> {
+            let policy = match policy {
+                "allow" => DebugMutationPolicy::Allow,
+                "discard" => DebugMutationPolicy::Discard,
+                "deny" => DebugMutationPolicy::Deny,
+                _ => panic!("unknown policy: {}", policy),
+            };
+            let ast = AstModule::parse("interactive", code, &Dialect::Extended)?;
+            eval.eval_statements_with_policy(ast, policy)
+        }
+
+        fn debug_watch<'v>(
+            expression: &str,
+            eval: &mut Evaluator<'v, '_>,
+        ) -> anyhow::Result<Value<'v>> {
+            eval.eval_watch(expression)
+        }
+
+        fn debug_set<'v>(
+            name: &str,
+            value: Value<'v>,
+            eval: &mut Evaluator<'v, '_>,
+        ) -> anyhow::Result<NoneType> {
+            eval.set_local_variable(name, value)?;
+            Ok(NoneType)
+        }
     }
 
     #[test]
@@ -186,4 +366,95 @@ def bar(y):
         );
         a.pass("load('test', 'bar'); assert_eq(bar(4), 4 + 7 + 2)");
     }
+
+    #[test]
+    fn test_debug_evaluate_captured() {
+        let mut a = assert::Assert::new();
+        a.globals_add(debugger);
+        a.pass(
+            r#"
+def outer():
+    x = 1
+    def inner():
+        # Only variables referenced in `inner` are captured.
+        x
+        return debug_evaluate("x + 1")
+    assert_eq(debug_evaluate("x = 5"), None)
+    assert_eq(x, 5)
+    return inner()
+assert_eq(outer(), 6)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_debug_evaluate_policy() {
+        let mut a = assert::Assert::new();
+        a.globals_add(debugger);
+        let check = r#"
+x = 10
+y = [1]
+assert_eq(debug_evaluate_with("x = 5", "discard"), None)
+assert_eq(x, 10)
+debug_evaluate_with("y.append(2)", "discard")
+assert_eq(y, [1, 2])
+assert_eq(debug_evaluate_with("x + 1", "deny"), 11)
+"#;
+        a.pass(check);
+        a.pass(&format!(
+            "def local():\n{}\nlocal()",
+            check.lines().map(|x| format!("    {}", x)).join("\n")
+        ));
+        a.fail(
+            "x = 1\ndebug_evaluate_with('x = 2', 'deny')",
+            "Assigning variables is not allowed",
+        );
+        a.fail(
+            "debug_evaluate_with('[1 for x in [2]]\\nfor y in []: pass', 'deny')",
+            "Assigning variables is not allowed",
+        );
+        a.fail(
+            "debug_evaluate_with('z = 1', 'discard')\ndebug_evaluate('z')",
+            "Variable `z` not found",
+        );
+    }
+
+    #[test]
+    fn test_debug_watch_and_set() {
+        let mut a = assert::Assert::new();
+        a.globals_add(debugger);
+        let check = r#"
+x = 10
+assert_eq(debug_watch("x * 2"), 20)
+debug_set("x", 5)
+assert_eq(x, 5)
+assert_eq(debug_watch("[y for y in [x]]"), [5])
+"#;
+        a.pass(check);
+        a.pass(&format!(
+            "def local():\n{}\nlocal()",
+            check.lines().map(|x| format!("    {}", x)).join("\n")
+        ));
+        a.pass(
+            r#"
+def outer():
+    x = 1
+    def inner():
+        x
+        debug_set("x", 2)
+        return x
+    return inner()
+assert_eq(outer(), 2)
+"#,
+        );
+        a.fail(
+            "x = 1\ndebug_watch('x = 2')",
+            "Assigning variables is not allowed",
+        );
+        a.fail(
+            "def f():\n    debug_set('y', 1)\nf()",
+            "Variable `y` not found",
+        );
+        a.fail("debug_set('y', 1)", "Variable `y` not found");
+    }
 }
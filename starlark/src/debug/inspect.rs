@@ -16,6 +16,7 @@
  */
 
 use crate::collections::SmallMap;
+use crate::debug::evaluate::local_value;
 use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::FrozenDef;
 use crate::eval::runtime::slots::LocalSlotIdCapturedOrNot;
@@ -51,10 +52,10 @@ fn inspect_local_variables<'v>(eval: &Evaluator<'v, '_>) -> Option<SmallMap<Stri
         .find_map(to_scope_names_by_local_slot_id)?;
     let mut res = SmallMap::new();
     for (slot, name) in names.iter().enumerate() {
-        // TODO(nga): correctly handle captured.
         if let Some(v) = eval
             .current_frame
             .get_slot_slow(LocalSlotIdCapturedOrNot(slot as u32))
+            .and_then(local_value)
         {
             res.insert(name.as_str().to_owned(), v);
         }
@@ -18,3 +18,6 @@
 mod breakpoint;
 mod evaluate;
 mod inspect;
+
+pub use breakpoint::BreakpointFilter;
+pub use evaluate::DebugMutationPolicy;
@@ -41,6 +41,8 @@ use crate::values::function::NativeFunc;
 use crate::values::function::NativeMeth;
 use crate::values::layout::value::ValueLike;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
+use crate::values::lazy::LazyValue;
+use crate::values::string::intern::interner::FrozenStringInterner;
 use crate::values::structs::AllocStruct;
 use crate::values::types::function::NativeFunction;
 use crate::values::types::function::NativeMethod;
@@ -67,7 +69,13 @@ struct GlobalsData {
     heap: FrozenHeapRef,
     variables: SymbolMap<FrozenValue>,
     variable_names: Vec<FrozenStringValue>,
+    /// Strings interned on `heap`, reused by modules evaluated with these globals.
+    #[allocative(skip)]
+    interned_strings: FrozenStringInterner,
     docstring: Option<String>,
+    /// Extra methods on built-in types, indexed by type name.
+    #[allocative(skip)]
+    type_extensions: SmallMap<String, Methods>,
 }
 
 #[derive(Debug)]
@@ -86,10 +94,12 @@ pub struct GlobalsBuilder {
     heap: FrozenHeap,
     // Normal top-level variables, e.g. True/hash
     variables: SymbolMap<FrozenValue>,
-    // The list of struct fields, pushed to the end
-    struct_fields: Vec<SmallMap<FrozenStringValue, FrozenValue>>,
+    // The names and fields of the structs being defined, innermost last
+    struct_fields: Vec<(String, SmallMap<FrozenStringValue, FrozenValue>)>,
     // The raw docstring for this module
     docstring: Option<String>,
+    // Extra methods on built-in types, indexed by type name
+    type_extensions: SmallMap<String, MethodsBuilder>,
 }
 
 /// Used to build a [`Methods`] value.
@@ -142,7 +152,15 @@ impl Globals {
     /// This function is only safe if you first call `heap` and keep a reference to it.
     /// Therefore, don't expose it on the public API.
     pub(crate) fn get_frozen(&self, name: &str) -> Option<FrozenValue> {
-        self.0.variables.get_str(name).copied()
+        Some(LazyValue::force_frozen(*self.0.variables.get_str(name)?))
+    }
+
+    /// The variables, computing any [`LazyValue`]s.
+    fn variables(&self) -> impl Iterator<Item = (&str, FrozenValue)> {
+        self.0
+            .variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), LazyValue::force_frozen(*value)))
     }
 
     /// Get all the names defined in this environment.
@@ -154,33 +172,64 @@ impl Globals {
         &self.0.heap
     }
 
+    /// Get a string interned while building these globals, either a global name
+    /// or a string interned with [`FrozenHeap::alloc_str_intern`] on
+    /// [`GlobalsBuilder::frozen_heap`].
+    pub fn interned_str(&self, s: &str) -> Option<FrozenStringValue> {
+        self.interned_str_hashed(Hashed::new(s))
+    }
+
+    pub(crate) fn interned_str_hashed(&self, s: Hashed<&str>) -> Option<FrozenStringValue> {
+        self.0.interned_strings.get(s)
+    }
+
     /// Print information about the values in this object.
     pub fn describe(&self) -> String {
-        self.0
-            .variables
-            .iter()
-            .map(|(name, val)| val.to_value().describe(name.as_str()))
+        self.variables()
+            .map(|(name, val)| val.to_value().describe(name))
             .join("\n")
     }
 
     /// Get the documentation for both the object itself, and its members. Returned as an `Object`
     pub fn documentation(&self) -> DocItem {
-        common_documentation(
-            &self.0.docstring,
-            self.0.variables.iter().map(|(n, v)| (n.as_str(), *v)),
-        )
+        common_documentation(&self.0.docstring, self.variables())
     }
 
     /// Get the documentation for each member. Useful when loading a number of objects into
     /// a single [`Globals`] instance, but where the documentation for each member will be
     /// split up later.
     pub fn member_documentation(&self) -> HashMap<String, Option<DocItem>> {
+        self.variables()
+            .map(|(name, value)| (name.to_owned(), value.to_value().documentation()))
+            .collect()
+    }
+
+    /// Get the documentation for the methods added to built-in types with
+    /// [`GlobalsBuilder::type_extension`], indexed by type name.
+    pub fn type_extension_documentation(&self) -> HashMap<String, DocItem> {
         self.0
-            .variables
+            .type_extensions
             .iter()
-            .map(|(symbol, value)| (symbol.as_str().to_owned(), value.to_value().documentation()))
+            .map(|(name, methods)| (name.clone(), methods.documentation()))
             .collect()
     }
+
+    /// The method `name` added to the type `type_name` with [`GlobalsBuilder::type_extension`].
+    pub(crate) fn get_type_extension(
+        &self,
+        type_name: &str,
+        name: &str,
+    ) -> Option<FrozenValueNotSpecial> {
+        self.0.type_extensions.get(type_name)?.get_frozen(name)
+    }
+
+    /// The names of the methods added to the type `type_name`.
+    pub(crate) fn type_extension_names(&self, type_name: &str) -> Vec<String> {
+        match self.0.type_extensions.get(type_name) {
+            None => Vec::new(),
+            Some(methods) => methods.names(),
+        }
+    }
 }
 
 impl Methods {
@@ -235,6 +284,7 @@ impl GlobalsBuilder {
             variables: SymbolMap::new(),
             struct_fields: Vec::new(),
             docstring: None,
+            type_extensions: SmallMap::new(),
         }
     }
 
@@ -263,10 +313,27 @@ impl GlobalsBuilder {
     /// Add a nested struct to the builder. If `f` adds the definition `foo`,
     /// it will end up on a struct `name`, accessible as `name.foo`.
     pub fn struct_(&mut self, name: &str, f: impl FnOnce(&mut GlobalsBuilder)) {
-        self.struct_fields.push(SmallMap::new());
+        self.struct_fields.push((name.to_owned(), SmallMap::new()));
         f(self);
-        let fields = self.struct_fields.pop().unwrap();
-        self.set(name, AllocStruct(fields));
+        let (_, fields) = self.struct_fields.pop().unwrap();
+        if fields
+            .values()
+            .any(|v| v.downcast_frozen_ref::<LazyValue>().is_some())
+        {
+            // Make the whole struct lazy, so the struct itself never holds a `LazyValue`.
+            self.set(
+                name,
+                LazyValue::new(move |heap| {
+                    heap.alloc(AllocStruct(
+                        fields
+                            .iter()
+                            .map(|(k, v)| (*k, LazyValue::force_frozen(*v))),
+                    ))
+                }),
+            );
+        } else {
+            self.set(name, AllocStruct(fields));
+        }
     }
 
     /// A fluent API for modifying [`GlobalsBuilder`] and returning the result.
@@ -288,26 +355,66 @@ impl GlobalsBuilder {
             .keys()
             .map(|x| self.heap.alloc_str_intern(x.as_str()))
             .collect();
+        let interned_strings = self.heap.take_str_interner();
         Globals(Arc::new(GlobalsData {
             heap: self.heap.into_ref(),
             variables: self.variables,
             variable_names,
+            interned_strings,
             docstring: self.docstring,
+            type_extensions: self
+                .type_extensions
+                .into_iter()
+                .map(|(k, v)| (k, v.build()))
+                .collect(),
         }))
     }
 
+    /// Add methods to the built-in type `type_name`, as returned by `type()`, e.g. `"string"`
+    /// to define `"x".my_helper()`. The methods are available to code evaluated with the
+    /// resulting [`Globals`], but can't replace the methods the type already has.
+    pub fn type_extension(&mut self, type_name: &str, f: impl FnOnce(&mut MethodsBuilder)) {
+        f(self
+            .type_extensions
+            .entry(type_name.to_owned())
+            .or_insert_with(MethodsBuilder::new));
+    }
+
+    /// A fluent API for modifying [`GlobalsBuilder`] using [`type_extension`](GlobalsBuilder::type_extension).
+    pub fn with_type_extension(
+        mut self,
+        type_name: &str,
+        f: impl FnOnce(&mut MethodsBuilder),
+    ) -> Self {
+        self.type_extension(type_name, f);
+        self
+    }
+
     /// Set a value in the [`GlobalsBuilder`].
     pub fn set<'v, V: AllocFrozenValue>(&'v mut self, name: &str, value: V) {
         let value = value.alloc_frozen_value(&self.heap);
         match self.struct_fields.last_mut() {
             None => self.variables.insert(name, value),
-            Some(fields) => {
+            Some((_, fields)) => {
                 let name = self.heap.alloc_str(name);
                 fields.insert(name, value)
             }
         };
     }
 
+    /// Set a value in the [`GlobalsBuilder`] which is only computed, by `compute`, the
+    /// first time a module refers to it. See [`LazyValue`] for the details.
+    ///
+    /// Inside [`struct_`](GlobalsBuilder::struct_) the whole struct becomes lazy, and the
+    /// value is computed the first time a module refers to the struct.
+    pub fn set_lazy(
+        &mut self,
+        name: &str,
+        compute: impl Fn(&FrozenHeap) -> FrozenValue + Send + Sync + 'static,
+    ) {
+        self.set(name, LazyValue::new(compute));
+    }
+
     /// Set a method. This function is usually called from code
     /// generated by `starlark_derive` and rarely needs to be called manually.
     pub fn set_function<F>(
@@ -320,11 +427,18 @@ impl GlobalsBuilder {
     ) where
         F: NativeFunc,
     {
+        // Functions on structs are named after the struct, e.g. `json.encode`.
+        let mut qualified = String::new();
+        for (struct_name, _) in &self.struct_fields {
+            qualified.push_str(struct_name);
+            qualified.push('.');
+        }
+        qualified.push_str(name);
         self.set(
             name,
             NativeFunction {
                 function: Box::new(f),
-                name: name.to_owned(),
+                name: qualified,
                 speculative_exec_safe,
                 typ,
                 raw_docs: Some(raw_docs),
@@ -517,8 +631,13 @@ impl GlobalsStatic {
     }
 
     /// Move all the globals in this [`GlobalsBuilder`] into a new one. All variables will
-    /// only be allocated once (ensuring things like function comparison works properly).
+    /// only be allocated once (ensuring things like function comparison works properly),
+    /// except inside [`GlobalsBuilder::struct_`], where functions are named after the
+    /// struct they are defined on, so are allocated for each builder.
     pub fn populate(&'static self, x: impl FnOnce(&mut GlobalsBuilder), out: &mut GlobalsBuilder) {
+        if !out.struct_fields.is_empty() {
+            return x(out);
+        }
         let globals = self.globals(x);
         for (name, value) in globals.0.variables.iter() {
             out.set(name.as_str(), *value)
@@ -632,4 +751,61 @@ assert_eq(magic.my_type, "magic")
 assert_eq(magic.my_value, 42)"#,
         );
     }
+
+    #[starlark_module]
+    fn string_extension(builder: &mut MethodsBuilder) {
+        /// Shout the string.
+        fn shout(this: &str, #[starlark(default = 1)] times: i32) -> anyhow::Result<String> {
+            Ok(format!(
+                "{}{}",
+                this.to_uppercase(),
+                "!".repeat(times as usize)
+            ))
+        }
+
+        /// Not used, as strings already have `upper`.
+        fn upper(this: &str) -> anyhow::Result<String> {
+            Ok(this.to_owned())
+        }
+    }
+
+    #[test]
+    fn test_type_extension() {
+        let mut a = Assert::new();
+        a.globals_add(|x| x.type_extension("string", string_extension));
+        a.pass(
+            r#"
+assert_eq("hi".shout(), "HI!")
+assert_eq("hi".shout(times = 2), "HI!!")
+assert_eq("hi".upper(), "HI")
+f = "x".shout
+assert_eq(f(), "X!")
+assert_eq(getattr("y", "shout")(), "Y!")
+assert_true(hasattr("y", "shout"))
+assert_true(not hasattr([], "shout"))
+assert_true("shout" in dir(""))
+def g(x):
+    return x.shout()
+assert_eq(g("z"), "Z!")"#,
+        );
+        a.fail(
+            "[].shout()",
+            "Object of type `list` has no attribute `shout`",
+        );
+
+        let globals = GlobalsBuilder::new()
+            .with_type_extension("string", string_extension)
+            .build();
+        let docs = globals.type_extension_documentation();
+        match &docs["string"] {
+            DocItem::Object(obj) => assert_eq!(
+                vec!["shout", "upper"],
+                obj.members
+                    .iter()
+                    .map(|(n, _)| n.as_str())
+                    .collect::<Vec<_>>()
+            ),
+            x => panic!("Expected object docs, got {:?}", x),
+        }
+    }
 }
@@ -24,12 +24,15 @@
 
 mod globals;
 mod module_dump;
+mod module_serialize;
 mod modules;
 pub(crate) mod names;
 pub(crate) mod slots;
+mod template;
 
 pub use globals::*;
 pub use modules::*;
+pub use template::*;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -48,8 +51,28 @@ pub(crate) enum EnvironmentError {
     ModuleHasNoSymbol(String),
     #[error("Module has no symbol `{0}`, did you mean `{1}`?")]
     ModuleHasNoSymbolDidYouMean(String, String),
+    #[error("Module `{0}` has no symbol `{1}`{}", suggestions(.2))]
+    LoadedModuleHasNoSymbol(String, String, Vec<String>),
     #[error("Module symbol `{0}` is not exported")]
     ModuleSymbolIsNotExported(String),
+    #[error("Module template has no parameter `{0}`")]
+    NoSuchTemplateParameter(String),
+    #[error("Cannot set template parameter `{0}`, the module was not created from this template")]
+    NotTemplateInstance(String),
     #[error("No imports are available, you tried `{0}` (no call to `Evaluator.set_loader`)")]
     NoImportsAvailable(String),
 }
+
+fn suggestions(xs: &[String]) -> String {
+    match xs {
+        [] => String::new(),
+        [x] => format!(", did you mean `{}`?", x),
+        xs => format!(
+            ", did you mean one of {}?",
+            xs.iter()
+                .map(|x| format!("`{}`", x))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
@@ -44,6 +44,32 @@ impl FrozenModule {
         }
         w
     }
+
+    /// Render the bytecode of the functions defined in this module, in definition order.
+    ///
+    /// Unlike [`dump_debug`](FrozenModule::dump_debug), the output only depends on the
+    /// program and the compiler, which makes it suitable for comparing against
+    /// expected output in tests of the compiler and optimizer. Functions loaded from
+    /// other modules are skipped.
+    pub fn dump_bytecode(&self) -> String {
+        let mut w = String::new();
+        for (name, value) in self.all_items() {
+            if let Some(def) = FrozenValueTyped::<FrozenDef>::new(value) {
+                if !def.is_defined_in(&self.module) {
+                    continue;
+                }
+                if !w.is_empty() {
+                    writeln!(w).unwrap();
+                }
+                writeln!(w, "def {}:", name.as_str()).unwrap();
+                def.bc()
+                    .dump_debug()
+                    .lines()
+                    .for_each(|line| writeln!(w, "  {}", line).unwrap());
+            }
+        }
+        w
+    }
 }
 
 impl FrozenHeapRef {
@@ -0,0 +1,454 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Binary serialization of frozen modules containing only data.
+//!
+//! Functions are compiled to bytecode which points directly into the heap and at native
+//! code, so they can't be written out. Modules exporting them have to be evaluated again.
+
+use std::collections::HashMap;
+
+use num_bigint::BigInt;
+
+use crate::collections::SmallMap;
+use crate::environment::FrozenModule;
+use crate::environment::Module;
+use crate::values::dict::Dict;
+use crate::values::dict::DictRef;
+use crate::values::list::ListRef;
+use crate::values::num::Num;
+use crate::values::structs::AllocStruct;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::types::bigint::StarlarkBigInt;
+use crate::values::Heap;
+use crate::values::Value;
+use crate::values::ValueIdentity;
+
+const MAGIC: &[u8] = b"starlark-module\0";
+const VERSION: u32 = 2;
+/// Values nested deeper than this are rejected, which also catches cycles.
+const MAX_DEPTH: usize = 1000;
+
+const TAG_NONE: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_BIG_INT: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_STR: u8 = 6;
+const TAG_LIST: u8 = 7;
+const TAG_TUPLE: u8 = 8;
+const TAG_DICT: u8 = 9;
+const TAG_STRUCT: u8 = 10;
+/// A value written before, referred to by the order it was finished in.
+const TAG_SHARED: u8 = 11;
+
+#[derive(Debug, thiserror::Error)]
+enum ModuleSerializeError {
+    #[error(
+        "Cannot serialize `{0}` of type `{1}`, only `None`, `bool`, `int`, `float`, `str`, \
+        `list`, `tuple`, `dict` and `struct` values can be serialized"
+    )]
+    UnsupportedValue(String, String),
+    #[error("Cannot serialize `{0}`, its value is nested too deeply or contains itself")]
+    TooDeep(String),
+    #[error("Not a serialized module, or serialized by a different version of starlark")]
+    BadHeader,
+    #[error("Serialized module is truncated or corrupted")]
+    Corrupted,
+}
+
+struct Writer<'a> {
+    buf: Vec<u8>,
+    /// The symbol being written, for errors.
+    name: &'a str,
+    /// Values already written, so values referenced several times are only written once.
+    shared: HashMap<ValueIdentity<'a>, usize>,
+}
+
+/// Whether a value is stored on the heap, so may be shared, rather than stored in the pointer.
+fn is_shareable(x: Value) -> bool {
+    !(x.is_none() || x.unpack_bool().is_some() || x.unpack_int().is_some())
+}
+
+impl<'a> Writer<'a> {
+    fn len(&mut self, x: usize) {
+        self.buf.extend_from_slice(&(x as u64).to_le_bytes());
+    }
+
+    fn bytes(&mut self, x: &[u8]) {
+        self.len(x.len());
+        self.buf.extend_from_slice(x);
+    }
+
+    fn value(&mut self, x: Value<'a>, depth: usize) -> anyhow::Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(ModuleSerializeError::TooDeep(self.name.to_owned()).into());
+        }
+        if let Some(index) = self.shared.get(&x.identity()) {
+            self.buf.push(TAG_SHARED);
+            self.len(*index);
+            return Ok(());
+        }
+        if x.is_none() {
+            self.buf.push(TAG_NONE);
+        } else if let Some(b) = x.unpack_bool() {
+            self.buf.push(if b { TAG_TRUE } else { TAG_FALSE });
+        } else if let Some(s) = x.unpack_str() {
+            self.buf.push(TAG_STR);
+            self.bytes(s.as_bytes());
+        } else if let Some(n) = x.unpack_num() {
+            match n {
+                Num::Int(i) => {
+                    self.buf.push(TAG_INT);
+                    self.buf.extend_from_slice(&i.to_le_bytes());
+                }
+                Num::BigInt(i) => {
+                    self.buf.push(TAG_BIG_INT);
+                    self.bytes(&i.get().to_signed_bytes_le());
+                }
+                Num::Float(f) => {
+                    self.buf.push(TAG_FLOAT);
+                    self.buf.extend_from_slice(&f.to_le_bytes());
+                }
+            }
+        } else if let Some(list) = ListRef::from_value(x) {
+            self.buf.push(TAG_LIST);
+            self.values(list.content(), depth)?;
+        } else if let Some(tuple) = TupleRef::from_value(x) {
+            self.buf.push(TAG_TUPLE);
+            self.values(tuple.content(), depth)?;
+        } else if let Some(dict) = DictRef::from_value(x) {
+            self.buf.push(TAG_DICT);
+            self.len(dict.len());
+            for (k, v) in dict.iter() {
+                self.value(k, depth + 1)?;
+                self.value(v, depth + 1)?;
+            }
+        } else if let Some(s) = StructRef::from_value(x) {
+            self.buf.push(TAG_STRUCT);
+            self.len(s.iter().len());
+            for (k, v) in s.iter() {
+                self.bytes(k.as_str().as_bytes());
+                self.value(v, depth + 1)?;
+            }
+        } else {
+            return Err(ModuleSerializeError::UnsupportedValue(
+                self.name.to_owned(),
+                x.get_type().to_owned(),
+            )
+            .into());
+        }
+        // Only record the value once it is complete, so a value containing itself is
+        // still rejected as too deep.
+        if is_shareable(x) {
+            self.shared.insert(x.identity(), self.shared.len());
+        }
+        Ok(())
+    }
+
+    fn values(&mut self, xs: &[Value<'a>], depth: usize) -> anyhow::Result<()> {
+        self.len(xs.len());
+        for x in xs {
+            self.value(*x, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a, 'v> {
+    buf: &'a [u8],
+    /// The values which can be referred to by [`TAG_SHARED`], in the order they were finished.
+    shared: Vec<Value<'v>>,
+}
+
+impl<'a, 'v> Reader<'a, 'v> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if n > self.buf.len() {
+            return Err(ModuleSerializeError::Corrupted.into());
+        }
+        let (x, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(x)
+    }
+
+    fn byte(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn len(&mut self) -> anyhow::Result<usize> {
+        let x = u64::from_le_bytes(self.take(8)?.try_into().unwrap());
+        // Every element takes at least a byte, which bounds preallocation on corrupt input.
+        if x > self.buf.len() as u64 {
+            return Err(ModuleSerializeError::Corrupted.into());
+        }
+        Ok(x as usize)
+    }
+
+    fn bytes(&mut self) -> anyhow::Result<&'a [u8]> {
+        let len = self.len()?;
+        self.take(len)
+    }
+
+    fn str(&mut self) -> anyhow::Result<&'a str> {
+        std::str::from_utf8(self.bytes()?).map_err(|_| ModuleSerializeError::Corrupted.into())
+    }
+
+    fn value(&mut self, heap: &'v Heap, depth: usize) -> anyhow::Result<Value<'v>> {
+        if depth > MAX_DEPTH {
+            return Err(ModuleSerializeError::Corrupted.into());
+        }
+        let x = match self.byte()? {
+            TAG_NONE => Value::new_none(),
+            TAG_FALSE => Value::new_bool(false),
+            TAG_TRUE => Value::new_bool(true),
+            TAG_INT => Value::new_int(i32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+            TAG_BIG_INT => {
+                StarlarkBigInt::alloc_bigint(BigInt::from_signed_bytes_le(self.bytes()?), heap)
+            }
+            TAG_FLOAT => heap.alloc(f64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+            TAG_STR => heap.alloc_str(self.str()?).to_value(),
+            TAG_LIST => {
+                let xs = self.values(heap, depth)?;
+                heap.alloc_list(&xs)
+            }
+            TAG_TUPLE => {
+                let xs = self.values(heap, depth)?;
+                heap.alloc_tuple(&xs)
+            }
+            TAG_DICT => {
+                let len = self.len()?;
+                let mut content = SmallMap::with_capacity(len);
+                for _ in 0..len {
+                    let k = self.value(heap, depth + 1)?;
+                    let v = self.value(heap, depth + 1)?;
+                    content.insert_hashed(k.get_hashed()?, v);
+                }
+                heap.alloc(Dict::new(content))
+            }
+            TAG_STRUCT => {
+                let len = self.len()?;
+                let mut fields = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let k = self.str()?;
+                    fields.push((k, self.value(heap, depth + 1)?));
+                }
+                heap.alloc(AllocStruct(fields))
+            }
+            TAG_SHARED => {
+                let index = u64::from_le_bytes(self.take(8)?.try_into().unwrap());
+                return usize::try_from(index)
+                    .ok()
+                    .and_then(|index| self.shared.get(index).copied())
+                    .ok_or_else(|| ModuleSerializeError::Corrupted.into());
+            }
+            _ => return Err(ModuleSerializeError::Corrupted.into()),
+        };
+        if is_shareable(x) {
+            self.shared.push(x);
+        }
+        Ok(x)
+    }
+
+    fn values(&mut self, heap: &'v Heap, depth: usize) -> anyhow::Result<Vec<Value<'v>>> {
+        let len = self.len()?;
+        let mut xs = Vec::with_capacity(len);
+        for _ in 0..len {
+            xs.push(self.value(heap, depth + 1)?);
+        }
+        Ok(xs)
+    }
+}
+
+impl FrozenModule {
+    /// Write the exported symbols and the docstring of this module in a compact binary format,
+    /// which [`deserialize`](FrozenModule::deserialize) reads back much faster than
+    /// evaluating the module again, e.g. to cache modules across process restarts.
+    ///
+    /// Only modules exporting data (`None`, `bool`, `int`, `float`, `str`, `list`, `tuple`,
+    /// `dict` and `struct` values) can be serialized, anything else such as functions is
+    /// an error. Values referenced several times are written once, and are still shared
+    /// after reading them back.
+    pub fn serialize(&self) -> anyhow::Result<Vec<u8>> {
+        let mut w = Writer {
+            buf: Vec::new(),
+            name: "",
+            shared: HashMap::new(),
+        };
+        w.buf.extend_from_slice(MAGIC);
+        w.buf.extend_from_slice(&VERSION.to_le_bytes());
+        match self.module.docstring() {
+            None => w.buf.push(0),
+            Some(docstring) => {
+                w.buf.push(1);
+                w.bytes(docstring.as_bytes());
+            }
+        }
+        let items: Vec<_> = self.items().collect();
+        w.len(items.len());
+        for (name, value) in items {
+            w.name = name.as_str();
+            w.bytes(name.as_str().as_bytes());
+            w.value(value.to_value(), 0)?;
+        }
+        Ok(w.buf)
+    }
+
+    /// Read a module written by [`serialize`](FrozenModule::serialize).
+    pub fn deserialize(data: &[u8]) -> anyhow::Result<FrozenModule> {
+        let module = Module::new();
+        let mut r = Reader {
+            buf: data,
+            shared: Vec::new(),
+        };
+        if r.take(MAGIC.len()).ok() != Some(MAGIC)
+            || r.take(4).ok() != Some(&VERSION.to_le_bytes()[..])
+        {
+            return Err(ModuleSerializeError::BadHeader.into());
+        }
+        match r.byte()? {
+            0 => {}
+            1 => module.set_docstring(r.str()?.to_owned()),
+            _ => return Err(ModuleSerializeError::Corrupted.into()),
+        }
+        for _ in 0..r.len()? {
+            let name = r.str()?;
+            let value = r.value(module.heap(), 0)?;
+            module.set(name, value);
+        }
+        if !r.buf.is_empty() {
+            return Err(ModuleSerializeError::Corrupted.into());
+        }
+        module.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::FrozenModule;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::list::ListRef;
+    use crate::values::tuple::TupleRef;
+
+    fn eval(program: &str) -> FrozenModule {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.eval_module(
+            AstModule::parse("x.star", program.to_owned(), &Dialect::Extended).unwrap(),
+            &Globals::extended(),
+        )
+        .unwrap();
+        module.freeze().unwrap()
+    }
+
+    #[test]
+    fn test_serialize_roundtrip() {
+        let module = eval(
+            r#"
+"""Module docs."""
+n = None
+b = [True, False]
+i = (1, -2, 12345678901234567890123, -12345678901234567890123)
+f = 1.5
+s = {"x": "y", 1: [], (2, 3): {}}
+st = struct(a = 1, b = struct(c = "d"))
+_private = 1
+"#,
+        );
+        let data = module.serialize().unwrap();
+        let reloaded = FrozenModule::deserialize(&data).unwrap();
+        for name in ["n", "b", "i", "f", "s", "st"] {
+            assert_eq!(
+                module.get(name).unwrap().value().to_repr(),
+                reloaded.get(name).unwrap().value().to_repr(),
+                "{}",
+                name
+            );
+        }
+        assert!(reloaded.get_option("_private").unwrap().is_none());
+        assert_eq!(
+            module.documentation().map(|x| format!("{:?}", x)),
+            reloaded.documentation().map(|x| format!("{:?}", x))
+        );
+        // The reloaded values are frozen and usable from other modules.
+        let user = Module::new();
+        user.import_public_symbols(&reloaded);
+        let mut eval = Evaluator::new(&user);
+        let res = eval
+            .eval_module(
+                AstModule::parse("y.star", "st.b.c + s['x']".to_owned(), &Dialect::Extended)
+                    .unwrap(),
+                &Globals::extended(),
+            )
+            .unwrap();
+        assert_eq!(Some("dy"), res.unpack_str());
+    }
+
+    #[test]
+    fn test_serialize_unsupported() {
+        let module = eval("def f(): pass\nx = [f]");
+        assert!(module
+            .serialize()
+            .unwrap_err()
+            .to_string()
+            .contains("Cannot serialize `f` of type `function`"),);
+        let module = eval("x = []\nx.append(x)");
+        assert!(module
+            .serialize()
+            .unwrap_err()
+            .to_string()
+            .contains("too deeply"));
+    }
+
+    #[test]
+    fn test_serialize_shared() {
+        let module = eval(
+            r#"
+x = []
+for _ in range(60):
+    x = [x, x]
+s = "shared"
+y = (s, s, x[0])
+"#,
+        );
+        let data = module.serialize().unwrap();
+        // Without sharing, `x` would take 2^60 lists.
+        assert!(data.len() < 10_000, "{}", data.len());
+        let reloaded = FrozenModule::deserialize(&data).unwrap();
+        let x = reloaded.get("x").unwrap();
+        let x = ListRef::from_value(x.value()).unwrap();
+        assert!(x.content()[0].ptr_eq(x.content()[1]));
+        let y = reloaded.get("y").unwrap();
+        let y = TupleRef::from_value(y.value()).unwrap();
+        assert!(y.content()[0].ptr_eq(y.content()[1]));
+        assert!(y.content()[2].ptr_eq(x.content()[0]));
+    }
+
+    #[test]
+    fn test_deserialize_invalid() {
+        assert!(FrozenModule::deserialize(b"garbage").is_err());
+        let data = eval("x = [1, 2, 3]").serialize().unwrap();
+        for len in 0..data.len() {
+            assert!(FrozenModule::deserialize(&data[..len]).is_err());
+        }
+    }
+}
@@ -43,8 +43,11 @@ use crate::environment::slots::ModuleSlotId;
 use crate::environment::slots::MutableSlots;
 use crate::environment::EnvironmentError;
 use crate::errors::did_you_mean::did_you_mean;
+use crate::errors::did_you_mean::near_misses;
+use crate::eval::runtime::profile::heap::HeapProfileFormat;
 use crate::eval::runtime::profile::heap::RetainedHeapProfileMode;
 use crate::eval::ProfileData;
+use crate::eval::ProfileMode;
 use crate::syntax::ast::Visibility;
 use crate::values::layout::heap::heap_type::HeapKind;
 use crate::values::layout::heap::profile::aggregated::AggregateHeapProfileInfo;
@@ -57,6 +60,7 @@ use crate::values::FrozenRef;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
 use crate::values::Heap;
+use crate::values::HeapOptions;
 use crate::values::OwnedFrozenValue;
 use crate::values::Trace;
 use crate::values::Tracer;
@@ -80,8 +84,9 @@ enum ModuleError {
 // Two Arc's should still be plenty cheap enough to qualify for `Dupe`.
 pub struct FrozenModule {
     heap: FrozenHeapRef,
-    module: FrozenRef<'static, FrozenModuleData>,
+    pub(crate) module: FrozenRef<'static, FrozenModuleData>,
     extra_value: Option<FrozenValue>,
+    value: Option<FrozenValue>,
     /// Module evaluation duration:
     /// * evaluation of the top-level statements
     /// * optimizations during that evaluation
@@ -133,8 +138,12 @@ pub struct Module {
     eval_duration: Cell<Duration>,
     /// Field that can be used for any purpose you want.
     extra_value: Cell<Option<Value<'static>>>,
+    /// The value of the expression statement at the end of the module, see [`Module::value`].
+    value: Cell<Option<Value<'static>>>,
     /// When `Some`, heap profile is collected on freeze.
     heap_profile_on_freeze: Cell<Option<RetainedHeapProfileMode>>,
+    /// Format of heap profiles, when not the default for the profile mode.
+    heap_profile_format: Cell<Option<HeapProfileFormat>>,
 }
 
 impl FrozenModule {
@@ -206,6 +215,11 @@ impl FrozenModule {
         self.module.describe()
     }
 
+    /// Exported symbols and their values.
+    pub(crate) fn items(&self) -> impl Iterator<Item = (FrozenStringValue, FrozenValue)> + '_ {
+        self.module.items()
+    }
+
     pub(crate) fn all_items(&self) -> impl Iterator<Item = (FrozenStringValue, FrozenValue)> + '_ {
         self.module.all_items()
     }
@@ -261,9 +275,19 @@ impl FrozenModule {
         self.extra_value
             .map(|v| unsafe { OwnedFrozenValue::new(self.heap.dupe(), v) })
     }
+
+    /// `value` field from `Module`, frozen.
+    pub fn value(&self) -> Option<OwnedFrozenValue> {
+        self.value
+            .map(|v| unsafe { OwnedFrozenValue::new(self.heap.dupe(), v) })
+    }
 }
 
 impl FrozenModuleData {
+    pub(crate) fn docstring(&self) -> Option<&str> {
+        self.docstring.as_deref()
+    }
+
     pub fn names(&self) -> impl Iterator<Item = FrozenStringValue> + '_ {
         self.names.symbols().map(|x| x.0)
     }
@@ -319,15 +343,30 @@ impl Default for Module {
 impl Module {
     /// Create a new module environment with no contents.
     pub fn new() -> Self {
+        Self::with_names_and_slots(MutableNames::new(), MutableSlots::new())
+    }
+
+    /// Create a new module whose [`heap`](Module::heap) allocates memory as per `options`.
+    pub fn with_heap_options(options: HeapOptions) -> Self {
+        Self {
+            heap: Heap::with_options(options),
+            ..Self::new()
+        }
+    }
+
+    /// Create a module whose variables are already allocated.
+    pub(crate) fn with_names_and_slots(names: MutableNames, slots: MutableSlots<'static>) -> Self {
         Self {
             heap: Heap::new(),
             frozen_heap: FrozenHeap::new(),
-            names: MutableNames::new(),
-            slots: MutableSlots::new(),
+            names,
+            slots,
             docstring: RefCell::new(None),
             eval_duration: Cell::new(Duration::ZERO),
             extra_value: Cell::new(None),
+            value: Cell::new(None),
             heap_profile_on_freeze: Cell::new(None),
+            heap_profile_format: Cell::new(None),
         }
     }
 
@@ -335,6 +374,17 @@ impl Module {
         self.heap_profile_on_freeze.set(Some(mode));
     }
 
+    pub(crate) fn set_heap_profile_format(&self, format: HeapProfileFormat) {
+        self.heap_profile_format.set(Some(format));
+    }
+
+    /// Format of heap profiles collected for this module in the given mode.
+    pub(crate) fn heap_profile_format(&self, mode: &ProfileMode) -> HeapProfileFormat {
+        self.heap_profile_format
+            .get()
+            .unwrap_or_else(|| HeapProfileFormat::default_for(mode))
+    }
+
     /// Get the heap on which values are allocated by this module.
     pub fn heap(&self) -> &Heap {
         &self.heap
@@ -384,7 +434,9 @@ impl Module {
             docstring,
             eval_duration,
             extra_value,
+            value,
             heap_profile_on_freeze,
+            heap_profile_format,
         } = self;
         let start = Instant::now();
         // This is when we do the GC/freeze, using the module slots as roots
@@ -394,13 +446,18 @@ impl Module {
         let freezer = Freezer::new(frozen_heap);
         let slots = slots.freeze(&freezer)?;
         let extra_value = extra_value.into_inner().freeze(&freezer)?;
+        let value = value.into_inner().freeze(&freezer)?;
         let stacks = if let Some(mode) = heap_profile_on_freeze.get() {
             // TODO(nga): retained heap profile does not store information about data
             //   allocated in frozen heap before freeze starts.
             let heap_profile = AggregateHeapProfileInfo::collect(&heap, Some(HeapKind::Frozen));
+            let format = heap_profile_format
+                .get()
+                .unwrap_or_else(|| HeapProfileFormat::default_for(&mode.to_profile_mode()));
             Some(RetainedHeapProfile {
                 info: heap_profile,
                 mode,
+                format,
             })
         } else {
             None
@@ -431,6 +488,7 @@ impl Module {
             heap: freezer.into_ref(),
             module: frozen_module_ref,
             extra_value,
+            value,
             eval_duration: start.elapsed() + eval_duration.get(),
         })
     }
@@ -474,19 +532,37 @@ impl Module {
         }
     }
 
+    /// Get the value of `symbol` from `module`, which was loaded as `module_name`.
+    /// If there is no such symbol, the error suggests the exported symbols it might be a
+    /// typo for.
     pub(crate) fn load_symbol<'v>(
         &'v self,
+        module_name: &str,
         module: &FrozenModule,
         symbol: &str,
     ) -> anyhow::Result<Value<'v>> {
         if Self::default_visibility(symbol) != Visibility::Public {
             return Err(EnvironmentError::CannotImportPrivateSymbol(symbol.to_owned()).into());
         }
-        match module.get_any_visibility(symbol)? {
-            (v, Visibility::Public) => Ok(v.owned_value(self.frozen_heap())),
-            (_, Visibility::Private) => {
+        match module.get_any_visibility_option(symbol) {
+            Some((v, Visibility::Public)) => Ok(v.owned_value(self.frozen_heap())),
+            Some((_, Visibility::Private)) => {
                 Err(EnvironmentError::ModuleSymbolIsNotExported(symbol.to_owned()).into())
             }
+            None => {
+                let exported: Vec<_> = module.items().map(|(name, _)| name).collect();
+                let suggestions = near_misses(symbol, exported.iter().map(|x| x.as_str()))
+                    .into_iter()
+                    .take(3)
+                    .map(|x| x.to_owned())
+                    .collect();
+                Err(EnvironmentError::LoadedModuleHasNoSymbol(
+                    module_name.to_owned(),
+                    symbol.to_owned(),
+                    suggestions,
+                )
+                .into())
+            }
         }
     }
 
@@ -506,6 +582,10 @@ impl Module {
             extra_value.trace(tracer);
             self.set_extra_value(extra_value);
         }
+        if let Some(mut value) = self.value() {
+            value.trace(tracer);
+            self.set_value(value);
+        }
     }
 
     /// Field that can be used for any purpose you want.
@@ -520,6 +600,20 @@ impl Module {
         // Cast lifetime.
         unsafe { transmute!(Option<Value>, Option<Value>, self.extra_value.get()) }
     }
+
+    pub(crate) fn set_value<'v>(&'v self, v: Value<'v>) {
+        // Cast lifetime.
+        let v = unsafe { transmute!(Value, Value, v) };
+        self.value.set(Some(v));
+    }
+
+    /// The value of the expression statement at the end of the module, such as `x + 2` in
+    /// `x = 1; x + 2`, if [`Dialect::enable_module_value`](crate::syntax::Dialect::enable_module_value)
+    /// was set when it was parsed.
+    pub fn value<'v>(&'v self) -> Option<Value<'v>> {
+        // Cast lifetime.
+        unsafe { transmute!(Option<Value>, Option<Value>, self.value.get()) }
+    }
 }
 
 #[test]
@@ -533,7 +627,9 @@ where
 mod tests {
     use crate::environment::Globals;
     use crate::environment::Module;
+    use crate::eval::runtime::profile::pprof;
     use crate::eval::Evaluator;
+    use crate::eval::HeapProfileFormat;
     use crate::eval::ProfileMode;
     use crate::syntax::AstModule;
     use crate::syntax::Dialect;
@@ -567,4 +663,54 @@ x = f(1)
         assert!(profile_info.unused_capacity.get() > 0);
         assert!(heap_summary.contains("\"x.star.f\""), "{:?}", heap_summary);
     }
+
+    #[test]
+    fn test_gen_heap_pprof_profile() {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(&ProfileMode::HeapFlameRetained)
+            .unwrap();
+        eval.set_heap_profile_format(HeapProfileFormat::Pprof);
+        eval.eval_module(
+            AstModule::parse(
+                "x.star",
+                "def f(x):\n    return list([x])\nx = f(1)\n".to_owned(),
+                &Dialect::Extended,
+            )
+            .unwrap(),
+            &Globals::standard(),
+        )
+        .unwrap();
+        let module = module.freeze().unwrap();
+        let profile = module.heap_profile().unwrap();
+        assert!(profile.gen().is_err());
+        let strings = pprof::tests::strings(&profile.gen_bytes().unwrap());
+        for s in ["space", "x.star.f", "list", "unused_capacity"] {
+            assert!(strings.iter().any(|x| x == s), "{} {:?}", s, strings);
+        }
+    }
+
+    #[test]
+    fn test_module_value() {
+        let eval = |code: &str, enable_module_value| {
+            let module = Module::new();
+            let dialect = Dialect {
+                enable_module_value,
+                ..Dialect::Extended
+            };
+            let ast = AstModule::parse("x.star", code.to_owned(), &dialect).unwrap();
+            Evaluator::new(&module)
+                .eval_module(ast, &Globals::standard())
+                .unwrap();
+            let value = module.value().map(|x| x.to_string());
+            let frozen = module.freeze().unwrap();
+            assert_eq!(value, frozen.value().map(|x| x.value().to_string()));
+            value
+        };
+
+        assert_eq!(eval("x = [1]\nx + [2]", true), Some("[1, 2]".to_owned()));
+        assert_eq!(eval("x = [1]\nx + [2]", false), None);
+        // The value of a trailing assignment is not the value of the module
+        assert_eq!(eval("x = 1\ny = x", true), None);
+    }
 }
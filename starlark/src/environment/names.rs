@@ -53,6 +53,10 @@ impl MutableNames {
         Self(RefCell::new(SmallMap::new()))
     }
 
+    pub(crate) fn from_map(names: SmallMap<FrozenStringValue, (ModuleSlotId, Visibility)>) -> Self {
+        Self(RefCell::new(names))
+    }
+
     pub fn slot_count(&self) -> u32 {
         self.0.borrow().len().try_into().unwrap()
     }
@@ -120,6 +124,10 @@ impl MutableNames {
             .collect()
     }
 
+    pub(crate) fn into_map(self) -> SmallMap<FrozenStringValue, (ModuleSlotId, Visibility)> {
+        self.0.into_inner()
+    }
+
     pub fn freeze(self) -> FrozenNames {
         FrozenNames(self.0.into_inner())
     }
@@ -48,6 +48,10 @@ impl<'v> MutableSlots<'v> {
         Self(RefCell::new(Vec::new()))
     }
 
+    pub(crate) fn from_vec(slots: Vec<Option<Value<'v>>>) -> Self {
+        Self(RefCell::new(slots))
+    }
+
     pub(crate) fn get_slots_mut(&self) -> RefMut<Vec<Option<Value<'v>>>> {
         self.0.borrow_mut()
     }
@@ -0,0 +1,254 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use allocative::Allocative;
+
+use crate::collections::Hashed;
+use crate::collections::SmallMap;
+use crate::environment::names::MutableNames;
+use crate::environment::slots::ModuleSlotId;
+use crate::environment::slots::MutableSlots;
+use crate::environment::EnvironmentError;
+use crate::environment::FrozenModule;
+use crate::environment::Module;
+use crate::syntax::ast::Visibility;
+use crate::values::FrozenHeap;
+use crate::values::FrozenHeapRef;
+use crate::values::FrozenStringValue;
+use crate::values::FrozenValue;
+use crate::values::Value;
+
+/// A recipe for creating many [`Module`]s with nearly identical scopes.
+///
+/// Build systems typically evaluate thousands of files, each starting with the
+/// public symbols of the same prelude plus a few globals specific to that file,
+/// such as the package being evaluated. Calling
+/// [`import_public_symbols`](Module::import_public_symbols) and [`set`](Module::set)
+/// for every file interns and hashes every name again. A template does that work
+/// once, so [`instantiate`](ModuleTemplate::instantiate) only copies the prepared
+/// tables.
+///
+/// ```
+/// # use starlark::environment::{FrozenModule, Globals, Module, ModuleTemplate};
+/// # use starlark::eval::Evaluator;
+/// # use starlark::syntax::{AstModule, Dialect};
+/// let prelude = Module::new();
+/// prelude.set("greeting", prelude.heap().alloc("hello"));
+/// let prelude = prelude.freeze().unwrap();
+///
+/// let template = ModuleTemplate::new(&[&prelude], &["package"]);
+/// for package in ["foo", "bar"] {
+///     let module = template.instantiate();
+///     template
+///         .set_parameter(&module, "package", module.heap().alloc(package))
+///         .unwrap();
+///     let ast = AstModule::parse(
+///         "BUILD",
+///         "greeting + ' ' + package".to_owned(),
+///         &Dialect::Standard,
+///     )
+///     .unwrap();
+///     let res = Evaluator::new(&module)
+///         .eval_module(ast, &Globals::standard())
+///         .unwrap();
+///     assert_eq!(res.unpack_str(), Some(format!("hello {}", package).as_str()));
+/// }
+/// ```
+#[derive(Debug, Allocative)]
+pub struct ModuleTemplate {
+    /// Heaps of the base modules and of the parameter names.
+    heaps: Vec<FrozenHeapRef>,
+    names: SmallMap<FrozenStringValue, (ModuleSlotId, Visibility)>,
+    slots: Vec<Option<FrozenValue>>,
+    parameters: SmallMap<String, ModuleSlotId>,
+}
+
+impl ModuleTemplate {
+    /// Create a template whose modules start with the public symbols of `bases`,
+    /// later bases shadowing earlier ones, and have a global for each of `parameters`.
+    ///
+    /// Like imported symbols, none of these are exported by the instantiated modules.
+    /// Parameters shadow symbols of the bases, and are unassigned until set with
+    /// [`set_parameter`](ModuleTemplate::set_parameter).
+    pub fn new(bases: &[&FrozenModule], parameters: &[&str]) -> Self {
+        let names = MutableNames::new();
+        let mut slots = Vec::new();
+        let mut heaps = Vec::with_capacity(bases.len() + 1);
+        for base in bases {
+            heaps.push(base.frozen_heap().clone());
+            for (name, value) in base.items() {
+                if Module::default_visibility(&name) == Visibility::Public {
+                    Self::set(&names, &mut slots, name, Some(value));
+                }
+            }
+        }
+
+        let heap = FrozenHeap::new();
+        let parameters = parameters
+            .iter()
+            .map(|name| {
+                let slot = Self::set(&names, &mut slots, heap.alloc_str_intern(name), None);
+                ((*name).to_owned(), slot)
+            })
+            .collect();
+        heaps.push(heap.into_ref());
+
+        Self {
+            heaps,
+            names: names.into_map(),
+            slots,
+            parameters,
+        }
+    }
+
+    fn set(
+        names: &MutableNames,
+        slots: &mut Vec<Option<FrozenValue>>,
+        name: FrozenStringValue,
+        value: Option<FrozenValue>,
+    ) -> ModuleSlotId {
+        let slot = names.add_name_visibility(name, Visibility::Private);
+        let index = slot.0 as usize;
+        if slots.len() <= index {
+            slots.resize(index + 1, None);
+        }
+        slots[index] = value;
+        slot
+    }
+
+    /// The names of the per-instance globals, in the order they were given.
+    pub fn parameters(&self) -> impl Iterator<Item = &str> {
+        self.parameters.keys().map(|x| x.as_str())
+    }
+
+    /// Create a new module from this template. Its parameters are unassigned.
+    pub fn instantiate(&self) -> Module {
+        let module = Module::with_names_and_slots(
+            MutableNames::from_map(self.names.clone()),
+            MutableSlots::from_vec(
+                self.slots
+                    .iter()
+                    .map(|x| x.map(Value::new_frozen))
+                    .collect(),
+            ),
+        );
+        for heap in &self.heaps {
+            module.frozen_heap().add_reference(heap);
+        }
+        module
+    }
+
+    /// Set the value of the parameter `name` in a module created by
+    /// [`instantiate`](ModuleTemplate::instantiate) on this template.
+    ///
+    /// Fails if the template has no such parameter, or if `module` was not created
+    /// from this template and so keeps `name` somewhere else, if at all.
+    pub fn set_parameter<'v>(
+        &self,
+        module: &'v Module,
+        name: &str,
+        value: Value<'v>,
+    ) -> anyhow::Result<()> {
+        let slot = match self.parameters.get(name) {
+            Some(slot) => *slot,
+            None => return Err(EnvironmentError::NoSuchTemplateParameter(name.to_owned()).into()),
+        };
+        match module.names().get_name(Hashed::new(name)) {
+            Some((module_slot, _)) if module_slot == slot => {
+                module.slots().set_slot(slot, value);
+                Ok(())
+            }
+            _ => Err(EnvironmentError::NotTemplateInstance(name.to_owned()).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Globals;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn base(code: &str) -> FrozenModule {
+        let module = Module::new();
+        let ast = AstModule::parse("base.star", code.to_owned(), &Dialect::Extended).unwrap();
+        Evaluator::new(&module)
+            .eval_module(ast, &Globals::standard())
+            .unwrap();
+        module.freeze().unwrap()
+    }
+
+    fn eval(module: &Module, code: &str) -> anyhow::Result<String> {
+        let ast = AstModule::parse("BUILD", code.to_owned(), &Dialect::Extended)?;
+        let res = Evaluator::new(module).eval_module(ast, &Globals::standard())?;
+        Ok(res.to_str())
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let first = base("x = 1\ny = 2\n_hidden = 3\ndef f(): return x");
+        let second = base("y = 20\nctx = 'shadowed'");
+        let template = ModuleTemplate::new(&[&first, &second], &["ctx"]);
+        assert_eq!(vec!["ctx"], template.parameters().collect::<Vec<_>>());
+
+        for ctx in ["a", "b"] {
+            let module = template.instantiate();
+            template
+                .set_parameter(&module, "ctx", module.heap().alloc(ctx))
+                .unwrap();
+            assert_eq!(
+                format!("[1, 20, 1, \"{}\"]", ctx),
+                eval(&module, "z = [x, y, f(), ctx]\nz").unwrap()
+            );
+            assert!(eval(&module, "_hidden").is_err());
+
+            let frozen = module.freeze().unwrap();
+            assert!(frozen.get("z").is_ok());
+            // Neither the base symbols nor the parameters are re-exported.
+            assert!(frozen.get("x").is_err());
+            assert!(frozen.get("ctx").is_err());
+        }
+    }
+
+    #[test]
+    fn test_unset_parameter() {
+        let template = ModuleTemplate::new(&[], &["ctx"]);
+        let module = template.instantiate();
+        assert!(eval(&module, "ctx").is_err());
+        assert!(template
+            .set_parameter(&module, "unknown", Value::new_none())
+            .is_err());
+    }
+
+    #[test]
+    fn test_set_parameter_other_module() {
+        let template = ModuleTemplate::new(&[], &["ctx"]);
+        let other = ModuleTemplate::new(&[], &["before", "ctx"]).instantiate();
+        assert!(template
+            .set_parameter(&other, "ctx", Value::new_none())
+            .unwrap_err()
+            .to_string()
+            .contains("not created from this template"));
+        let module = Module::new();
+        assert!(template
+            .set_parameter(&module, "ctx", Value::new_none())
+            .is_err());
+        assert_eq!(0, module.names().slot_count());
+    }
+}
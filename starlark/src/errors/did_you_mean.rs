@@ -24,8 +24,16 @@ pub(crate) fn did_you_mean<'a>(
     value: &str,
     variants: impl IntoIterator<Item = &'a str>,
 ) -> Option<&'a str> {
+    near_misses(value, variants).into_iter().next()
+}
+
+/// All the plausible suggestions for a typo, best first.
+pub(crate) fn near_misses<'a>(
+    value: &str,
+    variants: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
     if value.is_empty() {
-        return None;
+        return Vec::new();
     }
 
     let max_dist = if value.len() <= 2 {
@@ -35,17 +43,20 @@ pub(crate) fn did_you_mean<'a>(
         2
     };
 
-    variants
+    let mut res: Vec<_> = variants
         .into_iter()
         .map(|v| (v, levenshtein(value, v)))
         .filter(|(_, dist)| *dist <= max_dist)
-        .min_by_key(|(_v, sim)| *sim)
-        .map(|(v, _)| v)
+        .collect();
+    // Stable, so equally good suggestions stay in the order given
+    res.sort_by_key(|(_v, dist)| *dist);
+    res.into_iter().map(|(v, _)| v).collect()
 }
 
 #[cfg(test)]
 mod tests {
     use crate::errors::did_you_mean::did_you_mean;
+    use crate::errors::did_you_mean::near_misses;
 
     #[test]
     fn prefixes() {
@@ -92,4 +103,13 @@ mod tests {
         assert_eq!(Some("aaaay"), did_you_mean("aaaax", vec!["aaaay", "aaaaz"]));
         assert_eq!(Some("aaaaz"), did_you_mean("aaaax", vec!["aaaaz", "aaaay"]));
     }
+
+    #[test]
+    fn all_near_misses() {
+        assert_eq!(
+            vec!["colour", "colors", "colourr"],
+            near_misses("color", vec!["colourr", "colour", "shape", "colors"])
+        );
+        assert!(near_misses("", vec!["a"]).is_empty());
+    }
 }
@@ -106,7 +106,7 @@ impl Frame {
                 "{}* {}:{}, in {}",
                 indent,
                 location.file.filename(),
-                location.file.find_line(location.span.begin()) + 1,
+                location.resolve_span().begin_line + 1,
                 // Note we print caller function here as in Python, not callee,
                 // so in the stack trace, top frame is printed without executed function name.
                 caller,
@@ -229,8 +229,8 @@ fn get_display_list_for_diagnostic<'a>(
         // we want the source_span to capture any whitespace ahead of the diagnostic span to
         // get the column numbers correct in the DisplayList, and any trailing source code
         // on the last line for context.
-        let first_line_span = span.file.line_span(region.begin_line);
-        let last_line_span = span.file.line_span(region.end_line);
+        let first_line_span = span.file.line_span(span.file.find_line(span.span.begin()));
+        let last_line_span = span.file.line_span(span.file.find_line(span.span.end()));
         let source_span = span.span.merge(first_line_span).merge(last_line_span);
 
         Slice {
@@ -19,6 +19,7 @@
 
 use crate::collections::symbol_map::Symbol;
 use crate::eval::bc::compiler::expr::write_n_exprs;
+use crate::eval::bc::inline_cache::AttrCache;
 use crate::eval::bc::instr_impl::InstrAddAssign;
 use crate::eval::bc::instr_impl::InstrArrayIndex;
 use crate::eval::bc::instr_impl::InstrArrayIndexSet;
@@ -101,7 +102,12 @@ impl AssignModifyLhs {
                         let field = Symbol::new(field.as_str());
                         bc.write_instr::<InstrObjectField>(
                             span,
-                            (object, field.clone(), lhs_rhs.get::<0>().to_out()),
+                            (
+                                object,
+                                field.clone(),
+                                AttrCache::default(),
+                                lhs_rhs.get::<0>().to_out(),
+                            ),
                         );
                         rhs.write_bc(lhs_rhs.get::<1>().to_out(), bc);
                         op.write_bc(
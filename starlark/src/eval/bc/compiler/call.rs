@@ -24,6 +24,7 @@ use crate::eval::bc::call::BcCallArgsFull;
 use crate::eval::bc::call::BcCallArgsPos;
 use crate::eval::bc::compiler::expr::write_expr_opt;
 use crate::eval::bc::compiler::expr::write_exprs;
+use crate::eval::bc::inline_cache::AttrCache;
 use crate::eval::bc::instr_impl::InstrCall;
 use crate::eval::bc::instr_impl::InstrCallFrozen;
 use crate::eval::bc::instr_impl::InstrCallFrozenDef;
@@ -294,6 +295,7 @@ impl IrSpanned<CallCompiled> {
                                 (
                                     this,
                                     symbol.clone(),
+                                    AttrCache::default(),
                                     BcCallArgsPos { pos },
                                     file_span,
                                     target,
@@ -313,7 +315,14 @@ impl IrSpanned<CallCompiled> {
                         } else {
                             bc.write_instr::<InstrCallMethod>(
                                 span,
-                                (this, symbol.clone(), args, file_span, target),
+                                (
+                                    this,
+                                    symbol.clone(),
+                                    AttrCache::default(),
+                                    args,
+                                    file_span,
+                                    target,
+                                ),
                             );
                         }
                     })
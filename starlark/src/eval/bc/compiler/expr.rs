@@ -24,6 +24,7 @@ use gazebo::prelude::*;
 use crate::collections::Hashed;
 use crate::collections::SmallMap;
 use crate::eval::bc::compiler::if_compiler::write_if_else;
+use crate::eval::bc::inline_cache::AttrCache;
 use crate::eval::bc::instr_impl::*;
 use crate::eval::bc::slow_arg::BcInstrSlowArg;
 use crate::eval::bc::stack_ptr::BcSlot;
@@ -360,7 +361,10 @@ impl IrSpanned<ExprCompiled> {
                             bc.write_instr::<InstrFormatOne>(span, (*before, expr, *after, target))
                         }
                         Builtin1::Dot(field) => {
-                            bc.write_instr::<InstrObjectField>(span, (expr, field.clone(), target))
+                            bc.write_instr::<InstrObjectField>(
+                                span,
+                                (expr, field.clone(), AttrCache::default(), target),
+                            )
                         }
                     }
                 });
@@ -0,0 +1,96 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Inline caches for attribute and method lookup.
+
+use std::ptr;
+
+use once_cell::sync::OnceCell;
+
+use crate::collections::symbol_map::Symbol;
+use crate::environment::Methods;
+use crate::values::layout::value_not_special::FrozenValueNotSpecial;
+
+/// Cache of the member which `x.attr` finds in the [`Methods`] of the type of `x`,
+/// stored in the instruction doing the lookup.
+///
+/// The cache is keyed by the `Methods` pointer, which is unique per type, and holds
+/// the member found for the first type seen. Lookups for other types are not cached.
+/// Members are frozen values, so the cache cannot hold values of a heap being frozen,
+/// and the bytecode of frozen functions is compiled again on freeze, with empty caches.
+#[derive(Default)]
+pub(crate) struct AttrCache {
+    // `OnceCell` because frozen bytecode is executed concurrently.
+    cell: OnceCell<(&'static Methods, FrozenValueNotSpecial)>,
+}
+
+impl AttrCache {
+    /// Find `attribute` in `methods`, using the cache if present.
+    #[inline(always)]
+    pub(crate) fn get_member(
+        cache: Option<&AttrCache>,
+        methods: &'static Methods,
+        attribute: &Symbol,
+    ) -> Option<FrozenValueNotSpecial> {
+        match cache {
+            None => methods.get_frozen_symbol(attribute),
+            Some(cache) => cache.get_member_cached(methods, attribute),
+        }
+    }
+
+    #[inline(always)]
+    fn get_member_cached(
+        &self,
+        methods: &'static Methods,
+        attribute: &Symbol,
+    ) -> Option<FrozenValueNotSpecial> {
+        match self.cell.get() {
+            Some((cached, member)) if ptr::eq(*cached, methods) => Some(*member),
+            Some(_) => methods.get_frozen_symbol(attribute),
+            None => {
+                let member = methods.get_frozen_symbol(attribute)?;
+                // Another thread may have filled the cache meanwhile, which is fine.
+                let _ = self.cell.set((methods, member));
+                Some(member)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_attr_cache_polymorphic() {
+        // The same call sites see different types, and must not use the member of another.
+        assert::pass(
+            r#"
+def f(x):
+    return x.union([3])
+def g(x):
+    return x.pop
+assert_eq(f(set([1])), set([1, 3]))
+assert_eq(f(struct(union = lambda y: y)), [3])
+assert_eq(f(set([2])), set([2, 3]))
+assert_eq(g([1, 2])(), 2)
+assert_eq(g({1: 2})(1), 2)
+assert_eq(g([3])(), 3)
+"#,
+        );
+    }
+}
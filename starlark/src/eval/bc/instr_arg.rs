@@ -33,6 +33,7 @@ use crate::eval::bc::addr::BcAddrOffset;
 use crate::eval::bc::addr::BcPtrAddr;
 use crate::eval::bc::call::BcCallArgsFull;
 use crate::eval::bc::call::BcCallArgsPos;
+use crate::eval::bc::inline_cache::AttrCache;
 use crate::eval::bc::instr::BcInstr;
 use crate::eval::bc::instr_impl::InstrDefData;
 use crate::eval::bc::native_function::BcNativeFunction;
@@ -574,6 +575,19 @@ impl BcInstrArg for Vec<(BcAddr, BcInstrSlowArg)> {
     fn visit_jump_addr(_param: &Self, _consumer: &mut dyn FnMut(BcAddrOffset)) {}
 }
 
+impl BcInstrArg for AttrCache {
+    fn fmt_append(
+        _param: &Self,
+        _ip: BcAddr,
+        _end_arg: Option<&BcInstrEndArg>,
+        _f: &mut dyn Write,
+    ) -> fmt::Result {
+        Ok(())
+    }
+
+    fn visit_jump_addr(_param: &Self, _consumer: &mut dyn FnMut(BcAddrOffset)) {}
+}
+
 impl BcInstrArg for Symbol {
     fn fmt_append(
         param: &Self,
@@ -38,6 +38,7 @@ use crate::eval::bc::call::BcCallArgsForDef;
 use crate::eval::bc::call::BcCallArgsFull;
 use crate::eval::bc::call::BcCallArgsPos;
 use crate::eval::bc::frame::BcFramePtr;
+use crate::eval::bc::inline_cache::AttrCache;
 use crate::eval::bc::instr::BcInstr;
 use crate::eval::bc::instr::InstrControl;
 use crate::eval::bc::instr_arg::BcInstrArg;
@@ -53,8 +54,6 @@ use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::FrozenDef;
 use crate::eval::compiler::def::ParameterCompiled;
 use crate::eval::compiler::def::ParametersCompiled;
-use crate::eval::compiler::expr::get_attr_hashed_bind;
-use crate::eval::compiler::expr::get_attr_hashed_raw;
 use crate::eval::compiler::expr::EvalError;
 use crate::eval::compiler::expr::MemberOrValue;
 use crate::eval::compiler::expr_throw;
@@ -64,7 +63,6 @@ use crate::eval::compiler::stmt::before_stmt;
 use crate::eval::compiler::stmt::bit_or_assign;
 use crate::eval::compiler::stmt::possible_gc;
 use crate::eval::compiler::stmt::AssignError;
-use crate::eval::compiler::EvalException;
 use crate::eval::runtime::arguments::ResolvedArgName;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
@@ -368,17 +366,17 @@ impl InstrNoFlowImpl for InstrArrayIndexSetImpl {
 }
 
 impl InstrNoFlowImpl for InstrObjectFieldImpl {
-    type Arg = (BcSlotIn, Symbol, BcSlotOut);
+    type Arg = (BcSlotIn, Symbol, AttrCache, BcSlotOut);
 
     #[inline(always)]
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_>,
         frame: BcFramePtr<'v>,
         _ip: BcPtrAddr,
-        (object, field, target): &(BcSlotIn, Symbol, BcSlotOut),
+        (object, field, cache, target): &(BcSlotIn, Symbol, AttrCache, BcSlotOut),
     ) -> anyhow::Result<()> {
         let object = frame.get_bc_slot(*object);
-        let value = get_attr_hashed_bind(object, field, eval.heap())?;
+        let value = eval.get_attr_bind(object, field, Some(cache))?;
         frame.set_bc_slot(*target, value);
         Ok(())
     }
@@ -399,7 +397,7 @@ impl InstrNoFlowImpl for InstrObjectFieldRawImpl {
         (object, field, target): &(BcSlotIn, Symbol, BcSlotOut),
     ) -> anyhow::Result<()> {
         let object = frame.get_bc_slot(*object);
-        let value = get_attr_hashed_raw(object, field, eval.heap())?;
+        let value = eval.get_attr_raw(object, field, None)?;
         frame.set_bc_slot(*target, value.to_value());
         Ok(())
     }
@@ -1212,18 +1210,22 @@ impl BcInstr for InstrForLoop {
         enum LoopResult<'v> {
             Ok,
             Return(Value<'v>),
-            Err(EvalException),
+            Err(anyhow::Error),
         }
 
         let iter_ret = collection.with_iterator(eval.heap(), |iter| {
             let loop_start = ip.add_instr::<Self>();
             for item in iter {
+                // Loops are where runaway scripts spend their time, so enforce the limits here.
+                if let Err(e) = eval.check_limits() {
+                    return LoopResult::Err(e);
+                }
                 frame.set_bc_slot(*var, item);
                 match run_block(eval, loop_start) {
                     RunBlockResult::Continue => {}
                     RunBlockResult::Break => return LoopResult::Ok,
                     RunBlockResult::Return(v) => return LoopResult::Return(v),
-                    RunBlockResult::Err(e) => return LoopResult::Err(e),
+                    RunBlockResult::Err(e) => return LoopResult::Err(e.0),
                 }
             }
             LoopResult::Ok
@@ -1231,7 +1233,7 @@ impl BcInstr for InstrForLoop {
         match iter_ret {
             Ok(LoopResult::Ok) => InstrControl::Next(ip.add_rel(*loop_end)),
             Ok(LoopResult::Return(v)) => InstrControl::Return(v),
-            Ok(LoopResult::Err(e)) => InstrControl::Err(e.0),
+            Ok(LoopResult::Err(e)) => InstrControl::Err(e),
             Err(e) => InstrControl::Err(e),
         }
     }
@@ -1565,12 +1567,13 @@ fn call_method_common<'v>(
     frame: BcFramePtr<'v>,
     this: Value<'v>,
     symbol: &Symbol,
+    cache: Option<&AttrCache>,
     arguments: &Arguments<'v, '_>,
     span: FrozenRef<'static, FrameSpan>,
     target: BcSlotOut,
 ) -> anyhow::Result<()> {
     // TODO: wrong span: should be span of `object.method`, not of the whole expression
-    let method = get_attr_hashed_raw(this, symbol, eval.heap())?;
+    let method = eval.get_attr_raw(this, symbol, cache)?;
     let r = match method {
         MemberOrValue::Member(member) => member.invoke_method(this, span, arguments, eval)?,
         MemberOrValue::Value(value) => value.invoke_with_loc(Some(span), arguments, eval)?,
@@ -1604,13 +1607,14 @@ fn call_maybe_known_method_common<'v>(
         }
     }
 
-    call_method_common(eval, frame, this, symbol, arguments, span, target)
+    call_method_common(eval, frame, this, symbol, None, arguments, span, target)
 }
 
 impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallMethodImpl<A> {
     type Arg = (
         BcSlotIn,
         Symbol,
+        AttrCache,
         A,
         FrozenRef<'static, FrameSpan>,
         BcSlotOut,
@@ -1621,9 +1625,10 @@ impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallMethodImpl<A> {
         eval: &mut Evaluator<'v, '_>,
         frame: BcFramePtr<'v>,
         _ip: BcPtrAddr,
-        (this, symbol, args, span, target): &(
+        (this, symbol, cache, args, span, target): &(
             BcSlotIn,
             Symbol,
+            AttrCache,
             A,
             FrozenRef<'static, FrameSpan>,
             BcSlotOut,
@@ -1631,7 +1636,16 @@ impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallMethodImpl<A> {
     ) -> anyhow::Result<()> {
         let this = frame.get_bc_slot(*this);
         let arguments = Arguments(args.pop_from_stack(frame));
-        call_method_common(eval, frame, this, symbol, &arguments, *span, *target)
+        call_method_common(
+            eval,
+            frame,
+            this,
+            symbol,
+            Some(cache),
+            &arguments,
+            *span,
+            *target,
+        )
     }
 }
 
@@ -1696,7 +1710,7 @@ impl InstrNoFlowImpl for InstrPossibleGcImpl {
         (): &(),
     ) -> anyhow::Result<()> {
         possible_gc(eval);
-        Ok(())
+        eval.check_limits()
     }
 }
 
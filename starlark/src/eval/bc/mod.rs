@@ -24,6 +24,7 @@ pub(crate) mod compiler;
 pub(crate) mod definitely_assigned;
 pub(crate) mod frame;
 pub(crate) mod if_debug;
+pub(crate) mod inline_cache;
 pub(crate) mod instr;
 pub(crate) mod instr_arg;
 pub(crate) mod instr_impl;
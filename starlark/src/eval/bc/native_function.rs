@@ -62,6 +62,7 @@ impl BcNativeFunction {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        self.imp.invoke(eval, args)
+        eval.check_builtin_policy(&self.fun.name)?;
+        eval.invoke_native(&self.fun.name, args, |eval| self.imp.invoke(eval, args))
     }
 }
@@ -134,8 +134,9 @@ impl CallCompiled {
             return None;
         }
 
+        let cross_module = ctx.cross_module_inlining();
         let expr = if let Some(InlineDefBody::ReturnSafeToInlineExpr(expr)) =
-            &fun.def_info.inline_def_body
+            fun.inline_def_body(cross_module)
         {
             expr
         } else {
@@ -209,6 +210,12 @@ impl CallCompiled {
 
         let eval = ctx.eval()?;
 
+        // A call made now is not made again at runtime, so a hook or call log would
+        // see it at the wrong time, and a result mocked by the hook would be baked in.
+        if eval.call_hook.is_some() || eval.call_log.is_some() {
+            return None;
+        }
+
         // Only if all call arguments are frozen values.
         args.all_values(|arguments| {
             let v = fun.to_value().invoke(arguments.frozen_to_v(), eval).ok()?;
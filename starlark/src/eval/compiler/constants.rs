@@ -19,6 +19,7 @@ use dupe::Dupe;
 use once_cell::sync::Lazy;
 
 use crate::environment::Globals;
+use crate::environment::LibraryExtension;
 use crate::values::FrozenValue;
 
 #[derive(Copy, Clone, Dupe, Debug)]
@@ -42,6 +43,8 @@ impl PartialEq<BuiltinFn> for FrozenValue {
 pub(crate) struct Constants {
     pub(crate) fn_len: BuiltinFn,
     pub(crate) fn_type: BuiltinFn,
+    /// What set literals call, available even without [`LibraryExtension::Set`].
+    pub(crate) fn_set: BuiltinFn,
 }
 
 impl Constants {
@@ -51,6 +54,11 @@ impl Constants {
             Constants {
                 fn_len: BuiltinFn(g.get_frozen("len").unwrap()),
                 fn_type: BuiltinFn(g.get_frozen("type").unwrap()),
+                fn_set: BuiltinFn(
+                    Globals::extended_by(&[LibraryExtension::Set])
+                        .get_frozen("set")
+                        .unwrap(),
+                ),
             }
         });
         Lazy::force(&RES)
@@ -72,5 +80,9 @@ mod tests {
             Globals::extended().get_frozen("len").unwrap(),
             Constants::get().fn_len
         );
+        assert_eq!(
+            Globals::extended().get_frozen("set").unwrap(),
+            Constants::get().fn_set
+        );
     }
 }
@@ -46,6 +46,7 @@ use crate::environment::Globals;
 use crate::eval::bc::bytecode::Bc;
 use crate::eval::bc::frame::alloca_frame;
 use crate::eval::compiler::def_inline::inline_def_body;
+use crate::eval::compiler::def_inline::inline_frozen_def_body;
 use crate::eval::compiler::def_inline::InlineDefBody;
 use crate::eval::compiler::expr::ExprCompiled;
 use crate::eval::compiler::opt_ctx::OptCtx;
@@ -93,10 +94,10 @@ enum DefError {
     CheckReturnTypeNoType,
 }
 
-/// Store frozen `StmtCompiled`.
+/// Store frozen `StmtCompiled` and the inline body computed from it.
 /// This is initialized in `post_freeze`.
 struct StmtCompiledCell {
-    cell: UnsafeCell<Bc>,
+    cell: UnsafeCell<(Bc, Option<InlineDefBody>)>,
 }
 
 unsafe impl<'v> Trace<'v> for StmtCompiledCell {
@@ -111,18 +112,22 @@ unsafe impl Send for StmtCompiledCell {}
 impl StmtCompiledCell {
     fn new() -> StmtCompiledCell {
         StmtCompiledCell {
-            cell: UnsafeCell::new(Bc::default()),
+            cell: UnsafeCell::new((Bc::default(), None)),
         }
     }
 
     /// This function is unsafe if other thread is executing the stmt.
-    unsafe fn set(&self, value: Bc) {
+    unsafe fn set(&self, value: Bc, inline_def_body: Option<InlineDefBody>) {
         ptr::drop_in_place(self.cell.get());
-        ptr::write(self.cell.get(), value);
+        ptr::write(self.cell.get(), (value, inline_def_body));
     }
 
     fn get(&self) -> &Bc {
-        unsafe { &*self.cell.get() }
+        unsafe { &(*self.cell.get()).0 }
+    }
+
+    fn inline_def_body(&self) -> Option<&InlineDefBody> {
+        unsafe { (*self.cell.get()).1.as_ref() }
     }
 }
 
@@ -590,6 +595,15 @@ impl<'v, T1: ValueLike<'v>> DefGen<T1> {
     }
 }
 
+impl FrozenDef {
+    /// Whether this function was defined in `module`, rather than loaded into it.
+    pub(crate) fn is_defined_in(&self, module: &FrozenModuleData) -> bool {
+        self.module
+            .load_relaxed()
+            .map_or(false, |m| ptr::eq(&*m, module))
+    }
+}
+
 impl<'v> Freeze for Def<'v> {
     type Frozen = FrozenDef;
 
@@ -809,29 +823,51 @@ impl FrozenDef {
 
         // Now perform the optimization of function body with fully frozen module:
         // all module variables are frozen, so we can inline more aggressively.
-        let body_optimized = self
-            .def_info
-            .body_stmts
-            .optimize(&mut OptCtx::new(
-                &mut OptimizeOnFreezeContext {
-                    module: def_module.as_ref(),
-                    heap,
-                    frozen_heap,
-                },
-                self.parameters.len().try_into().unwrap(),
-            ))
-            .as_bc(
-                &self.def_info.stmt_compile_context,
-                self.def_info.used,
-                self.parameters.len() as u32,
+        let body_optimized = self.def_info.body_stmts.optimize(&mut OptCtx::new(
+            &mut OptimizeOnFreezeContext {
+                module: def_module.as_ref(),
+                heap,
                 frozen_heap,
-            );
+            },
+            self.parameters.len().try_into().unwrap(),
+        ));
+
+        // The body may have become safe to inline into other modules,
+        // for example, if it only calls other functions of this module.
+        let inline_def_body = if self.parameters.has_args_or_kwargs()
+            || !self.parameter_types.is_empty()
+            || self.return_type.is_some()
+        {
+            None
+        } else {
+            inline_frozen_def_body(self.parameters.len() as u32, &body_optimized)
+        };
+
+        let bc = body_optimized.as_bc(
+            &self.def_info.stmt_compile_context,
+            self.def_info.used,
+            self.parameters.len() as u32,
+            frozen_heap,
+        );
 
         // Store the optimized body.
         // This is (relatively) safe because we know that during freeze
         // nobody has a reference to stmt: nobody is executing this `def`.
         unsafe {
-            self.optimized_on_freeze_stmt.set(body_optimized);
+            self.optimized_on_freeze_stmt.set(bc, inline_def_body);
         }
     }
+
+    /// Body to inline at call sites in modules loading this function.
+    ///
+    /// Prefers the body computed on freeze, which can inline module variables,
+    /// unless cross-module inlining is disabled.
+    pub(crate) fn inline_def_body(&self, cross_module: bool) -> Option<&InlineDefBody> {
+        let frozen = if cross_module {
+            self.optimized_on_freeze_stmt.inline_def_body()
+        } else {
+            None
+        };
+        frozen.or(self.def_info.inline_def_body.as_ref())
+    }
 }
@@ -59,18 +59,28 @@ fn is_return_type_is(stmt: &StmtsCompiled) -> Option<FrozenStringValue> {
     }
 }
 
+/// Do not inline functions with more expressions than this.
+const MAX_INLINE_EXPRS: u32 = 100;
+
+/// Do not inline functions with more expressions than this into other modules
+/// using the body optimized on freeze: only trivial wrappers are worth it.
+const MAX_FROZEN_INLINE_EXPRS: u32 = 20;
+
 struct IsSafeToInlineExpr {
     /// Function parameter count.
     param_count: u32,
     /// How many expressions we visited already.
     counter: u32,
+    /// Maximum number of expressions to visit.
+    max_exprs: u32,
 }
 
 impl IsSafeToInlineExpr {
-    fn new(param_count: u32) -> IsSafeToInlineExpr {
+    fn new(param_count: u32, max_exprs: u32) -> IsSafeToInlineExpr {
         Self {
             param_count,
             counter: 0,
+            max_exprs,
         }
     }
 
@@ -85,7 +95,7 @@ impl IsSafeToInlineExpr {
     /// Expression which is has no access to locals or globals.
     fn is_safe_to_inline_expr(&mut self, expr: &ExprCompiled) -> bool {
         // Do not inline too large functions.
-        if self.counter > 100 {
+        if self.counter > self.max_exprs {
             return false;
         }
         self.counter += 1;
@@ -154,6 +164,7 @@ impl IsSafeToInlineExpr {
 fn is_return_safe_to_inline_expr(
     stmts: &StmtsCompiled,
     param_count: u32,
+    max_exprs: u32,
 ) -> Option<IrSpanned<ExprCompiled>> {
     match stmts.first() {
         None => {
@@ -165,7 +176,7 @@ fn is_return_safe_to_inline_expr(
         }
         Some(stmt) => match &stmt.node {
             StmtCompiled::Return(expr)
-                if IsSafeToInlineExpr::new(param_count).is_safe_to_inline_expr(expr) =>
+                if IsSafeToInlineExpr::new(param_count, max_exprs).is_safe_to_inline_expr(expr) =>
             {
                 Some(expr.clone())
             }
@@ -187,13 +198,26 @@ pub(crate) fn inline_def_body(
         // It is possible to sometimes inline functions with `*args` or `**kwargs`,
         // but let's postpone that for now.
         let param_count = params.count_param_variables();
-        if let Some(expr) = is_return_safe_to_inline_expr(body, param_count) {
+        if let Some(expr) = is_return_safe_to_inline_expr(body, param_count, MAX_INLINE_EXPRS) {
             return Some(InlineDefBody::ReturnSafeToInlineExpr(expr));
         }
     }
     None
 }
 
+/// Inline body of a frozen function computed from its body optimized on freeze.
+///
+/// Module variables are constants in that body, so functions like
+/// `def wrapper(x): return _helper(x)` become safe to inline into modules which load them.
+/// The caller must check the function has no `*args`, `**kwargs` or type annotations.
+pub(crate) fn inline_frozen_def_body(
+    param_count: u32,
+    body: &StmtsCompiled,
+) -> Option<InlineDefBody> {
+    is_return_safe_to_inline_expr(body, param_count, MAX_FROZEN_INLINE_EXPRS)
+        .map(InlineDefBody::ReturnSafeToInlineExpr)
+}
+
 pub(crate) struct CannotInline;
 
 /// Utility to inline function body at call site.
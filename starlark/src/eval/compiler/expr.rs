@@ -27,6 +27,7 @@ use crate::codemap::Spanned;
 use crate::collections::symbol_map::Symbol;
 use crate::environment::slots::ModuleSlotId;
 use crate::errors::did_you_mean::did_you_mean;
+use crate::eval::bc::inline_cache::AttrCache;
 use crate::eval::compiler::args::ArgsCompiledValue;
 use crate::eval::compiler::call::CallCompiled;
 use crate::eval::compiler::compr::ComprCompiled;
@@ -56,6 +57,7 @@ use crate::syntax::ast::ExprP;
 use crate::syntax::ast::LambdaP;
 use crate::syntax::ast::StmtP;
 use crate::syntax::lexer::TokenInt;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::function::BoundMethodGen;
 use crate::values::function::FrozenBoundMethod;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
@@ -828,7 +830,7 @@ impl ExprCompiled {
         ctx: &mut OptCtx,
     ) -> Option<FrozenValue> {
         // We assume `getattr` has no side effects.
-        let v = get_attr_hashed_raw(left.to_value(), attr, ctx.heap()).ok()?;
+        let v = get_attr_hashed_raw(left.to_value(), attr, None, ctx.heap()).ok()?;
         match v {
             MemberOrValue::Member(m) => match MaybeUnboundValue::new(m) {
                 MaybeUnboundValue::Method(m) => {
@@ -996,6 +998,7 @@ impl AstLiteral {
             },
             AstLiteral::Float(f) => heap.alloc(f.node),
             AstLiteral::String(x) => heap.alloc(x.node.as_str()),
+            AstLiteral::Bytes(x) => heap.alloc(StarlarkBytes::new(x.node.as_slice())),
         }
     }
 }
@@ -1072,11 +1075,12 @@ impl<'v> MemberOrValue<'v> {
 pub(crate) fn get_attr_hashed_raw<'v>(
     x: Value<'v>,
     attribute: &Symbol,
+    cache: Option<&AttrCache>,
     heap: &'v Heap,
 ) -> anyhow::Result<MemberOrValue<'v>> {
     let aref = x.get_ref();
     if let Some(methods) = aref.get_methods() {
-        if let Some(v) = methods.get_frozen_symbol(attribute) {
+        if let Some(v) = AttrCache::get_member(cache, methods, attribute) {
             return Ok(MemberOrValue::Member(v));
         }
     }
@@ -1089,11 +1093,12 @@ pub(crate) fn get_attr_hashed_raw<'v>(
 pub(crate) fn get_attr_hashed_bind<'v>(
     x: Value<'v>,
     attribute: &Symbol,
+    cache: Option<&AttrCache>,
     heap: &'v Heap,
 ) -> anyhow::Result<Value<'v>> {
     let aref = x.get_ref();
     if let Some(methods) = aref.get_methods() {
-        if let Some(v) = methods.get_frozen_symbol(attribute) {
+        if let Some(v) = AttrCache::get_member(cache, methods, attribute) {
             return MaybeUnboundValue::new(v).bind(x, heap);
         }
     }
@@ -1196,6 +1201,24 @@ impl<'v, 'a, 'e> Compiler<'v, 'a, 'e> {
                 let xs = exprs.into_map(|(k, v)| (self.expr(k), self.expr(v)));
                 ExprCompiled::Dict(xs)
             }
+            ExprP::Set(exprs) => {
+                // Call `set` with a list, there is no instruction for sets
+                let xs = exprs.into_map(|x| self.expr(x));
+                let fun = IrSpanned {
+                    node: ExprCompiled::Value(Constants::get().fn_set.0),
+                    span,
+                };
+                let args = ArgsCompiledValue {
+                    pos_named: vec![IrSpanned {
+                        node: ExprCompiled::List(xs),
+                        span,
+                    }],
+                    names: Vec::new(),
+                    args: None,
+                    kwargs: None,
+                };
+                CallCompiled::call(span, fun, args, &mut self.opt_ctx())
+            }
             ExprP::If(cond_then_expr_else_expr) => {
                 let (cond, then_expr, else_expr) = *cond_then_expr_else_expr;
                 let cond = self.expr(cond);
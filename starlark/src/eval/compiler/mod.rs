@@ -31,16 +31,21 @@ pub(crate) mod small_vec_1;
 pub(crate) mod span;
 pub(crate) mod stmt;
 
+use std::collections::HashMap;
 use std::fmt::Debug;
 
 use crate::codemap::CodeMap;
 use crate::environment::Globals;
 use crate::errors::Diagnostic;
+use crate::eval::compiler::expr::ExprCompiled;
+use crate::eval::compiler::scope::BindingId;
 use crate::eval::compiler::scope::ScopeData;
 use crate::eval::compiler::scope::ScopeId;
 use crate::eval::compiler::scope::ScopeNames;
+use crate::eval::compiler::span::IrSpanned;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::Evaluator;
+use crate::values::error::FrozenMutationError;
 use crate::values::FrozenRef;
 
 /// Error of evaluation of an expression.
@@ -51,6 +56,9 @@ pub(crate) struct EvalException(pub(crate) anyhow::Error);
 #[inline(never)]
 fn add_span_to_error(e: anyhow::Error, span: FrameSpan, eval: &Evaluator) -> anyhow::Error {
     Diagnostic::modify(e, |d: &mut Diagnostic| {
+        if let Some(e) = d.message.downcast_mut::<FrozenMutationError>() {
+            e.find_owner(eval.module_env.frozen_heap());
+        }
         d.set_span(span.span.span(), &span.span.file());
         d.set_call_stack(|| eval.call_stack.to_diagnostic_frames(span.inlined_frames));
     })
@@ -88,6 +96,9 @@ pub(crate) struct Compiler<'v, 'a, 'e> {
     pub(crate) has_before_stmt: bool,
     pub(crate) bc_profile: bool,
     pub(crate) check_types: bool,
+    /// The types of variables declared with an annotation, which their assignments
+    /// without an annotation are checked against.
+    pub(crate) declared_types: HashMap<BindingId, IrSpanned<ExprCompiled>>,
 }
 
 impl Compiler<'_, '_, '_> {
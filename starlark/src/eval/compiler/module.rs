@@ -52,20 +52,28 @@ impl<'v> Compiler<'v, '_, '_> {
             Some(loader) => expr_throw(loader.load(&name), span, self.eval)?,
         };
 
-        for (our_name, their_name) in load.node.args {
-            let (slot, _captured) = self.scope_data.get_assign_ident_slot(&our_name);
-            let slot = match slot {
-                Slot::Local(..) => unreachable!("symbol need to be resolved to module"),
-                Slot::Module(slot) => slot,
-            };
-            let value = expr_throw(
-                self.eval.module_env.load_symbol(&loadenv, &their_name.node),
+        // Check every symbol exists before binding any, so a typo is reported where it is
+        // written, rather than as an unbound variable later.
+        let mut values = Vec::with_capacity(load.node.args.len());
+        for (our_name, their_name) in &load.node.args {
+            values.push(expr_throw(
+                self.eval
+                    .module_env
+                    .load_symbol(&name, &loadenv, &their_name.node),
                 FrameSpan::new(FrozenFileSpan::new(
                     self.codemap,
                     our_name.span.merge(their_name.span),
                 )),
                 self.eval,
-            )?;
+            )?);
+        }
+
+        for ((our_name, _), value) in load.node.args.iter().zip(values) {
+            let (slot, _captured) = self.scope_data.get_assign_ident_slot(our_name);
+            let slot = match slot {
+                Slot::Local(..) => unreachable!("symbol need to be resolved to module"),
+                Slot::Module(slot) => slot,
+            };
             self.eval.set_slot_module(slot, value)
         }
 
@@ -98,4 +98,15 @@ impl<'v, 'a, 'e> OptCtx<'v, 'a, 'e> {
     pub(crate) fn frozen_module(&self) -> Option<&FrozenModuleData> {
         self.eval.frozen_module()
     }
+
+    /// Can calls to frozen functions use their inline bodies computed on freeze.
+    ///
+    /// Only when compiling a module: on freeze, functions of the module being frozen
+    /// are processed in unspecified order, and their inline bodies may not be computed yet.
+    pub(crate) fn cross_module_inlining(&mut self) -> bool {
+        match self.eval() {
+            Some(eval) => !eval.disable_cross_module_inlining,
+            None => false,
+        }
+    }
 }
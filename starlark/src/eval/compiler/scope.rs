@@ -72,6 +72,9 @@ pub(crate) struct Scope<'a> {
     unscopes: Vec<Unscope>,
     codemap: FrozenRef<'static, CodeMap>,
     globals: FrozenRef<'static, Globals>,
+    /// Names which are not defined yet may be defined by a later chunk of the module,
+    /// see [`AstModuleStream`](crate::syntax::AstModuleStream).
+    streamed: bool,
     pub(crate) errors: Vec<anyhow::Error>,
 }
 
@@ -222,6 +225,7 @@ impl<'a> Scope<'a> {
         globals: FrozenRef<'static, Globals>,
         codemap: FrozenRef<'static, CodeMap>,
         dialect: &Dialect,
+        streamed: bool,
     ) -> Self {
         // Not really important, sanity check
         assert_eq!(scope_id, ScopeId::module());
@@ -266,6 +270,7 @@ impl<'a> Scope<'a> {
             unscopes: Vec::new(),
             codemap,
             globals,
+            streamed,
             errors: Vec::new(),
         };
         scope.resolve_idents(code);
@@ -495,6 +500,9 @@ impl<'a> Scope<'a> {
                 None => {
                     // Must be a global, since we know all variables
                     match self.globals.get_frozen(ident) {
+                        None if self.streamed => {
+                            ResolvedIdent::Slot(self.add_forward_module_name(ident))
+                        }
                         None => {
                             self.errors.push(self.variable_not_found_err(ident));
                             return;
@@ -507,6 +515,23 @@ impl<'a> Scope<'a> {
         );
     }
 
+    /// Add a module variable for a name used in a streamed chunk which doesn't define it.
+    /// If no later chunk assigns it, reading it fails at runtime.
+    fn add_forward_module_name(&mut self, ident: &AstString) -> (Slot, BindingId) {
+        let name = self.frozen_heap.alloc_str_intern(ident);
+        let (binding_id, binding) = self
+            .scope_data
+            .new_binding(Visibility::Private, AssignCount::Any);
+        let slot = Slot::Module(self.module.add_name_visibility(name, Visibility::Private));
+        binding.slot = Some(slot);
+        if self.locals.len() > 1 {
+            binding.captured = Captured::Yes;
+        }
+        self.module_bindings
+            .insert_hashed(name.get_hashed(), binding_id);
+        (slot, binding_id)
+    }
+
     fn resolve_idents_in_compr(
         &mut self,
         exprs: &mut [&mut CstExpr],
@@ -652,6 +677,14 @@ impl Stmt {
             StmtP::Assign(dest, _) | StmtP::AssignModify(dest, _, _) => {
                 Assign::collect_defines_lvalue(dest, in_loop, scope_data, frozen_heap, result);
             }
+            StmtP::Declare(name, _) => AssignIdent::collect_assign_ident(
+                name,
+                in_loop,
+                Visibility::Public,
+                scope_data,
+                frozen_heap,
+                result,
+            ),
             StmtP::For(dest, over_body) => {
                 let (_over, body) = &mut **over_body;
                 Assign::collect_defines_lvalue(dest, InLoop::Yes, scope_data, frozen_heap, result);
@@ -987,6 +1020,7 @@ mod tests {
             FrozenRef::new(Globals::empty()),
             codemap,
             &Dialect::Extended,
+            false,
         );
         assert!(scope.errors.is_empty());
         let (.., scope_data) = scope.exit_module();
@@ -1053,6 +1087,7 @@ mod tests {
             fn visit_stmt(&mut self, stmt: &CstStmt) {
                 match &stmt.node {
                     StmtP::Assign(lhs, _rhs) => self.visit_assign(lhs),
+                    StmtP::Declare(name, _ty) => self.visit_lvalue(name),
                     StmtP::Def(DefP { name, params, .. }) => {
                         self.visit_lvalue(name);
                         for param in params {
@@ -801,13 +801,30 @@ impl Compiler<'_, '_, '_> {
             StmtP::Assign(lhs, ty_rhs) => {
                 let (ty, rhs) = *ty_rhs;
                 let rhs = self.expr(rhs);
-                let ty = self.expr_for_type(ty.map(Box::new));
+                let binding_id = match &lhs.node {
+                    AssignP::Identifier(ident) => ident.node.1,
+                    _ => None,
+                };
+                let ty = match (self.expr_for_type(ty.map(Box::new)), binding_id) {
+                    (Some(ty), Some(binding_id)) => {
+                        self.declared_types.insert(binding_id, ty.clone());
+                        Some(ty)
+                    }
+                    (None, Some(binding_id)) => self.declared_types.get(&binding_id).cloned(),
+                    (ty, None) => ty,
+                };
                 let lhs = self.assign(lhs);
                 StmtsCompiled::one(IrSpanned {
                     span,
                     node: StmtCompiled::Assign(lhs, ty, rhs),
                 })
             }
+            StmtP::Declare(name, ty) => {
+                if let Some(ty) = self.expr_for_type(Some(ty)) {
+                    self.declared_types.insert(name.node.1.unwrap(), ty);
+                }
+                StmtsCompiled::empty()
+            }
             StmtP::AssignModify(lhs, op, rhs) => {
                 let rhs = self.expr(*rhs);
                 self.assign_modify(span.span.span(), lhs, rhs, op)
@@ -22,23 +22,35 @@ pub(crate) mod bc;
 pub(crate) mod compiler;
 pub(crate) mod runtime;
 
+use std::collections::HashMap;
 use std::mem;
 use std::time::Instant;
 
 use dupe::Dupe;
 use gazebo::prelude::*;
 pub use runtime::arguments::Arguments;
+pub use runtime::builtin_policy::BuiltinPolicy;
+pub use runtime::call_hook::CallHook;
+pub use runtime::call_log::CallLog;
+pub use runtime::call_log::RecordedCall;
 pub use runtime::call_stack::CallStack;
+pub use runtime::call_stack::CallStackOverflow;
 pub use runtime::evaluator::Evaluator;
 pub use runtime::file_loader::FileLoader;
 pub use runtime::file_loader::ReturnFileLoader;
+pub use runtime::named_parameters::NamedParameters;
 pub use runtime::params::ParametersParser;
 pub use runtime::params::ParametersSpec;
 pub use runtime::params::ParametersSpecBuilder;
+pub use runtime::profile::coverage::Coverage;
 pub use runtime::profile::data::ProfileData;
+pub use runtime::profile::heap::HeapProfileFormat;
 pub use runtime::profile::ProfileMode;
+pub use starlark_derive::NamedParameters;
 
 use crate::collections::symbol_map::Symbol;
+pub use crate::debug::BreakpointFilter;
+pub use crate::debug::DebugMutationPolicy;
 use crate::docs::DocString;
 use crate::environment::Globals;
 use crate::eval::compiler::def::DefInfo;
@@ -51,6 +63,7 @@ use crate::eval::runtime::arguments::ArgNames;
 use crate::eval::runtime::arguments::ArgumentsFull;
 use crate::hint::unlikely;
 use crate::syntax::ast::AstModule;
+use crate::syntax::ast::StmtP;
 use crate::syntax::DialectTypes;
 use crate::values::Value;
 
@@ -64,8 +77,16 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             codemap,
             statement,
             dialect,
+            node_ids: _,
+            streamed,
         } = ast;
 
+        self.module_env
+            .frozen_heap()
+            .set_default_owner(codemap.filename());
+        self.module_env.frozen_heap().use_interned_strings(globals);
+        self.stmt_profile.add_executable(&codemap, &statement);
+
         let codemap = self
             .module_env
             .frozen_heap()
@@ -83,6 +104,15 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             self.module_env.set_docstring(docstring)
         }
 
+        let ends_with_expression = dialect.enable_module_value
+            && match &statement.node {
+                StmtP::Statements(xs) => {
+                    matches!(xs.last().map(|x| &x.node), Some(StmtP::Expression(_)))
+                }
+                StmtP::Expression(_) => true,
+                _ => false,
+            };
+
         let mut scope = Scope::enter_module(
             self.module_env.names(),
             self.module_env.frozen_heap(),
@@ -92,6 +122,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             globals,
             codemap,
             &dialect,
+            streamed,
         );
 
         // We want to grab the first error only, with ownership, so drop all but the first
@@ -139,6 +170,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             bc_profile: self.bc_profile.enabled(),
             eval: self,
             check_types: dialect.enable_types == DialectTypes::Enable,
+            declared_types: HashMap::new(),
         };
 
         let res = compiler.eval_module(statement, local_names);
@@ -153,6 +185,10 @@ impl<'v, 'a> Evaluator<'v, 'a> {
 
         self.module_env.add_eval_duration(start.elapsed());
 
+        if let (true, Ok(value)) = (ends_with_expression, &res) {
+            self.module_env.set_value(*value);
+        }
+
         // Return the result of evaluation
         res.map_err(|e| e.0)
     }
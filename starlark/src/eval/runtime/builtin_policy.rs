@@ -0,0 +1,146 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+enum BuiltinPolicyError {
+    #[error("Call to `{0}` is not permitted by the builtin policy `{1}`")]
+    NotPermitted(String, String),
+}
+
+/// Restricts which native functions and methods an evaluation may call, so the same
+/// [`Globals`](crate::environment::Globals) can be shared between code with different
+/// levels of trust. Set with [`Evaluator::set_builtin_policy`](crate::eval::Evaluator::set_builtin_policy).
+///
+/// Functions are matched by their qualified name: global functions by their name, such
+/// as `print`, functions on a struct of globals by the struct and function name, such as
+/// `json.encode`, and methods by the type and method name, such as `list.append` or
+/// `string.format`. The check happens when they are called, so a blocked function can
+/// still be referenced, passed around or stored. The pure builtins `len` and `type` are
+/// compiled to dedicated instructions, so are always permitted.
+#[derive(Debug, Clone)]
+pub struct BuiltinPolicy {
+    /// Name of the policy, reported when a call is blocked.
+    name: String,
+    /// If `true`, only `functions` may be called, otherwise `functions` may not be called.
+    allow: bool,
+    functions: HashSet<String>,
+}
+
+impl BuiltinPolicy {
+    /// A policy called `name` which blocks calls to the native functions and methods in
+    /// `functions`.
+    pub fn deny(name: &str, functions: &[&str]) -> Self {
+        Self::new(name, false, functions)
+    }
+
+    /// A policy called `name` which blocks calls to all native functions and methods except
+    /// `functions`.
+    pub fn allow(name: &str, functions: &[&str]) -> Self {
+        Self::new(name, true, functions)
+    }
+
+    fn new(name: &str, allow: bool, functions: &[&str]) -> Self {
+        Self {
+            name: name.to_owned(),
+            allow,
+            functions: functions.iter().map(|x| (*x).to_owned()).collect(),
+        }
+    }
+
+    /// Whether the native function or method with the qualified name `function` may be
+    /// called under this policy.
+    pub fn permits(&self, function: &str) -> bool {
+        matches!(function, "len" | "type") || self.functions.contains(function) == self.allow
+    }
+
+    pub(crate) fn check(&self, function: &str) -> anyhow::Result<()> {
+        if self.permits(function) {
+            Ok(())
+        } else {
+            Err(BuiltinPolicyError::NotPermitted(function.to_owned(), self.name.clone()).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Globals;
+    use crate::environment::LibraryExtension;
+    use crate::environment::Module;
+    use crate::eval::BuiltinPolicy;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn eval(policy: &BuiltinPolicy, code: &str) -> anyhow::Result<()> {
+        let module = Module::new();
+        let globals = Globals::extended_by(&[LibraryExtension::Json]);
+        let mut eval = Evaluator::new(&module);
+        eval.set_builtin_policy(policy);
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended)?;
+        eval.eval_module(ast, &globals)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_deny() {
+        let policy = BuiltinPolicy::deny("sandbox", &["fail", "str"]);
+        assert!(eval(&policy, "x = repr(1)\nf = str").is_ok());
+        let err = eval(&policy, "str(1)").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Call to `str` is not permitted by the builtin policy `sandbox`"),
+            "{:#}",
+            err
+        );
+        assert!(eval(&policy, "def f(x): fail(x)\nf('x')").is_err());
+        assert!(eval(&policy, "[1].append(2)").is_ok());
+    }
+
+    #[test]
+    fn test_methods() {
+        let policy = BuiltinPolicy::deny("sandbox", &["list.append", "json.encode"]);
+        let err = eval(&policy, "[1].append(2)").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Call to `list.append` is not permitted"),
+            "{:#}",
+            err
+        );
+        // Through a bound method, and with another type with the same method name
+        assert!(eval(&policy, "f = [1].append\nf(2)").is_err());
+        assert!(eval(&policy, "x = {1: 2}.get(1)\ny = 'a'.format()").is_ok());
+        // Functions on structs are matched with the struct name
+        assert!(eval(&policy, "json.encode(1)").is_err());
+        assert!(eval(&policy, "json.decode('1')").is_ok());
+    }
+
+    #[test]
+    fn test_allow() {
+        let policy = BuiltinPolicy::allow("pure", &["str", "string.upper"]);
+        assert!(eval(&policy, "x = str(len([1]))").is_ok());
+        assert!(eval(&policy, "print('x')").is_err());
+        assert!(!policy.permits("print"));
+        assert!(eval(&policy, "x = 'a'.upper()").is_ok());
+        assert!(eval(&policy, "x = 'a'.lower()").is_err());
+        assert!(eval(&policy, "x = [].append(1)").is_err());
+    }
+}
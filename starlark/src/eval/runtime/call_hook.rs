@@ -0,0 +1,165 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::values::Value;
+
+/// Intercepts the native function calls made during evaluation, e.g. to trace them,
+/// collect metrics, or replace functions with mocks in tests without building
+/// different globals. Set with [`Evaluator::set_call_hook`](crate::eval::Evaluator::set_call_hook).
+///
+/// Functions are identified by their name. Like [`CallLog`](crate::eval::CallLog),
+/// native methods and the builtins `len` and `type` are not seen. The hook runs outside
+/// the call log, so calls mocked by the hook are neither recorded nor replayed.
+pub trait CallHook {
+    /// Called before the native function `function` is invoked with `args`.
+    /// Return a value to skip the call and use that value as its result instead.
+    ///
+    /// The default implementation returns `None`, so the function is called.
+    fn before_call<'v>(
+        &self,
+        function: &str,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Option<Value<'v>>> {
+        let _ = (function, args, eval);
+        Ok(None)
+    }
+
+    /// Called after the native function `function` returned `result`, or after
+    /// [`before_call`](CallHook::before_call) provided it. The value returned is the
+    /// result of the call.
+    ///
+    /// The default implementation returns `result` unchanged.
+    fn after_call<'v>(
+        &self,
+        function: &str,
+        args: &Arguments<'v, '_>,
+        result: anyhow::Result<Value<'v>>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Value<'v>> {
+        let _ = (function, args, eval);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Arguments;
+    use crate::eval::CallHook;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::Value;
+
+    #[derive(Default)]
+    struct Hook {
+        trace: RefCell<Vec<String>>,
+    }
+
+    impl CallHook for Hook {
+        fn before_call<'v>(
+            &self,
+            function: &str,
+            args: &Arguments<'v, '_>,
+            eval: &mut Evaluator<'v, '_>,
+        ) -> anyhow::Result<Option<Value<'v>>> {
+            self.trace.borrow_mut().push(format!("before {}", function));
+            match function {
+                "hash" => Ok(Some(eval.heap().alloc(args.len()? as i32 * 100))),
+                "fail" => Err(anyhow::anyhow!("fail is disabled")),
+                _ => Ok(None),
+            }
+        }
+
+        fn after_call<'v>(
+            &self,
+            function: &str,
+            _args: &Arguments<'v, '_>,
+            result: anyhow::Result<Value<'v>>,
+            _eval: &mut Evaluator<'v, '_>,
+        ) -> anyhow::Result<Value<'v>> {
+            let result = result?;
+            self.trace
+                .borrow_mut()
+                .push(format!("after {} = {}", function, result));
+            Ok(result)
+        }
+    }
+
+    fn eval(hook: &Hook, code: &str) -> anyhow::Result<String> {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_call_hook(hook);
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended)?;
+        Ok(eval.eval_module(ast, &Globals::standard())?.to_repr())
+    }
+
+    #[test]
+    fn test_call_hook() {
+        let hook = Hook::default();
+        assert_eq!(
+            "99",
+            eval(
+                &hook,
+                "def f(x):\n    y = int(x)\n    return y + hash(x)\nf('-1')"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            vec![
+                "before int",
+                "after int = -1",
+                "before hash",
+                "after hash = 100"
+            ],
+            *hook.trace.borrow()
+        );
+    }
+
+    #[test]
+    fn test_call_hook_constant_args() {
+        // Not evaluated speculatively when compiling `f`, but on every call.
+        let hook = Hook::default();
+        assert_eq!(
+            "(100, 100)",
+            eval(&hook, "def f():\n    return hash('x')\n(f(), f())").unwrap()
+        );
+        assert_eq!(
+            vec![
+                "before hash",
+                "after hash = 100",
+                "before hash",
+                "after hash = 100"
+            ],
+            *hook.trace.borrow()
+        );
+    }
+
+    #[test]
+    fn test_call_hook_error() {
+        let hook = Hook::default();
+        let err = eval(&hook, "fail('boom')").unwrap_err();
+        assert!(err.to_string().contains("fail is disabled"), "{:#}", err);
+        assert_eq!(vec!["before fail"], *hook.trace.borrow());
+    }
+}
@@ -0,0 +1,268 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::values::freeze_copy::thaw_copy;
+use crate::values::OwnedFrozenValue;
+use crate::values::Value;
+
+#[derive(Error, Debug)]
+enum CallLogError {
+    #[error("Replay diverged at call {0}: expected `{1}`, got `{2}`")]
+    Diverged(usize, String, String),
+    #[error("Replay diverged at call {0}: no more calls were recorded, got `{1}`")]
+    Exhausted(usize, String),
+    #[error("Replay finished after {0} of {1} recorded calls")]
+    Unfinished(usize, usize),
+    #[error("{0}")]
+    Recorded(String),
+}
+
+/// A call to a native function made during evaluation, captured by a [`CallLog`].
+#[derive(Debug, Clone)]
+pub struct RecordedCall {
+    /// Name of the function.
+    pub function: String,
+    /// `repr` of the arguments, as they would be written in a call.
+    pub arguments: String,
+    /// The value returned, or `None` if it could not be copied out of the
+    /// evaluation (see [`Value::freeze_copy`]), or the error message.
+    pub result: Result<Option<OwnedFrozenValue>, String>,
+}
+
+impl RecordedCall {
+    fn call(&self) -> String {
+        format!("{}({})", self.function, self.arguments)
+    }
+}
+
+/// Records the native function calls made by an evaluation, or replays a previous
+/// recording. Set with [`Evaluator::set_call_log`](crate::eval::Evaluator::set_call_log).
+///
+/// When replaying, each call must be made with the same function and arguments as the
+/// recorded one, otherwise the call fails, and the recorded result is returned without
+/// calling the function. Results that could not be recorded are computed by calling the
+/// function again. Native methods and the builtins `len` and `type` are not seen.
+#[derive(Debug, Default)]
+pub struct CallLog {
+    replay: bool,
+    calls: RefCell<Vec<RecordedCall>>,
+    /// Number of calls replayed so far.
+    next: Cell<usize>,
+}
+
+impl CallLog {
+    /// A log which records the calls made.
+    pub fn record() -> Self {
+        Self::default()
+    }
+
+    /// A log which replays `calls`, as previously returned by [`calls`](CallLog::calls).
+    pub fn replay(calls: Vec<RecordedCall>) -> Self {
+        Self {
+            replay: true,
+            calls: RefCell::new(calls),
+            next: Cell::new(0),
+        }
+    }
+
+    /// The calls recorded, or being replayed.
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.calls.borrow().clone()
+    }
+
+    /// Check that all the recorded calls were made when replaying.
+    pub fn check_finished(&self) -> anyhow::Result<()> {
+        let len = self.calls.borrow().len();
+        if self.replay && self.next.get() != len {
+            return Err(CallLogError::Unfinished(self.next.get(), len).into());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn invoke<'v, 'a>(
+        &self,
+        function: &str,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, 'a>,
+        invoke: impl FnOnce(&mut Evaluator<'v, 'a>) -> anyhow::Result<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        let arguments = arguments_repr(args);
+        if !self.replay {
+            let res = invoke(eval);
+            let result = match &res {
+                Ok(v) => Ok(v.freeze_copy().ok()),
+                Err(e) => Err(format!("{:#}", e)),
+            };
+            self.calls.borrow_mut().push(RecordedCall {
+                function: function.to_owned(),
+                arguments,
+                result,
+            });
+            return res;
+        }
+
+        let index = self.next.get();
+        let call = format!("{}({})", function, arguments);
+        let result = match self.calls.borrow().get(index) {
+            None => return Err(CallLogError::Exhausted(index, call).into()),
+            Some(recorded) if recorded.function != function || recorded.arguments != arguments => {
+                return Err(CallLogError::Diverged(index, recorded.call(), call).into());
+            }
+            Some(recorded) => recorded.result.clone(),
+        };
+        self.next.set(index + 1);
+        match result {
+            Ok(Some(v)) => Ok(thaw_copy(v.owned_value(eval.frozen_heap()), eval.heap())),
+            Ok(None) => invoke(eval),
+            Err(e) => Err(CallLogError::Recorded(e).into()),
+        }
+    }
+}
+
+fn arguments_repr(args: &Arguments) -> String {
+    let mut res = String::new();
+    let mut sep = "";
+    for v in args.0.pos {
+        write!(res, "{}{}", sep, v.to_repr()).unwrap();
+        sep = ", ";
+    }
+    if let Some(v) = args.0.args {
+        write!(res, "{}*{}", sep, v.to_repr()).unwrap();
+        sep = ", ";
+    }
+    for ((_, name), v) in args.0.names.iter().zip(args.0.named) {
+        write!(res, "{}{}={}", sep, name.as_str(), v.to_repr()).unwrap();
+        sep = ", ";
+    }
+    if let Some(v) = args.0.kwargs {
+        write!(res, "{}**{}", sep, v.to_repr()).unwrap();
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicI32;
+    use std::sync::atomic::Ordering;
+
+    use crate as starlark;
+    use crate::environment::GlobalsBuilder;
+    use crate::environment::Module;
+    use crate::eval::CallLog;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::none::NoneType;
+
+    static COUNTER: AtomicI32 = AtomicI32::new(0);
+
+    #[starlark_module]
+    fn host_functions(globals: &mut GlobalsBuilder) {
+        fn next_id(prefix: &str) -> anyhow::Result<Vec<String>> {
+            let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![format!("{}{}", prefix, n)])
+        }
+
+        fn host_fail() -> anyhow::Result<NoneType> {
+            Err(anyhow::anyhow!("host failure"))
+        }
+    }
+
+    fn eval(log: &CallLog, code: &str) -> anyhow::Result<String> {
+        let module = Module::new();
+        let globals = GlobalsBuilder::extended().with(host_functions).build();
+        let mut eval = Evaluator::new(&module);
+        eval.set_call_log(log);
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended)?;
+        Ok(eval.eval_module(ast, &globals)?.to_repr())
+    }
+
+    const PROGRAM: &str = "\
+def f(p):
+    x = next_id(prefix = p)
+    x.append(str(len(x)))
+    return x
+f('a') + f('b')
+";
+
+    #[test]
+    fn test_record_replay() {
+        let log = CallLog::record();
+        let res = eval(&log, PROGRAM).unwrap();
+        let calls = log.calls();
+        assert_eq!(
+            vec![
+                "next_id(prefix=\"a\")",
+                "str(1)",
+                "next_id(prefix=\"b\")",
+                "str(1)"
+            ],
+            calls.iter().map(|c| c.call()).collect::<Vec<_>>()
+        );
+
+        // The counter has moved on, but replay returns the recorded ids.
+        let replay = CallLog::replay(calls);
+        assert_eq!(res, eval(&replay, PROGRAM).unwrap());
+        replay.check_finished().unwrap();
+    }
+
+    #[test]
+    fn test_replay_diverged() {
+        let log = CallLog::record();
+        eval(&log, "next_id('a')").unwrap();
+
+        let replay = CallLog::replay(log.calls());
+        let err = eval(&replay, "next_id('b')").unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "Replay diverged at call 0: expected `next_id(\"a\")`, got `next_id(\"b\")`"
+            ),
+            "{:#}",
+            err
+        );
+
+        let replay = CallLog::replay(log.calls());
+        eval(&replay, "1").unwrap();
+        assert_eq!(
+            "Replay finished after 0 of 1 recorded calls",
+            replay.check_finished().unwrap_err().to_string()
+        );
+
+        let replay = CallLog::replay(log.calls());
+        let err = eval(&replay, "next_id('a')\nnext_id('a')").unwrap_err();
+        assert!(err.to_string().contains("no more calls were recorded"));
+    }
+
+    #[test]
+    fn test_replay_error() {
+        let log = CallLog::record();
+        assert!(eval(&log, "host_fail()").is_err());
+        assert!(matches!(&log.calls()[0].result, Err(e) if e == "host failure"));
+
+        let replay = CallLog::replay(log.calls());
+        let err = eval(&replay, "host_fail()").unwrap_err();
+        assert!(err.to_string().contains("host failure"), "{:#}", err);
+    }
+}
@@ -32,6 +32,7 @@ use dupe::Dupe;
 use gazebo::prelude::*;
 
 use crate::codemap::FileSpan;
+use crate::errors::Diagnostic;
 use crate::errors::Frame;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::inlined_frame::InlinedFrames;
@@ -51,6 +52,13 @@ struct CheapFrame<'v> {
 }
 
 impl CheapFrame<'_> {
+    fn empty() -> Self {
+        CheapFrame {
+            function: Value::new_none(),
+            span: None,
+        }
+    }
+
     fn location(&self) -> Option<FileSpan> {
         self.span.map(|span| span.span.to_file_span())
     }
@@ -79,25 +87,46 @@ impl Debug for CheapFrame<'_> {
 enum CallStackError {
     #[error("Requested {0}-th top frame, but stack size is {1} (internal error)")]
     StackIsTooShallowForNthTopFrame(usize, usize),
-    #[error("Starlark call stack overflow")]
-    Overflow,
+}
+
+/// The error when a call would exceed the maximum depth of the Starlark call stack,
+/// see [`Evaluator::set_max_call_stack_depth`](crate::eval::Evaluator::set_max_call_stack_depth).
+///
+/// Evaluation errors are usually a [`Diagnostic`], so find it with
+/// `diagnostic.message.downcast_ref::<CallStackOverflow>()`.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Starlark call stack overflow, the maximum depth is {max_depth}")]
+pub struct CallStackOverflow {
+    max_depth: usize,
+    call_stack: CallStack,
+}
+
+impl CallStackOverflow {
+    /// The maximum number of frames, which was exceeded.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// The call stack including the call which overflowed it, truncated to the outermost
+    /// and innermost frames.
+    pub fn call_stack(&self) -> &CallStack {
+        &self.call_stack
+    }
 }
 
 /// Starlark call stack.
 #[derive(Debug)]
 pub(crate) struct CheapCallStack<'v> {
     count: usize,
-    stack: [CheapFrame<'v>; MAX_CALLSTACK_RECURSION],
+    /// Allocated up front, with one element per frame allowed.
+    stack: Vec<CheapFrame<'v>>,
 }
 
 impl<'v> Default for CheapCallStack<'v> {
     fn default() -> Self {
         Self {
             count: 0,
-            stack: [CheapFrame {
-                function: Value::new_none(),
-                span: None,
-            }; MAX_CALLSTACK_RECURSION],
+            stack: vec![CheapFrame::empty(); DEFAULT_MAX_CALLSTACK_RECURSION],
         }
     }
 }
@@ -113,8 +142,11 @@ impl<'v> Default for CheapCallStack<'v> {
 // * [tokio default stack size is 2MB][1]
 // [1] https://docs.rs/tokio/0.2.1/tokio/runtime/struct.Builder.html#method.thread_stack_size
 // TODO(nga): count loops in call stack size.
-// TODO(nga): make it configurable.
-const MAX_CALLSTACK_RECURSION: usize = 50;
+const DEFAULT_MAX_CALLSTACK_RECURSION: usize = 50;
+
+// How many of the outermost and innermost frames are kept in the call stack of an overflow.
+const OVERFLOW_OUTER_FRAMES: usize = 5;
+const OVERFLOW_INNER_FRAMES: usize = 10;
 
 unsafe impl<'v> Trace<'v> for CheapCallStack<'v> {
     fn trace(&mut self, tracer: &Tracer<'v>) {
@@ -125,13 +157,20 @@ unsafe impl<'v> Trace<'v> for CheapCallStack<'v> {
         // Not required, but since we are chosing not to walk those above
         // the current stack depth, it's good practice to blank those values out
         for x in unused {
-            x.function = Value::new_none();
-            x.span = None;
+            *x = CheapFrame::empty();
         }
     }
 }
 
 impl<'v> CheapCallStack<'v> {
+    /// Allow at most `depth` frames on the stack, raised to at least one frame and to
+    /// the frames which are on it now.
+    pub(crate) fn set_max_depth(&mut self, depth: usize) {
+        let depth = depth.max(self.count).max(1);
+        self.stack.resize(depth, CheapFrame::empty());
+        self.stack.shrink_to_fit();
+    }
+
     /// Push an element to the stack. It is important the each `push` is paired
     /// with a `pop`.
     pub(crate) fn push(
@@ -139,14 +178,32 @@ impl<'v> CheapCallStack<'v> {
         function: Value<'v>,
         span: Option<FrozenRef<'static, FrameSpan>>,
     ) -> anyhow::Result<()> {
-        if unlikely(self.count >= MAX_CALLSTACK_RECURSION) {
-            return Err(CallStackError::Overflow.into());
+        if unlikely(self.count >= self.stack.len()) {
+            return Err(self.overflow(function, span));
         }
         self.stack[self.count] = CheapFrame { function, span };
         self.count += 1;
         Ok(())
     }
 
+    #[cold]
+    #[inline(never)]
+    fn overflow(
+        &self,
+        function: Value<'v>,
+        span: Option<FrozenRef<'static, FrameSpan>>,
+    ) -> anyhow::Error {
+        let mut call_stack = self.to_diagnostic_frames(InlinedFrames::default());
+        CheapFrame { function, span }.extend_frames(&mut call_stack.frames);
+        call_stack.truncate(OVERFLOW_OUTER_FRAMES, OVERFLOW_INNER_FRAMES);
+        let err = CallStackOverflow {
+            max_depth: self.stack.len(),
+            call_stack: call_stack.clone(),
+        };
+        // Set the call stack now, so the full one isn't set as the error propagates.
+        Diagnostic::modify(err.into(), |d| d.set_call_stack(|| call_stack))
+    }
+
     /// Remove the top element from the stack. Called after `push`.
     pub(crate) fn pop(&mut self) {
         debug_assert!(self.count >= 1);
@@ -184,7 +241,10 @@ impl<'v> CheapCallStack<'v> {
             frame.extend_frames(&mut frames);
         }
         inlined_frames.extend_frames(&mut frames);
-        CallStack { frames }
+        CallStack {
+            frames,
+            omitted: None,
+        }
     }
 
     /// List the entries on the stack as values
@@ -197,6 +257,17 @@ impl<'v> CheapCallStack<'v> {
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct CallStack {
     frames: Vec<Frame>,
+    /// Frames left out by `truncate`.
+    omitted: Option<OmittedFrames>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct OmittedFrames {
+    /// Index in `frames` of the frame after those left out.
+    index: usize,
+    count: usize,
+    /// Name of the innermost frame left out, the caller of `frames[index]`.
+    caller: String,
 }
 
 impl CallStack {
@@ -209,6 +280,26 @@ impl CallStack {
     pub fn into_frames(self) -> Vec<Frame> {
         self.frames
     }
+
+    /// The number of frames left out of the middle of a truncated call stack,
+    /// e.g. that of a [`CallStackOverflow`].
+    pub fn omitted_frames(&self) -> usize {
+        self.omitted.as_ref().map_or(0, |x| x.count)
+    }
+
+    /// Keep only the `outer` outermost and `inner` innermost frames.
+    fn truncate(&mut self, outer: usize, inner: usize) {
+        // Leaving out a single frame saves nothing.
+        if self.omitted.is_none() && self.frames.len() > outer + inner + 1 {
+            let end = self.frames.len() - inner;
+            let omitted: Vec<Frame> = self.frames.drain(outer..end).collect();
+            self.omitted = Some(OmittedFrames {
+                index: outer,
+                count: omitted.len(),
+                caller: omitted.last().unwrap().name.clone(),
+            });
+        }
+    }
 }
 
 impl Display for CallStack {
@@ -218,7 +309,13 @@ impl Display for CallStack {
             writeln!(f, "Traceback (most recent call last):")?;
             // TODO(nga): use real module name.
             let mut prev = "<module>";
-            for x in &self.frames {
+            for (i, x) in self.frames.iter().enumerate() {
+                if let Some(omitted) = &self.omitted {
+                    if omitted.index == i {
+                        writeln!(f, "  ... {} frames omitted ...", omitted.count)?;
+                        prev = &omitted.caller;
+                    }
+                }
                 x.write_two_lines("  ", prev, f)?;
                 prev = &x.name;
             }
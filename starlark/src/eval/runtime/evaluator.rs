@@ -15,10 +15,13 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
 
 use dupe::Dupe;
 use gazebo::cast;
@@ -30,21 +33,32 @@ use crate::codemap::FileSpanRef;
 use crate::codemap::ResolvedFileSpan;
 use crate::collections::alloca::Alloca;
 use crate::collections::string_pool::StringPool;
+use crate::collections::symbol_map::Symbol;
+use crate::debug::BreakpointFilter;
 use crate::environment::slots::ModuleSlotId;
 use crate::environment::EnvironmentError;
 use crate::environment::FrozenModuleData;
+use crate::environment::Globals;
 use crate::environment::Module;
 use crate::errors::Diagnostic;
 use crate::eval::bc::frame::BcFramePtr;
+use crate::eval::bc::inline_cache::AttrCache;
 use crate::eval::compiler::def::CopySlotFromParent;
 use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::DefInfo;
 use crate::eval::compiler::def::FrozenDef;
+use crate::eval::compiler::expr::get_attr_hashed_bind;
+use crate::eval::compiler::expr::get_attr_hashed_raw;
+use crate::eval::compiler::expr::MemberOrValue;
 use crate::eval::runtime::before_stmt::BeforeStmt;
+use crate::eval::runtime::builtin_policy::BuiltinPolicy;
+use crate::eval::runtime::call_hook::CallHook;
+use crate::eval::runtime::call_log::CallLog;
 use crate::eval::runtime::call_stack::CheapCallStack;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::inlined_frame::InlinedFrames;
 use crate::eval::runtime::profile::bc::BcProfile;
+use crate::eval::runtime::profile::coverage::Coverage;
 use crate::eval::runtime::profile::data::ProfileData;
 use crate::eval::runtime::profile::heap::HeapProfile;
 use crate::eval::runtime::profile::heap::HeapProfileFormat;
@@ -56,6 +70,7 @@ use crate::eval::runtime::profile::typecheck::TypecheckProfile;
 use crate::eval::runtime::profile::ProfileMode;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
+use crate::eval::Arguments;
 use crate::eval::CallStack;
 use crate::eval::FileLoader;
 use crate::stdlib::breakpoint::BreakpointConsole;
@@ -63,9 +78,12 @@ use crate::stdlib::breakpoint::RealBreakpointConsole;
 use crate::stdlib::extra::PrintHandler;
 use crate::stdlib::extra::StderrPrintHandler;
 use crate::values::function::NativeFunction;
+use crate::values::layout::heap::heap_type::HeapError;
 use crate::values::layout::value_captured::value_captured_get;
 use crate::values::layout::value_captured::FrozenValueCaptured;
 use crate::values::layout::value_captured::ValueCaptured;
+use crate::values::layout::value_not_special::FrozenValueNotSpecial;
+use crate::values::types::unbound::MaybeUnboundValue;
 use crate::values::FrozenHeap;
 use crate::values::FrozenRef;
 use crate::values::Heap;
@@ -94,12 +112,12 @@ pub(crate) enum EvaluatorError {
     TopSecondFrameNotDef,
     #[error("Top frame is not native (internal error)")]
     TopFrameNotNative,
-    #[error(
-        "Coverage profile generation not implemented (but can be obtained with `.coverage()` function)"
-    )]
-    CoverageNotImplemented,
     #[error("Coverage not enabled")]
     CoverageNotEnabled,
+    #[error("Starlark fuel exhausted: all {0} units were used")]
+    FuelExhausted(u64),
+    #[error("Starlark time limit exceeded: evaluation took longer than {0:?}")]
+    TimeLimitExceeded(Duration),
 }
 
 /// Number of bytes to allocate between GC's.
@@ -128,6 +146,8 @@ pub struct Evaluator<'v, 'a> {
     pub(crate) heap_or_flame_profile: bool,
     // Is GC disabled for some reason
     pub(crate) disable_gc: bool,
+    // Do not inline functions loaded from other modules using their bodies optimized on freeze.
+    pub(crate) disable_cross_module_inlining: bool,
     // If true, the interpreter prints to stderr on GC.
     // This is used for debugging.
     pub(crate) verbose_gc: bool,
@@ -138,7 +158,7 @@ pub struct Evaluator<'v, 'a> {
     // Extra functions to run on each statement, usually empty
     pub(crate) before_stmt: BeforeStmt<'a>,
     // Used for line profiling
-    stmt_profile: StmtProfile,
+    pub(crate) stmt_profile: StmtProfile,
     // Bytecode profile.
     pub(crate) bc_profile: BcProfile,
     // Total time spent in runtime typechecking.
@@ -153,10 +173,25 @@ pub struct Evaluator<'v, 'a> {
     pub extra: Option<&'a dyn AnyLifetime<'a>>,
     /// Called to perform console IO each time `breakpoint` function is called.
     pub(crate) breakpoint_handler: Option<Box<dyn Fn() -> Box<dyn BreakpointConsole>>>,
+    /// Filters of the conditional `breakpoint` calls, by call location.
+    pub(crate) breakpoint_filters: HashMap<Option<FileSpan>, BreakpointFilter>,
+    /// Watch expressions shown each time a `breakpoint` is hit.
+    pub(crate) breakpoint_watches: Vec<String>,
     /// Use in implementation of `print` function.
     pub(crate) print_handler: &'a (dyn PrintHandler + 'a),
+    /// Which native functions may be called, if restricted.
+    pub(crate) builtin_policy: Option<&'a BuiltinPolicy>,
+    /// Records or replays native function calls, if set.
+    pub(crate) call_log: Option<&'a CallLog>,
+    /// Intercepts native function calls, if set.
+    pub(crate) call_hook: Option<&'a dyn CallHook>,
+    /// Maximum number of bytes the module may allocate, if limited.
+    pub(crate) max_allocated_bytes: Option<usize>,
+    /// Fuel left and the fuel initially given, if limited.
+    fuel: Option<(u64, u64)>,
+    /// Time at which evaluation must stop and the time limit, if limited.
+    deadline: Option<(Instant, Duration)>,
     // The Starlark-level call-stack of functions.
-    // Must go last because it's quite a big structure
     pub(crate) call_stack: CheapCallStack<'v>,
 }
 
@@ -184,6 +219,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             extra: None,
             next_gc_level: GC_THRESHOLD,
             disable_gc: false,
+            disable_cross_module_inlining: false,
             alloca: Alloca::new(),
             profile_or_instrumentation_mode: ProfileOrInstrumentationMode::None,
             heap_profile: HeapProfile::new(),
@@ -196,7 +232,15 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             module_def_info: DefInfo::empty(), // Will be replaced before it is used
             string_pool: StringPool::default(),
             breakpoint_handler: None,
+            breakpoint_filters: HashMap::new(),
+            breakpoint_watches: Vec::new(),
             print_handler: &StderrPrintHandler,
+            builtin_policy: None,
+            call_log: None,
+            call_hook: None,
+            max_allocated_bytes: None,
+            fuel: None,
+            deadline: None,
             verbose_gc: false,
         }
     }
@@ -208,6 +252,17 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.disable_gc = true;
     }
 
+    /// Disable inlining of small functions loaded from frozen modules
+    /// when their bodies refer to variables of their own module.
+    ///
+    /// Such functions, e.g. one-line wrappers around other functions, are inlined
+    /// into the callers compiled by this evaluator by default.
+    /// Inlining makes evaluation faster, but the inlined calls are not visible to
+    /// call hooks, profilers or breakpoints.
+    pub fn disable_cross_module_inlining(&mut self) {
+        self.disable_cross_module_inlining = true;
+    }
+
     /// Enable GC logging.
     pub fn verbose_gc(&mut self) {
         self.verbose_gc = true;
@@ -255,7 +310,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
                 self.stmt_profile.enable();
                 self.before_stmt(&|span, eval| eval.stmt_profile.before_stmt(span));
             }
-            ProfileMode::TimeFlame => {
+            ProfileMode::TimeFlame | ProfileMode::TimeSpeedscope | ProfileMode::TimeChromeTrace => {
                 self.flame_profile.enable();
                 self.heap_or_flame_profile = true;
             }
@@ -272,6 +327,14 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         Ok(())
     }
 
+    /// Set the format heap profiles are written in, overriding the default for the heap
+    /// [`ProfileMode`], which is summary CSV or flamegraph. Applies both to the allocated memory
+    /// profile from [`gen_profile`](Evaluator::gen_profile) and the retained memory profile from
+    /// [`FrozenModule::heap_profile`](crate::environment::FrozenModule::heap_profile).
+    pub fn set_heap_profile_format(&mut self, format: HeapProfileFormat) {
+        self.module_env.set_heap_profile_format(format);
+    }
+
     /// Enable instrumentation in module which is loaded by a module to be profiled.
     ///
     /// This function need to be called when evaluating a dependency of a module, if a module
@@ -295,7 +358,9 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             | ProfileMode::HeapSummaryRetained
             | ProfileMode::HeapFlameAllocated
             | ProfileMode::HeapFlameRetained
-            | ProfileMode::TimeFlame => {
+            | ProfileMode::TimeFlame
+            | ProfileMode::TimeSpeedscope
+            | ProfileMode::TimeChromeTrace => {
                 self.heap_or_flame_profile = true;
             }
             ProfileMode::Typecheck => {}
@@ -326,20 +391,22 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         };
         self.profile_or_instrumentation_mode = ProfileOrInstrumentationMode::Collected;
         match mode {
-            ProfileMode::HeapSummaryAllocated => self
-                .heap_profile
-                .gen(self.heap(), HeapProfileFormat::Summary),
-            ProfileMode::HeapFlameAllocated => self
-                .heap_profile
-                .gen(self.heap(), HeapProfileFormat::FlameGraph),
+            ProfileMode::HeapSummaryAllocated | ProfileMode::HeapFlameAllocated => {
+                let format = self.module_env.heap_profile_format(&mode);
+                self.heap_profile.gen(self.heap(), &mode, format)
+            }
             ProfileMode::HeapSummaryRetained | ProfileMode::HeapFlameRetained => {
                 Err(EvaluatorError::RetainedMemoryProfilingCannotBeObtainedFromEvaluator.into())
             }
             ProfileMode::Statement => self.stmt_profile.gen(),
-            ProfileMode::Coverage => Err(EvaluatorError::CoverageNotImplemented.into()),
+            ProfileMode::Coverage => Ok(ProfileData::new_coverage(
+                self.stmt_profile.line_coverage()?,
+            )),
             ProfileMode::Bytecode => self.bc_profile.gen_bc_profile(),
             ProfileMode::BytecodePairs => self.bc_profile.gen_bc_pairs_profile(),
-            ProfileMode::TimeFlame => self.flame_profile.gen(),
+            ProfileMode::TimeFlame | ProfileMode::TimeSpeedscope | ProfileMode::TimeChromeTrace => {
+                self.flame_profile.gen(mode)
+            }
             ProfileMode::Typecheck => self.typecheck_profile.gen(),
         }
     }
@@ -360,6 +427,20 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         }
     }
 
+    /// Get line coverage, which can be written out in the lcov format.
+    ///
+    /// Works if [`ProfileMode::Coverage`] is enabled, and has the same imprecision as
+    /// [`coverage`](Evaluator::coverage). The profile generated by
+    /// [`gen_profile`](Evaluator::gen_profile) in this mode is this coverage in the lcov format.
+    pub fn line_coverage(&self) -> anyhow::Result<Coverage> {
+        match self.profile_or_instrumentation_mode {
+            ProfileOrInstrumentationMode::Profile(ProfileMode::Coverage) => {
+                self.stmt_profile.line_coverage()
+            }
+            _ => Err(EvaluatorError::CoverageNotEnabled.into()),
+        }
+    }
+
     /// Enable interactive `breakpoint()`. When enabled, `breakpoint()`
     /// reads commands from stdin and write to stdout.
     /// When disabled (default), `breakpoint()` function results in error.
@@ -396,11 +477,239 @@ impl<'v, 'a> Evaluator<'v, 'a> {
         self.before_stmt(f);
     }
 
+    /// Set the maximum number of frames on the Starlark call stack, including the frame of
+    /// the module being evaluated. Calls beyond it fail with a
+    /// [`CallStackOverflow`](crate::eval::CallStackOverflow) error, whose call stack shows the
+    /// outermost and innermost frames of the recursion. Defaults to 50.
+    ///
+    /// A depth below one, or below the number of frames currently on the stack when called
+    /// during evaluation, is raised to that.
+    ///
+    /// Each frame uses native stack too, about 1K for a typical function, so a depth that
+    /// is too large for the native stack of the thread will crash the process instead.
+    pub fn set_max_call_stack_depth(&mut self, depth: usize) {
+        self.call_stack.set_max_depth(depth);
+    }
+
+    /// Set the maximum number of bytes the module being evaluated may have allocated,
+    /// across both its heap and its frozen heap, see [`allocated_bytes`](Evaluator::allocated_bytes).
+    /// Exceeding it fails the evaluation with a Starlark error.
+    ///
+    /// The limit is checked on function calls, loop iterations and module-level statements.
+    /// Operations which allocate a large value in one go, such as repeating a string or
+    /// a list, check it before allocating, so they fail without overshooting it. Garbage
+    /// is only collected between module-level statements, so inside a function unreachable
+    /// values count towards the limit too.
+    pub fn set_max_allocated_bytes(&mut self, bytes: usize) {
+        self.max_allocated_bytes = Some(bytes);
+        self.heap().set_max_allocated_bytes(Some(bytes));
+    }
+
+    /// Number of bytes currently allocated by the module being evaluated, on both its
+    /// heap and its frozen heap, including the content of `bytes` values but not other
+    /// memory allocated outside the starlark heaps.
+    pub fn allocated_bytes(&self) -> usize {
+        self.heap().allocated_bytes() + self.frozen_heap().allocated_bytes()
+    }
+
+    /// Give the evaluation `fuel` units of fuel. A unit is used by each function call, loop
+    /// iteration and module-level statement, and evaluating more once all of it is used
+    /// fails with a Starlark error. Unlike a time limit, the point at which the evaluation
+    /// stops is deterministic.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = Some((fuel, fuel));
+    }
+
+    /// Fuel left, if set with [`set_fuel`](Evaluator::set_fuel).
+    pub fn remaining_fuel(&self) -> Option<u64> {
+        self.fuel.map(|(left, _)| left)
+    }
+
+    /// Fail the evaluation with a Starlark error if it is still running `limit` after now.
+    ///
+    /// The time is only checked on function calls, loop iterations and module-level
+    /// statements, so a single long native call, such as sorting a large list, may
+    /// overshoot it.
+    pub fn set_time_limit(&mut self, limit: Duration) {
+        self.deadline = Some((Instant::now() + limit, limit));
+    }
+
+    /// Check the limits set by [`set_max_allocated_bytes`](Evaluator::set_max_allocated_bytes),
+    /// [`set_fuel`](Evaluator::set_fuel) and [`set_time_limit`](Evaluator::set_time_limit),
+    /// using a unit of fuel.
+    #[inline(always)]
+    pub(crate) fn check_limits(&mut self) -> anyhow::Result<()> {
+        #[cold]
+        #[inline(never)]
+        fn check(eval: &mut Evaluator) -> anyhow::Result<()> {
+            if let Some(limit) = eval.max_allocated_bytes {
+                let allocated = eval.allocated_bytes();
+                if allocated > limit {
+                    return Err(HeapError::MemoryLimitExceeded(allocated, limit).into());
+                }
+            }
+            if let Some((left, fuel)) = &mut eval.fuel {
+                match left.checked_sub(1) {
+                    Some(x) => *left = x,
+                    None => return Err(EvaluatorError::FuelExhausted(*fuel).into()),
+                }
+            }
+            if let Some((deadline, limit)) = eval.deadline {
+                if Instant::now() > deadline {
+                    return Err(EvaluatorError::TimeLimitExceeded(limit).into());
+                }
+            }
+            Ok(())
+        }
+
+        if self.max_allocated_bytes.is_none() && self.fuel.is_none() && self.deadline.is_none() {
+            Ok(())
+        } else {
+            check(self)
+        }
+    }
+
     /// Set the handler invoked when `print` function is used.
     pub fn set_print_handler(&mut self, handler: &'a (dyn PrintHandler + 'a)) {
         self.print_handler = handler;
     }
 
+    /// Restrict which native functions may be called during this evaluation. Calls
+    /// blocked by the policy fail with an error naming the function and the policy.
+    pub fn set_builtin_policy(&mut self, policy: &'a BuiltinPolicy) {
+        self.builtin_policy = Some(policy);
+    }
+
+    /// Record the native function calls made during this evaluation to `log`, or
+    /// replay them from `log`, see [`CallLog`].
+    pub fn set_call_log(&mut self, log: &'a CallLog) {
+        self.call_log = Some(log);
+    }
+
+    /// Intercept the native function calls made during this evaluation with `hook`,
+    /// see [`CallHook`].
+    pub fn set_call_hook(&mut self, hook: &'a dyn CallHook) {
+        self.call_hook = Some(hook);
+    }
+
+    /// Invoke the native function `name` with `invoke`, going through the call hook
+    /// and the call log, if any.
+    #[inline(always)]
+    pub(crate) fn invoke_native(
+        &mut self,
+        name: &str,
+        args: &Arguments<'v, '_>,
+        invoke: impl FnOnce(&mut Self) -> anyhow::Result<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        let invoke_logged = |eval: &mut Self| match eval.call_log {
+            None => invoke(eval),
+            Some(log) => log.invoke(name, args, eval, invoke),
+        };
+        match self.call_hook {
+            None => invoke_logged(self),
+            Some(hook) => {
+                let res = match hook.before_call(name, args, self)? {
+                    Some(v) => Ok(v),
+                    None => invoke_logged(self),
+                };
+                hook.after_call(name, args, res, self)
+            }
+        }
+    }
+
+    /// Check the native function `name` may be called under the builtin policy, if any.
+    #[inline(always)]
+    pub(crate) fn check_builtin_policy(&self, name: &str) -> anyhow::Result<()> {
+        match self.builtin_policy {
+            None => Ok(()),
+            Some(policy) => policy.check(name),
+        }
+    }
+
+    /// Check the native method `name` of `this` may be called under the builtin policy,
+    /// if any. Methods are checked by their name qualified with the type, e.g. `list.append`.
+    #[inline(always)]
+    pub(crate) fn check_builtin_policy_method(
+        &self,
+        this: Value<'v>,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        match self.builtin_policy {
+            None => Ok(()),
+            Some(policy) => policy.check(&format!("{}.{}", this.get_type(), name)),
+        }
+    }
+
+    /// The globals the innermost Starlark function or module on the call stack was
+    /// compiled with, skipping native functions.
+    fn current_globals(&self) -> FrozenRef<'static, Globals> {
+        for n in 0.. {
+            let func = match self.call_stack.top_nth_function(n) {
+                Ok(func) => func,
+                Err(_) => break,
+            };
+            if let Some(func) = func.downcast_ref::<Def>() {
+                return func.def_info.globals;
+            } else if let Some(func) = func.downcast_ref::<FrozenDef>() {
+                return func.def_info.globals;
+            } else if func.is_none() {
+                break;
+            }
+        }
+        self.module_def_info.globals
+    }
+
+    /// The method `attribute` added to the type of `value` by the current globals, see
+    /// [`GlobalsBuilder::type_extension`](crate::environment::GlobalsBuilder::type_extension).
+    pub(crate) fn get_type_extension(
+        &self,
+        value: Value<'v>,
+        attribute: &str,
+    ) -> Option<FrozenValueNotSpecial> {
+        self.current_globals()
+            .get_type_extension(value.get_type(), attribute)
+    }
+
+    /// The names of the methods added to the type of `value` by the current globals.
+    pub(crate) fn type_extension_names(&self, value: Value<'v>) -> Vec<String> {
+        self.current_globals()
+            .type_extension_names(value.get_type())
+    }
+
+    /// Like [`get_attr_hashed_raw`], falling back to the type extensions.
+    #[inline(always)]
+    pub(crate) fn get_attr_raw(
+        &self,
+        value: Value<'v>,
+        attribute: &Symbol,
+        cache: Option<&AttrCache>,
+    ) -> anyhow::Result<MemberOrValue<'v>> {
+        match get_attr_hashed_raw(value, attribute, cache, self.heap()) {
+            Err(e) => match self.get_type_extension(value, attribute.as_str()) {
+                Some(x) => Ok(MemberOrValue::Member(x)),
+                None => Err(e),
+            },
+            res => res,
+        }
+    }
+
+    /// Like [`get_attr_hashed_bind`], falling back to the type extensions.
+    #[inline(always)]
+    pub(crate) fn get_attr_bind(
+        &self,
+        value: Value<'v>,
+        attribute: &Symbol,
+        cache: Option<&AttrCache>,
+    ) -> anyhow::Result<Value<'v>> {
+        match get_attr_hashed_bind(value, attribute, cache, self.heap()) {
+            Err(e) => match self.get_type_extension(value, attribute.as_str()) {
+                Some(x) => MaybeUnboundValue::new(x).bind(value, self.heap()),
+                None => Err(e),
+            },
+            res => res,
+        }
+    }
+
     /// Called to add an entry to the call stack, by the function being invoked.
     /// Called for all types of function, including those written in Rust.
     #[inline(always)]
@@ -419,6 +728,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
             })
         }
 
+        self.check_limits()?;
         self.call_stack.push(function, span)?;
         // Must always call .pop regardless
         let res = within(self).map_err(|e| add_diagnostics(e, self));
@@ -17,12 +17,16 @@
 
 pub(crate) mod arguments;
 pub(crate) mod before_stmt;
+pub(crate) mod builtin_policy;
+pub(crate) mod call_hook;
+pub(crate) mod call_log;
 pub(crate) mod call_stack;
 pub(crate) mod evaluator;
 pub(crate) mod file_loader;
 pub(crate) mod frame_span;
 pub(crate) mod frozen_file_span;
 pub(crate) mod inlined_frame;
+pub(crate) mod named_parameters;
 pub(crate) mod params;
 pub(crate) mod profile;
 pub(crate) mod rust_loc;
@@ -0,0 +1,88 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use crate::docs;
+use crate::docs::DocString;
+use crate::eval::Arguments;
+use crate::eval::ParametersSpec;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+
+/// A struct holding the named-only parameters of a native function, one field per
+/// parameter. Useful for functions with many optional parameters, such as rule
+/// definitions.
+///
+/// Usually implemented with `#[derive(NamedParameters)]`, and taken by a function in
+/// a [`#[starlark_module]`](macro@crate::starlark_module) as a single parameter
+/// annotated with `#[starlark(named_params)]`:
+///
+/// ```
+/// use starlark::environment::GlobalsBuilder;
+/// use starlark::eval::NamedParameters;
+/// use starlark::starlark_module;
+///
+/// #[derive(NamedParameters)]
+/// struct RuleParams {
+///     /// The name of the target.
+///     name: String,
+///     /// Source files.
+///     srcs: Option<Vec<String>>,
+///     /// Link statically.
+///     #[starlark(default = false)]
+///     linkstatic: bool,
+/// }
+///
+/// #[starlark_module]
+/// fn rules(builder: &mut GlobalsBuilder) {
+///     fn rule(#[starlark(named_params)] params: RuleParams) -> anyhow::Result<String> {
+///         Ok(format!(
+///             "{}: {:?}, linkstatic={}",
+///             params.name,
+///             params.srcs.unwrap_or_default(),
+///             if params.linkstatic { "True" } else { "False" }
+///         ))
+///     }
+/// }
+///
+/// # let mut a = starlark::assert::Assert::new();
+/// # a.globals_add(rules);
+/// # a.eq("'x: [\"a.c\"], linkstatic=False'", "rule(name = 'x', srcs = ['a.c'])");
+/// ```
+///
+/// Fields of type `Option<T>` are optional, fields with `#[starlark(default = expr)]`
+/// default to `expr`, and any other fields are required. Doc comments on the fields
+/// document the parameters.
+pub trait NamedParameters<'v>: Sized {
+    /// The signature of function `function_name` taking these parameters.
+    fn signature(function_name: &str) -> ParametersSpec<FrozenValue>;
+
+    /// The types of the parameters, by index in the signature.
+    fn parameter_types() -> HashMap<usize, docs::Type>;
+
+    /// The documentation of the parameters, by name.
+    fn parameter_docs() -> HashMap<String, Option<DocString>>;
+
+    /// Parse `args`, given the `signature` created by
+    /// [`signature`](NamedParameters::signature).
+    fn parse(
+        signature: &ParametersSpec<FrozenValue>,
+        args: &Arguments<'v, '_>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Self>;
+}
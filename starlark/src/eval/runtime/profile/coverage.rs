@@ -0,0 +1,119 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Line coverage, collected with [`ProfileMode::Coverage`](crate::eval::ProfileMode::Coverage)
+/// and returned by [`Evaluator::line_coverage`](crate::eval::Evaluator::line_coverage).
+///
+/// A line is executable if a statement starts on it. All the statements of the modules
+/// evaluated with coverage enabled are known, including those never executed, while of
+/// other modules, such as loaded modules whose functions were called, only the executed
+/// lines are. Coverage of several evaluations can be [merged](Coverage::merge), e.g. to
+/// combine the coverage of a library with the coverage of the tests calling it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// For each file, the number of times each executable line (one-based) was executed.
+    files: BTreeMap<String, BTreeMap<usize, u64>>,
+}
+
+impl Coverage {
+    /// Record that `line` (one-based) of `file` is executable.
+    pub(crate) fn add_executable(&mut self, file: &str, line: usize) {
+        self.file_mut(file).entry(line).or_insert(0);
+    }
+
+    /// Record that `line` (one-based) of `file` was executed `count` times.
+    pub(crate) fn add_executed(&mut self, file: &str, line: usize, count: u64) {
+        *self.file_mut(file).entry(line).or_insert(0) += count;
+    }
+
+    fn file_mut(&mut self, file: &str) -> &mut BTreeMap<usize, u64> {
+        if !self.files.contains_key(file) {
+            self.files.insert(file.to_owned(), BTreeMap::new());
+        }
+        self.files.get_mut(file).unwrap()
+    }
+
+    /// Names of the files with coverage information, in sorted order.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(|x| x.as_str())
+    }
+
+    /// The executable lines (one-based) of `file` with the number of times they were executed,
+    /// in line order.
+    pub fn lines(&self, file: &str) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.files
+            .get(file)
+            .into_iter()
+            .flat_map(|x| x.iter().map(|(line, count)| (*line, *count)))
+    }
+
+    /// Add the coverage of another evaluation to this one.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (file, lines) in &other.files {
+            let mine = self.file_mut(file);
+            for (line, count) in lines {
+                *mine.entry(*line).or_insert(0) += count;
+            }
+        }
+    }
+
+    /// Render in the [lcov](https://github.com/linux-test-project/lcov) tracefile format,
+    /// which most coverage tools and services accept.
+    pub fn to_lcov(&self) -> String {
+        let mut w = String::new();
+        for (file, lines) in &self.files {
+            writeln!(w, "SF:{}", file).unwrap();
+            for (line, count) in lines {
+                writeln!(w, "DA:{},{}", line, count).unwrap();
+            }
+            writeln!(w, "LH:{}", lines.values().filter(|x| **x != 0).count()).unwrap();
+            writeln!(w, "LF:{}", lines.len()).unwrap();
+            writeln!(w, "end_of_record").unwrap();
+        }
+        w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::runtime::profile::coverage::Coverage;
+
+    #[test]
+    fn test_lcov() {
+        let mut coverage = Coverage::default();
+        coverage.add_executable("b.star", 1);
+        coverage.add_executable("a.star", 1);
+        coverage.add_executable("a.star", 2);
+        coverage.add_executed("a.star", 1, 2);
+        let mut other = Coverage::default();
+        other.add_executed("a.star", 1, 1);
+        other.add_executed("a.star", 3, 1);
+        coverage.merge(&other);
+        assert_eq!(
+            vec![(1, 3), (2, 0), (3, 1)],
+            coverage.lines("a.star").collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "SF:a.star\nDA:1,3\nDA:2,0\nDA:3,1\nLH:2\nLF:3\nend_of_record\n\
+            SF:b.star\nDA:1,0\nLH:0\nLF:1\nend_of_record\n",
+            coverage.to_lcov()
+        );
+    }
+}
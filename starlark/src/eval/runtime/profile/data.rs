@@ -24,7 +24,10 @@ use gazebo::prelude::*;
 
 use crate::eval::runtime::profile::bc::BcPairsProfileData;
 use crate::eval::runtime::profile::bc::BcProfileData;
+use crate::eval::runtime::profile::coverage::Coverage;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+use crate::eval::runtime::profile::heap::HeapProfileFormat;
+use crate::eval::runtime::profile::time_events::TimeEvents;
 use crate::eval::ProfileMode;
 use crate::values::AggregateHeapProfileInfo;
 
@@ -36,6 +39,10 @@ enum ProfileDataError {
     EmptyProfileList,
     #[error("Different profile modes in profile")]
     DifferentProfileModes,
+    #[error("Different heap profile formats in profile")]
+    DifferentHeapProfileFormats,
+    #[error("Profile data in format `{0:?}` is binary, use `gen_bytes`")]
+    BinaryProfile(HeapProfileFormat),
     #[error("Merge of profile data for profile mode `{0}` is not implemented")]
     MergeNotImplemented(ProfileMode),
 }
@@ -44,9 +51,11 @@ enum ProfileDataError {
 pub(crate) enum ProfileDataImpl {
     Bc(Box<BcProfileData>),
     BcPairs(BcPairsProfileData),
-    AggregateHeapProfileInfo(Box<AggregateHeapProfileInfo>),
+    AggregateHeapProfileInfo(Box<AggregateHeapProfileInfo>, HeapProfileFormat),
     /// Flame graph data is in milliseconds.
     TimeFlameProfile(FlameGraphData),
+    TimeEvents(TimeEvents),
+    Coverage(Coverage),
     Other(String),
 }
 
@@ -66,33 +75,65 @@ impl ProfileData {
         }
     }
 
+    pub(crate) fn new_coverage(coverage: Coverage) -> ProfileData {
+        ProfileData {
+            profile_mode: ProfileMode::Coverage,
+            profile: ProfileDataImpl::Coverage(coverage),
+        }
+    }
+
     /// Generate a string with profile data (e.g. CSV or flamegraph, depending on profile type).
+    ///
+    /// Fails for binary profiles, which must be generated with [`gen_bytes`](ProfileData::gen_bytes).
     pub fn gen(&self) -> anyhow::Result<String> {
         match (&self.profile, &self.profile_mode) {
             (ProfileDataImpl::Other(profile), _) => Ok(profile.clone()),
+            (ProfileDataImpl::Coverage(coverage), _) => Ok(coverage.to_lcov()),
             (ProfileDataImpl::Bc(bc), _) => Ok(bc.gen_csv()),
             (ProfileDataImpl::BcPairs(bc_pairs), _) => Ok(bc_pairs.gen_csv()),
             (
-                ProfileDataImpl::AggregateHeapProfileInfo(profile),
-                ProfileMode::HeapFlameRetained | ProfileMode::HeapFlameAllocated,
-            ) => Ok(profile.gen_flame_graph()),
-            (
-                ProfileDataImpl::AggregateHeapProfileInfo(profile),
-                ProfileMode::HeapSummaryRetained | ProfileMode::HeapSummaryAllocated,
-            ) => Ok(profile.gen_summary_csv()),
-            (ProfileDataImpl::AggregateHeapProfileInfo(_), _) => {
+                ProfileDataImpl::AggregateHeapProfileInfo(profile, format),
+                ProfileMode::HeapSummaryAllocated
+                | ProfileMode::HeapSummaryRetained
+                | ProfileMode::HeapFlameAllocated
+                | ProfileMode::HeapFlameRetained,
+            ) => match format {
+                HeapProfileFormat::Summary => Ok(profile.gen_summary_csv()),
+                HeapProfileFormat::FlameGraph => Ok(profile.gen_flame_graph()),
+                HeapProfileFormat::Pprof => Err(ProfileDataError::BinaryProfile(*format).into()),
+            },
+            (ProfileDataImpl::AggregateHeapProfileInfo(..), _) => {
                 Err(ProfileDataError::ProfileDataNotConsistent.into())
             }
             (ProfileDataImpl::TimeFlameProfile(data), ProfileMode::TimeFlame) => Ok(data.write()),
             (ProfileDataImpl::TimeFlameProfile(_), _) => {
                 Err(ProfileDataError::ProfileDataNotConsistent.into())
             }
+            (ProfileDataImpl::TimeEvents(events), ProfileMode::TimeSpeedscope) => {
+                Ok(events.gen_speedscope())
+            }
+            (ProfileDataImpl::TimeEvents(events), ProfileMode::TimeChromeTrace) => {
+                Ok(events.gen_chrome_trace())
+            }
+            (ProfileDataImpl::TimeEvents(_), _) => {
+                Err(ProfileDataError::ProfileDataNotConsistent.into())
+            }
+        }
+    }
+
+    /// Generate profile data as bytes, works for both text and binary profiles.
+    pub fn gen_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        match &self.profile {
+            ProfileDataImpl::AggregateHeapProfileInfo(profile, HeapProfileFormat::Pprof) => {
+                Ok(profile.gen_pprof())
+            }
+            _ => Ok(self.gen()?.into_bytes()),
         }
     }
 
     /// Write to a file.
     pub fn write(&self, path: &Path) -> anyhow::Result<()> {
-        fs::write(path, self.gen()?).with_context(|| {
+        fs::write(path, self.gen_bytes()?).with_context(|| {
             format!(
                 "write profile `{}` data to `{}`",
                 self.profile_mode,
@@ -138,11 +179,17 @@ impl ProfileData {
             | ProfileMode::HeapFlameAllocated
             | ProfileMode::HeapFlameRetained => {
                 let profiles = profiles.try_map(|p| match &p.profile {
-                    ProfileDataImpl::AggregateHeapProfileInfo(profile) => Ok(&**profile),
+                    ProfileDataImpl::AggregateHeapProfileInfo(profile, format) => {
+                        Ok((&**profile, *format))
+                    }
                     _ => Err(ProfileDataError::ProfileDataNotConsistent),
                 })?;
-                let profile = AggregateHeapProfileInfo::merge(profiles);
-                ProfileDataImpl::AggregateHeapProfileInfo(Box::new(profile))
+                let format = profiles[0].1;
+                if profiles.iter().any(|(_, f)| *f != format) {
+                    return Err(ProfileDataError::DifferentHeapProfileFormats.into());
+                }
+                let profile = AggregateHeapProfileInfo::merge(profiles.iter().map(|(p, _)| *p));
+                ProfileDataImpl::AggregateHeapProfileInfo(Box::new(profile), format)
             }
             ProfileMode::TimeFlame => {
                 let profiles = profiles.try_map(|p| match &p.profile {
@@ -152,6 +199,23 @@ impl ProfileData {
                 let profile = FlameGraphData::merge(profiles);
                 ProfileDataImpl::TimeFlameProfile(profile)
             }
+            ProfileMode::TimeSpeedscope | ProfileMode::TimeChromeTrace => {
+                let profiles = profiles.try_map(|p| match &p.profile {
+                    ProfileDataImpl::TimeEvents(events) => Ok(events),
+                    _ => Err(ProfileDataError::ProfileDataNotConsistent),
+                })?;
+                ProfileDataImpl::TimeEvents(TimeEvents::merge(profiles))
+            }
+            ProfileMode::Coverage => {
+                let mut coverage = Coverage::default();
+                for p in profiles {
+                    match &p.profile {
+                        ProfileDataImpl::Coverage(x) => coverage.merge(x),
+                        _ => return Err(ProfileDataError::ProfileDataNotConsistent.into()),
+                    }
+                }
+                ProfileDataImpl::Coverage(coverage)
+            }
             profile_mode => {
                 return Err(ProfileDataError::MergeNotImplemented(profile_mode.dupe()).into());
             }
@@ -170,6 +234,7 @@ mod tests {
     use crate::eval::runtime::profile::bc::BcPairsProfileData;
     use crate::eval::runtime::profile::data::ProfileDataImpl;
     use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+    use crate::eval::runtime::profile::heap::HeapProfileFormat;
     use crate::eval::ProfileData;
     use crate::eval::ProfileMode;
 
@@ -203,13 +268,31 @@ mod tests {
         ] {
             let profile = ProfileData {
                 profile_mode: profile_mode.dupe(),
-                profile: ProfileDataImpl::AggregateHeapProfileInfo(Box::default()),
+                profile: ProfileDataImpl::AggregateHeapProfileInfo(
+                    Box::default(),
+                    HeapProfileFormat::default_for(&profile_mode),
+                ),
             };
             // Smoke.
             ProfileData::merge([&profile, &profile]).unwrap();
         }
     }
 
+    #[test]
+    fn merge_aggregated_heap_profile_different_formats() {
+        let profile = |format| ProfileData {
+            profile_mode: ProfileMode::HeapSummaryAllocated,
+            profile: ProfileDataImpl::AggregateHeapProfileInfo(Box::default(), format),
+        };
+        let pprof = profile(HeapProfileFormat::Pprof);
+        let merged = ProfileData::merge([&pprof, &pprof]).unwrap();
+        assert!(merged.gen().is_err());
+        assert!(!merged.gen_bytes().unwrap().is_empty());
+
+        let summary = profile(HeapProfileFormat::Summary);
+        assert!(ProfileData::merge([&pprof, &summary]).is_err());
+    }
+
     #[test]
     fn merge_time_flame() {
         let profile = ProfileData {
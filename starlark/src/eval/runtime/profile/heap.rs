@@ -48,10 +48,32 @@ enum HeapProfileError {
     NotEnabled,
 }
 
-#[derive(Copy, Clone, Dupe, Debug)]
-pub(crate) enum HeapProfileFormat {
+/// Format heap profiles are written in, see
+/// [`Evaluator::set_heap_profile_format`](crate::eval::Evaluator::set_heap_profile_format).
+#[derive(Copy, Clone, Dupe, Debug, PartialEq, Eq, Allocative)]
+#[non_exhaustive]
+pub enum HeapProfileFormat {
+    /// Per-function summary in CSV format.
     Summary,
+    /// Folded stacks, compatible with
+    /// [flamegraph.pl](https://github.com/brendangregg/FlameGraph/blob/master/flamegraph.pl).
     FlameGraph,
+    /// Protobuf understood by [pprof](https://github.com/google/pprof),
+    /// with allocation count and bytes for every stack.
+    /// Binary, so must be generated with [`ProfileData::gen_bytes`].
+    Pprof,
+}
+
+impl HeapProfileFormat {
+    /// The format used for a heap profile mode when none is set explicitly.
+    pub(crate) fn default_for(mode: &ProfileMode) -> HeapProfileFormat {
+        match mode {
+            ProfileMode::HeapFlameAllocated | ProfileMode::HeapFlameRetained => {
+                HeapProfileFormat::FlameGraph
+            }
+            _ => HeapProfileFormat::Summary,
+        }
+    }
 }
 
 pub(crate) struct HeapProfile {
@@ -87,34 +109,24 @@ impl HeapProfile {
     pub(crate) fn gen(
         &self,
         heap: &Heap,
+        profile_mode: &ProfileMode,
         format: HeapProfileFormat,
     ) -> anyhow::Result<ProfileData> {
         if !self.enabled {
             return Err(HeapProfileError::NotEnabled.into());
         }
-        Ok(Self::gen_enabled(heap, format))
-    }
-
-    pub(crate) fn gen_enabled(heap: &Heap, format: HeapProfileFormat) -> ProfileData {
-        match format {
-            HeapProfileFormat::Summary => Self::write_summarized_heap_profile(heap),
-            HeapProfileFormat::FlameGraph => Self::write_flame_heap_profile(heap),
-        }
-    }
-
-    fn write_flame_heap_profile(heap: &Heap) -> ProfileData {
-        let stacks = AggregateHeapProfileInfo::collect(heap, None);
-        ProfileData {
-            profile_mode: ProfileMode::HeapFlameAllocated,
-            profile: ProfileDataImpl::AggregateHeapProfileInfo(Box::new(stacks)),
-        }
+        Ok(Self::gen_enabled(heap, profile_mode, format))
     }
 
-    fn write_summarized_heap_profile(heap: &Heap) -> ProfileData {
+    pub(crate) fn gen_enabled(
+        heap: &Heap,
+        profile_mode: &ProfileMode,
+        format: HeapProfileFormat,
+    ) -> ProfileData {
         let stacks = AggregateHeapProfileInfo::collect(heap, None);
         ProfileData {
-            profile_mode: ProfileMode::HeapSummaryAllocated,
-            profile: ProfileDataImpl::AggregateHeapProfileInfo(Box::new(stacks)),
+            profile_mode: profile_mode.dupe(),
+            profile: ProfileDataImpl::AggregateHeapProfileInfo(Box::new(stacks), format),
         }
     }
 }
@@ -147,12 +159,13 @@ f
         let globals = Globals::standard();
         let module = Module::new();
         let mut eval = Evaluator::new(&module);
-        eval.enable_profile(&ProfileMode::HeapSummaryAllocated)
-            .unwrap();
+        let mode = ProfileMode::HeapSummaryAllocated;
+        eval.enable_profile(&mode).unwrap();
         let f = eval.eval_module(ast, &globals)?;
         // first check module profiling works
-        HeapProfile::write_summarized_heap_profile(module.heap());
-        HeapProfile::write_flame_heap_profile(module.heap());
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::Summary);
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::FlameGraph);
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::Pprof);
 
         // second check function profiling works
         let module = Module::new();
@@ -160,8 +173,9 @@ f
         eval.enable_profile(&ProfileMode::HeapSummaryAllocated)
             .unwrap();
         eval.eval_function(f, &[Value::new_int(100)], &[])?;
-        HeapProfile::write_summarized_heap_profile(module.heap());
-        HeapProfile::write_flame_heap_profile(module.heap());
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::Summary);
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::FlameGraph);
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::Pprof);
 
         // finally, check a user can add values into the heap before/after
         let module = Module::new();
@@ -171,8 +185,9 @@ f
             .unwrap();
         eval.eval_function(f, &[Value::new_int(100)], &[])?;
         module.heap().alloc("Thing that goes after");
-        HeapProfile::write_summarized_heap_profile(module.heap());
-        HeapProfile::write_flame_heap_profile(module.heap());
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::Summary);
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::FlameGraph);
+        HeapProfile::gen_enabled(module.heap(), &mode, HeapProfileFormat::Pprof);
 
         Ok(())
     }
@@ -22,12 +22,15 @@ use allocative::Allocative;
 use dupe::Dupe;
 
 pub(crate) mod bc;
+pub(crate) mod coverage;
 pub(crate) mod csv;
 pub(crate) mod data;
 pub(crate) mod flamegraph;
 pub(crate) mod heap;
 pub(crate) mod or_instrumentation;
+pub(crate) mod pprof;
 pub(crate) mod stmt;
+pub(crate) mod time_events;
 pub(crate) mod time_flame;
 pub(crate) mod typecheck;
 
@@ -48,7 +51,7 @@ pub enum ProfileMode {
     HeapFlameRetained,
     /// The statement profile mode provides information about time spent in each statement.
     Statement,
-    /// Code coverage.
+    /// Code coverage, generated in the [lcov](https://github.com/linux-test-project/lcov) format.
     Coverage,
     /// The bytecode profile mode provides information about bytecode instructions.
     Bytecode,
@@ -57,6 +60,12 @@ pub enum ProfileMode {
     /// Provide output compatible with
     /// [flamegraph.pl](https://github.com/brendangregg/FlameGraph/blob/master/flamegraph.pl).
     TimeFlame,
+    /// Time spent in each call, in the [speedscope](https://www.speedscope.app) JSON format.
+    TimeSpeedscope,
+    /// Time spent in each call, in the Chrome
+    /// [trace event](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+    /// JSON format, which `chrome://tracing` and [Perfetto](https://ui.perfetto.dev) open.
+    TimeChromeTrace,
     /// Profile runtime typechecking.
     Typecheck,
 }
@@ -79,6 +88,8 @@ impl ProfileMode {
             ProfileMode::Bytecode => "bytecode",
             ProfileMode::BytecodePairs => "bytecode-pairs",
             ProfileMode::TimeFlame => "time-flame",
+            ProfileMode::TimeSpeedscope => "time-speedscope",
+            ProfileMode::TimeChromeTrace => "time-chrome-trace",
             ProfileMode::Typecheck => "typecheck",
         }
     }
@@ -98,6 +109,8 @@ impl FromStr for ProfileMode {
             ProfileMode::Bytecode,
             ProfileMode::BytecodePairs,
             ProfileMode::TimeFlame,
+            ProfileMode::TimeSpeedscope,
+            ProfileMode::TimeChromeTrace,
             ProfileMode::Typecheck,
         ] {
             if s == mode.name() {
@@ -0,0 +1,235 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Utility to write profiles in the [pprof](https://github.com/google/pprof) protobuf format.
+//!
+//! Only the parts of `profile.proto` we need are written, and the output is not gzipped,
+//! which `pprof` accepts as well.
+
+use starlark_map::small_set::SmallSet;
+
+/// Builder for a pprof profile.
+///
+/// Every distinct frame name becomes a function and a location with the same id.
+pub(crate) struct PprofBuilder {
+    strings: SmallSet<String>,
+    /// Pairs of (type, unit) string ids.
+    sample_types: Vec<(u64, u64)>,
+    /// Function names as string ids, function (and location) id is the index plus one.
+    functions: SmallSet<u64>,
+    /// Pairs of (location ids, leaf first; values).
+    samples: Vec<(Vec<u64>, Vec<i64>)>,
+}
+
+impl PprofBuilder {
+    /// New builder, with one value of type `(type, unit)` per sample type.
+    pub(crate) fn new(sample_types: &[(&str, &str)]) -> PprofBuilder {
+        let mut builder = PprofBuilder {
+            strings: SmallSet::new(),
+            sample_types: Vec::new(),
+            functions: SmallSet::new(),
+            samples: Vec::new(),
+        };
+        // The string table must start with the empty string.
+        builder.string("");
+        builder.sample_types = sample_types
+            .iter()
+            .map(|(typ, unit)| (builder.string(typ), builder.string(unit)))
+            .collect();
+        builder
+    }
+
+    fn string(&mut self, s: &str) -> u64 {
+        if let Some(index) = self.strings.get_index_of(s) {
+            return index as u64;
+        }
+        self.strings.insert(s.to_owned());
+        (self.strings.len() - 1) as u64
+    }
+
+    /// Location id of a frame with the given name.
+    pub(crate) fn location(&mut self, name: &str) -> u64 {
+        let name = self.string(name);
+        match self.functions.get_index_of(&name) {
+            Some(index) => index as u64 + 1,
+            None => {
+                self.functions.insert(name);
+                self.functions.len() as u64
+            }
+        }
+    }
+
+    /// Add a sample, with locations leaf first and one value per sample type.
+    pub(crate) fn add_sample(&mut self, locations: Vec<u64>, values: Vec<i64>) {
+        assert_eq!(values.len(), self.sample_types.len());
+        self.samples.push((locations, values));
+    }
+
+    /// Encode the `Profile` message.
+    pub(crate) fn build(self) -> Vec<u8> {
+        let mut profile = Vec::new();
+        for (typ, unit) in &self.sample_types {
+            let mut value_type = Vec::new();
+            write_varint_field(&mut value_type, 1, *typ);
+            write_varint_field(&mut value_type, 2, *unit);
+            write_bytes_field(&mut profile, 1, &value_type);
+        }
+        for (locations, values) in &self.samples {
+            let mut sample = Vec::new();
+            write_packed_field(&mut sample, 1, locations.iter().copied());
+            write_packed_field(&mut sample, 2, values.iter().map(|v| *v as u64));
+            write_bytes_field(&mut profile, 2, &sample);
+        }
+        for id in 1..=self.functions.len() as u64 {
+            let mut line = Vec::new();
+            write_varint_field(&mut line, 1, id);
+            let mut location = Vec::new();
+            write_varint_field(&mut location, 1, id);
+            write_bytes_field(&mut location, 4, &line);
+            write_bytes_field(&mut profile, 4, &location);
+        }
+        for (index, name) in self.functions.iter().enumerate() {
+            let mut function = Vec::new();
+            write_varint_field(&mut function, 1, index as u64 + 1);
+            write_varint_field(&mut function, 2, *name);
+            write_varint_field(&mut function, 3, *name);
+            write_bytes_field(&mut profile, 5, &function);
+        }
+        for s in &self.strings {
+            write_bytes_field(&mut profile, 6, s.as_bytes());
+        }
+        profile
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn write_varint_field(buf: &mut Vec<u8>, field: u64, value: u64) {
+    write_varint(buf, field << 3);
+    write_varint(buf, value);
+}
+
+fn write_bytes_field(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(buf, (field << 3) | 2);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn write_packed_field(buf: &mut Vec<u8>, field: u64, values: impl IntoIterator<Item = u64>) {
+    let mut packed = Vec::new();
+    for value in values {
+        write_varint(&mut packed, value);
+    }
+    write_bytes_field(buf, field, &packed);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::eval::runtime::profile::pprof::PprofBuilder;
+
+    /// A decoded protobuf field: either a varint or length-delimited bytes.
+    #[derive(Debug, PartialEq)]
+    pub(crate) enum Field {
+        Varint(u64),
+        Bytes(Vec<u8>),
+    }
+
+    fn read_varint(buf: &mut &[u8]) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = buf[0];
+            *buf = &buf[1..];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    /// Decode the fields of a message, which only uses varint and length-delimited fields.
+    pub(crate) fn decode(mut buf: &[u8]) -> Vec<(u64, Field)> {
+        let mut fields = Vec::new();
+        while !buf.is_empty() {
+            let key = read_varint(&mut buf);
+            let field = match key & 7 {
+                0 => Field::Varint(read_varint(&mut buf)),
+                2 => {
+                    let len = read_varint(&mut buf) as usize;
+                    let (bytes, rest) = buf.split_at(len);
+                    buf = rest;
+                    Field::Bytes(bytes.to_vec())
+                }
+                wire_type => panic!("unexpected wire type {}", wire_type),
+            };
+            fields.push((key >> 3, field));
+        }
+        fields
+    }
+
+    /// The string table of an encoded profile.
+    pub(crate) fn strings(profile: &[u8]) -> Vec<String> {
+        decode(profile)
+            .into_iter()
+            .filter_map(|(field, value)| match (field, value) {
+                (6, Field::Bytes(s)) => Some(String::from_utf8(s).unwrap()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pprof_builder() {
+        let mut builder = PprofBuilder::new(&[("space", "bytes")]);
+        let f = builder.location("f");
+        let g = builder.location("g");
+        assert_eq!(f, builder.location("f"));
+        builder.add_sample(vec![g, f], vec![300]);
+        let profile = builder.build();
+
+        assert_eq!(vec!["", "space", "bytes", "f", "g"], strings(&profile));
+        let fields = decode(&profile);
+        assert_eq!(
+            (1, Field::Bytes(vec![0x08, 1, 0x10, 2])),
+            fields[0],
+            "sample type"
+        );
+        // Locations `[2, 1]` and value 300 as varint.
+        assert_eq!(
+            (2, Field::Bytes(vec![0x0a, 2, 2, 1, 0x12, 2, 0xac, 0x02])),
+            fields[1],
+            "sample"
+        );
+        // Two locations and two functions.
+        assert_eq!(
+            vec![4, 4, 5, 5],
+            fields[2..6].iter().map(|(f, _)| *f).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            (5, Field::Bytes(vec![0x08, 2, 0x10, 4, 0x18, 4])),
+            fields[5],
+            "function `g`"
+        );
+    }
+}
@@ -29,10 +29,13 @@ use crate::codemap::FileSpan;
 use crate::codemap::FileSpanRef;
 use crate::codemap::ResolvedFileSpan;
 use crate::codemap::Span;
+use crate::eval::runtime::profile::coverage::Coverage;
 use crate::eval::runtime::profile::csv::CsvWriter;
 use crate::eval::runtime::profile::data::ProfileData;
 use crate::eval::runtime::small_duration::SmallDuration;
 use crate::eval::ProfileMode;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::Stmt;
 
 #[derive(Debug, thiserror::Error)]
 enum StmtProfileError {
@@ -49,6 +52,8 @@ pub(crate) struct StmtProfile(Option<Box<StmtProfileData>>);
 struct StmtProfileData {
     files: HashMap<CodeMapId, CodeMap>,
     stmts: HashMap<(CodeMapId, Span), (usize, SmallDuration)>,
+    /// All the statements of the modules evaluated, executed or not.
+    executable: HashSet<(CodeMapId, Span)>,
     next_file: CodeMapId,
     last_span: (CodeMapId, Span),
     last_start: Instant,
@@ -59,6 +64,7 @@ impl StmtProfileData {
         StmtProfileData {
             files: HashMap::new(),
             stmts: HashMap::new(),
+            executable: HashSet::new(),
             next_file: CodeMapId::EMPTY,
             last_span: (CodeMapId::EMPTY, Span::default()),
             last_start: Instant::now(),
@@ -103,6 +109,46 @@ impl StmtProfileData {
         }
     }
 
+    fn add_executable(&mut self, codemap: &CodeMap, stmt: &AstStmt) {
+        fn walk(x: &AstStmt, id: CodeMapId, executable: &mut HashSet<(CodeMapId, Span)>) {
+            if !matches!(&**x, Stmt::Statements(_)) {
+                executable.insert((id, x.span));
+            }
+            x.visit_stmt(|x| walk(x, id, executable));
+        }
+
+        self.files
+            .entry(codemap.id())
+            .or_insert_with(|| codemap.dupe());
+        walk(stmt, codemap.id(), &mut self.executable);
+    }
+
+    fn line_coverage(&self, now: Instant) -> Coverage {
+        // Count the statement running last, like `write_to_string`.
+        let mut data = self.clone();
+        data.add_last(now);
+
+        let mut coverage = Coverage::default();
+        let line = |(file, span): &(CodeMapId, Span)| {
+            let codemap = &data.files[file];
+            (
+                codemap.filename(),
+                codemap.resolve_span(*span).begin_line + 1,
+            )
+        };
+        for x in &data.executable {
+            let (file, line) = line(x);
+            coverage.add_executable(file, line);
+        }
+        for (x, (count, _)) in &data.stmts {
+            if x.0 != CodeMapId::EMPTY {
+                let (file, line) = line(x);
+                coverage.add_executed(file, line, *count as u64);
+            }
+        }
+        coverage
+    }
+
     fn write_to_string(&self, now: Instant) -> String {
         // The statement that was running last won't have been properly updated.
         // However, at this point, we have probably run some post-execution code,
@@ -194,6 +240,21 @@ impl StmtProfile {
         }
     }
 
+    /// Record the statements of a module about to be evaluated, if enabled.
+    pub(crate) fn add_executable(&mut self, codemap: &CodeMap, stmt: &AstStmt) {
+        if let Some(data) = &mut self.0 {
+            data.add_executable(codemap, stmt)
+        }
+    }
+
+    pub(crate) fn line_coverage(&self) -> anyhow::Result<Coverage> {
+        Ok(self
+            .0
+            .as_ref()
+            .ok_or(StmtProfileError::NotEnabled)?
+            .line_coverage(Instant::now()))
+    }
+
     pub(crate) fn coverage(&self) -> anyhow::Result<HashSet<ResolvedFileSpan>> {
         Ok(self
             .0
@@ -255,4 +316,48 @@ xx(*[2])
             coverage
         );
     }
+
+    #[test]
+    fn test_line_coverage() {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let program = r#"
+def f(x):
+    if x:
+        return 1
+    return 2
+
+def g():
+    pass
+
+f(False)
+f(False)
+"#;
+        eval.enable_profile(&ProfileMode::Coverage).unwrap();
+        eval.eval_module(
+            AstModule::parse("cov.star", program.to_owned(), &Dialect::Extended).unwrap(),
+            &GlobalsBuilder::standard().build(),
+        )
+        .unwrap();
+
+        let coverage = eval.line_coverage().unwrap();
+        assert_eq!(vec!["cov.star"], coverage.files().collect::<Vec<_>>());
+        assert_eq!(
+            vec![
+                (2, 1),
+                (3, 2),
+                (4, 0),
+                (5, 2),
+                (7, 1),
+                (8, 0),
+                (10, 1),
+                (11, 1)
+            ],
+            coverage.lines("cov.star").collect::<Vec<_>>()
+        );
+        assert_eq!(
+            coverage.to_lcov(),
+            eval.gen_profile().unwrap().gen().unwrap()
+        );
+    }
 }
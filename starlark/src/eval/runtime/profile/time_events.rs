@@ -0,0 +1,269 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Timed function calls, written in the [speedscope](https://www.speedscope.app)
+//! and Chrome [trace event](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+//! formats.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use serde_json::json;
+use starlark_map::small_set::SmallSet;
+
+#[derive(Clone, Copy, Debug)]
+enum TimeEvent {
+    /// Function with the given name index was entered.
+    Open(usize, Duration),
+    /// The innermost open function returned.
+    Close(usize, Duration),
+}
+
+/// The calls of one evaluation, balanced, with times relative to its start.
+#[derive(Clone, Debug, Default)]
+struct Timeline {
+    events: Vec<TimeEvent>,
+    end: Duration,
+}
+
+/// Function calls of one or more evaluations, in the order they happened.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TimeEvents {
+    names: SmallSet<String>,
+    /// One per evaluation, more than one after merge.
+    timelines: Vec<Timeline>,
+}
+
+impl TimeEvents {
+    fn name(&mut self, name: &str) -> usize {
+        if let Some(index) = self.names.get_index_of(name) {
+            return index;
+        }
+        self.names.insert(name.to_owned());
+        self.names.len() - 1
+    }
+
+    /// Build from call entries (`Some` of the index into `names`) and exits (`None`).
+    ///
+    /// Exits without an entry, made by calls in progress when profiling started, are ignored,
+    /// and calls in progress when profiling stopped are closed at the time of the last event.
+    pub(crate) fn new(
+        names: &[String],
+        frames: impl IntoIterator<Item = (Option<usize>, Instant)>,
+    ) -> TimeEvents {
+        let mut res = TimeEvents::default();
+        let mut timeline = Timeline::default();
+        let mut stack = Vec::new();
+        let mut start = None;
+        for (frame, time) in frames {
+            let at = time.duration_since(*start.get_or_insert(time));
+            timeline.end = at;
+            match frame {
+                Some(i) => {
+                    let name = res.name(&names[i]);
+                    stack.push(name);
+                    timeline.events.push(TimeEvent::Open(name, at));
+                }
+                None => {
+                    if let Some(name) = stack.pop() {
+                        timeline.events.push(TimeEvent::Close(name, at));
+                    }
+                }
+            }
+        }
+        while let Some(name) = stack.pop() {
+            timeline.events.push(TimeEvent::Close(name, timeline.end));
+        }
+        res.timelines.push(timeline);
+        res
+    }
+
+    /// Merge events of several evaluations, which are kept on separate timelines.
+    pub(crate) fn merge<'a>(profiles: impl IntoIterator<Item = &'a TimeEvents>) -> TimeEvents {
+        let mut res = TimeEvents::default();
+        for profile in profiles {
+            let names: Vec<usize> = profile.names.iter().map(|name| res.name(name)).collect();
+            for timeline in &profile.timelines {
+                res.timelines.push(Timeline {
+                    events: timeline
+                        .events
+                        .iter()
+                        .map(|e| match *e {
+                            TimeEvent::Open(i, at) => TimeEvent::Open(names[i], at),
+                            TimeEvent::Close(i, at) => TimeEvent::Close(names[i], at),
+                        })
+                        .collect(),
+                    end: timeline.end,
+                });
+            }
+        }
+        res
+    }
+
+    /// Speedscope JSON, with one evented profile per timeline, in nanoseconds.
+    pub(crate) fn gen_speedscope(&self) -> String {
+        let profiles: Vec<_> = self
+            .timelines
+            .iter()
+            .enumerate()
+            .map(|(i, timeline)| {
+                let events: Vec<_> = timeline
+                    .events
+                    .iter()
+                    .map(|e| match *e {
+                        TimeEvent::Open(frame, at) => {
+                            json!({"type": "O", "frame": frame, "at": at.as_nanos() as u64})
+                        }
+                        TimeEvent::Close(frame, at) => {
+                            json!({"type": "C", "frame": frame, "at": at.as_nanos() as u64})
+                        }
+                    })
+                    .collect();
+                json!({
+                    "type": "evented",
+                    "name": format!("starlark {}", i),
+                    "unit": "nanoseconds",
+                    "startValue": 0,
+                    "endValue": timeline.end.as_nanos() as u64,
+                    "events": events,
+                })
+            })
+            .collect();
+        let frames: Vec<_> = self.names.iter().map(|x| json!({ "name": x })).collect();
+        json!({
+            "$schema": "https://www.speedscope.app/file-format-schema.json",
+            "exporter": "starlark",
+            "shared": { "frames": frames },
+            "profiles": profiles,
+        })
+        .to_string()
+    }
+
+    /// Chrome trace event JSON, with one thread per timeline, in microseconds.
+    pub(crate) fn gen_chrome_trace(&self) -> String {
+        let mut events = Vec::new();
+        for (tid, timeline) in self.timelines.iter().enumerate() {
+            for e in &timeline.events {
+                let (ph, name, at) = match *e {
+                    TimeEvent::Open(name, at) => ("B", name, at),
+                    TimeEvent::Close(name, at) => ("E", name, at),
+                };
+                events.push(json!({
+                    "name": self.names.get_index(name),
+                    "ph": ph,
+                    "ts": at.as_nanos() as f64 / 1000.0,
+                    "pid": 1,
+                    "tid": tid + 1,
+                }));
+            }
+        }
+        json!({ "traceEvents": events, "displayTimeUnit": "ms" }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use serde_json::Value;
+
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::runtime::profile::time_events::TimeEvents;
+    use crate::eval::Evaluator;
+    use crate::eval::ProfileData;
+    use crate::eval::ProfileMode;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn events() -> TimeEvents {
+        let t = Instant::now();
+        let ms = |x| t + Duration::from_millis(x);
+        let names = ["f".to_owned(), "g".to_owned()];
+        TimeEvents::new(
+            &names,
+            [
+                (None, ms(0)),
+                (Some(0), ms(1)),
+                (Some(1), ms(2)),
+                (None, ms(4)),
+                (Some(1), ms(5)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_speedscope() {
+        let json: Value = serde_json::from_str(&events().gen_speedscope()).unwrap();
+        assert_eq!(json["shared"]["frames"][1]["name"], "g");
+        let profile = &json["profiles"][0];
+        assert_eq!(profile["endValue"], 5_000_000);
+        let events: Vec<_> = profile["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| {
+                format!(
+                    "{} {} {}",
+                    e["type"].as_str().unwrap(),
+                    e["frame"],
+                    e["at"].as_u64().unwrap() / 1_000_000
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec!["O 0 1", "O 1 2", "C 1 4", "O 1 5", "C 1 5", "C 0 5"],
+            events
+        );
+    }
+
+    #[test]
+    fn test_chrome_trace_merge() {
+        let events = events();
+        let merged = TimeEvents::merge([&events, &events]);
+        let json: Value = serde_json::from_str(&merged.gen_chrome_trace()).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+        assert_eq!(12, events.len());
+        assert_eq!(events[0]["name"], "f");
+        assert_eq!(events[0]["ph"], "B");
+        assert_eq!(events[0]["ts"].as_f64(), Some(1000.0));
+        assert_eq!(events[2]["ph"], "E");
+        assert_eq!(events[11]["tid"], 2);
+    }
+
+    #[test]
+    fn test_profile_modes() {
+        for mode in [ProfileMode::TimeSpeedscope, ProfileMode::TimeChromeTrace] {
+            let module = Module::new();
+            let mut eval = Evaluator::new(&module);
+            eval.enable_profile(&mode).unwrap();
+            let ast = AstModule::parse(
+                "a.star",
+                "def foo(x): return str(x)\nfoo(1)".to_owned(),
+                &Dialect::Standard,
+            )
+            .unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            let profile = eval.gen_profile().unwrap();
+            let profile = ProfileData::merge([&profile, &profile]).unwrap();
+            let text = profile.gen().unwrap();
+            serde_json::from_str::<Value>(&text).unwrap();
+            assert!(text.contains("foo"), "{}", text);
+        }
+    }
+}
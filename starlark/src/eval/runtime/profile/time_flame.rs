@@ -28,6 +28,7 @@ use crate::eval::runtime::profile::data::ProfileData;
 use crate::eval::runtime::profile::data::ProfileDataImpl;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
 use crate::eval::runtime::profile::flamegraph::FlameGraphNode;
+use crate::eval::runtime::profile::time_events::TimeEvents;
 use crate::eval::runtime::small_duration::SmallDuration;
 use crate::eval::ProfileMode;
 use crate::values::layout::heap::profile::arc_str::ArcStr;
@@ -177,21 +178,33 @@ impl<'v> FlameProfile<'v> {
     }
 
     // We could expose profile on the Heap, but it's an implementation detail that it works here.
-    pub(crate) fn gen(&self) -> anyhow::Result<ProfileData> {
+    pub(crate) fn gen(&self, mode: ProfileMode) -> anyhow::Result<ProfileData> {
         match &self.0 {
             None => Err(FlameProfileError::NotEnabled.into()),
-            Some(x) => Ok(Self::gen_profile(x)),
+            Some(x) => Ok(Self::gen_profile(x, mode)),
         }
     }
 
-    fn gen_profile(x: &FlameData) -> ProfileData {
-        // Need to write out lines which look like:
-        // root;calls1;calls2 1
-        // All the numbers at the end must be whole numbers (we use milliseconds)
+    fn gen_profile(x: &FlameData, mode: ProfileMode) -> ProfileData {
         let names = x.values.map(|x| x.to_repr());
+        let profile = match mode {
+            ProfileMode::TimeSpeedscope | ProfileMode::TimeChromeTrace => {
+                ProfileDataImpl::TimeEvents(TimeEvents::new(
+                    &names,
+                    x.frames.iter().map(|(frame, time)| match frame {
+                        Frame::Push(i) => (Some(i.0), *time),
+                        Frame::Pop => (None, *time),
+                    }),
+                ))
+            }
+            // Need to write out lines which look like:
+            // root;calls1;calls2 1
+            // All the numbers at the end must be whole numbers (we use milliseconds)
+            _ => ProfileDataImpl::TimeFlameProfile(Stacks::new(&names, &x.frames).render()),
+        };
         ProfileData {
-            profile_mode: ProfileMode::TimeFlame,
-            profile: ProfileDataImpl::TimeFlameProfile(Stacks::new(&names, &x.frames).render()),
+            profile_mode: mode,
+            profile,
         }
     }
 }
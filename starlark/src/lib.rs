@@ -399,6 +399,7 @@ pub mod docs;
 pub mod environment;
 pub mod errors;
 pub mod eval;
+#[cfg(feature = "lsp")]
 pub mod lsp;
 mod private;
 pub mod read_line;
@@ -430,10 +431,16 @@ pub mod __macro_refs {
 ///  `starlark::__derive_refs::foo`.
 #[doc(hidden)]
 pub mod __derive_refs {
+    pub use allocative;
     pub mod serde {
         pub use serde::ser::Error;
         pub use serde::Serialize;
         pub use serde::Serializer;
     }
     pub use inventory;
+
+    pub mod unpack {
+        pub use crate::values::unpack::derive::fields;
+        pub use crate::values::unpack::derive::optional;
+    }
 }
@@ -0,0 +1,538 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Bazel support, including bzlmod (`MODULE.bazel`) repository mapping.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use once_cell::sync::OnceCell;
+
+use crate::lsp::build_system::BuildSystem;
+use crate::syntax::ast::ArgumentP;
+use crate::syntax::ast::AstArgument;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::Stmt;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+/// Files whose presence marks the root of a Bazel workspace, in order of preference.
+pub(crate) const WORKSPACE_MARKERS: &[&str] = &["MODULE.bazel", "WORKSPACE.bazel", "WORKSPACE"];
+
+#[derive(thiserror::Error, Debug)]
+enum BazelError {
+    #[error("`bazel mod dump_repo_mapping` failed: {0}")]
+    DumpRepoMapping(String),
+    #[error("Repository mapping must be a JSON object of strings")]
+    InvalidRepoMapping,
+    #[error("`bazel info output_base` failed: {0}")]
+    OutputBase(String),
+}
+
+/// Mapping from the apparent repository names visible to the main repository
+/// (what users write in `@name//`) to canonical repository names (the names of the
+/// directories Bazel creates for them). The main repository has the canonical name `""`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RepositoryMapping {
+    mapping: HashMap<String, String>,
+}
+
+impl RepositoryMapping {
+    /// Parse the output of `bazel mod dump_repo_mapping ""`, a JSON object from
+    /// apparent name to canonical name.
+    pub fn parse_dump_repo_mapping(json: &str) -> anyhow::Result<Self> {
+        // Bazel prints one JSON object per requested repository; we only ever request one.
+        let json = json.lines().find(|x| !x.trim().is_empty()).unwrap_or("{}");
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let mapping = value
+            .as_object()
+            .ok_or(BazelError::InvalidRepoMapping)?
+            .iter()
+            .map(|(apparent, canonical)| match canonical.as_str() {
+                Some(canonical) => Ok((apparent.clone(), canonical.to_owned())),
+                None => Err(BazelError::InvalidRepoMapping),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { mapping })
+    }
+
+    /// Ask Bazel, run as `bazel`, for the repository mapping of the main repository in
+    /// `workspace_root`. Requires a Bazel version with bzlmod support (7.1 or later).
+    pub fn from_bazel(bazel: &Path, workspace_root: &Path) -> anyhow::Result<Self> {
+        let output = Command::new(bazel)
+            .args(["mod", "dump_repo_mapping", ""])
+            .current_dir(workspace_root)
+            .output()?;
+        if !output.status.success() {
+            return Err(BazelError::DumpRepoMapping(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            )
+            .into());
+        }
+        Self::parse_dump_repo_mapping(&String::from_utf8(output.stdout)?)
+    }
+
+    /// Build an approximate mapping from the contents of a `MODULE.bazel` file, for when
+    /// Bazel itself is not available.
+    ///
+    /// Bazel decides the exact canonical names of dependencies (they depend on the Bazel
+    /// version and resolved module versions), so dependencies are mapped to their module
+    /// names instead. That is enough to tell which repositories exist.
+    pub fn from_module_file(module: &AstModule) -> Self {
+        let mut mapping = HashMap::new();
+        for stmt in module.top_level_statements() {
+            let (name, args) = match top_level_call(stmt) {
+                Some(x) => x,
+                None => continue,
+            };
+            match name {
+                "module" => {
+                    for apparent in [string_arg(args, "name"), string_arg(args, "repo_name")]
+                        .into_iter()
+                        .flatten()
+                    {
+                        mapping.insert(apparent.to_owned(), String::new());
+                    }
+                }
+                "bazel_dep" => {
+                    if let Some(module_name) = string_arg(args, "name") {
+                        let apparent = string_arg(args, "repo_name").unwrap_or(module_name);
+                        mapping.insert(apparent.to_owned(), module_name.to_owned());
+                    }
+                }
+                "use_repo" => {
+                    for arg in args {
+                        match &arg.node {
+                            ArgumentP::Positional(x) => {
+                                if let Some(repo) = string_literal(x) {
+                                    mapping.insert(repo.to_owned(), repo.to_owned());
+                                }
+                            }
+                            ArgumentP::Named(alias, x) => {
+                                if let Some(repo) = string_literal(x) {
+                                    mapping.insert(alias.node.clone(), repo.to_owned());
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self { mapping }
+    }
+
+    /// The canonical name for a repository the main repository refers to as `apparent_name`.
+    pub fn canonical_name(&self, apparent_name: &str) -> Option<&str> {
+        self.mapping.get(apparent_name).map(|x| x.as_str())
+    }
+
+    /// Whether any apparent name maps to `canonical_name`.
+    fn has_canonical_name(&self, canonical_name: &str) -> bool {
+        self.mapping.values().any(|x| x == canonical_name)
+    }
+}
+
+fn top_level_call(stmt: &Stmt) -> Option<(&str, &[AstArgument])> {
+    let expr = match stmt {
+        Stmt::Expression(x) => x,
+        // `ext = use_extension(...)` is an assignment, but is never interesting to us.
+        _ => return None,
+    };
+    match &**expr {
+        Expr::Call(f, args) => match &***f {
+            Expr::Identifier(name, _) => Some((name.node.as_str(), args.as_slice())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn string_literal(x: &AstExpr) -> Option<&str> {
+    match &**x {
+        Expr::Literal(AstLiteral::String(s)) => Some(s.node.as_str()),
+        _ => None,
+    }
+}
+
+fn string_arg<'a>(args: &'a [AstArgument], name: &str) -> Option<&'a str> {
+    args.iter().find_map(|arg| match &arg.node {
+        ArgumentP::Named(arg_name, x) if arg_name.node == name => string_literal(x),
+        _ => None,
+    })
+}
+
+/// A Bazel workspace, using either `MODULE.bazel` or the legacy `WORKSPACE` file.
+///
+/// Running Bazel is slow, so it is only asked about the workspace the first time
+/// an external repository is looked up.
+#[derive(Debug)]
+pub struct Bazel {
+    workspace_root: PathBuf,
+    /// The Bazel executable, `bazel` from the `PATH` by default.
+    bazel: PathBuf,
+    workspace_name: Option<String>,
+    /// The mapping read from `MODULE.bazel`, used if Bazel can't give the real one.
+    /// `None` if there is no `MODULE.bazel`.
+    module_file_mapping: Option<RepositoryMapping>,
+    repo_mapping: OnceCell<RepositoryMapping>,
+    /// Where Bazel keeps its downloaded repositories, usually `<output_base>/external`.
+    external_directory: OnceCell<Option<PathBuf>>,
+}
+
+impl Bazel {
+    /// Create a Bazel workspace rooted at `workspace_root`.
+    ///
+    /// The repository mapping is obtained by asking Bazel, and falls back to
+    /// reading `MODULE.bazel` if that fails. Likewise, the output base holding
+    /// external repositories is obtained from Bazel, falling back to following the
+    /// `bazel-out` convenience symlink. Neither is computed until it is needed.
+    pub fn new(workspace_root: PathBuf) -> Self {
+        let module = fs::read_to_string(workspace_root.join("MODULE.bazel"))
+            .ok()
+            .and_then(|content| AstModule::parse("MODULE.bazel", content, &Dialect::Extended).ok());
+        Self {
+            workspace_root,
+            bazel: PathBuf::from("bazel"),
+            workspace_name: module.as_ref().and_then(module_name),
+            module_file_mapping: module.as_ref().map(RepositoryMapping::from_module_file),
+            repo_mapping: OnceCell::new(),
+            external_directory: OnceCell::new(),
+        }
+    }
+
+    /// Create a Bazel workspace with an explicit repository mapping.
+    pub fn with_repo_mapping(
+        workspace_root: PathBuf,
+        module_file: Option<&AstModule>,
+        repo_mapping: RepositoryMapping,
+    ) -> Self {
+        Self {
+            workspace_root,
+            bazel: PathBuf::from("bazel"),
+            workspace_name: module_file.and_then(module_name),
+            module_file_mapping: None,
+            repo_mapping: OnceCell::with_value(repo_mapping),
+            external_directory: OnceCell::new(),
+        }
+    }
+
+    /// Look for external repositories in the `external` directory of `output_base`,
+    /// as printed by `bazel info output_base`.
+    pub fn with_output_base(self, output_base: &Path) -> Self {
+        Self {
+            external_directory: OnceCell::with_value(Some(output_base.join("external"))),
+            ..self
+        }
+    }
+
+    /// Run `bazel` instead of the `bazel` found on the `PATH`.
+    pub fn with_bazel(self, bazel: impl Into<PathBuf>) -> Self {
+        Self {
+            bazel: bazel.into(),
+            ..self
+        }
+    }
+
+    /// The repository mapping of the main repository.
+    pub fn repo_mapping(&self) -> &RepositoryMapping {
+        self.repo_mapping
+            .get_or_init(|| match &self.module_file_mapping {
+                Some(fallback) => RepositoryMapping::from_bazel(&self.bazel, &self.workspace_root)
+                    .unwrap_or_else(|_| fallback.clone()),
+                None => RepositoryMapping::default(),
+            })
+    }
+
+    fn external_directory(&self) -> Option<&Path> {
+        self.external_directory
+            .get_or_init(|| {
+                let output_base = output_base_from_bazel(&self.bazel, &self.workspace_root)
+                    .ok()
+                    .or_else(|| output_base_from_symlink(&self.workspace_root))?;
+                Some(output_base.join("external"))
+            })
+            .as_deref()
+    }
+
+    /// The directory of a repository. External repositories are only found once
+    /// Bazel has fetched them.
+    fn canonical_repository_path(&self, canonical_name: &str) -> Option<PathBuf> {
+        match canonical_name {
+            "" => Some(self.workspace_root.clone()),
+            _ => {
+                let path = self.external_directory()?.join(canonical_name);
+                path.is_dir().then_some(path)
+            }
+        }
+    }
+}
+
+/// The name a `MODULE.bazel` file gives its module.
+fn module_name(module: &AstModule) -> Option<String> {
+    module
+        .top_level_statements()
+        .into_iter()
+        .find_map(|stmt| match top_level_call(stmt) {
+            Some(("module", args)) => string_arg(args, "name").map(|x| x.to_owned()),
+            _ => None,
+        })
+}
+
+/// Ask Bazel, run as `bazel`, where the output base of the workspace in `workspace_root` is.
+fn output_base_from_bazel(bazel: &Path, workspace_root: &Path) -> anyhow::Result<PathBuf> {
+    let output = Command::new(bazel)
+        .args(["info", "output_base"])
+        .current_dir(workspace_root)
+        .output()?;
+    if !output.status.success() {
+        return Err(BazelError::OutputBase(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )
+        .into());
+    }
+    Ok(PathBuf::from(String::from_utf8(output.stdout)?.trim()))
+}
+
+/// Find the output base through the `bazel-out` symlink Bazel creates in the
+/// workspace, which points to `<output_base>/execroot/<workspace>/bazel-out`.
+fn output_base_from_symlink(workspace_root: &Path) -> Option<PathBuf> {
+    let bazel_out = fs::canonicalize(workspace_root.join("bazel-out")).ok()?;
+    Some(bazel_out.ancestors().nth(3)?.to_owned())
+}
+
+impl BuildSystem for Bazel {
+    fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
+
+    fn workspace_name(&self) -> Option<&str> {
+        self.workspace_name.as_deref()
+    }
+
+    fn repository_path(&self, repository_name: &str) -> Option<PathBuf> {
+        if repository_name.is_empty() || Some(repository_name) == self.workspace_name() {
+            return Some(self.workspace_root.clone());
+        }
+        let repo_mapping = self.repo_mapping();
+        match repo_mapping.canonical_name(repository_name) {
+            Some(canonical) => self.canonical_repository_path(canonical),
+            // Canonical names (from `@@name//` labels) may be used directly, and without
+            // bzlmod there is no mapping, so every name is canonical.
+            None if repo_mapping.mapping.is_empty()
+                || repo_mapping.has_canonical_name(repository_name) =>
+            {
+                self.canonical_repository_path(repository_name)
+            }
+            None => None,
+        }
+    }
+
+    fn build_file_names(&self) -> &[&str] {
+        &["BUILD.bazel", "BUILD"]
+    }
+
+    fn query_buildable_targets(&self, repository: &str, package: &str) -> Option<Vec<String>> {
+        let repository = if repository.is_empty() {
+            String::new()
+        } else {
+            format!("@{}", repository)
+        };
+        let output = Command::new(&self.bazel)
+            .args([
+                "query",
+                "--output=label",
+                &format!("{}//{}:all", repository, package),
+            ])
+            .current_dir(&self.workspace_root)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(parse_query_output(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Extract the target names from the output of `bazel query --output=label`.
+fn parse_query_output(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().rsplit_once(':'))
+        .map(|(_, target)| target.to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODULE_BAZEL: &str = r#"
+module(name = "my_project", version = "1.0", repo_name = "proj")
+
+bazel_dep(name = "rules_cc", version = "0.0.9")
+bazel_dep(name = "platforms", version = "0.0.8", repo_name = "plats")
+
+ext = use_extension("//:ext.bzl", "ext")
+use_repo(ext, "generated", other = "generated_other")
+"#;
+
+    fn module_file() -> AstModule {
+        AstModule::parse("MODULE.bazel", MODULE_BAZEL.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[test]
+    fn test_parse_query_output() {
+        assert_eq!(
+            vec!["lib", "lib_test", "data.txt"],
+            parse_query_output("//pkg:lib\n//pkg:lib_test\n@repo//pkg:data.txt\n\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_dump_repo_mapping() {
+        let mapping = RepositoryMapping::parse_dump_repo_mapping(
+            r#"{"":"","my_project":"","rules_cc":"rules_cc~","plats":"platforms~"}"#,
+        )
+        .unwrap();
+        assert_eq!(Some(""), mapping.canonical_name("my_project"));
+        assert_eq!(Some("rules_cc~"), mapping.canonical_name("rules_cc"));
+        assert_eq!(Some("platforms~"), mapping.canonical_name("plats"));
+        assert_eq!(None, mapping.canonical_name("platforms"));
+
+        assert!(RepositoryMapping::parse_dump_repo_mapping("[]").is_err());
+        assert!(RepositoryMapping::parse_dump_repo_mapping(r#"{"a": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_mapping_from_module_file() {
+        let mapping = RepositoryMapping::from_module_file(&module_file());
+        assert_eq!(Some(""), mapping.canonical_name("my_project"));
+        assert_eq!(Some(""), mapping.canonical_name("proj"));
+        assert_eq!(Some("rules_cc"), mapping.canonical_name("rules_cc"));
+        assert_eq!(Some("platforms"), mapping.canonical_name("plats"));
+        assert_eq!(None, mapping.canonical_name("platforms"));
+        assert_eq!(Some("generated"), mapping.canonical_name("generated"));
+        assert_eq!(Some("generated_other"), mapping.canonical_name("other"));
+    }
+
+    #[test]
+    fn test_repository_path() {
+        let module = module_file();
+        let mapping = RepositoryMapping::from_module_file(&module);
+        // Looking for external repositories must not depend on a Bazel installed on the host.
+        let bazel = Bazel::with_repo_mapping(PathBuf::from("/ws"), Some(&module), mapping)
+            .with_bazel("/nonexistent/bazel");
+        assert_eq!(Some("my_project"), bazel.workspace_name());
+        assert_eq!(Some(PathBuf::from("/ws")), bazel.repository_path(""));
+        assert_eq!(
+            Some(PathBuf::from("/ws")),
+            bazel.repository_path("my_project")
+        );
+        assert_eq!(Some(PathBuf::from("/ws")), bazel.repository_path("proj"));
+        assert_eq!(None, bazel.repository_path("rules_cc"));
+        assert_eq!(None, bazel.repository_path("unknown"));
+    }
+
+    #[test]
+    fn test_new_is_lazy() {
+        let root = std::env::temp_dir().join(format!("starlark-bazel-lazy-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("MODULE.bazel"), MODULE_BAZEL).unwrap();
+
+        let bazel = Bazel::new(root.clone());
+        assert_eq!(Some("my_project"), bazel.workspace_name());
+        assert_eq!(Some(root.clone()), bazel.repository_path("my_project"));
+        // Bazel is only consulted once an external repository is needed.
+        assert!(bazel.repo_mapping.get().is_none());
+        assert!(bazel.external_directory.get().is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_external_repository_path() {
+        let output_base =
+            std::env::temp_dir().join(format!("starlark-bazel-output-base-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_base);
+        fs::create_dir_all(output_base.join("external/rules_cc")).unwrap();
+
+        let module = module_file();
+        let mapping = RepositoryMapping::from_module_file(&module);
+        let bazel = Bazel::with_repo_mapping(PathBuf::from("/ws"), Some(&module), mapping)
+            .with_output_base(&output_base);
+        assert_eq!(
+            Some(output_base.join("external/rules_cc")),
+            bazel.repository_path("rules_cc")
+        );
+        // Known, but not fetched yet.
+        assert_eq!(None, bazel.repository_path("plats"));
+        assert_eq!(None, bazel.repository_path("unknown"));
+
+        // A legacy `WORKSPACE` has no repository mapping.
+        let bazel =
+            Bazel::with_repo_mapping(PathBuf::from("/ws"), None, RepositoryMapping::default())
+                .with_output_base(&output_base);
+        assert_eq!(
+            Some(output_base.join("external/rules_cc")),
+            bazel.repository_path("rules_cc")
+        );
+
+        fs::remove_dir_all(&output_base).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_base_from_bazel() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("starlark-bazel-fake-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let output_base = root.join("output_base");
+        fs::create_dir_all(output_base.join("external/rules_cc")).unwrap();
+        // A fake `bazel` which only knows `bazel info output_base`.
+        let fake = root.join("bazel");
+        fs::write(
+            &fake,
+            format!(
+                "#!/bin/sh\n[ \"$1 $2\" = \"info output_base\" ] && echo '{}'\n",
+                output_base.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&fake, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let module = module_file();
+        let mapping = RepositoryMapping::from_module_file(&module);
+        let bazel =
+            Bazel::with_repo_mapping(root.clone(), Some(&module), mapping).with_bazel(&fake);
+        assert_eq!(
+            Some(output_base.join("external/rules_cc")),
+            bazel.repository_path("rules_cc")
+        );
+        assert_eq!(None, bazel.query_buildable_targets("", "pkg"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
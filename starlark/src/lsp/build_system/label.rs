@@ -0,0 +1,278 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Labels, the `@repo//pkg:target` strings build systems use to refer to files and targets.
+
+use std::fmt;
+use std::fmt::Display;
+
+#[derive(thiserror::Error, Debug)]
+enum LabelError {
+    #[error("`{0}` is not a label, labels start with `@`, `//` or `:`")]
+    NotALabel(String),
+    #[error("Invalid repository name in label `{0}`")]
+    InvalidRepository(String),
+    #[error("Invalid package name in label `{0}`")]
+    InvalidPackage(String),
+    #[error("Missing target name in label `{0}`")]
+    EmptyTarget(String),
+}
+
+/// A parsed label, such as `@repo//pkg/path:target`.
+///
+/// Shorthands are expanded when parsing, so `//pkg/path` has the target `path`,
+/// and `@repo` is `@repo//:repo`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+    /// The `repo` in `@repo//pkg:target`, or `None` if the label refers to the
+    /// repository it is used in. The main repository can be named explicitly as `@//`,
+    /// in which case this is the empty string.
+    pub repository: Option<String>,
+    /// Whether the repository was given by its canonical name, as in `@@repo//pkg:target`.
+    pub canonical_repository: bool,
+    /// The `pkg` in `@repo//pkg:target`, or `None` for labels relative to the
+    /// current package, such as `:target`.
+    pub package: Option<String>,
+    /// The `target` in `@repo//pkg:target`. May contain `/`, e.g. for files in
+    /// subdirectories of the package.
+    pub target: String,
+}
+
+impl Label {
+    /// Parse a label. Only labels starting with `@`, `//` or `:` are accepted,
+    /// as anything else is ambiguous with a path.
+    pub fn parse(label: &str) -> anyhow::Result<Self> {
+        let (repository, canonical_repository, rest) = match label.strip_prefix('@') {
+            Some(rest) => {
+                let (canonical, rest) = match rest.strip_prefix('@') {
+                    Some(rest) => (true, rest),
+                    None => (false, rest),
+                };
+                let (name, rest) = match rest.split_once("//") {
+                    Some((name, rest)) => (name, Some(rest)),
+                    None => (rest, None),
+                };
+                if !Self::is_valid_repository(name) || (name.is_empty() && rest.is_none()) {
+                    return Err(LabelError::InvalidRepository(label.to_owned()).into());
+                }
+                let rest = match rest {
+                    Some(rest) => rest,
+                    // `@repo` is short for `@repo//:repo`.
+                    None => {
+                        return Ok(Self {
+                            repository: Some(name.to_owned()),
+                            canonical_repository: canonical,
+                            package: Some(String::new()),
+                            target: name.to_owned(),
+                        });
+                    }
+                };
+                (Some(name.to_owned()), canonical, rest)
+            }
+            None => match label.strip_prefix("//") {
+                Some(rest) => (None, false, rest),
+                None => match label.strip_prefix(':') {
+                    Some(target) => {
+                        return Ok(Self {
+                            repository: None,
+                            canonical_repository: false,
+                            package: None,
+                            target: Self::check_target(label, target)?,
+                        });
+                    }
+                    None => return Err(LabelError::NotALabel(label.to_owned()).into()),
+                },
+            },
+        };
+
+        let (package, target) = match rest.split_once(':') {
+            Some(x) => x,
+            // `//pkg/path` is short for `//pkg/path:path`.
+            None => (rest, rest.rsplit('/').next().unwrap_or(rest)),
+        };
+        if !Self::is_valid_package(package) {
+            return Err(LabelError::InvalidPackage(label.to_owned()).into());
+        }
+        Ok(Self {
+            repository,
+            canonical_repository,
+            package: Some(package.to_owned()),
+            target: Self::check_target(label, target)?,
+        })
+    }
+
+    fn is_valid_repository(name: &str) -> bool {
+        name.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-~+".contains(c))
+    }
+
+    fn is_valid_package(package: &str) -> bool {
+        package.is_empty()
+            || (!package.starts_with('/') && !package.ends_with('/') && !package.contains("//"))
+    }
+
+    fn check_target(label: &str, target: &str) -> anyhow::Result<String> {
+        if target.is_empty() || target.contains(':') {
+            return Err(LabelError::EmptyTarget(label.to_owned()).into());
+        }
+        Ok(target.to_owned())
+    }
+
+    /// Whether this label is relative to the package it is used in.
+    pub fn is_relative(&self) -> bool {
+        self.package.is_none()
+    }
+
+    /// Make this label independent of the package it was used in, `package`
+    /// in `repository` (`None` for the current repository).
+    pub fn normalize(&self, repository: Option<&str>, package: &str) -> Self {
+        let mut res = self.clone();
+        if res.repository.is_none() {
+            res.repository = repository.map(|x| x.to_owned());
+        }
+        if res.package.is_none() {
+            res.package = Some(package.to_owned());
+        }
+        res
+    }
+
+    /// Render the label in its shortest form, omitting the target if it's implied by
+    /// the package, e.g. `//pkg/path` instead of `//pkg/path:path`.
+    pub fn render_short(&self) -> String {
+        match &self.package {
+            Some(package)
+                if !package.is_empty()
+                    && package.rsplit('/').next() == Some(self.target.as_str()) =>
+            {
+                format!("{}//{}", self.render_repository(), package)
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    fn render_repository(&self) -> String {
+        match &self.repository {
+            None => String::new(),
+            Some(name) if self.canonical_repository => format!("@@{}", name),
+            Some(name) => format!("@{}", name),
+        }
+    }
+}
+
+impl Display for Label {
+    /// The label in full, e.g. `@repo//pkg/path:path`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.package {
+            Some(package) => write!(
+                f,
+                "{}//{}:{}",
+                self.render_repository(),
+                package,
+                self.target
+            ),
+            None => write!(f, ":{}", self.target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(repository: Option<&str>, package: Option<&str>, target: &str) -> Label {
+        Label {
+            repository: repository.map(|x| x.to_owned()),
+            canonical_repository: false,
+            package: package.map(|x| x.to_owned()),
+            target: target.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let parse = |x| Label::parse(x).unwrap();
+        assert_eq!(
+            label(Some("repo"), Some("pkg/path"), "file.bzl"),
+            parse("@repo//pkg/path:file.bzl")
+        );
+        assert_eq!(label(None, Some("pkg/path"), "path"), parse("//pkg/path"));
+        assert_eq!(label(None, Some(""), "target"), parse("//:target"));
+        assert_eq!(label(Some(""), Some("pkg"), "x"), parse("@//pkg:x"));
+        assert_eq!(label(Some("repo"), Some(""), "repo"), parse("@repo"));
+        assert_eq!(label(None, None, "sub/file.bzl"), parse(":sub/file.bzl"));
+        assert_eq!(
+            Label {
+                canonical_repository: true,
+                ..label(Some("rules_cc~"), Some("cc"), "defs.bzl")
+            },
+            parse("@@rules_cc~//cc:defs.bzl")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for x in [
+            "pkg:target",
+            "@",
+            "@re po//pkg",
+            "//pkg/:x",
+            "///pkg:x",
+            "//",
+            "//pkg:",
+            ":",
+            "//pkg:a:b",
+        ] {
+            assert!(Label::parse(x).is_err(), "{}", x);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        for (input, full, short) in [
+            (
+                "@repo//pkg:file.bzl",
+                "@repo//pkg:file.bzl",
+                "@repo//pkg:file.bzl",
+            ),
+            ("//pkg/path", "//pkg/path:path", "//pkg/path"),
+            ("@@repo~//pkg:pkg", "@@repo~//pkg:pkg", "@@repo~//pkg"),
+            ("@repo", "@repo//:repo", "@repo//:repo"),
+            (":target", ":target", ":target"),
+        ] {
+            let label = Label::parse(input).unwrap();
+            assert_eq!(full, label.to_string());
+            assert_eq!(short, label.render_short());
+            assert_eq!(label, Label::parse(&label.render_short()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let relative = Label::parse(":file.bzl").unwrap();
+        assert!(relative.is_relative());
+        let normalized = relative.normalize(Some("repo"), "pkg");
+        assert!(!normalized.is_relative());
+        assert_eq!("@repo//pkg:file.bzl", normalized.to_string());
+        assert_eq!(
+            "//other:x",
+            Label::parse("//other:x")
+                .unwrap()
+                .normalize(None, "pkg")
+                .to_string()
+        );
+    }
+}
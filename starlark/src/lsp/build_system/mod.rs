@@ -0,0 +1,220 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Knowledge about the build system a workspace uses, so that paths written as
+//! labels (e.g. `load("@repo//pkg:file.bzl", ...)`) can be mapped to files on disk.
+
+use std::fmt::Debug;
+use std::path::Path;
+use std::path::PathBuf;
+
+pub use crate::lsp::build_system::bazel::Bazel;
+pub use crate::lsp::build_system::bazel::RepositoryMapping;
+pub use crate::lsp::build_system::label::Label;
+pub use crate::lsp::build_system::registry::BuildSystemConfig;
+pub use crate::lsp::build_system::registry::BuildSystemRegistry;
+pub use crate::lsp::build_system::target_cache::TargetQueryCache;
+pub use crate::lsp::build_system::target_cache::TargetQuerySnapshot;
+
+mod bazel;
+mod label;
+mod registry;
+mod target_cache;
+
+/// A build system that owns a workspace on disk.
+pub trait BuildSystem: Debug + Send + Sync {
+    /// The root directory of the main repository.
+    fn workspace_root(&self) -> &Path;
+
+    /// The name the main repository declares for itself, if any.
+    fn workspace_name(&self) -> Option<&str>;
+
+    /// The directory holding the repository the user refers to as `repository_name`,
+    /// i.e. the `foo` in `@foo//bar:baz.bzl`. Returns `None` if the repository is unknown.
+    fn repository_path(&self, repository_name: &str) -> Option<PathBuf>;
+
+    /// Names of the files that define the targets of a package, in order of preference.
+    fn build_file_names(&self) -> &[&str] {
+        &["BUILD", "BUCK"]
+    }
+
+    /// The directory of the package `path` belongs to, i.e. the nearest directory
+    /// containing a build file, starting with `path` itself if it is a directory.
+    /// `path` doesn't need to exist, so this also works for targets that aren't files.
+    fn package_directory(&self, path: &Path) -> Option<PathBuf> {
+        let skip = if path.is_dir() { 0 } else { 1 };
+        path.ancestors()
+            .skip(skip)
+            .find(|dir| {
+                self.build_file_names()
+                    .iter()
+                    .any(|name| dir.join(name).is_file())
+            })
+            .map(|dir| dir.to_owned())
+    }
+
+    /// The package of the main repository that `path` belongs to, i.e. the one whose
+    /// [`package_directory`](BuildSystem::package_directory) it is in. Returns `None` if
+    /// `path` is not in a package of the main repository.
+    fn package_for_path(&self, path: &Path) -> Option<Package> {
+        let directory = self.package_directory(path)?;
+        let name = path_to_label_part(directory.strip_prefix(self.workspace_root()).ok()?)?;
+        Some(Package { name, directory })
+    }
+
+    /// The label to `load` the file `target` by from `current_file`: relative to the
+    /// package, as in `:file.bzl`, if both files are in the same package, and relative
+    /// to the main repository otherwise. Returns `None` if `target` is not in a package
+    /// of the main repository.
+    fn render_as_load(&self, target: &Path, current_file: &Path) -> Option<String> {
+        let package = self.package_for_path(target)?;
+        let name = path_to_label_part(target.strip_prefix(&package.directory).ok()?)?;
+        if self.package_for_path(current_file).as_ref() == Some(&package) {
+            return Some(format!(":{}", name));
+        }
+        Some(format!("//{}:{}", package.name, name))
+    }
+
+    /// The names of the targets defined in `package` of `repository`, i.e. the
+    /// `baz` in `@repository//package:baz`. Returns `None` if the targets can't be listed.
+    ///
+    /// This may run the build tool and take a while, see [`TargetQueryCache`] for a
+    /// cached and non-blocking way to call it.
+    fn query_buildable_targets(&self, repository: &str, package: &str) -> Option<Vec<String>> {
+        let _ = (repository, package);
+        None
+    }
+}
+
+/// A package of the main repository, see [`BuildSystem::package_for_path`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Package {
+    /// The name of the package, i.e. its path from the root of the repository as written
+    /// in labels, like `foo/bar` in `//foo/bar:baz`. Empty for the root package.
+    pub name: String,
+    /// The directory containing the package's build file.
+    pub directory: PathBuf,
+}
+
+/// Find the build system for the workspace containing `path`, by looking for
+/// marker files in `path` and its parents. Only the built-in build systems are
+/// considered, use [`BuildSystemRegistry`] to add others.
+pub fn try_resolve_build_system(path: &Path) -> Option<Box<dyn BuildSystem>> {
+    BuildSystemRegistry::builtin().resolve(path)
+}
+
+/// Join the components of a relative path with `/`, as labels do on all platforms.
+fn path_to_label_part(path: &Path) -> Option<String> {
+    let parts = path
+        .components()
+        .map(|x| x.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct TestBuildSystem(PathBuf);
+
+    impl BuildSystem for TestBuildSystem {
+        fn workspace_root(&self) -> &Path {
+            &self.0
+        }
+
+        fn workspace_name(&self) -> Option<&str> {
+            None
+        }
+
+        fn repository_path(&self, _repository_name: &str) -> Option<PathBuf> {
+            Some(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn test_package_boundaries() {
+        let root = std::env::temp_dir().join(format!(
+            "starlark-build-system-packages-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("pkg/sub/nested")).unwrap();
+        fs::write(root.join("BUILD"), "").unwrap();
+        fs::write(root.join("pkg/BUCK"), "").unwrap();
+        fs::write(root.join("pkg/sub/nested/BUILD"), "").unwrap();
+        let build_system = TestBuildSystem(root.clone());
+
+        assert_eq!(
+            Some(root.join("pkg")),
+            build_system.package_directory(&root.join("pkg/sub/defs.bzl"))
+        );
+        assert_eq!(
+            Some(root.join("pkg/sub/nested")),
+            build_system.package_directory(&root.join("pkg/sub/nested"))
+        );
+        assert_eq!(
+            Some(root.clone()),
+            build_system.package_directory(&root.join("top.bzl"))
+        );
+
+        assert_eq!(
+            Some(Package {
+                name: "pkg/sub/nested".to_owned(),
+                directory: root.join("pkg/sub/nested"),
+            }),
+            build_system.package_for_path(&root.join("pkg/sub/nested/x/y.bzl"))
+        );
+        assert_eq!(
+            Some(Package {
+                name: String::new(),
+                directory: root.clone(),
+            }),
+            build_system.package_for_path(&root.join("top.bzl"))
+        );
+        assert_eq!(
+            None,
+            build_system.package_for_path(&std::env::temp_dir().join("outside.bzl"))
+        );
+
+        let render = |target: &str, current: &str| {
+            build_system.render_as_load(&root.join(target), &root.join(current))
+        };
+        assert_eq!(
+            Some(":sub/defs.bzl".to_owned()),
+            render("pkg/sub/defs.bzl", "pkg/BUCK")
+        );
+        // The directory of the current file doesn't make it the same package.
+        assert_eq!(
+            Some("//pkg:sub/defs.bzl".to_owned()),
+            render("pkg/sub/defs.bzl", "pkg/sub/nested/BUILD")
+        );
+        assert_eq!(Some("//:top.bzl".to_owned()), render("top.bzl", "pkg/BUCK"));
+        assert_eq!(
+            None,
+            build_system.render_as_load(
+                &std::env::temp_dir().join("outside.bzl"),
+                &root.join("pkg/BUCK")
+            )
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,270 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A registry of build systems, so that embedders can add their own alongside the built-in ones.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Debug;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use dupe::Dupe;
+use serde::Deserialize;
+
+use crate::lsp::build_system::bazel::WORKSPACE_MARKERS;
+use crate::lsp::build_system::Bazel;
+use crate::lsp::build_system::BuildSystem;
+
+/// Creates a build system for the workspace rooted at the given directory,
+/// or returns `None` if the directory turns out not to be usable.
+type Factory = Box<dyn Fn(&Path) -> Option<Box<dyn BuildSystem>> + Send + Sync>;
+
+struct Registration {
+    name: String,
+    markers: Vec<String>,
+    factory: Factory,
+}
+
+/// An ordered collection of build systems, each recognized by marker files
+/// in the root of the workspace it owns.
+///
+/// Resolution walks from a path up to the filesystem root and picks the first
+/// directory containing a marker of any registered build system, so the innermost
+/// workspace wins. If several build systems match the same directory, the one
+/// registered last is used, which lets custom registrations override the built-in ones.
+#[derive(Default)]
+pub struct BuildSystemRegistry {
+    registrations: Vec<Registration>,
+}
+
+impl Debug for BuildSystemRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.registrations.iter().map(|r| (&r.name, &r.markers)))
+            .finish()
+    }
+}
+
+impl BuildSystemRegistry {
+    /// A registry without any build systems.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with the build systems supported out of the box.
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register("bazel", WORKSPACE_MARKERS, |root| {
+            Some(Box::new(Bazel::new(root.to_owned())))
+        });
+        registry
+    }
+
+    /// Register a build system that owns any directory containing one of `markers`.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        markers: &[&str],
+        factory: impl Fn(&Path) -> Option<Box<dyn BuildSystem>> + Send + Sync + 'static,
+    ) {
+        self.registrations.push(Registration {
+            name: name.into(),
+            markers: markers.iter().map(|x| (*x).to_owned()).collect(),
+            factory: Box::new(factory),
+        });
+    }
+
+    /// Register a build system described by configuration rather than code.
+    pub fn register_config(&mut self, config: BuildSystemConfig) {
+        let config = Arc::new(config);
+        self.registrations.push(Registration {
+            name: config.name.clone(),
+            markers: config.markers.clone(),
+            factory: Box::new(move |root| {
+                Some(Box::new(ConfiguredBuildSystem {
+                    config: config.dupe(),
+                    workspace_root: root.to_owned(),
+                }))
+            }),
+        });
+    }
+
+    /// The names of the registered build systems, in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.registrations.iter().map(|r| r.name.as_str())
+    }
+
+    /// Find the build system for the workspace containing `path`.
+    pub fn resolve(&self, path: &Path) -> Option<Box<dyn BuildSystem>> {
+        path.ancestors().find_map(|dir| {
+            self.registrations
+                .iter()
+                .rev()
+                .filter(|r| r.markers.iter().any(|m| dir.join(m).exists()))
+                .find_map(|r| (r.factory)(dir))
+        })
+    }
+}
+
+/// A build system described by configuration, typically loaded from a JSON file:
+///
+/// ```json
+/// {
+///     "name": "pants",
+///     "markers": ["pants.toml"],
+///     "workspace_name": "monorepo",
+///     "repositories": {"third_party": "3rdparty/python"}
+/// }
+/// ```
+///
+/// Relative repository paths are resolved against the workspace root.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BuildSystemConfig {
+    /// Name of the build system, for diagnostics.
+    pub name: String,
+    /// Files or directories whose presence marks the workspace root.
+    pub markers: Vec<String>,
+    /// The name the main repository uses for itself, if any.
+    #[serde(default)]
+    pub workspace_name: Option<String>,
+    /// Paths of the repositories that can be referred to as `@name//`.
+    #[serde(default)]
+    pub repositories: HashMap<String, PathBuf>,
+}
+
+#[derive(Debug)]
+struct ConfiguredBuildSystem {
+    config: Arc<BuildSystemConfig>,
+    workspace_root: PathBuf,
+}
+
+impl BuildSystem for ConfiguredBuildSystem {
+    fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
+
+    fn workspace_name(&self) -> Option<&str> {
+        self.config.workspace_name.as_deref()
+    }
+
+    fn repository_path(&self, repository_name: &str) -> Option<PathBuf> {
+        if repository_name.is_empty() || Some(repository_name) == self.workspace_name() {
+            return Some(self.workspace_root.clone());
+        }
+        self.config
+            .repositories
+            .get(repository_name)
+            .map(|path| self.workspace_root.join(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// A fresh directory tree for one test: `root/sub/leaf`, all empty.
+    fn temp_tree(test: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "starlark-build-system-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub/leaf")).unwrap();
+        root
+    }
+
+    fn config(name: &str, marker: &str) -> BuildSystemConfig {
+        BuildSystemConfig {
+            name: name.to_owned(),
+            markers: vec![marker.to_owned()],
+            workspace_name: None,
+            repositories: HashMap::from([("lib".to_owned(), PathBuf::from("third_party/lib"))]),
+        }
+    }
+
+    #[test]
+    fn test_resolve_innermost_workspace() {
+        let root = temp_tree("innermost");
+        fs::write(root.join("outer.toml"), "").unwrap();
+        fs::write(root.join("sub/inner.toml"), "").unwrap();
+
+        let mut registry = BuildSystemRegistry::new();
+        registry.register_config(config("outer", "outer.toml"));
+        registry.register_config(config("inner", "inner.toml"));
+
+        let resolved = registry.resolve(&root.join("sub/leaf")).unwrap();
+        assert_eq!(root.join("sub"), resolved.workspace_root());
+        assert_eq!(
+            Some(root.join("sub/third_party/lib")),
+            resolved.repository_path("lib")
+        );
+        assert_eq!(None, resolved.repository_path("unknown"));
+
+        let resolved = registry.resolve(&root).unwrap();
+        assert_eq!(root, resolved.workspace_root());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_later_registration_wins() {
+        let root = temp_tree("override");
+        fs::write(root.join("WORKSPACE"), "").unwrap();
+
+        let mut registry = BuildSystemRegistry::builtin();
+        registry.register("custom", &["WORKSPACE"], |root| {
+            Some(Box::new(ConfiguredBuildSystem {
+                config: Arc::new(BuildSystemConfig {
+                    workspace_name: Some("custom".to_owned()),
+                    ..config("custom", "WORKSPACE")
+                }),
+                workspace_root: root.to_owned(),
+            }))
+        });
+        assert_eq!(
+            vec!["bazel", "custom"],
+            registry.names().collect::<Vec<_>>()
+        );
+
+        let resolved = registry.resolve(&root.join("sub")).unwrap();
+        assert_eq!(Some("custom"), resolved.workspace_name());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let configs: Vec<BuildSystemConfig> = serde_json::from_str(
+            r#"[{"name": "pants", "markers": ["pants.toml"], "repositories": {"x": "y"}}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![BuildSystemConfig {
+                name: "pants".to_owned(),
+                markers: vec!["pants.toml".to_owned()],
+                workspace_name: None,
+                repositories: HashMap::from([("x".to_owned(), PathBuf::from("y"))]),
+            }],
+            configs
+        );
+    }
+}
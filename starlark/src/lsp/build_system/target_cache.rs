@@ -0,0 +1,357 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Caching of [`BuildSystem::query_buildable_targets`], which usually runs the build
+//! tool and is far too slow to call on every request from the editor.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use dupe::Dupe;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::lsp::build_system::BuildSystem;
+
+/// `(repository, package)`
+type PackageKey = (String, String);
+
+struct Entry {
+    /// `None` if the query failed, which is cached too, so a broken package
+    /// doesn't run the build tool on every request.
+    targets: Option<Vec<String>>,
+    /// `None` if the entry was restored from a [`TargetQuerySnapshot`], so it is
+    /// only used until it can be refreshed.
+    fetched: Option<Instant>,
+    /// Modification time of the package's build file when the query started.
+    build_file_modified: Option<SystemTime>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<PackageKey, Entry>,
+    /// Packages with a query running in the background.
+    refreshing: HashSet<PackageKey>,
+}
+
+/// The entries of a [`TargetQueryCache`], which can be serialized to keep them between
+/// runs, see [`TargetQueryCache::snapshot`].
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TargetQuerySnapshot {
+    entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotEntry {
+    repository: String,
+    package: String,
+    targets: Option<Vec<String>>,
+    build_file_modified: Option<SystemTime>,
+}
+
+/// Cache of the targets in each package, as returned by
+/// [`BuildSystem::query_buildable_targets`].
+///
+/// An entry is reused until it is older than the time to live, or the package's
+/// build file has been modified since it was fetched. Edits that the modification
+/// time can't see (e.g. to a macro in a `.bzl` file) can be reported with
+/// [`invalidate_file`](TargetQueryCache::invalidate_file).
+///
+/// [`get`](TargetQueryCache::get) waits for the query, while
+/// [`get_nonblocking`](TargetQueryCache::get_nonblocking) answers from the cache
+/// immediately and refreshes it on a background thread, which is what a language
+/// server handling requests on a single thread wants.
+#[derive(Clone, Dupe)]
+pub struct TargetQueryCache {
+    build_system: Arc<dyn BuildSystem>,
+    ttl: Duration,
+    state: Arc<Mutex<State>>,
+}
+
+impl Debug for TargetQueryCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TargetQueryCache")
+            .field("build_system", &self.build_system)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TargetQueryCache {
+    /// The default time to live of cached entries.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(5 * 60);
+
+    /// Create a cache for the given build system, with the default time to live.
+    pub fn new(build_system: Arc<dyn BuildSystem>) -> Self {
+        Self::with_ttl(build_system, Self::DEFAULT_TTL)
+    }
+
+    /// Create a cache whose entries are refreshed after `ttl`.
+    pub fn with_ttl(build_system: Arc<dyn BuildSystem>, ttl: Duration) -> Self {
+        Self {
+            build_system,
+            ttl,
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// The build system whose targets are cached.
+    pub fn build_system(&self) -> &dyn BuildSystem {
+        &*self.build_system
+    }
+
+    /// The targets in `package` of `repository`, running the query on this thread
+    /// if the cached entry is missing or stale.
+    pub fn get(&self, repository: &str, package: &str) -> Option<Vec<String>> {
+        let key = (repository.to_owned(), package.to_owned());
+        let modified = self.build_file_modified(&key);
+        if let Some(entry) = self.state.lock().unwrap().entries.get(&key) {
+            if self.is_fresh(entry, modified) {
+                return entry.targets.clone();
+            }
+        }
+        self.refresh(key, modified)
+    }
+
+    /// The cached targets in `package` of `repository`, possibly stale, without waiting
+    /// for the build system. If the entry is missing or stale, a query is started in
+    /// the background, and its result is returned by later calls.
+    pub fn get_nonblocking(&self, repository: &str, package: &str) -> Option<Vec<String>> {
+        let key = (repository.to_owned(), package.to_owned());
+        let modified = self.build_file_modified(&key);
+        let mut state = self.state.lock().unwrap();
+        let (targets, fresh) = match state.entries.get(&key) {
+            Some(entry) => (entry.targets.clone(), self.is_fresh(entry, modified)),
+            None => (None, false),
+        };
+        if !fresh && state.refreshing.insert(key.clone()) {
+            let this = self.dupe();
+            thread::spawn(move || this.refresh(key, modified));
+        }
+        targets
+    }
+
+    /// Tell the cache that the file at `path` changed. A changed build file invalidates
+    /// its package. Any other Starlark file may define macros used by any package, so
+    /// invalidates everything.
+    pub fn invalidate_file(&self, path: &Path) {
+        let file_name = path.file_name().and_then(|x| x.to_str()).unwrap_or("");
+        let mut state = self.state.lock().unwrap();
+        if self.build_system.build_file_names().contains(&file_name) {
+            let dir = path.parent();
+            state
+                .entries
+                .retain(|key, _| self.package_dir(key).as_deref() != dir);
+        } else if path.extension().map_or(false, |x| x == "bzl") {
+            state.entries.clear();
+        }
+    }
+
+    /// The cached entries, e.g. to save them when the program exits.
+    pub fn snapshot(&self) -> TargetQuerySnapshot {
+        let state = self.state.lock().unwrap();
+        TargetQuerySnapshot {
+            entries: state
+                .entries
+                .iter()
+                .map(|((repository, package), entry)| SnapshotEntry {
+                    repository: repository.clone(),
+                    package: package.clone(),
+                    targets: entry.targets.clone(),
+                    build_file_modified: entry.build_file_modified,
+                })
+                .collect(),
+        }
+    }
+
+    /// Add the entries of a [`snapshot`](TargetQueryCache::snapshot), e.g. one saved by a
+    /// previous run. They are stale, so [`get`](TargetQueryCache::get) queries the build
+    /// system again, but [`get_nonblocking`](TargetQueryCache::get_nonblocking) can answer
+    /// with them while it does. Entries already in the cache are kept.
+    pub fn restore(&self, snapshot: TargetQuerySnapshot) {
+        let mut state = self.state.lock().unwrap();
+        for x in snapshot.entries {
+            state
+                .entries
+                .entry((x.repository, x.package))
+                .or_insert(Entry {
+                    targets: x.targets,
+                    fetched: None,
+                    build_file_modified: x.build_file_modified,
+                });
+        }
+    }
+
+    /// Forget all cached entries.
+    pub fn clear(&self) {
+        self.state.lock().unwrap().entries.clear();
+    }
+
+    fn is_fresh(&self, entry: &Entry, build_file_modified: Option<SystemTime>) -> bool {
+        entry.fetched.map_or(false, |x| x.elapsed() < self.ttl)
+            && entry.build_file_modified == build_file_modified
+    }
+
+    fn refresh(
+        &self,
+        key: PackageKey,
+        build_file_modified: Option<SystemTime>,
+    ) -> Option<Vec<String>> {
+        let fetched = Instant::now();
+        let targets = self.build_system.query_buildable_targets(&key.0, &key.1);
+        let mut state = self.state.lock().unwrap();
+        state.refreshing.remove(&key);
+        state.entries.insert(
+            key,
+            Entry {
+                targets: targets.clone(),
+                fetched: Some(fetched),
+                build_file_modified,
+            },
+        );
+        targets
+    }
+
+    fn package_dir(&self, (repository, package): &PackageKey) -> Option<PathBuf> {
+        Some(self.build_system.repository_path(repository)?.join(package))
+    }
+
+    fn build_file_modified(&self, key: &PackageKey) -> Option<SystemTime> {
+        let dir = self.package_dir(key)?;
+        self.build_system
+            .build_file_names()
+            .iter()
+            .find_map(|name| fs::metadata(dir.join(name)).ok())
+            .and_then(|metadata| metadata.modified().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    /// A build system whose packages each contain one target named after the
+    /// number of queries made so far.
+    #[derive(Debug, Default)]
+    struct CountingBuildSystem {
+        queries: AtomicUsize,
+    }
+
+    impl BuildSystem for CountingBuildSystem {
+        fn workspace_root(&self) -> &Path {
+            Path::new("/workspace")
+        }
+
+        fn workspace_name(&self) -> Option<&str> {
+            None
+        }
+
+        fn repository_path(&self, _repository_name: &str) -> Option<PathBuf> {
+            Some(PathBuf::from("/workspace"))
+        }
+
+        fn query_buildable_targets(&self, _repository: &str, package: &str) -> Option<Vec<String>> {
+            let n = self.queries.fetch_add(1, Ordering::SeqCst);
+            Some(vec![format!("{}_{}", package, n)])
+        }
+    }
+
+    fn cache(ttl: Duration) -> (Arc<CountingBuildSystem>, TargetQueryCache) {
+        let build_system = Arc::new(CountingBuildSystem::default());
+        let cache = TargetQueryCache::with_ttl(build_system.dupe(), ttl);
+        (build_system, cache)
+    }
+
+    #[test]
+    fn test_cached_per_package() {
+        let (build_system, cache) = cache(TargetQueryCache::DEFAULT_TTL);
+        assert_eq!(Some(vec!["a_0".to_owned()]), cache.get("", "a"));
+        assert_eq!(Some(vec!["a_0".to_owned()]), cache.get("", "a"));
+        assert_eq!(Some(vec!["b_1".to_owned()]), cache.get("", "b"));
+        assert_eq!(2, build_system.queries.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_ttl() {
+        let (_, cache) = cache(Duration::ZERO);
+        assert_eq!(Some(vec!["a_0".to_owned()]), cache.get("", "a"));
+        assert_eq!(Some(vec!["a_1".to_owned()]), cache.get("", "a"));
+    }
+
+    #[test]
+    fn test_invalidate_file() {
+        let (_, cache) = cache(TargetQueryCache::DEFAULT_TTL);
+        cache.get("", "a");
+        cache.get("", "b");
+        cache.invalidate_file(Path::new("/workspace/a/BUILD"));
+        assert_eq!(Some(vec!["a_2".to_owned()]), cache.get("", "a"));
+        assert_eq!(Some(vec!["b_1".to_owned()]), cache.get("", "b"));
+        cache.invalidate_file(Path::new("/workspace/a/other.txt"));
+        assert_eq!(Some(vec!["b_1".to_owned()]), cache.get("", "b"));
+        cache.invalidate_file(Path::new("/workspace/macros.bzl"));
+        assert_eq!(Some(vec!["b_3".to_owned()]), cache.get("", "b"));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let (_, cache) = cache(TargetQueryCache::DEFAULT_TTL);
+        cache.get("", "a");
+        let snapshot = serde_json::to_string(&cache.snapshot()).unwrap();
+
+        let build_system = Arc::new(CountingBuildSystem {
+            queries: AtomicUsize::new(10),
+        });
+        let restored = TargetQueryCache::new(build_system.dupe());
+        restored.restore(serde_json::from_str(&snapshot).unwrap());
+        // Answered from the snapshot straight away, but refreshed before being relied on.
+        assert_eq!(
+            Some(vec!["a_0".to_owned()]),
+            restored.get_nonblocking("", "a")
+        );
+        assert_ne!(Some(vec!["a_0".to_owned()]), restored.get("", "a"));
+        assert_eq!(None, restored.get_nonblocking("", "b"));
+    }
+
+    #[test]
+    fn test_nonblocking() {
+        let (_, cache) = cache(TargetQueryCache::DEFAULT_TTL);
+        assert_eq!(None, cache.get_nonblocking("", "a"));
+        let start = Instant::now();
+        loop {
+            if let Some(targets) = cache.get_nonblocking("", "a") {
+                assert_eq!(vec!["a_0".to_owned()], targets);
+                break;
+            }
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
@@ -19,10 +19,14 @@
 
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::fs;
+use std::iter;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
 
 use derivative::Derivative;
 use derive_more::Display;
@@ -41,6 +45,9 @@ use lsp_types::notification::DidOpenTextDocument;
 use lsp_types::notification::LogMessage;
 use lsp_types::notification::PublishDiagnostics;
 use lsp_types::request::GotoDefinition;
+use lsp_types::request::Request as _;
+use lsp_types::request::SelectionRangeRequest;
+use lsp_types::request::Shutdown;
 use lsp_types::DefinitionOptions;
 use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
@@ -55,6 +62,9 @@ use lsp_types::MessageType;
 use lsp_types::OneOf;
 use lsp_types::PublishDiagnosticsParams;
 use lsp_types::Range;
+use lsp_types::SelectionRange;
+use lsp_types::SelectionRangeParams;
+use lsp_types::SelectionRangeProviderCapability;
 use lsp_types::ServerCapabilities;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
@@ -253,6 +263,22 @@ pub trait LspContext {
     /// Parse a file with the given contents. The filename is used in the diagnostics.
     fn parse_file_with_contents(&self, uri: &LspUrl, content: String) -> LspEvalResult;
 
+    /// Like [`parse_file_with_contents`](LspContext::parse_file_with_contents), but passing
+    /// each diagnostic to `on_diagnostic` as soon as it is known, so the server can show
+    /// partial diagnostics while a slow check is still running. Returns the parsed module.
+    ///
+    /// The default implementation reports all the diagnostics once parsing finishes.
+    fn parse_file_with_contents_streaming(
+        &self,
+        uri: &LspUrl,
+        content: String,
+        on_diagnostic: &mut dyn FnMut(Diagnostic),
+    ) -> Option<AstModule> {
+        let result = self.parse_file_with_contents(uri, content);
+        result.diagnostics.into_iter().for_each(on_diagnostic);
+        result.ast
+    }
+
     /// Resolve a path given in a `load()` statement.
     ///
     /// `path` is the string representation in the `load()` statement. Its meaning is
@@ -293,6 +319,34 @@ pub trait LspContext {
         current_file: &LspUrl,
         symbol: &str,
     ) -> anyhow::Result<Option<LspUrl>>;
+
+    /// The file to keep the state returned by [`save_state`](LspContext::save_state) in
+    /// between runs of the server. Nothing is kept if this returns `None`, the default.
+    fn state_file(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// State that doesn't depend on the open documents and is slow to rebuild, such as
+    /// caches of the workspace, so a restarted server can start from where the last one
+    /// stopped. Called when the server shuts down.
+    fn save_state(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Restore the state returned by [`save_state`](LspContext::save_state) in the last run
+    /// of the server, as long as it was the same version. Called before any other request.
+    fn restore_state(&self, state: serde_json::Value) -> anyhow::Result<()> {
+        let _ = state;
+        Ok(())
+    }
+}
+
+/// What is written to [`LspContext::state_file`].
+#[derive(Serialize, Deserialize)]
+struct StateSnapshot {
+    /// The version of the server that wrote it, as the state of another may not be compatible.
+    version: String,
+    state: serde_json::Value,
 }
 
 /// Errors when [`LspContext::resolve_load()`] cannot resolve a given path.
@@ -320,6 +374,9 @@ struct Backend<T: LspContext> {
 }
 
 /// The logic implementations of stuff
+/// How often diagnostics are published while a file is still being checked.
+const PARTIAL_DIAGNOSTICS_INTERVAL: Duration = Duration::from_millis(200);
+
 impl<T: LspContext> Backend<T> {
     fn server_capabilities(settings: LspServerSettings) -> ServerCapabilities {
         let definition_provider = settings.enable_goto_definition.then_some({
@@ -332,6 +389,7 @@ impl<T: LspContext> Backend<T> {
         ServerCapabilities {
             text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
             definition_provider,
+            selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
             ..ServerCapabilities::default()
         }
     }
@@ -353,14 +411,28 @@ impl<T: LspContext> Backend<T> {
     }
 
     fn validate(&self, uri: Url, version: Option<i64>, text: String) -> anyhow::Result<()> {
-        let uri = uri.try_into()?;
-        let eval_result = self.context.parse_file_with_contents(&uri, text);
-        if let Some(ast) = eval_result.ast {
+        let lsp_uri: LspUrl = uri.clone().try_into()?;
+        // Publish the diagnostics found so far as they arrive, so long runs give feedback.
+        // Each publication replaces all the previous ones for the file, so publishing for
+        // every diagnostic would be quadratic, and the client only sees one batch per interval.
+        let mut diagnostics = Vec::new();
+        let mut last_publish = Instant::now();
+        let ast = self
+            .context
+            .parse_file_with_contents_streaming(&lsp_uri, text, &mut |x| {
+                diagnostics.push(x);
+                if last_publish.elapsed() >= PARTIAL_DIAGNOSTICS_INTERVAL {
+                    self.publish_diagnostics(uri.clone(), diagnostics.clone(), version);
+                    last_publish = Instant::now();
+                }
+            });
+        if let Some(ast) = ast {
             let module = Arc::new(LspModule::new(ast));
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
-            last_valid_parse.insert(uri.clone(), module);
+            last_valid_parse.insert(lsp_uri, module);
         }
-        self.publish_diagnostics(uri.try_into()?, eval_result.diagnostics, version);
+        // Always publish at the end, which also clears any diagnostics left from before.
+        self.publish_diagnostics(uri, diagnostics, version);
         Ok(())
     }
 
@@ -401,6 +473,12 @@ impl<T: LspContext> Backend<T> {
         self.send_response(new_response(id, self.find_definition(params)));
     }
 
+    /// Expand the selection at each position to the enclosing expressions and statements,
+    /// based on the last valid parse of the file.
+    fn selection_range(&self, id: RequestId, params: SelectionRangeParams) {
+        self.send_response(new_response(id, self.find_selection_ranges(params)));
+    }
+
     /// Get the file contents of a starlark: URI.
     fn get_starlark_file_contents(&self, id: RequestId, params: StarlarkFileContentsParams) {
         let response: anyhow::Result<_> = match params.uri {
@@ -587,6 +665,99 @@ impl<T: LspContext> Backend<T> {
         };
         Ok(GotoDefinitionResponse::Link(response))
     }
+
+    fn find_selection_ranges(
+        &self,
+        params: SelectionRangeParams,
+    ) -> anyhow::Result<Vec<SelectionRange>> {
+        let uri = params.text_document.uri.try_into()?;
+        let module = self.get_ast(&uri);
+        Ok(params
+            .positions
+            .into_iter()
+            .map(|position| {
+                let empty = SelectionRange {
+                    range: Range::new(position, position),
+                    parent: None,
+                };
+                let Some(module) = &module else {
+                    return empty;
+                };
+                let ast = &module.ast;
+                let node = ast.node_at(position.line as usize, position.character as usize);
+                let ranges: Vec<Range> = iter::successors(node, |id| ast.node_parent(*id))
+                    .filter_map(|id| Some(ast.node_span(id)?.resolve_span().into()))
+                    .collect();
+                // Nest from the outside in, skipping nodes with the same range as their child.
+                let mut res: Option<SelectionRange> = None;
+                for range in ranges.into_iter().rev() {
+                    if res.as_ref().map(|x| x.range) != Some(range) {
+                        res = Some(SelectionRange {
+                            range,
+                            parent: res.map(Box::new),
+                        });
+                    }
+                }
+                res.unwrap_or(empty)
+            })
+            .collect())
+    }
+
+    /// Restore the state saved by the last run, if any. Failing to is not fatal, as the
+    /// state is only there to speed things up.
+    fn restore_state(&self) {
+        let Some(path) = self.context.state_file() else {
+            return;
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => return self.state_file_warning("read", &path, e.into()),
+        };
+        let snapshot = match serde_json::from_str::<StateSnapshot>(&contents) {
+            Ok(snapshot) if snapshot.version == env!("CARGO_PKG_VERSION") => snapshot,
+            Ok(_) => return,
+            Err(e) => return self.state_file_warning("read", &path, e.into()),
+        };
+        if let Err(e) = self.context.restore_state(snapshot.state) {
+            self.state_file_warning("restore", &path, e);
+        }
+    }
+
+    fn save_state(&self) {
+        let Some(path) = self.context.state_file() else {
+            return;
+        };
+        let Some(state) = self.context.save_state() else {
+            return;
+        };
+        let snapshot = StateSnapshot {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            state,
+        };
+        // Write to a temporary file first, so a server killed halfway doesn't leave a
+        // truncated file behind.
+        let temp = path.with_extension("tmp");
+        let res = serde_json::to_vec(&snapshot)
+            .map_err(anyhow::Error::from)
+            .and_then(|x| Ok(fs::write(&temp, x)?))
+            .and_then(|()| Ok(fs::rename(&temp, &path)?));
+        if let Err(e) = res {
+            self.state_file_warning("save", &path, e);
+        }
+    }
+
+    fn state_file_warning(&self, action: &str, path: &Path, e: anyhow::Error) {
+        self.log_message(
+            MessageType::WARNING,
+            &format!(
+                "Could not {} the server state in `{}`: {:#}",
+                action,
+                path.display(),
+                e
+            ),
+        )
+    }
 }
 
 /// The library style pieces
@@ -624,10 +795,18 @@ impl<T: LspContext> Backend<T> {
                     //            be handled client side.
                     if let Some(params) = as_request::<GotoDefinition>(&req) {
                         self.goto_definition(req.id, params);
+                    } else if let Some(params) = as_request::<SelectionRangeRequest>(&req) {
+                        self.selection_range(req.id, params);
                     } else if let Some(params) = as_request::<StarlarkFileContentsRequest>(&req) {
                         self.get_starlark_file_contents(req.id, params);
-                    } else if self.connection.handle_shutdown(&req)? {
-                        return Ok(());
+                    } else {
+                        // Save before replying, as the client may stop the process after.
+                        if req.method == Shutdown::METHOD {
+                            self.save_state();
+                        }
+                        if self.connection.handle_shutdown(&req)? {
+                            return Ok(());
+                        }
                     }
                     // Currently don't handle any other requests
                 }
@@ -683,14 +862,18 @@ pub fn server_with_connection<T: LspContext>(
     let initialize_data = serde_json::json!({
             "capabilities": server_capabilities,
     });
-    connection.initialize_finish(init_request_id, initialize_data)?;
-
-    Backend {
+    let backend = Backend {
         connection,
         context,
         last_valid_parse: RwLock::default(),
-    }
-    .main_loop(initialization_params)?;
+    };
+    // Restore before replying, so no other request sees the server without its state.
+    backend.restore_state();
+    backend
+        .connection
+        .initialize_finish(init_request_id, initialize_data)?;
+
+    backend.main_loop(initialization_params)?;
 
     Ok(())
 }
@@ -768,18 +951,23 @@ where
 //            some paths. Revisit later.
 #[cfg(all(test, not(windows)))]
 mod test {
+    use std::fs;
     use std::path::Path;
     use std::path::PathBuf;
 
     use anyhow::Context;
     use lsp_server::Request;
     use lsp_server::RequestId;
+    use lsp_types::notification::PublishDiagnostics;
     use lsp_types::request::GotoDefinition;
+    use lsp_types::request::SelectionRangeRequest;
     use lsp_types::GotoDefinitionParams;
     use lsp_types::GotoDefinitionResponse;
     use lsp_types::LocationLink;
     use lsp_types::Position;
     use lsp_types::Range;
+    use lsp_types::SelectionRange;
+    use lsp_types::SelectionRangeParams;
     use lsp_types::TextDocumentIdentifier;
     use lsp_types::TextDocumentPositionParams;
     use lsp_types::Url;
@@ -871,6 +1059,25 @@ mod test {
         Url::from_file_path(PathBuf::from("/tmp").join(rel_path)).unwrap()
     }
 
+    #[test]
+    fn publishes_diagnostics_in_batches() -> anyhow::Result<()> {
+        let uri = temp_file_uri("file.star");
+
+        let mut server = TestServer::new()?;
+        server.change_file(uri.clone(), "x = 1\nx = 2\ny = 1\ny = 2\n".to_owned())?;
+        // Two duplicate assignments, and two unused assignments, all found well within
+        // one interval, so published together rather than one publication for each.
+        let notification = server.get_notification::<PublishDiagnostics>()?;
+        assert_eq!(notification.uri, uri);
+        assert_eq!(notification.diagnostics.len(), 4);
+
+        // Once the problems are fixed, the old diagnostics are cleared
+        server.change_file(uri, String::new())?;
+        let cleared = server.get_notification::<PublishDiagnostics>()?;
+        assert!(cleared.diagnostics.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn sends_empty_goto_definition_on_nonexistent_file() -> anyhow::Result<()> {
         let mut server = TestServer::new()?;
@@ -926,6 +1133,51 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn expands_selection_to_enclosing_nodes() -> anyhow::Result<()> {
+        let uri = temp_file_uri("file.star");
+
+        let mut server = TestServer::new()?;
+        let contents = "def f(a):\n    return a + [2]\n";
+        server.open_file(uri.clone(), contents.to_owned())?;
+
+        let request = server.new_request::<SelectionRangeRequest>(SelectionRangeParams {
+            text_document: TextDocumentIdentifier { uri },
+            positions: vec![Position::new(1, 16), Position::new(5, 0)],
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<SelectionRange>>(request_id)?;
+
+        let mut ranges = Vec::new();
+        let mut selection = Some(&response[0]);
+        while let Some(x) = selection {
+            ranges.push(x.range);
+            selection = x.parent.as_deref();
+        }
+        let range =
+            |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+        assert_eq!(
+            vec![
+                range(1, 16, 17),
+                range(1, 15, 18),
+                range(1, 11, 18),
+                range(1, 4, 18),
+                Range::new(Position::new(0, 0), Position::new(2, 0)),
+            ],
+            ranges
+        );
+        assert_eq!(
+            SelectionRange {
+                range: range(5, 0, 0),
+                parent: None
+            },
+            response[1]
+        );
+        Ok(())
+    }
+
     #[test]
     fn returns_old_definitions_if_current_file_does_not_parse() -> anyhow::Result<()> {
         let uri = temp_file_uri("file.star");
@@ -1596,6 +1848,38 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn keeps_state_between_runs() -> anyhow::Result<()> {
+        let state_file =
+            std::env::temp_dir().join(format!("starlark-lsp-state-{}.json", std::process::id()));
+        let _ = fs::remove_file(&state_file);
+        let state = serde_json::json!({"targets": ["//foo:bar"]});
+
+        let server = TestServer::new_with_state_file(state_file.clone())?;
+        assert_eq!(None, *server.state().read().unwrap());
+        *server.state().write().unwrap() = Some(state.clone());
+        // Saved on shutdown.
+        drop(server);
+
+        let server = TestServer::new_with_state_file(state_file.clone())?;
+        assert_eq!(Some(state.clone()), *server.state().read().unwrap());
+        drop(server);
+
+        // The state of another version of the server is ignored.
+        let snapshot: serde_json::Value = serde_json::from_slice(&fs::read(&state_file)?)?;
+        assert_eq!(Some(&state), snapshot.get("state"));
+        fs::write(
+            &state_file,
+            serde_json::json!({"version": "0.0.0", "state": state}).to_string(),
+        )?;
+        let server = TestServer::new_with_state_file(state_file.clone())?;
+        assert_eq!(None, *server.state().read().unwrap());
+        drop(server);
+
+        fs::remove_file(&state_file)?;
+        Ok(())
+    }
+
     #[test]
     fn returns_starlark_file_contents() -> anyhow::Result<()> {
         let mut server = TestServer::new()?;
@@ -119,6 +119,8 @@ struct TestServerContext {
     dirs: Arc<RwLock<HashSet<PathBuf>>>,
     builtin_docs: Arc<HashMap<LspUrl, String>>,
     builtin_symbols: Arc<HashMap<String, LspUrl>>,
+    state_file: Option<PathBuf>,
+    state: Arc<RwLock<Option<serde_json::Value>>>,
 }
 
 impl LspContext for TestServerContext {
@@ -226,6 +228,19 @@ impl LspContext for TestServerContext {
     ) -> anyhow::Result<Option<LspUrl>> {
         Ok(self.builtin_symbols.get(symbol).cloned())
     }
+
+    fn state_file(&self) -> Option<PathBuf> {
+        self.state_file.clone()
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        self.state.read().unwrap().clone()
+    }
+
+    fn restore_state(&self, state: serde_json::Value) -> anyhow::Result<()> {
+        *self.state.write().unwrap() = Some(state);
+        Ok(())
+    }
 }
 
 /// A server for use in testing that provides helpers for sending requests, correlating
@@ -252,6 +267,8 @@ pub struct TestServer {
     initialize_response: Option<InitializeResult>,
     /// Documentation for built in symbols.
     builtin_docs: Arc<HashMap<LspUrl, String>>,
+    /// The state the server saves on shutdown and restores on startup.
+    state: Arc<RwLock<Option<serde_json::Value>>>,
 }
 
 impl Drop for TestServer {
@@ -351,6 +368,19 @@ impl TestServer {
     /// initialization payload and makes sure that when the server is dropped, the threads
     /// are attempted to be stopped.
     pub(crate) fn new_with_settings(settings: Option<LspServerSettings>) -> anyhow::Result<Self> {
+        Self::start(settings, None)
+    }
+
+    /// Create and start a new LSP server which keeps its [`state`](TestServer::state)
+    /// in `state_file` between runs.
+    pub(crate) fn new_with_state_file(state_file: PathBuf) -> anyhow::Result<Self> {
+        Self::start(None, Some(state_file))
+    }
+
+    fn start(
+        settings: Option<LspServerSettings>,
+        state_file: Option<PathBuf>,
+    ) -> anyhow::Result<Self> {
         let (server_connection, client_connection) = Connection::memory();
 
         let builtin = Self::testing_builtins(&std::env::current_dir()?)?;
@@ -376,11 +406,14 @@ impl TestServer {
             .collect();
         let file_contents = Arc::new(RwLock::new(prelude_file_contents));
         let dirs = Arc::new(RwLock::new(HashSet::new()));
+        let state = Arc::new(RwLock::new(None));
         let ctx = TestServerContext {
             file_contents: file_contents.dupe(),
             dirs: dirs.dupe(),
             builtin_docs: builtin_docs.dupe(),
             builtin_symbols,
+            state_file,
+            state: state.dupe(),
         };
 
         let server_thread = std::thread::spawn(|| {
@@ -401,6 +434,7 @@ impl TestServer {
             dirs,
             initialize_response: None,
             builtin_docs,
+            state,
         };
         ret.initialize(settings)
     }
@@ -453,6 +487,11 @@ impl TestServer {
         Ok(self)
     }
 
+    /// The state of the server's context, see [`LspContext::save_state`].
+    pub(crate) fn state(&self) -> &RwLock<Option<serde_json::Value>> {
+        &self.state
+    }
+
     pub fn initialization_result(&self) -> Option<InitializeResult> {
         self.initialize_response.clone()
     }
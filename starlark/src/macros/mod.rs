@@ -180,8 +180,73 @@ macro_rules! starlark_complex_values {
 ///     }
 /// }
 /// ```
+///
+/// For a type with type parameters, list them after the name, followed by a `where`
+/// clause giving the bounds the `StarlarkValue` instance needs. The parameters must
+/// be `'static`, since simple values can't contain references:
+///
+/// ```
+/// use std::fmt::Debug;
+/// use std::fmt::Display;
+///
+/// use allocative::Allocative;
+/// use derive_more::Display;
+/// use starlark::values::{NoSerialize, ProvidesStaticType, StarlarkValue};
+/// use starlark::{starlark_simple_value, starlark_type};
+///
+/// trait Content: Debug + Display + Allocative + Send + Sync + 'static {}
+///
+/// #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+/// struct MyWrapper<T: Content + 'static>(T);
+/// starlark_simple_value!(MyWrapper<T> where T: Content);
+/// impl<'v, T: Content> StarlarkValue<'v> for MyWrapper<T> {
+///     starlark_type!("my_wrapper");
+/// }
+/// ```
 #[macro_export]
 macro_rules! starlark_simple_value {
+    ($x:ident < $($p:ident),+ $(,)? > $(where $($bounds:tt)*)?) => {
+        $crate::__macro_refs::item! {
+            impl<'v, $($p),+> $crate::values::AllocValue<'v> for $x<$($p),+> $(where $($bounds)*)? {
+                #[inline]
+                fn alloc_value(self, heap: &'v $crate::values::Heap) -> $crate::values::Value<'v> {
+                    heap.alloc_simple(self)
+                }
+            }
+
+            impl<$($p),+> $crate::values::AllocFrozenValue for $x<$($p),+> $(where $($bounds)*)? {
+                #[inline]
+                fn alloc_frozen_value(self, heap: &$crate::values::FrozenHeap) -> $crate::values::FrozenValue {
+                    heap.alloc_simple(self)
+                }
+            }
+
+            impl<$($p),+> $x<$($p),+> $(where $($bounds)*)? {
+                /// Downcast a value to self type.
+                #[inline]
+                pub fn from_value<'v>(x: $crate::values::Value<'v>) -> Option<&'v Self> {
+                    $crate::values::ValueLike::downcast_ref::<Self>(x)
+                }
+            }
+
+            impl<'v, $($p),+> $crate::values::type_repr::StarlarkTypeRepr for &'v $x<$($p),+> $(where $($bounds)*)? {
+                fn starlark_type_repr() -> String {
+                    <$x<$($p),+> as $crate::values::StarlarkValue>::get_type_starlark_repr()
+                }
+            }
+
+            impl<'v, $($p),+> $crate::values::UnpackValue<'v> for &'v $x<$($p),+> $(where $($bounds)*)? {
+                fn expected() -> String {
+                    <$x<$($p),+> as $crate::values::StarlarkValue>::get_type_value_static().as_str().to_owned()
+                }
+
+                #[inline]
+                fn unpack_value(x: $crate::values::Value<'v>) -> Option<&'v $x<$($p),+>> {
+                    $x::<$($p),+>::from_value(x)
+                }
+            }
+        }
+    };
     ($x:ident) => {
         $crate::__macro_refs::item! {
             impl<'v> $crate::values::AllocValue<'v> for $x {
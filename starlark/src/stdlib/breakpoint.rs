@@ -23,6 +23,7 @@ use thiserror::Error;
 
 use crate as starlark;
 use crate::environment::GlobalsBuilder;
+use crate::eval::BreakpointFilter;
 use crate::eval::Evaluator;
 use crate::read_line::ReadLine;
 use crate::syntax::AstModule;
@@ -80,14 +81,22 @@ enum Next {
     Fail,   // Stop running
 }
 
-fn cmd_help(_eval: &mut Evaluator, rl: &mut dyn BreakpointConsole) -> anyhow::Result<Next> {
+fn cmd_help(
+    _eval: &mut Evaluator,
+    rl: &mut dyn BreakpointConsole,
+    _args: &str,
+) -> anyhow::Result<Next> {
     for (name, msg, _) in COMMANDS {
         rl.println(&format!("* :{}, {}", name[0], msg))
     }
     Ok(Next::Again)
 }
 
-fn cmd_variables(eval: &mut Evaluator, rl: &mut dyn BreakpointConsole) -> anyhow::Result<Next> {
+fn cmd_variables(
+    eval: &mut Evaluator,
+    rl: &mut dyn BreakpointConsole,
+    _args: &str,
+) -> anyhow::Result<Next> {
     fn truncate(mut s: String, n: usize) -> String {
         if s.len() > n {
             s.truncate(n);
@@ -102,37 +111,85 @@ fn cmd_variables(eval: &mut Evaluator, rl: &mut dyn BreakpointConsole) -> anyhow
     Ok(Next::Again)
 }
 
-fn cmd_stack(eval: &mut Evaluator, rl: &mut dyn BreakpointConsole) -> anyhow::Result<Next> {
+fn cmd_stack(
+    eval: &mut Evaluator,
+    rl: &mut dyn BreakpointConsole,
+    _args: &str,
+) -> anyhow::Result<Next> {
     for line in eval.call_stack().to_string().lines() {
         rl.println(line)
     }
     Ok(Next::Again)
 }
 
-fn cmd_resume(_eval: &mut Evaluator, _rl: &mut dyn BreakpointConsole) -> anyhow::Result<Next> {
+fn print_watches(eval: &mut Evaluator, rl: &mut dyn BreakpointConsole) {
+    for expression in eval.breakpoint_watches.clone() {
+        match eval.eval_watch(&expression) {
+            Ok(v) => rl.println(&format!("* {} = {}", expression, v)),
+            Err(e) => rl.println(&format!("* {} = {:#}", expression, e)),
+        }
+    }
+}
+
+fn cmd_watch(
+    eval: &mut Evaluator,
+    rl: &mut dyn BreakpointConsole,
+    args: &str,
+) -> anyhow::Result<Next> {
+    if !args.is_empty() && !eval.breakpoint_watches.iter().any(|x| x == args) {
+        eval.breakpoint_watches.push(args.to_owned());
+    }
+    print_watches(eval, rl);
+    Ok(Next::Again)
+}
+
+fn cmd_unwatch(
+    eval: &mut Evaluator,
+    _rl: &mut dyn BreakpointConsole,
+    _args: &str,
+) -> anyhow::Result<Next> {
+    eval.breakpoint_watches.clear();
+    Ok(Next::Again)
+}
+
+fn cmd_resume(
+    _eval: &mut Evaluator,
+    _rl: &mut dyn BreakpointConsole,
+    _args: &str,
+) -> anyhow::Result<Next> {
     Ok(Next::Resume)
 }
 
-fn cmd_fail(_eval: &mut Evaluator, _rl: &mut dyn BreakpointConsole) -> anyhow::Result<Next> {
+fn cmd_fail(
+    _eval: &mut Evaluator,
+    _rl: &mut dyn BreakpointConsole,
+    _args: &str,
+) -> anyhow::Result<Next> {
     Ok(Next::Fail)
 }
 
+/// A command, given the text after its name.
+type Command = fn(&mut Evaluator, &mut dyn BreakpointConsole, &str) -> anyhow::Result<Next>;
+
 const COMMANDS: &[(
     &[&str], // Possible names
     &str,    // Help text
-    fn(eval: &mut Evaluator, &mut dyn BreakpointConsole) -> anyhow::Result<Next>,
+    Command,
 )] = &[
     (&["help", "?"], "Show this help message", cmd_help),
     (&["vars"], "Show all local variables", cmd_variables),
     (&["stack"], "Show the stack trace", cmd_stack),
+    (
+        &["watch"],
+        "Show the watch expressions, after adding the expression given, if any",
+        cmd_watch,
+    ),
+    (&["unwatch"], "Remove all watch expressions", cmd_unwatch),
     (&["resume", "quit", "exit"], "Resume execution", cmd_resume),
     (&["fail"], "Abort with a failure message", cmd_fail),
 ];
 
-fn pick_command(
-    x: &str,
-    rl: &mut dyn BreakpointConsole,
-) -> Option<fn(eval: &mut Evaluator, &mut dyn BreakpointConsole) -> anyhow::Result<Next>> {
+fn pick_command(x: &str, rl: &mut dyn BreakpointConsole) -> Option<Command> {
     // If we can find a command that matches perfectly, do that
     // Otherwise return the longest match, but if they are multiple, show a warning
     let mut poss = Vec::new();
@@ -167,8 +224,10 @@ fn breakpoint_loop(
         match readline {
             Some(line) => {
                 if let Some(line) = line.strip_prefix(':') {
-                    if let Some(cmd) = pick_command(line.trim_end(), &mut *rl) {
-                        match cmd(eval, &mut *rl)? {
+                    let line = line.trim();
+                    let (name, args) = line.split_once(' ').unwrap_or((line, ""));
+                    if let Some(cmd) = pick_command(name, &mut *rl) {
+                        match cmd(eval, &mut *rl, args.trim())? {
                             Next::Again => {}
                             Next::Resume => return Ok(State::Allow),
                             Next::Fail => {
@@ -206,7 +265,32 @@ const BREAKPOINT_HIT_MESSAGE: &str = "BREAKPOINT HIT! :resume to continue, :help
 
 #[starlark_module]
 pub fn global(builder: &mut GlobalsBuilder) {
-    fn breakpoint(eval: &mut Evaluator) -> anyhow::Result<NoneType> {
+    /// Pause execution and open the debugger console, if one is enabled.
+    ///
+    /// With `condition`, a Starlark expression, only pause if it is true in the calling frame.
+    /// With `hit_condition`, only pause when the number of times this call was reached
+    /// (with the condition true) matches it, e.g. `"3"` for the third time, `">= 3"` or
+    /// `"% 3"` for every third time.
+    fn breakpoint(
+        #[starlark(require = named)] condition: Option<&str>,
+        #[starlark(require = named)] hit_condition: Option<&str>,
+        eval: &mut Evaluator,
+    ) -> anyhow::Result<NoneType> {
+        let mut condition_error = None;
+        if condition.is_some() || hit_condition.is_some() {
+            let location = eval.call_stack_top_location();
+            let mut filter = match eval.breakpoint_filters.remove(&location) {
+                Some(filter) => filter,
+                None => BreakpointFilter::new(condition.map(str::to_owned), hit_condition)?,
+            };
+            let stop = filter.should_stop(eval);
+            eval.breakpoint_filters.insert(location, filter);
+            match stop {
+                Ok(false) => return Ok(NoneType),
+                Ok(true) => {}
+                Err(e) => condition_error = Some(e),
+            }
+        }
         {
             let mut guard = BREAKPOINT_MUTEX.lock().unwrap();
             if *guard == State::Allow {
@@ -215,6 +299,10 @@ pub fn global(builder: &mut GlobalsBuilder) {
                     None => return Err(BreakpointError::NoHandler.into()),
                 };
                 rl.println(BREAKPOINT_HIT_MESSAGE);
+                if let Some(e) = condition_error {
+                    rl.println(&format!("Breakpoint condition failed: {:#}", e));
+                }
+                print_watches(eval, &mut *rl);
                 *guard = breakpoint_loop(eval, rl)?;
             }
         }
@@ -232,6 +320,7 @@ mod tests {
 
     use super::*;
     use crate::assert::Assert;
+    use crate::environment::Module;
 
     // Breakpoint tests should not be executed concurrently
     // to avoid interfering with `BREAKPOINT_MUTEX`.
@@ -309,6 +398,109 @@ mod tests {
         );
     }
 
+    /// Lines printed by breakpoints in `code`, which each run `commands`.
+    fn breakpoint_lines(code: &str, commands: &[&'static str]) -> anyhow::Result<Vec<String>> {
+        struct Handler {
+            printed_lines: Rc<RefCell<Vec<String>>>,
+            commands: Vec<&'static str>,
+        }
+
+        impl BreakpointConsole for Handler {
+            fn read_line(&mut self) -> anyhow::Result<Option<String>> {
+                Ok(self.commands.pop().map(str::to_owned))
+            }
+
+            fn println(&mut self, line: &str) {
+                self.printed_lines.borrow_mut().push(line.to_owned());
+            }
+        }
+
+        let printed_lines = Rc::new(RefCell::new(Vec::new()));
+        let module = Module::new();
+        let globals = GlobalsBuilder::standard().with(global).build();
+        let mut eval = Evaluator::new(&module);
+        let lines = printed_lines.dupe();
+        let commands = commands.iter().rev().copied().collect::<Vec<_>>();
+        eval.breakpoint_handler = Some(Box::new(move || {
+            Box::new(Handler {
+                printed_lines: lines.dupe(),
+                commands: commands.clone(),
+            })
+        }));
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended)?;
+        eval.eval_module(ast, &globals)?;
+        let res = printed_lines.borrow().clone();
+        Ok(res)
+    }
+
+    #[test]
+    fn test_breakpoint_condition() {
+        let _g = TEST_MUTEX.lock();
+        reset_global_state();
+
+        let code = r#"
+def f():
+    for i in range(1, 7):
+        breakpoint(condition = "i % 2 == 0", hit_condition = ">= 2")
+f()
+"#;
+        assert_eq!(
+            vec![BREAKPOINT_HIT_MESSAGE, "4", BREAKPOINT_HIT_MESSAGE, "6"],
+            breakpoint_lines(code, &["i", ":resume"]).unwrap()
+        );
+
+        let lines =
+            breakpoint_lines("i = 1\nbreakpoint(condition = 'j')", &["i", ":resume"]).unwrap();
+        assert_eq!(BREAKPOINT_HIT_MESSAGE, lines[0]);
+        assert!(
+            lines[1].starts_with("Breakpoint condition failed: ") && lines[1].contains("`j`"),
+            "{:?}",
+            lines
+        );
+        assert_eq!("1", lines[2]);
+
+        let err = breakpoint_lines("breakpoint(hit_condition = 'x')", &[]).unwrap_err();
+        assert!(
+            err.to_string().contains("Invalid hit condition"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_watch() {
+        let _g = TEST_MUTEX.lock();
+        reset_global_state();
+
+        let code = r#"
+def f():
+    for i in range(2):
+        breakpoint()
+f()
+"#;
+        let commands = [":watch i * 10", ":resume"];
+        assert_eq!(
+            vec![
+                BREAKPOINT_HIT_MESSAGE,
+                "* i * 10 = 0",
+                BREAKPOINT_HIT_MESSAGE,
+                "* i * 10 = 10",
+                "* i * 10 = 10",
+            ],
+            breakpoint_lines(code, &commands).unwrap()
+        );
+
+        let commands = [":watch x = 1", ":unwatch", ":watch", "x", ":resume"];
+        assert_eq!(
+            vec![
+                BREAKPOINT_HIT_MESSAGE,
+                "* x = 1 = Assigning variables is not allowed here",
+                "2",
+            ],
+            breakpoint_lines("x = 2\nbreakpoint()", &commands).unwrap()
+        );
+    }
+
     #[test]
     fn test_breakpoint_disabled() {
         let _g = TEST_MUTEX.lock();
@@ -21,28 +21,37 @@ use std::fmt::Display;
 use allocative::Allocative;
 use gazebo::prelude::*;
 use itertools::Itertools;
+use num_traits::Signed;
 
 use crate as starlark;
 use crate::any::ProvidesStaticType;
 use crate::coerce::coerce;
 use crate::coerce::Coerce;
 use crate::collections::symbol_map::Symbol;
+use crate::collections::SmallSet;
 use crate::environment::GlobalsBuilder;
 use crate::eval::runtime::arguments::ArgNames;
 use crate::eval::runtime::arguments::ArgumentsFull;
 use crate::eval::runtime::rust_loc::rust_loc;
 use crate::eval::Arguments;
 use crate::eval::Evaluator;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::dict::DictRef;
 use crate::values::function::FUNCTION_TYPE;
 use crate::values::layout::typed::string::StringValueLike;
 use crate::values::none::NoneType;
+use crate::values::num::Num;
 use crate::values::regex::StarlarkRegex;
+use crate::values::types::bigint::StarlarkBigInt;
+use crate::values::types::identity_dict::IdentityDict;
+use crate::values::types::set::collect_set;
+use crate::values::types::set::Set;
 use crate::values::types::tuple::value::Tuple;
 use crate::values::Freeze;
 use crate::values::Freezer;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
+use crate::values::Heap;
 use crate::values::StarlarkValue;
 use crate::values::StringValue;
 use crate::values::Trace;
@@ -137,6 +146,70 @@ pub fn regex(builder: &mut GlobalsBuilder) {
     }
 }
 
+#[starlark_module]
+pub fn identity_dict(builder: &mut GlobalsBuilder) {
+    /// Create an empty identity dictionary. Its keys are compared by identity rather
+    /// than equality, so any value can be used as a key, including unhashable values
+    /// like lists. Two separately created values are always different keys, even
+    /// if they are equal.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// a = []
+    /// d = idict()
+    /// d[a] = 1
+    /// d[a] == 1 and [] not in d
+    /// # "#);
+    /// ```
+    #[starlark(type = IdentityDict::TYPE)]
+    fn idict<'v>() -> anyhow::Result<IdentityDict<'v>> {
+        Ok(IdentityDict::new())
+    }
+}
+
+#[starlark_module]
+pub fn set(builder: &mut GlobalsBuilder) {
+    /// Create a set containing the elements of the iterable, or an empty set if not given.
+    /// The elements must be hashable, and are iterated in insertion order.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// s = set([1, 2, 1])
+    /// s.add(3)
+    /// len(s) == 3 and 2 in s and s | set([4]) == set([1, 2, 3, 4])
+    /// # "#);
+    /// ```
+    #[starlark(type = Set::TYPE, speculative_exec_safe)]
+    fn set<'v>(
+        #[starlark(require = pos)] x: Option<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        match x {
+            None => Ok(Set::new(SmallSet::new())),
+            Some(x) => Ok(Set::new(collect_set(x, heap)?)),
+        }
+    }
+}
+
+#[starlark_module]
+pub fn bytes(builder: &mut GlobalsBuilder) {
+    /// Create `bytes` from the UTF-8 encoding of a string, a copy of other `bytes`,
+    /// or an iterable of ints between 0 and 255.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// bytes("hi") == bytes([104, 105]) and str(bytes([104, 105])) == "hi"
+    /// # "#);
+    /// ```
+    #[starlark(type = StarlarkBytes::TYPE, speculative_exec_safe)]
+    fn bytes<'v>(
+        #[starlark(require = pos)] x: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<StarlarkBytes> {
+        StarlarkBytes::from_starlark(x, heap)
+    }
+}
+
 struct PrintWrapper<'a, 'b>(&'a Vec<Value<'b>>);
 impl fmt::Display for PrintWrapper<'_, '_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -191,8 +264,19 @@ pub fn pprint(builder: &mut GlobalsBuilder) {
 
 #[starlark_module]
 pub fn abs(builder: &mut GlobalsBuilder) {
-    fn abs(#[starlark(require = pos)] x: i32) -> anyhow::Result<i32> {
-        Ok(x.abs())
+    fn abs<'v>(
+        #[starlark(require = pos, type = "int.type")] x: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        match x.unpack_num() {
+            Some(Num::Int(x)) => match x.checked_abs() {
+                Some(x) => Ok(Value::new_int(x)),
+                // `abs(-2147483648)` is a big int
+                None => Ok(heap.alloc(-(x as i64))),
+            },
+            Some(Num::BigInt(x)) => Ok(StarlarkBigInt::alloc_bigint(x.get().abs(), heap)),
+            _ => Ok(heap.alloc((x.to_int()? as i64).abs())),
+        }
     }
 }
 
@@ -324,6 +408,18 @@ assert_eq(["hello world!"], filter(contains_hello, ["hello world!", "goodbye"]))
         );
     }
 
+    #[test]
+    fn test_abs() {
+        assert::all_true(
+            r#"
+abs(-3) == 3 and abs(3) == 3
+abs(-2147483648) == 2147483648
+abs(-(1 << 100)) == 1 << 100
+abs(1 << 100) == 1 << 100
+"#,
+        );
+    }
+
     #[test]
     fn test_map() {
         assert::pass(
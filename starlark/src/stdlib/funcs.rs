@@ -21,8 +21,15 @@
 use std::char;
 use std::cmp::Ordering;
 use std::fmt::Display;
+use std::mem;
+use std::num::IntErrorKind;
 use std::num::NonZeroI32;
 
+use num_bigint::BigInt;
+use num_bigint::BigUint;
+use num_traits::FromPrimitive;
+use num_traits::Num as _;
+
 use crate as starlark;
 use crate::collections::SmallMap;
 use crate::environment::GlobalsBuilder;
@@ -41,7 +48,9 @@ use crate::values::range::Range;
 use crate::values::string::STRING_TYPE;
 use crate::values::tuple::AllocTuple;
 use crate::values::tuple::TupleRef;
+use crate::values::types::bigint::StarlarkBigInt;
 use crate::values::types::tuple::value::Tuple;
+use crate::values::types::unbound::MaybeUnboundValue;
 use crate::values::AllocValue;
 use crate::values::FrozenStringValue;
 use crate::values::Heap;
@@ -377,8 +386,17 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
-    fn dir(#[starlark(require = pos)] x: Value) -> anyhow::Result<Vec<String>> {
-        Ok(x.dir_attr())
+    fn dir<'v>(
+        #[starlark(require = pos)] x: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut res = x.dir_attr();
+        let extensions = eval.type_extension_names(x);
+        if !extensions.is_empty() {
+            res.extend(extensions);
+            res.sort();
+        }
+        Ok(res)
     }
 
     /// [enumerate](
@@ -500,11 +518,15 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         #[starlark(require = pos)] a: Value<'v>,
         #[starlark(require = pos)] attr: &str,
         #[starlark(require = pos)] default: Option<Value<'v>>,
-        heap: &'v Heap,
+        eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
+        let heap = eval.heap();
         // TODO(nga): this doesn't cache string hash, so it is suboptimal.
-        match a.get_attr(attr, heap)? {
-            Some(v) => Ok(v),
+        if let Some(v) = a.get_attr(attr, heap)? {
+            return Ok(v);
+        }
+        match eval.get_type_extension(a, attr) {
+            Some(v) => MaybeUnboundValue::new(v).bind(a, heap),
             None => match default {
                 Some(x) => Ok(x),
                 None => ValueError::unsupported_owned(a.get_type(), &format!(".{}", attr), None),
@@ -522,9 +544,9 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     fn hasattr<'v>(
         #[starlark(require = pos)] a: Value<'v>,
         #[starlark(require = pos)] attr: &str,
-        heap: &'v Heap,
+        eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<bool> {
-        Ok(a.has_attr(attr, heap))
+        Ok(a.has_attr(attr, eval.heap()) || eval.get_type_extension(a, attr).is_some())
     }
 
     /// [hash](
@@ -606,14 +628,14 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// int(3.14) == 3
     /// int(-12345.6789) == -12345
     /// int(2e9) == 2000000000
+    /// int(1e20) == 100000000000000000000
+    /// int('123456789012345678901234567890') == 123456789012345678901234567890
+    /// int('-0x80000000', 16) == -2147483648
     /// # "#);
     /// # starlark::assert::fail(r#"
     /// int("hello")   # error: not a valid number
     /// # "#, "not a valid number");
     /// # starlark::assert::fail(r#"
-    /// int(1e100)   # error: overflow
-    /// # "#, "cannot convert float to integer");
-    /// # starlark::assert::fail(r#"
     /// int(float("nan"))   # error: cannot convert NaN to int
     /// # "#, "cannot convert float to integer");
     /// # starlark::assert::fail(r#"
@@ -624,6 +646,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     fn int<'v>(
         #[starlark(require = pos)] a: Option<Value<'v>>,
         #[starlark(type = "[int.type, bool.type]")] base: Option<Value<'v>>,
+        heap: &'v Heap,
     ) -> anyhow::Result<Value<'v>> {
         if a.is_none() {
             return Ok(Value::new_int(0));
@@ -689,18 +712,25 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
                     error,
                 )
             }
-            match (u32::from_str_radix(s, base), negate) {
-                (Ok(i), false) => i32::try_from(i)
-                    .map(Value::new_int)
-                    .map_err(|_| err(a, base, "overflow")),
-                (Ok(i), true) => {
-                    if i > 0x80000000 {
-                        Err(err(a, base, "overflow"))
-                    } else {
-                        Ok(Value::new_int(0u32.wrapping_sub(i) as i32))
-                    }
+            match u32::from_str_radix(s, base) {
+                Ok(i) => {
+                    let i = i as i64;
+                    Ok(heap.alloc(if negate { -i } else { i }))
+                }
+                // Too big for a `u32`, so a big int, if it is only made of digits
+                Err(x)
+                    if *x.kind() == IntErrorKind::PosOverflow
+                        && s.chars().all(|c| c.is_digit(base)) =>
+                {
+                    let i: BigInt = BigUint::from_str_radix(s, base)
+                        .map_err(|x| err(a, base, x))?
+                        .into();
+                    Ok(StarlarkBigInt::alloc_bigint(
+                        if negate { -i } else { i },
+                        heap,
+                    ))
                 }
-                (Err(x), _) => Err(err(a, base, x)),
+                Err(x) => Err(err(a, base, x)),
             }
         } else if let Some(base) = base {
             Err(anyhow::anyhow!(
@@ -711,10 +741,13 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
             match num {
                 Num::Float(f) => match Num::from(f.trunc()).as_int() {
                     Some(i) => Ok(Value::new_int(i)),
-                    None => Err(anyhow::anyhow!(
-                        "int() cannot convert float to integer: {}",
-                        a.to_repr()
-                    )),
+                    None => match BigInt::from_f64(f.trunc()) {
+                        Some(i) => Ok(StarlarkBigInt::alloc_bigint(i, heap)),
+                        None => Err(anyhow::anyhow!(
+                            "int() cannot convert float to integer: {}",
+                            a.to_repr()
+                        )),
+                    },
                 },
                 Num::Int(..) | Num::BigInt(..) => Ok(a),
             }
@@ -776,7 +809,11 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
             if let Some(xs) = ListRef::from_value(a) {
                 heap.alloc_list(xs.content())
             } else {
-                a.with_iterator(heap, |it| heap.alloc(AllocList(it)))?
+                a.with_iterator(heap, |it| {
+                    // Collecting a large iterator such as a range may exceed the heap limit.
+                    heap.check_allocation(it.size_hint().0 * mem::size_of::<Value>())?;
+                    Ok::<_, anyhow::Error>(heap.alloc(AllocList(it)))
+                })??
             }
         } else {
             heap.alloc(AllocList::EMPTY)
@@ -1066,7 +1103,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
             Ok(a)
         } else {
             let mut s = eval.string_pool.alloc();
-            a.collect_repr(&mut s);
+            a.collect_str(&mut s);
             let r = eval.heap().alloc_str(&s);
             eval.string_pool.release(s);
             Ok(r)
@@ -1218,8 +1255,11 @@ hash(foo)
         assert::eq("-2147483647 - 1", "int('-2147483648')");
         assert::eq("0", "int('0')");
         assert::eq("0", "int('-0')");
-        assert::fail("int('2147483648')", "overflow");
-        assert::fail("int('-2147483649')", "overflow");
+        assert::eq("2147483647 + 1", "int('2147483648')");
+        assert::eq("-2147483647 - 2", "int('-2147483649')");
+        assert::eq("-(1 << 100)", "int('-0x10000000000000000000000000', 16)");
+        assert::eq("1 << 70", "int(1.0 * (1 << 70))");
+        assert::fail("int('99999999999_1')", "not a valid number");
     }
 
     #[test]
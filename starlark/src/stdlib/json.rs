@@ -15,8 +15,6 @@
  * limitations under the License.
  */
 
-use std::str::FromStr;
-
 use gazebo::prelude::*;
 use num_bigint::BigInt;
 use thiserror::Error;
@@ -41,12 +39,12 @@ fn serde_to_starlark<'v>(x: serde_json::Value, heap: &'v Heap) -> anyhow::Result
         serde_json::Value::Null => Ok(Value::new_none()),
         serde_json::Value::Bool(x) => Ok(Value::new_bool(x)),
         serde_json::Value::Number(x) => {
-            if let Some(x) = x.as_u64() {
+            if let Some(x) = x.as_i64() {
                 Ok(heap.alloc(x))
+            } else if let Some(x) = x.as_u64() {
+                Ok(StarlarkBigInt::alloc_bigint(BigInt::from(x), heap))
             } else if let Some(x) = x.as_f64() {
                 Ok(heap.alloc(x))
-            } else if let Ok(x) = BigInt::from_str(&x.to_string()) {
-                Ok(StarlarkBigInt::alloc_bigint(x, heap))
             } else {
                 Err(JsonError::UnrepresentableNumber(x.to_string()).into())
             }
@@ -96,6 +94,15 @@ mod tests {
     fn test_json_encode() {
         let a = Assert::new();
         a.eq("'[10]'", "json.encode([10])");
+        a.eq("'[-1,2147483648]'", "json.encode([-1, 2147483648])");
+        a.eq(
+            "'123456789123456789123456789'",
+            "json.encode(123456789123456789123456789)",
+        );
+        a.eq(
+            "'\"1361129467683753853853498429727072845824\"'",
+            "json.encode(1 << 130)",
+        );
     }
 
     #[test]
@@ -111,5 +118,9 @@ mod tests {
             "123456789123456789123456789",
             "json.decode('123456789123456789123456789')",
         );
+        a.is_true("json.decode('18446744073709551615') == 18446744073709551615");
+        a.is_true("type(json.decode('18446744073709551615')) == 'int'");
+        a.is_true("json.decode('-1') == -1 and type(json.decode('-1')) == 'int'");
+        a.is_true("type(json.decode('1e3')) == 'float'");
     }
 }
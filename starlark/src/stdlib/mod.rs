@@ -30,6 +30,7 @@ mod funcs;
 pub(crate) mod json;
 
 pub(crate) mod list;
+pub(crate) mod namedtuple;
 pub(crate) mod record;
 pub(crate) mod string;
 pub(crate) mod structs;
@@ -49,7 +50,8 @@ pub(crate) fn standard_environment() -> GlobalsBuilder {
 /// The extra library definitions available in this Starlark implementation, but not in the standard.
 #[derive(PartialEq, Eq, Copy, Clone, Dupe)]
 pub enum LibraryExtension {
-    /// Definitions to support the `struct` type, the `struct()` constructor.
+    /// Definitions to support the `struct` type, the `struct()` constructor,
+    /// and `structs.to_dict()`/`structs.replace()` to convert and update structs.
     StructType,
     /// Definitions to support the `record` type, the `record()` constructor and `field()` function.
     RecordType,
@@ -78,6 +80,15 @@ pub enum LibraryExtension {
     Json,
     /// Add a function `abs()` which will take the absolute value of an int.
     Abs,
+    /// Add a function `idict()` creating a dictionary whose keys are compared by identity,
+    /// so any value (including unhashable ones) can be used as a key.
+    IdentityDict,
+    /// Definitions to support the `set` type, the `set()` constructor.
+    Set,
+    /// Definitions to support the `bytes` type, the `bytes()` constructor.
+    Bytes,
+    /// Definitions to support the `namedtuple` type, the `namedtuple()` constructor.
+    NamedTuple,
     // Make sure if you add anything new, you add it to `all` below.
 }
 
@@ -99,6 +110,10 @@ impl LibraryExtension {
             Breakpoint,
             Json,
             Abs,
+            IdentityDict,
+            Set,
+            Bytes,
+            NamedTuple,
         ]
     }
 
@@ -106,7 +121,10 @@ impl LibraryExtension {
     pub fn add(self, builder: &mut GlobalsBuilder) {
         use LibraryExtension::*;
         match self {
-            StructType => structs::global(builder),
+            StructType => {
+                structs::global(builder);
+                structs::structs(builder)
+            }
             RecordType => record::global(builder),
             EnumType => enumeration::global(builder),
             Map => extra::map(builder),
@@ -119,6 +137,10 @@ impl LibraryExtension {
             Breakpoint => breakpoint::global(builder),
             Json => json::json(builder),
             Abs => extra::abs(builder),
+            IdentityDict => extra::identity_dict(builder),
+            Set => extra::set(builder),
+            Bytes => extra::bytes(builder),
+            NamedTuple => namedtuple::global(builder),
         }
     }
 }
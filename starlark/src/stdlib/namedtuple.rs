@@ -0,0 +1,143 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use either::Either;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::namedtuple::NamedTupleType;
+
+#[starlark_module]
+pub fn global(builder: &mut GlobalsBuilder) {
+    /// Creates a named tuple type, whose instances are tuples whose elements can also be
+    /// accessed by name. The fields are given as a list of names, or as a single string
+    /// with the names separated by commas or whitespace.
+    ///
+    /// Unlike `record`, fields have no types, so nothing is checked when creating one.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// Point = namedtuple("Point", "x y")
+    /// p = Point(1, y = 2)
+    /// x, y = p
+    /// p.y == 2 and x == 1 and str(p) == "Point(x=1, y=2)"
+    /// # "#);
+    /// ```
+    fn namedtuple(
+        #[starlark(require = pos)] name: &str,
+        #[starlark(require = pos)] fields: Either<&str, Vec<String>>,
+    ) -> anyhow::Result<NamedTupleType> {
+        let fields = match fields {
+            Either::Left(s) => s
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|x| !x.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            Either::Right(xs) => xs,
+        };
+        NamedTupleType::new(name.to_owned(), fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_namedtuple() {
+        assert::pass(
+            r#"
+Point = namedtuple("Point", ["x", "y"])
+p = Point(1, 2)
+assert_eq(p.x, 1)
+assert_eq(p.y, 2)
+assert_eq(p, Point(y = 2, x = 1))
+assert_ne(p, Point(2, 1))
+assert_eq(p[0], 1)
+assert_eq(p[-1], 2)
+assert_eq(len(p), 2)
+assert_eq(list(p), [1, 2])
+x, y = p
+assert_eq((x, y), (1, 2))
+assert_eq(dir(p), ["x", "y"])
+assert_eq(type(p), "namedtuple")
+assert_eq(str(p), "Point(x=1, y=2)")
+assert_eq(str(Point), 'namedtuple("Point", ["x", "y"])')
+assert_eq(Point.type, "Point")
+assert_eq({p: 1}[Point(1, 2)], 1)
+assert_eq(json.encode(p), '{"x":1,"y":2}')
+"#,
+        );
+        assert::pass(
+            r#"
+Empty = namedtuple("Empty", [])
+assert_eq(bool(Empty()), False)
+assert_eq(namedtuple("P", "a, b  c")(1, 2, 3).c, 3)
+assert_eq(namedtuple("P", ("a",))(1).a, 1)
+"#,
+        );
+        assert::pass(
+            r#"
+Point = namedtuple("Point", ["x", "y"])
+def f(p: Point.type) -> "namedtuple":
+    return p
+f(Point(1, 2))"#,
+        );
+    }
+
+    #[test]
+    fn test_namedtuple_fails() {
+        assert::fails(r#"namedtuple("P", ["x", "x"])"#, &["`x`", "more than once"]);
+        assert::fails(
+            r#"namedtuple("P", ["1x"])"#,
+            &["`1x`", "not a valid identifier"],
+        );
+        assert::fails(r#"namedtuple("P", "x")(1, 2)"#, &["extra positional"]);
+        assert::fails(
+            r#"namedtuple("P", "x y")(1)"#,
+            &["Missing parameter", "`y`"],
+        );
+        assert::fails(r#"namedtuple("P", "x")(1).z"#, &["has no attribute", "z"]);
+        assert::fails(r#"namedtuple("P", "x")(1)[1]"#, &["out of bound"]);
+    }
+
+    #[test]
+    fn test_namedtuple_equality() {
+        let mut a = Assert::new();
+        a.module(
+            "m",
+            r#"
+P = namedtuple("P", "x y")
+p = P(1, 2)
+"#,
+        );
+        a.pass(
+            r#"
+load('m', P1='P', 'p')
+P = namedtuple("P", "x y")
+assert_eq(p, P(1, 2))
+assert_eq(p, P1(1, 2))
+assert_ne(p, namedtuple("Q", "x y")(1, 2))
+assert_ne(p, namedtuple("P", "x z")(1, 2))
+assert_ne(p, (1, 2))
+"#,
+        );
+    }
+}
@@ -347,8 +347,11 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// the default.
     ///
     /// The *format specifier*, after a colon, specifies field width,
-    /// alignment, padding, and numeric precision.
-    /// Currently it must be empty, but it is reserved for future use.
+    /// alignment, padding, and numeric precision, following
+    /// [Python's format specification mini-language](
+    /// https://docs.python.org/3/library/string.html#format-specification-mini-language):
+    /// `[[fill]align][sign][#][0][width][grouping][.precision][type]`.
+    /// If a conversion is given, the specifier applies to the converted string.
     ///
     /// Examples:
     ///
@@ -359,6 +362,9 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
     /// "a{}b{}c".format(1, 2) == "a1b2c"
     /// "({1}, {0})".format("zero", "one") == "(one, zero)"
     /// "Is {0!r} {0!s}?".format("heterological") == "Is \"heterological\" heterological?"
+    /// "{:>6.2f}".format(3.14159) == "  3.14"
+    /// "{:#x}".format(255) == "0xff"
+    /// "{:,}".format(1234567) == "1,234,567"
     /// # "#);
     /// ```
     #[starlark(speculative_exec_safe)]
@@ -17,10 +17,16 @@
 
 //! Implementation of `struct` function.
 use crate as starlark;
+use crate::collections::SmallMap;
 use crate::environment::GlobalsBuilder;
 use crate::eval::Arguments;
+use crate::values::dict::Dict;
 use crate::values::structs::value::Struct;
+use crate::values::structs::value::StructError;
+use crate::values::structs::StructRef;
 use crate::values::Heap;
+use crate::values::StringValue;
+use crate::values::Value;
 
 #[starlark_module]
 pub fn global(builder: &mut GlobalsBuilder) {
@@ -34,3 +40,86 @@ pub fn global(builder: &mut GlobalsBuilder) {
         Ok(Struct::new(args.names_map()?))
     }
 }
+
+pub(crate) fn structs(globals: &mut GlobalsBuilder) {
+    globals.struct_("structs", structs_members);
+}
+
+/// Operations on structs are kept outside the struct itself, so any name can be used as a field.
+#[starlark_module]
+fn structs_members(globals: &mut GlobalsBuilder) {
+    /// Convert the struct to a dictionary from field names to values.
+    /// Only the top level is converted, fields which are themselves structs are left as is.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// structs.to_dict(struct(host="localhost", port=80)) == {"host": "localhost", "port": 80}
+    /// # "#);
+    /// ```
+    fn to_dict<'v>(#[starlark(require = pos)] x: StructRef<'v>) -> anyhow::Result<Dict<'v>> {
+        let mut res = SmallMap::with_capacity(x.iter().len());
+        for (k, v) in x.iter() {
+            res.insert_hashed(k.to_value().get_hashed()?, v);
+        }
+        Ok(Dict::new(res))
+    }
+
+    /// Create a copy of the struct, with the values of the given fields replaced.
+    /// Every named argument must name a field the struct already has.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// structs.replace(struct(host="localhost", port=80), port=8080) == struct(host="localhost", port=8080)
+    /// # "#);
+    /// ```
+    fn replace<'v>(
+        #[starlark(require = pos)] x: StructRef<'v>,
+        #[starlark(kwargs)] kwargs: SmallMap<StringValue<'v>, Value<'v>>,
+    ) -> anyhow::Result<Struct<'v>> {
+        let mut fields: SmallMap<StringValue<'v>, Value<'v>> = x.iter().collect();
+        for (k, v) in kwargs.into_iter_hashed() {
+            match fields.get_mut_hashed(k.as_ref()) {
+                Some(x) => *x = v,
+                None => return Err(StructError::UnknownField(k.key().as_str().to_owned()).into()),
+            }
+        }
+        Ok(Struct::new(fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_structs() {
+        assert::pass(
+            r#"
+s = struct(host = "localhost", port = 80, inner = struct(a = 1))
+assert_eq(structs.to_dict(s), {"host": "localhost", "port": 80, "inner": struct(a = 1)})
+assert_eq(structs.to_dict(struct()), {})
+assert_eq(structs.replace(s, port = 8080), struct(host = "localhost", port = 8080, inner = struct(a = 1)))
+assert_eq(structs.replace(s), s)
+assert_eq(dir(s), ["host", "inner", "port"])
+"#,
+        );
+        assert::fail(
+            "structs.replace(struct(a = 1), b = 2)",
+            "struct has no field `b`",
+        );
+    }
+
+    #[test]
+    fn test_any_field_name() {
+        assert::pass(
+            r#"
+s = struct(replace = 1, to_dict = 2)
+assert_eq(s.replace, 1)
+assert_eq(s.to_dict, 2)
+assert_eq(dir(s), ["replace", "to_dict"])
+assert_eq(structs.replace(s, replace = 3).replace, 3)
+"#,
+        );
+        assert::is_true("not hasattr(struct(a = 1), 'replace')");
+    }
+}
@@ -34,6 +34,7 @@ use crate::codemap::Pos;
 use crate::codemap::Span;
 use crate::codemap::Spanned;
 use crate::syntax::lexer::TokenInt;
+use crate::syntax::node_id::AstNodeIds;
 use crate::syntax::Dialect;
 
 /// Payload types attached to AST nodes.
@@ -81,6 +82,7 @@ pub(crate) type AstString = Spanned<String>;
 pub(crate) type AstParameter = AstParameterP<AstNoPayload>;
 pub(crate) type AstInt = Spanned<TokenInt>;
 pub(crate) type AstFloat = Spanned<f64>;
+pub(crate) type AstBytes = Spanned<Vec<u8>>;
 pub(crate) type AstStmt = AstStmtP<AstNoPayload>;
 
 // We don't care _that_ much about the size of these structures,
@@ -110,6 +112,10 @@ pub struct AstModule {
     pub(crate) codemap: CodeMap,
     pub(crate) statement: AstStmt,
     pub(crate) dialect: Dialect,
+    #[derivative(Debug = "ignore")]
+    pub(crate) node_ids: AstNodeIds,
+    /// Whether this is one chunk of an [`AstModuleStream`](crate::syntax::AstModuleStream).
+    pub(crate) streamed: bool,
 }
 
 impl AstModule {
@@ -170,6 +176,7 @@ pub(crate) enum AstLiteral {
     Int(AstInt),
     Float(AstFloat),
     String(AstString),
+    Bytes(AstBytes),
 }
 
 #[derive(Debug)]
@@ -215,6 +222,8 @@ pub(crate) enum ExprP<P: AstPayload> {
     If(Box<(AstExprP<P>, AstExprP<P>, AstExprP<P>)>), // Order: condition, v1, v2 <=> v1 if condition else v2
     List(Vec<AstExprP<P>>),
     Dict(Vec<(AstExprP<P>, AstExprP<P>)>),
+    /// Only when [`Dialect::enable_set_literals`](crate::syntax::Dialect::enable_set_literals).
+    Set(Vec<AstExprP<P>>),
     ListComprehension(Box<AstExprP<P>>, Box<ForClauseP<P>>, Vec<ClauseP<P>>),
     DictComprehension(
         Box<(AstExprP<P>, AstExprP<P>)>,
@@ -334,6 +343,8 @@ pub(crate) enum StmtP<P: AstPayload> {
     Expression(AstExprP<P>),
     // LHS : TYPE = RHS for the fields
     Assign(AstAssignP<P>, Box<(Option<AstExprP<P>>, AstExprP<P>)>),
+    // LHS : TYPE, declaring the type of a variable without assigning it
+    Declare(AstAssignIdentP<P>, Box<AstExprP<P>>),
     AssignModify(AstAssignP<P>, AssignOp, Box<AstExprP<P>>),
     Statements(Vec<AstStmtP<P>>),
     If(AstExprP<P>, Box<AstStmtP<P>>),
@@ -462,6 +473,10 @@ impl Display for AstLiteral {
             AstLiteral::Int(i) => write!(f, "{}", &i.node),
             AstLiteral::Float(n) => write!(f, "{}", &n.node),
             AstLiteral::String(s) => fmt_string_literal(f, &s.node),
+            AstLiteral::Bytes(s) => {
+                f.write_str("b")?;
+                fmt_string_literal(f, &String::from_utf8_lossy(&s.node))
+            }
         }
     }
 }
@@ -535,6 +550,11 @@ impl Display for Expr {
                 comma_separated_fmt(f, v, |x, f| write!(f, "{}: {}", x.0.node, x.1.node), false)?;
                 f.write_str("}")
             }
+            Expr::Set(v) => {
+                f.write_str("{")?;
+                comma_separated_fmt(f, v, |x, f| write!(f, "{}", x.node), false)?;
+                f.write_str("}")
+            }
             Expr::ListComprehension(e, for_, c) => {
                 write!(f, "[{}", e.node)?;
                 write!(f, "{}", for_)?;
@@ -644,6 +664,7 @@ impl Stmt {
                 }
                 writeln!(f, "= {}", r.node)
             }
+            Stmt::Declare(l, ty) => writeln!(f, "{}{} : {}", tab, l.node, ty.node),
             Stmt::AssignModify(l, op, r) => writeln!(f, "{}{}{}{}", tab, l.node, op, r.node),
             Stmt::Statements(v) => {
                 for s in v {
@@ -15,6 +15,8 @@
  * limitations under the License.
  */
 
+use std::mem;
+
 use dupe::Dupe;
 use thiserror::Error;
 
@@ -25,6 +27,12 @@ use crate::codemap::Spanned;
 use crate::errors::Diagnostic;
 use crate::syntax::ast::Visibility;
 
+/// The message of a parse error for code which would be accepted by
+/// [`Dialect::Extended`], wrapping the original message.
+#[derive(Error, Debug)]
+#[error("{0:#} (hint: this is allowed in the extended dialect)")]
+pub(crate) struct ExtendedDialectHint(anyhow::Error);
+
 #[derive(Error, Debug)]
 pub(crate) enum DialectError {
     #[error("`def` is not allowed in this dialect")]
@@ -37,6 +45,10 @@ pub(crate) enum DialectError {
     KeywordOnlyArguments,
     #[error("type annotations are not allowed in this dialect")]
     Types,
+    #[error("set literals are not allowed in this dialect")]
+    SetLiterals,
+    #[error("bytes literals are not allowed in this dialect")]
+    BytesLiterals,
 }
 
 /// How to handle type annotations in Starlark.
@@ -78,6 +90,19 @@ pub struct Dialect {
     /// Are `for`, `if` and other statements allowed at the top level.
     /// Only enabled in [`Extended`](Dialect::Extended).
     pub enable_top_level_stmt: bool,
+    /// Is the value of an expression statement at the end of the module kept as the value of
+    /// the module, separately from its exports, for calculator and script use cases.
+    /// See [`Module::value`](crate::environment::Module::value).
+    /// Not enabled in either [`Standard`](Dialect::Standard) or [`Extended`](Dialect::Extended).
+    pub enable_module_value: bool,
+    /// Are set literals such as `{1, 2}` permitted, creating values of the type added by
+    /// [`LibraryExtension::Set`](crate::environment::LibraryExtension::Set).
+    /// Not enabled in either [`Standard`](Dialect::Standard) or [`Extended`](Dialect::Extended).
+    pub enable_set_literals: bool,
+    /// Are bytes literals such as `b"abc"` permitted, creating values of the type added by
+    /// [`LibraryExtension::Bytes`](crate::environment::LibraryExtension::Bytes).
+    /// Not enabled in either [`Standard`](Dialect::Standard) or [`Extended`](Dialect::Extended).
+    pub enable_bytes_literals: bool,
 }
 
 // These are morally enumerations, so give them enumeration-like names
@@ -94,6 +119,9 @@ impl Dialect {
         enable_tabs: true,
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
+        enable_module_value: false,
+        enable_set_literals: false,
+        enable_bytes_literals: false,
     };
 
     /// A superset of [`Standard`](Dialect::Standard), including extra features (types, top-level statements etc).
@@ -106,9 +134,22 @@ impl Dialect {
         enable_tabs: true,
         enable_load_reexport: true,
         enable_top_level_stmt: true,
+        enable_module_value: false,
+        enable_set_literals: false,
+        enable_bytes_literals: false,
     };
 }
 
+impl ExtendedDialectHint {
+    /// Add the hint to the message of `err`, an error from parsing.
+    pub(crate) fn add(err: anyhow::Error) -> anyhow::Error {
+        Diagnostic::modify(err, |d| {
+            let message = mem::replace(&mut d.message, anyhow::anyhow!(""));
+            d.message = ExtendedDialectHint(message).into();
+        })
+    }
+}
+
 fn err<T>(codemap: &CodeMap, span: Span, err: DialectError) -> anyhow::Result<T> {
     Err(Diagnostic::new(err, span, codemap))
 }
@@ -153,6 +194,30 @@ impl Dialect {
         }
     }
 
+    pub(crate) fn check_set_literal<T>(
+        &self,
+        codemap: &CodeMap,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_set_literals {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::SetLiterals)
+        }
+    }
+
+    pub(crate) fn check_bytes_literal<T>(
+        &self,
+        codemap: &CodeMap,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_bytes_literals {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::BytesLiterals)
+        }
+    }
+
     pub(crate) fn check_type<T>(
         &self,
         codemap: &CodeMap,
@@ -165,6 +230,29 @@ impl Dialect {
         }
     }
 
+    /// This dialect with the parsing features of [`Extended`](Dialect::Extended) turned on,
+    /// or [`None`] if it already has them all.
+    pub(crate) fn with_extended_features(&self) -> Option<Dialect> {
+        let res = Dialect {
+            enable_def: true,
+            enable_lambda: true,
+            enable_load: true,
+            enable_keyword_only_arguments: true,
+            enable_types: match self.enable_types {
+                DialectTypes::Disable => DialectTypes::ParseOnly,
+                x => x,
+            },
+            enable_tabs: true,
+            enable_top_level_stmt: true,
+            ..self.clone()
+        };
+        if &res == self {
+            None
+        } else {
+            Some(res)
+        }
+    }
+
     pub(crate) fn load_visibility(&self) -> Visibility {
         if self.enable_load_reexport {
             Visibility::Public
@@ -15,11 +15,16 @@
  * limitations under the License.
  */
 
+use std::path::Path;
+
 use gazebo::prelude::*;
 
 use crate::assert;
 use crate::assert::Assert;
+use crate::errors::EvalMessage;
 use crate::syntax::ast::Stmt;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
 
 #[test]
 fn test_empty() {
@@ -219,6 +224,46 @@ fail(2)
     }
 }
 
+#[test]
+fn test_loads() {
+    let ast = assert::parse_ast(
+        r#"
+load("a.bzl", "x", y = "z")
+def f():
+    pass
+load("b.bzl", "w")
+"#,
+    );
+    let loads = ast.loads();
+    let loads = loads.map(|load| {
+        let loaded = load.loaded.map(|x| {
+            format!(
+                "{} ({}) = {} ({})",
+                x.local, x.local_span, x.symbol, x.symbol_span
+            )
+        });
+        (load.module_id, load.span.to_string(), loaded)
+    });
+    assert_eq!(
+        loads,
+        vec![
+            (
+                "a.bzl",
+                "assert.bzl:2:6-13".to_owned(),
+                vec![
+                    "x (assert.bzl:2:15-18) = x (assert.bzl:2:15-18)".to_owned(),
+                    "y (assert.bzl:2:20-21) = z (assert.bzl:2:24-27)".to_owned(),
+                ]
+            ),
+            (
+                "b.bzl",
+                "assert.bzl:5:6-13".to_owned(),
+                vec!["w (assert.bzl:5:15-18) = w (assert.bzl:5:15-18)".to_owned()]
+            ),
+        ]
+    );
+}
+
 #[test]
 fn test_comprehension() {
     assert_eq!(
@@ -231,6 +276,16 @@ fn test_comprehension() {
     );
 }
 
+#[test]
+fn test_set_literal() {
+    let mut a = Assert::new();
+    a.parse_fail("x = !{1, 2}!");
+    a.dialect_set(|x| x.enable_set_literals = true);
+    assert_eq!(a.parse("x = {1, y + 2,}"), "x = {1, (y + 2)}\n");
+    assert_eq!(a.parse("x = {1: 2}"), "x = {1: 2}\n");
+    assert_eq!(a.parse("x = {}"), "x = {}\n");
+}
+
 #[test]
 fn test_lambda() {
     assert_eq!(
@@ -296,8 +351,39 @@ fn test_op_associativity() {
     assert::fail("0 <= 1 < 2", "Parse error");
 }
 
+#[test]
+fn test_adjacent_string_literals() {
+    let err = assert::parse_fail("srcs = [\"a.c\" !\"b.c\"!]");
+    assert!(
+        err.to_string().contains("did you forget a comma?"),
+        "{}",
+        err
+    );
+    let err = assert::parse_fail("x = 1 !\"b\"!");
+    assert!(!err.to_string().contains("comma"), "{}", err);
+}
+
 #[test]
 fn test_bad_assignment() {
     assert::parse_fail("[!x or y!] = 1");
     assert::parse_fail("![x]! += 1");
 }
+
+#[test]
+fn test_extended_dialect_hint() {
+    let parse = |x: &str| {
+        let err = AstModule::parse("filename", x.to_owned(), &Dialect::Standard).unwrap_err();
+        EvalMessage::from_anyhow(Path::new("filename"), &err)
+    };
+    let x = parse("def f(x: int.type):\n    pass\n");
+    assert!(x.extended_dialect);
+    assert_eq!(
+        x.description,
+        "type annotations are not allowed in this dialect (hint: this is allowed in the extended dialect)"
+    );
+    assert!(parse("for x in []:\n    pass\n").extended_dialect);
+    assert!(parse("def f(*, x):\n    pass\n").extended_dialect);
+    let x = parse("x = (");
+    assert!(!x.extended_dialect);
+    assert!(!x.description.contains("hint"));
+}
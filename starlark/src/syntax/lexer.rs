@@ -380,6 +380,64 @@ impl<'a> Lexer<'a> {
         )
     }
 
+    /// Turn the string literal `string` just lexed into a bytes literal.
+    fn bytes(&self, string: Lexeme, triple: bool, raw: bool) -> Lexeme {
+        let (start, token, end) = string?;
+        match token {
+            Token::String(x) if raw => return Ok((start, Token::Bytes(x.into_bytes()), end)),
+            Token::String(_) => {}
+            _ => unreachable!("Lexer only produces strings from quotes"),
+        }
+        // Escapes like `\xff` are bytes rather than characters, so start again from the
+        // source, between the prefix and the quotes
+        let quotes = if triple { 3 } else { 1 };
+        let contents_start =
+            start + self.lexer.source()[start..].find(['\'', '"']).unwrap() + quotes;
+        let source = &self.lexer.source()[contents_start..end - quotes];
+        let mut res = Vec::with_capacity(source.len());
+        let mut it = CursorChars::new_offset(source, 0);
+        while let Some(c) = it.next() {
+            match c {
+                '\r' => {}
+                '\\' => {
+                    let pos = it.pos();
+                    let byte = match it.next() {
+                        Some('x') => Self::escape_char(&mut it, 2, 2, 16).ok(),
+                        Some(c @ '0'..='7') => {
+                            it.unnext(c);
+                            Self::escape_char(&mut it, 1, 3, 8).ok()
+                        }
+                        Some(c) => {
+                            it.unnext(c);
+                            let mut buf = String::new();
+                            // Already checked when lexing it as a string
+                            if Self::escape(&mut it, &mut buf).is_ok() {
+                                res.extend_from_slice(buf.as_bytes());
+                                continue;
+                            }
+                            None
+                        }
+                        None => None,
+                    };
+                    match byte.and_then(|x| u8::try_from(x as u32).ok()) {
+                        Some(x) => res.push(x),
+                        None => {
+                            return self.err_span(
+                                LexemeError::InvalidEscapeSequence(
+                                    source[pos..it.pos()].to_owned(),
+                                ),
+                                contents_start + pos - 1,
+                                contents_start + it.pos(),
+                            );
+                        }
+                    }
+                }
+                c => res.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        Ok((start, Token::Bytes(res), end))
+    }
+
     fn int(&self, s: &str, radix: u32) -> Lexeme {
         let span = self.lexer.span();
         match i32::from_str_radix(s, radix) {
@@ -459,10 +517,13 @@ impl<'a> Lexer<'a> {
                         }
                         Token::Int(..) => unreachable!("Lexer does not produce Int tokens"),
                         Token::RawDoubleQuote => {
-                            let raw = self.lexer.span().len() == 2;
-                            if self.lexer.remainder().starts_with("\"\"") {
+                            let prefix = self.lexer.slice();
+                            let raw = prefix.contains('r');
+                            let bytes = prefix.contains('b');
+                            let triple = self.lexer.remainder().starts_with("\"\"");
+                            let res = if triple {
                                 let mut qs = 0;
-                                Some(self.string(true, raw, |c| {
+                                self.string(true, raw, |c| {
                                     if c == '\"' {
                                         qs += 1;
                                         qs == 3
@@ -470,16 +531,24 @@ impl<'a> Lexer<'a> {
                                         qs = 0;
                                         false
                                     }
-                                }))
+                                })
                             } else {
-                                Some(self.string(false, raw, |c| c == '\"'))
-                            }
+                                self.string(false, raw, |c| c == '\"')
+                            };
+                            Some(if bytes {
+                                self.bytes(res, triple, raw)
+                            } else {
+                                res
+                            })
                         }
                         Token::RawSingleQuote => {
-                            let raw = self.lexer.span().len() == 2;
-                            if self.lexer.remainder().starts_with("''") {
+                            let prefix = self.lexer.slice();
+                            let raw = prefix.contains('r');
+                            let bytes = prefix.contains('b');
+                            let triple = self.lexer.remainder().starts_with("''");
+                            let res = if triple {
                                 let mut qs = 0;
-                                Some(self.string(true, raw, |c| {
+                                self.string(true, raw, |c| {
                                     if c == '\'' {
                                         qs += 1;
                                         qs == 3
@@ -487,10 +556,15 @@ impl<'a> Lexer<'a> {
                                         qs = 0;
                                         false
                                     }
-                                }))
+                                })
                             } else {
-                                Some(self.string(false, raw, |c| c == '\''))
-                            }
+                                self.string(false, raw, |c| c == '\'')
+                            };
+                            Some(if bytes {
+                                self.bytes(res, triple, raw)
+                            } else {
+                                res
+                            })
                         }
                         Token::OpeningCurly | Token::OpeningRound | Token::OpeningSquare => {
                             self.parens += 1;
@@ -537,9 +611,15 @@ pub enum Token {
     // things ourselves
     #[token("'")]
     #[token("r'")]
+    #[token("b'")]
+    #[token("br'")]
+    #[token("rb'")]
     RawSingleQuote,
     #[token("\"")]
     #[token("r\"")]
+    #[token("b\"")]
+    #[token("br\"")]
+    #[token("rb\"")]
     RawDoubleQuote,
 
     #[regex(
@@ -569,6 +649,7 @@ pub enum Token {
     Float(f64), // A float literal (3.14, .3, 1e6, 0.)
 
     String(String), // A string literal
+    Bytes(Vec<u8>), // A bytes literal
 
     // Keywords
     #[token("and")]
@@ -703,6 +784,10 @@ impl Token {
                 // Reuse the StarlarkValue implementation since it's close to hand.
                 serde_json::to_string(x).unwrap()
             }
+            Token::Bytes(x) => format!(
+                "b{}",
+                serde_json::to_string(&String::from_utf8_lossy(x)).unwrap()
+            ),
             _ => {
                 let s = self.to_string();
                 // Out display is often: keyword 'lambda'
@@ -792,6 +877,7 @@ impl Display for Token {
             Token::RawBinInt => write!(f, "binary integer literal"),
             Token::Float(n) => write!(f, "float literal '{}'", n),
             Token::String(s) => write!(f, "string literal '{}'", s),
+            Token::Bytes(s) => write!(f, "bytes literal '{}'", String::from_utf8_lossy(s)),
             Token::RawSingleQuote => write!(f, "starting '"),
             Token::RawDoubleQuote => write!(f, "starting \""),
             Token::Tabs => Ok(()),
@@ -93,6 +93,11 @@ fn test_comment() {
     assert_eq!(assert::lex("a # a comment\n"), "a \n \n");
     // But it should not eat everything
     assert_eq!(assert::lex("[\n# a comment\n]"), "[ ] \n");
+    // So a shebang line lets scripts be executed directly
+    assert_eq!(
+        assert::lex("#!/usr/bin/env starlark\nprint(1)\n"),
+        "print ( 1 ) \n \n"
+    );
 }
 
 #[test]
@@ -144,6 +149,15 @@ fn test_string_escape() {
     assert::parse_fail("test 'more !\\x0!");
 }
 
+#[test]
+fn test_bytes_lit() {
+    assert_eq!(assert::lex("b'abc'"), "b\"abc\" \n");
+    assert_eq!(assert::lex("b\"a\\x41\\101\\n\""), "b\"aAA\\n\" \n");
+    assert_eq!(assert::lex("rb'\\n' br'x'"), "b\"\\\\n\" b\"x\" \n");
+    // Only a prefix directly before the quote makes a bytes literal
+    assert_eq!(assert::lex("ab'x'"), "ab \"x\" \n");
+}
+
 #[test]
 fn test_simple_example() {
     assert_eq!(
@@ -20,7 +20,16 @@
 pub use ast::AstModule;
 pub use dialect::Dialect;
 pub use dialect::DialectTypes;
+pub use node_id::AstNodeId;
 pub use parser::AstLoad;
+pub use parser::AstLoadedSymbol;
+pub use stream::AstModuleStream;
+
+pub use crate::analysis::ConstArgument;
+pub use crate::analysis::ConstExpr;
+pub use crate::analysis::ConstValue;
+pub use crate::analysis::ModuleMetrics;
+pub use crate::analysis::TopLevelCall;
 
 #[cfg(test)]
 mod grammar_tests;
@@ -31,8 +40,9 @@ mod testcases;
 
 pub(crate) mod ast;
 pub(crate) mod cursors;
-mod dialect;
+pub(crate) mod dialect;
 pub(crate) mod lexer;
+pub(crate) mod node_id;
 pub(crate) mod payload_map;
 pub(crate) mod validate;
 
@@ -50,4 +60,5 @@ mod grammar {
 }
 
 pub(crate) mod parser;
+pub(crate) mod stream;
 pub(crate) mod uniplate;
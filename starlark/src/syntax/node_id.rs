@@ -0,0 +1,169 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cmp;
+
+use dupe::Dupe;
+
+use crate::codemap::FileSpan;
+use crate::codemap::Span;
+use crate::syntax::ast::AstNoPayload;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::uniplate::Visit;
+use crate::syntax::AstModule;
+
+/// Identifies a statement or expression within an [`AstModule`].
+///
+/// Ids number the nodes in the order they appear, so they are the same every time a
+/// given source is parsed. Passes can record the ids of the nodes they are interested
+/// in, and find them again later without holding references into the AST or comparing
+/// spans.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AstNodeId(u32);
+
+/// What is needed to look up a node by [`AstNodeId`], indexed by that id. Built once
+/// when the module is parsed, so lookups never walk the AST.
+#[derive(Debug, Default)]
+pub(crate) struct AstNodeIds {
+    nodes: Vec<AstNodeEntry>,
+}
+
+#[derive(Debug)]
+struct AstNodeEntry {
+    span: Span,
+    /// The id of the enclosing node, or the node itself for the root.
+    parent: u32,
+    /// The number of nodes in the subtree rooted here, including this one, so the
+    /// next sibling has id `id + size`.
+    size: u32,
+}
+
+impl AstNodeIds {
+    pub(crate) fn new(statement: &AstStmt) -> Self {
+        fn walk(x: Visit<AstNoPayload>, parent: u32, nodes: &mut Vec<AstNodeEntry>) {
+            let id = nodes.len();
+            let span = match x {
+                Visit::Stmt(x) => x.span,
+                Visit::Expr(x) => x.span,
+            };
+            nodes.push(AstNodeEntry {
+                span,
+                parent,
+                size: 0,
+            });
+            x.visit_children(|x| walk(x, id as u32, nodes));
+            nodes[id].size = (nodes.len() - id) as u32;
+        }
+
+        let mut nodes = Vec::new();
+        walk(Visit::Stmt(statement), 0, &mut nodes);
+        Self { nodes }
+    }
+
+    fn get(&self, id: AstNodeId) -> Option<&AstNodeEntry> {
+        self.nodes.get(id.0 as usize)
+    }
+
+    /// The innermost node whose span contains `span`.
+    fn containing(&self, span: Span) -> Option<AstNodeId> {
+        let contains = |id: u32| {
+            let x = self.nodes[id as usize].span;
+            x.begin() <= span.begin() && span.end() <= x.end()
+        };
+        if self.nodes.is_empty() || !contains(0) {
+            return None;
+        }
+        let mut current = 0;
+        let mut child = 1;
+        while child < current + self.nodes[current as usize].size {
+            if contains(child) {
+                current = child;
+                child += 1;
+            } else {
+                child += self.nodes[child as usize].size;
+            }
+        }
+        Some(AstNodeId(current))
+    }
+}
+
+impl AstModule {
+    /// The location of the statement or expression with the given id, or [`None`] if
+    /// the id is not from this module.
+    pub fn node_span(&self, id: AstNodeId) -> Option<FileSpan> {
+        Some(self.file_span(self.node_ids.get(id)?.span))
+    }
+
+    /// The innermost statement or expression enclosing the node `id`.
+    pub fn node_parent(&self, id: AstNodeId) -> Option<AstNodeId> {
+        let parent = self.node_ids.get(id)?.parent;
+        if id.0 == 0 {
+            None
+        } else {
+            Some(AstNodeId(parent))
+        }
+    }
+
+    /// The innermost statement or expression at the position, where `line` and `column`
+    /// are zero based.
+    pub fn node_at(&self, line: usize, column: usize) -> Option<AstNodeId> {
+        let line_span = self.codemap.line_span_opt(line)?;
+        let pos = cmp::min(line_span.begin() + column as u32, line_span.end());
+        self.node_containing(Span::new(pos, pos))
+    }
+
+    /// The innermost statement or expression whose span contains `span`.
+    pub(crate) fn node_containing(&self, span: Span) -> Option<AstNodeId> {
+        self.node_ids.containing(span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_node_ids() {
+        let modu = AstModule::parse(
+            "X",
+            "x = 1\ndef f(a):\n    return a + [2]\n".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let at = |line, column| {
+            let id = modu.node_at(line, column).unwrap();
+            (id, modu.node_span(id).unwrap().to_string())
+        };
+
+        let (two, two_span) = at(2, 16);
+        assert_eq!(two_span, "X:3:17-18");
+        let list = modu.node_parent(two).unwrap();
+        assert_eq!(modu.node_span(list).unwrap().to_string(), "X:3:16-19");
+        let plus = modu.node_parent(list).unwrap();
+        assert_eq!(at(2, 14), (plus, "X:3:12-19".to_owned()));
+        assert_eq!(at(0, 4).1, "X:1:5-6");
+
+        // Ids are assigned in order, and are the same when parsed again
+        assert!(list < two);
+        let again =
+            AstModule::parse("X", modu.codemap.source().to_owned(), &Dialect::Extended).unwrap();
+        assert_eq!(again.node_at(2, 16), Some(two));
+
+        assert_eq!(modu.node_at(10, 0), None);
+    }
+}
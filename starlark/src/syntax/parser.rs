@@ -32,9 +32,11 @@ use crate::syntax::ast::AstModule;
 use crate::syntax::ast::AstStmt;
 use crate::syntax::ast::Stmt;
 use crate::syntax::dialect::Dialect;
+use crate::syntax::dialect::ExtendedDialectHint;
 use crate::syntax::grammar::StarlarkParser;
 use crate::syntax::lexer::Lexer;
 use crate::syntax::lexer::Token;
+use crate::syntax::node_id::AstNodeIds;
 
 fn one_of(expected: &[String]) -> String {
     let mut result = String::new();
@@ -66,13 +68,27 @@ pub(crate) fn parse_error_add_span(
     let message = match &err {
         lu::ParseError::InvalidToken { .. } => "Parse error: invalid token".to_owned(),
         lu::ParseError::UnrecognizedToken {
-            token: (_x, t, ..),
+            token: (x, t, ..),
             expected,
-        } => format!(
-            "Parse error: unexpected {} here, expected {}",
-            t,
-            one_of(expected)
-        ),
+        } => {
+            let mut message = format!(
+                "Parse error: unexpected {} here, expected {}",
+                t,
+                one_of(expected)
+            );
+            // Python concatenates adjacent string literals, Starlark does not, so this is
+            // usually a missing comma in a list of strings, e.g. `["a" "b"]`.
+            if matches!(t, Token::String(_))
+                && codemap.source()[..*x]
+                    .trim_end()
+                    .ends_with(|c| c == '"' || c == '\'')
+            {
+                message.push_str(
+                    ". Adjacent string literals are not concatenated, did you forget a comma?",
+                );
+            }
+            message
+        }
         lu::ParseError::ExtraToken { token: (_x, t, ..) } => {
             format!("Parse error: extraneous token {}", t)
         }
@@ -100,17 +116,35 @@ pub(crate) fn parse_error_add_span(
 
 /// A `load` statement loading zero or more symbols from another module.
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct AstLoad<'a> {
-    /// Span where this load is written
+    /// Span where the module being loaded is written
     pub span: FileSpan,
     /// Module being loaded
     pub module_id: &'a str,
     /// Symbols loaded from that module (local ident -> source ident)
     pub symbols: SmallMap<&'a str, &'a str>,
+    /// Symbols loaded from that module, in the order they are written
+    pub loaded: Vec<AstLoadedSymbol<'a>>,
+}
+
+/// A symbol loaded by a `load` statement, as in `load("module", local = "symbol")`,
+/// or `load("module", "symbol")` where the local name is the same as the symbol.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AstLoadedSymbol<'a> {
+    /// The name the symbol is bound to in this module
+    pub local: &'a str,
+    /// Span where the local name is written, the same as `symbol_span` if there is no alias
+    pub local_span: FileSpan,
+    /// The name of the symbol in the module being loaded
+    pub symbol: &'a str,
+    /// Span where the symbol is written, including the quotes
+    pub symbol_span: FileSpan,
 }
 
 impl AstModule {
-    fn create(
+    pub(crate) fn create(
         codemap: CodeMap,
         statement: AstStmt,
         dialect: &Dialect,
@@ -118,8 +152,10 @@ impl AstModule {
         Stmt::validate(&codemap, &statement, dialect)?;
         Ok(AstModule {
             codemap,
+            node_ids: AstNodeIds::new(&statement),
             statement,
             dialect: dialect.clone(),
+            streamed: false,
         })
     }
 
@@ -143,8 +179,23 @@ impl AstModule {
     /// let err: Diagnostic = err.downcast::<Diagnostic>().unwrap();
     /// assert_eq!(err.span.unwrap().to_string(), "filename:2:11");
     /// ```
+    ///
+    /// If the module is only invalid because of a feature missing from the dialect, but
+    /// present in [`Dialect::Extended`], the error message says so, and
+    /// [`EvalMessage::extended_dialect`](crate::errors::EvalMessage::extended_dialect) is set.
     pub fn parse(filename: &str, content: String, dialect: &Dialect) -> anyhow::Result<Self> {
         let codemap = CodeMap::new(filename.to_owned(), content);
+        Self::parse_codemap(codemap.dupe(), dialect).map_err(|e| {
+            match dialect.with_extended_features() {
+                Some(extended) if Self::parse_codemap(codemap, &extended).is_ok() => {
+                    ExtendedDialectHint::add(e)
+                }
+                _ => e,
+            }
+        })
+    }
+
+    fn parse_codemap(codemap: CodeMap, dialect: &Dialect) -> anyhow::Result<Self> {
         let lexer = Lexer::new(codemap.source(), dialect, codemap.dupe());
         match StarlarkParser::new().parse(&codemap, dialect, lexer) {
             Ok(v) => Ok(AstModule::create(codemap, v, dialect)?),
@@ -170,6 +221,16 @@ impl AstModule {
                         .iter()
                         .map(|(name, sym)| (name.node.0.as_str(), sym.node.as_str()))
                         .collect(),
+                    loaded: load
+                        .args
+                        .iter()
+                        .map(|(name, sym)| AstLoadedSymbol {
+                            local: name.node.0.as_str(),
+                            local_span: codemap.file_span(name.span),
+                            symbol: sym.node.as_str(),
+                            symbol_span: codemap.file_span(sym.span),
+                        })
+                        .collect(),
                 }),
                 Stmt::Statements(stmts) => {
                     for s in stmts {
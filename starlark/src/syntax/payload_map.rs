@@ -71,6 +71,9 @@ impl<A: AstPayload> StmtP<A> {
                     Box::new((ty.map(|ty| ty.into_map_payload(f)), rhs.into_map_payload(f))),
                 )
             }
+            StmtP::Declare(lhs, ty) => {
+                StmtP::Declare(lhs.into_map_payload(f), Box::new(ty.into_map_payload(f)))
+            }
             StmtP::AssignModify(lhs, op, rhs) => StmtP::AssignModify(
                 lhs.into_map_payload(f),
                 op,
@@ -175,6 +178,7 @@ impl<A: AstPayload> ExprP<A> {
             ExprP::Dict(kvs) => {
                 ExprP::Dict(kvs.into_map(|(k, v)| (k.into_map_payload(f), v.into_map_payload(f))))
             }
+            ExprP::Set(es) => ExprP::Set(es.into_map(|e| e.into_map_payload(f))),
             ExprP::ListComprehension(e, c0, cs) => ExprP::ListComprehension(
                 Box::new(e.into_map_payload(f)),
                 Box::new(c0.into_map_payload(f)),
@@ -0,0 +1,276 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Parsing a file a few top-level statements at a time, see [`AstModuleStream`].
+
+use std::io::BufRead;
+
+use dupe::Dupe;
+
+use crate::codemap::CodeMap;
+use crate::syntax::ast::AstModule;
+use crate::syntax::dialect::Dialect;
+use crate::syntax::grammar::StarlarkParser;
+use crate::syntax::lexer::Lexer;
+use crate::syntax::lexer::Token;
+use crate::syntax::parser::parse_error_add_span;
+
+/// The number of lines to read before looking for a statement to split at.
+const CHUNK_LINES: usize = 1000;
+
+/// An iterator over the top-level statements of a file, parsed a chunk at a time,
+/// created by [`AstModule::parse_stream`].
+///
+/// Each [`AstModule`] holds a run of complete top-level statements, and can be passed
+/// to [`Evaluator::eval_module`](crate::eval::Evaluator::eval_module) in turn with the
+/// same [`Module`](crate::environment::Module). Only the chunk being parsed is held in
+/// memory, so machine-generated files with hundreds of thousands of statements can be
+/// evaluated without building an AST for the whole file.
+///
+/// Errors report the correct line in the file. A chunk may use names defined by later
+/// chunks, such as a function calling another function defined further down, so a name
+/// which is never defined is only reported when it is read, rather than before the
+/// module runs. After an error the iterator is finished.
+pub struct AstModuleStream<R: BufRead> {
+    filename: String,
+    dialect: Dialect,
+    reader: R,
+    /// Lines read but not yet parsed.
+    buffer: String,
+    /// The number of lines in `buffer`.
+    buffer_lines: usize,
+    /// The line in the file `buffer` starts at.
+    first_line: usize,
+    /// Read more lines before looking for a split once `buffer` has this many.
+    want_lines: usize,
+    done: bool,
+}
+
+impl AstModule {
+    /// Parse a Starlark module from `reader` a few top-level statements at a time,
+    /// trading some error checking for bounded memory use. For details see [`AstModuleStream`].
+    pub fn parse_stream<R: BufRead>(
+        filename: &str,
+        reader: R,
+        dialect: &Dialect,
+    ) -> AstModuleStream<R> {
+        AstModuleStream {
+            filename: filename.to_owned(),
+            dialect: dialect.clone(),
+            reader,
+            buffer: String::new(),
+            buffer_lines: 0,
+            first_line: 0,
+            want_lines: CHUNK_LINES,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> AstModuleStream<R> {
+    /// The byte offset of the last top-level statement in `buffer` which doesn't start
+    /// it, if any. Everything before that offset is complete statements.
+    fn last_split(&self) -> Option<usize> {
+        let codemap = CodeMap::default();
+        let mut indent = 0;
+        let mut line_start = true;
+        let mut res = None;
+        for lexeme in Lexer::new(&self.buffer, &self.dialect, codemap) {
+            // The last statement is often incomplete, so stop at the first error
+            let Ok((start, token, _)) = lexeme else {
+                break;
+            };
+            match token {
+                Token::Indent => indent += 1,
+                Token::Dedent => indent -= 1,
+                Token::Newline => line_start = true,
+                Token::Else | Token::Elif => line_start = false,
+                _ => {
+                    if line_start && indent == 0 && start > 0 {
+                        res = Some(start);
+                    }
+                    line_start = false;
+                }
+            }
+        }
+        res
+    }
+
+    /// Parse the first `len` bytes of `buffer`, and remove them.
+    fn parse(&mut self, len: usize) -> anyhow::Result<AstModule> {
+        let rest = self.buffer.split_off(len);
+        let source = std::mem::replace(&mut self.buffer, rest);
+        let lines = source.matches('\n').count();
+        let codemap = CodeMap::new_at_line(self.filename.clone(), source, self.first_line);
+        self.first_line += lines;
+        self.buffer_lines -= lines;
+        self.want_lines = CHUNK_LINES;
+
+        let lexer = Lexer::new(codemap.source(), &self.dialect, codemap.dupe());
+        match StarlarkParser::new().parse(&codemap, &self.dialect, lexer) {
+            Ok(v) => Ok(AstModule {
+                streamed: true,
+                ..AstModule::create(codemap, v, &self.dialect)?
+            }),
+            Err(p) => Err(parse_error_add_span(p, codemap.source().len(), &codemap)),
+        }
+    }
+
+    fn next_chunk(&mut self) -> anyhow::Result<Option<AstModule>> {
+        loop {
+            let read = self.reader.read_line(&mut self.buffer)?;
+            if read == 0 {
+                self.done = true;
+                return if self.buffer.trim().is_empty() {
+                    Ok(None)
+                } else {
+                    self.parse(self.buffer.len()).map(Some)
+                };
+            }
+            self.buffer_lines += 1;
+            if self.buffer_lines >= self.want_lines {
+                match self.last_split() {
+                    Some(split) => return self.parse(split).map(Some),
+                    // A single huge statement, read more before trying again
+                    None => self.want_lines *= 2,
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for AstModuleStream<R> {
+    type Item = anyhow::Result<AstModule>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.next_chunk();
+        if res.is_err() {
+            self.done = true;
+        }
+        res.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::errors::Diagnostic;
+    use crate::eval::Evaluator;
+    use crate::syntax::stream::CHUNK_LINES;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn chunks(code: &str) -> Vec<AstModule> {
+        AstModule::parse_stream("x.star", Cursor::new(code), &Dialect::Extended)
+            .collect::<anyhow::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_split_statements() {
+        let mut code = String::new();
+        for i in 0..2500 {
+            code.push_str(&format!("x{} = [\n    {},\n]\n", i, i));
+        }
+        let asts = chunks(&code);
+        assert!(asts.len() > 1);
+        let mut lines = 0;
+        for ast in &asts {
+            assert_eq!(
+                ast.codemap.resolve_span(ast.statement.span).begin_line,
+                lines
+            );
+            lines += ast.codemap.source().matches('\n').count();
+        }
+        assert_eq!(lines, 7500);
+    }
+
+    #[test]
+    fn test_blocks_not_split() {
+        let mut code = String::new();
+        for i in 0..1000 {
+            code.push_str(&format!("if x{}:\n    pass\nelif y:\n    pass\nelse:\n", i));
+            code.push_str("    pass\n");
+        }
+        // Every chunk must parse on its own, so no chunk starts with `elif` or `else`
+        assert!(chunks(&code).len() > 1);
+        assert!(chunks("").is_empty());
+    }
+
+    #[test]
+    fn test_error_line() {
+        let mut code = "x = 1\n".repeat(CHUNK_LINES * 2);
+        code.push_str("y = (\n");
+        let err = AstModule::parse_stream("x.star", Cursor::new(code), &Dialect::Extended)
+            .find_map(|x| x.err())
+            .unwrap();
+        let err = err.downcast::<Diagnostic>().unwrap();
+        assert_eq!(
+            err.span.unwrap().resolve_span().begin_line,
+            CHUNK_LINES * 2 + 1
+        );
+    }
+
+    #[test]
+    fn test_eval_chunks() {
+        let mut code = "xs = []\n".to_owned();
+        code.push_str(&"xs.append(len(xs))\n".repeat(CHUNK_LINES * 3));
+        code.push_str("def f():\n    return len(xs)\nn = f()\n");
+
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        for ast in AstModule::parse_stream("x.star", Cursor::new(code), &Dialect::Extended) {
+            eval.eval_module(ast.unwrap(), &Globals::standard())
+                .unwrap();
+        }
+        assert_eq!(module.get("n").unwrap().unpack_int(), Some(3000));
+    }
+
+    #[test]
+    fn test_forward_reference() {
+        let mut code = "def f():\n    return g()\n".to_owned();
+        code.push_str(&"x = 1\n".repeat(CHUNK_LINES * 2));
+        code.push_str("def g():\n    return 7\nn = f()\n");
+
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let asts = AstModule::parse_stream("x.star", Cursor::new(code), &Dialect::Extended);
+        for ast in asts {
+            eval.eval_module(ast.unwrap(), &Globals::standard())
+                .unwrap();
+        }
+        assert_eq!(module.get("n").unwrap().unpack_int(), Some(7));
+
+        // A name which is never defined fails when it is read
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let code = "def f():\n    return h()\nf()\n";
+        let ast = chunks(code).pop().unwrap();
+        let err = eval.eval_module(ast, &Globals::standard()).unwrap_err();
+        assert!(
+            err.to_string().contains("`h` referenced before assignment"),
+            "{}",
+            err
+        );
+    }
+}
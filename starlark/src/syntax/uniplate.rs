@@ -39,6 +39,15 @@ pub(crate) enum Visit<'a, P: AstPayload> {
     Expr(&'a AstExprP<P>),
 }
 
+// Derive would require `P: Copy`, which is unnecessary.
+impl<'a, P: AstPayload> Clone for Visit<'a, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, P: AstPayload> Copy for Visit<'a, P> {}
+
 pub(crate) enum VisitMut<'a, P: AstPayload> {
     Stmt(&'a mut AstStmtP<P>),
     Expr(&'a mut AstExprP<P>),
@@ -100,6 +109,7 @@ impl<P: AstPayload> StmtP<P> {
                 ty.iter().for_each(|x| f(Visit::Expr(x)));
                 f(Visit::Expr(rhs));
             }
+            StmtP::Declare(_, ty) => f(Visit::Expr(ty)),
             StmtP::AssignModify(lhs, _, rhs) => {
                 lhs.visit_expr(|x| f(Visit::Expr(x)));
                 f(Visit::Expr(rhs));
@@ -154,6 +164,7 @@ impl<P: AstPayload> StmtP<P> {
                 ty.iter_mut().for_each(|x| f(VisitMut::Expr(x)));
                 f(VisitMut::Expr(rhs));
             }
+            StmtP::Declare(_, ty) => f(VisitMut::Expr(ty)),
             StmtP::AssignModify(lhs, _, rhs) => {
                 lhs.visit_expr_mut(|x| f(VisitMut::Expr(x)));
                 f(VisitMut::Expr(rhs));
@@ -303,7 +314,7 @@ impl<P: AstPayload> ExprP<P> {
                 f(b);
                 f(c);
             }
-            ExprP::List(x) => x.iter().for_each(|x| f(x)),
+            ExprP::List(x) | ExprP::Set(x) => x.iter().for_each(|x| f(x)),
             ExprP::Dict(x) => x.iter().for_each(|(x, y)| {
                 f(x);
                 f(y);
@@ -365,7 +376,7 @@ impl<P: AstPayload> ExprP<P> {
                 f(b);
                 f(c);
             }
-            ExprP::List(x) => x.iter_mut().for_each(|x| f(x)),
+            ExprP::List(x) | ExprP::Set(x) => x.iter_mut().for_each(|x| f(x)),
             ExprP::Dict(x) => x.iter_mut().for_each(|(x, y)| {
                 f(x);
                 f(y);
@@ -67,6 +67,8 @@ enum ValidateError {
     TypeAnnotationOnAssignOp,
     #[error("type annotations not allowed on multiple assignments")]
     TypeAnnotationOnTupleAssign,
+    #[error("type annotations without a value are only allowed on a single variable")]
+    TypeAnnotationOnInvalidDeclaration,
 }
 
 #[derive(Eq, PartialEq, PartialOrd, Ord)]
@@ -332,6 +334,25 @@ impl Stmt {
         })
     }
 
+    /// Validate `lhs: ty`, declaring the type of a variable without assigning it.
+    pub(crate) fn check_declaration(
+        codemap: &CodeMap,
+        lhs: AstExpr,
+        ty: AstExpr,
+    ) -> anyhow::Result<Stmt> {
+        match lhs.node {
+            Expr::Identifier(x, ()) => Ok(Stmt::Declare(
+                x.into_map(|s| AssignIdentP(s, ())),
+                Box::new(ty),
+            )),
+            _ => Err(Diagnostic::new(
+                ValidateError::TypeAnnotationOnInvalidDeclaration,
+                lhs.span,
+                codemap,
+            )),
+        }
+    }
+
     /// Validate all statements only occur where they are allowed to.
     pub(crate) fn validate(
         codemap: &CodeMap,
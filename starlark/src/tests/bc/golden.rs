@@ -27,17 +27,7 @@ use crate::eval::compiler::def::FrozenDef;
 const REGENERATE_VAR_NAME: &str = "STARLARK_RUST_REGENERATE_BC_TESTS";
 
 #[allow(clippy::write_literal)] // We mark generated files as generated, but not this file.
-fn make_golden(program: &str) -> String {
-    let program = program.trim();
-
-    let mut a = Assert::new();
-    let def = a
-        .module("instrs.star", program)
-        .get("test")
-        .unwrap()
-        .downcast::<FrozenDef>()
-        .unwrap();
-
+fn golden_header(program: &str) -> String {
     let mut golden = String::new();
     writeln!(golden, "# {at}generated", at = "@").unwrap();
     writeln!(golden, "# To regenerate, run:").unwrap();
@@ -53,17 +43,54 @@ fn make_golden(program: &str) -> String {
     writeln!(golden).unwrap();
     writeln!(golden, "# Bytecode:").unwrap();
     writeln!(golden).unwrap();
+    golden
+}
+
+fn make_golden(program: &str) -> String {
+    let program = program.trim();
+
+    let mut a = Assert::new();
+    let def = a
+        .module("instrs.star", program)
+        .get("test")
+        .unwrap()
+        .downcast::<FrozenDef>()
+        .unwrap();
+
+    let mut golden = golden_header(program);
     writeln!(golden, "{}", def.bc().dump_debug().trim()).unwrap();
     golden
 }
 
+fn make_module_golden(program: &str) -> String {
+    let program = program.trim();
+
+    let mut a = Assert::new();
+    a.module("lib.star", "def imported():\n  return 1");
+    let module = a.module("instrs.star", program);
+
+    let mut golden = golden_header(program);
+    writeln!(golden, "{}", module.dump_bytecode().trim()).unwrap();
+    golden
+}
+
+/// Compare the bytecode of function `test` in `program` against the golden file.
 pub(crate) fn bc_golden_test(test_name: &str, program: &str) {
+    check_golden(test_name, make_golden(program));
+}
+
+/// Compare the bytecode of all functions defined in `program` against the golden file.
+/// The program can load `imported` from `lib.star`.
+pub(crate) fn bc_module_golden_test(test_name: &str, program: &str) {
+    check_golden(test_name, make_module_golden(program));
+}
+
+fn check_golden(test_name: &str, actual: String) {
     let manifest_dir =
         env::var("CARGO_MANIFEST_DIR").expect("`CARGO_MANIFEST_DIR` variable must be set");
 
     let golden_file_name = format!("{manifest_dir}/src/tests/bc/golden/{test_name}.golden");
 
-    let actual = make_golden(program);
     if env::var(REGENERATE_VAR_NAME).is_ok() {
         fs::write(golden_file_name, &actual).unwrap();
     } else {
@@ -23,3 +23,4 @@ mod definitely_assigned;
 mod expr;
 pub(crate) mod golden;
 mod if_stmt;
+mod module;
@@ -0,0 +1,36 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::tests::bc::golden::bc_module_golden_test;
+
+#[test]
+fn test_module_functions() {
+    bc_module_golden_test(
+        "module_functions",
+        r#"
+load("lib.star", "imported")
+
+def add(x, y):
+    return x + y
+
+def _private(xs):
+    return [add(x, imported()) for x in xs]
+
+double = lambda x: x * 2
+"#,
+    );
+}
@@ -21,6 +21,8 @@ use crate as starlark;
 use crate::assert;
 use crate::assert::Assert;
 use crate::environment::GlobalsBuilder;
+use crate::errors::Diagnostic;
+use crate::eval::CallStackOverflow;
 use crate::values::UnpackValue;
 use crate::values::Value;
 
@@ -84,6 +86,46 @@ def rec6(): rec2()
     assert::fail("def f(a, **kwargs, *args): pass", "parameter after another");
 }
 
+#[test]
+fn test_max_call_stack_depth() {
+    let program = r#"
+def f(n):
+    return f(n - 1) if n else True
+"#;
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_max_call_stack_depth(80));
+    a.is_true(&format!("{}f(70)", program));
+    let err = a.fail(&format!("{}f(90)", program), "Starlark call stack overflow");
+    assert!(err.to_string().contains("in f"), "{}", err);
+    assert!(
+        err.to_string().contains("... 65 frames omitted ..."),
+        "{}",
+        err
+    );
+    let overflow = err
+        .downcast_ref::<Diagnostic>()
+        .unwrap()
+        .message
+        .downcast_ref::<CallStackOverflow>()
+        .unwrap();
+    assert_eq!(80, overflow.max_depth());
+    assert_eq!(65, overflow.call_stack().omitted_frames());
+    let frames = overflow.call_stack().clone().into_frames();
+    assert_eq!(15, frames.len());
+    assert!(frames.iter().all(|x| x.name == "f" && x.location.is_some()));
+
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_max_call_stack_depth(5));
+    a.is_true(&format!("{}f(3)", program));
+    a.fail(&format!("{}f(4)", program), "Starlark call stack overflow");
+
+    // Too small depths are raised to the module frame.
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_max_call_stack_depth(0));
+    a.is_true("1 == 1");
+    a.fail(&format!("{}f(0)", program), "Starlark call stack overflow");
+}
+
 #[test]
 fn funcall_extra_args_def() {
     fn f(x: &str) -> String {
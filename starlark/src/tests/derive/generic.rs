@@ -0,0 +1,108 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Simple values with type parameters.
+
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+
+use allocative::Allocative;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+
+trait Content: Debug + Display + Allocative + Send + Sync + 'static {
+    fn len(&self) -> i32;
+}
+
+#[derive(Debug, derive_more::Display, Allocative)]
+struct Int(i32);
+
+impl Content for Int {
+    fn len(&self) -> i32 {
+        1
+    }
+}
+
+#[derive(Debug, derive_more::Display, Allocative)]
+struct Str(String);
+
+impl Content for Str {
+    fn len(&self) -> i32 {
+        self.0.len() as i32
+    }
+}
+
+#[derive(Debug, ProvidesStaticType, NoSerialize, Allocative)]
+struct Wrapper<T: Content + 'static>(T);
+
+starlark_simple_value!(Wrapper<T> where T: Content);
+
+impl<T: Content> Display for Wrapper<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wrapper({})", self.0)
+    }
+}
+
+impl<'v, T: Content> StarlarkValue<'v> for Wrapper<T> {
+    starlark_type!("wrapper");
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.len())
+    }
+}
+
+#[starlark_module]
+fn wrappers(builder: &mut GlobalsBuilder) {
+    fn wrap_int(x: i32) -> anyhow::Result<Wrapper<Int>> {
+        Ok(Wrapper(Int(x)))
+    }
+
+    fn wrap_str(x: &str) -> anyhow::Result<Wrapper<Str>> {
+        Ok(Wrapper(Str(x.to_owned())))
+    }
+
+    fn unwrap_int(x: &Wrapper<Int>) -> anyhow::Result<i32> {
+        Ok((x.0).0)
+    }
+}
+
+#[test]
+fn test_generic_simple_value() {
+    let heap = Heap::new();
+    let x: Value = heap.alloc(Wrapper(Int(3)));
+    assert_eq!(x.to_str(), "wrapper(3)");
+    assert_eq!(x.get_type(), "wrapper");
+    // Each instantiation is a distinct type
+    assert!(Wrapper::<Int>::from_value(x).is_some());
+    assert!(Wrapper::<Str>::from_value(x).is_none());
+}
+
+#[test]
+fn test_generic_simple_value_module() {
+    let mut a = Assert::new();
+    a.globals_add(wrappers);
+    a.eq("3", "unwrap_int(wrap_int(3))");
+    a.eq("5", "len(wrap_str('hello'))");
+    a.fail("unwrap_int(wrap_str('x'))", "Type of parameter");
+}
@@ -18,5 +18,8 @@
 mod attrs;
 mod docs;
 mod freeze;
+mod generic;
 mod module;
+mod simple_value;
 mod trace;
+mod unpack;
@@ -18,6 +18,7 @@
 mod basic;
 mod default_value;
 mod methods;
+mod named_params;
 mod named_positional;
 mod return_impl;
 mod special_params;
@@ -0,0 +1,104 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::docs::DocItem;
+use crate::docs::Param;
+use crate::environment::GlobalsBuilder;
+use crate::eval::NamedParameters;
+use crate::values::Value;
+
+#[derive(NamedParameters)]
+struct RuleParams<'v> {
+    /// The name of the target.
+    name: &'v str,
+    /// Source files.
+    srcs: Option<Vec<&'v str>>,
+    #[starlark(default = 3)]
+    count: i32,
+    extra: Option<Value<'v>>,
+}
+
+#[starlark_module]
+fn named_params_functions(globals: &mut GlobalsBuilder) {
+    /// Define a rule.
+    fn rule<'v>(#[starlark(named_params)] params: RuleParams<'v>) -> anyhow::Result<String> {
+        Ok(format!(
+            "{} {:?} {} {}",
+            params.name,
+            params.srcs.unwrap_or_default(),
+            params.count,
+            params.extra.map_or_else(|| "-".to_owned(), |x| x.to_repr())
+        ))
+    }
+}
+
+#[test]
+fn test_named_params() {
+    let mut a = Assert::new();
+    a.globals_add(named_params_functions);
+    a.eq("'x [] 3 -'", "rule(name = 'x')");
+    a.eq(
+        "'x [\"a.c\"] 5 [1]'",
+        "rule(count = 5, srcs = ['a.c'], name = 'x', extra = [1])",
+    );
+    a.fail("rule(srcs = ['a.c'])", "Missing parameter `name`");
+    a.fail("rule('a', name = 'x')", "positional");
+    a.fail("rule(name = 'x', count = 'y')", "Type of parameter");
+    a.fail(
+        "rule(name = 'x', unknown = 1)",
+        "Found `unknown` extra named parameter",
+    );
+}
+
+#[test]
+fn test_named_params_documentation() {
+    let globals = GlobalsBuilder::new().with(named_params_functions).build();
+    let function = match globals.get("rule").unwrap().documentation() {
+        Some(DocItem::Function(f)) => f,
+        x => panic!("Expected function docs, got {:?}", x),
+    };
+    assert_eq!(
+        "Define a rule.",
+        function.docs.as_ref().unwrap().summary.as_str()
+    );
+    let params: Vec<_> = function
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            Param::Arg {
+                name, docs, typ, ..
+            } => Some((
+                name.as_str(),
+                docs.as_ref().map(|d| d.summary.as_str()),
+                typ.as_ref().unwrap().raw_type.as_str(),
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("name", Some("The name of the target."), "str.type"),
+            ("srcs", Some("Source files."), "[None, [str.type]]"),
+            ("count", None, "int.type"),
+            ("extra", None, "[None, \"\"]"),
+        ],
+        params
+    );
+    assert!(matches!(function.params[0], Param::NoArgs));
+}
@@ -0,0 +1,87 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::values::StarlarkSimpleValue;
+
+#[derive(Debug, Clone, StarlarkSimpleValue)]
+#[starlark(type = "point")]
+struct Point {
+    x: i32,
+    y: i32,
+    label: String,
+    #[starlark(skip)]
+    secret: u64,
+}
+
+#[derive(Debug, StarlarkSimpleValue)]
+struct Line {
+    start: Point,
+    end: Point,
+}
+
+#[test]
+fn test_derive_simple_value_allocative() {
+    let point = Point {
+        x: 1,
+        y: 2,
+        label: "a".repeat(100),
+        secret: 42,
+    };
+    // The string of the label is counted.
+    assert!(allocative::size_of_unique_allocated_data(&point) >= 100);
+}
+
+#[test]
+fn test_derive_simple_value() {
+    let mut a = Assert::new();
+    let start = Point {
+        x: 1,
+        y: 2,
+        label: "a".to_owned(),
+        secret: 42,
+    };
+    let end = Point {
+        x: 3,
+        y: 4,
+        label: "b".to_owned(),
+        secret: 43,
+    };
+    a.globals_add(|gb| {
+        gb.set("point", start.clone());
+        gb.set("line", Line { start, end });
+    });
+    a.eq("point.x + point.y", "3");
+    a.eq("point.label", "'a'");
+    a.eq("dir(point)", "['label', 'x', 'y']");
+    a.is_true("not hasattr(point, 'secret')");
+    a.eq("type(point)", "'point'");
+    a.eq("str(point)", "'point(x=1, y=2, label=\"a\")'");
+    a.eq("line.end.label", "'b'");
+    // Nested values are shown as Starlark shows them, without the skipped fields.
+    a.eq(
+        "str(line)",
+        "'Line(start=point(x=1, y=2, label=\"a\"), end=point(x=3, y=4, label=\"b\"))'",
+    );
+    a.eq("type(line)", "'Line'");
+    a.fail(
+        "point.x = 3",
+        "Operation `.x=` not supported on type `point`",
+    );
+    a.fail("json.encode(point)", "not supported on type `point`");
+}
@@ -0,0 +1,85 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::values::StarlarkUnpack;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+#[derive(StarlarkUnpack)]
+struct Target<'v> {
+    name: &'v str,
+    #[starlark(rename = "srcs")]
+    sources: Vec<String>,
+    #[starlark(default = 1)]
+    count: i32,
+    visibility: Option<Vec<&'v str>>,
+    #[starlark(rename = "data", default = Vec::new())]
+    extra: Vec<Value<'v>>,
+}
+
+#[starlark_module]
+fn unpack_functions(globals: &mut GlobalsBuilder) {
+    fn target<'v>(t: Target<'v>) -> anyhow::Result<String> {
+        Ok(format!(
+            "{} {:?} {} {:?} {}",
+            t.name,
+            t.sources,
+            t.count,
+            t.visibility,
+            t.extra.len()
+        ))
+    }
+}
+
+#[test]
+fn test_unpack_derive() {
+    let mut a = Assert::new();
+    a.globals_add(unpack_functions);
+    a.eq(
+        "'x [\"a.c\"] 1 None 0'",
+        "target({'name': 'x', 'srcs': ['a.c']})",
+    );
+    a.eq(
+        "'x [] 3 Some([\"//foo\"]) 2'",
+        "target(struct(name = 'x', srcs = [], count = 3, visibility = ['//foo'], data = [1, 2]))",
+    );
+    a.eq(
+        "'x [] 1 None 0'",
+        "target({'name': 'x', 'srcs': [], 'count': None, 'visibility': None, 'unknown': 1})",
+    );
+    a.fail(
+        "target({'srcs': []})",
+        "expected `struct or dict with fields `name`, `srcs`, `count`, `visibility`, `data``",
+    );
+    a.fail(
+        "target({'name': 'x', 'srcs': [], 'count': 'y'})",
+        "Type of parameter",
+    );
+    a.fail("target({1: 'x'})", "Type of parameter");
+    a.fail("target('x')", "Type of parameter");
+}
+
+#[test]
+fn test_unpack_derive_expected() {
+    assert_eq!(
+        "struct or dict with fields `name`, `srcs`, `count`, `visibility`, `data`",
+        Target::expected()
+    );
+}
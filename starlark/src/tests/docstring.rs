@@ -379,3 +379,60 @@ Some extra details can go here,
     assert_eq!(expected_m2, m2_docs);
     assert_eq!(expected_m3, m3_docs);
 }
+
+#[test]
+fn test_frozen_closure_documentation() {
+    use crate::docs::DocItem;
+    use crate::docs::DocString;
+    use crate::docs::Function;
+    use crate::docs::Param;
+    use crate::docs::Return;
+
+    let m = assert::pass_module(
+        r#"
+def make_rule(default_srcs):
+    def rule(name, srcs = default_srcs, visibility = None):
+        """
+        Define a rule.
+
+        Args:
+            name: The name of the target
+        """
+        pass
+    return rule
+
+my_rule = make_rule(["a.txt"])
+"#,
+    );
+
+    let expected = Some(DocItem::Function(Function {
+        docs: DocString::from_docstring(DocStringKind::Starlark, "Define a rule."),
+        params: vec![
+            Param::Arg {
+                name: "name".to_owned(),
+                docs: DocString::from_docstring(DocStringKind::Starlark, "The name of the target"),
+                typ: None,
+                default_value: None,
+            },
+            Param::Arg {
+                name: "srcs".to_owned(),
+                docs: None,
+                typ: None,
+                default_value: Some("[\"a.txt\"]".to_owned()),
+            },
+            Param::Arg {
+                name: "visibility".to_owned(),
+                docs: None,
+                typ: None,
+                default_value: Some("None".to_owned()),
+            },
+        ],
+        ret: Return::default(),
+    }));
+    let my_rule = m.get("my_rule").unwrap();
+    assert_eq!(expected, my_rule.documentation());
+    assert_eq!(
+        expected,
+        my_rule.value().unpack_frozen().unwrap().documentation()
+    );
+}
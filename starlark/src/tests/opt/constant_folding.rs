@@ -23,6 +23,14 @@ fn test_fold_list_add() {
     bc_golden_test("constant_folding_list_add", "def test(): return [1] + [2]");
 }
 
+#[test]
+fn test_fold_string_add() {
+    bc_golden_test(
+        "constant_folding_string_add",
+        "def test(): return \"a\" + \"b\" + \"c\"",
+    );
+}
+
 #[test]
 fn test_fold_list_add_too_large() {
     let mut a = Assert::new();
@@ -18,6 +18,7 @@
 //! Test function bodies inlined.
 
 use crate::assert::Assert;
+use crate::environment::FrozenModule;
 use crate::eval::bc::opcode::BcOpcode;
 use crate::eval::compiler::def::FrozenDef;
 use crate::tests::bc::golden::bc_golden_test;
@@ -178,3 +179,63 @@ def test(x, y):
 "#,
     );
 }
+
+fn first_opcode(module: &FrozenModule, name: &str) -> BcOpcode {
+    let f = module.get(name).unwrap();
+    let f = f.value().downcast_ref::<FrozenDef>().unwrap();
+    f.bc().instrs.opcodes()[0]
+}
+
+const WRAPPER_LIB: &str = r#"
+def _helper(x):
+    return [x, x]
+
+def wrapper(x):
+    # `_helper` is not frozen when `wrapper` is compiled.
+    return _helper(x)
+"#;
+
+#[test]
+fn test_frozen_wrapper_inlined_into_other_module() {
+    let mut a = Assert::new();
+    a.module("lib.bzl", WRAPPER_LIB);
+    let m = a.module(
+        "user.bzl",
+        "load('lib.bzl', 'wrapper')\ndef f(): return wrapper(1)",
+    );
+    assert_eq!(BcOpcode::ListOfConsts, first_opcode(&m, "f"));
+    a.eq("[1, 1]", "load('user.bzl', 'f')\nf()");
+}
+
+#[test]
+fn test_frozen_wrapper_not_inlined_when_disabled() {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.disable_cross_module_inlining());
+    a.module("lib.bzl", WRAPPER_LIB);
+    let m = a.module(
+        "user.bzl",
+        "load('lib.bzl', 'wrapper')\ndef f(): return wrapper(1)",
+    );
+    assert_ne!(BcOpcode::ListOfConsts, first_opcode(&m, "f"));
+    a.eq("[1, 1]", "load('user.bzl', 'f')\nf()");
+}
+
+#[test]
+fn test_large_frozen_function_not_inlined_into_other_module() {
+    let mut a = Assert::new();
+    a.module(
+        "lib.bzl",
+        r#"
+def _helper(x):
+    return [x, x]
+
+def large(x):
+    return [_helper(x), _helper(x), _helper(x), _helper(x), _helper(x), _helper(x), _helper(x), _helper(x)]
+"#,
+    );
+    let m = a.module(
+        "user.bzl",
+        "load('lib.bzl', 'large')\ndef f(): return large(1)",
+    );
+    assert_ne!(BcOpcode::ListOfConsts, first_opcode(&m, "f"));
+}
@@ -21,6 +21,7 @@ use std::fmt::Write;
 use std::mem;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use std::time::Duration;
 
 use derive_more::Display;
 use once_cell::sync::Lazy;
@@ -28,11 +29,18 @@ use once_cell::sync::Lazy;
 use crate as starlark;
 use crate::assert;
 use crate::assert::Assert;
+use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
+use crate::environment::Module;
 use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
 use crate::values::any::StarlarkAny;
+use crate::values::ChunkGrowth;
 use crate::values::FrozenHeap;
+use crate::values::FrozenStringValue;
 use crate::values::Heap;
+use crate::values::HeapOptions;
 
 #[test]
 fn test_garbage_collect() {
@@ -229,3 +237,206 @@ fn test_display_debug() {
     assert_eq!(format!("{:?}", v), "FrozenValue(\"test\")");
     assert_eq!(format!("{:#?}", v), "FrozenValue(\n    \"test\",\n)");
 }
+
+#[test]
+fn test_heap_options() {
+    // The number of chunks before and after a garbage collection.
+    let chunks = |options: HeapOptions| {
+        let module = Module::with_heap_options(options);
+        let mut eval = Evaluator::new(&module);
+        eval.disable_gc();
+        let ast = AstModule::parse(
+            "x.star",
+            "x = [[i] for i in range(10000)]".to_owned(),
+            &Dialect::Standard,
+        )
+        .unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+        let heap = module.heap();
+        assert!(heap.wasted_bytes() <= heap.allocated_bytes());
+        let before = heap.arena_chunks();
+        // The module holds all the values, so tracing from it keeps them valid.
+        unsafe { heap.garbage_collect(|tracer| module.trace(tracer)) };
+        (before, heap.arena_chunks())
+    };
+
+    let (default_before, default_after) = chunks(HeapOptions::default());
+    assert!(default_before > 2, "{}", default_before);
+    let reserved = chunks(HeapOptions {
+        initial_capacity: 16 * 1024 * 1024,
+        chunk_growth: ChunkGrowth::Restart,
+    });
+    assert_eq!(reserved, (2, 2));
+    let (_, kept_after) = chunks(HeapOptions {
+        initial_capacity: 0,
+        chunk_growth: ChunkGrowth::KeepCapacity,
+    });
+    assert!(
+        kept_after < default_after,
+        "{} {}",
+        kept_after,
+        default_after
+    );
+}
+
+#[test]
+fn test_max_allocated_bytes() {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_max_allocated_bytes(1024 * 1024));
+    a.pass("x = [[i] for i in range(100)]");
+    a.fail("x = [[i] for i in range(1000000)]", "memory limit exceeded");
+    a.fail(
+        r#"
+def f():
+    xs = []
+    for i in range(1000000):
+        xs.append(str(i))
+f()
+"#,
+        "memory limit exceeded",
+    );
+    a.fail(
+        r#"
+def f(xs):
+    return f(xs + xs)
+f([1])
+"#,
+        "memory limit exceeded",
+    );
+    // The content of `bytes` lives outside the heap, but still counts.
+    a.fail(
+        r#"
+def f():
+    xs = []
+    for i in range(1000):
+        xs.append(bytes('x') * 10000)
+f()
+"#,
+        "memory limit exceeded",
+    );
+
+    // Module-level garbage is collected before the limit is checked.
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_max_allocated_bytes(1024 * 1024);
+    let program = "x = [str(i) for i in range(5000)]\n".repeat(20);
+    let ast = AstModule::parse("x.star", program, &Dialect::Standard).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    assert!(eval.allocated_bytes() <= 1024 * 1024);
+    assert!(eval.allocated_bytes() >= module.heap().allocated_bytes());
+}
+
+#[test]
+fn test_max_allocated_bytes_single_allocation() {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_max_allocated_bytes(1024 * 1024));
+    a.fail("x = 'x' * (1 << 30)", "memory limit exceeded");
+    a.fail("x = list(range(1 << 28))", "memory limit exceeded");
+    a.fail("x = [1, 2] * (1 << 24)", "memory limit exceeded");
+    a.fail("x = bytes('x') * (1 << 30)", "memory limit exceeded");
+    a.eq("'ab' * 3", "'ababab'");
+    a.eq("[1, 2] * 2", "[1, 2, 1, 2]");
+
+    // The failed allocation is reported with the call stack, and the evaluator keeps working.
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_max_allocated_bytes(1024 * 1024);
+    let program = r#"
+def big():
+    n = 1 << 30
+    return 'x' * n
+def small():
+    return 'x' * 10
+big()
+"#;
+    let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+    let err = eval
+        .eval_module(ast, &Globals::standard())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("memory limit exceeded"), "{}", err);
+    assert!(err.contains("big()"), "{}", err);
+    let small = module.get("small").unwrap();
+    let res = eval.eval_function(small, &[], &[]).unwrap();
+    assert_eq!("xxxxxxxxxx", res.unpack_str().unwrap());
+
+    // Allocating directly on the heap is not limited.
+    module.heap().alloc_str(&"x".repeat(2 * 1024 * 1024));
+}
+
+#[test]
+fn test_fuel() {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_fuel(1000));
+    a.pass("x = [i for i in range(100)]");
+    a.fail("x = [i for i in range(1000)]", "fuel exhausted");
+    a.fail(
+        r#"
+def f(n):
+    return f(n + 1) if n < 10 else [g() for _ in range(1000)]
+def g():
+    pass
+f(0)
+"#,
+        "fuel exhausted",
+    );
+
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    assert_eq!(None, eval.remaining_fuel());
+    eval.set_fuel(100);
+    let ast = AstModule::parse("x.star", "x = 1\ny = 2".to_owned(), &Dialect::Standard).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    let left = eval.remaining_fuel().unwrap();
+    assert!(left < 100 && left > 90, "{}", left);
+}
+
+#[test]
+fn test_time_limit() {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_time_limit(Duration::from_secs(60)));
+    a.pass("x = [i for i in range(1000)]");
+    a.setup_eval(|eval| eval.set_time_limit(Duration::ZERO));
+    a.fail("x = 1\ny = 2", "time limit exceeded");
+    a.fail(
+        r#"
+def f():
+    for _ in range(1000000000):
+        pass
+f()
+"#,
+        "time limit exceeded",
+    );
+}
+
+#[test]
+fn test_interned_strings_shared_with_globals() {
+    let globals = GlobalsBuilder::standard();
+    let shared = globals.frozen_heap().alloc_str_intern("shared_name");
+    let globals = globals.build();
+    let same = |x: FrozenStringValue, y: FrozenStringValue| x.to_value().ptr_eq(y.to_value());
+    assert!(same(globals.interned_str("shared_name").unwrap(), shared));
+    assert!(globals.interned_str("len").is_some());
+    assert!(globals.interned_str("not_interned").is_none());
+
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.eval_module(
+        AstModule::parse(
+            "x.star",
+            "shared_name = len([])\nother_name = 1".to_owned(),
+            &Dialect::Standard,
+        )
+        .unwrap(),
+        &globals,
+    )
+    .unwrap();
+    // Identifiers in the module reuse the strings of the globals.
+    let heap = module.frozen_heap();
+    assert!(same(heap.alloc_str_intern("shared_name"), shared));
+    assert!(same(
+        heap.alloc_str_intern("len"),
+        globals.interned_str("len").unwrap()
+    ));
+    assert!(globals.interned_str("other_name").is_none());
+}
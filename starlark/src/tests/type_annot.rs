@@ -65,3 +65,31 @@ xs[0] : int.type = 4
     assert::fail("a = 1\na : '' += 1", "not allowed on augmented assignments");
     assert::fail("a : str.type = 1", "does not match the type annotation");
 }
+
+#[test]
+fn test_type_declaration() {
+    assert::pass(
+        r#"
+x : str.type
+x = "test"
+def f(b):
+    y : int.type
+    if b:
+        y = 1
+    else:
+        y = 2
+    return y
+assert_eq(f(True), 1)
+"#,
+    );
+    assert::fail("x : str.type\nx = 1", "does not match the type annotation");
+    assert::fail(
+        "x : str.type = 'a'\nx = 1",
+        "does not match the type annotation",
+    );
+    assert::fail(
+        "def f():\n  x : int.type\n  return x\nf()",
+        "referenced before assignment",
+    );
+    assert::fail("a.b : int.type", "only allowed on a single variable");
+}
@@ -450,7 +450,7 @@ Traceback (most recent call last):
       add(z)
   * imported.bzl:11, in add
       x.append(z)
-error: Immutable
+error: Immutable `list`; copy it with `list(x)` to get a value that can be mutated
   --> imported.bzl:11:3
    |
 11 |   x.append(z)
@@ -470,7 +470,7 @@ Traceback (most recent call last):
       add(z)
   * imported.bzl:11, in add
       x.append(z)
-error: Immutable
+error: Immutable `list`; copy it with `list(x)` to get a value that can be mutated
   --> imported.bzl:11:3
    |
 11 |   x.append(z)
@@ -550,7 +550,7 @@ fn test_load_did_you_mean() {
     a.module("categories", "colour = 1");
     a.fail(
         "load('categories', 'color')",
-        "Module has no symbol `color`, did you mean `colour`?",
+        "Module `categories` has no symbol `color`, did you mean `colour`?",
     );
 }
 
@@ -902,3 +902,13 @@ animal("Joe")
     let animal = SmallMap::<String, Value>::unpack_value(res).unwrap();
     println!("animal = {:?}", animal);
 }
+
+#[test]
+fn test_load_did_you_mean_several() {
+    let mut a = Assert::new();
+    a.module("shapes", "circle = 1\ncircles = 2\n_circl = 3\nsquare = 4");
+    a.fail(
+        "load('shapes', 'square', 'circl')",
+        "Module `shapes` has no symbol `circl`, did you mean one of `circle`, `circles`?",
+    );
+}
@@ -15,21 +15,35 @@
  * limitations under the License.
  */
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use dupe::Dupe;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 
+use crate::codemap::CodeMap;
 use crate::codemap::Span;
 use crate::eval::compiler::scope::BindingId;
 use crate::eval::compiler::scope::CstAssign;
 use crate::eval::compiler::scope::CstAssignIdent;
 use crate::eval::compiler::scope::CstExpr;
+use crate::eval::compiler::scope::CstParameter;
 use crate::eval::compiler::scope::CstPayload;
 use crate::eval::compiler::scope::CstStmt;
 use crate::eval::compiler::scope::ResolvedIdent;
+use crate::syntax::ast::ArgumentP;
 use crate::syntax::ast::AssignOp;
 use crate::syntax::ast::AssignP;
+use crate::syntax::ast::AstAssignIdentP;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstParameterP;
+use crate::syntax::ast::AstPayload;
+use crate::syntax::ast::BinOp;
 use crate::syntax::ast::ClauseP;
 use crate::syntax::ast::DefP;
 use crate::syntax::ast::ExprP;
@@ -68,6 +82,113 @@ impl<'a> BindExpr<'a> {
     }
 }
 
+/// A refinement of the type of a variable, learnt from a condition that guards its use.
+#[derive(Clone, Debug)]
+pub(crate) struct Narrow {
+    /// The result of `type(x)`, with `None` being `"NoneType"`.
+    type_name: String,
+    /// Whether `type(x) == type_name` or `type(x) != type_name`.
+    matches: bool,
+}
+
+impl Narrow {
+    pub(crate) fn apply(&self, ty: &Ty) -> Ty {
+        ty.narrow_type_name(&self.type_name, self.matches)
+    }
+
+    /// The narrowings that hold when `cond` evaluates to `positive`.
+    fn from_condition(cond: &CstExpr, positive: bool) -> Vec<(BindingId, Narrow)> {
+        fn ident(x: &CstExpr) -> Option<BindingId> {
+            match &**x {
+                ExprP::Identifier(_, Some(ResolvedIdent::Slot((_, id)))) => Some(*id),
+                _ => None,
+            }
+        }
+
+        // `type(x)` or `x == None`, as the binding and the type name
+        fn type_of(x: &CstExpr, other: &CstExpr) -> Option<(BindingId, String)> {
+            match (&**x, &**other) {
+                (ExprP::Call(f, args), ExprP::Literal(AstLiteral::String(name)))
+                    if args.len() == 1 =>
+                {
+                    match (&***f, &*args[0]) {
+                        (ExprP::Identifier(f, _), ArgumentP::Positional(x)) if f.node == "type" => {
+                            Some((ident(x)?, name.node.clone()))
+                        }
+                        _ => None,
+                    }
+                }
+                (_, ExprP::Identifier(none, _)) if none.node == "None" => {
+                    Some((ident(x)?, "NoneType".to_owned()))
+                }
+                _ => None,
+            }
+        }
+
+        match &**cond {
+            ExprP::Identifier(..) if positive => match ident(cond) {
+                Some(id) => vec![(
+                    id,
+                    Narrow {
+                        type_name: "NoneType".to_owned(),
+                        matches: false,
+                    },
+                )],
+                None => Vec::new(),
+            },
+            ExprP::Not(x) => Self::from_condition(x, !positive),
+            ExprP::Op(a, BinOp::And, b) if positive => {
+                let mut res = Self::from_condition(a, true);
+                res.extend(Self::from_condition(b, true));
+                res
+            }
+            ExprP::Op(a, BinOp::Or, b) if !positive => {
+                let mut res = Self::from_condition(a, false);
+                res.extend(Self::from_condition(b, false));
+                res
+            }
+            ExprP::Op(a, op @ (BinOp::Equal | BinOp::NotEqual), b) => {
+                match type_of(a, b).or_else(|| type_of(b, a)) {
+                    Some((id, type_name)) => vec![(
+                        id,
+                        Narrow {
+                            type_name,
+                            matches: positive == (*op == BinOp::Equal),
+                        },
+                    )],
+                    None => Vec::new(),
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Is the `def` at `span` marked with a `# @unchecked` comment, either on the line before
+/// or at the end of the line it starts on. The body of such a function isn't typechecked,
+/// for code using patterns the typechecker can't model, but its signature is still used
+/// to check its callers.
+fn is_unchecked(codemap: &CodeMap, span: Span) -> bool {
+    const MARKER: &str = "# @unchecked";
+    let line = codemap.find_line(span.begin());
+    codemap.source_line(line).trim_end().ends_with(MARKER)
+        || (line > 0 && codemap.source_line(line - 1).trim() == MARKER)
+}
+
+/// Does executing this statement never continue to the next statement.
+fn always_exits(x: &CstStmt) -> bool {
+    match &**x {
+        StmtP::Return(_) | StmtP::Break | StmtP::Continue => true,
+        StmtP::Expression(x) => match &**x {
+            ExprP::Call(f, _) => matches!(&***f, ExprP::Identifier(f, _) if f.node == "fail"),
+            _ => false,
+        },
+        StmtP::Statements(xs) => xs.last().map_or(false, always_exits),
+        StmtP::IfElse(_, then_else) => always_exits(&then_else.0) && always_exits(&then_else.1),
+        _ => false,
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct Bindings<'a> {
     pub(crate) expressions: HashMap<BindingId, Vec<BindExpr<'a>>>,
@@ -75,7 +196,18 @@ pub(crate) struct Bindings<'a> {
     pub(crate) types: HashMap<BindingId, Ty>,
     pub(crate) check: Vec<&'a CstExpr>,
     pub(crate) check_type: Vec<(Span, Option<&'a CstExpr>, Ty)>,
+    /// Values with a type annotation, by the span of the annotation.
+    pub(crate) check_annotation: Vec<(Span, &'a CstExpr, Ty)>,
+    /// Variables declared with a type annotation, which later assignments must match.
+    pub(crate) declared: HashMap<BindingId, Ty>,
+    /// The conditions of `if` statements, with their then and else blocks.
+    pub(crate) conditions: Vec<(&'a CstExpr, &'a CstStmt, Option<&'a CstStmt>)>,
+    /// Expression statements with more statements after them in the same block,
+    /// with the span of those statements.
+    pub(crate) followed: Vec<(&'a CstExpr, Span)>,
     pub(crate) approximations: Vec<Approximation>,
+    /// Narrowings of the types of identifier expressions, by the span of the identifier.
+    pub(crate) narrows: HashMap<Span, Vec<Narrow>>,
 }
 
 /// Interface representing the types of all bindings in a module.
@@ -97,13 +229,92 @@ impl Interface {
     pub fn get(&self, name: &str) -> Option<&Ty> {
         self.0.get(name)
     }
+
+    /// All the bindings with their types, in order of name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Ty)> {
+        self.sorted().into_iter()
+    }
+
+    /// The number of bindings.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there are no bindings.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The bindings in order of name.
+    pub(crate) fn sorted(&self) -> BTreeMap<&str, &Ty> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v)).collect()
+    }
+}
+
+// Serialized in order, so the same interface always gives the same output.
+impl Serialize for Interface {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.sorted().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interface {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        HashMap::deserialize(deserializer).map(Interface::new)
+    }
 }
 
 pub type Loads = HashMap<String, Interface>;
 
+/// The signature of a `def` or `lambda` with parameters `params`, along with the
+/// types each parameter has when seen from inside the function body.
+pub(crate) fn function_signature<'a, P: AstPayload>(
+    params: &'a [AstParameterP<P>],
+    approximations: &mut Vec<Approximation>,
+) -> (Vec<Param>, Vec<(&'a AstAssignIdentP<P>, Ty)>) {
+    let mut signature = Vec::with_capacity(params.len());
+    let mut bound = Vec::with_capacity(params.len());
+    let mut seen_no_args = false;
+    for p in params {
+        match &**p {
+            ParameterP::Normal(name, ty) | ParameterP::WithDefaultValue(name, ty, _) => {
+                let ty = Ty::from_expr_opt(ty, approximations);
+                let mut param = if seen_no_args {
+                    Param::name_only(&name.0, ty.clone())
+                } else {
+                    Param::pos_or_name(&name.0, ty.clone())
+                };
+                if matches!(&**p, ParameterP::WithDefaultValue(..)) {
+                    param = param.optional();
+                }
+                signature.push(param);
+                bound.push((name, ty));
+            }
+            ParameterP::NoArgs => seen_no_args = true,
+            ParameterP::Args(name, ty) => {
+                // There is the type we require people calling us use (usually any)
+                // and then separately the type we are when we are running (always tuple)
+                signature.push(Param::args(Ty::from_expr_opt(ty, approximations)));
+                bound.push((name, Ty::name("tuple")));
+            }
+            ParameterP::KwArgs(name, ty) => {
+                let ty = Ty::from_expr_opt(ty, approximations);
+                let ty = if ty.is_any_or_var() {
+                    Ty::dict(Ty::Any, Ty::Any)
+                } else {
+                    ty
+                };
+                signature.push(Param::kwargs(ty.clone()));
+                bound.push((name, ty));
+            }
+        }
+    }
+    (signature, bound)
+}
+
 impl<'a> Bindings<'a> {
     /// Collect all the assignments to variables
-    pub(crate) fn collect(x: &'a CstStmt, loads: &'_ Loads) -> Self {
+    pub(crate) fn collect(x: &'a CstStmt, loads: &'_ Loads, codemap: &CodeMap) -> Self {
         fn assign<'a>(lhs: &'a CstAssign, rhs: BindExpr<'a>, bindings: &mut Bindings<'a>) {
             match &**lhs {
                 AssignP::Identifier(x) => {
@@ -143,10 +354,78 @@ impl<'a> Bindings<'a> {
             }
         }
 
+        fn function_params<'a>(
+            params: &'a [CstParameter],
+            bindings: &mut Bindings<'a>,
+        ) -> Vec<Param> {
+            let (signature, bound) = function_signature(params, &mut bindings.approximations);
+            for (name, ty) in bound {
+                bindings.types.insert(name.1.unwrap(), ty);
+                bindings.descriptions.insert(name.1.unwrap(), name);
+            }
+            signature
+        }
+
+        /// Record that the uses of variables within `xs` are narrowed by `narrows`, unless
+        /// the variable is assigned within `xs`, in which case we don't know what it is.
+        fn narrow_uses<'a>(
+            xs: &[Visit<'a, CstPayload>],
+            mut narrows: Vec<(BindingId, Narrow)>,
+            bindings: &mut Bindings<'a>,
+        ) {
+            fn assigned(x: &Visit<CstPayload>, res: &mut HashSet<BindingId>) {
+                if let Visit::Stmt(x) = x {
+                    match &x.node {
+                        StmtP::Assign(lhs, _)
+                        | StmtP::AssignModify(lhs, _, _)
+                        | StmtP::For(lhs, _) => lhs.visit_lvalue(|x| {
+                            res.insert(x.1.unwrap());
+                        }),
+                        _ => {}
+                    }
+                }
+                x.visit_children(|x| assigned(&x, res))
+            }
+
+            fn uses(
+                x: &Visit<CstPayload>,
+                narrows: &[(BindingId, Narrow)],
+                bindings: &mut Bindings,
+            ) {
+                if let Visit::Expr(x) = x {
+                    if let ExprP::Identifier(_, Some(ResolvedIdent::Slot((_, id)))) = &x.node {
+                        for (narrow_id, narrow) in narrows {
+                            if narrow_id == id {
+                                bindings
+                                    .narrows
+                                    .entry(x.span)
+                                    .or_default()
+                                    .push(narrow.clone());
+                            }
+                        }
+                    }
+                }
+                x.visit_children(|x| uses(&x, narrows, bindings))
+            }
+
+            if narrows.is_empty() {
+                return;
+            }
+            let mut assigns = HashSet::new();
+            for x in xs {
+                assigned(x, &mut assigns);
+            }
+            narrows.retain(|(id, _)| !assigns.contains(id));
+            for x in xs {
+                uses(x, &narrows, bindings);
+            }
+        }
+
         fn visit<'a>(
             x: Visit<'a, CstPayload>,
             return_type: &Ty,
             loads: &Loads,
+            codemap: &CodeMap,
             bindings: &mut Bindings<'a>,
         ) {
             match x {
@@ -155,16 +434,30 @@ impl<'a> Bindings<'a> {
                         if let Some(ty) = &ty_rhs.0 {
                             let ty2 = Ty::from_expr(ty, &mut bindings.approximations);
                             bindings
-                                .check_type
-                                .push((ty.span, Some(&ty_rhs.1), ty2.clone()));
+                                .check_annotation
+                                .push((ty.span, &ty_rhs.1, ty2.clone()));
                             if let AssignP::Identifier(id) = &**lhs {
                                 // FIXME: This could be duplicated if you declare the type of a variable twice,
                                 // we would only see the second one.
-                                bindings.types.insert(id.1.unwrap(), ty2);
+                                bindings.types.insert(id.1.unwrap(), ty2.clone());
+                                bindings.declared.insert(id.1.unwrap(), ty2);
+                            }
+                        } else if let AssignP::Identifier(id) = &**lhs {
+                            if let Some(ty) = bindings.declared.get(&id.1.unwrap()) {
+                                let ty = ty.clone();
+                                bindings
+                                    .check_type
+                                    .push((ty_rhs.1.span, Some(&ty_rhs.1), ty));
                             }
                         }
                         assign(lhs, BindExpr::Expr(&ty_rhs.1), bindings)
                     }
+                    StmtP::Declare(id, ty) => {
+                        let ty = Ty::from_expr(ty, &mut bindings.approximations);
+                        bindings.descriptions.insert(id.1.unwrap(), id);
+                        bindings.types.insert(id.1.unwrap(), ty.clone());
+                        bindings.declared.insert(id.1.unwrap(), ty);
+                    }
                     StmtP::AssignModify(lhs, op, rhs) => {
                         assign(lhs, BindExpr::AssignOp(lhs, *op, rhs), bindings)
                     }
@@ -176,62 +469,29 @@ impl<'a> Bindings<'a> {
                     StmtP::Def(DefP {
                         name,
                         params,
-                        return_type,
+                        return_type: return_type_expr,
                         ..
                     }) => {
                         bindings.descriptions.insert(name.1.unwrap(), name);
-                        let mut params2 = Vec::with_capacity(params.len());
-                        let mut seen_no_args = false;
-                        for p in params {
-                            let name_ty = match &**p {
-                                ParameterP::Normal(name, ty)
-                                | ParameterP::WithDefaultValue(name, ty, _) => {
-                                    let ty = Ty::from_expr_opt(ty, &mut bindings.approximations);
-                                    let mut param = if seen_no_args {
-                                        Param::name_only(&name.0, ty.clone())
-                                    } else {
-                                        Param::pos_or_name(&name.0, ty.clone())
-                                    };
-                                    if matches!(&**p, ParameterP::WithDefaultValue(..)) {
-                                        param = param.optional();
-                                    }
-                                    params2.push(param);
-                                    Some((name, ty))
-                                }
-                                ParameterP::NoArgs => {
-                                    seen_no_args = true;
-                                    None
-                                }
-                                ParameterP::Args(name, ty) => {
-                                    // There is the type we require people calling us use (usually any)
-                                    // and then separately the type we are when we are running (always tuple)
-                                    params2.push(Param::args(Ty::from_expr_opt(
-                                        ty,
-                                        &mut bindings.approximations,
-                                    )));
-                                    Some((name, Ty::name("tuple")))
-                                }
-                                ParameterP::KwArgs(name, ty) => {
-                                    let ty = Ty::from_expr_opt(ty, &mut bindings.approximations);
-                                    let ty = if ty.is_any() {
-                                        Ty::dict(Ty::Any, Ty::Any)
-                                    } else {
-                                        ty
-                                    };
-                                    params2.push(Param::kwargs(ty.clone()));
-                                    Some((name, ty))
-                                }
-                            };
-                            if let Some((name, ty)) = name_ty {
-                                bindings.types.insert(name.1.unwrap(), ty);
-                                bindings.descriptions.insert(name.1.unwrap(), name);
-                            }
-                        }
-                        let ret_ty = Ty::from_expr_opt(return_type, &mut bindings.approximations);
+                        let params2 = function_params(params, bindings);
+                        let ret_ty =
+                            Ty::from_expr_opt(return_type_expr, &mut bindings.approximations);
                         bindings
                             .types
                             .insert(name.1.unwrap(), Ty::function(params2, ret_ty.clone()));
-                        x.visit_children(|x| visit(x, &ret_ty, loads, bindings));
+                        if is_unchecked(codemap, x.span) {
+                            // Only the parts of the signature evaluated outside the body
+                            for x in params {
+                                x.visit_expr(|x| {
+                                    visit(Visit::Expr(x), return_type, loads, codemap, bindings)
+                                });
+                            }
+                            return_type_expr.iter().for_each(|x| {
+                                visit(Visit::Expr(x), return_type, loads, codemap, bindings)
+                            });
+                        } else {
+                            x.visit_children(|x| visit(x, &ret_ty, loads, codemap, bindings));
+                        }
                         // We do our own visit_children, with a different return type
                         return;
                     }
@@ -278,11 +538,71 @@ impl<'a> Bindings<'a> {
 
                         bindings.check.push(x)
                     }
-                    StmtP::If(x, _) => bindings.check.push(x),
-                    StmtP::IfElse(x, _) => bindings.check.push(x),
+                    StmtP::If(cond, then_block) => {
+                        narrow_uses(
+                            &[Visit::Stmt(then_block)],
+                            Narrow::from_condition(cond, true),
+                            bindings,
+                        );
+                        bindings.conditions.push((cond, then_block, None))
+                    }
+                    StmtP::IfElse(cond, then_else) => {
+                        narrow_uses(
+                            &[Visit::Stmt(&then_else.0)],
+                            Narrow::from_condition(cond, true),
+                            bindings,
+                        );
+                        narrow_uses(
+                            &[Visit::Stmt(&then_else.1)],
+                            Narrow::from_condition(cond, false),
+                            bindings,
+                        );
+                        bindings
+                            .conditions
+                            .push((cond, &then_else.0, Some(&then_else.1)))
+                    }
+                    StmtP::Statements(xs) => {
+                        for (i, stmt) in xs.iter().enumerate() {
+                            if let (StmtP::Expression(x), Some(last)) = (&**stmt, xs.last()) {
+                                if i + 1 < xs.len() {
+                                    bindings.followed.push((x, xs[i + 1].span.merge(last.span)));
+                                }
+                            }
+                        }
+                        // After `if x == None: return` we know `x` isn't `None`
+                        for (i, stmt) in xs.iter().enumerate() {
+                            if let StmtP::If(cond, then_block) = &**stmt {
+                                if always_exits(then_block) {
+                                    let rest =
+                                        xs[i + 1..].iter().map(Visit::Stmt).collect::<Vec<_>>();
+                                    narrow_uses(
+                                        &rest,
+                                        Narrow::from_condition(cond, false),
+                                        bindings,
+                                    );
+                                }
+                            }
+                        }
+                    }
                     _ => {}
                 },
                 Visit::Expr(x) => match &**x {
+                    ExprP::If(cond_then_else) => {
+                        let (cond, then_expr, else_expr) = &**cond_then_else;
+                        narrow_uses(
+                            &[Visit::Expr(then_expr)],
+                            Narrow::from_condition(cond, true),
+                            bindings,
+                        );
+                        narrow_uses(
+                            &[Visit::Expr(else_expr)],
+                            Narrow::from_condition(cond, false),
+                            bindings,
+                        );
+                    }
+                    ExprP::Lambda(lambda) => {
+                        function_params(&lambda.params, bindings);
+                    }
                     ExprP::ListComprehension(_, for1, clauses)
                     | ExprP::DictComprehension(_, for1, clauses) => {
                         fn get_for_clause(
@@ -306,11 +626,11 @@ impl<'a> Bindings<'a> {
                     _ => {}
                 },
             }
-            x.visit_children(|x| visit(x, return_type, loads, bindings))
+            x.visit_children(|x| visit(x, return_type, loads, codemap, bindings))
         }
 
         let mut res = Bindings::default();
-        visit(Visit::Stmt(x), &Ty::Any, loads, &mut res);
+        visit(Visit::Stmt(x), &Ty::Any, loads, codemap, &mut res);
         res
     }
 }
@@ -0,0 +1,79 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::collections::StarlarkHasher;
+use crate::typing::bindings::Interface;
+
+/// A cache of the [`Interface`]s of typechecked modules, so that in a large workspace only
+/// the modules whose content, or the interfaces of whose loads, have changed need to be
+/// typechecked again. Each entry is keyed by a [`digest`](InterfaceCache::digest) of both.
+///
+/// The cache can be serialized, e.g. to JSON, to keep it between runs.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct InterfaceCache {
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    digest: u64,
+    interface: Interface,
+}
+
+impl InterfaceCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The digest of a module with `content` that loads the modules with the interfaces in
+    /// `loads`, which changes whenever either does. It is the same between runs.
+    pub fn digest(content: &str, loads: &HashMap<String, Interface>) -> u64 {
+        let mut hasher = StarlarkHasher::new();
+        content.hash(&mut hasher);
+        let loads: BTreeMap<_, _> = loads.iter().map(|(k, v)| (k, v.sorted())).collect();
+        loads.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The interface of the module `filename`, if it was cached with the same `digest`.
+    pub fn get(&self, filename: &str, digest: u64) -> Option<&Interface> {
+        self.entries
+            .get(filename)
+            .filter(|x| x.digest == digest)
+            .map(|x| &x.interface)
+    }
+
+    /// Record the interface of the module `filename`, computed for `digest`.
+    pub fn insert(&mut self, filename: &str, digest: u64, interface: Interface) {
+        self.entries
+            .insert(filename.to_owned(), CacheEntry { digest, interface });
+    }
+
+    /// Forget the module `filename`, for example because it was deleted.
+    pub fn remove(&mut self, filename: &str) {
+        self.entries.remove(filename);
+    }
+}
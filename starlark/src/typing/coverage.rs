@@ -0,0 +1,185 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+use serde::Serialize;
+
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::ParameterP;
+use crate::syntax::ast::Stmt;
+use crate::syntax::AstModule;
+use crate::typing::Interface;
+use crate::typing::TypingOracle;
+
+/// How much of a module is covered by type annotations and inferred types,
+/// as produced by [`AstModule::type_coverage`].
+///
+/// Coverages of several modules can be added together to get the coverage of a workspace.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TypeCoverage {
+    /// Number of `def` statements, including nested ones.
+    pub functions: usize,
+    /// Number of parameters of those functions, including `*args` and `**kwargs`.
+    pub parameters: usize,
+    /// Number of those parameters with a type annotation.
+    pub annotated_parameters: usize,
+    /// Number of functions with a return type annotation.
+    pub annotated_returns: usize,
+    /// Number of variables, parameters and functions the typechecker inferred a type for.
+    pub bindings: usize,
+    /// Number of those whose type was inferred as `typing.Any`.
+    pub any_bindings: usize,
+    /// Number of places where the typechecker had to approximate, as reported by
+    /// [`Approximation`](crate::typing::Approximation).
+    pub approximations: usize,
+}
+
+impl TypeCoverage {
+    fn ratio(x: usize, total: usize, empty: f64) -> f64 {
+        if total == 0 {
+            empty
+        } else {
+            x as f64 / total as f64
+        }
+    }
+
+    /// Fraction of parameters with a type annotation, `1.0` if there are none.
+    pub fn parameter_ratio(&self) -> f64 {
+        Self::ratio(self.annotated_parameters, self.parameters, 1.0)
+    }
+
+    /// Fraction of functions with a return type annotation, `1.0` if there are none.
+    pub fn return_ratio(&self) -> f64 {
+        Self::ratio(self.annotated_returns, self.functions, 1.0)
+    }
+
+    /// Fraction of bindings inferred as `typing.Any`, `0.0` if there are none.
+    pub fn any_ratio(&self) -> f64 {
+        Self::ratio(self.any_bindings, self.bindings, 0.0)
+    }
+
+    fn stmt(&mut self, x: &AstStmt) {
+        if let Stmt::Def(def) = &**x {
+            self.functions += 1;
+            if def.return_type.is_some() {
+                self.annotated_returns += 1;
+            }
+            for param in &def.params {
+                let ty = match &param.node {
+                    ParameterP::Normal(_, ty)
+                    | ParameterP::WithDefaultValue(_, ty, _)
+                    | ParameterP::Args(_, ty)
+                    | ParameterP::KwArgs(_, ty) => ty,
+                    ParameterP::NoArgs => continue,
+                };
+                self.parameters += 1;
+                if ty.is_some() {
+                    self.annotated_parameters += 1;
+                }
+            }
+        }
+        x.visit_stmt(|x| self.stmt(x));
+    }
+}
+
+impl AddAssign<&TypeCoverage> for TypeCoverage {
+    fn add_assign(&mut self, other: &TypeCoverage) {
+        self.functions += other.functions;
+        self.parameters += other.parameters;
+        self.annotated_parameters += other.annotated_parameters;
+        self.annotated_returns += other.annotated_returns;
+        self.bindings += other.bindings;
+        self.any_bindings += other.any_bindings;
+        self.approximations += other.approximations;
+    }
+}
+
+impl AstModule {
+    /// Typecheck this module like [`typecheck`](AstModule::typecheck), and report how
+    /// much of it is annotated and how much of it the typechecker could not infer,
+    /// to track the adoption of types in a codebase over time.
+    pub fn type_coverage(
+        self,
+        oracle: &dyn TypingOracle,
+        loads: &HashMap<String, Interface>,
+    ) -> TypeCoverage {
+        let mut coverage = TypeCoverage::default();
+        coverage.stmt(&self.statement);
+        let (_, typemap, _, approximations) = self.typecheck(oracle, loads);
+        for ty in typemap.types() {
+            coverage.bindings += 1;
+            if ty.is_any() {
+                coverage.any_bindings += 1;
+            }
+        }
+        coverage.approximations = approximations.len();
+        coverage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stdlib::LibraryExtension;
+    use crate::syntax::Dialect;
+    use crate::typing::OracleStandard;
+
+    fn coverage(x: &str) -> TypeCoverage {
+        AstModule::parse("X", x.to_owned(), &Dialect::Extended)
+            .unwrap()
+            .type_coverage(
+                &OracleStandard::new(LibraryExtension::all()),
+                &HashMap::new(),
+            )
+    }
+
+    #[test]
+    fn test_type_coverage() {
+        let x = coverage(
+            r#"
+load("a.bzl", "a")
+def f(x: int, y, *, z: str = "", **kwargs) -> int:
+    def g():
+        return a
+    return x
+xs = [1]
+"#,
+        );
+        assert_eq!(2, x.functions);
+        assert_eq!(4, x.parameters);
+        assert_eq!(2, x.annotated_parameters);
+        assert_eq!(1, x.annotated_returns);
+        assert_eq!(0.5, x.parameter_ratio());
+        assert_eq!(0.5, x.return_ratio());
+        // `a` from an unknown module, `y` without annotation and `g` returning `a`.
+        assert!(x.any_bindings >= 2, "{:?}", x);
+        assert!(x.any_bindings < x.bindings, "{:?}", x);
+    }
+
+    #[test]
+    fn test_type_coverage_empty() {
+        let mut x = coverage("");
+        assert_eq!(TypeCoverage::default(), x);
+        assert_eq!(1.0, x.parameter_ratio());
+        assert_eq!(0.0, x.any_ratio());
+        x += &coverage("def f(x: int): pass");
+        assert_eq!(1, x.annotated_parameters);
+        assert_eq!(0.0, x.return_ratio());
+    }
+}
@@ -15,7 +15,9 @@
  * limitations under the License.
  */
 
+use std::borrow::Cow;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Debug;
 
@@ -27,6 +29,7 @@ use crate::codemap::CodeMap;
 use crate::codemap::ResolvedFileSpan;
 use crate::codemap::Span;
 use crate::eval::compiler::scope::BindingId;
+use crate::eval::compiler::scope::CstArgument;
 use crate::eval::compiler::scope::CstAssign;
 use crate::eval::compiler::scope::CstExpr;
 use crate::eval::compiler::scope::CstPayload;
@@ -38,7 +41,10 @@ use crate::syntax::ast::BinOp;
 use crate::syntax::ast::ClauseP;
 use crate::syntax::ast::ExprP;
 use crate::syntax::ast::ForClauseP;
+use crate::typing::bindings::function_signature;
 use crate::typing::bindings::BindExpr;
+use crate::typing::bindings::Narrow;
+use crate::typing::fix::TypingFix;
 use crate::typing::oracle::traits::TypingOracle;
 use crate::typing::ty::Approximation;
 use crate::typing::ty::Arg;
@@ -68,15 +74,37 @@ pub(crate) enum TypingError {
         loc: ResolvedFileSpan,
         got: String,
         require: String,
+        fix: Option<TypingFix>,
     },
     #[error("Call to a non-callable type `{ty}`, at {loc}")]
     CallToNonCallable { loc: ResolvedFileSpan, ty: String },
     #[error("Missing required parameter `{name}`, at {loc}")]
-    MissingRequiredParameter { loc: ResolvedFileSpan, name: String },
+    MissingRequiredParameter {
+        loc: ResolvedFileSpan,
+        name: String,
+        fix: Option<TypingFix>,
+    },
     #[error("Unexpected parameter named `{name}`, at {loc}")]
     UnexpectedNamedArgument { loc: ResolvedFileSpan, name: String },
     #[error("Too many positional arguments, at {loc}")]
     TooManyPositionalArguments { loc: ResolvedFileSpan },
+    #[error("The value `{value}` is not one of `{typ}`, at {loc}")]
+    InvalidEnumValue {
+        loc: ResolvedFileSpan,
+        typ: String,
+        value: String,
+    },
+}
+
+impl TypingError {
+    pub(crate) fn fix(&self) -> Option<&TypingFix> {
+        match self {
+            Self::IncompatibleType { fix, .. } | Self::MissingRequiredParameter { fix, .. } => {
+                fix.as_ref()
+            }
+            _ => None,
+        }
+    }
 }
 
 pub(crate) struct TypingContext<'a> {
@@ -87,6 +115,23 @@ pub(crate) struct TypingContext<'a> {
     pub(crate) errors: RefCell<Vec<TypingError>>,
     pub(crate) approximoations: RefCell<Vec<Approximation>>,
     pub(crate) types: HashMap<BindingId, Ty>,
+    pub(crate) narrows: HashMap<Span, Vec<Narrow>>,
+}
+
+/// The type of the values of a `**kwargs` parameter, if it is known.
+fn kwargs_value(ty: &Ty) -> Option<Ty> {
+    let values: Vec<_> = ty
+        .iter_union()
+        .filter_map(|x| match x {
+            Ty::Dict(k_v) => Some(k_v.1.clone()),
+            _ => None,
+        })
+        .collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(Ty::unions(values))
+    }
 }
 
 impl TypingContext<'_> {
@@ -106,12 +151,16 @@ impl TypingContext<'_> {
         self.codemap.file_span(span).resolve()
     }
 
-    fn validate_args(&self, params: &[Param], args: &[Arg], span: Span) {
+    /// Check the arguments match the parameters, returning the types the type variables in
+    /// the parameters take for this call.
+    fn validate_args(&self, params: &[Param], args: &[Arg], span: Span) -> BTreeMap<String, Ty> {
         // Want to figure out which arguments go in which positions
         let mut param_args: Vec<Vec<&Ty>> = vec![vec![]; params.len()];
         // The next index a positional parameter might fill
         let mut param_pos = 0;
         let mut seen_vargs = false;
+        // The `*args` and `**kwargs` arguments, with the first parameter they might fill
+        let mut splats = Vec::new();
 
         for arg in args {
             match arg {
@@ -121,7 +170,7 @@ impl TypingContext<'_> {
                             self.add_error(TypingError::TooManyPositionalArguments {
                                 loc: self.resolve(span),
                             });
-                            return;
+                            return BTreeMap::new();
                         }
                         Some(param) => {
                             let found_index = param_pos;
@@ -152,51 +201,74 @@ impl TypingContext<'_> {
                     }
                 }
                 Arg::Args(_) => {
+                    splats.push((arg, param_pos));
                     param_pos = params.len();
                     seen_vargs = true;
                 }
                 Arg::Kwargs(_) => {
+                    splats.push((arg, 0));
                     seen_vargs = true;
                 }
             }
         }
 
-        for (param, args) in std::iter::zip(params, param_args) {
+        let mut vars = BTreeMap::new();
+        for (param, args) in std::iter::zip(params, &param_args) {
+            for ty in args {
+                match param.mode {
+                    ParamMode::Kwargs => param
+                        .ty
+                        .bind_vars(&Ty::dict(Ty::string(), (*ty).clone()), &mut vars),
+                    _ => param.ty.bind_vars(ty, &mut vars),
+                }
+            }
+        }
+        // Variables only seen in empty containers, such as `[]`, stay unbound
+        let bindings: BTreeMap<String, Ty> = vars
+            .into_iter()
+            .map(|(k, v)| (k, Ty::unions(v)))
+            .filter(|(_, v)| !v.is_void())
+            .collect();
+
+        for (param, args) in std::iter::zip(params, &param_args) {
+            let param_ty = if bindings.is_empty() {
+                Cow::Borrowed(&param.ty)
+            } else {
+                Cow::Owned(param.ty.instantiate(&bindings))
+            };
             if !param.allows_many() && args.len() > 1 {
                 panic!("bad")
             }
             if args.is_empty() {
                 // We assume that *args/**kwargs might have splatted things everywhere.
                 if !param.optional && !seen_vargs {
+                    let fix = match &param.mode {
+                        ParamMode::PosOrName(name) | ParamMode::NameOnly(name) => {
+                            TypingFix::add_argument(&self.codemap, span, name, &param.ty)
+                        }
+                        _ => None,
+                    };
                     self.add_error(TypingError::MissingRequiredParameter {
                         loc: self.resolve(span),
                         name: param.name().to_owned(),
+                        fix,
                     });
                 }
                 continue;
             }
             match param.mode {
                 ParamMode::PosOnly | ParamMode::PosOrName(_) | ParamMode::NameOnly(_) => {
-                    self.validate_type(args[0], &param.ty, span)
+                    self.validate_type(args[0], &param_ty, span)
                 }
                 ParamMode::Args => {
                     for ty in args {
                         // For an arg, we require the type annotation to be inner value,
                         // rather than the outer (which is always a tuple)
-                        self.validate_type(ty, &param.ty, span);
+                        self.validate_type(ty, &param_ty, span);
                     }
                 }
                 ParamMode::Kwargs => {
-                    let val_types: Vec<_> = param
-                        .ty
-                        .iter_union()
-                        .filter_map(|x| match x {
-                            Ty::Dict(k_v) => Some(k_v.1.clone()),
-                            _ => None,
-                        })
-                        .collect();
-                    if !val_types.is_empty() {
-                        let require = Ty::unions(val_types);
+                    if let Some(require) = kwargs_value(&param_ty) {
                         for ty in args {
                             self.validate_type(ty, &require, span);
                         }
@@ -204,6 +276,30 @@ impl TypingContext<'_> {
                 }
             }
         }
+
+        // We don't know how many items a splat has, or which names, so only require that
+        // its items fit one of the parameters they might go to
+        for (arg, start) in splats {
+            let mut require = Vec::new();
+            for (param, args) in std::iter::zip(&params[start..], &param_args[start..]) {
+                let param_ty = param.ty.instantiate(&bindings);
+                match (arg, &param.mode) {
+                    (Arg::Args(_), ParamMode::Args) => require.push(param_ty),
+                    (Arg::Args(_), ParamMode::PosOnly | ParamMode::PosOrName(_))
+                    | (Arg::Kwargs(_), ParamMode::PosOrName(_) | ParamMode::NameOnly(_))
+                        if args.is_empty() =>
+                    {
+                        require.push(param_ty)
+                    }
+                    (Arg::Kwargs(_), ParamMode::Kwargs) => require.extend(kwargs_value(&param_ty)),
+                    _ => {}
+                }
+            }
+            if let (Arg::Args(ty) | Arg::Kwargs(ty), false) = (arg, require.is_empty()) {
+                self.validate_type(ty, &Ty::unions(require), span);
+            }
+        }
+        bindings
     }
 
     fn validate_call(&self, fun: &Ty, args: &[Arg], span: Span) -> Ty {
@@ -214,8 +310,12 @@ impl TypingContext<'_> {
             }
         }
 
-        if fun.is_any() || fun.is_void() {
-            return fun.clone(); // Everything is valid
+        // Everything is valid
+        if fun.is_any_or_var() {
+            return Ty::Any;
+        }
+        if fun.is_void() {
+            return Ty::Void;
         }
         let funs: Vec<_> = fun.iter_union().filter_map(unpack_function).collect();
         if funs.is_empty() {
@@ -245,8 +345,8 @@ impl TypingContext<'_> {
                     }),
                 }
             } else {
-                self.validate_args(&fun.params, args, span);
-                (*fun.result).clone()
+                let bindings = self.validate_args(&fun.params, args, span);
+                fun.result.instantiate(&bindings)
             };
             let errors_after_this = self.errors.borrow().len();
             if errors_before_this == errors_after_this {
@@ -278,6 +378,20 @@ impl TypingContext<'_> {
                 loc: self.resolve(span),
                 got: got.to_string(),
                 require: require.to_string(),
+                fix: None,
+            });
+        }
+    }
+
+    /// Like [`validate_type`](Self::validate_type), where `require` comes from the
+    /// annotation at `span`, so the fix is to change the annotation.
+    pub(crate) fn validate_annotation(&self, got: &Ty, require: &Ty, span: Span) {
+        if !got.intersects(require, Some(self)) {
+            self.add_error(TypingError::IncompatibleType {
+                loc: self.resolve(span),
+                got: got.to_string(),
+                require: require.to_string(),
+                fix: TypingFix::change_annotation(&self.codemap, span, got),
             });
         }
     }
@@ -402,12 +516,108 @@ impl TypingContext<'_> {
         }
     }
 
+    /// The type created by a call to `record` or `enum`, which depends on the argument
+    /// expressions, rather than just their types.
+    fn record_or_enum_type(&self, f: &CstExpr, args: &[CstArgument]) -> Option<Ty> {
+        /// The name of a builtin function being called.
+        fn builtin_name(f: &CstExpr) -> Option<&str> {
+            match &**f {
+                ExprP::Identifier(name, ident)
+                    if !matches!(ident, Some(ResolvedIdent::Slot(_))) =>
+                {
+                    Some(name.as_str())
+                }
+                _ => None,
+            }
+        }
+
+        match builtin_name(f)? {
+            "record" => {
+                let mut params = Vec::with_capacity(args.len());
+                let mut fields = BTreeMap::new();
+                for arg in args {
+                    let (name, typ) = match &**arg {
+                        ArgumentP::Named(name, typ) => (name.as_str(), typ),
+                        _ => return None,
+                    };
+                    // Either `name = typ` or `name = field(typ, default)`
+                    let (typ, optional) = match &**typ {
+                        ExprP::Call(f, args) if builtin_name(f) == Some("field") => {
+                            match args.first().map(|x| &**x) {
+                                Some(ArgumentP::Positional(typ)) => (typ, args.len() > 1),
+                                _ => return None,
+                            }
+                        }
+                        _ => (typ, false),
+                    };
+                    let ty = Ty::from_expr(typ, &mut self.approximoations.borrow_mut());
+                    let mut param = Param::name_only(name, ty.clone());
+                    if optional {
+                        param = param.optional();
+                    }
+                    params.push(param);
+                    fields.insert(name.to_owned(), ty);
+                }
+                Some(Ty::function(params, Ty::Record { fields }))
+            }
+            "enum" => {
+                let variants = args
+                    .iter()
+                    .map(|x| match &**x {
+                        ArgumentP::Positional(x) => match &**x {
+                            ExprP::Literal(AstLiteral::String(x)) => Some(x.node.clone()),
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Ty::function(
+                    vec![Param::pos_only(Ty::string())],
+                    Ty::Enum { variants },
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Check that a call to an enum type, such as `Colors("red")`, is for a value of that enum.
+    fn validate_enum_value(&self, f: &Ty, args: &[CstArgument]) {
+        let value = match args {
+            [x] => match &**x {
+                ArgumentP::Positional(x) => match &**x {
+                    ExprP::Literal(AstLiteral::String(x)) => x,
+                    _ => return,
+                },
+                _ => return,
+            },
+            _ => return,
+        };
+        let (typ, variants) = match f {
+            Ty::Function(TyFunction { result, .. }) => match &**result {
+                typ @ Ty::Enum { variants } => (typ, variants),
+                _ => return,
+            },
+            _ => return,
+        };
+        if !variants.contains(&value.node) {
+            self.add_error(TypingError::InvalidEnumValue {
+                loc: self.resolve(value.span),
+                typ: typ.to_string(),
+                value: value.node.clone(),
+            });
+        }
+    }
+
     pub(crate) fn expression_type(&self, x: &CstExpr) -> Ty {
         let span = x.span;
         match &**x {
             ExprP::Tuple(xs) => Ty::Tuple(xs.map(|x| self.expression_type(x))),
             ExprP::Dot(a, b) => self.expression_attribute(&self.expression_type(a), b, b.span),
             ExprP::Call(f, args) => {
+                if let Some(ty) = self.record_or_enum_type(f, args) {
+                    // The arguments are types or literals, so there is nothing more to check
+                    return ty;
+                }
                 let args_ty = args.map(|x| match &**x {
                     ArgumentP::Positional(x) => Arg::Pos(self.expression_type(x)),
                     ArgumentP::Named(name, x) => {
@@ -415,19 +625,30 @@ impl TypingContext<'_> {
                     }
                     ArgumentP::Args(x) => {
                         let ty = self.expression_type(x);
-                        self.from_iterated(&ty, x.span);
-                        Arg::Args(ty)
+                        Arg::Args(
+                            ty.iter_item()
+                                .unwrap_or_else(|| self.from_iterated(&ty, x.span)),
+                        )
                     }
                     ArgumentP::KwArgs(x) => {
                         let ty = self.expression_type(x);
                         self.validate_type(&ty, &Ty::dict(Ty::Any, Ty::Any), x.span);
-                        Arg::Kwargs(ty)
+                        Arg::Kwargs(Ty::unions(
+                            ty.iter_union()
+                                .map(|x| match x {
+                                    Ty::Dict(k_v) => k_v.1.clone(),
+                                    _ => Ty::Any,
+                                })
+                                .collect(),
+                        ))
                     }
                 });
                 let f_ty = self.expression_type(f);
                 // If we can't resolve the types of the arguments, we can't validate the call,
                 // but we still know the type of the result since the args don't impact that
-                self.validate_call(&f_ty, &args_ty, span)
+                let res = self.validate_call(&f_ty, &args_ty, span);
+                self.validate_enum_value(&f_ty, args);
+                res
             }
             ExprP::ArrayIndirection(a_b) => {
                 self.expression_primitive("index", &[&a_b.0, &a_b.1], span)
@@ -441,19 +662,24 @@ impl TypingContext<'_> {
             ExprP::Identifier(x, i) => {
                 if let Some(ResolvedIdent::Slot((_, i))) = i {
                     if let Some(ty) = self.types.get(i) {
-                        return ty.clone();
+                        return match self.narrows.get(&span) {
+                            None => ty.clone(),
+                            Some(narrows) => narrows.iter().fold(ty.clone(), |ty, n| n.apply(&ty)),
+                        };
                     }
                 }
                 self.builtin(x, x.span)
             }
-            ExprP::Lambda(_) => {
-                self.approximation("We don't type check lambdas", ());
-                Ty::name("function")
+            ExprP::Lambda(lambda) => {
+                // Any approximations were already recorded when collecting the bindings
+                let (params, _) = function_signature(&lambda.params, &mut Vec::new());
+                Ty::function(params, self.expression_type(&lambda.body))
             }
             ExprP::Literal(x) => match x {
                 AstLiteral::Int(_) => Ty::int(),
                 AstLiteral::Float(_) => Ty::float(),
                 AstLiteral::String(_) => Ty::string(),
+                AstLiteral::Bytes(_) => Ty::name("bytes"),
             },
             ExprP::Not(x) => {
                 if self.expression_type(x).is_void() {
@@ -520,6 +746,12 @@ impl TypingContext<'_> {
                     .unzip();
                 Ty::dict(Ty::unions(ks), Ty::unions(vs))
             }
+            ExprP::Set(xs) => {
+                for x in xs {
+                    self.expression_type(x);
+                }
+                Ty::name("set")
+            }
             ExprP::ListComprehension(a, b, c) => {
                 self.check_comprehension(b, c);
                 Ty::list(self.expression_type(a))
@@ -0,0 +1,211 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use thiserror::Error;
+
+use crate::typing::bindings::Interface;
+use crate::typing::ty::Param;
+use crate::typing::ty::ParamMode;
+use crate::typing::ty::Ty;
+use crate::typing::ty::TyFunction;
+
+/// A change between two versions of the [`Interface`] of a module which might break
+/// the modules that load it.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BreakingChange {
+    /// The symbol is no longer exported.
+    #[error("`{symbol}` was removed")]
+    Removed {
+        /// The name of the symbol.
+        symbol: String,
+    },
+    /// The type of the symbol doesn't include all the values it used to.
+    #[error("`{symbol}` changed type from `{old}` to `{new}`")]
+    TypeChanged {
+        /// The name of the symbol.
+        symbol: String,
+        /// The type it had.
+        old: Ty,
+        /// The type it has now.
+        new: Ty,
+    },
+    /// A parameter of the function was removed, so calls passing it fail.
+    #[error("`{symbol}` no longer has parameter `{param}`")]
+    ParameterRemoved {
+        /// The name of the function.
+        symbol: String,
+        /// The name of the parameter.
+        param: String,
+    },
+    /// A parameter of the function is required which wasn't before, so calls that
+    /// don't pass it fail.
+    #[error("`{symbol}` has new required parameter `{param}`")]
+    ParameterAdded {
+        /// The name of the function.
+        symbol: String,
+        /// The name of the parameter.
+        param: String,
+    },
+    /// A parameter can no longer be passed in the position it used to be.
+    #[error("`{symbol}` parameter `{param}` can't be passed by position the way it used to")]
+    ParameterMoved {
+        /// The name of the function.
+        symbol: String,
+        /// The name of the parameter.
+        param: String,
+    },
+    /// The type of a parameter doesn't include all the values it used to.
+    #[error("`{symbol}` parameter `{param}` narrowed type from `{old}` to `{new}`")]
+    ParameterNarrowed {
+        /// The name of the function.
+        symbol: String,
+        /// The name of the parameter.
+        param: String,
+        /// The type it had.
+        old: Ty,
+        /// The type it has now.
+        new: Ty,
+    },
+}
+
+impl Interface {
+    /// The changes from `self` to `new` which might break the modules loading this one,
+    /// ordered by symbol. Symbols which were added, and types which were widened, don't
+    /// break anything, so are not reported.
+    pub fn breaking_changes(&self, new: &Interface) -> Vec<BreakingChange> {
+        let mut res = Vec::new();
+        for (symbol, old_ty) in self.sorted() {
+            let symbol = symbol.to_owned();
+            match (old_ty, new.get(&symbol)) {
+                (_, None) => res.push(BreakingChange::Removed { symbol }),
+                (Ty::Function(old), Some(Ty::Function(new))) => {
+                    function_changes(&symbol, old, new, &mut res)
+                }
+                (old, Some(new)) => {
+                    if !covers(new, old) {
+                        res.push(BreakingChange::TypeChanged {
+                            symbol,
+                            old: old.clone(),
+                            new: new.clone(),
+                        })
+                    }
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Can every call to `old` still be made to `new`.
+fn function_changes(
+    symbol: &str,
+    old: &TyFunction,
+    new: &TyFunction,
+    res: &mut Vec<BreakingChange>,
+) {
+    let has = |mode: ParamMode| new.params.iter().find(|x| x.mode == mode);
+    let positional = |i: usize| {
+        let x = new.params.get(i)?;
+        match x.mode {
+            ParamMode::PosOnly | ParamMode::PosOrName(_) | ParamMode::Args => Some(x),
+            _ => None,
+        }
+    };
+    let named = |name: &str| {
+        new.params.iter().find(|x| match &x.mode {
+            ParamMode::PosOrName(x) | ParamMode::NameOnly(x) => x == name,
+            _ => false,
+        })
+    };
+    let change = |param: &Param, kind: fn(String, String) -> BreakingChange| {
+        kind(symbol.to_owned(), param.name().to_owned())
+    };
+    let removed = |symbol, param| BreakingChange::ParameterRemoved { symbol, param };
+    let moved = |symbol, param| BreakingChange::ParameterMoved { symbol, param };
+
+    for (i, param) in old.params.iter().enumerate() {
+        let found = match &param.mode {
+            ParamMode::PosOnly => positional(i),
+            ParamMode::PosOrName(name) => match (positional(i), named(name)) {
+                (Some(x), Some(y)) if x == y => Some(x),
+                // Calls passing it by name still work, those passing it by position don't
+                (_, Some(y)) => {
+                    res.push(change(param, moved));
+                    Some(y)
+                }
+                (_, None) => has(ParamMode::Kwargs),
+            },
+            ParamMode::NameOnly(name) => named(name).or_else(|| has(ParamMode::Kwargs)),
+            ParamMode::Args => has(ParamMode::Args),
+            ParamMode::Kwargs => has(ParamMode::Kwargs),
+        };
+        if found.is_none() {
+            res.push(change(param, removed));
+        }
+        if let Some(found) = found {
+            if !covers(&found.ty, &param.ty) {
+                res.push(BreakingChange::ParameterNarrowed {
+                    symbol: symbol.to_owned(),
+                    param: param.name().to_owned(),
+                    old: param.ty.clone(),
+                    new: found.ty.clone(),
+                });
+            }
+        }
+    }
+
+    // Parameters which must now be passed, but which calls might not have passed before
+    for (i, param) in new.params.iter().enumerate() {
+        if param.optional || matches!(param.mode, ParamMode::Args | ParamMode::Kwargs) {
+            continue;
+        }
+        let before = match &param.mode {
+            ParamMode::PosOnly => old.params.get(i),
+            ParamMode::PosOrName(name) | ParamMode::NameOnly(name) => {
+                old.params.iter().find(|x| match &x.mode {
+                    ParamMode::PosOrName(x) | ParamMode::NameOnly(x) => x == name,
+                    _ => false,
+                })
+            }
+            ParamMode::Args | ParamMode::Kwargs => unreachable!(),
+        };
+        if before.map_or(true, |x| x.optional) {
+            res.push(change(param, |symbol, param| {
+                BreakingChange::ParameterAdded { symbol, param }
+            }));
+        }
+    }
+}
+
+/// Does `wide` include every value of `narrow`.
+fn covers(wide: &Ty, narrow: &Ty) -> bool {
+    match (wide, narrow) {
+        (Ty::Any | Ty::Var(_), _) | (_, Ty::Void) => true,
+        _ if wide == narrow => true,
+        (_, Ty::Union(xs)) => xs.alternatives().iter().all(|x| covers(wide, x)),
+        (Ty::Union(xs), _) => xs.alternatives().iter().any(|x| covers(x, narrow)),
+        (Ty::List(x), Ty::List(y)) | (Ty::Iter(x), Ty::Iter(y)) => covers(x, y),
+        (Ty::Dict(x), Ty::Dict(y)) => covers(&x.0, &y.0) && covers(&x.1, &y.1),
+        (Ty::Tuple(xs), Ty::Tuple(ys)) => {
+            xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| covers(x, y))
+        }
+        (Ty::Name(x), Ty::Tuple(_)) => x.as_str() == "tuple",
+        // Changes to function signatures are checked separately
+        (Ty::Function(_), Ty::Function(_)) => true,
+        _ => false,
+    }
+}
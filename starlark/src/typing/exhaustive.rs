@@ -0,0 +1,170 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashSet;
+
+use gazebo::variants::VariantName;
+use thiserror::Error;
+
+use crate::analysis::types::LintWarning;
+use crate::codemap::Span;
+use crate::eval::compiler::scope::CstExpr;
+use crate::eval::compiler::scope::CstStmt;
+use crate::syntax::ast::ArgumentP;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::BinOp;
+use crate::syntax::ast::ExprP;
+use crate::syntax::ast::StmtP;
+use crate::typing::ctx::TypingContext;
+use crate::typing::ty::Ty;
+
+/// Why a chain of `if` statements doesn't handle every case.
+#[derive(Error, Debug, VariantName)]
+pub(crate) enum NonExhaustive {
+    #[error("The `if` chain over `{0}` never handles the values {1}, add them or an `else`")]
+    UnhandledVariants(Ty, String),
+}
+
+impl LintWarning for NonExhaustive {
+    fn is_serious(&self) -> bool {
+        false
+    }
+}
+
+/// The conditions of each `if`/`elif` chain without a final `else`, with the span of
+/// the first condition, given the conditions of all the `if` statements.
+pub(crate) fn if_chains<'a>(
+    conditions: &[(&'a CstExpr, &'a CstStmt, Option<&'a CstStmt>)],
+) -> Vec<(Vec<&'a CstExpr>, Span)> {
+    fn elif(x: &CstStmt) -> Option<(&CstExpr, Option<&CstStmt>)> {
+        match &**x {
+            StmtP::If(cond, _) => Some((cond, None)),
+            StmtP::IfElse(cond, then_else) => Some((cond, Some(&then_else.1))),
+            _ => None,
+        }
+    }
+
+    // Every `elif` is also an `if` statement in its own right, only start at the heads
+    let elifs: HashSet<Span> = conditions
+        .iter()
+        .filter_map(|(_, _, x)| Some(elif((*x)?)?.0.span))
+        .collect();
+    let mut res = Vec::new();
+    'chains: for (cond, _, mut else_block) in conditions {
+        if elifs.contains(&cond.span) {
+            continue;
+        }
+        let mut chain = vec![*cond];
+        while let Some(x) = else_block {
+            match elif(x) {
+                Some((cond, next)) => {
+                    chain.push(cond);
+                    else_block = next;
+                }
+                None => continue 'chains,
+            }
+        }
+        res.push((chain, cond.span));
+    }
+    res
+}
+
+impl TypingContext<'_> {
+    /// The name of the variable, its enum type, and the values of the enum `cond`
+    /// compares it against, if it is such a comparison.
+    fn enum_comparison<'a>(&self, cond: &'a CstExpr) -> Option<(&'a str, Ty, Vec<String>)> {
+        let (lhs, rhs, many) = match &**cond {
+            ExprP::Op(lhs, BinOp::Equal, rhs) => (lhs, rhs, false),
+            ExprP::Op(lhs, BinOp::In, rhs) => (lhs, rhs, true),
+            _ => return None,
+        };
+        let (ident, by_value) = match &***lhs {
+            ExprP::Dot(x, attr) if attr.node == "value" => (&**x, true),
+            _ => (&**lhs, false),
+        };
+        let name = match &**ident {
+            ExprP::Identifier(name, _) => name,
+            _ => return None,
+        };
+        let ty = self.expression_type(ident);
+        if !matches!(ty, Ty::Enum { .. }) {
+            return None;
+        }
+        let values: &[CstExpr] = match &***rhs {
+            ExprP::Tuple(xs) | ExprP::List(xs) if many => xs,
+            _ if many => return None,
+            _ => std::slice::from_ref(&**rhs),
+        };
+        let mut variants = Vec::with_capacity(values.len());
+        for x in values {
+            let literal = if by_value {
+                x
+            } else {
+                // A value of the same enum type, constructed from a literal
+                match &**x {
+                    ExprP::Call(_, args) if self.expression_type(x) == ty => match &args[..] {
+                        [arg] => match &arg.node {
+                            ArgumentP::Positional(x) => x,
+                            _ => return None,
+                        },
+                        _ => return None,
+                    },
+                    _ => return None,
+                }
+            };
+            match &**literal {
+                ExprP::Literal(AstLiteral::String(x)) => variants.push(x.node.clone()),
+                _ => return None,
+            }
+        }
+        Some((name.node.as_str(), ty, variants))
+    }
+
+    /// If every condition of `chain` compares the same variable with values of an enum,
+    /// the values it never compares against.
+    pub(crate) fn unhandled_variants(&self, chain: &[&CstExpr]) -> Option<NonExhaustive> {
+        // A lone `if` is usually handling a special case, not every case
+        if chain.len() < 2 {
+            return None;
+        }
+        let mut handled = HashSet::new();
+        let mut var = None;
+        for cond in chain {
+            let (name, ty, variants) = self.enum_comparison(cond)?;
+            match &var {
+                None => var = Some((name, ty)),
+                Some(x) if x.0 == name && x.1 == ty => {}
+                Some(_) => return None,
+            }
+            handled.extend(variants);
+        }
+        let (_, ty) = var?;
+        let unhandled = match &ty {
+            Ty::Enum { variants } => variants
+                .iter()
+                .filter(|x| !handled.contains(*x))
+                .map(|x| format!("`{:?}`", x))
+                .collect::<Vec<_>>(),
+            _ => return None,
+        };
+        if unhandled.is_empty() {
+            None
+        } else {
+            Some(NonExhaustive::UnhandledVariants(ty, unhandled.join(", ")))
+        }
+    }
+}
@@ -0,0 +1,108 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
+use crate::codemap::Span;
+use crate::typing::ctx::TypingError;
+use crate::typing::ty::Ty;
+
+/// A change to the source that would resolve a type error, e.g. for an editor to offer
+/// as a code action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypingFix {
+    /// What the fix does, e.g. "Change the annotation to `[int.type]`".
+    pub title: String,
+    /// The text to replace, which is empty if the fix inserts text.
+    pub span: FileSpan,
+    /// The text to replace it with.
+    pub replacement: String,
+}
+
+impl TypingFix {
+    /// The fix for an error returned by [`typecheck`](crate::syntax::AstModule::typecheck),
+    /// if there is one.
+    pub fn of(err: &anyhow::Error) -> Option<&TypingFix> {
+        err.downcast_ref::<TypingError>()?.fix()
+    }
+
+    /// Replace the annotation at `span` with one for `got`, if `got` can be written down.
+    pub(crate) fn change_annotation(codemap: &CodeMap, span: Span, got: &Ty) -> Option<Self> {
+        let annotation = annotation(got)?;
+        Some(Self {
+            title: format!("Change the annotation to `{}`", annotation),
+            span: codemap.file_span(span),
+            replacement: annotation,
+        })
+    }
+
+    /// Pass `name` in the call at `span`, with a placeholder value of type `ty`.
+    pub(crate) fn add_argument(codemap: &CodeMap, span: Span, name: &str, ty: &Ty) -> Option<Self> {
+        let call = codemap.source_span(span);
+        let close = call.rfind(')')?;
+        let sep = if call[..close].trim_end().ends_with(['(', ',']) {
+            ""
+        } else {
+            ", "
+        };
+        let pos = span.begin() + close as u32;
+        Some(Self {
+            title: format!("Add missing argument `{}`", name),
+            span: codemap.file_span(Span::new(pos, pos)),
+            replacement: format!("{}{} = {}", sep, name, placeholder(ty)),
+        })
+    }
+}
+
+/// The annotation for a type, in the syntax [`Ty::from_expr`] accepts.
+fn annotation(ty: &Ty) -> Option<String> {
+    let many =
+        |xs: &mut dyn Iterator<Item = &Ty>| -> Option<Vec<String>> { xs.map(annotation).collect() };
+    Some(match ty {
+        Ty::Any => "\"\"".to_owned(),
+        Ty::None => "None".to_owned(),
+        Ty::Name(x) => match x.as_str() {
+            "string" => "str.type".to_owned(),
+            x @ ("int" | "float" | "bool" | "tuple") => format!("{}.type", x),
+            x => format!("{:?}", x),
+        },
+        Ty::List(x) => format!("[{}]", annotation(x)?),
+        Ty::Dict(k_v) => format!("{{{}: {}}}", annotation(&k_v.0)?, annotation(&k_v.1)?),
+        Ty::Tuple(xs) if xs.len() == 1 => format!("({},)", annotation(&xs[0])?),
+        Ty::Tuple(xs) => format!("({})", many(&mut xs.iter())?.join(", ")),
+        // A list of several types is a union
+        Ty::Union(xs) => format!("[{}]", many(&mut xs.alternatives().iter())?.join(", ")),
+        _ => return None,
+    })
+}
+
+/// A value of type `ty` to fill in, which the user is expected to replace.
+fn placeholder(ty: &Ty) -> &'static str {
+    match ty {
+        Ty::List(_) => "[]",
+        Ty::Dict(_) => "{}",
+        Ty::Tuple(_) => "()",
+        Ty::Name(x) => match x.as_str() {
+            "string" => "\"\"",
+            "int" => "0",
+            "float" => "0.0",
+            "bool" => "False",
+            _ => "None",
+        },
+        _ => "None",
+    }
+}
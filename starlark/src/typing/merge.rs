@@ -0,0 +1,72 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::typing::bindings::Interface;
+use crate::typing::ty::Ty;
+
+/// A symbol which the interfaces passed to [`Interface::merge`] give different types.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("`{symbol}` has conflicting types {}", .types.iter().map(|x| format!("`{}`", x)).join(", "))]
+pub struct InterfaceConflict {
+    /// The name of the symbol.
+    pub symbol: String,
+    /// The different types, in the order of the interfaces they first appear in.
+    pub types: Vec<Ty>,
+}
+
+impl Interface {
+    /// Combine the bindings of several interfaces, for example those of the modules making
+    /// up a prelude. A symbol given different types by different interfaces is reported
+    /// as a conflict, ordered by symbol, and has the union of those types in the result.
+    pub fn merge<'a>(
+        interfaces: impl IntoIterator<Item = &'a Interface>,
+    ) -> (Interface, Vec<InterfaceConflict>) {
+        let mut types: BTreeMap<&str, Vec<&Ty>> = BTreeMap::new();
+        for interface in interfaces {
+            for (symbol, ty) in interface.iter() {
+                let xs = types.entry(symbol).or_default();
+                if !xs.contains(&ty) {
+                    xs.push(ty);
+                }
+            }
+        }
+
+        let mut conflicts = Vec::new();
+        let bindings = types
+            .into_iter()
+            .map(|(symbol, types)| {
+                let types: Vec<Ty> = types.into_iter().cloned().collect();
+                let ty = if types.len() == 1 {
+                    types[0].clone()
+                } else {
+                    conflicts.push(InterfaceConflict {
+                        symbol: symbol.to_owned(),
+                        types: types.clone(),
+                    });
+                    Ty::unions(types)
+                };
+                (symbol.to_owned(), ty)
+            })
+            .collect();
+        (Interface::new(bindings), conflicts)
+    }
+}
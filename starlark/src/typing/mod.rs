@@ -24,26 +24,46 @@
 //! Types required to support the [`typecheck`](crate::syntax::AstModule::typecheck) function.
 
 pub(crate) mod bindings;
+pub(crate) mod cache;
+pub(crate) mod coverage;
 pub(crate) mod ctx;
+pub(crate) mod diff;
+pub(crate) mod exhaustive;
+pub(crate) mod fix;
+pub(crate) mod merge;
 pub(crate) mod oracle;
+pub(crate) mod prelude;
+pub(crate) mod profile;
+pub(crate) mod stub;
 pub(crate) mod ty;
 pub(crate) mod typecheck;
+pub(crate) mod unreachable;
 
 #[cfg(test)]
 mod tests;
 
 pub use bindings::Interface;
+pub use cache::InterfaceCache;
+pub use coverage::TypeCoverage;
+pub use diff::BreakingChange;
+pub use fix::TypingFix;
+pub use merge::InterfaceConflict;
+pub use oracle::configurable::OracleConfigurable;
 pub use oracle::docs::OracleDocs;
 pub use oracle::standard::OracleStandard;
 pub use oracle::traits::OracleNoBuiltins;
 pub use oracle::traits::OracleNone;
 pub use oracle::traits::TypingOracle;
+pub use profile::FunctionProfile;
+pub use profile::TypecheckProfile;
 pub use ty::Approximation;
 pub use ty::Arg;
 pub use ty::Param;
 pub use ty::ParamMode;
 pub use ty::Ty;
+pub use ty::TyDisplay;
 pub use ty::TyFunction;
 pub use ty::TyName;
+pub use ty::TyStyle;
 pub use ty::TyUnion;
 pub use typecheck::TypeMap;
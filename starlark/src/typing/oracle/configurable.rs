@@ -0,0 +1,102 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashSet;
+
+use crate::typing::oracle::traits::TypingOracle;
+use crate::typing::ty::Arg;
+use crate::typing::ty::Param;
+use crate::typing::ty::Ty;
+
+/// A [`TypingOracle`] for functions like Bazel's `select`, which take a dict from
+/// conditions to values and produce a value that is only chosen later, once the
+/// configuration is known.
+///
+/// A call to such a function has the union of the types of the dict values, so
+/// `select({":a": ["x.c"], "//conditions:default": []})` is a list of strings and
+/// can be used wherever a list of strings is expected.
+///
+/// Put this oracle before the ones describing the other builtins, so it takes precedence.
+pub struct OracleConfigurable {
+    functions: HashSet<String>,
+}
+
+impl OracleConfigurable {
+    /// An oracle treating the builtins named `functions` as producing configurable values.
+    pub fn new(functions: &[&str]) -> Self {
+        Self {
+            functions: functions.iter().map(|x| (*x).to_owned()).collect(),
+        }
+    }
+
+    fn branch_types(name: &str, branches: &Ty) -> Result<Ty, String> {
+        let mut res = Vec::new();
+        for ty in branches.iter_union() {
+            match ty {
+                Ty::Dict(k_v) => res.push(k_v.1.clone()),
+                Ty::Any => return Ok(Ty::Any),
+                _ => {
+                    return Err(format!(
+                        "Expected `{}` to be called with a dict of values, got `{}`",
+                        name, ty
+                    ));
+                }
+            }
+        }
+        Ok(Ty::unions(res))
+    }
+}
+
+impl TypingOracle for OracleConfigurable {
+    fn builtin(&self, name: &str) -> Option<Result<Ty, ()>> {
+        if !self.functions.contains(name) {
+            return None;
+        }
+        Some(Ok(Ty::special_function(
+            name,
+            vec![
+                Param::pos_only(Ty::dict(Ty::string(), Ty::Any)),
+                Param::kwargs(Ty::Any),
+            ],
+            Ty::Any,
+        )))
+    }
+
+    fn builtin_call(&self, name: &str, args: &[Arg]) -> Option<Result<Ty, String>> {
+        if !self.functions.contains(name) {
+            return None;
+        }
+        let mut branches = None;
+        for arg in args {
+            match arg {
+                Arg::Pos(ty) if branches.is_none() => branches = Some(ty),
+                // Such as `no_match_error` for `select`.
+                Arg::Name(..) | Arg::Kwargs(_) => {}
+                _ => {
+                    return Some(Err(format!(
+                        "Expected `{}` to have one positional argument",
+                        name
+                    )));
+                }
+            }
+        }
+        Some(match branches {
+            Some(branches) => Self::branch_types(name, branches),
+            None => Err(format!("Missing the dict of values for `{}`", name)),
+        })
+    }
+}
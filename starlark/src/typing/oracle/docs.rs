@@ -19,6 +19,8 @@ use std::collections::HashMap;
 
 use crate::docs::Doc;
 use crate::docs::DocItem;
+use crate::environment::FrozenModule;
+use crate::environment::Globals;
 use crate::typing::Ty;
 use crate::typing::TypingOracle;
 
@@ -77,6 +79,64 @@ impl OracleDocs {
             }
         }
     }
+
+    /// Create a new [`OracleDocs`] for the values a [`FrozenModule`] exports, so code evaluated
+    /// with that module as its globals (e.g. a prelude, or native functions registered by an
+    /// embedder) can be typechecked. Values are typed from their documentation, or for plain
+    /// data such as strings and lists, from their runtime type.
+    pub fn new_module(module: &FrozenModule) -> Self {
+        let mut res = Self::default();
+        res.add_module(module);
+        res
+    }
+
+    /// Like [`Self::new_module`], but adding to an existing oracle (overwriting any duplicates).
+    pub fn add_module(&mut self, module: &FrozenModule) {
+        self.functions.extend(module_types(module));
+    }
+
+    /// Add the methods `globals` adds to built-in types, see
+    /// [`GlobalsBuilder::type_extension`](crate::environment::GlobalsBuilder::type_extension).
+    /// Unlike [`Self::add_doc`], the existing attributes of those types are kept.
+    pub fn add_type_extensions(&mut self, globals: &Globals) {
+        for (name, docs) in globals.type_extension_documentation() {
+            if let DocItem::Object(obj) = docs {
+                let items = self.objects.entry(name).or_default();
+                for (name, member) in &obj.members {
+                    items.insert(name.clone(), Ty::from_docs_member(member));
+                }
+            }
+        }
+    }
+}
+
+/// The types of the values a [`FrozenModule`] exports, leaving out those we can't say anything about.
+pub(crate) fn module_types(module: &FrozenModule) -> impl Iterator<Item = (String, Ty)> + '_ {
+    module.names().filter_map(|name| {
+        // Private symbols aren't visible to code using the module
+        let value = module.get(&name).ok()?;
+        let value = value.value();
+        let ty = match value.get_type() {
+            "NoneType" => Ty::None,
+            // These document their methods, which the standard oracle already knows
+            x @ ("string" | "int" | "float" | "bool" | "list" | "dict" | "tuple" | "set"
+            | "bytes") => Ty::name(x),
+            _ => match value.get_ref().documentation() {
+                Some(DocItem::Function(x)) => Ty::from_docs_function(&x),
+                Some(DocItem::Object(obj)) => Ty::Struct {
+                    fields: obj
+                        .members
+                        .iter()
+                        .map(|(name, member)| (name.clone(), Ty::from_docs_member(member)))
+                        .collect(),
+                    extra: false,
+                },
+                // Might be callable, or have attributes, so we can't say anything
+                _ => return None,
+            },
+        };
+        Some((name.as_str().to_owned(), ty))
+    })
 }
 
 impl TypingOracle for OracleDocs {
@@ -91,6 +151,8 @@ impl TypingOracle for OracleDocs {
             Ty::Tuple(_) => "tuple",
             Ty::Dict(_) => "dict",
             Ty::Struct { .. } => "struct",
+            Ty::Record { .. } => "record",
+            Ty::Enum { .. } => "enum",
             _ => return None,
         };
         match self.objects.get(name)?.get(attr) {
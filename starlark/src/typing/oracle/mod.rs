@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+pub(crate) mod configurable;
 pub(crate) mod docs;
 pub(crate) mod standard;
 pub(crate) mod traits;
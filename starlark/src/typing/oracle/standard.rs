@@ -56,6 +56,7 @@ impl OracleStandard {
         }
 
         add::<crate::values::bool::StarlarkBool>(&mut fallback);
+        add::<crate::values::bytes::StarlarkBytes>(&mut fallback);
         add::<crate::values::enumeration::FrozenEnumType>(&mut fallback);
         add::<crate::values::float::StarlarkFloat>(&mut fallback);
         add::<crate::values::int::PointerI32>(&mut fallback);
@@ -70,16 +71,100 @@ impl OracleStandard {
         add::<crate::values::list::value::ListGen<crate::values::list::value::FrozenListData>>(
             &mut fallback,
         );
+        add::<crate::values::types::set::SetGen<crate::values::types::set::FrozenSetData>>(
+            &mut fallback,
+        );
         add::<crate::values::string::StarlarkStr>(&mut fallback);
         add::<crate::values::structs::value::FrozenStruct>(&mut fallback);
         add::<crate::values::tuple::value::FrozenTuple>(&mut fallback);
 
         Self { fallback }
     }
+
+    /// Add the methods `globals` adds to built-in types, see
+    /// [`OracleDocs::add_type_extensions`].
+    pub fn add_type_extensions(&mut self, globals: &Globals) {
+        self.fallback.add_type_extensions(globals);
+    }
+
+    /// The operators on `int` and `float` whose result depends on the type of the right
+    /// operand, which we compute in [`builtin_call`](TypingOracle::builtin_call), so that
+    /// mixing `int` and `float` promotes the result to `float`, as at runtime.
+    fn numeric_operator(ty: &Ty, attr: &str) -> Option<Ty> {
+        let is_int = if ty == &Ty::int() {
+            true
+        } else if ty == &Ty::float() {
+            false
+        } else {
+            return None;
+        };
+        match attr {
+            "__add__" | "__subtract__" | "__multiply__" | "__percent__" | "__divide__"
+            | "__floordivide__" | "__less__" => {}
+            "__bitand__" | "__bitor__" | "__bitxor__" | "__leftshift__" | "__rightshift__"
+                if is_int => {}
+            _ => return None,
+        }
+        let name = format!("{}.{}", if is_int { "int" } else { "float" }, attr);
+        Some(Ty::special_function(
+            &name,
+            vec![Param::pos_only(Ty::Any)],
+            Ty::Any,
+        ))
+    }
+
+    /// The result of `lhs <op> rhs`, where `lhs` is an `int` (or a `float` if `is_float`).
+    fn numeric_result(op: &str, is_float: bool, rhs: &Ty) -> Result<Ty, String> {
+        let rhs_float = match rhs {
+            Ty::Any => return Ok(Ty::Any),
+            Ty::Union(xs) => {
+                let mut res = Vec::new();
+                for x in xs.alternatives() {
+                    // Like other calls, succeed if any alternative might.
+                    if let Ok(t) = Self::numeric_result(op, is_float, x) {
+                        res.push(t);
+                    }
+                }
+                return if res.is_empty() {
+                    Err(format!("Operand of type `{}` is not a number", rhs))
+                } else {
+                    Ok(Ty::unions(res))
+                };
+            }
+            x if x == &Ty::int() => false,
+            x if x == &Ty::float() => true,
+            // Sequence repetition, e.g. `2 * "x"`
+            Ty::List(_) | Ty::Tuple(_) if op == "__multiply__" && !is_float => {
+                return Ok(rhs.clone());
+            }
+            x if op == "__multiply__"
+                && !is_float
+                && (x == &Ty::string() || x == &Ty::name("tuple")) =>
+            {
+                return Ok(rhs.clone());
+            }
+            _ => return Err(format!("Operand of type `{}` is not a number", rhs)),
+        };
+        Ok(match op {
+            "__less__" => Ty::bool(),
+            "__divide__" => Ty::float(),
+            "__bitand__" | "__bitor__" | "__bitxor__" | "__leftshift__" | "__rightshift__" => {
+                if rhs_float {
+                    return Err("Bitwise operators require `int` operands".to_owned());
+                }
+                Ty::int()
+            }
+            _ if is_float || rhs_float => Ty::float(),
+            _ => Ty::int(),
+        })
+    }
 }
 
 impl TypingOracle for OracleStandard {
     fn attribute(&self, ty: &Ty, attr: &str) -> Option<Result<Ty, ()>> {
+        if let Some(res) = Self::numeric_operator(ty, attr) {
+            return Some(Ok(res));
+        }
         // TODO: Don't fall back for __ attributes that we own
         self.fallback.attribute(ty, attr)
     }
@@ -88,6 +173,8 @@ impl TypingOracle for OracleStandard {
         Some(Ok(match name {
             "None" => Ty::None,
             "True" | "False" => Ty::bool(),
+            // Never returns, so code after it is unreachable
+            "fail" => Ty::function(vec![Param::args(Ty::Any)], Ty::Void),
             "zip" => Ty::special_function("zip", vec![Param::args(Ty::Any)], Ty::list(Ty::Any)),
             "struct" => Ty::special_function(
                 "struct",
@@ -138,7 +225,18 @@ impl TypingOracle for OracleStandard {
                 }
                 Some(Ok(Ty::list(Ty::Tuple(res))))
             }
-            _ => None,
+            _ => {
+                let (lhs, op) = name.split_once('.')?;
+                let is_float = match lhs {
+                    "int" => false,
+                    "float" => true,
+                    _ => return None,
+                };
+                match args {
+                    [Arg::Pos(rhs)] => Some(Self::numeric_result(op, is_float, rhs)),
+                    _ => None,
+                }
+            }
         }
     }
 
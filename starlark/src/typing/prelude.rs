@@ -0,0 +1,46 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Symbols that are implicitly in scope, such as those from a prelude.
+
+use crate::environment::FrozenModule;
+use crate::typing::bindings::Interface;
+use crate::typing::oracle::docs::module_types;
+use crate::typing::ty::Ty;
+use crate::typing::TypingOracle;
+
+impl Interface {
+    /// The types of the symbols a [`FrozenModule`] exports, typed from their documentation,
+    /// or for plain data such as strings and lists, from their runtime type. Symbols whose
+    /// type can't be determined are left out.
+    ///
+    /// Used as a [`TypingOracle`] (usually ahead of the standard one), this declares the
+    /// symbols of a prelude to modules which use them without loading them.
+    pub fn from_module(module: &FrozenModule) -> Interface {
+        Interface::new(module_types(module).collect())
+    }
+}
+
+/// The symbols of an [`Interface`] are treated as builtins, so code relying on them
+/// being in scope without a `load`, e.g. from a prelude, can be typechecked. An interface
+/// declaring them can be written with [`Interface::parse_stub`] or generated with
+/// [`Interface::from_module`].
+impl TypingOracle for Interface {
+    fn builtin(&self, name: &str) -> Option<Result<Ty, ()>> {
+        Some(Ok(self.get(name)?.clone()))
+    }
+}
@@ -0,0 +1,124 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! How long typechecking a module took, to find the slowest files and functions.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
+use crate::codemap::Span;
+use crate::eval::compiler::scope::BindingId;
+use crate::eval::compiler::scope::CstStmt;
+use crate::syntax::ast::StmtP;
+
+/// How long each phase of typechecking a module took, and how long solving the types
+/// in each function took, from [`AstModule::typecheck_profile`](crate::syntax::AstModule::typecheck_profile).
+#[derive(Debug, Clone, Default)]
+pub struct TypecheckProfile {
+    /// Giving every identifier a unique binding.
+    pub scope: Duration,
+    /// Collecting the expressions each binding gets its type from.
+    pub bindings: Duration,
+    /// Solving the types of the bindings.
+    pub solve: Duration,
+    /// Checking the expressions and annotations, and looking for lints.
+    pub check: Duration,
+    /// The time spent solving the types of the bindings in each function, slowest first.
+    pub functions: Vec<FunctionProfile>,
+}
+
+/// How long solving the types of the bindings in one function took.
+#[derive(Debug, Clone)]
+pub struct FunctionProfile {
+    /// The name of the function, dotted for nested functions, or `<module>` for
+    /// the bindings at the top level.
+    pub name: String,
+    /// Where the function is defined.
+    pub span: FileSpan,
+    /// The time taken.
+    pub time: Duration,
+}
+
+impl TypecheckProfile {
+    /// The total time taken by all the phases.
+    pub fn total(&self) -> Duration {
+        self.scope + self.bindings + self.solve + self.check
+    }
+}
+
+/// The times recorded while solving the bindings.
+#[derive(Default)]
+pub(crate) struct SolveTimes {
+    pub(crate) solve: Duration,
+    pub(crate) check: Duration,
+    pub(crate) bindings: HashMap<BindingId, Duration>,
+}
+
+/// The functions defined in `x`, with their dotted names.
+fn functions(x: &CstStmt, prefix: &str, res: &mut Vec<(String, Span)>) {
+    match &**x {
+        StmtP::Def(def) => {
+            let name = format!("{}{}", prefix, def.name.0);
+            let prefix = format!("{}.", name);
+            res.push((name, x.span));
+            x.visit_stmt(|x| functions(x, &prefix, res));
+        }
+        _ => x.visit_stmt(|x| functions(x, prefix, res)),
+    }
+}
+
+/// The time spent on each binding, added up by the function containing the span of the binding.
+pub(crate) fn function_profiles(
+    cst: &CstStmt,
+    codemap: &CodeMap,
+    times: &HashMap<BindingId, Duration>,
+    spans: impl Fn(BindingId) -> Option<Span>,
+) -> Vec<FunctionProfile> {
+    let mut defs = Vec::new();
+    functions(cst, "", &mut defs);
+    let mut res: HashMap<Option<usize>, Duration> = HashMap::new();
+    for (id, time) in times {
+        // The innermost function is the smallest one containing the binding. The span of
+        // a def ends where the next statement begins, so the end is excluded.
+        let def = spans(*id).and_then(|span| {
+            defs.iter()
+                .enumerate()
+                .filter(|(_, (_, x))| x.begin() <= span.begin() && span.begin() < x.end())
+                .min_by_key(|(_, (_, x))| x.len())
+                .map(|(i, _)| i)
+        });
+        *res.entry(def).or_default() += *time;
+    }
+    let mut res: Vec<FunctionProfile> = res
+        .into_iter()
+        .map(|(def, time)| {
+            let (name, span) = match def {
+                Some(i) => (defs[i].0.clone(), defs[i].1),
+                None => ("<module>".to_owned(), cst.span),
+            };
+            FunctionProfile {
+                name,
+                span: codemap.file_span(span),
+                time,
+            }
+        })
+        .collect();
+    res.sort_by(|a, b| b.time.cmp(&a.time));
+    res
+}
@@ -0,0 +1,106 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::errors::Diagnostic;
+use crate::syntax::ast::AssignP;
+use crate::syntax::ast::StmtP;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::typing::bindings::function_signature;
+use crate::typing::bindings::Interface;
+use crate::typing::ty::Ty;
+
+#[derive(Debug, Error)]
+enum StubError {
+    #[error(
+        "Interface files may only contain `def` statements, annotated assignments and `load` statements"
+    )]
+    UnsupportedStatement,
+    #[error("Missing type annotation for `{0}`")]
+    MissingAnnotation(String),
+}
+
+impl Interface {
+    /// Parse an interface file, declaring the types of the symbols a module exports,
+    /// for modules that can't be typechecked themselves, such as generated files or
+    /// those written for another dialect. The result can be passed in the `loads` of
+    /// [`typecheck`](AstModule::typecheck) in place of the interface of the module.
+    ///
+    /// An interface file is Starlark, where the bodies of `def` statements and the
+    /// values of annotated assignments are ignored, so can be left out:
+    ///
+    /// ```python
+    /// def compile(srcs: [str.type], out: str.type = None) -> str.type: pass
+    /// VERSION: int.type = 0
+    /// NAME: str.type
+    /// ```
+    ///
+    /// As in a module, symbols starting with `_` are private, so are left out, as are
+    /// the symbols brought in by `load` statements.
+    pub fn parse_stub(filename: &str, content: String) -> anyhow::Result<Interface> {
+        let ast = AstModule::parse(filename, content, &Dialect::Extended)?;
+        // Types we can't describe precisely are just less precise, nothing to report.
+        let mut approximations = Vec::new();
+        let mut res = HashMap::new();
+        for x in ast.top_level_statements() {
+            match &**x {
+                StmtP::Def(def) => {
+                    let (params, _) = function_signature(&def.params, &mut approximations);
+                    let result = Ty::from_expr_opt(&def.return_type, &mut approximations);
+                    res.insert(def.name.0.clone(), Ty::function(params, result));
+                }
+                StmtP::Declare(name, ty) => {
+                    res.insert(name.0.clone(), Ty::from_expr(ty, &mut approximations));
+                }
+                StmtP::Assign(lhs, ty_rhs) => match (&**lhs, &ty_rhs.0) {
+                    (AssignP::Identifier(name), Some(ty)) => {
+                        res.insert(name.0.clone(), Ty::from_expr(ty, &mut approximations));
+                    }
+                    (AssignP::Identifier(name), None) => {
+                        return Err(Diagnostic::new(
+                            StubError::MissingAnnotation(name.0.clone()),
+                            x.span,
+                            &ast.codemap,
+                        ));
+                    }
+                    _ => {
+                        return Err(Diagnostic::new(
+                            StubError::UnsupportedStatement,
+                            x.span,
+                            &ast.codemap,
+                        ));
+                    }
+                },
+                // Loaded symbols are private, and expressions are docstrings
+                StmtP::Load(_) | StmtP::Expression(_) => {}
+                _ => {
+                    return Err(Diagnostic::new(
+                        StubError::UnsupportedStatement,
+                        x.span,
+                        &ast.codemap,
+                    ));
+                }
+            }
+        }
+        res.retain(|name, _| !name.starts_with('_'));
+        Ok(Interface::new(res))
+    }
+}
@@ -17,18 +17,32 @@
 
 use std::collections::HashMap;
 
+use dupe::Dupe;
 use once_cell::sync::Lazy;
 
+use crate as starlark;
+use crate::environment::Globals;
+use crate::environment::GlobalsBuilder;
+use crate::environment::MethodsBuilder;
+use crate::environment::Module;
+use crate::eval::Evaluator;
 use crate::stdlib::LibraryExtension;
 use crate::syntax::AstModule;
 use crate::syntax::Dialect;
 use crate::typing::Approximation;
+use crate::typing::BreakingChange;
 use crate::typing::Interface;
+use crate::typing::InterfaceCache;
+use crate::typing::InterfaceConflict;
+use crate::typing::OracleConfigurable;
+use crate::typing::OracleDocs;
 use crate::typing::OracleNoBuiltins;
 use crate::typing::OracleStandard;
 use crate::typing::Param;
 use crate::typing::Ty;
+use crate::typing::TyStyle;
 use crate::typing::TypeMap;
+use crate::typing::TypingFix;
 use crate::typing::TypingOracle;
 
 fn mk_oracle() -> impl TypingOracle {
@@ -134,3 +148,1045 @@ fn test_false_negative() {
     assert!(approx.is_empty());
     assert!(errs.is_empty());
 }
+
+#[test]
+fn test_configurable() {
+    let oracle: Vec<Box<dyn TypingOracle>> = vec![
+        Box::new(OracleConfigurable::new(&["select"])),
+        Box::new(mk_oracle()),
+    ];
+    let typecheck = |code: &str| {
+        AstModule::parse("filename", code.to_owned(), &Dialect::Extended)
+            .unwrap()
+            .typecheck(&oracle, &HashMap::new())
+    };
+
+    let (errs, _, interface, _) = typecheck(
+        r#"
+srcs = select({":linux": ["a.c"], "//conditions:default": []}, no_match_error = "x")
+mixed = select({":linux": 1, ":mac": "x"})
+"#,
+    );
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(interface.get("srcs").unwrap(), &Ty::list(Ty::string()));
+    assert_eq!(
+        interface.get("mixed").unwrap(),
+        &Ty::union2(Ty::int(), Ty::string())
+    );
+
+    let (errs, _, _, _) = typecheck(r#"x: int.type = select({":linux": "a"})"#);
+    assert_eq!(errs.len(), 1);
+    let (errs, _, _, _) = typecheck(r#"select(["a"])"#);
+    assert_eq!(errs.len(), 1);
+}
+
+#[test]
+fn test_module_docs() {
+    let module = Module::new();
+    {
+        let mut eval = Evaluator::new(&module);
+        let ast = AstModule::parse(
+            "prelude.star",
+            r#"
+def double(x: int.type) -> int.type:
+    return x * 2
+VERSION = "1.0"
+ns = struct(f = double)
+_private = 1
+"#
+            .to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        eval.eval_module(ast, &Globals::extended()).unwrap();
+    }
+    let module = module.freeze().unwrap();
+    let docs = OracleDocs::new_module(&module);
+    assert_eq!(docs.builtin("VERSION"), Some(Ok(Ty::string())));
+    assert_eq!(docs.builtin("_private"), None);
+
+    let oracle: Vec<Box<dyn TypingOracle>> = vec![Box::new(docs), Box::new(mk_oracle())];
+    let typecheck = |code: &str| {
+        AstModule::parse("filename", code.to_owned(), &Dialect::Extended)
+            .unwrap()
+            .typecheck(&oracle, &HashMap::new())
+    };
+    let (errs, _, interface, _) = typecheck("x = double(1)\ny = VERSION.upper()");
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(interface.get("x").unwrap(), &Ty::int());
+    assert_eq!(interface.get("y").unwrap(), &Ty::string());
+
+    let (errs, _, _, _) = typecheck("double(VERSION)");
+    assert_eq!(errs.len(), 1);
+    let (errs, _, _, _) = typecheck("ns.f(1)\nns.g(1)");
+    assert_eq!(errs.len(), 1);
+}
+
+#[starlark_module]
+fn string_extension(builder: &mut MethodsBuilder) {
+    fn shout(this: &str) -> anyhow::Result<String> {
+        Ok(this.to_uppercase())
+    }
+}
+
+#[test]
+fn test_oracle_type_extensions() {
+    let globals = GlobalsBuilder::extended()
+        .with_type_extension("string", string_extension)
+        .build();
+    let mut standard = OracleStandard::new(LibraryExtension::all());
+    standard.add_type_extensions(&globals);
+    let oracle: Vec<Box<dyn TypingOracle>> = vec![Box::new(standard), Box::new(OracleNoBuiltins)];
+    assert_eq!(
+        oracle.attribute(&Ty::string(), "shout"),
+        Some(Ok(Ty::function(vec![], Ty::string())))
+    );
+    // The built-in methods are still there.
+    assert!(matches!(
+        oracle.attribute(&Ty::string(), "upper"),
+        Some(Ok(_))
+    ));
+    assert_eq!(oracle.attribute(&Ty::string(), "whisper"), Some(Err(())));
+
+    let (errs, _, interface, _) = AstModule::parse(
+        "filename",
+        "x = 'a'.shout().lower()".to_owned(),
+        &Dialect::Extended,
+    )
+    .unwrap()
+    .typecheck(&oracle, &HashMap::new());
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(interface.get("x").unwrap(), &Ty::string());
+}
+
+#[test]
+fn test_intersect_and_difference() {
+    let strukt = |fields: &[(&str, Ty)], extra| Ty::Struct {
+        fields: fields
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), v.clone()))
+            .collect(),
+        extra,
+    };
+    let int_or_str = Ty::union2(Ty::int(), Ty::string());
+
+    assert_eq!(int_or_str.intersect(&Ty::int(), None), Ty::int());
+    assert_eq!(int_or_str.intersect(&Ty::Any, None), int_or_str);
+    assert_eq!(Ty::int().intersect(&Ty::string(), None), Ty::Void);
+    assert_eq!(
+        Ty::list(int_or_str.clone()).intersect(&Ty::list(Ty::string()), None),
+        Ty::list(Ty::string())
+    );
+    assert_eq!(
+        Ty::Tuple(vec![Ty::int()]).intersect(&Ty::name("tuple"), None),
+        Ty::Tuple(vec![Ty::int()])
+    );
+
+    // Structs only overlap if they could have the same fields
+    let a = strukt(&[("a", Ty::int())], false);
+    let b = strukt(&[("b", Ty::int())], false);
+    let any = strukt(&[("b", int_or_str.clone())], true);
+    assert_eq!(a.intersect(&b, None), Ty::Void);
+    assert!(!a.intersects(&b, None));
+    assert_eq!(a.intersect(&any, None), Ty::Void);
+    assert_eq!(b.intersect(&any, None), b);
+    assert_eq!(
+        strukt(&[("a", Ty::int())], true).intersect(&any, None),
+        strukt(&[("a", Ty::int()), ("b", int_or_str.clone())], true)
+    );
+
+    assert_eq!(int_or_str.difference(&Ty::int()), Ty::string());
+    assert_eq!(
+        Ty::union2(Ty::list(Ty::int()), Ty::None).difference(&Ty::name("list")),
+        Ty::None
+    );
+    assert_eq!(int_or_str.difference(&Ty::Any), Ty::Void);
+    assert_eq!(Ty::Any.difference(&Ty::int()), Ty::Any);
+}
+
+#[test]
+fn test_lambda() {
+    let (errs, _, interface, approx) = typecheck(
+        r#"
+f = lambda x, y = 1: [x, y]
+z = f("a")
+"#,
+        &HashMap::new(),
+    );
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(
+        interface.get("f").unwrap(),
+        &Ty::function(
+            vec![
+                Param::pos_or_name("x", Ty::Any),
+                Param::pos_or_name("y", Ty::Any).optional(),
+            ],
+            Ty::list(Ty::Any)
+        )
+    );
+    assert_eq!(interface.get("z").unwrap(), &Ty::list(Ty::Any));
+
+    let (errs, _, _, _) = typecheck(
+        r#"
+f = lambda: "x"
+y: int.type = f()
+f(1)
+"#,
+        &HashMap::new(),
+    );
+    assert_eq!(errs.len(), 2, "{:?}", errs);
+}
+
+#[test]
+fn test_narrowing() {
+    let (errs, _, interface, approx) = typecheck(
+        r#"
+def f(x: [None, str.type], y: [int.type, str.type]):
+    a = x.upper() if x else ""
+    if x == None:
+        return
+    b = x.upper()
+    if type(y) == "string":
+        c = y.upper()
+    else:
+        d = range(y)
+    return (a, b, c, d)
+"#,
+        &HashMap::new(),
+    );
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert!(interface.get("f").is_some());
+
+    let (errs, _, _, _) = typecheck(
+        r#"
+def f(x: [None, str.type]):
+    if x == None:
+        hash(x)
+    elif type(x) == "string":
+        hash(x)
+    hash(x) if x != None else hash(x)
+"#,
+        &HashMap::new(),
+    );
+    assert_eq!(errs.len(), 2, "{:?}", errs);
+    assert_eq!(
+        format!("{:#}", errs[0]),
+        r#"Expected type `"string"` but got `None`, at filename:4:9-16"#
+    );
+}
+
+#[test]
+fn test_struct() {
+    let (errs, _, interface, approx) = typecheck(
+        r#"
+s = struct(a = 1, b = "x")
+a = s.a
+either = struct(a = 1) if a else struct(a = "x", c = True)
+c = either.c
+extra = struct(a = 1, **{}).other
+"#,
+        &HashMap::new(),
+    );
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(interface.get("a").unwrap(), &Ty::int());
+    assert_eq!(interface.get("c").unwrap(), &Ty::bool());
+    assert_eq!(interface.get("extra").unwrap(), &Ty::Any);
+
+    let (errs, _, _, _) = typecheck(
+        r#"
+s = struct(a = 1) if True else struct(b = 2)
+s.c
+struct(a = 1).b
+"#,
+        &HashMap::new(),
+    );
+    assert_eq!(errs.len(), 2, "{:?}", errs);
+    assert_eq!(
+        format!("{:#}", errs[0]),
+        r#"The attribute `c` is not available on the type `[struct(a = "int"), struct(b = "int")]`, at filename:3:3-4"#
+    );
+}
+
+#[test]
+fn test_unreachable_branches() {
+    let (errs, typemap, _, _) = typecheck(
+        r#"
+def f(x: None):
+    if x:
+        fail("never")
+    if False:
+        pass
+    elif f:
+        pass
+    else:
+        pass
+    if True:
+        pass
+    if type(x) == "string":
+        pass
+"#,
+        &HashMap::new(),
+    );
+    assert!(errs.is_empty(), "{:?}", errs);
+    let unreachable = typemap
+        .unreachable_branches()
+        .iter()
+        .map(|x| format!("{} {}", x.short_name, x.location))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unreachable,
+        vec![
+            "condition-always-false filename:4:9-22",
+            "condition-always-false filename:6:9-13",
+            "condition-always-true filename:10:9-13",
+        ]
+    );
+}
+
+#[test]
+fn test_unreachable_code() {
+    let (errs, typemap, _, _) = typecheck(
+        r#"
+def always_fails():
+    fail("never")
+def f(x):
+    if x:
+        fail("never")
+        x = 1
+        return x
+    always_fails()
+    print(x)
+def g():
+    fail("last")
+"#,
+        &HashMap::new(),
+    );
+    assert!(errs.is_empty(), "{:?}", errs);
+    let unreachable = typemap
+        .unreachable_code()
+        .iter()
+        .map(|x| format!("{} {}", x.short_name, x.location))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unreachable,
+        // The result type of `always_fails` isn't inferred, so only `fail` counts
+        vec!["after-never-returns filename:7:9-8:17"]
+    );
+
+    // A statement with a type error is reported as such, not as never returning.
+    let (errs, typemap, _, _) = typecheck(
+        r#"
+def f(x):
+    hash(1)
+    return x
+"#,
+        &HashMap::new(),
+    );
+    assert_eq!(errs.len(), 1, "{:?}", errs);
+    assert!(
+        typemap.unreachable_code().is_empty(),
+        "{:?}",
+        typemap.unreachable_code()
+    );
+}
+
+#[test]
+fn test_record_and_enum() {
+    let (errs, _, interface, approx) = typecheck(
+        r#"
+Host = record(name = str.type, port = field(int.type, 80))
+Color = enum("red", "green")
+host = Host(name = "localhost")
+port = host.port
+color = Color("red")
+index = color.index
+"#,
+        &HashMap::new(),
+    );
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(interface.get("port").unwrap(), &Ty::int());
+    assert_eq!(interface.get("index").unwrap(), &Ty::int());
+    assert_eq!(
+        interface.get("color").unwrap().to_string(),
+        r#"enum("red", "green")"#
+    );
+    assert_eq!(
+        interface.get("host").unwrap().to_string(),
+        r#"record(name = "string", port = "int")"#
+    );
+
+    let (errs, _, _, _) = typecheck(
+        r#"
+Host = record(name = str.type, port = field(int.type, 80))
+Color = enum("red", "green")
+Host(name = "localhost", port = "80")
+Host(port = 80)
+Host(name = "localhost").address
+Color("purple")
+"#,
+        &HashMap::new(),
+    );
+    let errs: Vec<_> = errs.iter().map(|x| format!("{:#}", x)).collect();
+    assert_eq!(errs.len(), 4, "{:?}", errs);
+    assert_eq!(
+        errs[3],
+        r#"The value `purple` is not one of `enum("red", "green")`, at filename:7:7-15"#
+    );
+}
+
+#[test]
+fn test_non_exhaustive() {
+    let non_exhaustive = |code: &str| {
+        let (errs, typemap, _, _) = typecheck(code, &HashMap::new());
+        assert!(errs.is_empty(), "{:?}", errs);
+        typemap
+            .non_exhaustive()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let warnings = non_exhaustive(
+        r#"
+Color = enum("red", "green", "blue")
+color = Color("red")
+if color == Color("red"):
+    pass
+elif color == Color("green"):
+    pass
+"#,
+    );
+    assert_eq!(
+        warnings,
+        vec![
+            r#"filename:4:4-25: The `if` chain over `enum("red", "green", "blue")` never handles the values `"blue"`, add them or an `else`"#
+        ]
+    );
+
+    let warnings = non_exhaustive(
+        r#"
+Color = enum("red", "green", "blue")
+color = Color("red")
+if color.value == "red":
+    pass
+elif color in (Color("green"), Color("blue")):
+    pass
+if color == Color("red"):
+    pass
+elif color == Color("green"):
+    pass
+else:
+    pass
+if color == Color("red"):
+    pass
+other = Color("green")
+if color == Color("red"):
+    pass
+elif other == Color("green"):
+    pass
+"#,
+    );
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+#[test]
+fn test_generics() {
+    let (errs, _, interface, _) = typecheck(
+        r#"
+def head(xs: ["_a"]) -> "_a":
+    return xs[0]
+def pick(default: "_a", x: [None, "_a"]) -> "_a":
+    return default if x == None else x
+def swap(x: ("_a", "_b")) -> ("_b", "_a"):
+    return (x[1], x[0])
+a = head([1, 2])
+b = head(["x"]).upper()
+c = pick(1, None)
+d = swap((1, "x"))
+e = head(xs = [])
+"#,
+        &HashMap::new(),
+    );
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(interface.get("a").unwrap(), &Ty::int());
+    assert_eq!(interface.get("b").unwrap(), &Ty::string());
+    assert_eq!(interface.get("c").unwrap(), &Ty::int());
+    assert_eq!(
+        interface.get("d").unwrap(),
+        &Ty::Tuple(vec![Ty::string(), Ty::int()])
+    );
+    assert_eq!(interface.get("e").unwrap(), &Ty::Any);
+    assert_eq!(
+        interface.get("head").unwrap().to_string(),
+        r#"def(#xs: ["_a"]) -> "_a""#
+    );
+
+    let (errs, _, _, _) = typecheck(
+        r#"
+def head(xs: ["_a"]) -> "_a":
+    return xs[0]
+hash(head([1]))
+"#,
+        &HashMap::new(),
+    );
+    assert_eq!(errs.len(), 1, "{:?}", errs);
+
+    // A type variable may be a function, and `**kwargs` is always a dictionary.
+    let (errs, _, _, _) = typecheck(
+        r#"
+def call(f: "_f", **kwargs: "_k"):
+    return f(1, **kwargs) if kwargs.get("x") else f()
+"#,
+        &HashMap::new(),
+    );
+    assert!(errs.is_empty(), "{:?}", errs);
+}
+
+#[test]
+fn test_callable_annotation() {
+    let (errs, _, interface, approx) = typecheck(
+        r#"
+def apply(f: "def(int, name: str = ..) -> bool", x: int.type) -> bool.type:
+    return f(x, name = "x")
+def positive(x, name = ""):
+    return x > 0
+ok = apply(positive, 1)
+also = apply(lambda x, *args, **kwargs: True, 1)
+"#,
+        &HashMap::new(),
+    );
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(interface.get("ok").unwrap(), &Ty::bool());
+    assert_eq!(
+        interface.get("apply").unwrap().to_string(),
+        r#"def(#f: def(#: "int", #name: "string"=..) -> "bool", #x: "int") -> "bool""#
+    );
+    assert_eq!(
+        interface
+            .get("apply")
+            .unwrap()
+            .display_with(TyStyle::Annotation)
+            .to_string(),
+        "def(f: def(int, name: str = ..) -> bool, x: int) -> bool"
+    );
+
+    let (errs, _, _, _) = typecheck(
+        r#"
+def apply(f: "def(int, name: str = ..) -> bool", x: int.type) -> bool.type:
+    return f(x)
+apply(lambda: True, 1)
+apply(lambda x, y, z: True, 1)
+apply(lambda x, name = "": "x", 1)
+apply(lambda x, name = "": True, 1)
+"#,
+        &HashMap::new(),
+    );
+    let errs: Vec<_> = errs.iter().map(|x| format!("{:#}", x)).collect();
+    assert_eq!(errs.len(), 3, "{:?}", errs);
+}
+
+#[test]
+fn test_new_style_annotations() {
+    let (errs, _, interface, approx) = typecheck(
+        r#"
+def f(xs: list[str], d: dict[str, int | None], t: tuple[int, bool]) -> str | None:
+    return None
+y = f(["x"], {"x": None}, (1, True))
+"#,
+        &HashMap::new(),
+    );
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(
+        interface.get("y").unwrap(),
+        &Ty::union2(Ty::string(), Ty::None)
+    );
+    assert_eq!(
+        interface.get("f").unwrap().to_string(),
+        r#"def(#xs: ["string"], #d: {"string": ["int", None]}, #t: ("int", "bool")) -> ["string", None]"#
+    );
+    assert_eq!(
+        interface
+            .get("f")
+            .unwrap()
+            .display_with(TyStyle::Annotation)
+            .to_string(),
+        "def(xs: list[str], d: dict[str, int | None], t: tuple[int, bool]) -> str | None"
+    );
+
+    let (errs, _, _, _) = typecheck(
+        r#"
+def f(xs: list[str]) -> int:
+    return len(xs)
+f([1])
+"#,
+        &HashMap::new(),
+    );
+    assert_eq!(errs.len(), 1, "{:?}", errs);
+}
+
+#[test]
+fn test_annotation_style() {
+    let (errs, _, interface, approx) = typecheck(
+        r#"
+def f(x: "_a", *args: int, flag: bool = False, **kwargs: str) -> "_a":
+    return x
+def g(xs: list[tuple[int, str]], t: tuple[()], d: dict[str, list[str]]) -> None:
+    pass
+"#,
+        &HashMap::new(),
+    );
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    let render = |name: &str| {
+        interface
+            .get(name)
+            .unwrap()
+            .display_with(TyStyle::Annotation)
+            .to_string()
+    };
+    assert_eq!(
+        render("f"),
+        r#"def(x: "_a", *args: int, flag: bool = .., **kwargs: str) -> "_a""#
+    );
+    assert_eq!(
+        render("g"),
+        "def(xs: list[tuple[int, str]], t: tuple[()], d: dict[str, list[str]]) -> None"
+    );
+    assert_eq!(
+        Ty::Iter(Box::new(Ty::Any))
+            .display_with(TyStyle::Annotation)
+            .to_string(),
+        "typing.Iterable[typing.Any]"
+    );
+    // The legacy style is what `Display` produces.
+    let ty = interface.get("g").unwrap();
+    assert_eq!(ty.display_with(TyStyle::Legacy).to_string(), ty.to_string());
+}
+
+#[test]
+fn test_set() {
+    let dialect = Dialect {
+        enable_set_literals: true,
+        ..Dialect::Extended
+    };
+    let (errs, _, interface, approx) = AstModule::parse(
+        "filename",
+        r#"
+s = {1, 2}
+s.add(3)
+t = s.union(set([4]))
+b = s.issubset([1])
+"#
+        .to_owned(),
+        &dialect,
+    )
+    .unwrap()
+    .typecheck(&mk_oracle(), &HashMap::new());
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    assert_eq!(interface.get("s").unwrap(), &Ty::name("set"));
+    assert_eq!(interface.get("t").unwrap(), &Ty::name("set"));
+    assert_eq!(interface.get("b").unwrap(), &Ty::bool());
+
+    let (errs, _, _, _) = typecheck("set([1]).append(2)", &HashMap::new());
+    assert_eq!(errs.len(), 1, "{:?}", errs);
+}
+
+#[test]
+fn test_stub_interface() {
+    let stub = Interface::parse_stub(
+        "generated.star-interface",
+        r#"
+"""Generated rules."""
+load("other.bzl", "helper")
+def compile(srcs: [str.type], *, out: str.type = None) -> str.type:
+    pass
+VERSION: int.type = 0
+_internal: int.type = 0
+"#
+        .to_owned(),
+    )
+    .unwrap();
+    assert_eq!(stub.get("VERSION"), Some(&Ty::int()));
+    assert_eq!(stub.get("_internal"), None);
+    assert_eq!(stub.get("helper"), None);
+
+    let (errs, _, interface, _) = typecheck(
+        r#"
+load("generated.bzl", "compile", "VERSION")
+out = compile(["a.c"], out = "a.o")
+compile("a.c")
+hash(VERSION)
+"#,
+        &hashmap!["generated.bzl".to_owned() => stub],
+    );
+    let errs: Vec<_> = errs.iter().map(|x| format!("{:#}", x)).collect();
+    assert_eq!(errs.len(), 2, "{:?}", errs);
+    assert_eq!(interface.get("out"), Some(&Ty::string()));
+
+    let err = Interface::parse_stub("x", "x = 1".to_owned()).unwrap_err();
+    assert!(err.to_string().contains("Missing type annotation for `x`"));
+    assert!(Interface::parse_stub("x", "print(1)\nif True:\n  pass".to_owned()).is_err());
+}
+
+#[test]
+fn test_prelude_interface() {
+    let prelude = Interface::parse_stub(
+        "prelude.star-interface",
+        "def rule(name: str.type) -> None: pass\nPLATFORM: str.type".to_owned(),
+    )
+    .unwrap();
+    let code = "rule(name = PLATFORM.upper())\nrule(name = 1)";
+    let typecheck = |oracle: &dyn TypingOracle| {
+        AstModule::parse("filename", code.to_owned(), &Dialect::Extended)
+            .unwrap()
+            .typecheck(oracle, &HashMap::new())
+            .0
+            .iter()
+            .map(|x| format!("{:#}", x))
+            .collect::<Vec<_>>()
+    };
+
+    let errs = typecheck(&mk_oracle());
+    assert_eq!(errs.len(), 3, "{:?}", errs);
+    assert!(
+        errs.iter().all(|x| x.contains("is not known")),
+        "{:?}",
+        errs
+    );
+
+    let oracle: Vec<Box<dyn TypingOracle>> = vec![Box::new(prelude), Box::new(mk_oracle())];
+    let errs = typecheck(&oracle);
+    assert_eq!(errs.len(), 1, "{:?}", errs);
+    assert!(errs[0].contains("filename:2:"), "{:?}", errs);
+
+    let module = Module::new();
+    module.set("VERSION", module.heap().alloc("1.0"));
+    module.set_private(
+        module.frozen_heap().alloc_str("hidden"),
+        module.heap().alloc(1),
+    );
+    let interface = Interface::from_module(&module.freeze().unwrap());
+    assert_eq!(interface.get("VERSION"), Some(&Ty::string()));
+    assert_eq!(interface.get("hidden"), None);
+    assert_eq!(interface.builtin("VERSION"), Some(Ok(Ty::string())));
+    assert_eq!(interface.builtin("other"), None);
+}
+
+#[test]
+fn test_interface_cache() {
+    let code = r#"
+def f(x: int.type) -> [str.type]:
+    return [str(x)]
+s = struct(a = 1)
+"#;
+    let (_, _, interface, _) = typecheck(code, &HashMap::new());
+    let json = serde_json::to_string(&interface).unwrap();
+    let decoded: Interface = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.get("f"), interface.get("f"));
+    assert_eq!(decoded.get("s"), interface.get("s"));
+    assert_eq!(json, serde_json::to_string(&decoded).unwrap());
+
+    let loads = hashmap!["lib.bzl".to_owned() => interface.dupe()];
+    let digest = InterfaceCache::digest(code, &loads);
+    let mut cache = InterfaceCache::new();
+    cache.insert("main.bzl", digest, interface);
+    let cache: InterfaceCache =
+        serde_json::from_str(&serde_json::to_string(&cache).unwrap()).unwrap();
+    assert!(cache.get("main.bzl", digest).is_some());
+    assert!(cache.get("other.bzl", digest).is_none());
+
+    // Changing either the content or a loaded interface invalidates the entry
+    assert_ne!(InterfaceCache::digest("x = 1", &loads), digest);
+    let changed = hashmap!["lib.bzl".to_owned() => Interface::empty()];
+    assert_ne!(InterfaceCache::digest(code, &changed), digest);
+    assert_eq!(InterfaceCache::digest(code, &loads), digest);
+}
+
+#[test]
+fn test_int_plus_float() {
+    let (errs, _, interface, approx) = typecheck(
+        r#"
+a = 1 + 1.0
+b = 1.0 - 2
+c = 2 * 1.5
+d = 1 / 2
+e = 1 < 2.5
+f = 1 + 2
+g = 1.5 >= 1
+h = 7 // 2.0
+"#,
+        &HashMap::new(),
+    );
+    assert!(approx.is_empty(), "{:?}", approx);
+    assert!(errs.is_empty(), "{:?}", errs);
+    for x in ["a", "b", "c", "d", "h"] {
+        assert_eq!(interface.get(x).unwrap(), &Ty::float(), "{}", x);
+    }
+    assert_eq!(interface.get("e").unwrap(), &Ty::bool());
+    assert_eq!(interface.get("f").unwrap(), &Ty::int());
+    assert_eq!(interface.get("g").unwrap(), &Ty::bool());
+}
+
+#[test]
+fn test_fixes() {
+    let code = r#"
+def f(x: int.type, y: str.type) -> int.type:
+    return x
+a: int.type = ["x"]
+b = f(1)
+c = f(y = "a", )
+d: str.type = 1 == 1
+"#;
+    let (errs, _, _, _) = typecheck(code, &HashMap::new());
+    let mut fixes: Vec<_> = errs
+        .iter()
+        .map(|e| {
+            let x = TypingFix::of(e).unwrap();
+            (
+                x.span.resolve_span().begin_line,
+                x.title.as_str(),
+                x.replacement.as_str(),
+            )
+        })
+        .collect();
+    fixes.sort();
+    assert_eq!(
+        fixes,
+        vec![
+            (3, "Change the annotation to `[str.type]`", "[str.type]"),
+            (4, "Add missing argument `y`", ", y = \"\""),
+            (5, "Add missing argument `x`", "x = 0"),
+            (6, "Change the annotation to `bool.type`", "bool.type"),
+        ]
+    );
+    // Fixes that insert text are at the closing bracket of the call
+    let fix = errs
+        .iter()
+        .filter_map(TypingFix::of)
+        .find(|x| x.title.contains("`x`"))
+        .unwrap();
+    assert_eq!(fix.span.source_span(), "");
+    let offset = code.find("c = f(y = \"a\", )").unwrap() + "c = f(y = \"a\", ".len();
+    assert_eq!(
+        fix.span.resolve_span().begin_column,
+        offset - code.find("c =").unwrap()
+    );
+}
+
+#[test]
+fn test_unchecked_def() {
+    let (errs, _, interface, _) = typecheck(
+        r#"
+# @unchecked
+def dynamic(x: int.type) -> str.type:
+    return x.no_such_field + 1
+
+def inline(x: int.type) -> str.type: # @unchecked
+    return x["a"]
+
+def checked(x: int.type) -> str.type:
+    return x
+
+a = dynamic(1)
+b = inline("wrong")
+"#,
+        &HashMap::new(),
+    );
+    // The bodies of `dynamic` and `inline` are skipped, but calls are still checked
+    let mut errs: Vec<_> = errs.iter().map(|x| x.to_string()).collect();
+    errs.sort();
+    assert_eq!(
+        errs,
+        vec![
+            "Expected type `\"int\"` but got `\"string\"`, at filename:13:5-20",
+            "Expected type `\"string\"` but got `\"int\"`, at filename:10:5-13",
+        ]
+    );
+    assert_eq!(interface.get("a").unwrap(), &Ty::string());
+}
+
+#[test]
+fn test_declared_variables() {
+    let (errs, _, interface, _) = typecheck(
+        r#"
+x: int.type
+x = 1
+y: str.type = "a"
+if x:
+    y = 2
+z: [int.type]
+"#,
+        &HashMap::new(),
+    );
+    let errs: Vec<_> = errs.iter().map(|x| x.to_string()).collect();
+    assert_eq!(
+        errs,
+        vec!["Expected type `\"string\"` but got `\"int\"`, at filename:6:9-10"]
+    );
+    assert_eq!(interface.get("x").unwrap(), &Ty::int());
+    assert_eq!(interface.get("z").unwrap(), &Ty::list(Ty::int()));
+}
+
+#[test]
+fn test_breaking_changes() {
+    let interface = |code| typecheck(code, &HashMap::new()).2;
+    let changes = |old, new| -> Vec<String> {
+        interface(old)
+            .breaking_changes(&interface(new))
+            .iter()
+            .map(|x| x.to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        changes(
+            "def f(a, b: int.type, c = 1): pass\nX = 1",
+            "def f(a, b: [int.type, str.type], c = 1, d = 2, *args, **kwargs): pass\nX = 2\nY = 1",
+        ),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        changes(
+            r#"
+def f(a, b: [int.type, str.type], c = 1): pass
+def g(a, b): pass
+def h(x): pass
+X = 1
+Y = 1
+"#,
+            r#"
+def f(a, b: int.type, c): pass
+def g(b, a): pass
+def h(*, x): pass
+X = "1"
+"#,
+        ),
+        vec![
+            "`X` changed type from `\"int\"` to `\"string\"`",
+            "`Y` was removed",
+            "`f` parameter `b` narrowed type from `[\"int\", \"string\"]` to `\"int\"`",
+            "`f` has new required parameter `c`",
+            "`g` parameter `a` can't be passed by position the way it used to",
+            "`g` parameter `b` can't be passed by position the way it used to",
+            "`h` parameter `x` can't be passed by position the way it used to",
+        ]
+    );
+    assert_eq!(
+        interface("def f(a): pass").breaking_changes(&interface("def f(): pass")),
+        vec![BreakingChange::ParameterRemoved {
+            symbol: "f".to_owned(),
+            param: "a".to_owned()
+        }]
+    );
+}
+
+#[test]
+fn test_splat_arguments() {
+    let (errs, _, _, _) = typecheck(
+        r#"
+def named(**kwargs: {str.type: str.type}):
+    pass
+def positional(x: int.type, *args: int.type):
+    pass
+def mixed(x: int.type, y: str.type = "", **kwargs: {str.type: bool.type}):
+    pass
+def ok():
+    named(**{"a": "b"})
+    positional(*[1, 2])
+    positional(1, *(2, 3))
+    mixed(**{"y": "a"})
+    mixed(1, **{"z": True})
+def bad():
+    named(**{"a": 1})
+    positional(*["a"])
+    positional(1, *("a",))
+    mixed(1, **{"z": 1})
+"#,
+        &HashMap::new(),
+    );
+    let mut errs: Vec<_> = errs.iter().map(|x| x.to_string()).collect();
+    errs.sort();
+    assert_eq!(
+        errs,
+        vec![
+            "Expected type `\"int\"` but got `\"string\"`, at filename:16:5-23",
+            "Expected type `\"int\"` but got `\"string\"`, at filename:17:5-27",
+            "Expected type `\"string\"` but got `\"int\"`, at filename:15:5-22",
+            // The name `z` can't go to `y`, but we don't know the names in a splat
+            "Expected type `[\"bool\", \"string\"]` but got `\"int\"`, at filename:18:5-25",
+        ]
+    );
+}
+
+#[test]
+fn test_typecheck_profile() {
+    let ast = AstModule::parse(
+        "filename",
+        r#"
+x = 1
+def f():
+    y = 2
+    def g():
+        z = 3
+        return z
+    return y
+z = f()
+"#
+        .to_owned(),
+        &Dialect::Extended,
+    )
+    .unwrap();
+    let ((errs, _, _, _), profile) = ast.typecheck_profile(&mk_oracle(), &HashMap::new());
+    assert!(errs.is_empty());
+    let mut names: Vec<&str> = profile.functions.iter().map(|x| x.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["<module>", "f", "f.g"]);
+    assert!(profile.total() >= profile.solve);
+}
+
+#[test]
+fn test_interface_merge() {
+    let a = Interface::new(hashmap![
+        "x".to_owned() => Ty::int(),
+        "f".to_owned() => Ty::string(),
+    ]);
+    let b = Interface::new(hashmap![
+        "x".to_owned() => Ty::int(),
+        "f".to_owned() => Ty::None,
+        "y".to_owned() => Ty::bool(),
+    ]);
+    assert_eq!(
+        b.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+        vec!["f", "x", "y"]
+    );
+    assert_eq!(b.len(), 3);
+    assert!(Interface::empty().is_empty());
+
+    let (merged, conflicts) = Interface::merge([&a, &b]);
+    assert_eq!(merged.len(), 3);
+    assert_eq!(merged.get("x"), Some(&Ty::int()));
+    assert_eq!(merged.get("y"), Some(&Ty::bool()));
+    assert_eq!(
+        merged.get("f"),
+        Some(&Ty::unions(vec![Ty::string(), Ty::None]))
+    );
+    assert_eq!(
+        conflicts,
+        vec![InterfaceConflict {
+            symbol: "f".to_owned(),
+            types: vec![Ty::string(), Ty::None],
+        }]
+    );
+    assert_eq!(
+        conflicts[0].to_string(),
+        "`f` has conflicting types `\"string\"`, `None`"
+    );
+    assert!(Interface::merge([&a, &a]).1.is_empty());
+}
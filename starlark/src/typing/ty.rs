@@ -20,16 +20,19 @@ use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
 
+use dupe::Dupe;
 use either::Either;
 use gazebo::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::docs;
-use crate::eval::compiler::scope::CstExpr;
 use crate::syntax::ast::AstExpr;
 use crate::syntax::ast::AstExprP;
 use crate::syntax::ast::AstLiteral;
 use crate::syntax::ast::AstPayload;
 use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::BinOp;
 use crate::syntax::ast::ExprP;
 use crate::syntax::ast::StmtP;
 use crate::syntax::AstModule;
@@ -69,14 +72,15 @@ pub enum Arg {
     Pos(Ty),
     /// A named argument.
     Name(String, Ty),
-    /// A `*args`.
+    /// A `*args`, with the type of its elements.
     Args(Ty),
-    /// A `**kwargs`.
+    /// A `**kwargs`, with the type of its values.
     Kwargs(Ty),
 }
 
 /// The type of a parameter - can be positional, by name, `*args` or `**kwargs`.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Serialize, Deserialize)]
 pub enum ParamMode {
     /// Parameter can only be passed by position.
     PosOnly,
@@ -91,7 +95,8 @@ pub enum ParamMode {
 }
 
 /// A parameter argument to a function
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Serialize, Deserialize)]
 pub struct Param {
     /// The type of parameter
     pub mode: ParamMode,
@@ -182,13 +187,18 @@ impl Param {
 }
 
 /// A Starlark type.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Serialize, Deserialize)]
 pub enum Ty {
     /// Type that can't be inhabited.
     /// If an expression has this type, then the code cannot be reached.
     Void,
     /// Type that contain anything
     Any,
+    /// A type variable, written as a type name starting with `_`, such as `"_a"`.
+    /// Each call to a function mentioning it in its signature replaces it with the
+    /// types of the corresponding arguments, otherwise it behaves like [`Ty::Any`].
+    Var(String),
     /// A series of alternative types.
     Union(TyUnion),
     /// A name, represented by `"name"` in the Starlark type.
@@ -216,10 +226,21 @@ pub enum Ty {
     },
     /// A `function`.
     Function(TyFunction),
+    /// A value created by calling a record type, as made by `record(...)`.
+    Record {
+        /// The fields of the record, with their types.
+        fields: BTreeMap<String, Ty>,
+    },
+    /// A value created by calling an enum type, as made by `enum(...)`.
+    Enum {
+        /// The values of the enum, in the order they were declared.
+        variants: Vec<String>,
+    },
 }
 
 /// The name of an atomic type.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Serialize, Deserialize)]
 pub struct TyName(String);
 
 impl Display for TyName {
@@ -243,7 +264,8 @@ impl TyName {
 
 /// A series of types that are unioned together.
 /// Must be at least two elements, all distinct elements, with no nested `Union` types directly inside it.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Serialize, Deserialize)]
 pub struct TyUnion(Vec<Ty>);
 
 impl Display for TyUnion {
@@ -266,7 +288,8 @@ impl TyUnion {
 }
 
 /// A function.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Serialize, Deserialize)]
 pub struct TyFunction {
     /// The name of the function. Typically `""`, but for a few special builtin functions the name
     /// is used later to call [`TypingOracle::builtin_call`](crate::typing::TypingOracle::builtin_call).
@@ -279,6 +302,60 @@ pub struct TyFunction {
     pub result: Box<Ty>,
 }
 
+impl TyFunction {
+    /// Replace the type variables in the signature by their types in `bindings`.
+    pub(crate) fn instantiate(&self, bindings: &BTreeMap<String, Ty>) -> TyFunction {
+        TyFunction {
+            name: self.name.clone(),
+            type_attr: self.type_attr.clone(),
+            params: self.params.map(|x| Param {
+                ty: x.ty.instantiate(bindings),
+                ..x.clone()
+            }),
+            result: Box::new(self.result.instantiate(bindings)),
+        }
+    }
+
+    /// Might a function of one of these types be called as though it had the other.
+    /// Signatures with `*args` or `**kwargs` are too loose to compare the parameters.
+    fn intersects(&self, other: &TyFunction, ctx: Option<&TypingContext>) -> bool {
+        let loose = |x: &TyFunction| x.params.iter().any(|x| x.allows_many());
+        (loose(self)
+            || loose(other)
+            || (self.params_supplied_by(other, ctx) && other.params_supplied_by(self, ctx)))
+            && self.result.intersects(&other.result, ctx)
+    }
+
+    /// Would the parameters of `self` be filled in when called as per `other`: every
+    /// required one must have a counterpart, by position or name, of an overlapping type.
+    fn params_supplied_by(&self, other: &TyFunction, ctx: Option<&TypingContext>) -> bool {
+        let other_pos: Vec<_> = other.params.iter().filter(|x| x.allows_pos()).collect();
+        fn named(x: &Param) -> Option<&str> {
+            match &x.mode {
+                ParamMode::PosOrName(x) | ParamMode::NameOnly(x) => Some(x),
+                _ => None,
+            }
+        }
+        let mut pos = 0;
+        self.params.iter().all(|param| {
+            let by_pos = if param.allows_pos() {
+                pos += 1;
+                other_pos.get(pos - 1).copied()
+            } else {
+                None
+            };
+            let counterpart = by_pos.or_else(|| {
+                let name = named(param)?;
+                other.params.iter().find(|x| named(x) == Some(name))
+            });
+            match counterpart {
+                Some(x) => param.ty.intersects(&x.ty, ctx),
+                None => param.optional,
+            }
+        })
+    }
+}
+
 fn merge_adjacent<T>(xs: Vec<T>, f: impl Fn(T, T) -> Either<T, (T, T)>) -> Vec<T> {
     let mut res = Vec::new();
     let mut last = None;
@@ -391,6 +468,11 @@ impl Ty {
         self == &Ty::Void
     }
 
+    /// Is this [`Ty::Any`], or a type variable which acts like it.
+    pub(crate) fn is_any_or_var(&self) -> bool {
+        matches!(self, Ty::Any | Ty::Var(_))
+    }
+
     pub(crate) fn is_list(&self) -> bool {
         matches!(self, Ty::List(_))
     }
@@ -461,6 +543,128 @@ impl Ty {
         }
     }
 
+    /// Would a value of this type have `type(x) == name`. Only meaningful on non-union types.
+    pub(crate) fn has_type_name(&self, name: &str) -> bool {
+        match (self, Ty::name(name)) {
+            (Ty::Name(a), Ty::Name(b)) => *a == b,
+            (Ty::Tuple(_), Ty::Name(b)) => b.as_str() == "tuple",
+            (Ty::Record { .. }, Ty::Name(b)) => b.as_str() == "record",
+            (Ty::Enum { .. }, Ty::Name(b)) => b.as_str() == "enum",
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(&b),
+        }
+    }
+
+    /// Restrict this type to the values where `type(x) == name` is `matches`.
+    pub(crate) fn narrow_type_name(&self, name: &str, matches: bool) -> Ty {
+        if matches {
+            self.intersect(&Ty::name(name), None)
+        } else {
+            self.difference(&Ty::name(name))
+        }
+    }
+
+    /// The values which are of both types, as precisely as we can tell.
+    pub(crate) fn intersect(&self, other: &Self, ctx: Option<&TypingContext>) -> Ty {
+        if self.is_any_or_var() {
+            return other.clone();
+        }
+        if other.is_any_or_var() {
+            return self.clone();
+        }
+        let mut res = Vec::new();
+        for x in self.iter_union() {
+            for y in other.iter_union() {
+                res.push(x.intersect_alternative(y, ctx));
+            }
+        }
+        Ty::unions(res)
+    }
+
+    /// Like [`intersect`](Self::intersect), for types which aren't unions.
+    fn intersect_alternative(&self, other: &Self, ctx: Option<&TypingContext>) -> Ty {
+        match (self, other) {
+            (Ty::Any | Ty::Var(_), y) => y.clone(),
+            (x, Ty::Any | Ty::Var(_)) => x.clone(),
+            (Ty::List(x), Ty::List(y)) => match x.intersect(y, ctx) {
+                Ty::Void if !x.is_void() && !y.is_void() => Ty::Void,
+                xy => Ty::list(xy),
+            },
+            (Ty::Dict(x), Ty::Dict(y)) => {
+                match (x.0.intersect(&y.0, ctx), x.1.intersect(&y.1, ctx)) {
+                    (Ty::Void, _) | (_, Ty::Void) => Ty::Void,
+                    (k, v) => Ty::dict(k, v),
+                }
+            }
+            (Ty::Tuple(xs), Ty::Tuple(ys)) if xs.len() == ys.len() => {
+                let xys = std::iter::zip(xs, ys)
+                    .map(|(x, y)| x.intersect(y, ctx))
+                    .collect::<Vec<_>>();
+                if xys.iter().any(|x| x.is_void()) {
+                    Ty::Void
+                } else {
+                    Ty::Tuple(xys)
+                }
+            }
+            (
+                Ty::Struct { fields, extra },
+                Ty::Struct {
+                    fields: fields2,
+                    extra: extra2,
+                },
+            ) => {
+                let mut res = BTreeMap::new();
+                for (k, v) in fields {
+                    let v = match fields2.get(k) {
+                        Some(v2) => v.intersect(v2, ctx),
+                        // A field the other struct can't have
+                        None if !extra2 => return Ty::Void,
+                        None => v.clone(),
+                    };
+                    if v.is_void() {
+                        return Ty::Void;
+                    }
+                    res.insert(k.clone(), v);
+                }
+                for (k, v) in fields2 {
+                    if !fields.contains_key(k) {
+                        if !extra {
+                            return Ty::Void;
+                        }
+                        res.insert(k.clone(), v.clone());
+                    }
+                }
+                Ty::Struct {
+                    fields: res,
+                    extra: *extra && *extra2,
+                }
+            }
+            // A type name covers all the more precise types with that name, e.g. `tuple`
+            (x, Ty::Name(y)) if x.has_type_name(y.as_str()) => x.clone(),
+            (Ty::Name(x), y) if y.has_type_name(x.as_str()) => y.clone(),
+            (x, y) if x.intersects(y, ctx) => x.clone(),
+            _ => Ty::Void,
+        }
+    }
+
+    /// The values of this type which aren't of type `other`. Since types are approximate,
+    /// only alternatives of `self` entirely within `other` are removed.
+    pub(crate) fn difference(&self, other: &Self) -> Ty {
+        if other.is_any_or_var() {
+            return Ty::Void;
+        }
+        Ty::unions(
+            self.iter_union()
+                .filter(|x| {
+                    x.is_any_or_var()
+                        || !other
+                            .iter_union()
+                            .any(|y| &x.intersect_alternative(y, None) == *x)
+                })
+                .cloned()
+                .collect(),
+        )
+    }
+
     /// Create a union of two entries.
     pub fn union2(a: Self, b: Self) -> Self {
         Self::unions(vec![a, b])
@@ -480,6 +684,23 @@ impl Ty {
         }
     }
 
+    /// The type of the items when iterating over `self`, if it is a container whose
+    /// structure we know.
+    pub(crate) fn iter_item(&self) -> Option<Ty> {
+        match self {
+            Ty::List(x) | Ty::Iter(x) => Some((**x).clone()),
+            Ty::Tuple(xs) => Some(Ty::unions(xs.clone())),
+            Ty::Dict(k_v) => Some(k_v.0.clone()),
+            Ty::Union(xs) => xs
+                .alternatives()
+                .iter()
+                .map(|x| x.iter_item())
+                .collect::<Option<Vec<_>>>()
+                .map(Ty::unions),
+            _ => None,
+        }
+    }
+
     /// Returns false on Void, since that is definitely not a list
     pub(crate) fn probably_a_list(&self) -> bool {
         if self.is_void() {
@@ -492,7 +713,7 @@ impl Ty {
     pub(crate) fn attribute(&self, attr: &str, ctx: &TypingContext) -> Result<Ty, ()> {
         // There are some structural types which have to be handled in a specific way
         match self {
-            Ty::Any => Ok(Ty::Any),
+            Ty::Any | Ty::Var(_) => Ok(Ty::Any),
             Ty::Void => Ok(Ty::Void),
             Ty::Union(xs) => {
                 let rs = xs
@@ -508,6 +729,24 @@ impl Ty {
                     Ok(Ty::unions(rs))
                 }
             }
+            Ty::Struct { fields, extra } => match fields.get(attr) {
+                Some(ty) => Ok(ty.clone()),
+                None => match ctx.oracle.attribute(self, attr) {
+                    Some(Ok(ty)) => Ok(ty),
+                    // Fields added via `**kwargs` could be anything
+                    _ if *extra => Ok(Ty::Any),
+                    _ => Err(()),
+                },
+            },
+            Ty::Record { fields } => match fields.get(attr) {
+                Some(ty) => Ok(ty.clone()),
+                None => ctx.oracle.attribute(self, attr).unwrap_or(Err(())),
+            },
+            Ty::Enum { .. } => match attr {
+                "value" => Ok(Ty::string()),
+                "index" => Ok(Ty::int()),
+                _ => ctx.oracle.attribute(self, attr).unwrap_or(Err(())),
+            },
             _ => match ctx.oracle.attribute(self, attr) {
                 Some(r) => r,
                 None => Ok(ctx.approximation("oracle.attribute", format!("{}.{}", self, attr))),
@@ -517,7 +756,7 @@ impl Ty {
 
     /// If you get to a point where these types are being checked, might they succeed
     pub(crate) fn intersects(&self, other: &Self, ctx: Option<&TypingContext>) -> bool {
-        if self.is_any() || self.is_void() || other.is_any() || other.is_void() {
+        if self.is_any_or_var() || self.is_void() || other.is_any_or_var() || other.is_void() {
             return true;
         }
 
@@ -548,10 +787,9 @@ impl Ty {
                         Some(yy) => x.intersects(&yy, ctx),
                         None => false,
                     },
-                    (Ty::Function(_), Ty::Function(_)) => true,
+                    (Ty::Function(x), Ty::Function(y)) => x.intersects(y, ctx),
                     (Ty::Struct { .. }, Ty::Struct { .. }) => {
-                        // FIXME: Can probably be a bit more precise here
-                        true
+                        !x.intersect_alternative(y, ctx).is_void()
                     }
                     // There are lots of other cases that overlap, but add them as we need them
                     (x, y) => x == y,
@@ -564,8 +802,73 @@ impl Ty {
         return false;
     }
 
-    pub(crate) fn from_expr_opt(
-        x: &Option<Box<CstExpr>>,
+    /// Record what each type variable in `self` would be if a value of type `arg` were
+    /// used where `self` is required, adding them to `bindings`.
+    pub(crate) fn bind_vars(&self, arg: &Ty, bindings: &mut BTreeMap<String, Vec<Ty>>) {
+        match (self, arg) {
+            (Ty::Var(x), _) => bindings.entry(x.clone()).or_default().push(arg.clone()),
+            (_, Ty::Union(ys)) => {
+                for y in ys.alternatives() {
+                    self.bind_vars(y, bindings);
+                }
+            }
+            (Ty::Union(xs), _) => {
+                // Only bind variables to what isn't already covered by another alternative
+                let (vars, others): (Vec<_>, Vec<_>) = xs
+                    .alternatives()
+                    .iter()
+                    .partition(|x| matches!(x, Ty::Var(_)));
+                if !others.iter().any(|x| x.intersects(arg, None)) {
+                    for x in vars {
+                        x.bind_vars(arg, bindings);
+                    }
+                }
+            }
+            (Ty::List(x), Ty::List(y)) | (Ty::Iter(x), Ty::List(y)) => x.bind_vars(y, bindings),
+            (Ty::Dict(x), Ty::Dict(y)) => {
+                x.0.bind_vars(&y.0, bindings);
+                x.1.bind_vars(&y.1, bindings);
+            }
+            (Ty::Tuple(xs), Ty::Tuple(ys)) if xs.len() == ys.len() => {
+                for (x, y) in std::iter::zip(xs, ys) {
+                    x.bind_vars(y, bindings);
+                }
+            }
+            (Ty::Function(x), Ty::Function(y)) => x.result.bind_vars(&y.result, bindings),
+            _ => {}
+        }
+    }
+
+    /// Replace the type variables in `self` by their types in `bindings`, or by
+    /// [`Ty::Any`] if they are not bound.
+    pub(crate) fn instantiate(&self, bindings: &BTreeMap<String, Ty>) -> Ty {
+        match self {
+            Ty::Var(x) => bindings.get(x).cloned().unwrap_or(Ty::Any),
+            Ty::Union(xs) => Ty::unions(xs.alternatives().map(|x| x.instantiate(bindings))),
+            Ty::Iter(x) => Ty::Iter(Box::new(x.instantiate(bindings))),
+            Ty::List(x) => Ty::list(x.instantiate(bindings)),
+            Ty::Tuple(xs) => Ty::Tuple(xs.map(|x| x.instantiate(bindings))),
+            Ty::Dict(k_v) => Ty::dict(k_v.0.instantiate(bindings), k_v.1.instantiate(bindings)),
+            Ty::Struct { fields, extra } => Ty::Struct {
+                fields: fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.instantiate(bindings)))
+                    .collect(),
+                extra: *extra,
+            },
+            Ty::Record { fields } => Ty::Record {
+                fields: fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.instantiate(bindings)))
+                    .collect(),
+            },
+            Ty::Function(x) => Ty::Function(x.instantiate(bindings)),
+            Ty::Void | Ty::Any | Ty::Name(_) | Ty::None | Ty::Enum { .. } => self.clone(),
+        }
+    }
+
+    pub(crate) fn from_expr_opt<P: AstPayload>(
+        x: &Option<Box<AstExprP<P>>>,
         approximations: &mut Vec<Approximation>,
     ) -> Self {
         match x {
@@ -578,8 +881,19 @@ impl Ty {
         x: &AstExprP<P>,
         approximations: &mut Vec<Approximation>,
     ) -> Self {
+        Self::from_expr_impl(x, false, approximations)
+    }
+
+    /// Like [`Ty::from_expr`], but if `bare_names` then identifiers such as `int` are
+    /// treated as type names, as they are within a function signature.
+    fn from_expr_impl<P: AstPayload>(
+        x: &AstExprP<P>,
+        bare_names: bool,
+        approximations: &mut Vec<Approximation>,
+    ) -> Self {
+        let mut f = |x| Self::from_expr_impl(x, bare_names, approximations);
         match &**x {
-            ExprP::Tuple(xs) => Ty::Tuple(xs.map(|x| Self::from_expr(x, approximations))),
+            ExprP::Tuple(xs) => Ty::Tuple(xs.map(f)),
             ExprP::Dot(x, b) if &**b == "type" => match &***x {
                 ExprP::Identifier(x, _) => match (*x).as_str() {
                     "str" => Ty::string(),
@@ -591,24 +905,80 @@ impl Ty {
                 }
             },
             ExprP::Literal(AstLiteral::String(x)) => {
-                if x.is_empty() || x.starts_with('_') {
+                if x.len() > 1 && x.starts_with('_') {
+                    Ty::Var(x.as_str().to_owned())
+                } else if x.is_empty() || x.starts_with('_') {
                     Ty::Any
+                } else if x.starts_with("def(") {
+                    match Self::from_signature(x, approximations) {
+                        Some(ty) => ty,
+                        None => {
+                            approximations.push(Approximation::new("Invalid signature", x));
+                            Ty::name("function")
+                        }
+                    }
                 } else {
                     Ty::name(x.as_str())
                 }
             }
             ExprP::List(x) => {
                 if x.len() == 1 {
-                    Ty::list(Self::from_expr(&x[0], approximations))
+                    Ty::list(f(&x[0]))
                 } else {
-                    Ty::unions(x.map(|x| Self::from_expr(x, approximations)))
+                    Ty::unions(x.map(f))
                 }
             }
-            ExprP::Dict(x) if x.len() == 1 => Ty::dict(
-                Self::from_expr(&x[0].0, approximations),
-                Self::from_expr(&x[0].1, approximations),
-            ),
+            ExprP::Dict(x) if x.len() == 1 => {
+                let k = f(&x[0].0);
+                Ty::dict(k, f(&x[0].1))
+            }
             ExprP::Identifier(x, _) if &**x == "None" => Ty::None,
+            // The type functions themselves, as in `x: int` or `list[str]`
+            ExprP::Identifier(x, _)
+                if bare_names
+                    || matches!(
+                        x.as_str(),
+                        "str"
+                            | "int"
+                            | "bool"
+                            | "float"
+                            | "list"
+                            | "dict"
+                            | "tuple"
+                            | "set"
+                            | "bytes"
+                    ) =>
+            {
+                match x.as_str() {
+                    "str" => Ty::string(),
+                    x => Ty::name(x),
+                }
+            }
+            ExprP::Op(a, BinOp::BitOr, b) => {
+                let a = f(a);
+                Ty::union2(a, f(b))
+            }
+            ExprP::ArrayIndirection(ai) => {
+                let (a, i) = &**ai;
+                let args = match &**i {
+                    ExprP::Tuple(xs) => xs.iter().collect(),
+                    _ => vec![i],
+                };
+                match (&**a, &args[..]) {
+                    (ExprP::Identifier(a, _), [x]) if &**a == "list" => Ty::list(f(x)),
+                    (ExprP::Identifier(a, _), [k, v]) if &**a == "dict" => {
+                        let k = f(k);
+                        Ty::dict(k, f(v))
+                    }
+                    (ExprP::Identifier(a, _), _) if &**a == "tuple" => {
+                        Ty::Tuple(args.into_iter().map(f).collect())
+                    }
+                    _ => {
+                        approximations.push(Approximation::new("Unknown type", x));
+                        Ty::Any
+                    }
+                }
+            }
             _ => {
                 approximations.push(Approximation::new("Unknown type", x));
                 Ty::Any
@@ -616,6 +986,87 @@ impl Ty {
         }
     }
 
+    /// Parse a function signature, written like `"def(int, name: str, opt: int = ..) -> bool"`.
+    /// Parameters without a name are positional only, those after a `*` are named only,
+    /// those with a default are optional, and `*args`/`**kwargs` give the types of their
+    /// elements/values. The types are written as in annotations, or as bare names.
+    fn from_signature(sig: &str, approximations: &mut Vec<Approximation>) -> Option<Ty> {
+        /// Split at the top-level occurrences of `sep`, outside of any brackets or strings.
+        fn split_top(x: &str, sep: char) -> Vec<&str> {
+            let mut res = Vec::new();
+            let mut depth = 0;
+            let mut quote = None;
+            let mut start = 0;
+            for (i, c) in x.char_indices() {
+                match (quote, c) {
+                    (Some(q), c) if c == q => quote = None,
+                    (Some(_), _) => {}
+                    (None, c) if c == sep && depth == 0 => {
+                        res.push(&x[start..i]);
+                        start = i + c.len_utf8();
+                    }
+                    (None, '"' | '\'') => quote = Some(c),
+                    (None, '(' | '[' | '{') => depth += 1,
+                    (None, ')' | ']' | '}') => depth -= 1,
+                    _ => {}
+                }
+            }
+            res.push(&x[start..]);
+            res
+        }
+
+        let parse_type = |x: &str, approximations: &mut Vec<Approximation>| {
+            let ast =
+                AstModule::parse("signature", x.trim().to_owned(), &Dialect::Standard).ok()?;
+            match &ast.statement.node {
+                StmtP::Expression(x) => Some(Self::from_expr_impl(x, true, approximations)),
+                _ => None,
+            }
+        };
+
+        // Everything up to the `)` matching `def(` are the parameters, then the result
+        let (params, result) = match split_top(sig.strip_prefix("def(")?, ')').as_slice() {
+            [params, result] => (*params, result.trim()),
+            _ => return None,
+        };
+        let result = match result.strip_prefix("->") {
+            Some(result) => parse_type(result, approximations)?,
+            None if result.is_empty() => Ty::Any,
+            None => return None,
+        };
+
+        let mut res = Vec::new();
+        let mut named_only = false;
+        for param in split_top(params, ',') {
+            let (param, optional) = match split_top(param, '=').as_slice() {
+                [param] => (param.trim(), false),
+                [param, _default] => (param.trim(), true),
+                _ => return None,
+            };
+            if param.is_empty() && !optional {
+                continue;
+            } else if param == "*" {
+                named_only = true;
+                continue;
+            }
+            let (name, ty) = match split_top(param, ':').as_slice() {
+                [ty] => (None, parse_type(ty, approximations)?),
+                [name, ty] => (Some(name.trim()), parse_type(ty, approximations)?),
+                _ => return None,
+            };
+            let param = match name {
+                Some(name) if name.starts_with("**") => Param::kwargs(Ty::dict(Ty::string(), ty)),
+                Some(name) if name.starts_with('*') => Param::args(ty),
+                None if named_only => return None,
+                None => Param::pos_only(ty),
+                Some(name) if named_only => Param::name_only(name, ty),
+                Some(name) => Param::pos_or_name(name, ty),
+            };
+            res.push(if optional { param.optional() } else { param });
+        }
+        Some(Ty::function(res, result))
+    }
+
     pub(crate) fn from_docs_member(member: &docs::Member) -> Self {
         match member {
             docs::Member::Property(x) => Self::from_docs_type(&x.typ),
@@ -696,6 +1147,7 @@ impl Display for Ty {
         match self {
             Ty::Void => write!(f, "Void"),
             Ty::Any => write!(f, "\"\""),
+            Ty::Var(x) => write!(f, "\"{}\"", x),
             Ty::Union(xs) => write!(f, "{}", xs),
             Ty::Name(x) => write!(f, "{}", x),
             Ty::None => write!(f, "None"),
@@ -725,6 +1177,22 @@ impl Display for Ty {
                 }
                 write!(f, ")")
             }
+            Ty::Record { fields } => {
+                write!(f, "record(")?;
+                for (k, v) in fields {
+                    comma(f)?;
+                    write!(f, "{} = {}", k, v)?;
+                }
+                write!(f, ")")
+            }
+            Ty::Enum { variants } => {
+                write!(f, "enum(")?;
+                for x in variants {
+                    comma(f)?;
+                    write!(f, "{:?}", x)?;
+                }
+                write!(f, ")")
+            }
             Ty::Function(TyFunction {
                 name,
                 params,
@@ -748,3 +1216,133 @@ impl Display for Ty {
         }
     }
 }
+
+/// The syntax a [`Ty`] is rendered in, see [`Ty::display_with`].
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Default)]
+pub enum TyStyle {
+    /// Types written as strings and literals, as in `"int"`, `["string"]` and
+    /// `{"string": "int"}`, which is what [`Display`] produces.
+    #[default]
+    Legacy,
+    /// Types written as annotation expressions, as in `int`, `list[str]`,
+    /// `dict[str, int]` and `int | None`.
+    Annotation,
+}
+
+/// A [`Ty`] rendered in a given [`TyStyle`], as returned by [`Ty::display_with`].
+pub struct TyDisplay<'a> {
+    ty: &'a Ty,
+    style: TyStyle,
+}
+
+impl Ty {
+    /// Render the type in the given style, e.g. to match the annotations accepted
+    /// by the dialect in use. [`Display`] renders [`TyStyle::Legacy`].
+    pub fn display_with(&self, style: TyStyle) -> TyDisplay<'_> {
+        TyDisplay { ty: self, style }
+    }
+}
+
+impl Display for TyDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.style {
+            TyStyle::Legacy => write!(f, "{}", self.ty),
+            TyStyle::Annotation => fmt_annotation(self.ty, f),
+        }
+    }
+}
+
+fn fmt_annotation(ty: &Ty, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn annotation(ty: &Ty) -> TyDisplay<'_> {
+        ty.display_with(TyStyle::Annotation)
+    }
+
+    let mut comma = commas();
+    match ty {
+        Ty::Void => write!(f, "typing.Never"),
+        Ty::Any => write!(f, "typing.Any"),
+        Ty::Var(x) => write!(f, "\"{}\"", x),
+        Ty::Union(xs) => {
+            for (i, x) in xs.alternatives().iter().enumerate() {
+                if i != 0 {
+                    write!(f, " | ")?;
+                }
+                write!(f, "{}", annotation(x))?;
+            }
+            Ok(())
+        }
+        Ty::Name(x) if x == "string" => write!(f, "str"),
+        Ty::Name(x) => write!(f, "{}", x.as_str()),
+        Ty::None => write!(f, "None"),
+        Ty::Iter(x) => write!(f, "typing.Iterable[{}]", annotation(x)),
+        Ty::List(x) => write!(f, "list[{}]", annotation(x)),
+        Ty::Tuple(xs) if xs.is_empty() => write!(f, "tuple[()]"),
+        Ty::Tuple(xs) => {
+            write!(f, "tuple[")?;
+            for x in xs {
+                comma(f)?;
+                write!(f, "{}", annotation(x))?;
+            }
+            write!(f, "]")
+        }
+        Ty::Dict(k_v) => write!(f, "dict[{}, {}]", annotation(&k_v.0), annotation(&k_v.1)),
+        Ty::Struct { fields, extra } => {
+            write!(f, "struct(")?;
+            for (k, v) in fields {
+                comma(f)?;
+                write!(f, "{} = {}", k, annotation(v))?;
+            }
+            if *extra {
+                comma(f)?;
+                write!(f, "..")?;
+            }
+            write!(f, ")")
+        }
+        Ty::Record { fields } => {
+            write!(f, "record(")?;
+            for (k, v) in fields {
+                comma(f)?;
+                write!(f, "{} = {}", k, annotation(v))?;
+            }
+            write!(f, ")")
+        }
+        Ty::Enum { variants } => {
+            write!(f, "enum(")?;
+            for x in variants {
+                comma(f)?;
+                write!(f, "{:?}", x)?;
+            }
+            write!(f, ")")
+        }
+        // The syntax of signatures in annotations, see `Ty::from_signature`.
+        Ty::Function(TyFunction { params, result, .. }) => {
+            write!(f, "def(")?;
+            let mut named_only = false;
+            for param in params {
+                comma(f)?;
+                match &param.mode {
+                    ParamMode::PosOnly => write!(f, "{}", annotation(&param.ty))?,
+                    ParamMode::PosOrName(name) => write!(f, "{}: {}", name, annotation(&param.ty))?,
+                    ParamMode::NameOnly(name) => {
+                        if !named_only {
+                            write!(f, "*, ")?;
+                        }
+                        write!(f, "{}: {}", name, annotation(&param.ty))?
+                    }
+                    ParamMode::Args => write!(f, "*args: {}", annotation(&param.ty))?,
+                    ParamMode::Kwargs => match &param.ty {
+                        Ty::Dict(k_v) => write!(f, "**kwargs: {}", annotation(&k_v.1))?,
+                        ty => write!(f, "**kwargs: {}", annotation(ty))?,
+                    },
+                }
+                if matches!(param.mode, ParamMode::NameOnly(_) | ParamMode::Args) {
+                    named_only = true;
+                }
+                if param.optional && !param.allows_many() {
+                    write!(f, " = ..")?;
+                }
+            }
+            write!(f, ") -> {}", annotation(result))
+        }
+    }
+}
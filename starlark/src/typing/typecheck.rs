@@ -19,10 +19,14 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Display;
+use std::mem;
+use std::time::Instant;
 
 use dupe::Dupe;
 use gazebo::prelude::*;
 
+use crate::analysis::types::LintT;
+use crate::analysis::Lint;
 use crate::codemap::CodeMap;
 use crate::codemap::FileSpanRef;
 use crate::codemap::Span;
@@ -40,9 +44,15 @@ use crate::typing::bindings::Bindings;
 use crate::typing::bindings::Interface;
 use crate::typing::ctx::TypingContext;
 use crate::typing::ctx::TypingError;
+use crate::typing::exhaustive::if_chains;
 use crate::typing::oracle::traits::TypingOracle;
+use crate::typing::profile::function_profiles;
+use crate::typing::profile::SolveTimes;
+use crate::typing::profile::TypecheckProfile;
 use crate::typing::ty::Approximation;
 use crate::typing::ty::Ty;
+use crate::typing::unreachable::unreachable_branches;
+use crate::typing::unreachable::UnreachableCode;
 use crate::values::FrozenHeap;
 use crate::values::FrozenRef;
 
@@ -67,6 +77,7 @@ fn unique_identifiers<'f>(
         FrozenRef::new(Globals::empty()),
         codemap,
         &Dialect::Extended,
+        false,
     );
     (cst, scope)
 }
@@ -74,9 +85,17 @@ fn unique_identifiers<'f>(
 // Things which are None in the map have type void - they are never constructed
 fn solve_bindings(
     oracle: &dyn TypingOracle,
-    bindings: Bindings,
+    mut bindings: Bindings,
     codemap: &CodeMap,
-) -> (Vec<TypingError>, HashMap<BindingId, Ty>, Vec<Approximation>) {
+    mut times: Option<&mut SolveTimes>,
+) -> (
+    Vec<TypingError>,
+    HashMap<BindingId, Ty>,
+    Vec<Approximation>,
+    Vec<Lint>,
+    Vec<Lint>,
+    Vec<Lint>,
+) {
     let mut types = bindings
         .expressions
         .keys()
@@ -85,6 +104,7 @@ fn solve_bindings(
     for (k, ty) in bindings.types {
         types.insert(k, ty);
     }
+    let start = Instant::now();
     // FIXME: Should be a fixed point, just do 10 iterations since that probably converges
     let mut changed = false;
     let mut ctx = TypingContext {
@@ -93,12 +113,14 @@ fn solve_bindings(
         errors: RefCell::new(Vec::new()),
         approximoations: RefCell::new(Vec::new()),
         types,
+        narrows: mem::take(&mut bindings.narrows),
     };
     const ITERATIONS: usize = 100;
     for _iteration in 0..ITERATIONS {
         changed = false;
         ctx.errors.borrow_mut().clear();
         for (name, exprs) in &bindings.expressions {
+            let binding_start = times.is_some().then(Instant::now);
             for expr in exprs {
                 let ty = ctx.expression_bind_type(expr);
                 let t = ctx.types.get_mut(name).unwrap();
@@ -108,6 +130,9 @@ fn solve_bindings(
                     *t = new;
                 }
             }
+            if let (Some(times), Some(binding_start)) = (&mut times, binding_start) {
+                *times.bindings.entry(*name).or_default() += binding_start.elapsed();
+            }
         }
         if !changed {
             break;
@@ -119,6 +144,10 @@ fn solve_bindings(
             ITERATIONS,
         ));
     }
+    let check_start = Instant::now();
+    if let Some(times) = &mut times {
+        times.solve = check_start - start;
+    }
     // Make sure we check every expression, looking for failures
     for x in &bindings.check {
         ctx.expression_type(x);
@@ -130,10 +159,53 @@ fn solve_bindings(
         };
         ctx.validate_type(&ty, require, *span);
     }
+    for (span, e, require) in &bindings.check_annotation {
+        ctx.validate_annotation(&ctx.expression_type(e), require, *span);
+    }
+    let mut unreachable = Vec::new();
+    for (cond, then_block, else_block) in &bindings.conditions {
+        let errors = ctx.errors.borrow().len();
+        let ty = ctx.expression_type(cond);
+        // If the condition is an error we have already reported it, and its type is meaningless
+        if ctx.errors.borrow().len() == errors {
+            for (block, problem) in unreachable_branches(cond, &ty, then_block, *else_block) {
+                unreachable.push(LintT::new(codemap, block.span, problem).erase());
+            }
+        }
+    }
+    let mut unreachable_code = Vec::new();
+    for (x, rest) in &bindings.followed {
+        let errors = ctx.errors.borrow().len();
+        let ty = ctx.expression_type(x);
+        // A statement with a type error is also `Void`, but that doesn't mean it never returns.
+        let had_errors = ctx.errors.borrow().len() != errors;
+        // Any errors were already reported when checking the statement
+        ctx.errors.borrow_mut().truncate(errors);
+        if ty.is_void() && !had_errors {
+            unreachable_code
+                .push(LintT::new(codemap, *rest, UnreachableCode::AfterNeverReturns).erase());
+        }
+    }
+    let mut non_exhaustive = Vec::new();
+    for (chain, span) in if_chains(&bindings.conditions) {
+        let errors = ctx.errors.borrow().len();
+        let problem = ctx.unhandled_variants(&chain);
+        // Any errors were already reported when checking the conditions
+        ctx.errors.borrow_mut().truncate(errors);
+        if let Some(problem) = problem {
+            non_exhaustive.push(LintT::new(codemap, span, problem).erase());
+        }
+    }
+    if let Some(times) = times {
+        times.check = check_start.elapsed();
+    }
     (
         ctx.errors.into_inner(),
         ctx.types,
         ctx.approximoations.into_inner(),
+        unreachable,
+        unreachable_code,
+        non_exhaustive,
     )
 }
 
@@ -142,6 +214,34 @@ fn solve_bindings(
 pub struct TypeMap {
     codemap: CodeMap,
     bindings: HashMap<BindingId, (String, Span, Ty)>,
+    unreachable: Vec<Lint>,
+    unreachable_code: Vec<Lint>,
+    non_exhaustive: Vec<Lint>,
+}
+
+impl TypeMap {
+    /// The types of all the bindings.
+    pub(crate) fn types(&self) -> impl Iterator<Item = &Ty> {
+        self.bindings.values().map(|(_, _, ty)| ty)
+    }
+
+    /// The branches of `if` statements that can never be taken, as determined from
+    /// literal conditions and the types of the conditions.
+    pub fn unreachable_branches(&self) -> &[Lint] {
+        &self.unreachable
+    }
+
+    /// The statements that can never be executed, because a statement before them in the
+    /// same block, such as a call to `fail()`, never returns.
+    pub fn unreachable_code(&self) -> &[Lint] {
+        &self.unreachable_code
+    }
+
+    /// The `if`/`elif` chains without an `else`, comparing a variable with the values of
+    /// an enum, which never handle some of its values.
+    pub fn non_exhaustive(&self) -> &[Lint] {
+        &self.non_exhaustive
+    }
 }
 
 impl Display for TypeMap {
@@ -165,19 +265,60 @@ impl Display for TypeMap {
 
 impl AstModule {
     /// Typecheck a module
+    ///
+    /// Some of the errors have a fix, which [`TypingFix::of`](crate::typing::TypingFix::of) returns.
     pub fn typecheck(
         self,
         oracle: &dyn TypingOracle,
         loads: &HashMap<String, Interface>,
+    ) -> (Vec<anyhow::Error>, TypeMap, Interface, Vec<Approximation>) {
+        self.typecheck_impl(oracle, loads, None)
+    }
+
+    /// Typecheck a module like [`typecheck`](AstModule::typecheck), also timing
+    /// each phase of the typechecker and each function in the module.
+    pub fn typecheck_profile(
+        self,
+        oracle: &dyn TypingOracle,
+        loads: &HashMap<String, Interface>,
+    ) -> (
+        (Vec<anyhow::Error>, TypeMap, Interface, Vec<Approximation>),
+        TypecheckProfile,
+    ) {
+        let mut profile = TypecheckProfile::default();
+        let res = self.typecheck_impl(oracle, loads, Some(&mut profile));
+        (res, profile)
+    }
+
+    fn typecheck_impl(
+        self,
+        oracle: &dyn TypingOracle,
+        loads: &HashMap<String, Interface>,
+        profile: Option<&mut TypecheckProfile>,
     ) -> (Vec<anyhow::Error>, TypeMap, Interface, Vec<Approximation>) {
         let codemap = self.codemap.dupe();
         let names = MutableNames::new();
         let frozen_heap = FrozenHeap::new();
+        let start = Instant::now();
         let (cst, scope) = unique_identifiers(&frozen_heap, self, &names);
-        let bindings = Bindings::collect(&cst, loads);
+        let bindings_start = Instant::now();
+        let bindings = Bindings::collect(&cst, loads, &codemap);
+        let bindings_time = bindings_start.elapsed();
         let descriptions = bindings.descriptions.clone();
         let mut approximations = bindings.approximations.clone();
-        let (errors, types, solve_approximations) = solve_bindings(oracle, bindings, &codemap);
+        let mut times = profile.is_some().then(SolveTimes::default);
+        let (errors, types, solve_approximations, unreachable, unreachable_code, non_exhaustive) =
+            solve_bindings(oracle, bindings, &codemap, times.as_mut());
+
+        if let (Some(profile), Some(times)) = (profile, times) {
+            profile.scope = bindings_start - start;
+            profile.bindings = bindings_time;
+            profile.solve = times.solve;
+            profile.check = times.check;
+            profile.functions = function_profiles(&cst, &codemap, &times.bindings, |id| {
+                descriptions.get(&id).map(|x| x.span)
+            });
+        }
 
         approximations.extend(solve_approximations);
 
@@ -192,6 +333,9 @@ impl AstModule {
         let typemap = TypeMap {
             bindings: typemap,
             codemap: codemap.dupe(),
+            unreachable,
+            unreachable_code,
+            non_exhaustive,
         };
 
         let errors = errors.into_map(|x| anyhow::anyhow!(x));
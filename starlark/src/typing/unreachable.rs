@@ -0,0 +1,109 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use gazebo::variants::VariantName;
+use thiserror::Error;
+
+use crate::analysis::types::LintWarning;
+use crate::eval::compiler::scope::CstExpr;
+use crate::eval::compiler::scope::CstStmt;
+use crate::eval::compiler::scope::ResolvedIdent;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::ExprP;
+use crate::syntax::lexer::TokenInt;
+use crate::typing::ty::Ty;
+
+/// Why a branch of an `if` statement can never be taken.
+#[derive(Error, Debug, VariantName)]
+pub(crate) enum UnreachableBranch {
+    #[error("Branch is never taken, because the condition is always false, as {0}")]
+    ConditionAlwaysFalse(String),
+    #[error("Branch is never taken, because the condition is always true, as {0}")]
+    ConditionAlwaysTrue(String),
+    #[error("Branch is never taken, because evaluating the condition never succeeds")]
+    ConditionNeverSucceeds,
+}
+
+impl LintWarning for UnreachableBranch {
+    fn is_serious(&self) -> bool {
+        false
+    }
+}
+
+/// Why statements can never be executed.
+#[derive(Error, Debug, VariantName)]
+pub(crate) enum UnreachableCode {
+    #[error("Code is unreachable, because the statement before it never returns")]
+    AfterNeverReturns,
+}
+
+impl LintWarning for UnreachableCode {
+    fn is_serious(&self) -> bool {
+        false
+    }
+}
+
+/// The truth value of a condition that is a literal, if known.
+fn literal_truth(x: &CstExpr) -> Option<bool> {
+    match &**x {
+        ExprP::Identifier(name, ident) if !matches!(ident, Some(ResolvedIdent::Slot(_))) => {
+            match name.node.as_str() {
+                "True" => Some(true),
+                "False" | "None" => Some(false),
+                _ => None,
+            }
+        }
+        ExprP::Literal(AstLiteral::Int(x)) => Some(!matches!(x.node, TokenInt::I32(0))),
+        ExprP::Literal(AstLiteral::String(x)) => Some(!x.node.is_empty()),
+        ExprP::Not(x) => literal_truth(x).map(|x| !x),
+        _ => None,
+    }
+}
+
+/// The branches of `if cond: then_block else: else_block` that can never be taken, given
+/// that `cond` has type `ty`.
+pub(crate) fn unreachable_branches<'a>(
+    cond: &CstExpr,
+    ty: &Ty,
+    then_block: &'a CstStmt,
+    else_block: Option<&'a CstStmt>,
+) -> Vec<(&'a CstStmt, UnreachableBranch)> {
+    let truth = match literal_truth(cond) {
+        Some(truth) => Some((truth, "it is a literal".to_owned())),
+        None => match ty {
+            Ty::None => Some((false, "it has type `None`".to_owned())),
+            Ty::Function(_) => Some((true, "it is a function".to_owned())),
+            Ty::Void => {
+                return std::iter::once(then_block)
+                    .chain(else_block)
+                    .map(|x| (x, UnreachableBranch::ConditionNeverSucceeds))
+                    .collect();
+            }
+            _ => None,
+        },
+    };
+    match truth {
+        Some((false, reason)) => {
+            vec![(then_block, UnreachableBranch::ConditionAlwaysFalse(reason))]
+        }
+        Some((true, reason)) => match else_block {
+            Some(else_block) => vec![(else_block, UnreachableBranch::ConditionAlwaysTrue(reason))],
+            None => Vec::new(),
+        },
+        None => Vec::new(),
+    }
+}
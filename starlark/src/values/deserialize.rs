@@ -0,0 +1,499 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Converting Starlark values into Rust types with [`serde`].
+
+use std::fmt;
+use std::fmt::Display;
+
+use serde::de;
+use serde::de::DeserializeSeed;
+use serde::de::Deserializer;
+use serde::de::EnumAccess;
+use serde::de::IntoDeserializer;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Unexpected;
+use serde::de::VariantAccess;
+use serde::de::Visitor;
+use serde::forward_to_deserialize_any;
+
+use crate::values::dict::DictRef;
+use crate::values::float::StarlarkFloat;
+use crate::values::list::ListRef;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::FrozenValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// An error from deserializing a [`Value`], along with where in the value it happened,
+/// e.g. ``at `deps[1]`: invalid type: integer `1`, expected a string``.
+#[derive(Debug)]
+pub struct DeserializeError {
+    /// Path from the value being deserialized to where the error happened, innermost last.
+    path: Vec<String>,
+    message: String,
+}
+
+impl DeserializeError {
+    /// Where in the deserialized value the error happened, e.g. `deps[1]`,
+    /// or the empty string if it was at the top level.
+    pub fn path(&self) -> String {
+        self.path.concat().trim_start_matches('.').to_owned()
+    }
+
+    /// The error message, without the path.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn at(mut self, segment: impl FnOnce() -> String) -> Self {
+        self.path.insert(0, segment());
+        self
+    }
+}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "at `{}`: {}", self.path(), self.message)
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        DeserializeError {
+            path: Vec::new(),
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// Values are deserialized as:
+///
+/// * `None` as a unit or a missing `Option`.
+/// * `bool`, `int`, `float` and `str` as the corresponding Rust types.
+/// * `list` and `tuple` as sequences.
+/// * `dict` and `struct` as maps, so either can be deserialized into a Rust struct.
+/// * Enums from a string for unit variants, or a single entry `dict`/`struct`
+///   mapping the variant name to its contents.
+impl<'v> Deserializer<'v> for Value<'v> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'v>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.is_none() {
+            visitor.visit_unit()
+        } else if let Some(x) = self.unpack_bool() {
+            visitor.visit_bool(x)
+        } else if let Some(x) = self.unpack_str() {
+            visitor.visit_borrowed_str(x)
+        } else if let Some(x) = self.downcast_ref::<StarlarkFloat>() {
+            visitor.visit_f64(x.0)
+        } else if let Some(x) = self.unpack_integer::<i64>() {
+            visitor.visit_i64(x)
+        } else if let Some(x) = self.unpack_integer::<u64>() {
+            visitor.visit_u64(x)
+        } else if let Some(x) = ListRef::from_value(self) {
+            visitor.visit_seq(Seq::new(x.content()))
+        } else if let Some(x) = TupleRef::from_value(self) {
+            visitor.visit_seq(Seq::new(x.content()))
+        } else if let Some(x) = DictRef::from_value(self) {
+            visitor.visit_map(Map::new(
+                x.iter()
+                    .map(|(k, v)| (k, format!("[{}]", k.to_repr()), v))
+                    .collect(),
+            ))
+        } else if let Some(x) = StructRef::from_value(self) {
+            visitor.visit_map(Map::new(
+                x.iter()
+                    .map(|(k, v)| (k.to_value(), format!(".{}", k.as_str()), v))
+                    .collect(),
+            ))
+        } else {
+            Err(de::Error::invalid_type(
+                Unexpected::Other(self.get_type()),
+                &visitor,
+            ))
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'v>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.is_none() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'v>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'v>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if let Some(x) = self.unpack_str() {
+            return visitor.visit_enum(x.into_deserializer());
+        }
+        let entries = if let Some(x) = DictRef::from_value(self) {
+            x.iter().collect::<Vec<_>>()
+        } else if let Some(x) = StructRef::from_value(self) {
+            x.iter().map(|(k, v)| (k.to_value(), v)).collect()
+        } else {
+            return Err(de::Error::invalid_type(
+                Unexpected::Other(self.get_type()),
+                &"a string or a single entry dict",
+            ));
+        };
+        match entries.as_slice() {
+            [(variant, value)] => visitor.visit_enum(Enum {
+                variant: *variant,
+                value: *value,
+            }),
+            _ => Err(de::Error::invalid_length(
+                entries.len(),
+                &"a single entry dict",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        <W: Visitor<'v>>
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Same as for [`Value`].
+impl<'de> Deserializer<'de> for FrozenValue {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.to_value().deserialize_any(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.to_value().deserialize_option(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.to_value().deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.to_value().deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'v> IntoDeserializer<'v, DeserializeError> for Value<'v> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct Seq<'a, 'v> {
+    values: &'a [Value<'v>],
+    index: usize,
+}
+
+impl<'a, 'v> Seq<'a, 'v> {
+    fn new(values: &'a [Value<'v>]) -> Self {
+        Seq { values, index: 0 }
+    }
+}
+
+impl<'a, 'v> SeqAccess<'v> for Seq<'a, 'v> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'v>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        let Some(value) = self.values.get(self.index) else {
+            return Ok(None);
+        };
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(*value)
+            .map(Some)
+            .map_err(|e| e.at(|| format!("[{}]", index)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len() - self.index)
+    }
+}
+
+/// Entries of a map, with how to describe each key in an error path.
+struct Map<'v> {
+    entries: std::vec::IntoIter<(Value<'v>, String, Value<'v>)>,
+    value: Option<(String, Value<'v>)>,
+}
+
+impl<'v> Map<'v> {
+    fn new(entries: Vec<(Value<'v>, String, Value<'v>)>) -> Self {
+        Map {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'v> MapAccess<'v> for Map<'v> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'v>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, path, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        let res = seed.deserialize(key).map_err(|e| e.at(|| path.clone()))?;
+        self.value = Some((path, value));
+        Ok(Some(res))
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'v>>(
+        &mut self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        let (path, value) = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(value).map_err(|e| e.at(|| path))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct Enum<'v> {
+    variant: Value<'v>,
+    value: Value<'v>,
+}
+
+impl<'v> EnumAccess<'v> for Enum<'v> {
+    type Error = DeserializeError;
+    type Variant = Self;
+
+    fn variant_seed<T: DeserializeSeed<'v>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, Self), Self::Error> {
+        Ok((seed.deserialize(self.variant)?, self))
+    }
+}
+
+impl<'v> VariantAccess<'v> for Enum<'v> {
+    type Error = DeserializeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        de::Deserialize::deserialize(self.value)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'v>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        let path = variant_path(self.variant);
+        seed.deserialize(self.value).map_err(|e| e.at(|| path))
+    }
+
+    fn tuple_variant<V: Visitor<'v>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let path = variant_path(self.variant);
+        self.value
+            .deserialize_seq(visitor)
+            .map_err(|e| e.at(|| path))
+    }
+
+    fn struct_variant<V: Visitor<'v>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let path = variant_path(self.variant);
+        self.value
+            .deserialize_map(visitor)
+            .map_err(|e| e.at(|| path))
+    }
+}
+
+fn variant_path(variant: Value) -> String {
+    match variant.unpack_str() {
+        Some(x) => format!(".{}", x),
+        None => format!("[{}]", variant.to_repr()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use crate::assert;
+    use crate::values::OwnedFrozenValue;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+        port: u16,
+        ratio: f64,
+        tags: Vec<String>,
+        limits: BTreeMap<String, i64>,
+        parent: Option<Box<Config>>,
+        #[serde(default)]
+        mode: Mode,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Default)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        #[default]
+        Fast,
+        Retry(u32),
+        Range {
+            start: i32,
+            end: i32,
+        },
+    }
+
+    fn eval(code: &str) -> OwnedFrozenValue {
+        assert::pass(code)
+    }
+
+    #[test]
+    fn test_deserialize_struct() {
+        let value = eval(
+            r#"
+struct(
+    name = "web",
+    port = 80,
+    ratio = 0.5,
+    tags = ("a", "b"),
+    limits = {"cpu": 2, "mem": 1 << 40},
+    parent = {
+        "name": "base",
+        "port": 8080,
+        "ratio": 1.0,
+        "tags": [],
+        "limits": {},
+        "parent": None,
+        "mode": {"range": struct(start = -1, end = 1)},
+    },
+    mode = {"retry": 3},
+)
+"#,
+        );
+        let config: Config = value.deserialize().unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "web".to_owned(),
+                port: 80,
+                ratio: 0.5,
+                tags: vec!["a".to_owned(), "b".to_owned()],
+                limits: [("cpu".to_owned(), 2), ("mem".to_owned(), 1 << 40)]
+                    .into_iter()
+                    .collect(),
+                parent: Some(Box::new(Config {
+                    name: "base".to_owned(),
+                    port: 8080,
+                    ratio: 1.0,
+                    tags: Vec::new(),
+                    limits: BTreeMap::new(),
+                    parent: None,
+                    mode: Mode::Range { start: -1, end: 1 },
+                })),
+                mode: Mode::Retry(3),
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_borrowed() {
+        let value = eval(r#"["x", "y"]"#);
+        let xs: Vec<&str> = value.deserialize().unwrap();
+        assert_eq!(xs, vec!["x", "y"]);
+        let mode: Mode = eval(r#""fast""#).deserialize().unwrap();
+        assert_eq!(mode, Mode::Fast);
+    }
+
+    #[test]
+    fn test_deserialize_error_path() {
+        let value = eval(r#"{"a": [struct(x = 1), struct(x = "2")]}"#);
+        #[derive(Deserialize, Debug)]
+        struct X {
+            #[allow(dead_code)]
+            x: i32,
+        }
+        let err = value.deserialize::<BTreeMap<String, Vec<X>>>().unwrap_err();
+        assert_eq!(err.path(), r#"["a"][1].x"#);
+        assert_eq!(
+            err.to_string(),
+            r#"at `["a"][1].x`: invalid type: string "2", expected i32"#
+        );
+
+        let err = eval("[1, len]").deserialize::<Vec<i32>>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at `[1]`: invalid type: function, expected i32"
+        );
+
+        let err = eval("300").deserialize::<u8>().unwrap_err();
+        assert_eq!(err.path(), "");
+        assert_eq!(err.to_string(), "invalid value: integer `300`, expected u8");
+    }
+}
@@ -19,6 +19,7 @@
 
 use thiserror::Error;
 
+use crate::values::FrozenHeap;
 use crate::values::StarlarkValue;
 use crate::values::Value;
 
@@ -66,6 +67,50 @@ pub enum ValueError {
     NoAttrDidYouMean(String, String, String),
 }
 
+/// A frozen list or dict was mutated, as other frozen values report
+/// [`ValueError::CannotMutateImmutableValue`]. The owner is filled in by the evaluator,
+/// which knows the heaps of the loaded modules.
+#[derive(Debug, Error)]
+#[error(
+    "Immutable `{typ}`{}; copy it with `{typ}(x)` to get a value that can be mutated",
+    owner_note(.owner)
+)]
+pub(crate) struct FrozenMutationError {
+    typ: &'static str,
+    /// Address of the value, to find the heap it was frozen into.
+    address: usize,
+    owner: Option<String>,
+}
+
+fn owner_note(owner: &Option<String>) -> String {
+    match owner {
+        Some(owner) => format!(
+            " from module `{}`, frozen when that module finished evaluating",
+            owner
+        ),
+        None => String::new(),
+    }
+}
+
+impl FrozenMutationError {
+    #[cold]
+    pub(crate) fn new(typ: &'static str, address: usize) -> anyhow::Error {
+        FrozenMutationError {
+            typ,
+            address,
+            owner: None,
+        }
+        .into()
+    }
+
+    /// Find the module owning the value among `heap` and the heaps it keeps alive.
+    pub(crate) fn find_owner(&mut self, heap: &FrozenHeap) {
+        if self.owner.is_none() {
+            self.owner = heap.owner_of(self.address);
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub(crate) enum ControlError {
     #[error("Value of type `{0}` is not hashable")]
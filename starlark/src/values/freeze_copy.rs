@@ -0,0 +1,220 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Copying individual values to a frozen heap while their module is still mutable.
+
+use std::collections::HashMap;
+
+use crate::collections::Hashed;
+use crate::collections::SmallMap;
+use crate::values::bytes::StarlarkBytes;
+use crate::values::dict::DictRef;
+use crate::values::float::StarlarkFloat;
+use crate::values::list::ListRef;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::types::bigint::StarlarkBigInt;
+use crate::values::types::dict::value::FrozenDictData;
+use crate::values::types::dict::Dict;
+use crate::values::types::structs::value::FrozenStruct;
+use crate::values::types::structs::value::Struct;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::OwnedFrozenValue;
+use crate::values::Value;
+use crate::values::ValueIdentity;
+use crate::values::ValueLike;
+
+#[derive(Debug, thiserror::Error)]
+enum FreezeCopyError {
+    #[error("Value of type `{0}` cannot be frozen on its own, only data values can")]
+    Unsupported(String),
+    #[error("Value `{0}` cannot be frozen on its own, because it contains itself")]
+    Cycle(String),
+}
+
+struct FreezeCopier<'v> {
+    heap: FrozenHeap,
+    /// Values already copied, or `None` for values currently being copied.
+    copied: HashMap<ValueIdentity<'v>, Option<FrozenValue>>,
+}
+
+impl<'v> FreezeCopier<'v> {
+    fn copy(&mut self, value: Value<'v>) -> anyhow::Result<FrozenValue> {
+        if value.is_none() || value.unpack_bool().is_some() || value.unpack_int().is_some() {
+            // These are never allocated on a heap.
+            return Ok(value.unpack_frozen().unwrap());
+        }
+        match self.copied.get(&value.identity()) {
+            Some(Some(x)) => return Ok(*x),
+            Some(None) => return Err(FreezeCopyError::Cycle(value.to_repr()).into()),
+            None => {}
+        }
+        self.copied.insert(value.identity(), None);
+        let res = self.copy_uncached(value)?;
+        self.copied.insert(value.identity(), Some(res));
+        Ok(res)
+    }
+
+    fn copy_uncached(&mut self, value: Value<'v>) -> anyhow::Result<FrozenValue> {
+        if let Some(s) = value.unpack_str() {
+            Ok(self.heap.alloc_str(s).to_frozen_value())
+        } else if let Some(x) = value.downcast_ref::<StarlarkFloat>() {
+            Ok(self.heap.alloc_float(*x))
+        } else if let Some(x) = value.downcast_ref::<StarlarkBigInt>() {
+            Ok(self.heap.alloc_simple(x.clone()))
+        } else if let Some(x) = value.downcast_ref::<StarlarkBytes>() {
+            Ok(self.heap.alloc_bytes(x.clone()))
+        } else if let Some(x) = ListRef::from_value(value) {
+            let elems = x
+                .iter()
+                .map(|v| self.copy(v))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok(self.heap.alloc_list(&elems))
+        } else if let Some(x) = TupleRef::from_value(value) {
+            let elems = x
+                .iter()
+                .map(|v| self.copy(v))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok(self.heap.alloc_tuple(&elems))
+        } else if let Some(x) = DictRef::from_value(value) {
+            let mut content = SmallMap::with_capacity(x.len());
+            for (k, v) in x.iter_hashed() {
+                // Copies compare and hash the same as the originals.
+                let k = Hashed::new_unchecked(k.hash(), self.copy(*k.key())?);
+                content.insert_hashed(k, self.copy(v)?);
+            }
+            Ok(self.heap.alloc(FrozenDictData { content }))
+        } else if let Some(x) = StructRef::from_value(value) {
+            let mut fields = SmallMap::with_capacity(x.iter().len());
+            for (k, v) in x.iter() {
+                fields.insert(self.heap.alloc_str(k.as_str()), self.copy(v)?);
+            }
+            Ok(self.heap.alloc(FrozenStruct::new(fields)))
+        } else {
+            Err(FreezeCopyError::Unsupported(value.get_type().to_owned()).into())
+        }
+    }
+}
+
+impl<'v> Value<'v> {
+    /// Copy this value, and everything reachable from it, to a new frozen heap,
+    /// leaving the value itself and the module it lives in mutable.
+    ///
+    /// The result can be sent to other threads while evaluation continues,
+    /// e.g. to publish an intermediate registry dict. Later mutations of this
+    /// value are not reflected in the copy. Only data values can be copied:
+    /// `None`, `bool`, `int`, `float`, `str`, `bytes`, `list`, `tuple`, `dict`
+    /// and `struct`. Values that contain themselves are rejected.
+    pub fn freeze_copy(self) -> anyhow::Result<OwnedFrozenValue> {
+        let mut copier = FreezeCopier {
+            heap: FrozenHeap::new(),
+            copied: HashMap::new(),
+        };
+        let value = copier.copy(self)?;
+        // SAFETY: all values reachable from `value` were allocated on `copier.heap`,
+        //   or are static.
+        Ok(unsafe { OwnedFrozenValue::new(copier.heap.into_ref(), value) })
+    }
+}
+
+/// The opposite of [`Value::freeze_copy`]: copy the containers reachable from a
+/// frozen `value` to `heap`, so that lists and dicts in the result can be mutated.
+/// Other values are shared with `value`, which the caller must keep alive.
+pub(crate) fn thaw_copy<'v>(value: Value<'v>, heap: &'v Heap) -> Value<'v> {
+    if let Some(x) = ListRef::from_value(value) {
+        let elems: Vec<_> = x.iter().map(|v| thaw_copy(v, heap)).collect();
+        heap.alloc_list(&elems)
+    } else if let Some(x) = TupleRef::from_value(value) {
+        let elems: Vec<_> = x.iter().map(|v| thaw_copy(v, heap)).collect();
+        heap.alloc_tuple(&elems)
+    } else if let Some(x) = DictRef::from_value(value) {
+        let mut content = SmallMap::with_capacity(x.len());
+        for (k, v) in x.iter_hashed() {
+            content.insert_hashed(k, thaw_copy(v, heap));
+        }
+        heap.alloc(Dict::new(content))
+    } else if let Some(x) = StructRef::from_value(value) {
+        let mut fields = SmallMap::with_capacity(x.iter().len());
+        for (k, v) in x.iter() {
+            fields.insert(k, thaw_copy(v, heap));
+        }
+        heap.alloc(Struct::new(fields))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::values::Value;
+
+    fn eval<'v>(module: &'v Module, program: &str) -> Value<'v> {
+        let ast = AstModule::parse("test.star", program.to_owned(), &Dialect::Extended).unwrap();
+        let mut eval = Evaluator::new(module);
+        eval.eval_module(ast, &Globals::extended()).unwrap()
+    }
+
+    #[test]
+    fn test_freeze_copy() {
+        let module = Module::new();
+        let registry = eval(&module, "registry = {'a': [1, 2]}\nregistry");
+        let copy = registry.freeze_copy().unwrap();
+        assert_eq!(r#"{"a": [1, 2]}"#, copy.value().to_repr());
+
+        // The original stays mutable, and the copy does not change.
+        eval(
+            &module,
+            "registry['a'].append(3)\nregistry['b'] = struct(x = 1.5, y = (True, None))",
+        );
+        assert_eq!(r#"{"a": [1, 2]}"#, copy.value().to_repr());
+        assert_eq!(
+            r#"{"a": [1, 2, 3], "b": struct(x=1.5, y=(True, None))}"#,
+            registry.freeze_copy().unwrap().value().to_repr()
+        );
+        assert!(module.freeze().is_ok());
+    }
+
+    #[test]
+    fn test_freeze_copy_shared() {
+        let module = Module::new();
+        let x = eval(&module, "x = []\n[x, x]");
+        assert_eq!("[[], []]", x.freeze_copy().unwrap().value().to_repr());
+    }
+
+    #[test]
+    fn test_freeze_copy_errors() {
+        let module = Module::new();
+        let x = eval(&module, "x = []\nx.append(x)\nx");
+        assert!(x
+            .freeze_copy()
+            .unwrap_err()
+            .to_string()
+            .contains("because it contains itself"));
+        let f = eval(&module, "def f(): pass\n[f]");
+        assert_eq!(
+            "Value of type `function` cannot be frozen on its own, only data values can",
+            f.freeze_copy().unwrap_err().to_string()
+        );
+    }
+}
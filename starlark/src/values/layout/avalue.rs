@@ -55,6 +55,7 @@ use crate::values::string::StarlarkStr;
 use crate::values::traits::StarlarkValueDyn;
 use crate::values::types::any_array::AnyArray;
 use crate::values::types::array::Array;
+use crate::values::types::bytes::StarlarkBytes;
 use crate::values::types::list::value::FrozenListData;
 use crate::values::types::list::value::ListData;
 use crate::values::types::tuple::value::FrozenTuple;
@@ -254,6 +255,10 @@ pub(crate) fn float_avalue<'v>(x: StarlarkFloat) -> impl AValue<'v, ExtraElem =
     AValueImpl(Direct, x)
 }
 
+pub(crate) fn bytes_avalue<'v>(x: StarlarkBytes) -> impl AValue<'v, ExtraElem = ()> + Send + Sync {
+    AValueImpl(Direct, x)
+}
+
 // A type where the second element is in control of what instances are in scope
 pub(crate) struct Direct;
 
@@ -342,6 +347,38 @@ impl<'v> AValue<'v> for AValueImpl<Direct, StarlarkFloat> {
     }
 }
 
+/// Like a simple value, but the content lives outside the heap, so it is recorded
+/// against the heap it is copied or frozen into.
+impl<'v> AValue<'v> for AValueImpl<Direct, StarlarkBytes> {
+    type StarlarkValue = StarlarkBytes;
+
+    type ExtraElem = ();
+
+    fn extra_len(&self) -> usize {
+        0
+    }
+
+    fn offset_of_extra() -> usize {
+        mem::size_of::<Self>()
+    }
+
+    unsafe fn heap_freeze(
+        me: *mut AValueRepr<Self>,
+        freezer: &Freezer,
+    ) -> anyhow::Result<FrozenValue> {
+        freezer
+            .heap
+            .add_external_bytes((*me).payload.1.as_bytes().len());
+        Self::heap_freeze_simple_impl(me, freezer)
+    }
+
+    unsafe fn heap_copy(me: *mut AValueRepr<Self>, tracer: &Tracer<'v>) -> Value<'v> {
+        Self::heap_copy_impl(me, tracer, |x, tracer| {
+            tracer.add_external_bytes(x.as_bytes().len())
+        })
+    }
+}
+
 pub(crate) type StarlarkStrAValue = AValueImpl<Direct, StarlarkStr>;
 
 impl<'v> AValue<'v> for AValueImpl<Direct, StarlarkStr> {
@@ -26,6 +26,7 @@
 //! to tag it as being a usize, and the word after is the size of the
 //! item it replaced.
 
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::mem;
@@ -81,6 +82,9 @@ pub(crate) struct Arena {
     non_drop: Bump,
     /// Arena for things which might need dropping (e.g. Vec, with memory on heap)
     drop: Bump,
+    /// Memory owned by values in this arena but allocated outside it, e.g. the
+    /// content of `bytes`.
+    external_bytes: Cell<usize>,
 }
 
 /// Reservation is morally a Reservation<T>, but we treat is as an
@@ -139,6 +143,25 @@ impl<'c> Iterator for ChunkIter<'c> {
 }
 
 impl Arena {
+    /// An arena with `capacity` bytes reserved, split between the bumps.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Arena {
+            non_drop: Bump::with_capacity(capacity / 2),
+            drop: Bump::with_capacity(capacity / 2),
+            external_bytes: Cell::new(0),
+        }
+    }
+
+    /// Record that a value allocated in this arena owns `bytes` more outside of it.
+    pub(crate) fn add_external_bytes(&self, bytes: usize) {
+        self.external_bytes
+            .set(self.external_bytes.get().saturating_add(bytes));
+    }
+
+    pub(crate) fn external_bytes(&self) -> usize {
+        self.external_bytes.get()
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.allocated_bytes() == 0
     }
@@ -385,6 +408,12 @@ impl Arena {
         }
     }
 
+    /// Whether `ptr` points into memory allocated by this arena.
+    pub(crate) fn contains(&self, ptr: usize) -> bool {
+        self.used_chunks()
+            .any(|(data, len)| (data as usize..data as usize + len).contains(&ptr))
+    }
+
     // Iterate over the values in the both bumps in any order
     fn for_each_unordered<'a>(&'a self, mut f: impl FnMut(&'a AValueHeader)) {
         for bump in [&self.drop, &self.non_drop] {
@@ -421,6 +450,27 @@ impl Arena {
     pub(crate) fn unused_capacity(&self) -> usize {
         self.drop.chunk_capacity() + self.non_drop.chunk_capacity()
     }
+
+    /// The (start, used length) of the chunks holding values.
+    fn used_chunks(&self) -> impl Iterator<Item = (*mut u8, usize)> + '_ {
+        // SAFETY: Callers consume the iterator straight away, without allocating from the
+        // arena meanwhile.
+        [&self.drop, &self.non_drop]
+            .into_iter()
+            .flat_map(|bump| unsafe { bump.iter_allocated_chunks_raw() })
+    }
+
+    /// Number of chunks of memory the arena holds values in.
+    pub(crate) fn chunks(&self) -> usize {
+        self.used_chunks().count()
+    }
+
+    /// Memory at the end of chunks that were filled, so a value too big for the space
+    /// left went into a new chunk instead.
+    pub(crate) fn wasted_bytes(&self) -> usize {
+        let used: usize = self.used_chunks().map(|(_, len)| len).sum();
+        self.allocated_bytes() - self.available_bytes() - used
+    }
 }
 
 impl Drop for Arena {
@@ -435,7 +485,12 @@ impl Drop for Arena {
 
 impl Allocative for Arena {
     fn visit<'a, 'b: 'a>(&self, visitor: &'a mut allocative::Visitor<'b>) {
-        let Arena { drop, non_drop } = self;
+        // External memory is visited through the values owning it.
+        let Arena {
+            drop,
+            non_drop,
+            external_bytes: _,
+        } = self;
 
         fn visit_bump<'a, 'b: 'a>(bump: &Bump, visitor: &'a mut Visitor<'b>) {
             let mut visitor =
@@ -46,14 +46,18 @@ impl<T> Drop for FastCell<T> {
 
 impl<T: Default> Default for FastCell<T> {
     fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> FastCell<T> {
+    pub(crate) fn new(value: T) -> Self {
         Self {
-            value: UnsafeCell::new(MaybeUninit::new(T::default())),
+            value: UnsafeCell::new(MaybeUninit::new(value)),
             init: Cell::new(true),
         }
     }
-}
 
-impl<T> FastCell<T> {
     /// Get a reference to the value.
     ///
     /// This operation is safe under assumption that other `unsafe` operations
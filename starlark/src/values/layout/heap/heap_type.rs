@@ -18,6 +18,7 @@
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::cmp;
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -45,11 +46,14 @@ use crate::collections::maybe_uninit_backport::maybe_uninit_write_slice;
 use crate::collections::maybe_uninit_backport::maybe_uninit_write_slice_cloned;
 use crate::collections::Hashed;
 use crate::collections::StarlarkHashValue;
+use crate::environment::Globals;
 use crate::eval::compiler::def::FrozenDef;
 use crate::values::any::StarlarkAny;
 use crate::values::array::Array;
+use crate::values::label::StarlarkLabel;
 use crate::values::layout::avalue::any_array_avalue;
 use crate::values::layout::avalue::array_avalue;
+use crate::values::layout::avalue::bytes_avalue;
 use crate::values::layout::avalue::complex;
 use crate::values::layout::avalue::complex_no_freeze;
 use crate::values::layout::avalue::float_avalue;
@@ -71,6 +75,8 @@ use crate::values::layout::heap::call_enter_exit::NeedsDrop;
 use crate::values::layout::heap::call_enter_exit::NoDrop;
 use crate::values::layout::heap::fast_cell::FastCell;
 use crate::values::layout::heap::maybe_uninit_slice_util::maybe_uninit_write_from_exact_size_iter;
+use crate::values::layout::heap::options::ChunkGrowth;
+use crate::values::layout::heap::options::HeapOptions;
 use crate::values::layout::heap::profile::by_type::HeapSummary;
 use crate::values::layout::heap::repr::AValueRepr;
 use crate::values::layout::static_string::constant_string;
@@ -79,6 +85,7 @@ use crate::values::layout::value::FrozenValue;
 use crate::values::layout::value::Value;
 use crate::values::string::intern::interner::FrozenStringInterner;
 use crate::values::string::StarlarkStr;
+use crate::values::types::bytes::StarlarkBytes;
 use crate::values::types::float::StarlarkFloat;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
@@ -99,12 +106,21 @@ pub(crate) enum HeapKind {
     Frozen,
 }
 
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum HeapError {
+    #[error("Starlark memory limit exceeded: {0} bytes allocated, limit is {1} bytes")]
+    MemoryLimitExceeded(usize, usize),
+}
+
 /// A heap on which [`Value`]s can be allocated. The values will be annotated with the heap lifetime.
 #[derive(Default)]
 pub struct Heap {
     /// Peak memory seen when a garbage collection takes place (may be lower than currently allocated)
     peak_allocated: Cell<usize>,
     arena: FastCell<Arena>,
+    options: HeapOptions,
+    /// Checked by [`Heap::check_allocation`] before large allocations, if set.
+    max_allocated_bytes: Cell<Option<usize>>,
 }
 
 impl Debug for Heap {
@@ -128,6 +144,10 @@ pub struct FrozenHeap {
     refs: RefCell<SmallSet<FrozenHeapRef>>,
     /// String interner.
     str_interner: RefCell<FrozenStringInterner>,
+    /// Globals whose interned strings are reused instead of allocating new ones.
+    shared_strings: RefCell<Option<Globals>>,
+    /// The module the values belong to, used to explain errors when they are mutated.
+    owner: RefCell<Option<String>>,
 }
 
 /// `FrozenHeap` when it is no longer modified and can be share between threads.
@@ -137,6 +157,7 @@ pub struct FrozenHeap {
 struct FrozenFrozenHeap {
     arena: Arena,
     refs: Box<[FrozenHeapRef]>,
+    owner: Option<String>,
 }
 
 // Safe because we never mutate the Arena other than with &mut
@@ -217,6 +238,19 @@ impl FrozenHeapRef {
             .as_ref()
             .map_or_else(HeapSummary::default, |a| a.arena.allocated_summary())
     }
+
+    /// The owner of the heap holding the value at `ptr`, searching this heap and those it
+    /// keeps alive. Skips the heaps in `visited`, adding those it searches.
+    fn owner_of(&self, ptr: usize, visited: &mut HashSet<*const FrozenFrozenHeap>) -> Option<&str> {
+        let heap = self.0.as_deref()?;
+        if !visited.insert(heap) {
+            return None;
+        }
+        if heap.arena.contains(ptr) {
+            return heap.owner.as_deref();
+        }
+        heap.refs.iter().find_map(|x| x.owner_of(ptr, visited))
+    }
 }
 
 impl FrozenHeap {
@@ -229,7 +263,9 @@ impl FrozenHeap {
     /// [`FrozenHeapRef`] which can be [`clone`](Clone::clone)d, shared between threads,
     /// and ensures the underlying values allocated on the [`FrozenHeap`] remain valid.
     pub fn into_ref(self) -> FrozenHeapRef {
-        let FrozenHeap { arena, refs, .. } = self;
+        let FrozenHeap {
+            arena, refs, owner, ..
+        } = self;
         let refs = refs.into_inner();
         if arena.is_empty() && refs.is_empty() {
             FrozenHeapRef::default()
@@ -237,10 +273,33 @@ impl FrozenHeap {
             FrozenHeapRef(Some(Arc::new(FrozenFrozenHeap {
                 arena,
                 refs: refs.into_iter().collect(),
+                owner: owner.into_inner(),
             })))
         }
     }
 
+    /// Record the module the values on this heap belong to, unless already set.
+    pub(crate) fn set_default_owner(&self, owner: &str) {
+        self.owner
+            .borrow_mut()
+            .get_or_insert_with(|| owner.to_owned());
+    }
+
+    /// The module owning the frozen value at `ptr`, if it is on this heap or one it
+    /// keeps alive, and that heap records its owner. Values shared between all heaps,
+    /// such as the empty list, have no owner.
+    pub(crate) fn owner_of(&self, ptr: usize) -> Option<String> {
+        if self.arena.contains(ptr) {
+            return self.owner.borrow().clone();
+        }
+        let mut visited = HashSet::new();
+        self.refs
+            .borrow()
+            .iter()
+            .find_map(|x| x.owner_of(ptr, &mut visited))
+            .map(|x| x.to_owned())
+    }
+
     /// Keep the argument [`FrozenHeapRef`] alive as long as this [`FrozenHeap`]
     /// is kept alive. Used if a [`FrozenValue`] in this heap points at values in another
     /// [`FrozenHeap`].
@@ -291,16 +350,54 @@ impl FrozenHeap {
         self.alloc_str_impl(x, StarlarkStr::UNINIT_HASH)
     }
 
-    /// Intern string.
-    pub(crate) fn alloc_str_intern(&self, s: &str) -> FrozenStringValue {
+    /// Intern a string: allocating the same string again on this heap returns the same value.
+    ///
+    /// If [`use_interned_strings`](FrozenHeap::use_interned_strings) was called,
+    /// strings interned by those globals are reused rather than allocated.
+    pub fn alloc_str_intern(&self, s: &str) -> FrozenStringValue {
         if let Some(s) = constant_string(s) {
             s
         } else {
             let s = Hashed::new(s);
             self.str_interner
                 .borrow_mut()
-                .intern(s, || self.alloc_str_hashed(s))
+                .intern(s, || self.alloc_str_shared(s))
+        }
+    }
+
+    fn alloc_str_shared(&self, s: Hashed<&str>) -> FrozenStringValue {
+        if let Some(globals) = &*self.shared_strings.borrow() {
+            if let Some(shared) = globals.interned_str_hashed(s) {
+                self.add_reference(globals.heap());
+                return shared;
+            }
         }
+        self.alloc_str_hashed(s)
+    }
+
+    /// Reuse the strings interned on the heap of `globals`
+    /// (such as the names of the globals) when interning strings on this heap,
+    /// so they are only stored once across all modules using these globals.
+    ///
+    /// Evaluating a module with [`Evaluator::eval_module`](crate::eval::Evaluator::eval_module)
+    /// calls this automatically, so identifiers are shared during compilation.
+    pub fn use_interned_strings(&self, globals: &Globals) {
+        *self.shared_strings.borrow_mut() = Some(globals.dupe());
+    }
+
+    /// Take the strings interned so far, leaving the interner empty.
+    pub(crate) fn take_str_interner(&self) -> FrozenStringInterner {
+        mem::take(&mut *self.str_interner.borrow_mut())
+    }
+
+    /// Allocate a label-like string such as `//package/path:name`, sharing the
+    /// `//package/path:` prefix with the other labels allocated on this heap.
+    pub fn alloc_label(&self, label: &str) -> FrozenValue {
+        let (prefix, name) = StarlarkLabel::split(label);
+        self.alloc_simple(StarlarkLabel::new(
+            self.alloc_str_intern(prefix),
+            self.alloc_str_intern(name),
+        ))
     }
 
     /// Allocate prehashed string.
@@ -387,6 +484,16 @@ impl FrozenHeap {
         self.alloc_raw(float_avalue(f))
     }
 
+    pub(crate) fn alloc_bytes(&self, x: StarlarkBytes) -> FrozenValue {
+        self.add_external_bytes(x.as_bytes().len());
+        self.alloc_raw(bytes_avalue(x))
+    }
+
+    /// Record memory owned by a value on this heap but allocated outside of it.
+    pub(crate) fn add_external_bytes(&self, bytes: usize) {
+        self.arena.add_external_bytes(bytes)
+    }
+
     pub(crate) fn alloc_simple_typed<T: StarlarkValue<'static> + Send + Sync>(
         &self,
         val: T,
@@ -462,10 +569,10 @@ impl FrozenHeap {
         val.alloc_frozen_value(self)
     }
 
-    /// Number of bytes allocated on this heap, not including any memory
-    /// allocated outside of the starlark heap.
+    /// Number of bytes allocated on this heap, including the content of `bytes`
+    /// values but not any other memory allocated outside of the starlark heap.
     pub fn allocated_bytes(&self) -> usize {
-        self.arena.allocated_bytes()
+        self.arena.allocated_bytes() + self.arena.external_bytes()
     }
 
     /// Number of bytes allocated by the heap but not yet filled.
@@ -562,10 +669,40 @@ impl Heap {
         Self::default()
     }
 
-    /// Number of bytes allocated on this heap, not including any memory
-    /// allocated outside of the starlark heap.
+    /// Create a new [`Heap`] which allocates memory as per `options`.
+    pub fn with_options(options: HeapOptions) -> Self {
+        Self {
+            peak_allocated: Cell::new(0),
+            arena: FastCell::new(Arena::with_capacity(options.initial_capacity)),
+            options,
+            max_allocated_bytes: Cell::new(None),
+        }
+    }
+
+    /// Limit the number of bytes the fallible allocation functions,
+    /// such as [`try_alloc_str_init`](Heap::try_alloc_str_init), may take this heap to.
+    /// Other allocations and garbage collection are not limited.
+    pub(crate) fn set_max_allocated_bytes(&self, bytes: Option<usize>) {
+        self.max_allocated_bytes.set(bytes);
+    }
+
+    /// Check allocating `bytes` more would not take this heap over the limit set by
+    /// [`set_max_allocated_bytes`](Heap::set_max_allocated_bytes).
+    pub(crate) fn check_allocation(&self, bytes: usize) -> anyhow::Result<()> {
+        if let Some(limit) = self.max_allocated_bytes.get() {
+            let allocated = self.allocated_bytes().saturating_add(bytes);
+            if allocated > limit {
+                return Err(HeapError::MemoryLimitExceeded(allocated, limit).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of bytes allocated on this heap, including the content of `bytes`
+    /// values but not any other memory allocated outside of the starlark heap.
     pub fn allocated_bytes(&self) -> usize {
-        self.arena.borrow().allocated_bytes()
+        let arena = self.arena.borrow();
+        arena.allocated_bytes() + arena.external_bytes()
     }
 
     /// Peak memory allocated to this heap, even if the value is now lower
@@ -579,6 +716,18 @@ impl Heap {
         self.arena.borrow().available_bytes()
     }
 
+    /// Number of chunks of memory the heap has allocated values in. Many chunks
+    /// suggest a larger [`initial_capacity`](HeapOptions::initial_capacity).
+    pub fn arena_chunks(&self) -> usize {
+        self.arena.borrow().chunks()
+    }
+
+    /// Number of bytes left unused at the end of chunks, because the next value
+    /// didn't fit and went into a new chunk.
+    pub fn wasted_bytes(&self) -> usize {
+        self.arena.borrow().wasted_bytes()
+    }
+
     fn alloc_raw<'v, 'v2: 'v2>(&'v self, x: impl AValue<'v2, ExtraElem = ()>) -> Value<'v> {
         let arena = self.arena.borrow();
         let v: &AValueRepr<_> = arena.alloc(x);
@@ -616,6 +765,17 @@ impl Heap {
         }
     }
 
+    /// Like [`alloc_str_init`](Heap::alloc_str_init), but failing if the string
+    /// would take the heap over its limit.
+    pub(crate) fn try_alloc_str_init<'v>(
+        &'v self,
+        len: usize,
+        init: impl FnOnce(*mut u8),
+    ) -> anyhow::Result<StringValue<'v>> {
+        self.check_allocation(len)?;
+        Ok(self.alloc_str_init(len, init))
+    }
+
     /// Allocate a string on the heap.
     pub fn alloc_str<'v>(&'v self, x: &str) -> StringValue<'v> {
         if let Some(x) = constant_string(x) {
@@ -736,6 +896,22 @@ impl Heap {
         self.alloc_raw(list_avalue(array))
     }
 
+    /// Allocate a list of `elems` repeated `n` times, failing if that
+    /// would take the heap over its limit.
+    pub(crate) fn try_alloc_list_repeat<'v>(
+        &'v self,
+        elems: &[Value<'v>],
+        n: usize,
+    ) -> anyhow::Result<Value<'v>> {
+        let len = elems.len() * n;
+        self.check_allocation(len * mem::size_of::<Value>())?;
+        let array = self.alloc_array(len);
+        for _ in 0..n {
+            array.extend_from_slice(elems);
+        }
+        Ok(self.alloc_raw(list_avalue(array)))
+    }
+
     pub(crate) fn alloc_char<'v>(&'v self, x: char) -> StringValue<'v> {
         let mut dst = [0; 4];
         let res = x.encode_utf8(&mut dst);
@@ -746,6 +922,11 @@ impl Heap {
         self.alloc_raw(float_avalue(f))
     }
 
+    pub(crate) fn alloc_bytes<'v>(&'v self, x: StarlarkBytes) -> Value<'v> {
+        self.arena.borrow().add_external_bytes(x.as_bytes().len());
+        self.alloc_raw(bytes_avalue(x))
+    }
+
     /// Allocate a simple [`StarlarkValue`] on this heap.
     ///
     /// Simple value is any starlark value which:
@@ -823,10 +1004,16 @@ impl Heap {
         // Must rewrite all Value's so they point at the new heap.
         // Take the arena out of the heap to make sure nobody allocates in it,
         // but hold the reference until the GC is done.
-        let _arena = self.arena.take();
+        let arena = self.arena.take();
+        let capacity = match self.options.chunk_growth {
+            ChunkGrowth::Restart => self.options.initial_capacity,
+            ChunkGrowth::KeepCapacity => {
+                cmp::max(self.options.initial_capacity, arena.allocated_bytes())
+            }
+        };
 
         let tracer = Tracer::<'v> {
-            arena: Arena::default(),
+            arena: Arena::with_capacity(capacity),
             phantom: PhantomData,
         };
         f(&tracer);
@@ -886,6 +1073,11 @@ impl<'v> Tracer<'v> {
         *value = self.adjust(*value)
     }
 
+    /// Record memory owned by a value copied by this tracer but allocated outside the heap.
+    pub(crate) fn add_external_bytes(&self, bytes: usize) {
+        self.arena.add_external_bytes(bytes)
+    }
+
     /// Helper function to annotate that this field has been considered for tracing,
     /// but is not relevant because it has a static lifetime containing no relevant values.
     /// Does nothing.
@@ -0,0 +1,44 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dupe::Dupe;
+
+/// How a [`Heap`](crate::values::Heap) sizes the memory it starts with after a
+/// garbage collection, which copies the live values into fresh memory.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Default)]
+pub enum ChunkGrowth {
+    /// Start again from [`HeapOptions::initial_capacity`], adding chunks of increasing
+    /// size as they fill up.
+    #[default]
+    Restart,
+    /// Reserve as much memory as was in use before the collection, on the basis that the
+    /// heap will grow to that size again, so fewer chunks are needed to get there.
+    KeepCapacity,
+}
+
+/// Options controlling how a [`Heap`](crate::values::Heap) allocates memory, for
+/// embedders running many evaluations who want to tune allocation for their workload.
+/// The [`arena_chunks`](crate::values::Heap::arena_chunks) and
+/// [`wasted_bytes`](crate::values::Heap::wasted_bytes) counters show the effect.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Default)]
+pub struct HeapOptions {
+    /// Bytes to reserve up front, so small evaluations fit in a single chunk.
+    /// With `0` memory is only reserved once the first value is allocated.
+    pub initial_capacity: usize,
+    /// How to size the memory after a garbage collection.
+    pub chunk_growth: ChunkGrowth,
+}
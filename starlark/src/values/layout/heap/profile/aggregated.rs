@@ -34,7 +34,9 @@ use starlark_map::small_map::SmallMap;
 use crate::eval::runtime::profile::data::ProfileDataImpl;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
 use crate::eval::runtime::profile::flamegraph::FlameGraphNode;
+use crate::eval::runtime::profile::heap::HeapProfileFormat;
 use crate::eval::runtime::profile::heap::RetainedHeapProfileMode;
+use crate::eval::runtime::profile::pprof::PprofBuilder;
 use crate::eval::runtime::small_duration::SmallDuration;
 use crate::eval::ProfileData;
 use crate::values::layout::heap::arena::ArenaVisitor;
@@ -288,6 +290,21 @@ impl<'c> StackFrameWithContext<'c> {
             frame.write_flame_graph(child_node);
         }
     }
+
+    /// Write this stack frame's data as pprof samples, `stack` being its callers, leaf first.
+    fn write_pprof(&self, builder: &mut PprofBuilder, stack: &mut Vec<u64>) {
+        for (k, v) in &self.frame.allocs.summary {
+            let mut locations = vec![builder.location(k)];
+            locations.extend(stack.iter().rev());
+            builder.add_sample(locations, vec![v.count as i64, v.bytes as i64]);
+        }
+
+        for (id, frame) in self.callees() {
+            stack.push(builder.location(id));
+            frame.write_pprof(builder, stack);
+            stack.pop().unwrap();
+        }
+    }
 }
 
 /// `Clone` wrapper.
@@ -400,6 +417,20 @@ impl AggregateHeapProfileInfo {
         data.write()
     }
 
+    /// Write this out in pprof protobuf format, with allocation count and bytes per stack.
+    pub fn gen_pprof(&self) -> Vec<u8> {
+        let mut builder = PprofBuilder::new(&[("allocations", "count"), ("space", "bytes")]);
+        let mut stack = Vec::new();
+        self.root().write_pprof(&mut builder, &mut stack);
+        assert!(stack.is_empty());
+        let unused_capacity = builder.location("unused_capacity");
+        builder.add_sample(
+            vec![unused_capacity],
+            vec![0, self.unused_capacity.get() as i64],
+        );
+        builder.build()
+    }
+
     /// Write per-function summary in CSV format.
     pub fn gen_summary_csv(&self) -> String {
         HeapSummaryByFunction::init(self).gen_csv()
@@ -410,12 +441,16 @@ impl AggregateHeapProfileInfo {
 pub(crate) struct RetainedHeapProfile {
     pub(crate) info: AggregateHeapProfileInfo,
     pub(crate) mode: RetainedHeapProfileMode,
+    pub(crate) format: HeapProfileFormat,
 }
 
 impl RetainedHeapProfile {
     pub(crate) fn to_profile(&self) -> ProfileData {
         ProfileData {
-            profile: ProfileDataImpl::AggregateHeapProfileInfo(Box::new(self.info.clone())),
+            profile: ProfileDataImpl::AggregateHeapProfileInfo(
+                Box::new(self.info.clone()),
+                self.format,
+            ),
             profile_mode: self.mode.to_profile_mode(),
         }
     }
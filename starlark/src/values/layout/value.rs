@@ -98,6 +98,7 @@ use crate::values::stack_guard;
 use crate::values::string::StarlarkStr;
 use crate::values::structs::value::FrozenStruct;
 use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::types::bytes::StarlarkBytes;
 use crate::values::types::list::value::FrozenListData;
 use crate::values::types::tuple::value::FrozenTuple;
 use crate::values::types::tuple::value::Tuple;
@@ -680,10 +681,14 @@ impl<'v> Value<'v> {
     }
 
     /// Implement the `str()` function - converts a string value to itself,
-    /// otherwise uses `repr()`.
+    /// decodes bytes as UTF-8, otherwise uses `repr()`.
     pub fn to_str(self) -> String {
         match self.unpack_str() {
-            None => self.to_repr(),
+            None => {
+                let mut s = String::new();
+                self.collect_str(&mut s);
+                s
+            }
             Some(s) => s.to_owned(),
         }
     }
@@ -958,6 +963,16 @@ impl FrozenValue {
         Value::new_frozen(self)
     }
 
+    /// Get the documentation of this value, if it has any.
+    ///
+    /// For functions defined in Starlark, including closures and lambdas, this is
+    /// their signature with parameter types and default values, and their parsed docstring.
+    /// This lets embedders generate documentation for libraries written in Starlark,
+    /// not just for native globals.
+    pub fn documentation(self) -> Option<DocItem> {
+        self.to_value().documentation()
+    }
+
     /// Is this type builtin? We perform certain optimizations only on builtin types
     /// because we know they have well defined semantics.
     pub(crate) fn is_builtin(self) -> bool {
@@ -1114,6 +1129,9 @@ pub trait ValueLike<'v>:
     fn collect_str(self, collector: &mut String) {
         if let Some(s) = self.to_value().unpack_str() {
             collector.push_str(s);
+        } else if let Some(x) = StarlarkBytes::from_value(self.to_value()) {
+            // Bytes are decoded, replacing invalid UTF-8
+            collector.push_str(&String::from_utf8_lossy(x.as_bytes()));
         } else {
             self.collect_repr(collector);
         }
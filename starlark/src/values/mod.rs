@@ -34,6 +34,8 @@ pub use starlark_derive::starlark_attrs;
 pub use starlark_derive::Freeze;
 pub use starlark_derive::NoSerialize;
 pub use starlark_derive::StarlarkAttrs;
+pub use starlark_derive::StarlarkSimpleValue;
+pub use starlark_derive::StarlarkUnpack;
 pub use starlark_derive::Trace;
 
 pub use crate::any::AnyLifetime;
@@ -42,6 +44,7 @@ pub use crate::coerce::Coerce;
 pub use crate::values::alloc_value::AllocFrozenValue;
 pub use crate::values::alloc_value::AllocValue;
 pub use crate::values::demand::Demand;
+pub use crate::values::deserialize::DeserializeError;
 pub use crate::values::error::ValueError;
 pub use crate::values::freeze::Freeze;
 pub use crate::values::frozen_ref::FrozenRef;
@@ -50,6 +53,8 @@ pub use crate::values::layout::heap::heap_type::FrozenHeap;
 pub use crate::values::layout::heap::heap_type::FrozenHeapRef;
 pub use crate::values::layout::heap::heap_type::Heap;
 pub use crate::values::layout::heap::heap_type::Tracer;
+pub use crate::values::layout::heap::options::ChunkGrowth;
+pub use crate::values::layout::heap::options::HeapOptions;
 pub use crate::values::layout::heap::profile::aggregated::AggregateHeapProfileInfo;
 pub use crate::values::layout::identity::ValueIdentity;
 pub use crate::values::layout::static_string::constant_string;
@@ -64,18 +69,23 @@ pub use crate::values::layout::value::Value;
 pub use crate::values::layout::value::ValueLike;
 pub use crate::values::owned::OwnedFrozenValue;
 pub use crate::values::owned::OwnedFrozenValueTyped;
+pub use crate::values::serialize::SerializeError;
 pub use crate::values::trace::Trace;
 pub use crate::values::traits::ComplexValue;
 pub use crate::values::traits::StarlarkValue;
 pub use crate::values::types::any;
 pub use crate::values::types::array;
 pub use crate::values::types::bool;
+pub use crate::values::types::bytes;
 pub use crate::values::types::dict;
 pub use crate::values::types::enumeration;
 pub use crate::values::types::float;
 pub use crate::values::types::function;
 pub use crate::values::types::int;
+pub use crate::values::types::label;
+pub use crate::values::types::lazy;
 pub use crate::values::types::list;
+pub use crate::values::types::namedtuple;
 pub use crate::values::types::none;
 pub use crate::values::types::range;
 pub use crate::values::types::record;
@@ -90,8 +100,10 @@ mod alloc_value;
 pub(crate) mod basic;
 mod comparison;
 pub(crate) mod demand;
+mod deserialize;
 pub(crate) mod error;
 mod freeze;
+pub(crate) mod freeze_copy;
 pub(crate) mod frozen_ref;
 mod index;
 pub(crate) mod iter;
@@ -99,10 +111,11 @@ pub(crate) mod layout;
 pub(crate) mod num;
 mod owned;
 pub(crate) mod recursive_repr_or_json_guard;
+mod serialize;
 mod stack_guard;
 mod trace;
 mod traits;
 pub mod type_repr;
 pub(crate) mod types;
 pub(crate) mod typing;
-mod unpack;
+pub(crate) mod unpack;
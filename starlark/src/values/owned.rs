@@ -17,20 +17,28 @@
 
 use std::fmt;
 use std::fmt::Display;
+use std::hash::Hash;
 use std::ops::Deref;
 
 use allocative::Allocative;
 use dupe::Clone_;
 use dupe::Dupe;
 use dupe::Dupe_;
+use serde::Deserialize;
 
+use crate::collections::SmallMap;
+use crate::docs::DocItem;
+use crate::values::list::ListRef;
 use crate::values::none::NoneType;
+use crate::values::structs::StructRef;
 use crate::values::AllocFrozenValue;
+use crate::values::DeserializeError;
 use crate::values::FrozenHeap;
 use crate::values::FrozenHeapRef;
 use crate::values::FrozenValue;
 use crate::values::FrozenValueTyped;
 use crate::values::StarlarkValue;
+use crate::values::UnpackValue;
 use crate::values::Value;
 
 /// A [`FrozenValue`] along with a [`FrozenHeapRef`] that ensures it is kept alive.
@@ -107,6 +115,58 @@ impl OwnedFrozenValue {
         self.value.unpack_str()
     }
 
+    /// Unpack the underlying value as `T`, or [`None`] if it is not a `T`.
+    /// Anything `T` borrows (e.g. `&str`) lives as long as `self`.
+    pub fn unpack<'v, T: UnpackValue<'v>>(&'v self) -> Option<T> {
+        T::unpack_value(self.value())
+    }
+
+    /// Unpack the elements of the underlying list, or [`None`] if it is not a list
+    /// or any element is not a `T`.
+    pub fn downcast_list<'v, T: UnpackValue<'v>>(&'v self) -> Option<Vec<T>> {
+        ListRef::from_value(self.value())?
+            .iter()
+            .map(T::unpack_value)
+            .collect()
+    }
+
+    /// Unpack the entries of the underlying dictionary, or [`None`] if it is not a
+    /// dictionary or any key is not a `K` or any value is not a `V`.
+    ///
+    /// ```
+    /// use starlark::collections::SmallMap;
+    /// use starlark::environment::{Globals, Module};
+    /// use starlark::eval::Evaluator;
+    /// use starlark::syntax::{AstModule, Dialect};
+    ///
+    /// let module = Module::new();
+    /// let ast = AstModule::parse("x.star", "ports = {'http': 80}".to_owned(), &Dialect::Standard).unwrap();
+    /// Evaluator::new(&module).eval_module(ast, &Globals::standard()).unwrap();
+    /// let ports = module.freeze().unwrap().get("ports").unwrap();
+    /// let expected: SmallMap<String, i32> = [("http".to_owned(), 80)].into_iter().collect();
+    /// assert_eq!(ports.downcast_dict::<String, i32>(), Some(expected));
+    /// ```
+    pub fn downcast_dict<'v, K: UnpackValue<'v> + Hash + Eq, V: UnpackValue<'v>>(
+        &'v self,
+    ) -> Option<SmallMap<K, V>> {
+        self.unpack()
+    }
+
+    /// Unpack the fields of the underlying struct, or [`None`] if it is not a struct
+    /// or any field is not a `V`.
+    pub fn downcast_struct<'v, V: UnpackValue<'v>>(&'v self) -> Option<SmallMap<&'v str, V>> {
+        StructRef::from_value(self.value())?
+            .iter()
+            .map(|(k, v)| Some((k.as_str(), V::unpack_value(v)?)))
+            .collect()
+    }
+
+    /// Deserialize the underlying value into a Rust type, see the
+    /// [`Deserializer`](serde::Deserializer) implementation of [`Value`].
+    pub fn deserialize<'v, T: Deserialize<'v>>(&'v self) -> Result<T, DeserializeError> {
+        T::deserialize(self.value())
+    }
+
     /// Check if `self` references `<T>`.
     pub fn downcast<T: StarlarkValue<'static>>(self) -> Result<OwnedFrozenValueTyped<T>, Self> {
         match FrozenValueTyped::new(self.value) {
@@ -123,6 +183,11 @@ impl OwnedFrozenValue {
         Value::new_frozen(self.value)
     }
 
+    /// Get the documentation of the value stored inside, see [`FrozenValue::documentation`].
+    pub fn documentation(&self) -> Option<DocItem> {
+        self.value.documentation()
+    }
+
     /// Extract a [`Value`] by passing the [`FrozenHeap`] which will promise to keep it alive.
     /// When using with a [`Module`](crate::environment::Module),
     /// see the [`frozen_heap`](crate::environment::Module::frozen_heap) function.
@@ -266,3 +331,40 @@ impl<T: StarlarkValue<'static>> OwnedFrozenValueTyped<T> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::collections::SmallMap;
+    use crate::values::OwnedFrozenValue;
+
+    fn eval(code: &str) -> OwnedFrozenValue {
+        assert::pass_module(&format!("x = {}", code))
+            .get("x")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_downcast_collections() {
+        let list = eval("['a', 'b']");
+        assert_eq!(list.downcast_list::<&str>(), Some(vec!["a", "b"]));
+        assert_eq!(list.downcast_list::<i32>(), None);
+        assert_eq!(eval("('a',)").downcast_list::<&str>(), None);
+
+        let dict = eval("{'a': 1, 'b': 2}");
+        let expected: SmallMap<String, i32> = [("a".to_owned(), 1), ("b".to_owned(), 2)]
+            .into_iter()
+            .collect();
+        assert_eq!(dict.downcast_dict::<String, i32>(), Some(expected));
+        assert_eq!(dict.downcast_dict::<String, String>(), None);
+        assert_eq!(list.downcast_dict::<String, i32>(), None);
+
+        let strukt = eval("struct(a = 1, b = 2)");
+        let expected: SmallMap<&str, i32> = [("a", 1), ("b", 2)].into_iter().collect();
+        assert_eq!(strukt.downcast_struct::<i32>(), Some(expected));
+        assert_eq!(strukt.downcast_struct::<bool>(), None);
+        assert_eq!(dict.downcast_struct::<i32>(), None);
+
+        assert_eq!(eval("[1, 2]").unpack::<Vec<i32>>(), Some(vec![1, 2]));
+    }
+}
@@ -0,0 +1,540 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Allocating Rust types onto a [`Heap`] with [`serde`].
+
+use std::fmt;
+use std::fmt::Display;
+
+use num_bigint::BigInt;
+use serde::ser;
+use serde::ser::Serialize;
+use serde::ser::SerializeMap;
+use serde::ser::SerializeSeq;
+use serde::ser::SerializeStruct;
+use serde::ser::SerializeStructVariant;
+use serde::ser::SerializeTuple;
+use serde::ser::SerializeTupleStruct;
+use serde::ser::SerializeTupleVariant;
+use serde::ser::Serializer;
+
+use crate::collections::SmallMap;
+use crate::values::bytes::StarlarkBytes;
+use crate::values::dict::Dict;
+use crate::values::list::AllocList;
+use crate::values::tuple::AllocTuple;
+use crate::values::types::bigint::StarlarkBigInt;
+use crate::values::Heap;
+use crate::values::Value;
+
+/// An error from [`Heap::alloc_serde`].
+#[derive(Debug)]
+pub struct SerializeError(String);
+
+impl Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerializeError(msg.to_string())
+    }
+}
+
+impl Heap {
+    /// Allocate any type implementing [`Serialize`] on the heap, as the inverse of the
+    /// [`Deserializer`](serde::Deserializer) implementation of [`Value`].
+    ///
+    /// Sequences become lists, while tuples, tuple structs and fixed-size arrays become tuples.
+    /// Maps and structs become dicts, `None` and `()` become `None`, unit enum variants
+    /// become their name and other enum variants become a single entry dict mapping
+    /// their name to their contents.
+    /// Fails if a map key is not hashable in Starlark, e.g. a list.
+    ///
+    /// ```
+    /// use starlark::values::Heap;
+    ///
+    /// let heap = Heap::new();
+    /// let value = heap.alloc_serde(&("x", vec![1, 2], Some(true))).unwrap();
+    /// assert_eq!(value.to_repr(), r#"("x", [1, 2], True)"#);
+    /// ```
+    pub fn alloc_serde<'v, T: Serialize + ?Sized>(
+        &'v self,
+        x: &T,
+    ) -> Result<Value<'v>, SerializeError> {
+        x.serialize(HeapSerializer { heap: self })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct HeapSerializer<'v> {
+    heap: &'v Heap,
+}
+
+impl<'v> HeapSerializer<'v> {
+    /// A single entry dict `{variant: value}`.
+    fn variant(self, variant: &'static str, value: Value<'v>) -> Result<Value<'v>, SerializeError> {
+        let mut content = SmallMap::with_capacity(1);
+        insert(&mut content, self.heap.alloc_str(variant).to_value(), value)?;
+        Ok(self.heap.alloc(Dict::new(content)))
+    }
+}
+
+fn insert<'v>(
+    content: &mut SmallMap<Value<'v>, Value<'v>>,
+    key: Value<'v>,
+    value: Value<'v>,
+) -> Result<(), SerializeError> {
+    let key = key.get_hashed().map_err(ser::Error::custom)?;
+    content.insert_hashed(key, value);
+    Ok(())
+}
+
+impl<'v> Serializer for HeapSerializer<'v> {
+    type Ok = Value<'v>;
+    type Error = SerializeError;
+    type SerializeSeq = ListSerializer<'v>;
+    type SerializeTuple = ListSerializer<'v>;
+    type SerializeTupleStruct = ListSerializer<'v>;
+    type SerializeTupleVariant = ListSerializer<'v>;
+    type SerializeMap = DictSerializer<'v>;
+    type SerializeStruct = DictSerializer<'v>;
+    type SerializeStructVariant = DictSerializer<'v>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_int(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_int(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value<'v>, SerializeError> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value<'v>, SerializeError> {
+        Ok(StarlarkBigInt::alloc_bigint(BigInt::from(v), self.heap))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_int(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_int(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value<'v>, SerializeError> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value<'v>, SerializeError> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value<'v>, SerializeError> {
+        Ok(StarlarkBigInt::alloc_bigint(BigInt::from(v), self.heap))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value<'v>, SerializeError> {
+        Ok(self.heap.alloc(f64::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value<'v>, SerializeError> {
+        Ok(self.heap.alloc(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value<'v>, SerializeError> {
+        Ok(self.heap.alloc(v.encode_utf8(&mut [0; 4]) as &str))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value<'v>, SerializeError> {
+        Ok(self.heap.alloc_str(v).to_value())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value<'v>, SerializeError> {
+        Ok(self.heap.alloc(StarlarkBytes::new(v)))
+    }
+
+    fn serialize_none(self) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_none())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value<'v>, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_none())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value<'v>, SerializeError> {
+        Ok(Value::new_none())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value<'v>, SerializeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value<'v>, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value<'v>, SerializeError> {
+        let value = value.serialize(self)?;
+        self.variant(variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer<'v>, SerializeError> {
+        Ok(ListSerializer::new(self, len, false, None))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer<'v>, SerializeError> {
+        Ok(ListSerializer::new(self, Some(len), true, None))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer<'v>, SerializeError> {
+        Ok(ListSerializer::new(self, Some(len), true, None))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer<'v>, SerializeError> {
+        Ok(ListSerializer::new(self, Some(len), true, Some(variant)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<DictSerializer<'v>, SerializeError> {
+        Ok(DictSerializer::new(self, len, None))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<DictSerializer<'v>, SerializeError> {
+        Ok(DictSerializer::new(self, Some(len), None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<DictSerializer<'v>, SerializeError> {
+        Ok(DictSerializer::new(self, Some(len), Some(variant)))
+    }
+}
+
+/// Builds a list or a tuple, optionally wrapped as an enum variant.
+struct ListSerializer<'v> {
+    serializer: HeapSerializer<'v>,
+    values: Vec<Value<'v>>,
+    tuple: bool,
+    variant: Option<&'static str>,
+}
+
+impl<'v> ListSerializer<'v> {
+    fn new(
+        serializer: HeapSerializer<'v>,
+        len: Option<usize>,
+        tuple: bool,
+        variant: Option<&'static str>,
+    ) -> Self {
+        ListSerializer {
+            serializer,
+            values: Vec::with_capacity(len.unwrap_or_default()),
+            tuple,
+            variant,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.values.push(value.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value<'v>, SerializeError> {
+        let heap = self.serializer.heap;
+        let value = if self.tuple {
+            heap.alloc(AllocTuple(self.values))
+        } else {
+            heap.alloc(AllocList(self.values))
+        };
+        match self.variant {
+            None => Ok(value),
+            Some(variant) => self.serializer.variant(variant, value),
+        }
+    }
+}
+
+impl<'v> SerializeSeq for ListSerializer<'v> {
+    type Ok = Value<'v>;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value<'v>, SerializeError> {
+        self.finish()
+    }
+}
+
+impl<'v> SerializeTuple for ListSerializer<'v> {
+    type Ok = Value<'v>;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value<'v>, SerializeError> {
+        self.finish()
+    }
+}
+
+impl<'v> SerializeTupleStruct for ListSerializer<'v> {
+    type Ok = Value<'v>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value<'v>, SerializeError> {
+        self.finish()
+    }
+}
+
+impl<'v> SerializeTupleVariant for ListSerializer<'v> {
+    type Ok = Value<'v>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value<'v>, SerializeError> {
+        self.finish()
+    }
+}
+
+/// Builds a dict, optionally wrapped as an enum variant.
+struct DictSerializer<'v> {
+    serializer: HeapSerializer<'v>,
+    content: SmallMap<Value<'v>, Value<'v>>,
+    key: Option<Value<'v>>,
+    variant: Option<&'static str>,
+}
+
+impl<'v> DictSerializer<'v> {
+    fn new(
+        serializer: HeapSerializer<'v>,
+        len: Option<usize>,
+        variant: Option<&'static str>,
+    ) -> Self {
+        DictSerializer {
+            serializer,
+            content: SmallMap::with_capacity(len.unwrap_or_default()),
+            key: None,
+            variant,
+        }
+    }
+
+    fn field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        let key = self.serializer.heap.alloc_str(key).to_value();
+        let value = value.serialize(self.serializer)?;
+        insert(&mut self.content, key, value)
+    }
+
+    fn finish(self) -> Result<Value<'v>, SerializeError> {
+        let value = self.serializer.heap.alloc(Dict::new(self.content));
+        match self.variant {
+            None => Ok(value),
+            Some(variant) => self.serializer.variant(variant, value),
+        }
+    }
+}
+
+impl<'v> SerializeMap for DictSerializer<'v> {
+    type Ok = Value<'v>;
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.key = Some(key.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        let value = value.serialize(self.serializer)?;
+        insert(&mut self.content, key, value)
+    }
+
+    fn end(self) -> Result<Value<'v>, SerializeError> {
+        self.finish()
+    }
+}
+
+impl<'v> SerializeStruct for DictSerializer<'v> {
+    type Ok = Value<'v>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value<'v>, SerializeError> {
+        self.finish()
+    }
+}
+
+impl<'v> SerializeStructVariant for DictSerializer<'v> {
+    type Ok = Value<'v>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value<'v>, SerializeError> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+    use serde::Serialize;
+
+    use crate::values::Heap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+        Retry(u32),
+        Range { start: i32, end: i32 },
+    }
+
+    #[derive(Serialize)]
+    struct Config {
+        name: String,
+        port: u16,
+        big: u128,
+        ratio: f64,
+        tags: Vec<String>,
+        pair: (char, Option<i64>),
+        limits: BTreeMap<String, i64>,
+        modes: Vec<Mode>,
+    }
+
+    #[test]
+    fn test_alloc_serde() {
+        let heap = Heap::new();
+        let config = Config {
+            name: "web".to_owned(),
+            port: 80,
+            big: 1 << 100,
+            ratio: 0.5,
+            tags: vec!["a".to_owned()],
+            pair: ('x', None),
+            limits: [("cpu".to_owned(), 2)].into_iter().collect(),
+            modes: vec![
+                Mode::Fast,
+                Mode::Retry(3),
+                Mode::Range { start: -1, end: 1 },
+            ],
+        };
+        let value = heap.alloc_serde(&config).unwrap();
+        assert_eq!(
+            value.to_repr(),
+            r#"{"name": "web", "port": 80, "big": 1267650600228229401496703205376, "ratio": 0.5, "tags": ["a"], "pair": ("x", None), "limits": {"cpu": 2}, "modes": ["fast", {"retry": 3}, {"range": {"start": -1, "end": 1}}]}"#
+        );
+        // Round trips through the `Deserializer` for `Value`.
+        let modes = heap.alloc_serde(&config.modes).unwrap();
+        assert_eq!(Vec::<Mode>::deserialize(modes).unwrap(), config.modes);
+    }
+
+    #[test]
+    fn test_alloc_serde_unhashable_key() {
+        let heap = Heap::new();
+        let map: BTreeMap<Vec<i32>, i32> = [(vec![1], 1)].into_iter().collect();
+        let err = heap.alloc_serde(&map).unwrap_err();
+        assert!(err.to_string().contains("hashable"), "{}", err);
+    }
+}
@@ -181,7 +181,16 @@ impl Serialize for StarlarkBigInt {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.value.to_string())
+        if let Some(x) = self.value.to_i64() {
+            serializer.serialize_i64(x)
+        } else if let Some(x) = self.value.to_i128() {
+            serializer.serialize_i128(x)
+        } else if let Some(x) = self.value.to_u128() {
+            serializer.serialize_u128(x)
+        } else {
+            // Too big for any number type serde knows about
+            serializer.serialize_str(&self.value.to_string())
+        }
     }
 }
 
@@ -768,6 +777,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_int_function_big() {
+        assert::eq("1 << 100", "int(str(1 << 100))");
+        assert::eq("-(1 << 100)", "int(str(-(1 << 100)))");
+        assert::eq("1 << 80", "int(float(1 << 80))");
+    }
+
+    #[test]
+    fn test_percent_format() {
+        assert::eq("'1267650600228229401496703205376'", "'%d' % (1 << 100)");
+        assert::eq("'-10000000000000000000000000'", "'%x' % -(1 << 100)");
+        assert::eq("'2000000000000000000000000000000000'", "'%o' % (1 << 100)");
+        assert::eq("'100000000000000000000'", "'%d' % 1e20");
+        assert::eq("'  2147483648'", "'%12d' % 2147483648");
+    }
+
+    #[test]
+    fn test_dict_key() {
+        assert::is_true("{1 << 100: 'a'}[int('1267650600228229401496703205376')] == 'a'");
+        assert::is_true("{2147483648: 'a'}[2147483647 + 1] == 'a'");
+    }
+
     #[test]
     fn test_hash() {
         let mut hash1 = StarlarkHasher::new();
@@ -0,0 +1,323 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `bytes` type, an immutable sequence of bytes.
+//!
+//! Created with the `bytes()` function, or with literals like `b"abc"` when
+//! [`Dialect::enable_bytes_literals`](crate::syntax::Dialect::enable_bytes_literals) is on.
+//! Indexing gives an `int`, slicing gives `bytes`, and `str()` decodes them as UTF-8.
+
+use std::cmp;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use allocative::Allocative;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::values::index::apply_slice;
+use crate::values::index::convert_index;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::AllocFrozenValue;
+use crate::values::AllocValue;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::ValueLike;
+
+#[derive(Debug, Error)]
+enum BytesError {
+    #[error("Byte value `{0}` out of range, must be between 0 and 255")]
+    OutOfRange(i32),
+}
+
+/// An immutable sequence of bytes, the `bytes` type.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    ProvidesStaticType,
+    StarlarkDocs,
+    Allocative
+)]
+#[starlark_docs(builtin = "extension")]
+pub struct StarlarkBytes(Box<[u8]>);
+
+// Not a simple value: the content is allocated outside the heap, but counted
+// towards its size, and so its limit.
+impl<'v> AllocValue<'v> for StarlarkBytes {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_bytes(self)
+    }
+}
+
+impl AllocFrozenValue for StarlarkBytes {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc_bytes(self)
+    }
+}
+
+impl<'v> StarlarkTypeRepr for &'v StarlarkBytes {
+    fn starlark_type_repr() -> String {
+        StarlarkBytes::get_type_starlark_repr()
+    }
+}
+
+impl<'v> UnpackValue<'v> for &'v StarlarkBytes {
+    fn expected() -> String {
+        StarlarkBytes::TYPE.to_owned()
+    }
+
+    fn unpack_value(x: Value<'v>) -> Option<&'v StarlarkBytes> {
+        StarlarkBytes::from_value(x)
+    }
+}
+
+impl StarlarkBytes {
+    /// Create a `bytes` value with the given content.
+    pub fn new(x: impl Into<Box<[u8]>>) -> Self {
+        Self(x.into())
+    }
+
+    /// Downcast a value to `bytes`.
+    pub fn from_value(x: Value) -> Option<&StarlarkBytes> {
+        x.downcast_ref::<Self>()
+    }
+
+    /// The content of the value.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The UTF-8 encoding of a string, a copy of other `bytes`, or the bytes of an
+    /// iterable of ints between 0 and 255.
+    pub(crate) fn from_starlark<'v>(x: Value<'v>, heap: &'v Heap) -> anyhow::Result<Self> {
+        if let Some(x) = x.unpack_str() {
+            return Ok(Self::new(x.as_bytes()));
+        }
+        if let Some(x) = Self::from_value(x) {
+            return Ok(x.clone());
+        }
+        let mut res = Vec::new();
+        for x in x.iterate(heap)? {
+            let x = i32::unpack_param(x)?;
+            match u8::try_from(x) {
+                Ok(x) => res.push(x),
+                Err(_) => return Err(BytesError::OutOfRange(x).into()),
+            }
+        }
+        Ok(Self::new(res))
+    }
+}
+
+impl Display for StarlarkBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("b\"")?;
+        for x in self.0.iter() {
+            match x {
+                b'\n' => f.write_str("\\n")?,
+                b'\r' => f.write_str("\\r")?,
+                b'\t' => f.write_str("\\t")?,
+                b'\\' => f.write_str("\\\\")?,
+                b'"' => f.write_str("\\\"")?,
+                0x20..=0x7e => write!(f, "{}", *x as char)?,
+                x => write!(f, "\\x{:02x}", x)?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+impl<'v> StarlarkValue<'v> for StarlarkBytes {
+    starlark_type!("bytes");
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(bytes_methods)
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        self.0.hash(hasher);
+        Ok(())
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match Self::from_value(other) {
+            Some(other) => Ok(self == other),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, other: Value<'v>) -> anyhow::Result<Ordering> {
+        match Self::from_value(other) {
+            Some(other) => Ok(self.0.cmp(&other.0)),
+            None => ValueError::unsupported_with(self, "cmp()", other),
+        }
+    }
+
+    fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let i = convert_index(index, self.length()?)?;
+        Ok(Value::new_int(self.0[i as usize] as i32))
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value>,
+        stop: Option<Value>,
+        stride: Option<Value>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let res = apply_slice(&self.0, start, stop, stride)?;
+        Ok(heap.alloc(StarlarkBytes::new(res)))
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(i32::try_from(self.0.len()).map_err(|_| ValueError::IntegerOverflow)?)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        if let Some(needle) = Self::from_value(other) {
+            Ok(needle.0.is_empty() || self.0.windows(needle.0.len()).any(|x| x == &*needle.0))
+        } else if let Some(x) = other.unpack_int() {
+            match u8::try_from(x) {
+                Ok(x) => Ok(self.0.contains(&x)),
+                Err(_) => Err(BytesError::OutOfRange(x).into()),
+            }
+        } else {
+            ValueError::unsupported_owned(other.get_type(), "in", Some(Self::TYPE))
+        }
+    }
+
+    fn add(&self, rhs: Value<'v>, heap: &'v Heap) -> Option<anyhow::Result<Value<'v>>> {
+        let rhs = Self::from_value(rhs)?;
+        Some(Ok(
+            heap.alloc(StarlarkBytes::new([&*self.0, &*rhs.0].concat()))
+        ))
+    }
+
+    fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let l = i32::unpack_param(other)?;
+        let n = cmp::max(0, l) as usize;
+        // Starlark values must be smaller than 4 GiB.
+        let len = match self.0.len().checked_mul(n) {
+            Some(len) if len < u32::MAX as usize => len,
+            _ => return Err(ValueError::IntegerOverflow.into()),
+        };
+        // Check the limit before building the content, which counts towards it.
+        heap.check_allocation(len)?;
+        Ok(heap.alloc(StarlarkBytes::new(self.0.repeat(n))))
+    }
+}
+
+impl Serialize for StarlarkBytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[starlark_module]
+fn bytes_methods(builder: &mut MethodsBuilder) {
+    /// The bytes as a list of ints between 0 and 255.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// bytes("ab").elems() == [97, 98]
+    /// # "#);
+    /// ```
+    fn elems(this: &StarlarkBytes) -> anyhow::Result<Vec<i32>> {
+        Ok(this.0.iter().map(|x| *x as i32).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_bytes() {
+        assert::all_true(
+            r#"
+bytes("héllo") == bytes([104, 195, 169, 108, 108, 111])
+len(bytes("héllo")) == 6
+bytes("abc")[1] == 98
+bytes("abc")[-1] == 99
+bytes("abcd")[1:3] == bytes("bc")
+bytes("abcd")[::-1] == bytes("dcba")
+bytes("ab") + bytes("c") == bytes("abc")
+bytes("ab") * 2 == bytes("abab")
+bytes("b") in bytes("abc") and 97 in bytes("abc") and bytes("ac") not in bytes("abc")
+bytes("a") < bytes("b") and bytes("a") < bytes("ab")
+bytes("") == bytes([]) and not bytes("") and bool(bytes("a"))
+type(bytes("")) == "bytes"
+{bytes("a"): 1}[bytes("a")] == 1
+"#,
+        );
+        assert::eq(
+            "repr(bytes('a\"\\n\\x00\\xff'))",
+            r#"'b"a\\"\\n\\x00\\xc3\\xbf"'"#,
+        );
+        assert::eq("str(bytes([104, 105, 255]))", "'hi\\ufffd'");
+        assert::fail("bytes([256])", "out of range");
+        assert::fail("bytes('a')[1]", "out of bound");
+        assert::fail("bytes('a') + 'b'", "not supported");
+        assert::fail("bytes('abcd') * (1 << 30)", "Integer overflow");
+    }
+
+    #[test]
+    fn test_literals() {
+        let mut a = Assert::new();
+        a.dialect_set(|x| x.enable_bytes_literals = true);
+        a.all_true(
+            r#"
+b"abc" == bytes("abc")
+b'\xff\x00'.elems() == [255, 0]
+b"\377\101" == bytes([255, 65])
+b"é" == bytes("é")
+rb"\x" == bytes("\\x")
+"#,
+        );
+        a.is_true("b\"\"\"a\nb\"\"\" == bytes('a\\nb')");
+        a.fail("b'\\400'", "escape sequence");
+        assert::fail("b'abc'", "bytes literals are not allowed");
+    }
+
+    #[test]
+    fn test_json() {
+        assert::eq("json.encode(bytes('ab'))", "'[97,98]'");
+    }
+}
@@ -26,6 +26,7 @@ use crate::coerce::coerce;
 use crate::values::dict::value::DictGen;
 use crate::values::dict::value::FrozenDictData;
 use crate::values::dict::Dict;
+use crate::values::error::FrozenMutationError;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::FrozenValue;
 use crate::values::UnpackValue;
@@ -77,7 +78,7 @@ impl<'v> DictMut<'v> {
         #[inline(never)]
         fn error<'v>(x: Value<'v>) -> anyhow::Error {
             if x.downcast_ref::<DictGen<FrozenDictData>>().is_some() {
-                ValueError::CannotMutateImmutableValue.into()
+                FrozenMutationError::new(x.get_type(), x.ptr_value().ptr_value())
             } else {
                 NotDictError(x.get_type()).into()
             }
@@ -19,6 +19,8 @@ use std::any::TypeId;
 use std::cell::Ref;
 use std::cell::RefCell;
 use std::cell::RefMut;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -41,12 +43,15 @@ use crate::environment::Methods;
 use crate::environment::MethodsStatic;
 use crate::hint::unlikely;
 use crate::values::comparison::equals_small_map;
+use crate::values::dict::AllocDict;
 use crate::values::dict::DictOf;
 use crate::values::dict::DictRef;
+use crate::values::error::FrozenMutationError;
 use crate::values::error::ValueError;
 use crate::values::iter::ARefIterator;
 use crate::values::layout::avalue::VALUE_EMPTY_FROZEN_DICT;
 use crate::values::string::hash_string_value;
+use crate::values::type_repr::DictType;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
@@ -325,7 +330,10 @@ impl<'v> DictLike<'v> for FrozenDictData {
     }
 
     fn set_at(&self, _index: Hashed<Value<'v>>, _value: Value<'v>) -> anyhow::Result<()> {
-        Err(ValueError::CannotMutateImmutableValue.into())
+        Err(FrozenMutationError::new(
+            Dict::TYPE,
+            self as *const Self as usize,
+        ))
     }
 }
 
@@ -465,6 +473,73 @@ impl<'v, K: UnpackValue<'v> + Hash + Eq, V: UnpackValue<'v>> UnpackValue<'v> for
     }
 }
 
+impl<K: StarlarkTypeRepr, V: StarlarkTypeRepr> StarlarkTypeRepr for HashMap<K, V> {
+    fn starlark_type_repr() -> String {
+        DictType::<K, V>::starlark_type_repr()
+    }
+}
+
+/// Unpacked in the order of the dict, so if several keys unpack to the same `K`, the last one wins.
+impl<'v, K: UnpackValue<'v> + Hash + Eq, V: UnpackValue<'v>> UnpackValue<'v> for HashMap<K, V> {
+    fn expected() -> String {
+        format!("dict mapping {} to {}", K::expected(), V::expected())
+    }
+
+    fn unpack_value(value: Value<'v>) -> Option<Self> {
+        let dict = DictRef::from_value(value)?;
+        dict.iter()
+            .map(|(k, v)| Some((K::unpack_value(k)?, V::unpack_value(v)?)))
+            .collect()
+    }
+}
+
+/// Allocated as a dict in the iteration order of the `HashMap`, which is arbitrary.
+/// Panics if a key is not hashable.
+impl<'v, K: AllocValue<'v>, V: AllocValue<'v>> AllocValue<'v> for HashMap<K, V> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc(AllocDict(self))
+    }
+}
+
+impl<K: AllocFrozenValue, V: AllocFrozenValue> AllocFrozenValue for HashMap<K, V> {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc(AllocDict(self))
+    }
+}
+
+impl<K: StarlarkTypeRepr, V: StarlarkTypeRepr> StarlarkTypeRepr for BTreeMap<K, V> {
+    fn starlark_type_repr() -> String {
+        DictType::<K, V>::starlark_type_repr()
+    }
+}
+
+/// Unpacked in the order of the dict, so if several keys unpack to the same `K`, the last one wins.
+impl<'v, K: UnpackValue<'v> + Ord, V: UnpackValue<'v>> UnpackValue<'v> for BTreeMap<K, V> {
+    fn expected() -> String {
+        format!("dict mapping {} to {}", K::expected(), V::expected())
+    }
+
+    fn unpack_value(value: Value<'v>) -> Option<Self> {
+        let dict = DictRef::from_value(value)?;
+        dict.iter()
+            .map(|(k, v)| Some((K::unpack_value(k)?, V::unpack_value(v)?)))
+            .collect()
+    }
+}
+
+/// Allocated as a dict ordered by key. Panics if a key is not hashable.
+impl<'v, K: AllocValue<'v>, V: AllocValue<'v>> AllocValue<'v> for BTreeMap<K, V> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc(AllocDict(self))
+    }
+}
+
+impl<K: AllocFrozenValue, V: AllocFrozenValue> AllocFrozenValue for BTreeMap<K, V> {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        heap.alloc(AllocDict(self))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,6 +547,26 @@ mod tests {
     use crate::collections::SmallMap;
     use crate::values::Heap;
 
+    #[test]
+    fn test_std_maps() {
+        let heap = Heap::new();
+        let x = heap.alloc(BTreeMap::from([("b", 2), ("a", 1)]));
+        assert_eq!(x.to_repr(), r#"{"a": 1, "b": 2}"#);
+        assert_eq!(
+            BTreeMap::<String, i32>::unpack_value(x),
+            Some(BTreeMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)]))
+        );
+        assert_eq!(
+            HashMap::<&str, i32>::unpack_value(heap.alloc(HashMap::from([("a", 1)]))),
+            Some(HashMap::from([("a", 1)]))
+        );
+        assert_eq!(HashMap::<&str, bool>::unpack_value(x), None);
+        assert_eq!(
+            HashMap::<String, i32>::starlark_type_repr(),
+            SmallMap::<String, i32>::starlark_type_repr()
+        );
+    }
+
     #[test]
     fn test_mutate_dict() {
         assert::is_true(
@@ -487,6 +582,18 @@ b1 and b2 and b3
         );
     }
 
+    #[test]
+    fn test_mutate_frozen_dict() {
+        let mut a = assert::Assert::new();
+        a.module("x", "frozen = {1: 2}");
+        a.fail(
+            "load('x','frozen')\nfrozen[1] = 3",
+            "Immutable `dict` from module `x.bzl`, frozen when that module finished evaluating; \
+            copy it with `dict(x)` to get a value that can be mutated",
+        );
+        a.fail("load('x','frozen')\nfrozen.clear()", "Immutable `dict`");
+    }
+
     #[test]
     fn test_get_str() -> anyhow::Result<()> {
         let heap = Heap::new();
@@ -28,12 +28,14 @@ use crate::any::ProvidesStaticType;
 use crate::coerce::Coerce;
 use crate::docs;
 use crate::docs::DocItem;
+use crate::docs::DocString;
 use crate::docs::DocStringKind;
 use crate::eval::Arguments;
 use crate::eval::Evaluator;
 use crate::eval::ParametersParser;
 use crate::eval::ParametersSpec;
 use crate::private::Private;
+use crate::values::types::type_expr::type_union;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
 use crate::values::Freeze;
@@ -44,6 +46,7 @@ use crate::values::Heap;
 use crate::values::StarlarkValue;
 use crate::values::Trace;
 use crate::values::Value;
+use crate::values::ValueError;
 use crate::values::ValueLike;
 
 /// Return value of `type(any function)`.
@@ -129,6 +132,7 @@ pub struct NativeCallableRawDocs {
     pub rust_docstring: Option<&'static str>,
     pub signature: ParametersSpec<FrozenValue>,
     pub parameter_types: HashMap<usize, docs::Type>,
+    pub parameter_docs: HashMap<String, Option<DocString>>,
     pub return_type: Option<docs::Type>,
 }
 
@@ -138,7 +142,7 @@ impl NativeCallableRawDocs {
         docs::Function::from_docstring(
             DocStringKind::Rust,
             self.signature
-                .documentation(self.parameter_types.clone(), HashMap::new()),
+                .documentation(self.parameter_types.clone(), self.parameter_docs.clone()),
             self.return_type.clone(),
             self.rust_docstring,
         )
@@ -230,7 +234,8 @@ impl<'v> StarlarkValue<'v> for NativeFunction {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        self.function.invoke(eval, args)
+        eval.check_builtin_policy(&self.name)?;
+        eval.invoke_native(&self.name, args, |eval| self.function.invoke(eval, args))
     }
 
     fn get_attr(&self, attribute: &str, _heap: &'v Heap) -> Option<Value<'v>> {
@@ -242,6 +247,23 @@ impl<'v> StarlarkValue<'v> for NativeFunction {
         None
     }
 
+    fn at(&self, index: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match self.type_index(index, heap) {
+            Some(x) => x,
+            None => ValueError::unsupported_with(self, "[]", index),
+        }
+    }
+
+    fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match self.typ {
+            Some(typ) => match type_union([typ.to_value()], other, heap) {
+                Some(x) => Ok(x),
+                None => ValueError::unsupported_with(self, "|", other),
+            },
+            None => ValueError::unsupported_with(self, "|", other),
+        }
+    }
+
     fn has_attr(&self, _attribute: &str, _heap: &'v Heap) -> bool {
         // TODO(nga): implement properly.
         false
@@ -289,6 +311,7 @@ impl<'v> StarlarkValue<'v> for NativeMethod {
         eval: &mut Evaluator<'v, '_>,
         _: Private,
     ) -> anyhow::Result<Value<'v>> {
+        eval.check_builtin_policy_method(this, &self.name)?;
         self.function.invoke(eval, this, args)
     }
 
@@ -0,0 +1,449 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A dictionary whose keys are compared by identity rather than by equality.
+//!
+//! Any value can be used as a key, including unhashable ones like lists,
+//! which lets rule frameworks associate metadata with arbitrary values.
+//! Two keys are the same only if they are the same object, so two equal
+//! strings allocated separately are distinct keys.
+
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::mem;
+
+use allocative::Allocative;
+use gazebo::cell::ARef;
+use gazebo::display::display_keyed_container;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::coerce;
+use crate::coerce::Coerce;
+use crate::collections::SmallMap;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::values::error::ValueError;
+use crate::values::iter::ARefIterator;
+use crate::values::none::NoneOr;
+use crate::values::AllocValue;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Tracer;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// Entries of an identity dictionary.
+#[derive(Clone, Default, Debug, ProvidesStaticType, Allocative)]
+#[repr(C)]
+pub(crate) struct IdentityMap<V> {
+    /// Entries in insertion order.
+    entries: Vec<(V, V)>,
+    /// The position in `entries` of each key, by the pointer of the key value. Pointers
+    /// change when values are moved by garbage collection or freezing, so the index is
+    /// rebuilt after both. Freezing can map distinct keys to the same shared value, such
+    /// as the empty list, in which case both entries are kept and lookups find the first.
+    index: SmallMap<usize, usize>,
+}
+
+unsafe impl<'v> Coerce<IdentityMap<Value<'v>>> for IdentityMap<FrozenValue> {}
+
+fn identity_key(x: Value) -> usize {
+    x.ptr_value().ptr_value()
+}
+
+impl<V> IdentityMap<V> {
+    fn new(entries: Vec<(V, V)>, key: impl Fn(&V) -> usize) -> Self {
+        let mut index = SmallMap::with_capacity(entries.len());
+        for (i, (k, _)) in entries.iter().enumerate() {
+            index.entry(key(k)).or_insert(i);
+        }
+        IdentityMap { entries, index }
+    }
+}
+
+impl<'v> IdentityMap<Value<'v>> {
+    fn get(&self, key: Value<'v>) -> Option<Value<'v>> {
+        self.index
+            .get(&identity_key(key))
+            .map(|i| self.entries[*i].1)
+    }
+
+    fn contains_key(&self, key: Value<'v>) -> bool {
+        self.index.contains_key(&identity_key(key))
+    }
+
+    fn insert(&mut self, key: Value<'v>, value: Value<'v>) {
+        match self.index.get(&identity_key(key)) {
+            Some(i) => self.entries[*i].1 = value,
+            None => {
+                self.index.insert(identity_key(key), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    fn remove(&mut self, key: Value<'v>) -> Option<Value<'v>> {
+        let removed = self.index.remove(&identity_key(key))?;
+        for i in self.index.values_mut() {
+            if *i > removed {
+                *i -= 1;
+            }
+        }
+        Some(self.entries.remove(removed).1)
+    }
+
+    fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = (Value<'v>, Value<'v>)> + 'a {
+        self.entries.iter().copied()
+    }
+
+    fn keys<'a>(&'a self) -> impl ExactSizeIterator<Item = Value<'v>> + 'a {
+        self.entries.iter().map(|(k, _)| *k)
+    }
+}
+
+unsafe impl<'v> Trace<'v> for IdentityMap<Value<'v>> {
+    fn trace(&mut self, tracer: &Tracer<'v>) {
+        self.entries.trace(tracer);
+        *self = IdentityMap::new(mem::take(&mut self.entries), |k| identity_key(*k));
+    }
+}
+
+/// An identity dictionary, `IdentityDictGen<RefCell<IdentityMap<Value>>>` when mutable
+/// and `IdentityDictGen<IdentityMap<FrozenValue>>` when frozen.
+#[derive(
+    Clone,
+    Default,
+    Trace,
+    Debug,
+    ProvidesStaticType,
+    NoSerialize,
+    Allocative
+)]
+pub(crate) struct IdentityDictGen<T>(pub(crate) T);
+
+/// Type of a mutable identity dictionary.
+pub(crate) type IdentityDict<'v> = IdentityDictGen<RefCell<IdentityMap<Value<'v>>>>;
+
+type FrozenIdentityDict = IdentityDictGen<IdentityMap<FrozenValue>>;
+
+impl<'v> IdentityDict<'v> {
+    /// The result of calling `type()` on identity dictionaries.
+    pub(crate) const TYPE: &'static str = "idict";
+
+    pub(crate) fn new() -> Self {
+        IdentityDictGen(RefCell::new(IdentityMap::default()))
+    }
+}
+
+impl<'v> AllocValue<'v> for IdentityDict<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex(self)
+    }
+}
+
+impl<'v> Freeze for IdentityDict<'v> {
+    type Frozen = FrozenIdentityDict;
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let entries = self
+            .0
+            .into_inner()
+            .entries
+            .into_iter()
+            .map(|(k, v)| Ok((k.freeze(freezer)?, v.freeze(freezer)?)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(IdentityDictGen(IdentityMap::new(entries, |k| {
+            identity_key(k.to_value())
+        })))
+    }
+}
+
+trait IdentityDictLike<'v>: Debug + Allocative {
+    fn content(&self) -> ARef<IdentityMap<Value<'v>>>;
+    fn content_mut(&self) -> anyhow::Result<std::cell::RefMut<IdentityMap<Value<'v>>>>;
+}
+
+impl<'v> IdentityDictLike<'v> for RefCell<IdentityMap<Value<'v>>> {
+    fn content(&self) -> ARef<IdentityMap<Value<'v>>> {
+        ARef::new_ref(Ref::map(self.borrow(), |x| x))
+    }
+
+    fn content_mut(&self) -> anyhow::Result<std::cell::RefMut<IdentityMap<Value<'v>>>> {
+        self.try_borrow_mut()
+            .map_err(|_| ValueError::MutationDuringIteration.into())
+    }
+}
+
+impl<'v> IdentityDictLike<'v> for IdentityMap<FrozenValue> {
+    fn content(&self) -> ARef<IdentityMap<Value<'v>>> {
+        ARef::new_ptr(coerce(self))
+    }
+
+    fn content_mut(&self) -> anyhow::Result<std::cell::RefMut<IdentityMap<Value<'v>>>> {
+        Err(ValueError::CannotMutateImmutableValue.into())
+    }
+}
+
+impl<'v, T: IdentityDictLike<'v>> Display for IdentityDictGen<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display_keyed_container(f, "idict({", "})", ": ", self.0.content().iter())
+    }
+}
+
+fn content<'v>(x: Value<'v>) -> anyhow::Result<ARef<'v, IdentityMap<Value<'v>>>> {
+    if let Some(x) = x.downcast_ref::<IdentityDict<'v>>() {
+        Ok(x.0.content())
+    } else if let Some(x) = x.downcast_ref::<FrozenIdentityDict>() {
+        Ok(x.0.content())
+    } else {
+        Err(ValueError::IncorrectParameterTypeNamed("this".to_owned()).into())
+    }
+}
+
+fn content_mut<'v>(x: Value<'v>) -> anyhow::Result<std::cell::RefMut<'v, IdentityMap<Value<'v>>>> {
+    match x.downcast_ref::<IdentityDict<'v>>() {
+        Some(x) => x.0.content_mut(),
+        None => Err(ValueError::CannotMutateImmutableValue.into()),
+    }
+}
+
+impl<'v, T: IdentityDictLike<'v> + 'v> StarlarkValue<'v> for IdentityDictGen<T>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!(IdentityDict::TYPE);
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(identity_dict_methods)
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        r.push_str("idict({");
+        for (i, (k, v)) in self.0.content().iter().enumerate() {
+            if i != 0 {
+                r.push_str(", ");
+            }
+            k.collect_repr(r);
+            r.push_str(": ");
+            v.collect_repr(r);
+        }
+        r.push_str("})");
+    }
+
+    fn collect_repr_cycle(&self, collector: &mut String) {
+        collector.push_str("idict({...})");
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.content().entries.is_empty()
+    }
+
+    fn at(&self, index: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match self.0.content().get(index) {
+            Some(v) => Ok(v),
+            None => Err(ValueError::KeyNotFound(index.to_repr()).into()),
+        }
+    }
+
+    fn set_at(&self, index: Value<'v>, alloc_value: Value<'v>) -> anyhow::Result<()> {
+        self.0.content_mut()?.insert(index, alloc_value);
+        Ok(())
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.content().entries.len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self.0.content().contains_key(other))
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        _heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        Ok(Box::new(ARefIterator::new(self.0.content(), |x| x.keys())))
+    }
+
+    fn with_iterator(
+        &self,
+        _heap: &'v Heap,
+        f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        f(&mut self.0.content().keys())
+    }
+}
+
+#[starlark_module]
+fn identity_dict_methods(builder: &mut MethodsBuilder) {
+    /// Return the value for `key` if present, otherwise `default`.
+    fn get<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] key: Value<'v>,
+        #[starlark(require = pos, default = NoneOr::None)] default: NoneOr<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        match content(this)?.get(key) {
+            Some(v) => Ok(v),
+            None => Ok(default.into_option().unwrap_or_else(Value::new_none)),
+        }
+    }
+
+    /// Remove `key` and return its value. If the key is missing, return `default`
+    /// if given, otherwise fail.
+    fn pop<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] key: Value<'v>,
+        #[starlark(require = pos)] default: Option<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        match (content_mut(this)?.remove(key), default) {
+            (Some(v), _) => Ok(v),
+            (None, Some(default)) => Ok(default),
+            (None, None) => Err(ValueError::KeyNotFound(key.to_repr()).into()),
+        }
+    }
+
+    /// The keys of the identity dictionary, in insertion order.
+    fn keys<'v>(this: Value<'v>) -> anyhow::Result<Vec<Value<'v>>> {
+        Ok(content(this)?.keys().collect())
+    }
+
+    /// The values of the identity dictionary, in insertion order.
+    fn values<'v>(this: Value<'v>) -> anyhow::Result<Vec<Value<'v>>> {
+        Ok(content(this)?.iter().map(|(_, v)| v).collect())
+    }
+
+    /// The `(key, value)` pairs of the identity dictionary, in insertion order.
+    fn items<'v>(this: Value<'v>) -> anyhow::Result<Vec<(Value<'v>, Value<'v>)>> {
+        Ok(content(this)?.iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_identity_keys() {
+        assert::is_true(
+            r#"
+a = [1]
+b = [1]
+d = idict()
+d[a] = "a"
+d[b] = "b"
+len(d) == 2 and d[a] == "a" and d[b] == "b" and a in d and [1] not in d
+"#,
+        );
+    }
+
+    #[test]
+    fn test_methods() {
+        assert::is_true(
+            r#"
+k = {}
+d = idict()
+d[k] = 1
+d[None] = 2
+(
+    d.get(k) == 1 and d.get([]) == None and d.get([], 3) == 3 and
+    d.keys() == [k, None] and d.values() == [1, 2] and d.items() == [(k, 1), (None, 2)] and
+    d.pop(k) == 1 and d.pop(k, 4) == 4 and len(d) == 1 and
+    [x for x in d] == [None] and repr(d) == "idict({None: 2})"
+)
+"#,
+        );
+        assert::fail("d = idict(); d.pop([])", "not found");
+    }
+
+    #[test]
+    fn test_frozen() {
+        let mut a = Assert::new();
+        a.module(
+            "m.star",
+            r#"
+key = struct(x = 1)
+other = struct(x = 1)
+d = idict()
+d[key] = "value"
+"#,
+        );
+        a.is_true(
+            r#"
+load("m.star", "d", "key", "other")
+d[key] == "value" and other not in d and d.get(key) == "value"
+"#,
+        );
+        a.fail(
+            r#"
+load("m.star", "d", "key")
+d[key] = 1
+"#,
+            "Immutable",
+        );
+    }
+
+    #[test]
+    fn test_frozen_shared_keys() {
+        // Both empty lists freeze to the same shared value, and must not collapse.
+        let mut a = Assert::new();
+        a.module(
+            "m.star",
+            r#"
+a = []
+b = []
+d = idict()
+d[a] = 1
+d[b] = 2
+"#,
+        );
+        a.is_true(
+            r#"
+load("m.star", "d", "a")
+len(d) == 2 and d.values() == [1, 2] and d[a] == 1 and repr(d) == "idict({[]: 1, []: 2})"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_gc() {
+        assert::is_true(
+            r#"
+def build():
+    d = idict()
+    keys = [[i] for i in range(100)]
+    for k in keys:
+        d[k] = k[0]
+    garbage_collect()
+    return all([d[k] == k[0] for k in keys])
+build()
+"#,
+        );
+    }
+}
@@ -55,6 +55,7 @@ impl KnownMethod {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
+        eval.check_builtin_policy_method(this, &self.method.name)?;
         self.imp.invoke(eval, this, args)
     }
 }
@@ -0,0 +1,157 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A type [`StarlarkLabel`] for label-like strings such as `//package/path:name`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hasher;
+
+use allocative::Allocative;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHasher;
+use crate::values::FrozenStringValue;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::ValueLike;
+
+/// A label-like string, such as `//package/path:name`, allocated with
+/// [`FrozenHeap::alloc_label`](crate::values::FrozenHeap::alloc_label).
+///
+/// Workspaces often refer to hundreds of thousands of labels, most of which share a
+/// handful of package prefixes. The prefix (`//package/path:`) and the name (`name`) are
+/// interned separately, so each distinct prefix is only stored once per heap.
+#[derive(Debug, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct StarlarkLabel {
+    prefix: FrozenStringValue,
+    name: FrozenStringValue,
+}
+
+starlark_simple_value!(StarlarkLabel);
+
+impl StarlarkLabel {
+    /// The result of calling `type()` on a label.
+    pub const TYPE: &'static str = "label";
+
+    pub(crate) fn new(prefix: FrozenStringValue, name: FrozenStringValue) -> Self {
+        Self { prefix, name }
+    }
+
+    /// Split a label into the prefix shared with the other labels in its package,
+    /// up to and including the last `:` (or `/` if there is no `:`), and the rest.
+    pub(crate) fn split(label: &str) -> (&str, &str) {
+        let i = label
+            .rfind(':')
+            .or_else(|| label.rfind('/'))
+            .map_or(0, |i| i + 1);
+        label.split_at(i)
+    }
+
+    /// The shared prefix of the label, e.g. `//package/path:`.
+    pub fn prefix(&self) -> &str {
+        self.prefix.as_str()
+    }
+
+    /// The name of the label, after the prefix.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.prefix().bytes().chain(self.name().bytes())
+    }
+}
+
+impl Display for StarlarkLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.prefix(), self.name())
+    }
+}
+
+impl<'v> StarlarkValue<'v> for StarlarkLabel {
+    starlark_type!(StarlarkLabel::TYPE);
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        // Strings cache their hash, so this is cheap, and consistent across heaps
+        hasher.write_u32(self.prefix.get_hash().get());
+        hasher.write_u32(self.name.get_hash().get());
+        Ok(())
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match other.downcast_ref::<StarlarkLabel>() {
+            // Labels from the same heap share interned strings, so usually compare by pointer
+            Some(other) => Ok(self.prefix == other.prefix && self.name == other.name),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, other: Value<'v>) -> anyhow::Result<Ordering> {
+        match other.downcast_ref::<StarlarkLabel>() {
+            Some(other) => Ok(self.bytes().cmp(other.bytes())),
+            None => ValueError::unsupported_with(self, "cmp()", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::label::StarlarkLabel;
+    use crate::values::FrozenHeap;
+    use crate::values::ValueLike;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(StarlarkLabel::split("//foo/bar:baz"), ("//foo/bar:", "baz"));
+        assert_eq!(StarlarkLabel::split("cell//foo:"), ("cell//foo:", ""));
+        assert_eq!(StarlarkLabel::split("//foo/bar"), ("//foo/", "bar"));
+        assert_eq!(StarlarkLabel::split("baz"), ("", "baz"));
+    }
+
+    #[test]
+    fn test_shared_prefix() {
+        let heap = FrozenHeap::new();
+        let x = heap.alloc_label("//foo/bar:x");
+        let y = heap.alloc_label("//foo/bar:y");
+        let x = x.downcast_ref::<StarlarkLabel>().unwrap();
+        let y = y.downcast_ref::<StarlarkLabel>().unwrap();
+        assert!(x.prefix.to_value().ptr_eq(y.prefix.to_value()));
+        assert_eq!(x.to_string(), "//foo/bar:x");
+        assert_eq!(y.name(), "y");
+    }
+
+    #[test]
+    fn test_equality_across_heaps() {
+        let heap1 = FrozenHeap::new();
+        let heap2 = FrozenHeap::new();
+        let x1 = heap1.alloc_label("//foo:x").to_value();
+        let x2 = heap2.alloc_label("//foo:x").to_value();
+        let y = heap2.alloc_label("//foo/bar:y").to_value();
+        assert!(x1.equals(x2).unwrap());
+        assert!(!x1.equals(y).unwrap());
+        assert!(!x1.equals(heap1.alloc_str("//foo:x").to_value()).unwrap());
+        assert_eq!(x1.get_hash().unwrap(), x2.get_hash().unwrap());
+        assert_eq!(x1.to_str(), "//foo:x");
+        assert_eq!(x1.get_type(), "label");
+        // Ordered as the strings would be
+        assert!(y.compare(x1).unwrap().is_lt());
+    }
+}
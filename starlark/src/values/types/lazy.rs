@@ -0,0 +1,167 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A [`LazyValue`], a global computed the first time a script refers to it.
+//!
+//! Added to the globals with [`GlobalsBuilder::set_lazy`](crate::environment::GlobalsBuilder::set_lazy),
+//! so embedders can offer expensive globals which scripts that never mention them don't pay for.
+
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use once_cell::sync::OnceCell;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::values::FrozenHeap;
+use crate::values::FrozenHeapRef;
+use crate::values::FrozenValue;
+use crate::values::StarlarkValue;
+
+/// A value computed by a closure the first time it is needed, and then cached.
+///
+/// The closure allocates the value in a heap of its own, which lives as long as
+/// the [`LazyValue`]. Globals are resolved when a module is compiled, so the value is
+/// computed when the first module mentioning it is compiled, not when that code runs.
+/// A [`struct_`](crate::environment::GlobalsBuilder::struct_) with lazy fields is computed,
+/// along with its fields, when first referred to. Describing or documenting the globals
+/// computes the value too.
+#[derive(ProvidesStaticType, NoSerialize, Allocative)]
+pub struct LazyValue {
+    #[allocative(skip)]
+    compute: Box<dyn Fn(&FrozenHeap) -> FrozenValue + Send + Sync>,
+    #[allocative(skip)]
+    value: OnceCell<(FrozenHeapRef, FrozenValue)>,
+}
+
+starlark_simple_value!(LazyValue);
+
+impl LazyValue {
+    /// A value computed by `compute` when it is first needed.
+    pub fn new(compute: impl Fn(&FrozenHeap) -> FrozenValue + Send + Sync + 'static) -> Self {
+        Self {
+            compute: Box::new(compute),
+            value: OnceCell::new(),
+        }
+    }
+
+    /// The value, computing it if this is the first time it is needed.
+    pub fn get(&self) -> FrozenValue {
+        self.value
+            .get_or_init(|| {
+                let heap = FrozenHeap::new();
+                let value = (self.compute)(&heap);
+                (heap.into_ref(), value)
+            })
+            .1
+    }
+
+    /// Whether the value has been computed yet.
+    pub fn is_computed(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// The value `value` stands for, computing it if `value` is a [`LazyValue`].
+    pub(crate) fn force_frozen(value: FrozenValue) -> FrozenValue {
+        match value.downcast_frozen_ref::<LazyValue>() {
+            Some(lazy) => lazy.get(),
+            None => value,
+        }
+    }
+}
+
+impl Debug for LazyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyValue")
+            .field("value", &self.value.get().map(|x| x.1))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Display for LazyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value.get() {
+            Some((_, x)) => Display::fmt(x, f),
+            None => f.write_str("<lazy>"),
+        }
+    }
+}
+
+impl<'v> StarlarkValue<'v> for LazyValue {
+    starlark_type!("lazy");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use crate::assert::Assert;
+    use crate::environment::GlobalsBuilder;
+
+    #[test]
+    fn test_lazy_value() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut a = Assert::new();
+        a.globals_add(|x| {
+            let calls = calls.clone();
+            x.set_lazy("expensive", move |heap| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                heap.alloc(vec![1, 2, 3])
+            })
+        });
+        a.eq("1", "1");
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        a.eq("expensive", "[1, 2, 3]");
+        a.is_true("expensive[1] == 2 and len(expensive) == 3");
+        a.is_true("type(expensive) == 'list'");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_lazy_struct_field() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut a = Assert::new();
+        a.globals_add(|x| {
+            let calls = calls.clone();
+            x.struct_("ns", |x| {
+                x.set_lazy("expensive", move |heap| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    heap.alloc(vec![1, 2, 3])
+                })
+            })
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        a.eq("ns.expensive", "[1, 2, 3]");
+        a.eq("type(ns)", "'struct'");
+        a.eq("structs.to_dict(ns)", "{'expensive': [1, 2, 3]}");
+        a.eq("repr(ns)", "'struct(expensive=[1, 2, 3])'");
+        a.is_true("ns == struct(expensive = [1, 2, 3])");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_lazy_documentation() {
+        let globals = GlobalsBuilder::new()
+            .with(|x| x.set_lazy("expensive", |heap| heap.alloc(vec![1, 2, 3])))
+            .build();
+        assert_eq!(globals.describe(), "# expensive = [1, 2, 3]");
+    }
+}
@@ -23,6 +23,7 @@ use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::mem;
 use std::slice;
 
 use allocative::Allocative;
@@ -41,6 +42,7 @@ use crate::private::Private;
 use crate::values::array::Array;
 use crate::values::comparison::compare_slice;
 use crate::values::comparison::equals_slice;
+use crate::values::error::FrozenMutationError;
 use crate::values::error::ValueError;
 use crate::values::index::apply_slice;
 use crate::values::index::convert_index;
@@ -116,7 +118,7 @@ impl<'v> ListData<'v> {
         #[inline(never)]
         fn error<'v>(x: Value<'v>) -> anyhow::Error {
             if x.downcast_ref::<ListGen<FrozenListData>>().is_some() {
-                ValueError::CannotMutateImmutableValue.into()
+                FrozenMutationError::new(x.get_type(), x.ptr_value().ptr_value())
             } else {
                 NotListError(x.get_type()).into()
             }
@@ -368,7 +370,10 @@ impl<'v> ListLike<'v> for FrozenListData {
     }
 
     fn set_at(&self, _i: usize, _v: Value<'v>) -> anyhow::Result<()> {
-        Err(ValueError::CannotMutateImmutableValue.into())
+        Err(FrozenMutationError::new(
+            ListData::TYPE,
+            self as *const Self as usize,
+        ))
     }
 
     fn iterate<'a>(&'a self) -> Box<dyn Iterator<Item = Value<'v>> + 'a>
@@ -507,11 +512,13 @@ where
 
     fn mul(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         let l = i32::unpack_param(other)?;
-        let mut result = Vec::with_capacity(self.0.content().len() * cmp::max(0, l) as usize);
-        for _ in 0..l {
-            result.extend(self.0.content().iter());
+        let n = cmp::max(0, l) as usize;
+        // Starlark values must be smaller than 4 GiB.
+        match self.0.content().len().checked_mul(n) {
+            Some(len) if len < u32::MAX as usize / mem::size_of::<Value>() => {}
+            _ => return Err(ValueError::IntegerOverflow.into()),
         }
-        Ok(heap.alloc_list(&result))
+        heap.try_alloc_list_repeat(self.0.content(), n)
     }
 
     fn set_at(&self, index: Value<'v>, alloc_value: Value<'v>) -> anyhow::Result<()> {
@@ -599,10 +606,14 @@ def list_result():
     return [1, 2, 4]
 "#,
         );
-        a.fail("load('x','frozen_list')\nfrozen_list += [1]", "Immutable");
         a.fail(
-            "load('x','frozen_list_result')\nx = frozen_list_result()\nx += [1]",
-            "Immutable",
+            "load('x','frozen_list')\nfrozen_list += [1]",
+            "Immutable `list` from module `x.bzl`, frozen when that module finished evaluating; \
+            copy it with `list(x)` to get a value that can be mutated",
+        );
+        a.fail(
+            "load('x','frozen_list_result')\nx = frozen_list_result()\nx[0] = 1",
+            "Immutable `list` from module `x.bzl`",
         );
         a.is_true("load('x','list_result')\nx = list_result()\nx += [8]\nx == [1, 2, 4, 8]");
     }
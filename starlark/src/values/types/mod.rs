@@ -20,18 +20,25 @@ pub mod any_array;
 pub mod array;
 pub mod bigint;
 pub mod bool;
+pub mod bytes;
 pub mod dict;
 pub mod enumeration;
 pub mod float;
 pub mod function;
+pub(crate) mod identity_dict;
 pub mod int;
 pub(crate) mod known_methods;
+pub mod label;
+pub mod lazy;
 pub mod list;
+pub mod namedtuple;
 pub mod none;
 pub mod range;
 pub mod record;
 pub mod regex;
+pub(crate) mod set;
 pub mod string;
 pub mod structs;
 pub mod tuple;
+pub(crate) mod type_expr;
 pub(crate) mod unbound;
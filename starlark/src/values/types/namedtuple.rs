@@ -0,0 +1,303 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A `namedtuple` type, a tuple whose elements can also be accessed by name.
+//!
+//! Calling `namedtuple()` produces a [`NamedTupleType`]. Calling [`NamedTupleType`] produces
+//! a [`NamedTuple`]. Unlike [`record`](crate::values::record), the fields have no types
+//! and nothing is checked, so both creating and using them is cheaper.
+//!
+//! ```
+//! # starlark::assert::is_true(r#"
+//! Point = namedtuple("Point", ["x", "y"])
+//! p = Point(1, y = 2)
+//! x, y = p
+//! p.x == 1 and p[1] == 2 and x == 1 and y == 2
+//! # "#);
+//! ```
+
+use std::cell::Cell;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use allocative::Allocative;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::Coerce;
+use crate::collections::Hashed;
+use crate::collections::SmallSet;
+use crate::collections::StarlarkHasher;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::eval::ParametersSpec;
+use crate::values::comparison::equals_slice;
+use crate::values::function::FUNCTION_TYPE;
+use crate::values::index::convert_index;
+use crate::values::Freeze;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(Debug, Error)]
+enum NamedTupleError {
+    #[error("namedtuple field `{0}` is not a valid identifier")]
+    InvalidField(String),
+    #[error("namedtuple field `{0}` is given more than once")]
+    DuplicateField(String),
+}
+
+/// The result of `namedtuple()`, which creates named tuples when called.
+#[derive(Debug, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct NamedTupleType {
+    name: String,
+    fields: SmallSet<String>,
+    /// Computed in advance, since creating it on every call is expensive.
+    #[allocative(skip)]
+    parameter_spec: ParametersSpec<FrozenValue>,
+}
+
+starlark_simple_value!(NamedTupleType);
+
+impl NamedTupleType {
+    /// A type called `name` with the given fields, which must be identifiers.
+    pub(crate) fn new(name: String, fields: Vec<String>) -> anyhow::Result<Self> {
+        let mut parameters = ParametersSpec::with_capacity(name.clone(), fields.len());
+        parameters.no_more_positional_only_args();
+        let mut set = SmallSet::with_capacity(fields.len());
+        for field in fields {
+            let mut chars = field.chars();
+            let valid = chars
+                .next()
+                .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(NamedTupleError::InvalidField(field).into());
+            }
+            if set.contains(&field) {
+                return Err(NamedTupleError::DuplicateField(field).into());
+            }
+            parameters.required(&field);
+            set.insert(field);
+        }
+        Ok(Self {
+            name,
+            fields: set,
+            parameter_spec: parameters.finish(),
+        })
+    }
+
+    /// The name of the type.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The names of the fields, in order.
+    pub fn fields(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.fields.iter().map(String::as_str)
+    }
+}
+
+impl Display for NamedTupleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "namedtuple({:?}, [", self.name)?;
+        for (i, x) in self.fields.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{:?}", x)?;
+        }
+        f.write_str("])")
+    }
+}
+
+impl<'v> StarlarkValue<'v> for NamedTupleType {
+    starlark_type!(FUNCTION_TYPE);
+
+    fn invoke(
+        &self,
+        me: Value<'v>,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<Value<'v>> {
+        let slots = vec![Cell::new(None); self.fields.len()];
+        self.parameter_spec.collect(args, &slots, eval.heap())?;
+        // All parameters are required, so every slot has been filled.
+        let values = slots.into_iter().map(|x| x.get().unwrap()).collect();
+        Ok(eval.heap().alloc_complex(NamedTuple { typ: me, values }))
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        // Types declared the same way are equal, even if declared in different modules.
+        Ok(match other.downcast_ref::<NamedTupleType>() {
+            Some(other) => self.name == other.name && self.fields().eq(other.fields()),
+            None => false,
+        })
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        vec!["type".to_owned()]
+    }
+
+    fn has_attr(&self, attribute: &str, _heap: &'v Heap) -> bool {
+        attribute == "type"
+    }
+
+    fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
+        if attribute == "type" {
+            Some(heap.alloc(self.name.as_str()))
+        } else {
+            None
+        }
+    }
+}
+
+/// A named tuple, created by calling a [`NamedTupleType`].
+#[derive(Clone, Debug, Trace, Coerce, Freeze, ProvidesStaticType, Allocative)]
+#[repr(C)]
+pub struct NamedTupleGen<V> {
+    typ: V, // Must be NamedTupleType
+    values: Box<[V]>,
+}
+
+starlark_complex_value!(pub NamedTuple);
+
+impl<'v, V: ValueLike<'v>> NamedTupleGen<V> {
+    /// `type(x)` for named tuples.
+    pub const TYPE: &'static str = "namedtuple";
+
+    fn get_type(&self) -> &'v NamedTupleType {
+        // Safe to unwrap because we always ensure typ is NamedTupleType
+        self.typ
+            .to_value()
+            .downcast_ref::<NamedTupleType>()
+            .unwrap()
+    }
+
+    /// The values of the fields, in order.
+    pub fn values(&self) -> impl ExactSizeIterator<Item = Value<'v>> + '_ {
+        self.values.iter().map(|x| x.to_value())
+    }
+
+    /// Iterate over the names and values of the fields.
+    pub fn iter<'a>(&'a self) -> impl ExactSizeIterator<Item = (&'v str, Value<'v>)> + 'a
+    where
+        'v: 'a,
+    {
+        self.get_type().fields().zip(self.values())
+    }
+}
+
+impl<'v, V: ValueLike<'v>> Display for NamedTupleGen<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.get_type().name())?;
+        for (i, (k, v)) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}={}", k, v)?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for NamedTupleGen<V>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!(NamedTuple::TYPE);
+
+    fn matches_type(&self, ty: &str) -> bool {
+        ty == NamedTuple::TYPE || ty == self.get_type().name()
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.values.is_empty()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match NamedTuple::from_value(other) {
+            Some(other) if self.typ.equals(other.typ.to_value())? => {
+                equals_slice(&self.values, &other.values, |x, y| x.equals(*y))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
+        self.get_type().name().hash(hasher);
+        for v in &*self.values {
+            v.write_hash(hasher)?;
+        }
+        Ok(())
+    }
+
+    fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
+        self.get_attr_hashed(Hashed::new(attribute), heap)
+    }
+
+    fn get_attr_hashed(&self, attribute: Hashed<&str>, _heap: &'v Heap) -> Option<Value<'v>> {
+        let i = self.get_type().fields.get_index_of_hashed(attribute)?;
+        Some(self.values[i].to_value())
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        self.get_type().fields().map(str::to_owned).collect()
+    }
+
+    fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let i = convert_index(index, self.values.len() as i32)? as usize;
+        Ok(self.values[i].to_value())
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.values.len() as i32)
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        _heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        Ok(Box::new(self.values()))
+    }
+
+    fn with_iterator(
+        &self,
+        _heap: &'v Heap,
+        f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        f(&mut self.values())
+    }
+}
+
+impl<'v, V: ValueLike<'v>> Serialize for NamedTupleGen<V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(self.iter())
+    }
+}
@@ -29,6 +29,7 @@ use crate::collections::StarlarkHashValue;
 use crate::collections::StarlarkHasher;
 use crate::private::Private;
 use crate::values::basic::StarlarkValueBasic;
+use crate::values::types::type_expr::type_union;
 use crate::values::AllocFrozenValue;
 use crate::values::AllocValue;
 use crate::values::FrozenHeap;
@@ -37,6 +38,7 @@ use crate::values::Heap;
 use crate::values::StarlarkValue;
 use crate::values::UnpackValue;
 use crate::values::Value;
+use crate::values::ValueError;
 
 /// Define the None type, use [`NoneType`] in Rust.
 #[derive(
@@ -82,6 +84,14 @@ impl<'v> StarlarkValue<'v> for NoneType {
     fn to_bool(&self) -> bool {
         false
     }
+
+    fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        // `None | int`, a type annotation
+        match type_union([Value::new_none()], other, heap) {
+            Some(x) => Ok(x),
+            None => ValueError::unsupported_with(self, "|", other),
+        }
+    }
     fn write_hash(&self, hasher: &mut StarlarkHasher) -> anyhow::Result<()> {
         // just took the result of hash(None) in macos python 2.7.10 interpreter.
         hasher.write_u64(9_223_380_832_852_120_682);
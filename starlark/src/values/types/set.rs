@@ -0,0 +1,640 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `set` type, a collection of unique hashable values.
+//!
+//! Created with the `set()` function, or with literals like `{1, 2}` when
+//! [`Dialect::enable_set_literals`](crate::syntax::Dialect::enable_set_literals) is on.
+//! Sets iterate in insertion order, and like lists and dictionaries are mutable
+//! until frozen.
+
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use allocative::Allocative;
+use gazebo::cell::ARef;
+use gazebo::display::display_container;
+use gazebo::prelude::*;
+use serde::Serialize;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::coerce;
+use crate::coerce::Coerce;
+use crate::collections::SmallSet;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::values::error::FrozenMutationError;
+use crate::values::error::ValueError;
+use crate::values::iter::ARefIterator;
+use crate::values::none::NoneType;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::AllocValue;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// A set, `SetGen<RefCell<SetData>>` when mutable and `SetGen<FrozenSetData>` when frozen.
+#[derive(
+    Clone,
+    Default,
+    Trace,
+    Debug,
+    ProvidesStaticType,
+    StarlarkDocs,
+    Allocative
+)]
+#[starlark_docs(builtin = "extension")]
+pub(crate) struct SetGen<T>(pub(crate) T);
+
+/// The elements of a mutable set.
+#[derive(Clone, Default, Trace, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub(crate) struct SetData<'v> {
+    /// The elements, which must all be hashable values.
+    pub(crate) content: SmallSet<Value<'v>>,
+}
+
+/// The elements of a frozen set.
+#[derive(Clone, Default, Debug, ProvidesStaticType, Allocative)]
+#[repr(transparent)]
+pub(crate) struct FrozenSetData {
+    content: SmallSet<FrozenValue>,
+}
+
+unsafe impl<'v> Coerce<SetData<'v>> for FrozenSetData {}
+
+/// Type of a mutable set.
+pub(crate) type Set<'v> = SetGen<RefCell<SetData<'v>>>;
+
+/// Alias is used in `StarlarkDocs` derive.
+type FrozenSet = SetGen<FrozenSetData>;
+
+impl<'v> Set<'v> {
+    /// The result of calling `type()` on sets.
+    pub(crate) const TYPE: &'static str = "set";
+
+    pub(crate) fn new(content: SmallSet<Value<'v>>) -> Self {
+        SetGen(RefCell::new(SetData { content }))
+    }
+}
+
+impl<'v> AllocValue<'v> for Set<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex(self)
+    }
+}
+
+impl<'v> Freeze for Set<'v> {
+    type Frozen = FrozenSet;
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let content = self.0.into_inner().content.freeze(freezer)?;
+        Ok(SetGen(FrozenSetData { content }))
+    }
+}
+
+trait SetLike<'v>: Debug + Allocative {
+    fn content(&self) -> ARef<SmallSet<Value<'v>>>;
+    fn content_mut(&self) -> anyhow::Result<RefMut<SmallSet<Value<'v>>>>;
+}
+
+impl<'v> SetLike<'v> for RefCell<SetData<'v>> {
+    fn content(&self) -> ARef<SmallSet<Value<'v>>> {
+        ARef::new_ref(Ref::map(self.borrow(), |x| &x.content))
+    }
+
+    fn content_mut(&self) -> anyhow::Result<RefMut<SmallSet<Value<'v>>>> {
+        match self.try_borrow_mut() {
+            Ok(xs) => Ok(RefMut::map(xs, |x| &mut x.content)),
+            Err(_) => Err(ValueError::MutationDuringIteration.into()),
+        }
+    }
+}
+
+impl<'v> SetLike<'v> for FrozenSetData {
+    fn content(&self) -> ARef<SmallSet<Value<'v>>> {
+        let data: &SetData<'v> = coerce(self);
+        ARef::new_ptr(&data.content)
+    }
+
+    fn content_mut(&self) -> anyhow::Result<RefMut<SmallSet<Value<'v>>>> {
+        Err(FrozenMutationError::new(
+            Set::TYPE,
+            self as *const Self as usize,
+        ))
+    }
+}
+
+impl<'v, T: SetLike<'v>> Display for SetGen<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let content = self.0.content();
+        if content.is_empty() {
+            write!(f, "set()")
+        } else {
+            display_container(f, "set([", "])", content.iter())
+        }
+    }
+}
+
+/// The elements of `x`, if it is a set.
+fn set_content<'v>(x: Value<'v>) -> Option<ARef<'v, SmallSet<Value<'v>>>> {
+    if let Some(x) = x.downcast_ref::<Set<'v>>() {
+        Some(x.0.content())
+    } else {
+        x.downcast_ref::<FrozenSet>().map(|x| x.0.content())
+    }
+}
+
+impl<T: StarlarkTypeRepr> StarlarkTypeRepr for HashSet<T> {
+    fn starlark_type_repr() -> String {
+        FrozenSet::starlark_type_repr()
+    }
+}
+
+/// Unpacked from a set, or from a list or tuple, whose duplicates are dropped.
+impl<'v, T: UnpackValue<'v> + Hash + Eq> UnpackValue<'v> for HashSet<T> {
+    fn expected() -> String {
+        format!("set, list or tuple of {}", T::expected())
+    }
+
+    fn unpack_value(value: Value<'v>) -> Option<Self> {
+        match set_content(value) {
+            Some(xs) => xs.iter().map(|x| T::unpack_value(*x)).collect(),
+            None => Some(Vec::<T>::unpack_value(value)?.into_iter().collect()),
+        }
+    }
+}
+
+/// Allocated as a set in the iteration order of the `HashSet`, which is arbitrary.
+/// Panics if an element is not hashable.
+impl<'v, T: AllocValue<'v>> AllocValue<'v> for HashSet<T> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        let mut content = SmallSet::with_capacity(self.len());
+        for x in self {
+            content.insert_hashed(x.alloc_value(heap).get_hashed().unwrap());
+        }
+        heap.alloc(Set::new(content))
+    }
+}
+
+fn content<'v>(x: Value<'v>) -> anyhow::Result<ARef<'v, SmallSet<Value<'v>>>> {
+    set_content(x).ok_or_else(|| ValueError::IncorrectParameterTypeNamed("this".to_owned()).into())
+}
+
+fn content_mut<'v>(x: Value<'v>) -> anyhow::Result<RefMut<'v, SmallSet<Value<'v>>>> {
+    if let Some(x) = x.downcast_ref::<Set<'v>>() {
+        x.0.content_mut()
+    } else if let Some(x) = x.downcast_ref::<FrozenSet>() {
+        x.0.content_mut()
+    } else {
+        Err(ValueError::IncorrectParameterTypeNamed("this".to_owned()).into())
+    }
+}
+
+/// Collect the elements of an iterable, which must all be hashable.
+pub(crate) fn collect_set<'v>(
+    xs: Value<'v>,
+    heap: &'v Heap,
+) -> anyhow::Result<SmallSet<Value<'v>>> {
+    if let Some(xs) = set_content(xs) {
+        return Ok(xs.clone());
+    }
+    let mut res = SmallSet::new();
+    for x in xs.iterate(heap)? {
+        res.insert_hashed(x.get_hashed()?);
+    }
+    Ok(res)
+}
+
+/// The elements of `xs` for which `f` returns `true` when told whether `ys` contains them.
+fn filter<'v>(
+    xs: &SmallSet<Value<'v>>,
+    ys: &SmallSet<Value<'v>>,
+    f: impl Fn(bool) -> bool,
+) -> SmallSet<Value<'v>> {
+    let mut res = SmallSet::new();
+    for x in xs.iter_hashed() {
+        if f(ys.contains_hashed(x)) {
+            res.insert_hashed(x.copied());
+        }
+    }
+    res
+}
+
+fn union<'v>(xs: &SmallSet<Value<'v>>, ys: &SmallSet<Value<'v>>) -> SmallSet<Value<'v>> {
+    let mut res = xs.clone();
+    for y in ys.iter_hashed() {
+        res.insert_hashed(y.copied());
+    }
+    res
+}
+
+fn symmetric_difference<'v>(
+    xs: &SmallSet<Value<'v>>,
+    ys: &SmallSet<Value<'v>>,
+) -> SmallSet<Value<'v>> {
+    let mut res = filter(xs, ys, |x| !x);
+    for y in filter(ys, xs, |x| !x).into_iter_hashed() {
+        res.insert_hashed(y);
+    }
+    res
+}
+
+/// Combine the elements of a set with those of `rhs`, for the binary operators,
+/// or [`None`] if `rhs` is not a set.
+fn binary_op<'v>(
+    xs: &SmallSet<Value<'v>>,
+    rhs: Value<'v>,
+    heap: &'v Heap,
+    f: impl Fn(&SmallSet<Value<'v>>, &SmallSet<Value<'v>>) -> SmallSet<Value<'v>>,
+) -> Option<Value<'v>> {
+    let ys = set_content(rhs)?;
+    Some(heap.alloc(Set::new(f(xs, &ys))))
+}
+
+impl<'v, T: SetLike<'v> + 'v> StarlarkValue<'v> for SetGen<T>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!(Set::TYPE);
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(set_methods)
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        let content = self.0.content();
+        if content.is_empty() {
+            r.push_str("set()");
+            return;
+        }
+        r.push_str("set([");
+        for (i, x) in content.iter().enumerate() {
+            if i != 0 {
+                r.push_str(", ");
+            }
+            x.collect_repr(r);
+        }
+        r.push_str("])");
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.content().is_empty()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match set_content(other) {
+            None => Ok(false),
+            Some(other) => {
+                let content = self.0.content();
+                Ok(content.len() == other.len()
+                    && content.iter_hashed().all(|x| other.contains_hashed(x)))
+            }
+        }
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.content().len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self
+            .0
+            .content()
+            .contains_hashed(other.get_hashed()?.as_ref()))
+    }
+
+    fn iterate<'a>(
+        &'a self,
+        _heap: &'v Heap,
+    ) -> anyhow::Result<Box<dyn Iterator<Item = Value<'v>> + 'a>>
+    where
+        'v: 'a,
+    {
+        Ok(Box::new(ARefIterator::new(self.0.content(), |x| {
+            x.iter().copied()
+        })))
+    }
+
+    fn with_iterator(
+        &self,
+        _heap: &'v Heap,
+        f: &mut dyn FnMut(&mut dyn Iterator<Item = Value<'v>>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        f(&mut self.0.content().iter().copied())
+    }
+
+    fn bit_or(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        binary_op(&self.0.content(), rhs, heap, union)
+            .map_or_else(|| ValueError::unsupported_with(self, "|", rhs), Ok)
+    }
+
+    fn bit_and(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        binary_op(&self.0.content(), rhs, heap, |xs, ys| filter(xs, ys, |x| x))
+            .map_or_else(|| ValueError::unsupported_with(self, "&", rhs), Ok)
+    }
+
+    fn bit_xor(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        binary_op(&self.0.content(), rhs, heap, symmetric_difference)
+            .map_or_else(|| ValueError::unsupported_with(self, "^", rhs), Ok)
+    }
+
+    fn sub(&self, rhs: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        binary_op(&self.0.content(), rhs, heap, |xs, ys| {
+            filter(xs, ys, |x| !x)
+        })
+        .map_or_else(|| ValueError::unsupported_with(self, "-", rhs), Ok)
+    }
+}
+
+impl<'v, T: SetLike<'v>> Serialize for SetGen<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.content().iter())
+    }
+}
+
+#[starlark_module]
+fn set_methods(builder: &mut MethodsBuilder) {
+    /// Add an element to the set, doing nothing if it is already present.
+    fn add<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let value = value.get_hashed()?;
+        content_mut(this)?.insert_hashed(value);
+        Ok(NoneType)
+    }
+
+    /// Remove all the elements of the set.
+    fn clear(this: Value) -> anyhow::Result<NoneType> {
+        content_mut(this)?.clear();
+        Ok(NoneType)
+    }
+
+    /// Remove an element from the set, failing if it is not present.
+    fn remove<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let hashed = value.get_hashed()?;
+        if content_mut(this)?.remove_hashed(hashed.as_ref()) {
+            Ok(NoneType)
+        } else {
+            Err(ValueError::KeyNotFound(value.to_repr()).into())
+        }
+    }
+
+    /// Remove an element from the set if it is present.
+    fn discard<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] value: Value<'v>,
+    ) -> anyhow::Result<NoneType> {
+        let value = value.get_hashed()?;
+        content_mut(this)?.remove_hashed(value.as_ref());
+        Ok(NoneType)
+    }
+
+    /// Remove and return the first element of the set, failing if it is empty.
+    fn pop<'v>(this: Value<'v>) -> anyhow::Result<Value<'v>> {
+        let mut content = content_mut(this)?;
+        let first = match content.iter_hashed().next() {
+            Some(x) => x.copied(),
+            None => return Err(anyhow::anyhow!("pop from empty set")),
+        };
+        content.remove_hashed(first.as_ref());
+        Ok(first.into_key())
+    }
+
+    /// Add the elements of each of the iterables to the set.
+    fn update<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<NoneType> {
+        // Collect first, so a set can be updated with itself
+        let others = others.into_try_map(|x| collect_set(x, heap))?;
+        let mut content = content_mut(this)?;
+        for other in others {
+            for x in other.into_iter_hashed() {
+                content.insert_hashed(x);
+            }
+        }
+        Ok(NoneType)
+    }
+
+    /// A new set with the elements of the set and of each of the iterables.
+    fn union<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let mut res = content(this)?.clone();
+        for other in others {
+            res = union(&res, &collect_set(other, heap)?);
+        }
+        Ok(Set::new(res))
+    }
+
+    /// A new set with the elements of the set which are in every one of the iterables.
+    fn intersection<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let mut res = content(this)?.clone();
+        for other in others {
+            res = filter(&res, &collect_set(other, heap)?, |x| x);
+        }
+        Ok(Set::new(res))
+    }
+
+    /// A new set with the elements of the set which are in none of the iterables.
+    fn difference<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: Vec<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let mut res = content(this)?.clone();
+        for other in others {
+            res = filter(&res, &collect_set(other, heap)?, |x| !x);
+        }
+        Ok(Set::new(res))
+    }
+
+    /// A new set with the elements which are in either the set or the iterable, but not both.
+    fn symmetric_difference<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Set<'v>> {
+        let other = collect_set(other, heap)?;
+        let content = content(this)?;
+        Ok(Set::new(symmetric_difference(&content, &other)))
+    }
+
+    /// Whether every element of the set is in the iterable.
+    fn issubset<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        let other = collect_set(other, heap)?;
+        Ok(content(this)?
+            .iter_hashed()
+            .all(|x| other.contains_hashed(x)))
+    }
+
+    /// Whether every element of the iterable is in the set.
+    fn issuperset<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        let other = collect_set(other, heap)?;
+        let content = content(this)?;
+        Ok(other.iter_hashed().all(|x| content.contains_hashed(x)))
+    }
+
+    /// Whether the set and the iterable have no elements in common.
+    fn isdisjoint<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<bool> {
+        let other = collect_set(other, heap)?;
+        let content = content(this)?;
+        Ok(!other.iter_hashed().any(|x| content.contains_hashed(x)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::assert;
+    use crate::assert::Assert;
+    use crate::values::Heap;
+    use crate::values::UnpackValue;
+
+    #[test]
+    fn test_hash_set() {
+        let heap = Heap::new();
+        let x = heap.alloc(HashSet::from([1]));
+        assert_eq!(x.to_repr(), "set([1])");
+        assert_eq!(HashSet::<i32>::unpack_value(x), Some(HashSet::from([1])));
+        assert_eq!(
+            HashSet::<&str>::unpack_value(heap.alloc(("a", "b", "a"))),
+            Some(HashSet::from(["a", "b"]))
+        );
+        assert_eq!(HashSet::<&str>::unpack_value(x), None);
+    }
+
+    #[test]
+    fn test_set() {
+        assert::is_true(
+            r#"
+s = set([1, 2, 2, 3])
+len(s) == 3 and 2 in s and 4 not in s and list(s) == [1, 2, 3] and s and not set()
+"#,
+        );
+        assert::eq("repr(set([1, 'x']))", "'set([1, \"x\"])'");
+        assert::eq("str(set())", "'set()'");
+        assert::eq("set([1, 2])", "set([2, 1])");
+        assert::fail("set([[]])", "not hashable");
+        assert::fail("{set(): 1}", "not hashable");
+    }
+
+    #[test]
+    fn test_operators() {
+        assert::all_true(
+            r#"
+set([1, 2]) | set([2, 3]) == set([1, 2, 3])
+set([1, 2]) & set([2, 3]) == set([2])
+set([1, 2]) - set([2, 3]) == set([1])
+set([1, 2]) ^ set([2, 3]) == set([1, 3])
+"#,
+        );
+        assert::fail("set([1]) | [2]", "not supported");
+    }
+
+    #[test]
+    fn test_methods() {
+        assert::is_true(
+            r#"
+s = set()
+s.add(1)
+s.add(2)
+s.add(1)
+s.update([3], (4, 5))
+s.discard(5)
+s.discard(6)
+s.remove(4)
+(
+    s == set([1, 2, 3]) and s.pop() == 1 and s == set([2, 3]) and
+    s.union([1], [4]) == set([1, 2, 3, 4]) and
+    s.intersection([2, 5]) == set([2]) and
+    s.difference([2], [5]) == set([3]) and
+    s.symmetric_difference([3, 4]) == set([2, 4]) and
+    s.issubset([1, 2, 3]) and not s.issubset([2]) and
+    s.issuperset([2]) and s.isdisjoint([4]) and not s.isdisjoint([3])
+)
+"#,
+        );
+        assert::fail("set().pop()", "empty set");
+        assert::fail("set().remove(1)", "not found");
+        assert::fail("s = set([1]); [s.add(2) for x in s]", "iterat");
+    }
+
+    #[test]
+    fn test_literals() {
+        let mut a = Assert::new();
+        a.fail("{1, 2}", "not allowed in this dialect");
+        a.dialect_set(|x| x.enable_set_literals = true);
+        a.is_true(
+            r#"
+s = {1, 2, 1,}
+s.add(3)
+s == set([1, 2, 3]) and {1: 2} == dict([(1, 2)]) and {"x"} == set(["x"])
+"#,
+        );
+    }
+
+    #[test]
+    fn test_frozen() {
+        let mut a = Assert::new();
+        a.module("m.star", "s = set([1, 2])");
+        a.is_true("load('m.star', 's')\n1 in s and s | set([3]) == set([1, 2, 3])");
+        a.fail("load('m.star', 's')\ns.add(3)", "Immutable `set`");
+    }
+}
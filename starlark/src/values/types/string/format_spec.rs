@@ -0,0 +1,838 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Format specifiers, the part after the colon in `"{:>10.3f}".format(x)`,
+//! and the flags, width and precision of `"%-10.3f" % x`.
+//! Based on <https://docs.python.org/3/library/string.html#format-specification-mini-language>
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use num_traits::Signed;
+use thiserror::Error;
+
+use crate::values::float;
+use crate::values::num::Num;
+use crate::values::Value;
+
+/// Errors in a format specifier. Each error names the specifier and the offending part of it.
+#[derive(Debug, Error)]
+enum FormatSpecError {
+    #[error("Invalid format specifier `{spec}`: unexpected `{rest}`")]
+    Unexpected { spec: String, rest: String },
+    #[error("Invalid format specifier `{spec}`: unknown format type `{ty}`")]
+    UnknownType { spec: String, ty: char },
+    #[error("Invalid format specifier `{spec}`: missing precision after `.`")]
+    MissingPrecision { spec: String },
+    #[error("Invalid format specifier `{spec}`: `{number}` is too big")]
+    TooBig { spec: String, number: String },
+    #[error("Invalid format specifier `{spec}`: format type `{ty}` is not valid for type `{typ}`")]
+    TypeMismatch {
+        spec: String,
+        ty: char,
+        typ: &'static str,
+    },
+    #[error("Invalid format specifier `{spec}`: `{segment}` is not allowed for {what}")]
+    NotAllowed {
+        spec: String,
+        segment: String,
+        what: String,
+    },
+    #[error("Invalid format specifier `{spec}`: {value} is not a valid character code")]
+    InvalidChar { spec: String, value: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    /// `<`
+    Left,
+    /// `>`
+    Right,
+    /// `^`
+    Center,
+    /// `=`, padding goes between the sign and the digits.
+    AfterSign,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Align> {
+        match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            '=' => Some(Align::AfterSign),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sign {
+    /// `-`, the default: only negative numbers get a sign.
+    Negative,
+    /// `+`
+    Always,
+    /// ` `, a space in front of non-negative numbers.
+    Space,
+}
+
+impl Sign {
+    fn as_char(self) -> char {
+        match self {
+            Sign::Negative => '-',
+            Sign::Always => '+',
+            Sign::Space => ' ',
+        }
+    }
+}
+
+/// Format types accepted after the other parts of the specifier.
+const FORMAT_TYPES: &str = "bcdeEfFgGnosxX%";
+
+/// Widths and precisions are limited so a typo can't allocate gigabytes.
+const MAX_NUMBER: usize = 10_000;
+
+/// A parsed format specifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FormatSpec<'a> {
+    /// The original text, used in error messages.
+    spec: &'a str,
+    fill: Option<char>,
+    align: Option<Align>,
+    /// Only set if given explicitly.
+    sign: Option<Sign>,
+    /// `#`
+    alternate: bool,
+    /// `0` before the width.
+    zero: bool,
+    width: usize,
+    /// `,` or `_`.
+    grouping: Option<char>,
+    precision: Option<usize>,
+    ty: Option<char>,
+    /// Parsed from `%` interpolation, where precision of integers is the minimum number of digits.
+    percent: bool,
+}
+
+/// Consume a decimal number from the start of `rest`.
+fn take_number(spec: &str, rest: &mut &str) -> anyhow::Result<Option<usize>> {
+    let len = rest.bytes().take_while(|b| b.is_ascii_digit()).count();
+    if len == 0 {
+        return Ok(None);
+    }
+    let (number, tail) = rest.split_at(len);
+    *rest = tail;
+    match usize::from_str(number) {
+        Ok(n) if n <= MAX_NUMBER => Ok(Some(n)),
+        _ => Err(FormatSpecError::TooBig {
+            spec: spec.to_owned(),
+            number: number.to_owned(),
+        }
+        .into()),
+    }
+}
+
+/// Consume `c` from the start of `rest` if it is there.
+fn take_char(rest: &mut &str, c: char) -> bool {
+    match rest.strip_prefix(c) {
+        Some(tail) => {
+            *rest = tail;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Insert `sep` between every `size` digits counting from the right, first
+/// prepending zeros until the result is at least `min_width` long.
+fn group_digits(digits: &str, sep: char, size: usize, min_width: usize) -> String {
+    let mut len = digits.len();
+    while len + len.saturating_sub(1) / size < min_width {
+        len += 1;
+    }
+    let mut res = String::with_capacity(len + len / size);
+    for (i, c) in std::iter::repeat('0')
+        .take(len - digits.len())
+        .chain(digits.chars())
+        .enumerate()
+    {
+        if i != 0 && (len - i) % size == 0 {
+            res.push(sep);
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Scientific notation with the exponent written like Python does, e.g. `1.5e+03`.
+fn scientific(x: f64, precision: usize, exponent_char: char, alternate: bool) -> String {
+    let s = format!("{:.*e}", precision, x);
+    let (mantissa, exponent) = s.split_once('e').unwrap();
+    let exponent = i32::from_str(exponent).unwrap();
+    let point = if alternate && precision == 0 { "." } else { "" };
+    format!("{}{}{}{:+03}", mantissa, point, exponent_char, exponent)
+}
+
+/// Remove trailing zeros after the decimal point, and the point itself if nothing remains.
+fn strip_fraction_zeros(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
+}
+
+impl<'a> FormatSpec<'a> {
+    fn new(spec: &'a str) -> Self {
+        FormatSpec {
+            spec,
+            fill: None,
+            align: None,
+            sign: None,
+            alternate: false,
+            zero: false,
+            width: 0,
+            grouping: None,
+            precision: None,
+            ty: None,
+            percent: false,
+        }
+    }
+
+    /// Parse `[[fill]align][sign][#][0][width][grouping][.precision][type]`.
+    pub(crate) fn parse(spec: &'a str) -> anyhow::Result<Self> {
+        let mut res = FormatSpec::new(spec);
+        let mut rest = spec;
+
+        let mut chars = rest.chars();
+        let first = chars.next();
+        let second = chars.next();
+        if let Some(align) = second.and_then(Align::from_char) {
+            let fill = first.unwrap();
+            res.fill = Some(fill);
+            res.align = Some(align);
+            rest = &rest[fill.len_utf8() + 1..];
+        } else if let Some(align) = first.and_then(Align::from_char) {
+            res.align = Some(align);
+            rest = &rest[1..];
+        }
+
+        if take_char(&mut rest, '+') {
+            res.sign = Some(Sign::Always);
+        } else if take_char(&mut rest, '-') {
+            res.sign = Some(Sign::Negative);
+        } else if take_char(&mut rest, ' ') {
+            res.sign = Some(Sign::Space);
+        }
+        res.alternate = take_char(&mut rest, '#');
+        res.zero = take_char(&mut rest, '0');
+        res.width = take_number(spec, &mut rest)?.unwrap_or(0);
+        if take_char(&mut rest, ',') {
+            res.grouping = Some(',');
+        } else if take_char(&mut rest, '_') {
+            res.grouping = Some('_');
+        }
+        if take_char(&mut rest, '.') {
+            match take_number(spec, &mut rest)? {
+                Some(precision) => res.precision = Some(precision),
+                None => {
+                    return Err(FormatSpecError::MissingPrecision {
+                        spec: spec.to_owned(),
+                    }
+                    .into());
+                }
+            }
+        }
+        let mut chars = rest.chars();
+        if let Some(ty) = chars.next() {
+            if !FORMAT_TYPES.contains(ty) {
+                return Err(FormatSpecError::UnknownType {
+                    spec: spec.to_owned(),
+                    ty,
+                }
+                .into());
+            }
+            res.ty = Some(ty);
+            rest = chars.as_str();
+        }
+        if !rest.is_empty() {
+            return Err(FormatSpecError::Unexpected {
+                spec: spec.to_owned(),
+                rest: rest.to_owned(),
+            }
+            .into());
+        }
+        Ok(res)
+    }
+
+    /// Parse the `[flags][width][.precision]` part of a `%` conversion, e.g. `-10.3` in `%-10.3f`.
+    /// The conversion character itself is passed separately as `ty`.
+    pub(crate) fn parse_percent(spec: &'a str, ty: char) -> anyhow::Result<Self> {
+        let mut res = FormatSpec::new(spec);
+        res.percent = true;
+        let mut rest = spec;
+        loop {
+            if take_char(&mut rest, '-') {
+                res.align = Some(Align::Left);
+            } else if take_char(&mut rest, '+') {
+                res.sign = Some(Sign::Always);
+            } else if take_char(&mut rest, ' ') {
+                if res.sign != Some(Sign::Always) {
+                    res.sign = Some(Sign::Space);
+                }
+            } else if take_char(&mut rest, '#') {
+                res.alternate = true;
+            } else if take_char(&mut rest, '0') {
+                res.zero = true;
+            } else {
+                break;
+            }
+        }
+        if res.align == Some(Align::Left) {
+            // Left alignment wins over zero padding.
+            res.zero = false;
+        }
+        res.width = take_number(spec, &mut rest)?.unwrap_or(0);
+        if take_char(&mut rest, '.') {
+            res.precision = Some(take_number(spec, &mut rest)?.unwrap_or(0));
+        }
+        if !rest.is_empty() {
+            return Err(FormatSpecError::Unexpected {
+                spec: spec.to_owned(),
+                rest: rest.to_owned(),
+            }
+            .into());
+        }
+        if !FORMAT_TYPES.contains(ty) {
+            return Err(FormatSpecError::UnknownType {
+                spec: format!("{}{}", spec, ty),
+                ty,
+            }
+            .into());
+        }
+        res.ty = Some(ty);
+        Ok(res)
+    }
+
+    fn not_allowed(&self, segment: impl Into<String>, what: impl Into<String>) -> anyhow::Error {
+        FormatSpecError::NotAllowed {
+            spec: self.spec.to_owned(),
+            segment: segment.into(),
+            what: what.into(),
+        }
+        .into()
+    }
+
+    fn type_mismatch(&self, ty: char, typ: &'static str) -> anyhow::Error {
+        FormatSpecError::TypeMismatch {
+            spec: self.spec.to_owned(),
+            ty,
+            typ,
+        }
+        .into()
+    }
+
+    fn sign_str(&self, negative: bool) -> &'static str {
+        match (negative, self.sign) {
+            (true, _) => "-",
+            (false, None | Some(Sign::Negative)) => "",
+            (false, Some(Sign::Always)) => "+",
+            (false, Some(Sign::Space)) => " ",
+        }
+    }
+
+    /// Write `prefix` and `body` padded to the width. The prefix is the sign and
+    /// base marker, which go before the padding with `=` alignment.
+    fn pad(&self, out: &mut String, prefix: &str, body: &str, default_align: Align) {
+        let fill = self.fill.unwrap_or(if self.zero { '0' } else { ' ' });
+        let align = self.align.unwrap_or(if self.zero {
+            Align::AfterSign
+        } else {
+            default_align
+        });
+        let len = prefix.chars().count() + body.chars().count();
+        let padding = self.width.saturating_sub(len);
+        let (before, after) = match align {
+            Align::Left => (0, padding),
+            Align::Right | Align::AfterSign => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        if align != Align::AfterSign {
+            out.extend(std::iter::repeat(fill).take(before));
+            out.push_str(prefix);
+        } else {
+            out.push_str(prefix);
+            out.extend(std::iter::repeat(fill).take(before));
+        }
+        out.push_str(body);
+        out.extend(std::iter::repeat(fill).take(after));
+    }
+
+    /// Width left for the integer digits when zero padding with grouping, given
+    /// the length of everything else that is written.
+    fn grouping_min_width(&self, other_len: usize) -> usize {
+        if self.zero && self.align.is_none() {
+            self.width.saturating_sub(other_len)
+        } else {
+            0
+        }
+    }
+
+    /// Format a value with this specifier. Numbers are formatted as numbers,
+    /// everything else by its `str()`.
+    pub(crate) fn format(&self, value: Value, out: &mut String) -> anyhow::Result<()> {
+        match value.unpack_num() {
+            Some(num) => self.format_num(num, out),
+            None => match self.ty {
+                None | Some('s') => self.format_str(&value.to_str(), out),
+                Some(ty) => Err(self.type_mismatch(ty, value.get_type())),
+            },
+        }
+    }
+
+    /// Format a number, converting integers to floats for the float format types.
+    pub(crate) fn format_num(&self, num: Num, out: &mut String) -> anyhow::Result<()> {
+        match (num, self.ty) {
+            (Num::Float(f), _) => self.format_float(f, out),
+            (_, Some('e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%')) => {
+                self.format_float(num.as_float(), out)
+            }
+            _ => self.format_int(num, out),
+        }
+    }
+
+    /// Format a string; only width, alignment, fill and precision apply.
+    pub(crate) fn format_str(&self, s: &str, out: &mut String) -> anyhow::Result<()> {
+        if let Some(ty) = self.ty {
+            if ty != 's' {
+                return Err(self.type_mismatch(ty, "string"));
+            }
+        }
+        // `%` interpolation ignores the numeric flags for strings, like Python.
+        if !self.percent {
+            if let Some(sign) = self.sign {
+                return Err(self.not_allowed(sign.as_char(), "strings"));
+            }
+            if self.alternate {
+                return Err(self.not_allowed("#", "strings"));
+            }
+        }
+        if let Some(grouping) = self.grouping {
+            return Err(self.not_allowed(grouping, "strings"));
+        }
+        if self.align == Some(Align::AfterSign) {
+            return Err(self.not_allowed("=", "strings"));
+        }
+        let s = match self.precision {
+            Some(precision) => match s.char_indices().nth(precision) {
+                Some((i, _)) => &s[..i],
+                None => s,
+            },
+            None => s,
+        };
+        // Strings are left aligned by `format`, but right aligned by `%` interpolation.
+        let default_align = if self.percent {
+            Align::Right
+        } else {
+            Align::Left
+        };
+        let spec = FormatSpec {
+            align: Some(self.align.unwrap_or(default_align)),
+            fill: self.fill.or(if self.zero && !self.percent {
+                Some('0')
+            } else {
+                None
+            }),
+            zero: false,
+            ..self.clone()
+        };
+        spec.pad(out, "", s, default_align);
+        Ok(())
+    }
+
+    fn format_int(&self, num: Num, out: &mut String) -> anyhow::Result<()> {
+        let ty = self.ty.unwrap_or('d');
+        let (base, prefix) = match ty {
+            'd' | 'n' => (10, ""),
+            'b' => (2, "0b"),
+            'o' => (8, "0o"),
+            'x' => (16, "0x"),
+            'X' => (16, "0X"),
+            'c' => return self.format_char(num, out),
+            _ => return Err(self.type_mismatch(ty, "int")),
+        };
+        if let (Some(precision), false) = (self.precision, self.percent) {
+            return Err(self.not_allowed(format!(".{}", precision), "integers"));
+        }
+        let (negative, mut digits) = match num {
+            Num::Int(i) => (i < 0, {
+                let abs = (i as i64).unsigned_abs();
+                match base {
+                    2 => format!("{:b}", abs),
+                    8 => format!("{:o}", abs),
+                    16 => format!("{:x}", abs),
+                    _ => abs.to_string(),
+                }
+            }),
+            Num::BigInt(b) => (
+                b.get().is_negative(),
+                b.get().magnitude().to_str_radix(base),
+            ),
+            Num::Float(_) => unreachable!("floats are formatted by format_float"),
+        };
+        if ty == 'X' {
+            digits.make_ascii_uppercase();
+        }
+        if let Some(precision) = self.precision {
+            // `%.3d` means at least three digits.
+            if digits.len() < precision {
+                digits.insert_str(0, &"0".repeat(precision - digits.len()));
+            }
+        }
+        let prefix = format!(
+            "{}{}",
+            self.sign_str(negative),
+            if self.alternate { prefix } else { "" }
+        );
+        let body = match self.grouping {
+            None => digits,
+            Some(',') if ty != 'd' => {
+                return Err(self.not_allowed(",", format!("format type `{}`", ty)))
+            }
+            Some(sep) if ty == 'n' => return Err(self.not_allowed(sep, "format type `n`")),
+            Some(sep) => group_digits(
+                &digits,
+                sep,
+                if base == 10 { 3 } else { 4 },
+                self.grouping_min_width(prefix.len()),
+            ),
+        };
+        self.pad(out, &prefix, &body, Align::Right);
+        Ok(())
+    }
+
+    fn format_char(&self, num: Num, out: &mut String) -> anyhow::Result<()> {
+        if let Some(sign) = self.sign {
+            return Err(self.not_allowed(sign.as_char(), "format type `c`"));
+        }
+        if self.alternate {
+            return Err(self.not_allowed("#", "format type `c`"));
+        }
+        let c = match num {
+            Num::Int(i) => u32::try_from(i).ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match c {
+            Some(c) => {
+                self.pad(out, "", c.encode_utf8(&mut [0; 4]), Align::Right);
+                Ok(())
+            }
+            None => Err(FormatSpecError::InvalidChar {
+                spec: self.spec.to_owned(),
+                value: match num {
+                    Num::Int(i) => i.to_string(),
+                    Num::BigInt(b) => b.get().to_string(),
+                    Num::Float(f) => f.to_string(),
+                },
+            }
+            .into()),
+        }
+    }
+
+    fn format_float(&self, f: f64, out: &mut String) -> anyhow::Result<()> {
+        let ty = self.ty;
+        match ty {
+            None | Some('e' | 'E' | 'f' | 'F' | 'g' | 'G' | 'n' | '%') => {}
+            Some(ty) => return Err(self.type_mismatch(ty, "float")),
+        }
+        if let (Some(sep), Some('n')) = (self.grouping, ty) {
+            return Err(self.not_allowed(sep, "format type `n`"));
+        }
+
+        if !f.is_finite() {
+            // Infinities are written `+inf` and `-inf` in Starlark, like `str()` does.
+            let prefix = if f.is_nan() {
+                self.sign_str(false)
+            } else if f.is_sign_negative() {
+                "-"
+            } else if self.sign == Some(Sign::Space) {
+                " "
+            } else {
+                "+"
+            };
+            let spec = FormatSpec {
+                zero: false,
+                ..self.clone()
+            };
+            spec.pad(
+                out,
+                prefix,
+                if f.is_nan() { "nan" } else { "inf" },
+                Align::Right,
+            );
+            return Ok(());
+        }
+
+        let negative = f.is_sign_negative();
+        let abs = f.abs();
+        let mut suffix = "";
+        let body = match ty {
+            Some('f' | 'F') => self.fixed(abs, self.precision.unwrap_or(6)),
+            Some('%') => {
+                suffix = "%";
+                self.fixed(abs * 100.0, self.precision.unwrap_or(6))
+            }
+            Some(c @ ('e' | 'E')) => {
+                scientific(abs, self.precision.unwrap_or(6), c, self.alternate)
+            }
+            Some(c @ ('g' | 'G')) if self.precision.is_some() || self.alternate => self.general(
+                abs,
+                self.precision.unwrap_or(6),
+                if c == 'G' { 'E' } else { 'e' },
+                false,
+            ),
+            Some('n') => self.general(abs, self.precision.unwrap_or(6), 'e', false),
+            None if self.precision.is_some() => {
+                self.general(abs, self.precision.unwrap(), 'e', true)
+            }
+            _ => {
+                // Without a precision, `g` and no format type are the same as `str()`.
+                let mut s = String::new();
+                let exponent_char = if ty == Some('G') { 'E' } else { 'e' };
+                float::write_compact(&mut s, abs, exponent_char).unwrap();
+                s
+            }
+        };
+
+        let prefix = self.sign_str(negative);
+        let body = match self.grouping {
+            None => body,
+            Some(sep) => {
+                let int_len = body.bytes().take_while(|b| b.is_ascii_digit()).count();
+                let (int_part, rest) = body.split_at(int_len);
+                let min_width = self.grouping_min_width(prefix.len() + rest.len() + suffix.len());
+                format!("{}{}", group_digits(int_part, sep, 3, min_width), rest)
+            }
+        };
+        let body = if suffix.is_empty() {
+            body
+        } else {
+            format!("{}{}", body, suffix)
+        };
+        self.pad(out, prefix, &body, Align::Right);
+        Ok(())
+    }
+
+    /// Fixed point notation with `precision` digits after the point.
+    fn fixed(&self, x: f64, precision: usize) -> String {
+        let mut s = format!("{:.*}", precision, x);
+        if self.alternate && precision == 0 {
+            s.push('.');
+        }
+        s
+    }
+
+    /// The `g` format: `precision` significant digits, in fixed point notation
+    /// unless the exponent is too small or too large. `keep_point` makes fixed
+    /// point results always have a digit after the point, as Python does when
+    /// there is no format type.
+    fn general(&self, x: f64, precision: usize, exponent_char: char, keep_point: bool) -> String {
+        let precision = precision.max(1);
+        let exponent = if x == 0.0 {
+            0
+        } else {
+            // Format first so rounding is taken into account, e.g. 9.99 with precision 2 is `10`.
+            let s = format!("{:.*e}", precision - 1, x);
+            i32::from_str(s.split_once('e').unwrap().1).unwrap()
+        };
+        if -4 <= exponent && exponent < precision as i32 {
+            let s = format!("{:.*}", (precision as i32 - 1 - exponent) as usize, x);
+            let mut s = if self.alternate {
+                s
+            } else {
+                strip_fraction_zeros(&s).to_owned()
+            };
+            if keep_point && !s.contains('.') {
+                s.push_str(".0");
+            }
+            s
+        } else {
+            let s = scientific(x, precision - 1, exponent_char, self.alternate);
+            if self.alternate {
+                s
+            } else {
+                let (mantissa, exponent) = s.split_at(s.find(exponent_char).unwrap());
+                let mut res = strip_fraction_zeros(mantissa).to_owned();
+                write!(res, "{}", exponent).unwrap();
+                res
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_format_spec_int() {
+        assert::all_true(
+            r#"
+"{:5}".format(42) == "   42"
+"{:<5}|".format(42) == "42   |"
+"{:^6}".format(42) == "  42  "
+"{:*^7}".format(42) == "**42***"
+"{:05}".format(-42) == "-0042"
+"{:=+6}".format(42) == "+   42"
+"{: }".format(42) == " 42"
+"{:#x}".format(255) == "0xff"
+"{:#X}".format(255) == "0XFF"
+"{:#b}".format(5) == "0b101"
+"{:o}".format(-8) == "-10"
+"{:,}".format(1234567) == "1,234,567"
+"{:_}".format(-1234567) == "-1_234_567"
+"{:_x}".format(0xdeadbeef) == "dead_beef"
+"{:08,}".format(1234) == "0,001,234"
+"{:,}".format(12345678901234567890) == "12,345,678,901,234,567,890"
+"{:x}".format(-12345678901234567890) == "-ab54a98ceb1f0ad2"
+"{:c}".format(65) == "A"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_format_spec_float() {
+        assert::all_true(
+            r#"
+"{:>10.3f}".format(3.14159) == "     3.142"
+"{:.2f}".format(2) == "2.00"
+"{:+.1f}".format(1.25) == "+1.2"
+"{:.0f}".format(2.5) == "2"
+"{:#.0f}".format(3.0) == "3."
+"{:,.2f}".format(1234567.891) == "1,234,567.89"
+"{:010.2f}".format(-3.14159) == "-000003.14"
+"{:e}".format(1234.5) == "1.234500e+03"
+"{:.2E}".format(0.000123) == "1.23E-04"
+"{:.3g}".format(1234.5) == "1.23e+03"
+"{:.3g}".format(0.0001234) == "0.000123"
+"{:.3g}".format(2.0) == "2"
+"{:#.3g}".format(2.0) == "2.00"
+"{:.3}".format(2.0) == "2.0"
+"{:g}".format(1e6) == "1e+06"
+"{:.1%}".format(0.256) == "25.6%"
+"{}".format(1.5) == "1.5"
+"{:6}".format(1.5) == "   1.5"
+"{:6}".format(float("inf")) == "  +inf"
+"{:06}".format(float("-inf")) == "  -inf"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_format_spec_str() {
+        assert::all_true(
+            r#"
+"{:5}|".format("ab") == "ab   |"
+"{:>5}".format("ab") == "   ab"
+"{:^5}".format("ab") == " ab  "
+"{:.2}".format("abcd") == "ab"
+"{:-^7.2}".format("abcd") == "--ab---"
+"{!r:>6}".format("ab") == "  \"ab\""
+"{:6}|".format([1]) == "[1]   |"
+"{:>4}".format(True) == "True"
+"{0:>3}{0:<3}".format(1) == "  11  "
+"{x:>3}".format(x = 1) == "  1"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_format_spec_errors() {
+        assert::fail(
+            r#""{:>10.3q}".format(1.0)"#,
+            "Invalid format specifier `>10.3q`: unknown format type `q`",
+        );
+        assert::fail(
+            r#""{:5.3fx}".format(1.0)"#,
+            "Invalid format specifier `5.3fx`: unexpected `x`",
+        );
+        assert::fail(
+            r#""{:.f}".format(1.0)"#,
+            "Invalid format specifier `.f`: missing precision",
+        );
+        assert::fail(
+            r#""{:d}".format(1.0)"#,
+            "Invalid format specifier `d`: format type `d` is not valid for type `float`",
+        );
+        assert::fail(
+            r#""{:f}".format("x")"#,
+            "format type `f` is not valid for type `string`",
+        );
+        assert::fail(r#""{:.2}".format(1)"#, "`.2` is not allowed for integers");
+        assert::fail(
+            r#""{:,x}".format(1)"#,
+            "`,` is not allowed for format type `x`",
+        );
+        assert::fail(r#""{:+}".format("x")"#, "`+` is not allowed for strings");
+        assert::fail(r#""{:99999999999}".format(1)"#, "`99999999999` is too big");
+        assert::fail(r#""{:2000000000}".format(1)"#, "`2000000000` is too big");
+        assert::fail(r#""{:.20000f}".format(1.0)"#, "`20000` is too big");
+        assert::fail(r#"'%20000d' % 1"#, "`20000` is too big");
+    }
+
+    #[test]
+    fn test_percent_flags() {
+        assert::all_true(
+            r#"
+"%5d" % 42 == "   42"
+"%-5d|" % 42 == "42   |"
+"%05d" % -42 == "-0042"
+"%+d" % 42 == "+42"
+"% d" % 42 == " 42"
+"%.3d" % 7 == "007"
+"%#x" % 255 == "0xff"
+"%#o" % 8 == "0o10"
+"%8.3f" % 3.14159 == "   3.142"
+"%-8.2e|" % 1234.5 == "1.23e+03|"
+"%.3g" % 1234.5 == "1.23e+03"
+"%5s|" % "ab" == "   ab|"
+"%-5s|" % "ab" == "ab   |"
+"%.1s" % "ab" == "a"
+"%6r" % "ab" == "  \"ab\""
+"%5.1f%%" % 99.44 == " 99.4%"
+"%c" % "x" == "x"
+"%3c|" % "x" == "  x|"
+"%-3c|" % 120 == "x  |"
+"%c" % "é" == "é"
+"#,
+        );
+        assert::fail(
+            r#""%5c" % "xy""#,
+            "`%c` requires an int or a single character string, got \"xy\"",
+        );
+        assert::fail(r#""%c" % 1.5"#, "`%c` requires an int");
+        assert::fail(r#""%5b" % 1"#, "Unsupported format `%5b`");
+        assert::fail(
+            r#""%5q" % 1"#,
+            "Invalid format specifier `5q`: unknown format type `q`",
+        );
+        assert::fail(
+            r#""%5.2.1f" % 1"#,
+            "Invalid format specifier `5.2.1`: unexpected `.1`",
+        );
+    }
+}
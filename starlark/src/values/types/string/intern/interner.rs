@@ -17,6 +17,10 @@
 
 //! Generic interner for starlark strings.
 
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+
 use hashbrown::raw::RawTable;
 
 use crate::collections::Hashed;
@@ -28,7 +32,22 @@ pub(crate) struct FrozenStringInterner {
     map: RawTable<FrozenStringValue>,
 }
 
+impl Debug for FrozenStringInterner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrozenStringInterner")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
 impl FrozenStringInterner {
+    /// Find a previously interned string.
+    pub(crate) fn get(&self, s: Hashed<&str>) -> Option<FrozenStringValue> {
+        self.map
+            .get(s.hash().promote(), |x| s == x.get_hashed_str())
+            .copied()
+    }
+
     pub(crate) fn intern(
         &mut self,
         s: Hashed<&str>,
@@ -20,17 +20,20 @@
 
 use std::fmt::Write;
 use std::mem;
+use std::str;
 use std::str::FromStr;
 
-use dupe::Dupe;
 use gazebo::cast;
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
 use thiserror::Error;
 
 use crate::collections::string_pool::StringPool;
 use crate::values::dict::Dict;
 use crate::values::float;
-use crate::values::num;
 use crate::values::num::Num;
+use crate::values::string::format_spec::FormatSpec;
+use crate::values::types::bigint::StarlarkBigInt;
 use crate::values::types::tuple::value::Tuple;
 use crate::values::Heap;
 use crate::values::StringValue;
@@ -40,7 +43,7 @@ use crate::values::ValueError;
 use crate::values::ValueLike;
 
 /// Operator `%` format or evaluation errors
-#[derive(Clone, Dupe, Debug, Error)]
+#[derive(Clone, Debug, Error)]
 enum StringInterpolationError {
     /// Interpolation parameter is too big for the format string.
     #[error("Too many arguments for format string")]
@@ -48,6 +51,138 @@ enum StringInterpolationError {
     /// Interpolation parameter is too small for the format string.
     #[error("Not enough arguments for format string")]
     NotEnoughParameters,
+    /// The format string ends in the middle of a conversion.
+    #[error("Incomplete format `%{0}`")]
+    IncompleteFormat(String),
+    /// `%c` got something other than a code point or a single character.
+    #[error("`%c` requires an int or a single character string, got {0}")]
+    NotAChar(String),
+    /// A conversion `.format` supports, but `%` interpolation doesn't, e.g. `%5b`.
+    #[error("Unsupported format `%{0}`")]
+    UnsupportedConversion(String),
+}
+
+/// Bytes which can follow `%` before the conversion character: flags, width and precision.
+const PERCENT_SPEC_BYTES: &[u8] = b"#0- +123456789.";
+
+/// Format one `%` conversion with flags, width or precision, e.g. `%-10.3f`.
+/// `format` points after the `%` and is advanced past the conversion character.
+fn percent_with_spec<'v>(
+    format: &mut std::slice::Iter<u8>,
+    mut next_value: impl FnMut() -> anyhow::Result<Value<'v>>,
+    out: &mut String,
+) -> anyhow::Result<()> {
+    let rest = format.as_slice();
+    let spec_len = rest
+        .iter()
+        .take_while(|c| PERCENT_SPEC_BYTES.contains(c))
+        .count();
+    let (spec, rest) = rest.split_at(spec_len);
+    let spec = str::from_utf8(spec)?;
+    // Only decode the conversion character, not the whole remaining format string.
+    let first = &rest[..rest.len().min(4)];
+    let first = match str::from_utf8(first) {
+        Ok(first) => first,
+        Err(e) => str::from_utf8(&first[..e.valid_up_to()])?,
+    };
+    let conversion = first.chars().next();
+    let conversion = match conversion {
+        Some(c) => c,
+        None => return Err(StringInterpolationError::IncompleteFormat(spec.to_owned()).into()),
+    };
+    for _ in 0..spec_len + conversion.len_utf8() {
+        format.next();
+    }
+
+    if conversion == '%' {
+        out.push('%');
+        return Ok(());
+    }
+    let value = next_value()?;
+    match conversion {
+        's' => FormatSpec::parse_percent(spec, 's')?.format_str(&value.to_str(), out),
+        'r' => FormatSpec::parse_percent(spec, 's')?.format_str(&value.to_repr(), out),
+        'd' => {
+            let spec = FormatSpec::parse_percent(spec, 'd')?;
+            match value.unpack_num() {
+                Some(Num::Float(v)) => match trunc_float(v)? {
+                    Ok(v) => spec.format_num(Num::BigInt(&v), out),
+                    Err(v) => spec.format_num(Num::Int(v), out),
+                },
+                Some(v) => spec.format_num(v, out),
+                None => spec.format_num(Num::Int(value.to_int()?), out),
+            }
+        }
+        'o' | 'x' | 'X' => {
+            let spec = FormatSpec::parse_percent(spec, conversion)?;
+            match value.unpack_num() {
+                Some(v @ (Num::Int(_) | Num::BigInt(_))) => spec.format_num(v, out),
+                _ => spec.format_num(Num::Int(value.to_int()?), out),
+            }
+        }
+        'c' => percent_char(spec, value, out),
+        'e' | 'E' | 'f' | 'F' | 'g' | 'G' => {
+            let spec = FormatSpec::parse_percent(spec, conversion)?;
+            spec.format_num(Num::Float(Num::unpack_param(value)?.as_float()), out)
+        }
+        c => {
+            // Reject unknown conversions with the format spec error.
+            FormatSpec::parse_percent(spec, c)?;
+            Err(StringInterpolationError::UnsupportedConversion(format!("{}{}", spec, c)).into())
+        }
+    }
+}
+
+/// Write a single character string, or the character with an int's code point, for `%c`.
+fn percent_char(spec: &str, value: Value, out: &mut String) -> anyhow::Result<()> {
+    if let Some(s) = value.unpack_str() {
+        let mut chars = s.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(_), None) => FormatSpec::parse_percent(spec, 's')?.format_str(s, out),
+            _ => Err(StringInterpolationError::NotAChar(value.to_repr()).into()),
+        };
+    }
+    match value.unpack_num() {
+        Some(Num::Float(_)) | None => {
+            Err(StringInterpolationError::NotAChar(value.to_repr()).into())
+        }
+        Some(v) => FormatSpec::parse_percent(spec, 'c')?.format_num(v, out),
+    }
+}
+
+/// The integer part of a float for `%d`, as a big int if it is out of the `i32` range,
+/// or an error for NaN and infinity.
+fn trunc_float(v: f64) -> anyhow::Result<Result<StarlarkBigInt, i32>> {
+    if let Some(i) = Num::Float(v.trunc()).as_int() {
+        return Ok(Err(i));
+    }
+    match BigInt::from_f64(v.trunc()) {
+        Some(x) => Ok(StarlarkBigInt::try_from_bigint(x)),
+        None => ValueError::unsupported(&float::StarlarkFloat(v), "%d"),
+    }
+}
+
+/// Write an int for `%o`, `%x` or `%X`, with a `-` sign for negative numbers.
+fn percent_radix(value: Value, conversion: u8, out: &mut String) -> anyhow::Result<()> {
+    if let Some(Num::BigInt(v)) = value.unpack_num() {
+        match conversion {
+            b'o' => write!(out, "{:o}", v.get()),
+            b'x' => write!(out, "{:x}", v.get()),
+            _ => write!(out, "{:X}", v.get()),
+        }
+        .unwrap();
+        return Ok(());
+    }
+    let v = value.to_int()?;
+    let sign = if v < 0 { "-" } else { "" };
+    let v = v.unsigned_abs();
+    match conversion {
+        b'o' => write!(out, "{}{:o}", sign, v),
+        b'x' => write!(out, "{}{:x}", sign, v),
+        _ => write!(out, "{}{:X}", sign, v),
+    }
+    .unwrap();
+    Ok(())
 }
 
 pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
@@ -77,10 +212,17 @@ pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
     };
 
     // because of the way format is defined, we can deal with it as bytes
-    let mut format = format.as_bytes().iter().copied();
-    while let Some(c) = format.next() {
+    let mut format = format.as_bytes().iter();
+    while let Some(&c) = format.next() {
         if c == b'%' {
-            if let Some(c) = format.next() {
+            if format
+                .as_slice()
+                .first()
+                .map_or(false, |c| PERCENT_SPEC_BYTES.contains(c))
+            {
+                let out: &mut String = unsafe { cast::ptr_mut(&mut res) };
+                percent_with_spec(&mut format, &mut next_value, out)?;
+            } else if let Some(&c) = format.next() {
                 let out: &mut String = unsafe { cast::ptr_mut(&mut res) };
                 match c {
                     b'%' => res.push(b'%'),
@@ -94,47 +236,17 @@ pub(crate) fn percent(format: &str, value: Value) -> anyhow::Result<String> {
                     b'r' => next_value()?.collect_repr(out),
                     b'd' => {
                         let value = next_value()?;
-                        if let Some(num::Num::Float(v)) = value.unpack_num() {
-                            match num::Num::Float(v.trunc()).as_int() {
-                                None => {
-                                    return ValueError::unsupported(&float::StarlarkFloat(v), "%d");
-                                }
-                                Some(v) => write!(out, "{}", v).unwrap(),
-                            }
-                        } else {
-                            write!(out, "{}", value.to_int()?).unwrap()
+                        match value.unpack_num() {
+                            Some(Num::Float(v)) => match trunc_float(v)? {
+                                Ok(v) => write!(out, "{}", v.get()).unwrap(),
+                                Err(v) => write!(out, "{}", v).unwrap(),
+                            },
+                            Some(Num::BigInt(v)) => write!(out, "{}", v.get()).unwrap(),
+                            _ => write!(out, "{}", value.to_int()?).unwrap(),
                         }
                     }
-                    b'o' => {
-                        let v = next_value()?.to_int()?;
-                        write!(
-                            out,
-                            "{}{:o}",
-                            if v < 0 { "-" } else { "" },
-                            v.wrapping_abs() as u64
-                        )
-                        .unwrap();
-                    }
-                    b'x' => {
-                        let v = next_value()?.to_int()?;
-                        write!(
-                            out,
-                            "{}{:x}",
-                            if v < 0 { "-" } else { "" },
-                            v.wrapping_abs() as u64
-                        )
-                        .unwrap();
-                    }
-                    b'X' => {
-                        let v = next_value()?.to_int()?;
-                        write!(
-                            out,
-                            "{}{:X}",
-                            if v < 0 { "-" } else { "" },
-                            v.wrapping_abs() as u64
-                        )
-                        .unwrap()
-                    }
+                    c @ (b'o' | b'x' | b'X') => percent_radix(next_value()?, c, out)?,
+                    b'c' => percent_char("", next_value()?, out)?,
                     b'e' => {
                         let v = Num::unpack_param(next_value()?)?.as_float();
                         float::write_scientific(out, v, 'e', false).unwrap()
@@ -432,22 +544,28 @@ pub(crate) fn format<'v>(
 fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
     capture: &str,
     args: &mut FormatArgs<'v, T>,
-    kwargs: &Dict,
+    kwargs: &Dict<'v>,
     result: &mut String,
 ) -> anyhow::Result<()> {
-    let (n, conv) = {
-        if let Some((n, conv)) = capture.split_once('!') {
-            (n, conv)
-        } else {
-            (capture, "s")
-        }
+    // `{field!conv:spec}`, where the field name ends at the first `!` or `:`.
+    let (n, rest) = match capture.find(['!', ':']) {
+        Some(i) => capture.split_at(i),
+        None => (capture, ""),
+    };
+    let (conv, spec) = match rest.strip_prefix('!') {
+        Some(rest) => match rest.split_once(':') {
+            Some((conv, spec)) => (Some(conv), spec),
+            None => (Some(rest), ""),
+        },
+        None => (None, rest.strip_prefix(':').unwrap_or("")),
     };
     let conv_s = |x: Value, result: &mut String| x.collect_str(result);
     let conv_r = |x: Value, result: &mut String| x.collect_repr(result);
-    let conv: &dyn Fn(Value, &mut String) = match conv {
-        "s" => &conv_s,
-        "r" => &conv_r,
-        c => {
+    let conv: Option<&dyn Fn(Value, &mut String)> = match conv {
+        None => None,
+        Some("s") => Some(&conv_s),
+        Some("r") => Some(&conv_r),
+        Some(c) => {
             return Err(anyhow::anyhow!(
                 concat!(
                     "'{}' is not a valid format string specifier, only ",
@@ -457,13 +575,11 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
             ));
         }
     };
-    if n.is_empty() {
-        conv(args.next_ordered()?, result);
-        Ok(())
+    let value = if n.is_empty() {
+        args.next_ordered()?
     } else if n.chars().all(|c| c.is_ascii_digit()) {
         let i = usize::from_str(n).unwrap();
-        conv(args.by_index(i)?, result);
-        Ok(())
+        args.by_index(i)?
     } else {
         if let Some(x) = n.chars().find(|c| match c {
             '.' | ',' | '[' | ']' => true,
@@ -475,11 +591,23 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
             ));
         }
         match kwargs.get_str(n) {
-            None => Err(ValueError::KeyNotFound(n.to_owned()).into()),
-            Some(v) => {
-                conv(v, result);
-                Ok(())
+            None => return Err(ValueError::KeyNotFound(n.to_owned()).into()),
+            Some(v) => v,
+        }
+    };
+    if spec.is_empty() {
+        conv.unwrap_or(&conv_s)(value, result);
+        Ok(())
+    } else {
+        let spec = FormatSpec::parse(spec)?;
+        match conv {
+            // An explicit conversion turns the value into a string before applying the spec.
+            Some(conv) => {
+                let mut converted = String::new();
+                conv(value, &mut converted);
+                spec.format_str(&converted, result)
             }
+            None => spec.format(value, result),
         }
     }
 }
@@ -495,7 +623,7 @@ mod tests {
     fn format_capture_for_test<'v, T: Iterator<Item = Value<'v>>>(
         capture: &str,
         args: &mut FormatArgs<'v, T>,
-        kwargs: &Dict,
+        kwargs: &Dict<'v>,
     ) -> anyhow::Result<String> {
         let mut result = String::new();
         super::format_capture(capture, args, kwargs, &mut result)?;
@@ -28,6 +28,7 @@ use std::mem;
 use std::ops::Add;
 use std::ops::Deref;
 use std::ops::Sub;
+use std::ptr;
 use std::slice;
 use std::str;
 use std::sync::atomic;
@@ -57,6 +58,7 @@ use crate::values::ValueError;
 
 mod alloc_unpack;
 pub(crate) mod fast_string;
+mod format_spec;
 pub(crate) mod intern;
 pub(crate) mod interpolation;
 pub(crate) mod iter;
@@ -364,11 +366,22 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
 
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         let l = i32::unpack_param(other)?;
-        let mut result = String::with_capacity(self.len() * cmp::max(0, l) as usize);
-        for _i in 0..l {
-            result.push_str(self)
+        let n = cmp::max(0, l) as usize;
+        // Starlark values must be smaller than 4 GiB.
+        let len = match self.len().checked_mul(n) {
+            Some(len) if len < u32::MAX as usize / 2 => len,
+            _ => return Err(ValueError::IntegerOverflow.into()),
+        };
+        if len <= 1 {
+            return Ok(heap.alloc(self.as_str().repeat(n)));
         }
-        Ok(heap.alloc(result))
+        // Write directly into the heap, checking the heap limit before copying anything.
+        let s = heap.try_alloc_str_init(len, |dest| unsafe {
+            for i in 0..n {
+                ptr::copy_nonoverlapping(self.as_ptr(), dest.add(i * self.len()), self.len());
+            }
+        })?;
+        Ok(s.to_value())
     }
 
     fn percent(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
@@ -27,6 +27,7 @@ use serde::Serialize;
 use starlark_map::small_map::SmallMap;
 use starlark_map::Hashed;
 use starlark_map::StarlarkHasher;
+use thiserror::Error;
 
 use crate as starlark;
 use crate::any::ProvidesStaticType;
@@ -46,6 +47,12 @@ use crate::values::Value;
 use crate::values::ValueError;
 use crate::values::ValueLike;
 
+#[derive(Debug, Error)]
+pub(crate) enum StructError {
+    #[error("struct has no field `{0}`")]
+    UnknownField(String),
+}
+
 impl<'v, V: ValueLike<'v>> StructGen<'v, V> {
     /// The result of calling `type()` on a struct.
     pub(crate) const TYPE: &'static str = "struct";
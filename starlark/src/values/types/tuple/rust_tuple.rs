@@ -24,72 +24,154 @@ use crate::values::Heap;
 use crate::values::UnpackValue;
 use crate::values::Value;
 
-impl<'v, T1: AllocValue<'v>> AllocValue<'v> for (T1,) {
-    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
-        heap.alloc_tuple(&[self.0.alloc_value(heap)])
+/// Format the elements of a tuple the way Starlark writes it, e.g. `(a,)` or `(a, b)`.
+fn tuple_repr(xs: &[String]) -> String {
+    if xs.len() == 1 {
+        format!("({},)", xs[0])
+    } else {
+        format!("({})", xs.join(", "))
     }
 }
 
-impl<'v, T1: AllocValue<'v>, T2: AllocValue<'v>> AllocValue<'v> for (T1, T2) {
-    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
-        heap.alloc_tuple(&[self.0.alloc_value(heap), self.1.alloc_value(heap)])
-    }
-}
+macro_rules! rust_tuple_impls {
+    ($(($t:ident, $v:ident, $i:tt)),+) => {
+        impl<'v, $($t: AllocValue<'v>),+> AllocValue<'v> for ($($t,)+) {
+            fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+                heap.alloc_tuple(&[$(self.$i.alloc_value(heap)),+])
+            }
+        }
 
-impl<'v, T1: AllocValue<'v>, T2: AllocValue<'v>, T3: AllocValue<'v>> AllocValue<'v>
-    for (T1, T2, T3)
-{
-    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
-        heap.alloc_tuple(&[
-            self.0.alloc_value(heap),
-            self.1.alloc_value(heap),
-            self.2.alloc_value(heap),
-        ])
-    }
-}
+        impl<$($t: StarlarkTypeRepr),+> StarlarkTypeRepr for ($($t,)+) {
+            fn starlark_type_repr() -> String {
+                tuple_repr(&[$($t::starlark_type_repr()),+])
+            }
+        }
 
-impl<T1: StarlarkTypeRepr, T2: StarlarkTypeRepr> StarlarkTypeRepr for (T1, T2) {
-    fn starlark_type_repr() -> String {
-        format!(
-            "({}, {})",
-            T1::starlark_type_repr(),
-            T2::starlark_type_repr()
-        )
-    }
-}
+        impl<'v, $($t: UnpackValue<'v>),+> UnpackValue<'v> for ($($t,)+) {
+            fn expected() -> String {
+                format!("tuple {}", tuple_repr(&[$($t::expected()),+]))
+            }
 
-impl<T1: StarlarkTypeRepr> StarlarkTypeRepr for (T1,) {
-    fn starlark_type_repr() -> String {
-        format!("({},)", T1::starlark_type_repr())
-    }
+            fn unpack_value(value: Value<'v>) -> Option<Self> {
+                match Tuple::from_value(value)?.content() {
+                    [$($v),+] => Some(($($t::unpack_value(*$v)?,)+)),
+                    _ => None,
+                }
+            }
+        }
+    };
 }
 
-impl<T1: StarlarkTypeRepr, T2: StarlarkTypeRepr, T3: StarlarkTypeRepr> StarlarkTypeRepr
-    for (T1, T2, T3)
-{
-    fn starlark_type_repr() -> String {
-        format!(
-            "({}, {}, {})",
-            T1::starlark_type_repr(),
-            T2::starlark_type_repr(),
-            T3::starlark_type_repr()
-        )
-    }
-}
+rust_tuple_impls!((T1, v1, 0));
+rust_tuple_impls!((T1, v1, 0), (T2, v2, 1));
+rust_tuple_impls!((T1, v1, 0), (T2, v2, 1), (T3, v3, 2));
+rust_tuple_impls!((T1, v1, 0), (T2, v2, 1), (T3, v3, 2), (T4, v4, 3));
+rust_tuple_impls!(
+    (T1, v1, 0),
+    (T2, v2, 1),
+    (T3, v3, 2),
+    (T4, v4, 3),
+    (T5, v5, 4)
+);
+rust_tuple_impls!(
+    (T1, v1, 0),
+    (T2, v2, 1),
+    (T3, v3, 2),
+    (T4, v4, 3),
+    (T5, v5, 4),
+    (T6, v6, 5)
+);
+rust_tuple_impls!(
+    (T1, v1, 0),
+    (T2, v2, 1),
+    (T3, v3, 2),
+    (T4, v4, 3),
+    (T5, v5, 4),
+    (T6, v6, 5),
+    (T7, v7, 6)
+);
+rust_tuple_impls!(
+    (T1, v1, 0),
+    (T2, v2, 1),
+    (T3, v3, 2),
+    (T4, v4, 3),
+    (T5, v5, 4),
+    (T6, v6, 5),
+    (T7, v7, 6),
+    (T8, v8, 7)
+);
+rust_tuple_impls!(
+    (T1, v1, 0),
+    (T2, v2, 1),
+    (T3, v3, 2),
+    (T4, v4, 3),
+    (T5, v5, 4),
+    (T6, v6, 5),
+    (T7, v7, 6),
+    (T8, v8, 7),
+    (T9, v9, 8)
+);
+rust_tuple_impls!(
+    (T1, v1, 0),
+    (T2, v2, 1),
+    (T3, v3, 2),
+    (T4, v4, 3),
+    (T5, v5, 4),
+    (T6, v6, 5),
+    (T7, v7, 6),
+    (T8, v8, 7),
+    (T9, v9, 8),
+    (T10, v10, 9)
+);
+rust_tuple_impls!(
+    (T1, v1, 0),
+    (T2, v2, 1),
+    (T3, v3, 2),
+    (T4, v4, 3),
+    (T5, v5, 4),
+    (T6, v6, 5),
+    (T7, v7, 6),
+    (T8, v8, 7),
+    (T9, v9, 8),
+    (T10, v10, 9),
+    (T11, v11, 10)
+);
+rust_tuple_impls!(
+    (T1, v1, 0),
+    (T2, v2, 1),
+    (T3, v3, 2),
+    (T4, v4, 3),
+    (T5, v5, 4),
+    (T6, v6, 5),
+    (T7, v7, 6),
+    (T8, v8, 7),
+    (T9, v9, 8),
+    (T10, v10, 9),
+    (T11, v11, 10),
+    (T12, v12, 11)
+);
 
-impl<'v, T1: UnpackValue<'v>, T2: UnpackValue<'v>> UnpackValue<'v> for (T1, T2) {
-    fn expected() -> String {
-        format!("tuple ({}, {})", T1::expected(), T2::expected())
-    }
+#[cfg(test)]
+mod tests {
+    use crate::values::type_repr::StarlarkTypeRepr;
+    use crate::values::Heap;
+    use crate::values::UnpackValue;
 
-    fn unpack_value(value: Value<'v>) -> Option<Self> {
-        let t = Tuple::from_value(value)?;
-        if t.len() != 2 {
-            return None;
-        }
-        Some((
-            T1::unpack_value(t.content()[0])?,
-            T2::unpack_value(t.content()[1])?,
-        ))
+    #[test]
+    fn test_rust_tuple() {
+        let heap = Heap::new();
+        let x = heap.alloc((1, "a", true, None::<i32>, 5, 6, 7, 8, 9, 10, 11, (12,)));
+        assert_eq!(
+            x.to_repr(),
+            r#"(1, "a", True, None, 5, 6, 7, 8, 9, 10, 11, (12,))"#
+        );
+        type T = (i32, &'static str, bool);
+        assert_eq!(T::starlark_type_repr(), "(int.type, str.type, bool.type)");
+        assert_eq!(<(i32,)>::starlark_type_repr(), "(int.type,)");
+        assert_eq!(T::expected(), "tuple (int.type, str, bool.type)");
+        let x = heap.alloc((1, "a", true));
+        assert_eq!(<(i32, &str, bool)>::unpack_value(x), Some((1, "a", true)));
+        assert_eq!(<(i32, &str)>::unpack_value(x), None);
+        assert_eq!(<(i32, i32, bool)>::unpack_value(x), None);
     }
 }
@@ -0,0 +1,191 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The values of type annotations written like `list[str]` or `int | None`.
+//!
+//! Indexing one of the type functions `list`, `dict` or `tuple`, or combining types
+//! with `|`, produces a [`TypeExpr`], which
+//! [`TypeCompiled`](crate::values::typing::TypeCompiled) turns into a runtime check.
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use thiserror::Error;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::Coerce;
+use crate::values::function::NativeFunction;
+use crate::values::tuple::TupleRef;
+use crate::values::typing::TypeCompiled;
+use crate::values::Freeze;
+use crate::values::Heap;
+use crate::values::NoSerialize;
+use crate::values::StarlarkValue;
+use crate::values::Trace;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::ValueLike;
+
+#[derive(Debug, Error)]
+enum TypeExprError {
+    #[error("`{0}[]` expects {1} type arguments, but got {2}")]
+    WrongArgumentCount(&'static str, &'static str, usize),
+}
+
+/// What a [`TypeExpr`] combines its arguments into.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Allocative)]
+pub(crate) enum TypeExprKind {
+    /// `a | b | ...`, matching any of the arguments.
+    Union,
+    /// `list[t]`.
+    List,
+    /// `dict[k, v]`.
+    Dict,
+    /// `tuple[a, b, ...]`.
+    Tuple,
+}
+
+/// The result of `list[str]`, `int | None` and similar.
+#[derive(
+    Clone,
+    Debug,
+    Trace,
+    Freeze,
+    NoSerialize,
+    ProvidesStaticType,
+    Allocative
+)]
+pub(crate) struct TypeExprGen<V> {
+    #[trace(unsafe_ignore)]
+    #[freeze(identity)]
+    pub(crate) kind: TypeExprKind,
+    /// Each one a valid type annotation.
+    pub(crate) args: Vec<V>,
+}
+
+unsafe impl<From: Coerce<To>, To> Coerce<TypeExprGen<To>> for TypeExprGen<From> {}
+
+starlark_complex_value!(pub(crate) TypeExpr);
+
+impl<'v, V: ValueLike<'v>> Display for TypeExprGen<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, sep) = match self.kind {
+            TypeExprKind::Union => (None, " | "),
+            TypeExprKind::List => (Some("list"), ", "),
+            TypeExprKind::Dict => (Some("dict"), ", "),
+            TypeExprKind::Tuple => (Some("tuple"), ", "),
+        };
+        if let Some(name) = name {
+            write!(f, "{}[", name)?;
+        }
+        for (i, x) in self.args.iter().enumerate() {
+            if i != 0 {
+                f.write_str(sep)?;
+            }
+            // Strings such as `str.type` are shown without quotes
+            f.write_str(&x.to_value().to_str())?;
+        }
+        if name.is_some() {
+            write!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'v, V: ValueLike<'v> + 'v> StarlarkValue<'v> for TypeExprGen<V>
+where
+    Self: ProvidesStaticType,
+{
+    starlark_type!("type_expr");
+
+    fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let args = self.args.iter().map(|x| x.to_value());
+        let lhs = match self.kind {
+            TypeExprKind::Union => args.collect(),
+            kind => vec![heap.alloc(TypeExpr {
+                kind,
+                args: args.collect(),
+            })],
+        };
+        match type_union(lhs, other, heap) {
+            Some(x) => Ok(x),
+            None => ValueError::unsupported_with(self, "|", other),
+        }
+    }
+}
+
+/// The union of the types `lhs` and `other`, flattening any unions in `other`,
+/// or [`None`] if `other` is not a type.
+pub(crate) fn type_union<'v>(
+    lhs: impl IntoIterator<Item = Value<'v>>,
+    other: Value<'v>,
+    heap: &'v Heap,
+) -> Option<Value<'v>> {
+    TypeCompiled::new(other, heap).ok()?;
+    let mut args: Vec<Value<'v>> = lhs.into_iter().collect();
+    match TypeExpr::from_value(other) {
+        Some(x) if x.kind == TypeExprKind::Union => args.extend(x.args.iter().copied()),
+        _ => args.push(other),
+    }
+    Some(heap.alloc(TypeExpr {
+        kind: TypeExprKind::Union,
+        args,
+    }))
+}
+
+impl NativeFunction {
+    /// For the type functions `list`, `dict` and `tuple`, the result of indexing them
+    /// with type arguments.
+    pub(crate) fn type_index<'v>(
+        &self,
+        index: Value<'v>,
+        heap: &'v Heap,
+    ) -> Option<anyhow::Result<Value<'v>>> {
+        let (kind, name, expected) = match self.typ?.to_value().unpack_str()? {
+            "list" => (TypeExprKind::List, "list", "1"),
+            "dict" => (TypeExprKind::Dict, "dict", "2"),
+            "tuple" => (TypeExprKind::Tuple, "tuple", "at least 1"),
+            _ => return None,
+        };
+        let args = match TupleRef::from_value(index) {
+            Some(xs) => xs.content().to_vec(),
+            None => vec![index],
+        };
+        let ok = match kind {
+            TypeExprKind::List => args.len() == 1,
+            TypeExprKind::Dict => args.len() == 2,
+            _ => !args.is_empty(),
+        };
+        if !ok {
+            return Some(Err(TypeExprError::WrongArgumentCount(
+                name,
+                expected,
+                args.len(),
+            )
+            .into()));
+        }
+        for x in &args {
+            if let Err(e) = TypeCompiled::new(*x, heap) {
+                return Some(Err(e));
+            }
+        }
+        Some(Ok(heap.alloc(TypeExpr { kind, args })))
+    }
+}
@@ -25,13 +25,18 @@ use thiserror::Error;
 use crate::coerce::Coerce;
 use crate::values::dict::Dict;
 use crate::values::dict::DictRef;
+use crate::values::function::NativeFunction;
+use crate::values::function::FUNCTION_TYPE;
 use crate::values::list::ListRef;
 use crate::values::types::tuple::value::Tuple;
 use crate::values::types::tuple::value::TupleGen;
+use crate::values::types::type_expr::TypeExpr;
+use crate::values::types::type_expr::TypeExprKind;
 use crate::values::Heap;
 use crate::values::Trace;
 use crate::values::Tracer;
 use crate::values::Value;
+use crate::values::ValueLike;
 
 #[derive(Debug, Error)]
 enum TypingError {
@@ -278,6 +283,9 @@ impl TypeCompiled {
                 "string" => TypeCompiled::type_string(),
                 "int" => TypeCompiled::type_int(),
                 "bool" => TypeCompiled::type_bool(),
+                // A signature such as `def(int, name: str) -> bool`, which only the
+                // typechecker inspects, so at runtime any function will do.
+                t if t.starts_with("def(") => TypeCompiled::type_concrete(FUNCTION_TYPE),
                 t => TypeCompiled::type_concrete(t),
             }
         }
@@ -320,7 +328,11 @@ impl TypeCompiled {
     fn from_dict<'v>(t: DictRef<'v>, heap: &'v Heap) -> anyhow::Result<TypeCompiled> {
         // Dictionary with a single element
         fn unpack_singleton_dictionary<'v>(x: &Dict<'v>) -> Option<(Value<'v>, Value<'v>)> {
-            if x.len() == 1 { x.iter().next() } else { None }
+            if x.len() == 1 {
+                x.iter().next()
+            } else {
+                None
+            }
         }
 
         if let Some((tk, tv)) = unpack_singleton_dictionary(&t) {
@@ -338,6 +350,25 @@ impl TypeCompiled {
         }
     }
 
+    /// Parse `list[t]`, `a | b` and similar as type.
+    fn from_type_expr<'v>(t: &TypeExpr<'v>, heap: &'v Heap) -> anyhow::Result<TypeCompiled> {
+        // The number of arguments was checked when the `TypeExpr` was created
+        let mut ts = t.args.try_map(|t| TypeCompiled::new(*t, heap))?;
+        Ok(match t.kind {
+            TypeExprKind::Union if ts.len() == 2 => {
+                let t2 = ts.pop().unwrap();
+                TypeCompiled::type_any_of_two(ts.pop().unwrap(), t2)
+            }
+            TypeExprKind::Union => TypeCompiled::type_any_of(ts),
+            TypeExprKind::List => TypeCompiled::type_list_of(ts.pop().unwrap()),
+            TypeExprKind::Dict => {
+                let tv = ts.pop().unwrap();
+                TypeCompiled::type_dict_of(ts.pop().unwrap(), tv)
+            }
+            TypeExprKind::Tuple => TypeCompiled::type_tuple_of(ts),
+        })
+    }
+
     pub(crate) fn new<'v>(ty: Value<'v>, heap: &'v Heap) -> anyhow::Result<Self> {
         if let Some(s) = ty.unpack_str() {
             Ok(TypeCompiled::from_str(s))
@@ -349,6 +380,14 @@ impl TypeCompiled {
             TypeCompiled::from_list(t, heap)
         } else if let Some(t) = DictRef::from_value(ty) {
             TypeCompiled::from_dict(t, heap)
+        } else if let Some(t) = TypeExpr::from_value(ty) {
+            TypeCompiled::from_type_expr(t, heap)
+        } else if let Some(t) = ty
+            .downcast_ref::<NativeFunction>()
+            .and_then(|f| f.typ?.to_value().unpack_str())
+        {
+            // A type function such as `int`, rather than `int.type`
+            Ok(TypeCompiled::from_str(t))
         } else {
             Err(invalid_type_annotation(ty, heap).into())
         }
@@ -439,7 +478,6 @@ f(8) == False"#,
             &["type annotation", "`1`", "`int`", "`bool`", "`i`"],
         );
         // Type errors should be caught when the user forgets quotes around a valid type
-        a.fail("def f(v: bool):\n pass\n", r#"Perhaps you meant `"bool"`"#);
         a.fails(
             r#"Foo = record(value=int.type)
 def f(v: bool.type) -> Foo:
@@ -477,6 +515,7 @@ is_type(True, bool.type)
 is_type(True, "")
 is_type(None, None)
 is_type(assert_type, "function")
+is_type(assert_type, "def(int, name: str = ..) -> bool")
 is_type([], [int.type])
 is_type([], [""])
 is_type([1, 2, 3], [int.type])
@@ -487,6 +526,7 @@ is_type({"test": 1, "more": 2}, {str.type: int.type})
 is_type({1: 1, 2: 2}, {int.type: int.type})
 
 not is_type(1, None)
+not is_type(1, "def() -> int")
 not is_type((1, 1), str.type)
 not is_type('test', [int.type, bool.type])
 not is_type([1,2,None], [int.type])
@@ -504,6 +544,36 @@ is_type([1,2,"test"], ["_a"])
         a.fail("is_type(None, {'1': '', '2': ''})", "not a valid type");
         a.fail("is_type({}, {1: 'string', 2: 'bool'})", "not a valid type");
 
+        // The type functions and the annotations built from them are types too
+        a.all_true(
+            r#"
+is_type(True, bool)
+is_type(["x"], list[str])
+is_type({"x": [1]}, dict[str, list[int]])
+is_type(("x", 1), tuple[str, int])
+is_type(None, int | None)
+is_type(1, None | int)
+is_type("x", int | bool | str)
+
+not is_type(True, str)
+not is_type([1], list[str])
+not is_type({"x": 1}, dict[int, int])
+not is_type(("x", 1), tuple[str])
+not is_type("x", int | None)
+"#,
+        );
+        a.eq("'list[str]'", "repr(list[str])");
+        a.eq("'dict[int, string | None]'", "repr(dict[int, str | None])");
+        a.fails(
+            "def f(x: list[str] | None):\n pass\nf([1])",
+            &["type annotation", "`[1]`", "`list[str] | None`", "`x`"],
+        );
+        a.fail("list[int, str]", "expects 1 type arguments, but got 2");
+        a.fail("dict[str]", "expects 2 type arguments, but got 1");
+        a.fail("list[1]", "not a valid type");
+        a.fail("int | 1", "not supported");
+        a.fail("len | None", "not supported");
+
         // Should check the type of default parameters that aren't used
         a.fail(
             r#"
@@ -22,7 +22,9 @@ use std::ops::Deref;
 use dupe::Dupe;
 use either::Either;
 
+use crate::values::dict::DictRef;
 use crate::values::list::ListRef;
+use crate::values::structs::StructRef;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::types::tuple::value::Tuple;
 use crate::values::Value;
@@ -155,3 +157,32 @@ impl<'v, T: UnpackValue<'v>> UnpackValue<'v> for Vec<T> {
         }
     }
 }
+
+/// Helpers used by code generated by `#[derive(StarlarkUnpack)]`.
+pub(crate) mod derive {
+    use starlark_map::small_map::SmallMap;
+
+    use super::*;
+
+    /// The fields of a struct, or the entries of a dict with string keys.
+    /// Returns `None` for any other value, or a dict with a non-string key.
+    pub fn fields<'v>(value: Value<'v>) -> Option<SmallMap<&'v str, Value<'v>>> {
+        if let Some(s) = StructRef::from_value(value) {
+            s.iter()
+                .map(|(k, v)| Some((k.to_value().unpack_str()?, v)))
+                .collect()
+        } else if let Some(d) = DictRef::from_value(value) {
+            d.iter().map(|(k, v)| Some((k.unpack_str()?, v))).collect()
+        } else {
+            None
+        }
+    }
+
+    /// Unpack an optional field, treating a missing field and `None` the same way.
+    pub fn optional<'v, T: UnpackValue<'v>>(value: Option<Value<'v>>) -> Option<Option<T>> {
+        match value {
+            Some(v) if !v.is_none() => Some(Some(T::unpack_value(v)?)),
+            _ => Some(None),
+        }
+    }
+}
@@ -38,14 +38,14 @@ pub fn derive_attrs(input: TokenStream) -> TokenStream {
         .into()
 }
 
-struct Field {
-    ident: Ident,
+pub(crate) struct Field {
+    pub(crate) ident: Ident,
     starlark_args: Vec<Ident>,
     ty: Type,
 }
 
 impl Field {
-    fn name(&self) -> String {
+    pub(crate) fn name(&self) -> String {
         self.ident.to_string()
     }
 
@@ -70,22 +70,30 @@ impl Field {
         self.starlark_args.iter().any(|i| i == "skip")
     }
 
-    fn has_attr_match_item(&self) -> proc_macro2::TokenStream {
+    pub(crate) fn has_attr_match_item(&self) -> proc_macro2::TokenStream {
         let name = self.name();
         quote! {
             #name => true
         }
     }
 
-    fn get_attr_match_item(&self) -> proc_macro2::TokenStream {
+    pub(crate) fn get_attr_match_item(&self) -> proc_macro2::TokenStream {
         let name = self.name();
+        let alloc = self.alloc_expr();
+        quote! {
+            #name => Some(#alloc)
+        }
+    }
+
+    /// Allocate the value of the field on `heap`.
+    pub(crate) fn alloc_expr(&self) -> proc_macro2::TokenStream {
         let ident = &self.ident;
         match self.should_clone() {
             false => quote! {
-                #name => Some(heap.alloc(&self.#ident))
+                heap.alloc(&self.#ident)
             },
             true => quote! {
-                #name => Some(heap.alloc(self.#ident.clone()))
+                heap.alloc(self.#ident.clone())
             },
         }
     }
@@ -93,16 +101,17 @@ impl Field {
 
 static STARLARK_ATTR_ERR_MSG: &str = "valid starlark attributes are {skip}";
 
-fn expand_attrs_derive(data: Data, name: Ident) -> Result<proc_macro2::TokenStream> {
+/// Parse the fields of a struct, dropping those marked with `#[starlark(skip)]`.
+pub(crate) fn parse_fields(data: Data, derive: &str) -> Result<Vec<Field>> {
     let fields: Vec<_> = match data {
         Data::Struct(s) => Ok(s.fields.iter().cloned().collect()),
         Data::Enum(e) => Err(Error::new(
             e.enum_token.span(),
-            "#[derive(StarlarkAttrs)] does not support enums",
+            format!("#[derive({})] does not support enums", derive),
         )),
         Data::Union(u) => Err(Error::new(
             u.union_token.span(),
-            "#[derive(StarlarkAttrs)] does not support unions",
+            format!("#[derive({})] does not support unions", derive),
         )),
     }?;
 
@@ -142,6 +151,11 @@ fn expand_attrs_derive(data: Data, name: Ident) -> Result<proc_macro2::TokenStre
         })
         .filter(|f| f.as_ref().map(|f| !f.skip()).unwrap_or(true))
         .collect::<Result<_>>()?;
+    Ok(expose_fields)
+}
+
+fn expand_attrs_derive(data: Data, name: Ident) -> Result<proc_macro2::TokenStream> {
+    let expose_fields = parse_fields(data, "StarlarkAttrs")?;
 
     let has_attr_items = expose_fields.iter().map(|f| f.has_attr_match_item());
     let has_attr = quote! {
@@ -88,7 +88,8 @@ fn expand_docs_derive(input: DeriveInput) -> syn::Result<proc_macro2::TokenStrea
             if generics.type_params().count() != 0 {
                 return Err(syn::Error::new(
                     span,
-                    "If a an item name does not end in `Gen`, it must have no type parameters",
+                    "Documentation can only be registered for a type with type parameters \
+                    if its name ends in `Gen`, remove the `StarlarkDocs` derive",
                 ));
             }
 
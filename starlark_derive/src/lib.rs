@@ -34,8 +34,11 @@ mod docs;
 mod for_each_field;
 mod freeze;
 mod module;
+mod named_params;
 mod serde;
+mod simple_value;
 mod trace;
+mod unpack;
 mod visit_span;
 mod vtable;
 
@@ -52,7 +55,7 @@ mod vtable;
 /// }
 /// ```
 ///
-/// Parameters operate as named parameters of a given type, with six possible tweaks:
+/// Parameters operate as named parameters of a given type, with seven possible tweaks:
 ///
 /// * `this` (or `_this`) as the first argument means the argument is passed as a
 ///   bound method value, e.g. in `a.f(...)` the `a` would be `this`.
@@ -62,6 +65,12 @@ mod vtable;
 /// * A type of `Option` means the argument is optional.
 /// * A annotation `#[starlark(default = foo)] x : bool` means the argument defaults to `foo`
 ///   if not specified.
+/// * A annotation `#[starlark(named_params)] x : T` on the only parameter means the
+///   parameters are the fields of `T`, which must implement `NamedParameters`
+///   (usually through `#[derive(NamedParameters)]`).
+///
+/// If the doc comment of a function has a `# Arguments` section, it must document every
+/// required parameter, and nothing that isn't a parameter, otherwise compilation fails.
 ///
 /// During execution there are two local variables injected into scope:
 ///
@@ -142,6 +151,19 @@ pub fn derive_starlark_attrs(input: proc_macro::TokenStream) -> proc_macro::Toke
     attrs::derive_attrs(input)
 }
 
+/// Derive everything needed to expose a plain Rust struct as a read-only Starlark value:
+/// `StarlarkValue`, `ProvidesStaticType`, `Allocative`, `Display`, the allocation impls of
+/// `starlark_simple_value!`, and attributes for all fields not marked with `#[starlark(skip)]`.
+/// The type name defaults to the struct name and can be set with `#[starlark(type = "name")]`.
+/// The struct must also derive `Debug`, its exposed fields must implement `Clone` and
+/// `AllocValue`, and all its fields must implement `Allocative`. Values are displayed as
+/// `name(field=..., ...)` using the Starlark `repr` of the exposed fields, and cannot be
+/// serialized.
+#[proc_macro_derive(StarlarkSimpleValue, attributes(starlark))]
+pub fn derive_starlark_simple_value(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    simple_value::derive_simple_value(input)
+}
+
 /// Generate an accessor function on the provided type that returns its documentation
 /// based on `StarlarkValue::get_methods()`. This macro requires that the type implements
 /// `starlark::StarlarkValue`.
@@ -166,6 +188,25 @@ pub fn derive_starlark_docs(input: proc_macro::TokenStream) -> proc_macro::Token
     docs::derive_docs(input)
 }
 
+/// Derive the `NamedParameters` trait, mapping each field of a struct to a named-only
+/// parameter of a native function. `Option` fields are optional, fields annotated with
+/// `#[starlark(default = expr)]` default to `expr`, and other fields are required.
+/// Doc comments on the fields become the documentation of the parameters.
+#[proc_macro_derive(NamedParameters, attributes(starlark))]
+pub fn derive_named_parameters(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    named_params::derive_named_parameters(input)
+}
+
+/// Derive the `UnpackValue` trait, unpacking a Starlark struct or a dict with string keys
+/// into a Rust struct by field name. `Option` fields may be missing or `None`, fields annotated
+/// with `#[starlark(default = expr)]` default to `expr` when missing or `None`, and other fields
+/// are required. `#[starlark(rename = "name")]` reads the field under a different name.
+/// Unknown fields are ignored.
+#[proc_macro_derive(StarlarkUnpack, attributes(starlark))]
+pub fn derive_starlark_unpack(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    unpack::derive_starlark_unpack(input)
+}
+
 /// Generate `{has,get,dir}_attr` in the `StarlarkValue` impl block that proxy
 /// to the ones generated by `derive(StarlarkAttrs)`
 #[proc_macro]
@@ -173,8 +214,9 @@ pub fn starlark_attrs(_: proc_macro::TokenStream) -> proc_macro::TokenStream {
     attrs::starlark_attrs()
 }
 
-/// Derive the `ProvidesStaticType` trait. Requires the type has no type arguments, no constant arguments,
-/// and at most one lifetime argument.
+/// Derive the `ProvidesStaticType` trait. Requires the type has no constant arguments,
+/// and at most one lifetime argument. Each type argument must either be bounded by `'static`,
+/// as in `MyWrapper<T: Content + 'static>`, or implement `ProvidesStaticType` itself.
 #[proc_macro_derive(ProvidesStaticType)]
 pub fn derive_provides_static_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     any_lifetime::derive_provides_static_type(input)
@@ -18,7 +18,7 @@
 pub(crate) mod parse;
 mod render;
 mod typ;
-mod util;
+pub(crate) mod util;
 
 use proc_macro::TokenStream;
 use syn::parse_macro_input;
@@ -49,6 +49,7 @@ use crate::module::typ::StarAttr;
 use crate::module::typ::StarFun;
 use crate::module::typ::StarFunSource;
 use crate::module::typ::StarStmt;
+use crate::module::util::ident_string;
 
 #[derive(Default)]
 struct FnAttrs {
@@ -69,6 +70,7 @@ struct FnParamAttrs {
     named_only: bool,
     args: bool,
     kwargs: bool,
+    named_params: bool,
     starlark_type: Option<String>,
     unused_attrs: Vec<Attribute>,
 }
@@ -110,6 +112,9 @@ fn parse_starlark_fn_param_attr(
                 } else if ident == "kwargs" {
                     param_attrs.kwargs = true;
                     continue;
+                } else if ident == "named_params" {
+                    param_attrs.named_params = true;
+                    continue;
                 } else if ident == "require" {
                     parser.parse::<Token!(=)>()?;
                     let require = parser.parse::<Ident>()?;
@@ -128,6 +133,7 @@ fn parse_starlark_fn_param_attr(
                     `#[starlark(default = expr)]`, \
                     `#[starlark(require = pos)]`, \
                     `#[starlark(require = named)]`, \
+                    `#[starlark(named_params)]`, \
                     `#[starlark(this)]` attribute",
                 ));
             }
@@ -368,6 +374,9 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
         }
 
         let source = resolve_args(&mut args)?;
+        if let Some(docstring) = &docstring {
+            check_docstring_args(&func.sig.ident, docstring, &args)?;
+        }
 
         let fun = StarFun {
             name: func.sig.ident,
@@ -399,6 +408,18 @@ fn resolve_args(args: &mut [StarArg]) -> syn::Result<StarFunSource> {
         args[0].source = StarArgSource::This;
         args[1].source = StarArgSource::Parameters;
         Ok(StarFunSource::ThisArguments)
+    } else if args
+        .iter()
+        .any(|x| x.pass_style == StarArgPassStyle::NamedParams)
+    {
+        if args.len() != 1 {
+            return Err(syn::Error::new(
+                args[0].span,
+                "`#[starlark(named_params)]` parameter must be the only parameter",
+            ));
+        }
+        args[0].source = StarArgSource::Parameters;
+        Ok(StarFunSource::NamedParams)
     } else {
         let use_arguments = args
             .iter()
@@ -436,6 +457,81 @@ fn resolve_args(args: &mut [StarArg]) -> syn::Result<StarFunSource> {
     }
 }
 
+/// The parameter names documented in the `# Arguments` section of a docstring,
+/// or `None` if there is no such section.
+fn docstring_args(docstring: &str) -> Option<Vec<String>> {
+    let mut lines = docstring.lines().map(str::trim);
+    lines.find(|x| *x == "# Arguments")?;
+    let mut res = Vec::new();
+    for line in lines.take_while(|x| !x.starts_with("# ")) {
+        // Same shape as the runtime parser, e.g. ``* `name`: description``
+        let line = line.strip_prefix("* ").unwrap_or(line);
+        if let Some(rest) = line.strip_prefix('`') {
+            if let Some((name, _)) = rest.split_once('`') {
+                if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    res.push(name.to_owned());
+                }
+            }
+        }
+    }
+    Some(res)
+}
+
+/// Check the `# Arguments` section of a function docstring, if any, only documents
+/// parameters the function has, and documents all of its required parameters.
+fn check_docstring_args(name: &Ident, docstring: &str, args: &[StarArg]) -> syn::Result<()> {
+    let documented = match docstring_args(docstring) {
+        Some(documented) => documented,
+        None => return Ok(()),
+    };
+    if args.iter().any(|x| {
+        matches!(
+            x.pass_style,
+            StarArgPassStyle::Arguments | StarArgPassStyle::NamedParams
+        )
+    }) {
+        // The parameters are not known until runtime
+        return Ok(());
+    }
+    let params: Vec<(&StarArg, String)> = args
+        .iter()
+        .filter(|x| x.pass_style != StarArgPassStyle::This)
+        .map(|x| (x, ident_string(&x.name)))
+        .collect();
+    for doc in &documented {
+        if !params.iter().any(|(_, x)| x == doc) {
+            return Err(syn::Error::new(
+                name.span(),
+                format!(
+                    "Docstring of `{}` documents parameter `{}`, which does not exist",
+                    ident_string(name),
+                    doc
+                ),
+            ));
+        }
+    }
+    for (arg, param) in &params {
+        let required = arg.default.is_none()
+            && !arg.is_option()
+            && !matches!(
+                arg.pass_style,
+                StarArgPassStyle::Args | StarArgPassStyle::Kwargs
+            );
+        if required && !documented.contains(param) {
+            return Err(syn::Error::new(
+                arg.span,
+                format!(
+                    "Docstring of `{}` has an `# Arguments` section, \
+                    but does not document required parameter `{}`",
+                    ident_string(name),
+                    param
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
 /// Check there are no undeclared lifetiems in return type.
 fn check_lifetimes_in_return_type(return_type: &ReturnType, has_v: bool) -> syn::Result<()> {
     match return_type {
@@ -641,40 +737,59 @@ fn parse_arg(
 
             let arguments = is_ref_something(&ty, "Arguments");
 
-            let pass_style = match (
-                this,
-                param_attrs.args,
-                param_attrs.kwargs,
-                seen_star_args,
-                param_attrs.pos_only,
-                param_attrs.named_only,
-                arguments,
-            ) {
-                (true, _, _, _, _, _, false) => StarArgPassStyle::This,
-                (false, true, _, _, _, _, false) => StarArgPassStyle::Args,
-                (false, _, true, _, _, _, false) => StarArgPassStyle::Kwargs,
-                (false, _, _, true, true, _, false) => {
+            let pass_style = if param_attrs.named_params {
+                if this
+                    || param_attrs.args
+                    || param_attrs.kwargs
+                    || param_attrs.pos_only
+                    || param_attrs.named_only
+                    || param_attrs.default.is_some()
+                    || arguments
+                {
                     return Err(syn::Error::new(
                         span,
-                        "Positional-only arguments cannot follow *args",
+                        "`#[starlark(named_params)]` parameter is incompatible with other annotations",
                     ));
                 }
-                (false, false, false, true, false, _, false) => StarArgPassStyle::NamedOnly,
-                (false, false, false, false, false, false, false) => StarArgPassStyle::PosOrNamed,
-                (false, false, false, false, true, false, false) => StarArgPassStyle::PosOnly,
-                (false, false, false, false, false, true, false) => StarArgPassStyle::NamedOnly,
-                (false, false, false, false, true, true, false) => {
-                    return Err(syn::Error::new(
-                        span,
-                        "Function parameter cannot be both positional-only and named-only",
-                    ));
-                }
-                (false, false, false, _, false, false, true) => StarArgPassStyle::Arguments,
-                (_, _, _, _, _, _, true) => {
-                    return Err(syn::Error::new(
-                        span,
-                        "`&Arguments` parameter type is incompatible with annotations",
-                    ));
+                StarArgPassStyle::NamedParams
+            } else {
+                match (
+                    this,
+                    param_attrs.args,
+                    param_attrs.kwargs,
+                    seen_star_args,
+                    param_attrs.pos_only,
+                    param_attrs.named_only,
+                    arguments,
+                ) {
+                    (true, _, _, _, _, _, false) => StarArgPassStyle::This,
+                    (false, true, _, _, _, _, false) => StarArgPassStyle::Args,
+                    (false, _, true, _, _, _, false) => StarArgPassStyle::Kwargs,
+                    (false, _, _, true, true, _, false) => {
+                        return Err(syn::Error::new(
+                            span,
+                            "Positional-only arguments cannot follow *args",
+                        ));
+                    }
+                    (false, false, false, true, false, _, false) => StarArgPassStyle::NamedOnly,
+                    (false, false, false, false, false, false, false) => {
+                        StarArgPassStyle::PosOrNamed
+                    }
+                    (false, false, false, false, true, false, false) => StarArgPassStyle::PosOnly,
+                    (false, false, false, false, false, true, false) => StarArgPassStyle::NamedOnly,
+                    (false, false, false, false, true, true, false) => {
+                        return Err(syn::Error::new(
+                            span,
+                            "Function parameter cannot be both positional-only and named-only",
+                        ));
+                    }
+                    (false, false, false, _, false, false, true) => StarArgPassStyle::Arguments,
+                    (_, _, _, _, _, _, true) => {
+                        return Err(syn::Error::new(
+                            span,
+                            "`&Arguments` parameter type is incompatible with annotations",
+                        ));
+                    }
                 }
             };
             Ok(StarArgOrSpecial::StarArg(StarArg {
@@ -97,7 +97,7 @@ pub(crate) fn parse(mut input: ItemFn) -> syn::Result<StarModule> {
     })
 }
 
-fn is_attribute_docstring(x: &Attribute) -> Option<String> {
+pub(crate) fn is_attribute_docstring(x: &Attribute) -> Option<String> {
     if x.path.is_ident("doc") {
         if let Ok(Meta::NameValue(MetaNameValue {
             lit: syn::Lit::Str(s),
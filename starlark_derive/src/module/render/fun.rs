@@ -136,6 +136,32 @@ impl StarFun {
         (binding_params, binding_param_types, prepare, binding_args)
     }
 
+    /// Signature and documentation of the `#[starlark(named_params)]` parameter.
+    fn named_params_impl(&self) -> Option<TokenStream> {
+        if let StarFunSource::NamedParams = self.source {
+            let name_str = self.name_str();
+            let ty = &self.args[0].ty;
+            Some(quote_spanned! {self.span()=>
+                            #[allow(clippy::extra_unused_lifetimes)]
+            fn named_params_signature<'v>() -> starlark::eval::ParametersSpec<starlark::values::FrozenValue> {
+                                <#ty as starlark::eval::NamedParameters<'v>>::signature(#name_str)
+                            }
+
+                            #[allow(clippy::extra_unused_lifetimes)]
+            fn named_params_parameter_types<'v>() -> std::collections::HashMap<usize, starlark::docs::Type> {
+                                <#ty as starlark::eval::NamedParameters<'v>>::parameter_types()
+                            }
+
+                            #[allow(clippy::extra_unused_lifetimes)]
+            fn named_params_parameter_docs<'v>() -> std::collections::HashMap<std::string::String, Option<starlark::docs::DocString>> {
+                                <#ty as starlark::eval::NamedParameters<'v>>::parameter_docs()
+                            }
+                        })
+        } else {
+            None
+        }
+    }
+
     fn trait_name(&self) -> TokenStream {
         if self.is_method() {
             quote_spanned! {self.span()=> starlark::values::function::NativeMeth }
@@ -154,10 +180,13 @@ impl StarFun {
 
     /// Fields and field initializers for the struct implementing the trait.
     fn struct_fields(&self) -> syn::Result<(TokenStream, TokenStream)> {
-        let signature = if let StarFunSource::Signature { .. } = self.source {
-            Some(render_signature(self)?)
-        } else {
-            None
+        let signature = match self.source {
+            StarFunSource::Signature { .. } => Some(render_signature(self)?),
+            StarFunSource::NamedParams => {
+                let struct_name = self.struct_name();
+                Some(quote_spanned! { self.span()=> #struct_name::named_params_signature() })
+            }
+            _ => None,
         };
         if let Some(signature) = signature {
             Ok((
@@ -227,6 +256,8 @@ pub(crate) fn render_fun(x: StarFun) -> syn::Result<TokenStream> {
     let (heap_param, heap_param_type, heap_arg) = x.heap_param_arg();
     let (binding_params, binding_param_types, prepare, binding_args) = x.binding_params_arg();
 
+    let named_params = x.named_params_impl();
+
     let trait_name = x.trait_name();
     let (struct_fields, struct_fields_init) = x.struct_fields()?;
 
@@ -280,6 +311,8 @@ pub(crate) fn render_fun(x: StarFun) -> syn::Result<TokenStream> {
                 }
                 get_impl(Self::invoke_impl)
             }
+
+            #named_params
         }
 
         impl #trait_name for #struct_name {
@@ -368,6 +401,29 @@ fn render_binding(x: &StarFun) -> Bindings {
                 bindings: bind_args,
             }
         }
+        StarFunSource::NamedParams => {
+            let StarArg {
+                span,
+                attrs,
+                name,
+                ty,
+                mutable,
+                ..
+            } = &x.args[0];
+            let span = *span;
+            Bindings {
+                prepare: quote_spanned! { span=> },
+                bindings: vec![BindingArg {
+                    name: name.to_owned(),
+                    ty: ty.to_owned(),
+                    attrs: attrs.clone(),
+                    mutability: mut_token(*mutable),
+                    expr: quote_spanned! { span=>
+                        <#ty as starlark::eval::NamedParameters>::parse(&self.signature, parameters, eval.heap())?
+                    },
+                }],
+            }
+        }
         StarFunSource::Positional { required, optional } => {
             let bind_args = x.args.map(render_binding_arg);
             if optional == 0 {
@@ -506,10 +562,15 @@ fn render_documentation(x: &StarFun) -> syn::Result<(Ident, TokenStream)> {
     let name_str = ident_string(&x.name);
     let need_render_signature = match &x.source {
         StarFunSource::Signature { .. } | StarFunSource::Positional { .. } => true,
-        StarFunSource::Arguments | StarFunSource::ThisArguments => false,
+        StarFunSource::Arguments | StarFunSource::ThisArguments | StarFunSource::NamedParams => {
+            false
+        }
     };
+    let struct_name = x.struct_name();
     let documentation_signature = if need_render_signature {
         render_signature(x)?
+    } else if let StarFunSource::NamedParams = x.source {
+        quote_spanned! { span=> #struct_name::named_params_signature() }
     } else {
         // An Arguments can take anything, so give the most generic documentation signature
         quote_spanned! {
@@ -531,8 +592,11 @@ fn render_documentation(x: &StarFun) -> syn::Result<(Ident, TokenStream)> {
         .iter()
         .filter(|a| {
             // "this" gets ignored when creating the signature, so make sure the indexes match up.
-            // Arguments doesn't correspond to a parameter type, since it is many parameters
-            a.pass_style != StarArgPassStyle::This && a.pass_style != StarArgPassStyle::Arguments
+            // Arguments and named params don't correspond to a parameter type, since they are
+            // many parameters
+            a.pass_style != StarArgPassStyle::This
+                && a.pass_style != StarArgPassStyle::Arguments
+                && a.pass_style != StarArgPassStyle::NamedParams
         })
         .enumerate()
         .filter(|(_, a)| a.pass_style != StarArgPassStyle::Args) // these aren't coerced according to their type (Vec vs tuple)
@@ -542,12 +606,25 @@ fn render_documentation(x: &StarFun) -> syn::Result<(Ident, TokenStream)> {
         })
         .collect();
 
+    let (parameter_types, parameter_docs) = if let StarFunSource::NamedParams = x.source {
+        (
+            quote_spanned!(span=> #struct_name::named_params_parameter_types()),
+            quote_spanned!(span=> #struct_name::named_params_parameter_docs()),
+        )
+    } else {
+        (
+            quote_spanned!(span=> std::collections::HashMap::from([#(#parameter_types),*])),
+            quote_spanned!(span=> std::collections::HashMap::new()),
+        )
+    };
+
     let return_type_str = render_starlark_return_type(x, &x.starlark_return_type);
     let var_name = format_ident!("__documentation");
     let documentation = quote_spanned!(span=>
         let #var_name = {
             let signature = #documentation_signature;
-            let parameter_types = std::collections::HashMap::from([#(#parameter_types),*]);
+            let parameter_types = #parameter_types;
+            let parameter_docs = #parameter_docs;
             let return_type = Some(
                 starlark::docs::Type {
                     raw_type: #return_type_str
@@ -557,6 +634,7 @@ fn render_documentation(x: &StarFun) -> syn::Result<(Ident, TokenStream)> {
                 rust_docstring: #docs,
                 signature,
                 parameter_types,
+                parameter_docs,
                 return_type,
             }
         };
@@ -627,6 +705,12 @@ fn render_signature_args(args: &[StarArg], signature_var: &Ident) -> syn::Result
                     "unreachable: signature is not meant to be created for `&Arguments`",
                 ));
             }
+            StarArgPassStyle::NamedParams => {
+                return Err(syn::Error::new(
+                    arg.span,
+                    "unreachable: signature is not meant to be created for named params",
+                ));
+            }
         }
         sig_args.extend(render_signature_arg(arg, signature_var)?);
     }
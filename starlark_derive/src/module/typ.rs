@@ -182,6 +182,8 @@ pub(crate) enum StarArgPassStyle {
     Kwargs,
     /// `&Arguments`.
     Arguments,
+    /// `#[starlark(named_params)]`, a struct implementing `NamedParameters`.
+    NamedParams,
 }
 
 #[derive(Debug)]
@@ -218,6 +220,8 @@ pub(crate) enum StarFunSource {
     /// Fast-path function of some required parameters, followed by some optional parameters.
     /// No named parameters or `*args`/`**kwargs`.
     Positional { required: usize, optional: usize },
+    /// Function signature is single `#[starlark(named_params)]` parameter.
+    NamedParams,
 }
 
 impl StarArg {
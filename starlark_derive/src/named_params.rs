@@ -0,0 +1,267 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use proc_macro::TokenStream;
+use proc_macro2::Ident;
+use quote::quote;
+use quote::quote_spanned;
+use syn::parse::ParseStream;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Attribute;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Expr;
+use syn::Fields;
+use syn::GenericParam;
+use syn::Result;
+use syn::Token;
+use syn::Type;
+
+use crate::module::parse::is_attribute_docstring;
+use crate::module::util::ident_string;
+use crate::module::util::is_type_name;
+
+pub fn derive_named_parameters(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_named_parameters_derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    default: Option<Expr>,
+    docstring: Option<String>,
+}
+
+impl Field {
+    fn name(&self) -> String {
+        ident_string(&self.ident)
+    }
+
+    fn is_option(&self) -> bool {
+        is_type_name(&self.ty, "Option")
+    }
+
+    fn parse(field: &syn::Field) -> Result<Field> {
+        let mut default = None;
+        let mut docstring: Option<String> = None;
+        for attr in &field.attrs {
+            if attr.path.is_ident("starlark") {
+                default = Some(parse_starlark_field_attr(attr)?);
+            } else if let Some(doc) = is_attribute_docstring(attr) {
+                match &mut docstring {
+                    None => docstring = Some(doc),
+                    Some(docstring) => {
+                        docstring.push('\n');
+                        docstring.push_str(&doc);
+                    }
+                }
+            }
+        }
+        let field = Field {
+            // Only called for named fields.
+            ident: field.ident.clone().unwrap(),
+            ty: field.ty.clone(),
+            default,
+            docstring,
+        };
+        if field.is_option() && field.default.is_some() {
+            return Err(Error::new(
+                field.ty.span(),
+                "`Option` fields cannot have a default",
+            ));
+        }
+        Ok(field)
+    }
+
+    fn signature_item(&self) -> proc_macro2::TokenStream {
+        let name = self.name();
+        if self.is_option() || self.default.is_some() {
+            quote_spanned! { self.ident.span()=> __signature.optional(#name); }
+        } else {
+            quote_spanned! { self.ident.span()=> __signature.required(#name); }
+        }
+    }
+
+    fn parameter_type_item(&self, index: usize) -> proc_macro2::TokenStream {
+        let ty = &self.ty;
+        quote_spanned! { self.ident.span()=>
+            (
+                #index,
+                starlark::docs::Type {
+                    raw_type: <#ty as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr(),
+                },
+            )
+        }
+    }
+
+    fn parameter_doc_item(&self) -> proc_macro2::TokenStream {
+        let name = self.name();
+        let doc = match &self.docstring {
+            Some(d) => quote! {
+                starlark::docs::DocString::from_docstring(starlark::docs::DocStringKind::Rust, #d)
+            },
+            None => quote! { None },
+        };
+        quote_spanned! { self.ident.span()=> (#name.to_owned(), #doc) }
+    }
+
+    fn parse_item(&self, index: usize) -> proc_macro2::TokenStream {
+        let ident = &self.ident;
+        let name = self.name();
+        let source = quote_spanned! { self.ident.span()=> __args[#index].get() };
+        let value = if self.is_option() {
+            quote_spanned! { self.ident.span()=>
+                starlark::eval::Arguments::check_optional(#name, #source)?
+            }
+        } else if let Some(default) = &self.default {
+            quote_spanned! { self.ident.span()=>
+                {
+                    #[allow(clippy::unnecessary_lazy_evaluations)]
+                    #[allow(clippy::redundant_closure)]
+                    let x = starlark::eval::Arguments::check_optional(#name, #source)?.unwrap_or_else(|| #default);
+                    x
+                }
+            }
+        } else {
+            quote_spanned! { self.ident.span()=>
+                starlark::eval::Arguments::check_required(#name, #source)?
+            }
+        };
+        quote_spanned! { self.ident.span()=> #ident: #value }
+    }
+}
+
+/// Parse `#[starlark(default = expr)]`, returning `expr`.
+fn parse_starlark_field_attr(attr: &Attribute) -> Result<Expr> {
+    let parse = |parser: ParseStream| -> Result<Expr> {
+        let ident = parser.parse::<Ident>()?;
+        if ident != "default" {
+            return Err(Error::new(
+                ident.span(),
+                "Expecting `#[starlark(default = expr)]` attribute",
+            ));
+        }
+        parser.parse::<Token![=]>()?;
+        parser.parse::<Expr>()
+    };
+    attr.parse_args_with(parse)
+}
+
+fn expand_named_parameters_derive(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(Field::parse)
+                .collect::<Result<Vec<_>>>()?,
+            _ => {
+                return Err(Error::new(
+                    s.fields.span(),
+                    "#[derive(NamedParameters)] requires named fields",
+                ));
+            }
+        },
+        Data::Enum(e) => {
+            return Err(Error::new(
+                e.enum_token.span(),
+                "#[derive(NamedParameters)] does not support enums",
+            ));
+        }
+        Data::Union(u) => {
+            return Err(Error::new(
+                u.union_token.span(),
+                "#[derive(NamedParameters)] does not support unions",
+            ));
+        }
+    };
+
+    let name = &input.ident;
+    let params = &input.generics.params;
+    let (impl_lifetime, ty) = match params.len() {
+        0 => (quote! { 'v }, quote! { #name }),
+        1 => match params.first() {
+            Some(GenericParam::Lifetime(l)) => {
+                let l = &l.lifetime;
+                (quote! { #l }, quote! { #name<#l> })
+            }
+            _ => {
+                return Err(Error::new(
+                    params.span(),
+                    "#[derive(NamedParameters)] only supports a lifetime parameter",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                params.span(),
+                "#[derive(NamedParameters)] supports at most one lifetime parameter",
+            ));
+        }
+    };
+
+    let count = fields.len();
+    let signature_items = fields.iter().map(|f| f.signature_item());
+    let parameter_type_items = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| f.parameter_type_item(i));
+    let parameter_doc_items = fields.iter().map(|f| f.parameter_doc_item());
+    let parse_items = fields.iter().enumerate().map(|(i, f)| f.parse_item(i));
+
+    Ok(quote! {
+        impl<#impl_lifetime> starlark::eval::NamedParameters<#impl_lifetime> for #ty {
+            fn signature(
+                function_name: &str,
+            ) -> starlark::eval::ParametersSpec<starlark::values::FrozenValue> {
+                let mut __signature = starlark::eval::ParametersSpec::with_capacity(
+                    function_name.to_owned(),
+                    #count,
+                );
+                __signature.no_more_positional_args();
+                #(#signature_items)*
+                __signature.finish()
+            }
+
+            fn parameter_types() -> std::collections::HashMap<usize, starlark::docs::Type> {
+                std::collections::HashMap::from([#(#parameter_type_items),*])
+            }
+
+            fn parameter_docs(
+            ) -> std::collections::HashMap<std::string::String, Option<starlark::docs::DocString>>
+            {
+                std::collections::HashMap::from([#(#parameter_doc_items),*])
+            }
+
+            fn parse(
+                signature: &starlark::eval::ParametersSpec<starlark::values::FrozenValue>,
+                args: &starlark::eval::Arguments<#impl_lifetime, '_>,
+                heap: &#impl_lifetime starlark::values::Heap,
+            ) -> anyhow::Result<Self> {
+                let __args: [_; #count] = signature.collect_into(args, heap)?;
+                Ok(Self {
+                    #(#parse_items),*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,152 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use proc_macro::TokenStream;
+use proc_macro2::Ident;
+use quote::quote;
+use syn::parse::ParseStream;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::LitStr;
+use syn::Result;
+use syn::Token;
+
+use crate::attrs::parse_fields;
+
+pub fn derive_simple_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_simple_value_derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Parse `#[starlark(type = "name")]` on the struct, returning `name`.
+fn parse_type_attr(input: &DeriveInput) -> Result<Option<String>> {
+    let mut res = None;
+    for attr in &input.attrs {
+        if attr.path.is_ident("starlark") {
+            let parse = |parser: ParseStream| -> Result<String> {
+                if !parser.peek(Token![type]) {
+                    return Err(parser.error("Expecting `#[starlark(type = \"name\")]` attribute"));
+                }
+                parser.parse::<Token![type]>()?;
+                parser.parse::<Token![=]>()?;
+                Ok(parser.parse::<LitStr>()?.value())
+            };
+            res = Some(attr.parse_args_with(parse)?);
+        }
+    }
+    Ok(res)
+}
+
+fn expand_simple_value_derive(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "#[derive(StarlarkSimpleValue)] does not support generic types",
+        ));
+    }
+    let type_name = parse_type_attr(&input)?.unwrap_or_else(|| input.ident.to_string());
+    let name = input.ident;
+    // Every field takes memory, including those hidden from Starlark.
+    let all_fields: Vec<Ident> = match &input.data {
+        Data::Struct(s) => s.fields.iter().filter_map(|f| f.ident.clone()).collect(),
+        _ => Vec::new(),
+    };
+    let all_field_names = all_fields.iter().map(|x| x.to_string());
+    let fields = parse_fields(input.data, "StarlarkSimpleValue")?;
+
+    let display_items = fields.iter().enumerate().map(|(i, f)| {
+        let prefix = format!("{}{}=", if i == 0 { "" } else { ", " }, f.name());
+        let alloc = f.alloc_expr();
+        quote! {
+            f.write_str(#prefix)?;
+            f.write_str(&#alloc.to_repr())?;
+        }
+    });
+    let has_attr_items = fields.iter().map(|f| f.has_attr_match_item());
+    let get_attr_items = fields.iter().map(|f| f.get_attr_match_item());
+    let dir_names = fields.iter().map(|f| f.name());
+
+    Ok(quote! {
+        starlark::starlark_simple_value!(#name);
+
+        unsafe impl starlark::any::ProvidesStaticType for #name {
+            type StaticType = #name;
+        }
+
+        impl starlark::__derive_refs::allocative::Allocative for #name {
+            fn visit<'a, 'b: 'a>(&self, visitor: &'a mut starlark::__derive_refs::allocative::Visitor<'b>) {
+                let mut visitor = visitor.enter_self_sized::<Self>();
+                #(
+                    visitor.visit_field(
+                        starlark::__derive_refs::allocative::Key::new(#all_field_names),
+                        &self.#all_fields,
+                    );
+                )*
+                visitor.exit();
+            }
+        }
+
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                // Fields are shown as Starlark shows them, which needs them on a heap.
+                let heap = starlark::values::Heap::new();
+                std::write!(f, "{}(", #type_name)?;
+                #(#display_items)*
+                std::write!(f, ")")
+            }
+        }
+
+        impl starlark::__derive_refs::serde::Serialize for #name {
+            fn serialize<__S>(&self, serializer: __S) -> std::result::Result<__S::Ok, __S::Error>
+            where
+                __S: starlark::__derive_refs::serde::Serializer,
+            {
+                Err(starlark::__derive_refs::serde::Error::custom(format!(
+                    "Operation `serde::serialize` not supported on type `{}`",
+                    #type_name
+                )))
+            }
+        }
+
+        impl<'v> starlark::values::StarlarkValue<'v> for #name {
+            starlark::starlark_type!(#type_name);
+
+            fn has_attr(&self, attr: &str, _heap: &'v starlark::values::Heap) -> bool {
+                match attr {
+                    #(#has_attr_items,)*
+                    _ => false,
+                }
+            }
+
+            fn get_attr(&self, attr: &str, heap: &'v starlark::values::Heap) -> Option<starlark::values::Value<'v>> {
+                match attr {
+                    #(#get_attr_items,)*
+                    _ => None,
+                }
+            }
+
+            fn dir_attr(&self) -> Vec<String> {
+                vec![#(#dir_names.to_owned()),*]
+            }
+        }
+    })
+}
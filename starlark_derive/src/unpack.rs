@@ -0,0 +1,220 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use proc_macro::TokenStream;
+use proc_macro2::Ident;
+use quote::quote;
+use quote::quote_spanned;
+use syn::parse::ParseStream;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Expr;
+use syn::Fields;
+use syn::GenericParam;
+use syn::LitStr;
+use syn::Result;
+use syn::Token;
+use syn::Type;
+
+use crate::module::util::ident_string;
+use crate::module::util::is_type_name;
+
+pub fn derive_starlark_unpack(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_starlark_unpack_derive(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    /// Name of the field in Starlark, from `#[starlark(rename = "x")]` or the Rust name.
+    name: String,
+    default: Option<Expr>,
+}
+
+impl Field {
+    fn is_option(&self) -> bool {
+        is_type_name(&self.ty, "Option")
+    }
+
+    fn parse(field: &syn::Field) -> Result<Field> {
+        // Only called for named fields.
+        let ident = field.ident.clone().unwrap();
+        let mut name = ident_string(&ident);
+        let mut default = None;
+        for attr in &field.attrs {
+            if attr.path.is_ident("starlark") {
+                attr.parse_args_with(|parser: ParseStream| {
+                    parse_starlark_field_attr(parser, &mut name, &mut default)
+                })?;
+            }
+        }
+        let field = Field {
+            ident,
+            ty: field.ty.clone(),
+            name,
+            default,
+        };
+        if field.is_option() && field.default.is_some() {
+            return Err(Error::new(
+                field.ty.span(),
+                "`Option` fields cannot have a default",
+            ));
+        }
+        Ok(field)
+    }
+
+    fn unpack_item(&self) -> proc_macro2::TokenStream {
+        let ident = &self.ident;
+        let name = &self.name;
+        let value = if self.is_option() {
+            quote_spanned! { self.ident.span()=>
+                starlark::__derive_refs::unpack::optional(__fields.get(#name).copied())?
+            }
+        } else if let Some(default) = &self.default {
+            quote_spanned! { self.ident.span()=>
+                match __fields.get(#name) {
+                    Some(v) if !v.is_none() => starlark::values::UnpackValue::unpack_value(*v)?,
+                    _ => #default,
+                }
+            }
+        } else {
+            quote_spanned! { self.ident.span()=>
+                starlark::values::UnpackValue::unpack_value(*__fields.get(#name)?)?
+            }
+        };
+        quote_spanned! { self.ident.span()=> #ident: #value }
+    }
+}
+
+/// Parse the comma-separated contents of `#[starlark(rename = "x", default = expr)]`.
+fn parse_starlark_field_attr(
+    parser: ParseStream,
+    name: &mut String,
+    default: &mut Option<Expr>,
+) -> Result<()> {
+    loop {
+        let ident = parser.parse::<Ident>()?;
+        parser.parse::<Token![=]>()?;
+        if ident == "rename" {
+            *name = parser.parse::<LitStr>()?.value();
+        } else if ident == "default" {
+            *default = Some(parser.parse::<Expr>()?);
+        } else {
+            return Err(Error::new(
+                ident.span(),
+                "Expecting `#[starlark(rename = \"name\")]` or `#[starlark(default = expr)]` attribute",
+            ));
+        }
+        if parser.is_empty() {
+            return Ok(());
+        }
+        parser.parse::<Token![,]>()?;
+    }
+}
+
+fn expand_starlark_unpack_derive(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(Field::parse)
+                .collect::<Result<Vec<_>>>()?,
+            _ => {
+                return Err(Error::new(
+                    s.fields.span(),
+                    "#[derive(StarlarkUnpack)] requires named fields",
+                ));
+            }
+        },
+        Data::Enum(e) => {
+            return Err(Error::new(
+                e.enum_token.span(),
+                "#[derive(StarlarkUnpack)] does not support enums",
+            ));
+        }
+        Data::Union(u) => {
+            return Err(Error::new(
+                u.union_token.span(),
+                "#[derive(StarlarkUnpack)] does not support unions",
+            ));
+        }
+    };
+
+    let name = &input.ident;
+    let params = &input.generics.params;
+    let (repr_generics, impl_lifetime, ty) = match params.len() {
+        0 => (quote! {}, quote! { 'v }, quote! { #name }),
+        1 => match params.first() {
+            Some(GenericParam::Lifetime(l)) => {
+                let l = &l.lifetime;
+                (quote! { <#l> }, quote! { #l }, quote! { #name<#l> })
+            }
+            _ => {
+                return Err(Error::new(
+                    params.span(),
+                    "#[derive(StarlarkUnpack)] only supports a lifetime parameter",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                params.span(),
+                "#[derive(StarlarkUnpack)] supports at most one lifetime parameter",
+            ));
+        }
+    };
+
+    let expected = format!(
+        "struct or dict with fields {}",
+        fields
+            .iter()
+            .map(|f| format!("`{}`", f.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let unpack_items = fields.iter().map(|f| f.unpack_item());
+
+    Ok(quote! {
+        impl #repr_generics starlark::values::type_repr::StarlarkTypeRepr for #ty {
+            fn starlark_type_repr() -> std::string::String {
+                <starlark::values::FrozenValue as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr()
+            }
+        }
+
+        impl<#impl_lifetime> starlark::values::UnpackValue<#impl_lifetime> for #ty {
+            fn expected() -> std::string::String {
+                #expected.to_owned()
+            }
+
+            fn unpack_value(
+                value: starlark::values::Value<#impl_lifetime>,
+            ) -> std::option::Option<Self> {
+                let __fields = starlark::__derive_refs::unpack::fields(value)?;
+                Some(Self {
+                    #(#unpack_items),*
+                })
+            }
+        }
+    })
+}